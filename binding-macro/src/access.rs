@@ -0,0 +1,57 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{parse_macro_input, Attribute, Ident, ImplItemMethod, LitStr, Token};
+
+const ACCESS_ATTRIBUTE: &str = "access";
+const ADMIN_ACCESS: &str = "admin";
+const SERVICE_ACCESS: &str = "service";
+
+pub enum Access {
+    Admin,
+    Service(String),
+}
+
+impl Parse for Access {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let ident: Ident = input.parse()?;
+
+        if ident == ADMIN_ACCESS {
+            Ok(Access::Admin)
+        } else if ident == SERVICE_ACCESS {
+            input.parse::<Token![=]>()?;
+            let service: LitStr = input.parse()?;
+            Ok(Access::Service(service.value()))
+        } else {
+            Err(input.error("access should be `admin` or `service = \"name\"`"))
+        }
+    }
+}
+
+impl Access {
+    pub fn to_tokens(&self) -> proc_macro2::TokenStream {
+        match self {
+            Access::Admin => quote! { protocol::traits::MethodAccess::Admin },
+            Access::Service(service) => {
+                quote! { protocol::traits::MethodAccess::Service(#service.to_owned()) }
+            }
+        }
+    }
+}
+
+pub fn verify_access(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let _ = parse_macro_input!(attr as Access);
+    let method_item = parse_macro_input!(item as ImplItemMethod);
+
+    TokenStream::from(quote! {#method_item})
+}
+
+pub fn find_access(attrs: &[Attribute]) -> Option<Access> {
+    attrs
+        .iter()
+        .find(|attr| attr.path.is_ident(ACCESS_ATTRIBUTE))
+        .map(|attr| {
+            attr.parse_args::<Access>()
+                .unwrap_or_else(|e| panic!("parse access failed: {}", e))
+        })
+}
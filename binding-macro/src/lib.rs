@@ -1,15 +1,19 @@
 extern crate proc_macro;
 
+mod access;
 mod common;
 mod cycles;
 mod hooks;
+mod payload;
 mod read_write;
 mod service;
 
 use proc_macro::TokenStream;
 
+use crate::access::verify_access;
 use crate::cycles::gen_cycles_code;
 use crate::hooks::verify_hook;
+use crate::payload::verify_max_payload_size;
 use crate::read_write::verify_read_or_write;
 use crate::service::gen_service_code;
 
@@ -128,6 +132,66 @@ pub fn write(_: TokenStream, item: TokenStream) -> TokenStream {
     verify_read_or_write(item, true)
 }
 
+#[rustfmt::skip]
+/// `#[access]` limits callers of a `#[read]` or `#[write]` method, the
+/// executor checks it before the method is entered.
+///
+/// - `#[access(admin)]`: admins of the service, they are managed by the
+///   governance service at runtime.
+/// - `#[access(service = "name")]`: calls made by the named service.
+///
+/// Callers without access get a response of `FORBIDDEN_CODE`.
+///
+/// # Example:
+///
+/// ```rust
+/// struct Service;
+/// #[service]
+/// impl Service {
+///     #[access(admin)]
+///     #[write]
+///     fn update_config(
+///         &mut self,
+///         _ctx: ServiceContext,
+///         payload: ConfigPayload,
+///     ) -> ServiceResponse<()> {
+///         ServiceResponse::<()>::from_succeed(())
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn access(attr: TokenStream, item: TokenStream) -> TokenStream {
+    verify_access(attr, item)
+}
+
+#[rustfmt::skip]
+/// `#[max_payload_size]` limits the payload of a `#[write]` method in bytes.
+/// Mempool rejects transactions calling the method with larger payloads, and
+/// validators reject blocks with them. It applies on top of `max_tx_size` of
+/// metadata, which limits all transactions.
+///
+/// # Example:
+///
+/// ```rust
+/// struct Service;
+/// #[service]
+/// impl Service {
+///     #[max_payload_size(1_048_576)]
+///     #[write]
+///     fn anchor(
+///         &mut self,
+///         _ctx: ServiceContext,
+///         payload: AnchorPayload,
+///     ) -> ServiceResponse<()> {
+///         ServiceResponse::<()>::from_succeed(())
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn max_payload_size(attr: TokenStream, item: TokenStream) -> TokenStream {
+    verify_max_payload_size(attr, item)
+}
+
 #[rustfmt::skip]
 /// `# [cycles]` mark an `ImplFn` or `fn`, it will automatically generate code
 /// to complete the cycle deduction,
@@ -149,6 +213,10 @@ pub fn write(_: TokenStream, item: TokenStream) -> TokenStream {
 ///     }
 /// }
 /// ```
+///
+/// It is the base cost of the method. Storage access, events and calls to
+/// other services are charged by the framework on top of it, see
+/// `protocol::types::CYCLES_PRICING`.
 #[proc_macro_attribute]
 pub fn cycles(attr: TokenStream, item: TokenStream) -> TokenStream {
     gen_cycles_code(attr, item)
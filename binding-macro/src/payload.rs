@@ -0,0 +1,36 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{parse_macro_input, Attribute, ImplItemMethod, LitInt};
+
+const MAX_PAYLOAD_SIZE_ATTRIBUTE: &str = "max_payload_size";
+
+pub struct MaxPayloadSize {
+    pub value: u64,
+}
+
+impl Parse for MaxPayloadSize {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitInt = input.parse()?;
+        let value = lit.base10_parse::<u64>()?;
+        Ok(Self { value })
+    }
+}
+
+pub fn verify_max_payload_size(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let _ = parse_macro_input!(attr as MaxPayloadSize);
+    let method_item = parse_macro_input!(item as ImplItemMethod);
+
+    TokenStream::from(quote! {#method_item})
+}
+
+pub fn find_max_payload_size(attrs: &[Attribute]) -> Option<u64> {
+    attrs
+        .iter()
+        .find(|attr| attr.path.is_ident(MAX_PAYLOAD_SIZE_ATTRIBUTE))
+        .map(|attr| {
+            attr.parse_args::<MaxPayloadSize>()
+                .unwrap_or_else(|e| panic!("parse max_payload_size failed: {}", e))
+                .value
+        })
+}
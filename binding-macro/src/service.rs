@@ -2,6 +2,9 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, FnArg, Ident, ImplItem, ImplItemMethod, ItemImpl, Type};
 
+use crate::access::{find_access, Access};
+use crate::payload::find_max_payload_size;
+
 const READ_ATTRIBUTE: &str = "read";
 const WRITE_ATTRIBUTE: &str = "write";
 const GENESIS_ATTRIBUTE: &str = "genesis";
@@ -23,9 +26,11 @@ struct Hooks {
 }
 
 struct MethodMeta {
-    method_ident:  Ident,
-    payload_ident: Option<Ident>,
-    readonly:      bool,
+    method_ident:     Ident,
+    payload_ident:    Option<Ident>,
+    readonly:         bool,
+    access:           Option<Access>,
+    max_payload_size: Option<u64>,
 }
 
 pub fn gen_service_code(_: TokenStream, item: TokenStream) -> TokenStream {
@@ -85,6 +90,10 @@ pub fn gen_service_code(_: TokenStream, item: TokenStream) -> TokenStream {
     let (list_write_name_nonepayload, list_write_ident_nonepayload) =
         split_list_for_metadata_nonepayload(&list_method_meta, false);
 
+    let (list_access_name, list_access) = split_list_for_access(&list_method_meta);
+    let (list_payload_limit_name, list_payload_limit) =
+        split_list_for_payload_limit(&list_method_meta);
+
     TokenStream::from(quote! {
         impl #impl_generics protocol::traits::Service for #service_ident #ty_generics #where_clause {
             fn genesis_(&mut self, _payload: String) {
@@ -115,7 +124,7 @@ pub fn gen_service_code(_: TokenStream, item: TokenStream) -> TokenStream {
                     #(#list_read_name => {
                         let payload_res: Result<#list_read_payload, _> = serde_json::from_str(ctx.get_payload());
                         if payload_res.is_err() {
-                            return ServiceResponse::<String>::from_error(1, "decode service payload failed".to_owned());
+                            return ServiceResponse::<String>::from_error(protocol::traits::INVALID_PAYLOAD_CODE, "decode service payload failed".to_owned());
                         };
                         let payload = payload_res.unwrap();
                         let res = self.#list_read_ident(ctx, payload);
@@ -141,10 +150,21 @@ pub fn gen_service_code(_: TokenStream, item: TokenStream) -> TokenStream {
                             ServiceResponse::<String>::from_error(res.code, res.error_message.clone())
                         }
                     },)*
-                    _ => ServiceResponse::<String>::from_error(2, format!("not found method:{:?} of service:{:?}", method, service))
+                    _ => ServiceResponse::<String>::from_error(protocol::traits::METHOD_NOT_FOUND_CODE, format!("not found method:{:?} of service:{:?}", method, service))
                 }
             }
 
+            fn access_(&self, method: &str) -> protocol::traits::MethodAccess {
+                match method {
+                    #(#list_access_name => #list_access,)*
+                    _ => protocol::traits::MethodAccess::Public,
+                }
+            }
+
+            fn payload_limits_(&self) -> Vec<(String, u64)> {
+                vec![#((#list_payload_limit_name.to_owned(), #list_payload_limit),)*]
+            }
+
             fn write_(&mut self, ctx: protocol::types::ServiceContext) -> ServiceResponse<String> {
                 let service = ctx.get_service_name();
                 let method = ctx.get_service_method();
@@ -153,7 +173,7 @@ pub fn gen_service_code(_: TokenStream, item: TokenStream) -> TokenStream {
                     #(#list_write_name => {
                         let payload_res: Result<#list_write_payload, _> = serde_json::from_str(ctx.get_payload());
                         if payload_res.is_err() {
-                            return ServiceResponse::<String>::from_error(1, "decode service payload failed".to_owned());
+                            return ServiceResponse::<String>::from_error(protocol::traits::INVALID_PAYLOAD_CODE, "decode service payload failed".to_owned());
                         };
                         let payload = payload_res.unwrap();
                         let res = self.#list_write_ident(ctx, payload);
@@ -179,7 +199,7 @@ pub fn gen_service_code(_: TokenStream, item: TokenStream) -> TokenStream {
                             ServiceResponse::<String>::from_error(res.code, res.error_message.clone())
                         }
                     },)*
-                    _ => ServiceResponse::<String>::from_error(2, format!("not found method:{:?} of service:{:?}", method, service))
+                    _ => ServiceResponse::<String>::from_error(protocol::traits::METHOD_NOT_FOUND_CODE, format!("not found method:{:?} of service:{:?}", method, service))
                 }
             }
         }
@@ -227,6 +247,25 @@ fn split_list_for_metadata_nonepayload(
     (methods, method_idents)
 }
 
+fn split_list_for_access(list: &[MethodMeta]) -> (Vec<String>, Vec<proc_macro2::TokenStream>) {
+    list.iter()
+        .filter_map(|meta| {
+            meta.access
+                .as_ref()
+                .map(|access| (meta.method_ident.to_string(), access.to_tokens()))
+        })
+        .unzip()
+}
+
+fn split_list_for_payload_limit(list: &[MethodMeta]) -> (Vec<String>, Vec<u64>) {
+    list.iter()
+        .filter_map(|meta| {
+            meta.max_payload_size
+                .map(|limit| (meta.method_ident.to_string(), limit))
+        })
+        .unzip()
+}
+
 fn get_service_ident(impl_item: &ItemImpl) -> Ident {
     match &*impl_item.self_ty {
         Type::Path(type_path) => type_path.path.segments[0].ident.clone(),
@@ -381,6 +420,12 @@ fn extract_method_meta(method: ServiceMethod) -> MethodMeta {
         ServiceMethod::Write(impl_method) => (impl_method, false),
     };
 
+    let access = find_access(&impl_method.attrs);
+    let max_payload_size = find_max_payload_size(&impl_method.attrs);
+    if readonly && max_payload_size.is_some() {
+        panic!("max_payload_size only limits payloads of #[write] methods")
+    }
+
     match &impl_method.sig.inputs.len() {
         // Method input params: `(&self/&mut self, ctx: ServiceContext)`
         2 => {
@@ -388,6 +433,8 @@ fn extract_method_meta(method: ServiceMethod) -> MethodMeta {
                 method_ident: impl_method.sig.ident,
                 payload_ident: None,
                 readonly,
+                access,
+                max_payload_size,
             }
         },
         // Method input params: `(&self/&mut self, ctx: ServiceContext, payload: PayloadType)`
@@ -408,6 +455,8 @@ fn extract_method_meta(method: ServiceMethod) -> MethodMeta {
                 method_ident: impl_method.sig.ident,
                 payload_ident,
                 readonly,
+                access,
+                max_payload_size,
             }
         },
         _ => panic!("Method input params should be `(&self/&mut self, ctx: ServiceContext)` or `(&self/&mut self, ctx: ServiceContext, payload: PayloadType)`")
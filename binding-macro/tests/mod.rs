@@ -11,11 +11,12 @@ use serde::{Deserialize, Serialize};
 
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::{
-    ExecutorParams, Service, ServiceResponse, ServiceSDK, StoreArray, StoreBool, StoreMap,
-    StoreString, StoreUint64,
+    ExecutorParams, MethodAccess, Service, ServiceResponse, ServiceSDK, StoreArray, StoreBool,
+    StoreMap, StoreString, StoreUint64,
 };
 use protocol::types::{
     Address, Block, Hash, Receipt, ServiceContext, ServiceContextParams, SignedTransaction,
+    VoteExtension,
 };
 
 #[test]
@@ -392,6 +393,83 @@ fn test_service_none_response() {
     assert_eq!(test_service.hook_after, true);
 }
 
+#[test]
+fn test_access() {
+    struct Tests<SDK: ServiceSDK> {
+        _sdk: SDK,
+    }
+
+    #[service]
+    impl<SDK: ServiceSDK> Tests<SDK> {
+        #[access(admin)]
+        #[write]
+        fn test_admin(&mut self, _ctx: ServiceContext) -> ServiceResponse<()> {
+            ServiceResponse::<()>::from_succeed(())
+        }
+
+        #[access(service = "governance")]
+        #[write]
+        fn test_service(&mut self, _ctx: ServiceContext) -> ServiceResponse<()> {
+            ServiceResponse::<()>::from_succeed(())
+        }
+
+        #[read]
+        fn test_public(&self, _ctx: ServiceContext) -> ServiceResponse<()> {
+            ServiceResponse::<()>::from_succeed(())
+        }
+    }
+
+    let test_service = Tests {
+        _sdk: MockServiceSDK {},
+    };
+
+    assert_eq!(test_service.access_("test_admin"), MethodAccess::Admin);
+    assert_eq!(
+        test_service.access_("test_service"),
+        MethodAccess::Service("governance".to_owned())
+    );
+    assert_eq!(test_service.access_("test_public"), MethodAccess::Public);
+    assert_eq!(test_service.access_("test_notfound"), MethodAccess::Public);
+}
+
+#[test]
+fn test_max_payload_size() {
+    struct Tests<SDK: ServiceSDK> {
+        _sdk: SDK,
+    }
+
+    #[service]
+    impl<SDK: ServiceSDK> Tests<SDK> {
+        #[max_payload_size(1_048_576)]
+        #[write]
+        fn test_anchor(&mut self, _ctx: ServiceContext) -> ServiceResponse<()> {
+            ServiceResponse::<()>::from_succeed(())
+        }
+
+        #[access(admin)]
+        #[max_payload_size(128)]
+        #[write]
+        fn test_transfer(&mut self, _ctx: ServiceContext) -> ServiceResponse<()> {
+            ServiceResponse::<()>::from_succeed(())
+        }
+
+        #[write]
+        fn test_unlimited(&mut self, _ctx: ServiceContext) -> ServiceResponse<()> {
+            ServiceResponse::<()>::from_succeed(())
+        }
+    }
+
+    let test_service = Tests {
+        _sdk: MockServiceSDK {},
+    };
+
+    assert_eq!(test_service.payload_limits_(), vec![
+        ("test_anchor".to_owned(), 1_048_576),
+        ("test_transfer".to_owned(), 128)
+    ]);
+    assert_eq!(test_service.access_("test_transfer"), MethodAccess::Admin);
+}
+
 fn get_context(cycles_limit: u64, service: &str, method: &str, payload: &str) -> ServiceContext {
     let params = ServiceContextParams {
         tx_hash: None,
@@ -414,10 +492,13 @@ fn get_context(cycles_limit: u64, service: &str, method: &str, payload: &str) ->
 
 fn mock_executor_params() -> ExecutorParams {
     ExecutorParams {
-        state_root:   Hash::default(),
-        height:       9,
-        timestamp:    99,
-        cycles_limit: 99999,
+        state_root:      Hash::default(),
+        height:          9,
+        timestamp:       99,
+        cycles_limit:    99999,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
     }
 }
 
@@ -497,6 +578,11 @@ impl ServiceSDK for MockServiceSDK {
         unimplemented!()
     }
 
+    // Get vote extensions carried by the block of `height`
+    fn get_vote_extensions(&self, _height: Option<u64>) -> Vec<VoteExtension> {
+        unimplemented!()
+    }
+
     // Get a receipt by `tx_hash`
     // if not found on the chain, return None
     fn get_receipt_by_hash(&self, _tx_hash: &Hash) -> Option<Receipt> {
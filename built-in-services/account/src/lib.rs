@@ -0,0 +1,201 @@
+#[cfg(test)]
+mod tests;
+pub mod types;
+
+use binding_macro::{cycles, service};
+use protocol::traits::{ExecutorParams, ServiceResponse, ServiceSDK};
+use protocol::types::{Address, ServiceContext, VerifyAuthorizationPayload};
+
+use crate::types::{
+    AddSessionKeyPayload, Authorization, GetAuthorizationPayload, RemoveSessionKeyPayload,
+    SessionKey, SessionKeyEvent, SetAuthorizerEvent, SetAuthorizerPayload,
+};
+
+// Account value of authorization of an account
+const AUTHORIZATION_KEY: &str = "authorization";
+
+/// Executor calls `verify_authorization` when a transaction is signed by
+/// other than its sender. An account authorizes signers by session keys
+/// expiring at a height, or delegates the decision to a method of another
+/// service, such as multisig or daily spend limits.
+pub struct AccountService<SDK> {
+    sdk: SDK,
+}
+
+#[service]
+impl<SDK: ServiceSDK> AccountService<SDK> {
+    pub fn new(sdk: SDK) -> Self {
+        Self { sdk }
+    }
+
+    #[cycles(100_00)]
+    #[read]
+    fn get_authorization(
+        &self,
+        ctx: ServiceContext,
+        payload: GetAuthorizationPayload,
+    ) -> ServiceResponse<Authorization> {
+        ServiceResponse::<Authorization>::from_succeed(self._authorization(&payload.account))
+    }
+
+    #[cycles(100_00)]
+    #[read]
+    fn verify_authorization(
+        &self,
+        ctx: ServiceContext,
+        payload: VerifyAuthorizationPayload,
+    ) -> ServiceResponse<bool> {
+        if payload.signer == payload.sender {
+            return ServiceResponse::<bool>::from_succeed(true);
+        }
+
+        let authorization = self._authorization(&payload.sender);
+
+        // Session keys cannot change authorization of the account
+        if payload.service_name != ctx.get_service_name() {
+            let height = ctx.get_current_height();
+            let authorized = authorization
+                .session_keys
+                .iter()
+                .any(|k| k.key == payload.signer && k.expire_height >= height);
+
+            if authorized {
+                return ServiceResponse::<bool>::from_succeed(true);
+            }
+        }
+
+        if authorization.authorizer_service.is_empty() {
+            return ServiceResponse::<bool>::from_succeed(false);
+        }
+
+        let payload_res = serde_json::to_string(&payload);
+        if let Err(e) = payload_res {
+            return ServiceResponse::<bool>::from_error(103, format!("{:?}", e));
+        }
+        let payload_str = payload_res.unwrap();
+
+        let resp = self.sdk.read(
+            &ctx,
+            None,
+            &authorization.authorizer_service,
+            &authorization.authorizer_method,
+            &payload_str,
+        );
+        ServiceResponse::<bool>::from_succeed(!resp.is_error() && resp.succeed_data == "true")
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn set_authorizer(
+        &mut self,
+        ctx: ServiceContext,
+        payload: SetAuthorizerPayload,
+    ) -> ServiceResponse<()> {
+        if payload.service == ctx.get_service_name() {
+            return ServiceResponse::<()>::from_error(
+                101,
+                "cann't delegate authorization to account service".to_owned(),
+            );
+        }
+
+        let caller = ctx.get_caller();
+        let mut authorization = self._authorization(&caller);
+        authorization.authorizer_service = payload.service.clone();
+        authorization.authorizer_method = payload.method.clone();
+
+        self.sdk
+            .set_account_value(&caller, AUTHORIZATION_KEY.to_owned(), authorization);
+
+        let event = SetAuthorizerEvent {
+            account: caller,
+            service: payload.service,
+            method:  payload.method,
+        };
+        let event_res = serde_json::to_string(&event);
+
+        if let Err(e) = event_res {
+            return ServiceResponse::<()>::from_error(103, format!("{:?}", e));
+        };
+        let event_str = event_res.unwrap();
+        ctx.emit_event(event_str);
+
+        ServiceResponse::<()>::from_succeed(())
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn add_session_key(
+        &mut self,
+        ctx: ServiceContext,
+        payload: AddSessionKeyPayload,
+    ) -> ServiceResponse<()> {
+        if payload.expire_height < ctx.get_current_height() {
+            return ServiceResponse::<()>::from_error(102, "session key expired".to_owned());
+        }
+
+        let caller = ctx.get_caller();
+        let mut authorization = self._authorization(&caller);
+        authorization.session_keys.retain(|k| k.key != payload.key);
+        authorization.session_keys.push(SessionKey {
+            key:           payload.key.clone(),
+            expire_height: payload.expire_height,
+        });
+
+        self.sdk
+            .set_account_value(&caller, AUTHORIZATION_KEY.to_owned(), authorization);
+
+        self._emit_session_key_event(&ctx, SessionKeyEvent {
+            account:       caller,
+            key:           payload.key,
+            expire_height: payload.expire_height,
+        })
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn remove_session_key(
+        &mut self,
+        ctx: ServiceContext,
+        payload: RemoveSessionKeyPayload,
+    ) -> ServiceResponse<()> {
+        let caller = ctx.get_caller();
+        let mut authorization = self._authorization(&caller);
+
+        let count = authorization.session_keys.len();
+        authorization.session_keys.retain(|k| k.key != payload.key);
+        if authorization.session_keys.len() == count {
+            return ServiceResponse::<()>::from_error(104, "session key not existed".to_owned());
+        }
+
+        self.sdk
+            .set_account_value(&caller, AUTHORIZATION_KEY.to_owned(), authorization);
+
+        self._emit_session_key_event(&ctx, SessionKeyEvent {
+            account:       caller,
+            key:           payload.key,
+            expire_height: 0,
+        })
+    }
+
+    fn _authorization(&self, account: &Address) -> Authorization {
+        self.sdk
+            .get_account_value(account, &AUTHORIZATION_KEY.to_owned())
+            .unwrap_or_default()
+    }
+
+    fn _emit_session_key_event(
+        &self,
+        ctx: &ServiceContext,
+        event: SessionKeyEvent,
+    ) -> ServiceResponse<()> {
+        let event_res = serde_json::to_string(&event);
+
+        if let Err(e) = event_res {
+            return ServiceResponse::<()>::from_error(103, format!("{:?}", e));
+        };
+        let event_str = event_res.unwrap();
+        ctx.emit_event(event_str);
+
+        ServiceResponse::<()>::from_succeed(())
+    }
+}
@@ -0,0 +1,141 @@
+use protocol::types::{Address, Hash, ServiceContext, VerifyAuthorizationPayload};
+use testkit::{ContextBuilder, MockServiceSDK, TestKit};
+
+use crate::types::{
+    AddSessionKeyPayload, GetAuthorizationPayload, RemoveSessionKeyPayload, SetAuthorizerPayload,
+};
+use crate::AccountService;
+
+#[test]
+fn test_session_key() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let account = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let session_key = Address::from_hex("0x666cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, account.clone(), 1);
+
+    let mut service = new_account_service();
+
+    let add_res = service.add_session_key(context.clone(), AddSessionKeyPayload {
+        key:           session_key.clone(),
+        expire_height: 10,
+    });
+    assert!(!add_res.is_error());
+
+    let authorization = service
+        .get_authorization(context.clone(), GetAuthorizationPayload {
+            account: account.clone(),
+        })
+        .succeed_data;
+    assert_eq!(authorization.session_keys.len(), 1);
+    assert_eq!(authorization.session_keys[0].expire_height, 10);
+
+    let payload = mock_verify_payload(account.clone(), session_key.clone(), "asset");
+    let verify_res = service.verify_authorization(context.clone(), payload.clone());
+    assert!(verify_res.succeed_data);
+
+    // Session key cannot change authorization
+    let account_payload = mock_verify_payload(account.clone(), session_key.clone(), "account");
+    let verify_res = service.verify_authorization(context.clone(), account_payload);
+    assert!(!verify_res.succeed_data);
+
+    // Expired
+    let expired_context = mock_context(cycles_limit, account.clone(), 11);
+    let verify_res = service.verify_authorization(expired_context, payload.clone());
+    assert!(!verify_res.succeed_data);
+
+    let remove_res = service.remove_session_key(context.clone(), RemoveSessionKeyPayload {
+        key: session_key.clone(),
+    });
+    assert!(!remove_res.is_error());
+
+    let verify_res = service.verify_authorization(context.clone(), payload);
+    assert!(!verify_res.succeed_data);
+
+    let remove_res =
+        service.remove_session_key(context, RemoveSessionKeyPayload { key: session_key });
+    assert_eq!(remove_res.code, 104);
+}
+
+#[test]
+fn test_verify_authorization() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let account = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let signer = Address::from_hex("0x666cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, account.clone(), 1);
+
+    let mut service = new_account_service();
+
+    let verify_res = service.verify_authorization(
+        context.clone(),
+        mock_verify_payload(account.clone(), account.clone(), "asset"),
+    );
+    assert!(verify_res.succeed_data);
+
+    let verify_res = service.verify_authorization(
+        context.clone(),
+        mock_verify_payload(account, signer, "asset"),
+    );
+    assert!(!verify_res.succeed_data);
+
+    let set_res = service.set_authorizer(context, SetAuthorizerPayload {
+        service: "account".to_owned(),
+        method:  "verify_authorization".to_owned(),
+    });
+    assert_eq!(set_res.code, 101);
+}
+
+#[test]
+fn test_delegated_authorization() {
+    let account = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let signer = Address::from_hex("0x666cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(u64::max_value(), account.clone(), 1);
+
+    let kit = TestKit::new();
+    kit.dispatcher()
+        .stub_read("multisig", "verify_signer", "true");
+    let mut service = AccountService::new(kit.sdk());
+
+    let set_res = service.set_authorizer(context.clone(), SetAuthorizerPayload {
+        service: "multisig".to_owned(),
+        method:  "verify_signer".to_owned(),
+    });
+    assert!(!set_res.is_error());
+
+    let verify_res =
+        service.verify_authorization(context, mock_verify_payload(account, signer, "asset"));
+    assert!(verify_res.succeed_data);
+
+    let calls = kit.dispatcher().calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].service, "multisig");
+    assert_eq!(calls[0].method, "verify_signer");
+    assert!(!calls[0].write);
+}
+
+fn new_account_service() -> AccountService<MockServiceSDK> {
+    AccountService::new(TestKit::new().sdk())
+}
+
+fn mock_verify_payload(
+    sender: Address,
+    signer: Address,
+    service_name: &str,
+) -> VerifyAuthorizationPayload {
+    VerifyAuthorizationPayload {
+        sender,
+        signer,
+        tx_hash: Hash::from_empty(),
+        service_name: service_name.to_owned(),
+        method: "method".to_owned(),
+        payload: "{}".to_owned(),
+    }
+}
+
+fn mock_context(cycles_limit: u64, caller: Address, height: u64) -> ServiceContext {
+    ContextBuilder::new()
+        .cycles_limit(cycles_limit)
+        .caller(caller)
+        .height(height)
+        .service("account", "service_method", "service_payload")
+        .build()
+}
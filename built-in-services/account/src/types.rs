@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use bytes::Bytes;
+
+use protocol::fixed_codec::{FixedCodec, FixedCodecError};
+use protocol::types::Address;
+use protocol::ProtocolResult;
+
+/// Payload
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SetAuthorizerPayload {
+    pub service: String,
+    pub method:  String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SetAuthorizerEvent {
+    pub account: Address,
+    pub service: String,
+    pub method:  String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct AddSessionKeyPayload {
+    pub key:           Address,
+    pub expire_height: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RemoveSessionKeyPayload {
+    pub key: Address,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SessionKeyEvent {
+    pub account:       Address,
+    pub key:           Address,
+    // Zero if the key is removed
+    pub expire_height: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GetAuthorizationPayload {
+    pub account: Address,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SessionKey {
+    pub key:           Address,
+    pub expire_height: u64,
+}
+
+// Authorizer method takes `VerifyAuthorizationPayload` and returns a bool,
+// none if service is empty.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct Authorization {
+    pub authorizer_service: String,
+    pub authorizer_method:  String,
+    pub session_keys:       Vec<SessionKey>,
+}
+
+impl rlp::Decodable for SessionKey {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            key:           rlp.at(0)?.as_val()?,
+            expire_height: rlp.at(1)?.as_val()?,
+        })
+    }
+}
+
+impl rlp::Encodable for SessionKey {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(2)
+            .append(&self.key)
+            .append(&self.expire_height);
+    }
+}
+
+impl rlp::Decodable for Authorization {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            authorizer_service: rlp.at(0)?.as_val()?,
+            authorizer_method:  rlp.at(1)?.as_val()?,
+            session_keys:       rlp::decode_list(rlp.at(2)?.as_raw()),
+        })
+    }
+}
+
+impl rlp::Encodable for Authorization {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(3)
+            .append(&self.authorizer_service)
+            .append(&self.authorizer_method)
+            .append_list(&self.session_keys);
+    }
+}
+
+impl FixedCodec for Authorization {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::from(rlp::encode(self)))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
+}
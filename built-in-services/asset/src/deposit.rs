@@ -0,0 +1,28 @@
+// Account value of fee asset locked for storage
+pub const STORAGE_DEPOSIT_KEY: &str = "storage_deposit";
+// Account value of bytes stored by an address
+pub const STORED_BYTES_KEY: &str = "stored_bytes";
+// Number of addresses storing bytes, rent is charged from them each epoch
+pub const DEPOSIT_OWNERS_KEY: &str = "deposit_owners";
+// Address of an owner in a slot is stored under the prefix and the slot
+pub const DEPOSIT_OWNER_PREFIX: &str = "deposit_owner_";
+// Account value of the slot of an owner plus one, zero if it stores nothing
+pub const DEPOSIT_SLOT_KEY: &str = "deposit_slot";
+// Slot of the next owner paying rent of the epoch, none once all have paid
+pub const RENT_CURSOR_KEY: &str = "rent_cursor";
+
+// Owners charged rent in a block, the rest pay in blocks after
+pub const RENT_OWNERS_PER_BLOCK: u64 = 1000;
+// Cycles of settling the deposit of an owner, paid by the transaction
+pub const SETTLE_DEPOSIT_CYCLES: u64 = 10_000;
+
+/// Split rent of an owner whose balance can't cover it, the shortfall is
+/// taken from its deposit. Return rent paid and deposit refunded.
+pub fn split_rent(balance: u64, deposit: u64, rent: u64) -> (u64, u64) {
+    let from_deposit = rent.saturating_sub(balance).min(deposit);
+    (balance.min(rent) + from_deposit, deposit - from_deposit)
+}
+
+pub fn owner_key(slot: u64) -> String {
+    format!("{}{}", DEPOSIT_OWNER_PREFIX, slot)
+}
@@ -0,0 +1,18 @@
+// The asset issued in genesis pays for cycles
+pub const FEE_ASSET_KEY: &str = "fee_asset";
+// Fee collected from transactions of the executing block
+pub const COLLECTED_FEE_KEY: &str = "collected_fee";
+// Account value of fee distributed to an address but not claimed yet
+pub const CLAIMABLE_FEE_KEY: &str = "claimable_fee";
+
+/// Split fee between proposer and treasury by ratios, remainder of the
+/// division goes to treasury. Proposer takes all if both ratios are zero.
+pub fn split_fee(fee: u64, proposer_ratio: u64, treasury_ratio: u64) -> (u64, u64) {
+    let total_ratio = u128::from(proposer_ratio) + u128::from(treasury_ratio);
+    if total_ratio == 0 {
+        return (fee, 0);
+    }
+
+    let proposer_fee = (u128::from(fee) * u128::from(proposer_ratio) / total_ratio) as u64;
+    (proposer_fee, fee - proposer_fee)
+}
@@ -1,21 +1,44 @@
+pub mod deposit;
+pub mod fee;
 #[cfg(test)]
 mod tests;
 pub mod types;
 
+use std::cell::RefCell;
 use std::collections::BTreeMap;
+use std::rc::Rc;
 
 use bytes::Bytes;
 
-use binding_macro::{cycles, genesis, service};
+use binding_macro::{cycles, genesis, hook_after, hook_before, service, tx_hook_after};
 use protocol::traits::{ExecutorParams, ServiceResponse, ServiceSDK, StoreMap};
-use protocol::types::{Address, Hash, ServiceContext};
+use protocol::types::{
+    service_address, Address, Feature, Hash, Metadata, ServiceContext, ServiceContextParams,
+    STORAGE_RECLAIM_KEY,
+};
 
+use crate::deposit::{
+    owner_key, split_rent, DEPOSIT_OWNERS_KEY, DEPOSIT_SLOT_KEY, RENT_CURSOR_KEY,
+    RENT_OWNERS_PER_BLOCK, SETTLE_DEPOSIT_CYCLES, STORAGE_DEPOSIT_KEY, STORED_BYTES_KEY,
+};
+use crate::fee::{split_fee, CLAIMABLE_FEE_KEY, COLLECTED_FEE_KEY, FEE_ASSET_KEY};
 use crate::types::{
-    ApproveEvent, ApprovePayload, Asset, AssetBalance, CreateAssetPayload, GetAllowancePayload,
-    GetAllowanceResponse, GetAssetPayload, GetBalancePayload, GetBalanceResponse,
-    InitGenesisPayload, TransferEvent, TransferFromEvent, TransferFromPayload, TransferPayload,
+    ApproveEvent, ApprovePayload, Asset, AssetBalance, BurnEvent, BurnPayload, ClaimFeeEvent,
+    CreateAssetPayload, GetAllowancePayload, GetAllowanceResponse, GetAssetPayload,
+    GetBalancePayload, GetBalanceResponse, GetClaimableFeePayload, GetClaimableFeeResponse,
+    InitGenesisPayload, MintEvent, MintPayload, TransferEvent, TransferFromEvent,
+    TransferFromPayload, TransferPayload,
 };
 
+// Symbol of every asset is unique, value is id of the asset
+const SYMBOL_KEY_PREFIX: &str = "symbol_";
+// Set once symbols of assets created before the index are indexed
+const SYMBOL_INDEXED_KEY: &str = "symbol_indexed";
+// Symbols of vouchers the bridge creates on first receipt, no one else can
+// take them
+const BRIDGE_SERVICE: &str = "bridge";
+const BRIDGE_SYMBOL_PREFIX: &str = "BRIDGE-";
+
 pub struct AssetService<SDK> {
     sdk:    SDK,
     assets: Box<dyn StoreMap<Hash, Asset>>,
@@ -32,13 +55,17 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
     #[genesis]
     fn init_genesis(&mut self, payload: InitGenesisPayload) {
         let asset = Asset {
-            id:     payload.id,
-            name:   payload.name,
-            symbol: payload.symbol,
-            supply: payload.supply,
-            issuer: payload.issuer.clone(),
+            id:         payload.id,
+            name:       payload.name,
+            symbol:     payload.symbol,
+            supply:     payload.supply,
+            issuer:     payload.issuer.clone(),
+            decimals:   payload.decimals,
+            max_supply: payload.max_supply,
         };
 
+        // Symbol of the asset is indexed with assets created before the index,
+        // the genesis state stays as it was
         self.assets.insert(asset.id.clone(), asset.clone());
 
         let asset_balance = AssetBalance {
@@ -47,7 +74,441 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
         };
 
         self.sdk
-            .set_account_value(&asset.issuer, asset.id, asset_balance)
+            .set_account_value(&asset.issuer, asset.id.clone(), asset_balance);
+        if payload.fee_asset {
+            self.sdk.set_value(FEE_ASSET_KEY.to_owned(), asset.id)
+        }
+    }
+
+    #[cycles(100_00)]
+    #[read]
+    fn get_claimable_fee(
+        &self,
+        ctx: ServiceContext,
+        payload: GetClaimableFeePayload,
+    ) -> ServiceResponse<GetClaimableFeeResponse> {
+        let asset_id: Hash = match self.sdk.get_value(&FEE_ASSET_KEY.to_owned()) {
+            Some(asset_id) => asset_id,
+            None => {
+                return ServiceResponse::<GetClaimableFeeResponse>::from_error(
+                    107,
+                    "fee asset not existed".to_owned(),
+                )
+            }
+        };
+
+        let res = GetClaimableFeeResponse {
+            asset_id,
+            value: self._claimable_fee(&payload.user),
+            user: payload.user,
+        };
+        ServiceResponse::<GetClaimableFeeResponse>::from_succeed(res)
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn claim_fee(&mut self, ctx: ServiceContext) -> ServiceResponse<u64> {
+        let caller = ctx.get_caller();
+        let asset_id: Hash = match self.sdk.get_value(&FEE_ASSET_KEY.to_owned()) {
+            Some(asset_id) => asset_id,
+            None => {
+                return ServiceResponse::<u64>::from_error(107, "fee asset not existed".to_owned())
+            }
+        };
+
+        let value = self._claimable_fee(&caller);
+        if value == 0 {
+            return ServiceResponse::<u64>::from_error(108, "no claimable fee".to_owned());
+        }
+
+        let mut caller_asset_balance: AssetBalance = self
+            .sdk
+            .get_account_value(&caller, &asset_id)
+            .unwrap_or(AssetBalance {
+                value:     0,
+                allowance: BTreeMap::new(),
+            });
+        caller_asset_balance.value = match caller_asset_balance.value.checked_add(value) {
+            Some(v) => v,
+            None => return ServiceResponse::<u64>::from_error(106, "u64 overflow".to_owned()),
+        };
+
+        self.sdk
+            .set_account_value(&caller, asset_id.clone(), caller_asset_balance);
+        self.sdk
+            .set_account_value(&caller, CLAIMABLE_FEE_KEY.to_owned(), 0u64);
+
+        let event = ClaimFeeEvent {
+            asset_id,
+            user: caller,
+            value,
+        };
+        let event_res = serde_json::to_string(&event);
+
+        if let Err(e) = event_res {
+            return ServiceResponse::<u64>::from_error(103, format!("{:?}", e));
+        };
+        let event_str = event_res.unwrap();
+        ctx.emit_event(event_str);
+
+        ServiceResponse::<u64>::from_succeed(value)
+    }
+
+    // Caller pays cycles_price * cycles_used in fee asset. If its balance
+    // can't cover the fee, the transaction fails and its writes are reverted,
+    // then the balance left is paid. Storage deposits of the transaction are
+    // settled then, settling is paid as cycles of the transaction.
+    #[tx_hook_after]
+    fn collect_fee(&mut self, ctx: ServiceContext) {
+        let asset_id: Hash = match self.sdk.get_value(&FEE_ASSET_KEY.to_owned()) {
+            Some(asset_id) => asset_id,
+            None => return,
+        };
+
+        let changes = ctx.get_storage_deposit_changes();
+        if !changes.is_empty() {
+            let left = ctx.get_cycles_limit() - ctx.get_cycles_used();
+            ctx.sub_cycles((SETTLE_DEPOSIT_CYCLES * changes.len() as u64).min(left));
+        }
+
+        self._collect_fee(&ctx, &asset_id);
+
+        if !changes.is_empty() {
+            let metadata = self._get_metadata(
+                ctx.get_current_height(),
+                ctx.get_timestamp(),
+                &ctx.get_caller(),
+            );
+            self._settle_storage_deposit(
+                &ctx,
+                changes,
+                &asset_id,
+                metadata.storage_deposit_per_byte,
+            );
+        }
+    }
+
+    fn _collect_fee(&mut self, ctx: &ServiceContext, asset_id: &Hash) {
+        let fee = ctx.get_cycles_price().saturating_mul(ctx.get_cycles_used());
+        if fee == 0 {
+            return;
+        }
+
+        let caller = ctx.get_caller();
+        let mut caller_asset_balance = self._balance_of(&caller, asset_id);
+        if caller_asset_balance.value < fee && !ctx.is_fee_failed() {
+            ctx.fail_insufficient_fee();
+        }
+
+        let paid = fee.min(caller_asset_balance.value);
+        if paid == 0 {
+            return;
+        }
+
+        caller_asset_balance.value -= paid;
+        self.sdk
+            .set_account_value(&caller, asset_id.clone(), caller_asset_balance);
+        self._add_collected_fee(paid);
+    }
+
+    // Owners lock deposit for bytes they store and get it back for bytes
+    // released. The transaction fails if an owner can't lock the deposit.
+    fn _settle_storage_deposit(
+        &mut self,
+        ctx: &ServiceContext,
+        changes: Vec<(Address, i64)>,
+        asset_id: &Hash,
+        price: u64,
+    ) {
+        for (owner, bytes) in changes.into_iter() {
+            let mut balance = self._balance_of(&owner, asset_id);
+            let mut deposit = self._account_u64(&owner, STORAGE_DEPOSIT_KEY);
+            let mut stored = self._account_u64(&owner, STORED_BYTES_KEY);
+
+            let moved = if bytes > 0 {
+                let lock = price.saturating_mul(bytes as u64);
+                if balance.value < lock {
+                    if !ctx.is_fee_failed() {
+                        ctx.fail_insufficient_fee();
+                    }
+                    continue;
+                }
+
+                balance.value -= lock;
+                deposit += lock;
+                stored = stored.saturating_add(bytes as u64);
+                lock
+            } else {
+                let refund = price.saturating_mul((-bytes) as u64).min(deposit);
+                balance.value = balance.value.saturating_add(refund);
+                deposit -= refund;
+                stored = stored.saturating_sub((-bytes) as u64);
+                refund
+            };
+
+            if moved > 0 {
+                self.sdk
+                    .set_account_value(&owner, asset_id.clone(), balance);
+                self.sdk
+                    .set_account_value(&owner, STORAGE_DEPOSIT_KEY.to_owned(), deposit);
+            }
+            self.sdk
+                .set_account_value(&owner, STORED_BYTES_KEY.to_owned(), stored);
+
+            if stored == 0 {
+                self._remove_deposit_owner(&owner);
+            } else {
+                self._add_deposit_owner(&owner);
+            }
+        }
+    }
+
+    // Owners are kept in slots, the last one moves to the slot of an owner
+    // removed
+    fn _deposit_owners(&self) -> u64 {
+        self.sdk
+            .get_value(&DEPOSIT_OWNERS_KEY.to_owned())
+            .unwrap_or(0)
+    }
+
+    fn _add_deposit_owner(&mut self, owner: &Address) {
+        if self._account_u64(owner, DEPOSIT_SLOT_KEY) != 0 {
+            return;
+        }
+
+        let count = self._deposit_owners();
+        self.sdk.set_value(owner_key(count), owner.clone());
+        self.sdk
+            .set_account_value(owner, DEPOSIT_SLOT_KEY.to_owned(), count + 1);
+        self.sdk.set_value(DEPOSIT_OWNERS_KEY.to_owned(), count + 1);
+    }
+
+    fn _remove_deposit_owner(&mut self, owner: &Address) {
+        let slot = match self._account_u64(owner, DEPOSIT_SLOT_KEY) {
+            0 => return,
+            slot => slot - 1,
+        };
+
+        let last = self._deposit_owners().saturating_sub(1);
+        if slot < last {
+            let moved: Address = self.sdk.get_value(&owner_key(last)).unwrap_or_default();
+            self.sdk.set_value(owner_key(slot), moved.clone());
+            self.sdk
+                .set_account_value(&moved, DEPOSIT_SLOT_KEY.to_owned(), slot + 1);
+        }
+        self.sdk
+            .set_account_value(owner, DEPOSIT_SLOT_KEY.to_owned(), 0u64);
+        self.sdk.set_value(DEPOSIT_OWNERS_KEY.to_owned(), last);
+    }
+
+    #[hook_after]
+    fn distribute_fee(&mut self, params: &ExecutorParams) {
+        let collected = self._collected_fee();
+        if collected == 0 && self._deposit_owners() == 0 {
+            return;
+        }
+
+        let metadata = self._get_metadata(params.height, params.timestamp, &params.proposer);
+        self._charge_storage_rent(params.height, &metadata);
+
+        let collected = self._collected_fee();
+        if collected == 0 {
+            return;
+        }
+        self._distribute_fee(collected, &params.proposer, &metadata);
+    }
+
+    // Owners pay rent for bytes stored every epoch, rent is distributed as
+    // fee. Entries of owners whose balance can't cover rent are reclaimed by
+    // executor, the shortfall is taken from their deposit and the rest of it
+    // is refunded. At most `RENT_OWNERS_PER_BLOCK` owners pay in a block, the
+    // rest pay in blocks after, an epoch starts once all have paid.
+    fn _charge_storage_rent(&mut self, height: u64, metadata: &Metadata) {
+        if !metadata
+            .chain_spec
+            .is_active(Feature::StorageDeposit, height)
+        {
+            return;
+        }
+        let asset_id: Hash = match self.sdk.get_value(&FEE_ASSET_KEY.to_owned()) {
+            Some(asset_id) => asset_id,
+            None => return,
+        };
+
+        let mut cursor: u64 = self
+            .sdk
+            .get_value(&RENT_CURSOR_KEY.to_owned())
+            .unwrap_or(std::u64::MAX);
+        let epoch_end = metadata.rent_epoch != 0 && height % metadata.rent_epoch == 0;
+        if epoch_end && cursor >= self._deposit_owners() {
+            cursor = 0;
+        }
+        if cursor >= self._deposit_owners() {
+            return;
+        }
+
+        let mut reclaimed = vec![];
+        let mut paid = 0u64;
+        let mut charged = 0;
+        while cursor < self._deposit_owners() && charged < RENT_OWNERS_PER_BLOCK {
+            charged += 1;
+            let owner: Address = self.sdk.get_value(&owner_key(cursor)).unwrap_or_default();
+            let rent = self
+                ._account_u64(&owner, STORED_BYTES_KEY)
+                .saturating_mul(metadata.rent_per_byte);
+            let mut balance = self._balance_of(&owner, &asset_id);
+            let prev_value = balance.value;
+
+            if balance.value >= rent {
+                balance.value -= rent;
+                paid = paid.saturating_add(rent);
+                cursor += 1;
+            } else {
+                let deposit = self._account_u64(&owner, STORAGE_DEPOSIT_KEY);
+                let (rent_paid, refund) = split_rent(balance.value, deposit, rent);
+                balance.value = refund;
+                paid = paid.saturating_add(rent_paid);
+
+                self.sdk
+                    .set_account_value(&owner, STORAGE_DEPOSIT_KEY.to_owned(), 0u64);
+                self.sdk
+                    .set_account_value(&owner, STORED_BYTES_KEY.to_owned(), 0u64);
+                // The last owner moves to the slot, it pays next
+                self._remove_deposit_owner(&owner);
+                reclaimed.push(owner.clone());
+            }
+
+            if balance.value != prev_value {
+                self.sdk
+                    .set_account_value(&owner, asset_id.clone(), balance);
+            }
+        }
+
+        if cursor >= self._deposit_owners() {
+            cursor = std::u64::MAX;
+        }
+        self.sdk.set_value(RENT_CURSOR_KEY.to_owned(), cursor);
+        self._add_collected_fee(paid);
+        if !reclaimed.is_empty() {
+            self.sdk
+                .set_value(STORAGE_RECLAIM_KEY.to_owned(), reclaimed);
+        }
+    }
+
+    fn _distribute_fee(&mut self, collected: u64, proposer: &Address, metadata: &Metadata) {
+        let (proposer_fee, treasury_fee) = split_fee(
+            collected,
+            metadata.proposer_fee_ratio,
+            metadata.treasury_fee_ratio,
+        );
+
+        self._add_claimable_fee(proposer, proposer_fee);
+        self._add_claimable_fee(&metadata.treasury, treasury_fee);
+        self.sdk.set_value(COLLECTED_FEE_KEY.to_owned(), 0u64);
+    }
+
+    fn _collected_fee(&self) -> u64 {
+        self.sdk
+            .get_value(&COLLECTED_FEE_KEY.to_owned())
+            .unwrap_or(0)
+    }
+
+    fn _add_collected_fee(&mut self, value: u64) {
+        if value == 0 {
+            return;
+        }
+
+        let collected = self._collected_fee().saturating_add(value);
+        self.sdk.set_value(COLLECTED_FEE_KEY.to_owned(), collected);
+    }
+
+    fn _claimable_fee(&self, user: &Address) -> u64 {
+        self.sdk
+            .get_account_value(user, &CLAIMABLE_FEE_KEY.to_owned())
+            .unwrap_or(0)
+    }
+
+    fn _add_claimable_fee(&mut self, user: &Address, value: u64) {
+        if value == 0 {
+            return;
+        }
+
+        let claimable = self._claimable_fee(user).saturating_add(value);
+        self.sdk
+            .set_account_value(user, CLAIMABLE_FEE_KEY.to_owned(), claimable);
+    }
+
+    fn _account_u64(&self, user: &Address, key: &str) -> u64 {
+        self.sdk
+            .get_account_value(user, &key.to_owned())
+            .unwrap_or(0)
+    }
+
+    // Assets created before symbols were unique have no index, they are
+    // indexed once before transactions of the first block, in a block hook so
+    // no call pays for it. The earliest asset keeps a symbol shared by several.
+    #[hook_before]
+    fn index_symbols(&mut self, _params: &ExecutorParams) {
+        self._index_symbols()
+    }
+
+    fn _index_symbols(&mut self) {
+        if self
+            .sdk
+            .get_value::<_, bool>(&SYMBOL_INDEXED_KEY.to_owned())
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        let assets = self
+            .assets
+            .iter()
+            .map(|(id, asset)| (id.clone(), asset.symbol))
+            .collect::<Vec<_>>();
+        for (id, symbol) in assets {
+            let key = symbol_key(&symbol);
+            if self.sdk.get_value::<_, Hash>(&key).is_none() {
+                self.sdk.set_value(key, id);
+            }
+        }
+        self.sdk.set_value(SYMBOL_INDEXED_KEY.to_owned(), true);
+    }
+
+    fn _balance_of(&self, user: &Address, asset_id: &Hash) -> AssetBalance {
+        self.sdk
+            .get_account_value(user, asset_id)
+            .unwrap_or_else(|| AssetBalance {
+                value:     0,
+                allowance: BTreeMap::new(),
+            })
+    }
+
+    // Block hooks have no context, read metadata through a system one
+    fn _get_metadata(&self, height: u64, timestamp: u64, caller: &Address) -> Metadata {
+        let ctx = ServiceContext::new(ServiceContextParams {
+            tx_hash: None,
+            nonce: None,
+            cycles_limit: std::u64::MAX,
+            cycles_price: 0,
+            cycles_used: Rc::new(RefCell::new(0)),
+            caller: caller.clone(),
+            height,
+            timestamp,
+            service_name: "metadata".to_owned(),
+            service_method: "get_metadata".to_owned(),
+            service_payload: "".to_owned(),
+            extra: None,
+            events: Rc::new(RefCell::new(vec![])),
+        });
+
+        let resp = self.sdk.read(&ctx, None, "metadata", "get_metadata", "");
+        if resp.is_error() {
+            panic!("get metadata failed: {}", resp.error_message);
+        }
+
+        serde_json::from_str(&resp.succeed_data)
+            .unwrap_or_else(|e| panic!("decode metadata failed: {}", e))
     }
 
     #[cycles(100_00)]
@@ -150,14 +611,36 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
         if self.assets.contains(&id) {
             return ServiceResponse::<Asset>::from_error(102, "asset id existed".to_owned());
         }
+        if payload.symbol.starts_with(BRIDGE_SYMBOL_PREFIX)
+            && caller != service_address(BRIDGE_SERVICE)
+        {
+            return ServiceResponse::<Asset>::from_error(
+                112,
+                "asset symbol reserved for the bridge".to_owned(),
+            );
+        }
+        if self
+            .sdk
+            .get_value::<_, Hash>(&symbol_key(&payload.symbol))
+            .is_some()
+        {
+            return ServiceResponse::<Asset>::from_error(109, "asset symbol existed".to_owned());
+        }
+        if payload.max_supply != 0 && payload.supply > payload.max_supply {
+            return ServiceResponse::<Asset>::from_error(111, "exceed max supply".to_owned());
+        }
+
         let asset = Asset {
-            id:     id.clone(),
-            name:   payload.name,
-            symbol: payload.symbol,
-            supply: payload.supply,
-            issuer: caller,
+            id:         id.clone(),
+            name:       payload.name,
+            symbol:     payload.symbol,
+            supply:     payload.supply,
+            issuer:     caller,
+            decimals:   payload.decimals,
+            max_supply: payload.max_supply,
         };
-        self.assets.insert(id, asset.clone());
+        self.assets.insert(id.clone(), asset.clone());
+        self.sdk.set_value(symbol_key(&asset.symbol), id);
 
         let asset_balance = AssetBalance {
             value:     payload.supply,
@@ -322,6 +805,113 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
         ServiceResponse::<()>::from_succeed(())
     }
 
+    #[cycles(210_00)]
+    #[write]
+    fn mint(&mut self, ctx: ServiceContext, payload: MintPayload) -> ServiceResponse<()> {
+        let caller = ctx.get_caller();
+        let asset_id = payload.asset_id;
+        let value = payload.value;
+        let to = payload.to;
+
+        let mut asset = match self.assets.get(&asset_id) {
+            Some(asset) => asset,
+            None => {
+                return ServiceResponse::<()>::from_error(101, "asset id not existed".to_owned())
+            }
+        };
+
+        if asset.issuer != caller {
+            return ServiceResponse::<()>::from_error(110, "only issuer can mint".to_owned());
+        }
+
+        asset.supply = match asset.supply.checked_add(value) {
+            Some(v) => v,
+            None => return ServiceResponse::<()>::from_error(106, "u64 overflow".to_owned()),
+        };
+        if asset.max_supply != 0 && asset.supply > asset.max_supply {
+            return ServiceResponse::<()>::from_error(111, "exceed max supply".to_owned());
+        }
+
+        let mut to_asset_balance: AssetBalance = self
+            .sdk
+            .get_account_value(&to, &asset_id)
+            .unwrap_or(AssetBalance {
+                value:     0,
+                allowance: BTreeMap::new(),
+            });
+        to_asset_balance.value = match to_asset_balance.value.checked_add(value) {
+            Some(v) => v,
+            None => return ServiceResponse::<()>::from_error(106, "u64 overflow".to_owned()),
+        };
+
+        self.assets.insert(asset_id.clone(), asset);
+        self.sdk
+            .set_account_value(&to, asset_id.clone(), to_asset_balance);
+
+        let event = MintEvent {
+            asset_id,
+            to,
+            value,
+        };
+        let event_res = serde_json::to_string(&event);
+
+        if let Err(e) = event_res {
+            return ServiceResponse::<()>::from_error(103, format!("{:?}", e));
+        };
+        let event_str = event_res.unwrap();
+        ctx.emit_event(event_str);
+
+        ServiceResponse::<()>::from_succeed(())
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn burn(&mut self, ctx: ServiceContext, payload: BurnPayload) -> ServiceResponse<()> {
+        let caller = ctx.get_caller();
+        let asset_id = payload.asset_id;
+        let value = payload.value;
+
+        let mut asset = match self.assets.get(&asset_id) {
+            Some(asset) => asset,
+            None => {
+                return ServiceResponse::<()>::from_error(101, "asset id not existed".to_owned())
+            }
+        };
+
+        let mut caller_asset_balance: AssetBalance = self
+            .sdk
+            .get_account_value(&caller, &asset_id)
+            .unwrap_or(AssetBalance {
+                value:     0,
+                allowance: BTreeMap::new(),
+            });
+        if caller_asset_balance.value < value {
+            return ServiceResponse::<()>::from_error(105, "insufficient balance".to_owned());
+        }
+
+        caller_asset_balance.value -= value;
+        asset.supply = asset.supply.saturating_sub(value);
+
+        self.assets.insert(asset_id.clone(), asset);
+        self.sdk
+            .set_account_value(&caller, asset_id.clone(), caller_asset_balance);
+
+        let event = BurnEvent {
+            asset_id,
+            user: caller,
+            value,
+        };
+        let event_res = serde_json::to_string(&event);
+
+        if let Err(e) = event_res {
+            return ServiceResponse::<()>::from_error(103, format!("{:?}", e));
+        };
+        let event_str = event_res.unwrap();
+        ctx.emit_event(event_str);
+
+        ServiceResponse::<()>::from_succeed(())
+    }
+
     fn _transfer(
         &mut self,
         sender: Address,
@@ -374,3 +964,7 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
         Ok(())
     }
 }
+
+fn symbol_key(symbol: &str) -> String {
+    SYMBOL_KEY_PREFIX.to_owned() + symbol
+}
@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
 use std::rc::Rc;
 use std::sync::Arc;
 
@@ -7,17 +8,23 @@ use cita_trie::MemoryDB;
 
 use framework::binding::sdk::{DefalutServiceSDK, DefaultChainQuerier};
 use framework::binding::state::{GeneralServiceState, MPTTrie};
-use protocol::traits::{NoopDispatcher, Storage};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{NoopDispatcher, ServiceSDK, StateCommitment, Storage};
 use protocol::types::{
-    Address, Block, Hash, Proof, Receipt, ServiceContext, ServiceContextParams, SignedTransaction,
+    service_address, Address, Block, ChainSpec, FeatureActivation, Hash, Metadata, Proof, Receipt,
+    ServiceContext, ServiceContextError, ServiceContextParams, SignedTransaction,
+    STORAGE_RECLAIM_KEY,
 };
 use protocol::{types::Bytes, ProtocolResult};
 
+use crate::deposit::{split_rent, RENT_CURSOR_KEY, RENT_OWNERS_PER_BLOCK};
+use crate::fee::split_fee;
 use crate::types::{
-    ApprovePayload, CreateAssetPayload, GetAllowancePayload, GetAssetPayload, GetBalancePayload,
+    ApprovePayload, Asset, BurnPayload, CreateAssetPayload, GetAllowancePayload, GetAssetPayload,
+    GetBalancePayload, GetClaimableFeePayload, InitGenesisPayload, MintPayload,
     TransferFromPayload, TransferPayload,
 };
-use crate::AssetService;
+use crate::{symbol_key, AssetService};
 
 #[test]
 fn test_create_asset() {
@@ -34,6 +41,8 @@ fn test_create_asset() {
             name: "test".to_owned(),
             symbol: "test".to_owned(),
             supply,
+            decimals: 8,
+            max_supply: 0,
         })
         .succeed_data;
 
@@ -69,6 +78,8 @@ fn test_transfer() {
             name: "test".to_owned(),
             symbol: "test".to_owned(),
             supply,
+            decimals: 8,
+            max_supply: 0,
         })
         .succeed_data;
 
@@ -111,6 +122,8 @@ fn test_approve() {
             name: "test".to_owned(),
             symbol: "test".to_owned(),
             supply,
+            decimals: 8,
+            max_supply: 0,
         })
         .succeed_data;
 
@@ -147,6 +160,8 @@ fn test_transfer_from() {
             name: "test".to_owned(),
             symbol: "test".to_owned(),
             supply,
+            decimals: 8,
+            max_supply: 0,
         })
         .succeed_data;
 
@@ -194,9 +209,461 @@ fn test_transfer_from() {
     assert_eq!(balance_res.balance, 24);
 }
 
+#[test]
+fn test_mint_and_burn() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, caller.clone());
+
+    let mut service = new_asset_service();
+
+    let supply = 1024 * 1024;
+    let asset = service
+        .create_asset(context.clone(), CreateAssetPayload {
+            name: "test".to_owned(),
+            symbol: "test".to_owned(),
+            supply,
+            decimals: 8,
+            max_supply: supply + 1024,
+        })
+        .succeed_data;
+
+    let to_address = Address::from_hex("0x666cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let mint_res = service.mint(context.clone(), MintPayload {
+        asset_id: asset.id.clone(),
+        to:       to_address.clone(),
+        value:    1024,
+    });
+    assert!(!mint_res.is_error());
+
+    // Exceed max supply
+    let mint_res = service.mint(context.clone(), MintPayload {
+        asset_id: asset.id.clone(),
+        to:       to_address.clone(),
+        value:    1,
+    });
+    assert_eq!(mint_res.code, 111);
+
+    // Only issuer can mint
+    let to_context = mock_context(cycles_limit, to_address.clone());
+    let mint_res = service.mint(to_context.clone(), MintPayload {
+        asset_id: asset.id.clone(),
+        to:       to_address.clone(),
+        value:    1,
+    });
+    assert_eq!(mint_res.code, 110);
+
+    let burn_res = service.burn(to_context.clone(), BurnPayload {
+        asset_id: asset.id.clone(),
+        value:    1000,
+    });
+    assert!(!burn_res.is_error());
+
+    let burn_res = service.burn(to_context.clone(), BurnPayload {
+        asset_id: asset.id.clone(),
+        value:    25,
+    });
+    assert_eq!(burn_res.code, 105);
+
+    let balance_res = service
+        .get_balance(to_context, GetBalancePayload {
+            asset_id: asset.id.clone(),
+            user:     to_address,
+        })
+        .succeed_data;
+    assert_eq!(balance_res.balance, 24);
+
+    let asset = service
+        .get_asset(context, GetAssetPayload { id: asset.id })
+        .succeed_data;
+    assert_eq!(asset.supply, supply + 24);
+    assert_eq!(asset.decimals, 8);
+}
+
+#[test]
+fn test_unique_symbol() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, caller);
+
+    let mut service = new_asset_service();
+
+    let create_res = service.create_asset(context.clone(), CreateAssetPayload {
+        name:       "test".to_owned(),
+        symbol:     "test".to_owned(),
+        supply:     1024,
+        decimals:   8,
+        max_supply: 0,
+    });
+    assert!(!create_res.is_error());
+
+    let create_res = service.create_asset(context.clone(), CreateAssetPayload {
+        name:       "test2".to_owned(),
+        symbol:     "test".to_owned(),
+        supply:     1024,
+        decimals:   8,
+        max_supply: 0,
+    });
+    assert_eq!(create_res.code, 109);
+
+    let create_res = service.create_asset(context, CreateAssetPayload {
+        name:       "test3".to_owned(),
+        symbol:     "test3".to_owned(),
+        supply:     1024,
+        decimals:   8,
+        max_supply: 1000,
+    });
+    assert_eq!(create_res.code, 111);
+}
+
+#[test]
+fn test_reserve_bridge_symbol() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, caller);
+
+    let mut service = new_asset_service();
+
+    // Squatted before the first receipt of the voucher
+    let payload = CreateAssetPayload {
+        name:       "squatted".to_owned(),
+        symbol:     "BRIDGE-1A2B3C4D".to_owned(),
+        supply:     1024,
+        decimals:   0,
+        max_supply: 0,
+    };
+    let create_res = service.create_asset(context.clone(), payload.clone());
+    assert_eq!(create_res.code, 112);
+
+    // Bridge still creates the voucher on first receipt
+    let bridge_context = mock_context(cycles_limit, service_address("bridge"));
+    let create_res = service.create_asset(bridge_context, CreateAssetPayload {
+        supply: 0,
+        ..payload
+    });
+    assert!(!create_res.is_error());
+    assert_eq!(create_res.succeed_data.symbol, "BRIDGE-1A2B3C4D");
+}
+
+#[test]
+fn test_index_symbol_of_existing_asset() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, caller.clone());
+
+    let mut service = new_asset_service();
+
+    // Stored before symbols were indexed
+    let existing = Asset {
+        id: Hash::digest(Bytes::from("existing")),
+        name: "existing".to_owned(),
+        symbol: "test".to_owned(),
+        supply: 1024,
+        issuer: caller,
+        ..Default::default()
+    };
+    service.assets.insert(existing.id.clone(), existing.clone());
+    // Block hook before transactions
+    service._index_symbols();
+
+    let create_res = service.create_asset(context.clone(), CreateAssetPayload {
+        name:       "test".to_owned(),
+        symbol:     "test".to_owned(),
+        supply:     1024,
+        decimals:   8,
+        max_supply: 0,
+    });
+    assert_eq!(create_res.code, 109);
+    assert_eq!(
+        service.sdk.get_value::<_, Hash>(&symbol_key("test")),
+        Some(existing.id)
+    );
+
+    let create_res = service.create_asset(context, CreateAssetPayload {
+        name:       "test2".to_owned(),
+        symbol:     "test2".to_owned(),
+        supply:     1024,
+        decimals:   8,
+        max_supply: 0,
+    });
+    assert!(!create_res.is_error());
+}
+
+#[test]
+fn test_asset_codec_versions() {
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let mut asset = Asset {
+        id: Hash::digest(Bytes::from("test")),
+        name: "test".to_owned(),
+        symbol: "test".to_owned(),
+        supply: 1024,
+        issuer: caller,
+        ..Default::default()
+    };
+
+    // Assets without decimals and max supply are stored as they were before
+    let bytes = asset.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(bytes.as_ref()).item_count().unwrap(), 5);
+    assert_eq!(Asset::decode_fixed(bytes).unwrap(), asset);
+
+    asset.decimals = 8;
+    let bytes = asset.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(bytes.as_ref()).item_count().unwrap(), 7);
+    assert_eq!(Asset::decode_fixed(bytes).unwrap(), asset);
+}
+
+#[test]
+fn test_reject_decimals_out_of_range() {
+    let payload = r#"{"name":"test","symbol":"test","supply":1024,"decimals":256}"#;
+    assert!(serde_json::from_str::<CreateAssetPayload>(payload).is_err());
+}
+
+#[test]
+fn test_split_fee() {
+    assert_eq!(split_fee(1000, 8, 2), (800, 200));
+    assert_eq!(split_fee(999, 8, 2), (799, 200));
+    assert_eq!(split_fee(1000, 0, 0), (1000, 0));
+    assert_eq!(split_fee(1000, 0, 1), (0, 1000));
+    assert_eq!(
+        split_fee(std::u64::MAX, std::u64::MAX, 0),
+        (std::u64::MAX, 0)
+    );
+}
+
+#[test]
+fn test_split_rent() {
+    assert_eq!(split_rent(10, 100, 50), (50, 60));
+    assert_eq!(split_rent(10, 20, 50), (30, 0));
+    assert_eq!(split_rent(0, 0, 50), (0, 0));
+}
+
+#[test]
+fn test_storage_deposit_and_rent() {
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let mut service = new_asset_service();
+
+    let supply = 1024 * 1024;
+    let asset_id =
+        Hash::from_hex("0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c")
+            .unwrap();
+    service.init_genesis(InitGenesisPayload {
+        id: asset_id.clone(),
+        name: "MutaToken".to_owned(),
+        symbol: "MT".to_owned(),
+        supply,
+        issuer: caller.clone(),
+        decimals: 8,
+        max_supply: 0,
+        fee_asset: true,
+    });
+
+    let context = mock_context(1024 * 1024, caller.clone());
+    let balance = |service: &AssetService<_>| {
+        service
+            .get_balance(context.clone(), GetBalancePayload {
+                asset_id: asset_id.clone(),
+                user:     caller.clone(),
+            })
+            .succeed_data
+            .balance
+    };
+
+    // Lock 3 for every byte stored, refund for bytes released
+    service._settle_storage_deposit(&context, vec![(caller.clone(), 10)], &asset_id, 3);
+    assert_eq!(balance(&service), supply - 30);
+    service._settle_storage_deposit(&context, vec![(caller.clone(), -4)], &asset_id, 3);
+    assert_eq!(balance(&service), supply - 18);
+
+    // Transaction fails if the caller can't lock the deposit
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        service._settle_storage_deposit(
+            &context,
+            vec![(caller.clone(), supply as i64)],
+            &asset_id,
+            3,
+        )
+    }));
+    match result.map_err(|e| e.downcast::<ServiceContextError>().map(|e| *e)) {
+        Err(Ok(ServiceContextError::InsufficientFee)) => {}
+        _ => panic!("deposit should fail the transaction"),
+    }
+    assert_eq!(balance(&service), supply - 18);
+
+    // Rent of 6 bytes stored
+    let mut metadata = Metadata {
+        rent_epoch: 2,
+        rent_per_byte: 1,
+        chain_spec: storage_deposit_spec(),
+        ..Default::default()
+    };
+    service._charge_storage_rent(1, &metadata);
+    assert_eq!(balance(&service), supply - 18);
+    service._charge_storage_rent(2, &metadata);
+    assert_eq!(balance(&service), supply - 24);
+
+    // Balance can't cover rent, deposit is taken and storage is reclaimed
+    metadata.rent_per_byte = supply;
+    service._charge_storage_rent(4, &metadata);
+    assert_eq!(balance(&service), 0);
+    let reclaimed: Vec<Address> = service
+        .sdk
+        .get_value(&STORAGE_RECLAIM_KEY.to_owned())
+        .unwrap();
+    assert_eq!(reclaimed, vec![caller]);
+    assert_eq!(service._deposit_owners(), 0);
+}
+
+#[test]
+fn test_storage_rent_per_block() {
+    let mut service = new_asset_service();
+    let asset_id =
+        Hash::from_hex("0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c")
+            .unwrap();
+    let issuer = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    service.init_genesis(InitGenesisPayload {
+        id:         asset_id.clone(),
+        name:       "MutaToken".to_owned(),
+        symbol:     "MT".to_owned(),
+        supply:     0,
+        issuer:     issuer.clone(),
+        decimals:   8,
+        max_supply: 0,
+        fee_asset:  true,
+    });
+
+    let context = mock_context(1024 * 1024, issuer);
+    for i in 0..=RENT_OWNERS_PER_BLOCK {
+        let owner =
+            Address::from_bytes(Bytes::from(vec![i as u8, (i >> 8) as u8].repeat(10))).unwrap();
+        service._settle_storage_deposit(&context, vec![(owner, 1)], &asset_id, 0);
+    }
+    assert_eq!(service._deposit_owners(), RENT_OWNERS_PER_BLOCK + 1);
+
+    let metadata = Metadata {
+        rent_epoch: 2,
+        chain_spec: storage_deposit_spec(),
+        ..Default::default()
+    };
+    let cursor = |service: &AssetService<_>| -> u64 {
+        service.sdk.get_value(&RENT_CURSOR_KEY.to_owned()).unwrap()
+    };
+
+    // Owners left pay in the next block, the epoch starts after all paid
+    service._charge_storage_rent(2, &metadata);
+    assert_eq!(cursor(&service), RENT_OWNERS_PER_BLOCK);
+    service._charge_storage_rent(3, &metadata);
+    assert_eq!(cursor(&service), std::u64::MAX);
+    service._charge_storage_rent(5, &metadata);
+    assert_eq!(cursor(&service), std::u64::MAX);
+}
+
+#[test]
+fn test_genesis_without_fee_asset() {
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let mut service = new_asset_service();
+
+    let supply = 1024 * 1024;
+    let asset_id =
+        Hash::from_hex("0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c")
+            .unwrap();
+    service.init_genesis(InitGenesisPayload {
+        id: asset_id.clone(),
+        name: "MutaToken".to_owned(),
+        symbol: "MT".to_owned(),
+        supply,
+        issuer: caller.clone(),
+        decimals: 0,
+        max_supply: 0,
+        fee_asset: false,
+    });
+
+    // Nothing is charged, balance of the caller is kept
+    let context = mock_context(1024 * 1024, caller.clone());
+    context.sub_cycles(1000);
+    service.collect_fee(context.clone());
+
+    let balance_res = service
+        .get_balance(context.clone(), GetBalancePayload {
+            asset_id,
+            user: caller.clone(),
+        })
+        .succeed_data;
+    assert_eq!(balance_res.balance, supply);
+
+    let claimable_res = service.get_claimable_fee(context, GetClaimableFeePayload { user: caller });
+    assert_eq!(claimable_res.code, 107);
+}
+
+#[test]
+fn test_claim_fee() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let proposer = Address::from_hex("0x666cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let treasury = Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap();
+
+    let mut service = new_asset_service();
+
+    let supply = 1024 * 1024;
+    let asset_id =
+        Hash::from_hex("0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c")
+            .unwrap();
+    service.init_genesis(InitGenesisPayload {
+        id: asset_id.clone(),
+        name: "MutaToken".to_owned(),
+        symbol: "MT".to_owned(),
+        supply,
+        issuer: caller.clone(),
+        decimals: 8,
+        max_supply: 0,
+        fee_asset: true,
+    });
+
+    let context = mock_context(cycles_limit, caller.clone());
+    context.sub_cycles(1000);
+    service.collect_fee(context.clone());
+
+    let balance_res = service
+        .get_balance(context.clone(), GetBalancePayload {
+            asset_id: asset_id.clone(),
+            user:     caller,
+        })
+        .succeed_data;
+    assert_eq!(balance_res.balance, supply - 1000);
+
+    let metadata = Metadata {
+        proposer_fee_ratio: 8,
+        treasury_fee_ratio: 2,
+        treasury: treasury.clone(),
+        ..Default::default()
+    };
+    service._distribute_fee(1000, &proposer, &metadata);
+
+    let claimable_res = service
+        .get_claimable_fee(context.clone(), GetClaimableFeePayload {
+            user: treasury.clone(),
+        })
+        .succeed_data;
+    assert_eq!(claimable_res.value, 200);
+
+    let proposer_context = mock_context(cycles_limit, proposer.clone());
+    let claim_res = service.claim_fee(proposer_context.clone());
+    assert_eq!(claim_res.succeed_data, 800);
+
+    let claim_res = service.claim_fee(proposer_context.clone());
+    assert!(claim_res.is_error());
+
+    let balance_res = service
+        .get_balance(proposer_context, GetBalancePayload {
+            asset_id,
+            user: proposer,
+        })
+        .succeed_data;
+    assert_eq!(balance_res.balance, 800);
+}
+
 fn new_asset_service() -> AssetService<
     DefalutServiceSDK<
-        GeneralServiceState<MemoryDB>,
+        GeneralServiceState<MPTTrie<MemoryDB>>,
         DefaultChainQuerier<MockStorage>,
         NoopDispatcher,
     >,
@@ -214,6 +681,15 @@ fn new_asset_service() -> AssetService<
     AssetService::new(sdk)
 }
 
+fn storage_deposit_spec() -> ChainSpec {
+    ChainSpec {
+        activations: vec![FeatureActivation {
+            name:   "storage_deposit".to_owned(),
+            height: 0,
+        }],
+    }
+}
+
 fn mock_context(cycles_limit: u64, caller: Address) -> ServiceContext {
     let params = ServiceContextParams {
         tx_hash: None,
@@ -11,18 +11,30 @@ use protocol::ProtocolResult;
 /// Payload
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct InitGenesisPayload {
-    pub id:     Hash,
-    pub name:   String,
-    pub symbol: String,
-    pub supply: u64,
-    pub issuer: Address,
+    pub id:         Hash,
+    pub name:       String,
+    pub symbol:     String,
+    pub supply:     u64,
+    pub issuer:     Address,
+    #[serde(default)]
+    pub decimals:   u8,
+    #[serde(default)]
+    pub max_supply: u64,
+    // Fee is paid in the asset, chains without it charge no fee
+    #[serde(default)]
+    pub fee_asset:  bool,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct CreateAssetPayload {
-    pub name:   String,
-    pub symbol: String,
-    pub supply: u64,
+    pub name:       String,
+    pub symbol:     String,
+    pub supply:     u64,
+    #[serde(default)]
+    pub decimals:   u8,
+    // Zero means supply is not capped
+    #[serde(default)]
+    pub max_supply: u64,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -72,6 +84,33 @@ pub struct TransferFromEvent {
     pub value:     u64,
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct MintPayload {
+    pub asset_id: Hash,
+    pub to:       Address,
+    pub value:    u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct MintEvent {
+    pub asset_id: Hash,
+    pub to:       Address,
+    pub value:    u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct BurnPayload {
+    pub asset_id: Hash,
+    pub value:    u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct BurnEvent {
+    pub asset_id: Hash,
+    pub user:     Address,
+    pub value:    u64,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct GetBalancePayload {
     pub asset_id: Hash,
@@ -100,13 +139,36 @@ pub struct GetAllowanceResponse {
     pub value:    u64,
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GetClaimableFeePayload {
+    pub user: Address,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct GetClaimableFeeResponse {
+    pub asset_id: Hash,
+    pub user:     Address,
+    pub value:    u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ClaimFeeEvent {
+    pub asset_id: Hash,
+    pub user:     Address,
+    pub value:    u64,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Default)]
 pub struct Asset {
-    pub id:     Hash,
-    pub name:   String,
-    pub symbol: String,
-    pub supply: u64,
-    pub issuer: Address,
+    pub id:         Hash,
+    pub name:       String,
+    pub symbol:     String,
+    pub supply:     u64,
+    pub issuer:     Address,
+    #[serde(default)]
+    pub decimals:   u8,
+    #[serde(default)]
+    pub max_supply: u64,
 }
 
 pub struct AssetBalance {
@@ -121,24 +183,38 @@ struct AllowanceCodec {
 
 impl rlp::Decodable for Asset {
     fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        // Assets stored before decimals and max supply, or without them, have
+        // 5 items
+        let (decimals, max_supply) = match rlp.item_count()? {
+            5 => (0, 0),
+            7 => (rlp.at(5)?.as_val()?, rlp.at(6)?.as_val()?),
+            _ => return Err(rlp::DecoderError::RlpIncorrectListLen),
+        };
+
         Ok(Self {
-            id:     rlp.at(0)?.as_val()?,
-            name:   rlp.at(1)?.as_val()?,
+            id: rlp.at(0)?.as_val()?,
+            name: rlp.at(1)?.as_val()?,
             symbol: rlp.at(2)?.as_val()?,
             supply: rlp.at(3)?.as_val()?,
             issuer: rlp.at(4)?.as_val()?,
+            decimals,
+            max_supply,
         })
     }
 }
 
 impl rlp::Encodable for Asset {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
-        s.begin_list(5)
+        let legacy = self.decimals == 0 && self.max_supply == 0;
+        s.begin_list(if legacy { 5 } else { 7 })
             .append(&self.id)
             .append(&self.name)
             .append(&self.symbol)
             .append(&self.supply)
             .append(&self.issuer);
+        if !legacy {
+            s.append(&self.decimals).append(&self.max_supply);
+        }
     }
 }
 
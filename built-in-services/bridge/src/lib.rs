@@ -0,0 +1,548 @@
+pub mod light_client;
+#[cfg(test)]
+mod tests;
+pub mod types;
+
+use bytes::Bytes;
+use serde_json::json;
+
+use binding_macro::{cycles, genesis, service};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{ExecutorParams, ServiceResponse, ServiceSDK};
+use protocol::types::{
+    service_address, Address, Hash, MerkleRoot, Metadata, ServiceContext, ValidatorExtend,
+    METADATA_KEY,
+};
+
+use crate::light_client::{verify_block, LightClientError};
+use crate::types::{
+    ClientState, CreateClientPayload, GetClientPayload, GetPacketPayload, GetSequencePayload,
+    GetStateRootPayload, InitGenesisPayload, MetadataProof, Packet, ReceivePacketEvent,
+    ReceivePacketPayload, SendPacketEvent, TrackedRoot, TransferPayload, UpdateClientEvent,
+    UpdateClientPayload, Voucher,
+};
+
+const CHAIN_ID_KEY: &str = "chain_id";
+const ADMIN_KEY: &str = "admin";
+const ASSET_SERVICE: &str = "asset";
+const METADATA_SERVICE: &str = "metadata";
+// State roots kept per client, packets are proved at one of recent ones
+pub const TRACKED_ROOTS: u64 = 1024;
+
+/// Moves assets between this chain and counterparty chains. Counterparty
+/// blocks are tracked by light clients, and a packet sent there is stored as
+/// a commitment in our state, so a relayer proves it to the counterparty by
+/// state proof. Assets of this chain are escrowed by the service, assets of
+/// counterparty are represented by vouchers minted here.
+///
+/// Packets are delivered in order without acknowledgement or timeout yet, a
+/// packet failed on counterparty is not refunded.
+pub struct BridgeService<SDK> {
+    sdk: SDK,
+}
+
+#[service]
+impl<SDK: ServiceSDK> BridgeService<SDK> {
+    pub fn new(sdk: SDK) -> Self {
+        Self { sdk }
+    }
+
+    #[genesis]
+    fn init_genesis(&mut self, payload: InitGenesisPayload) {
+        self.sdk
+            .set_value(CHAIN_ID_KEY.to_owned(), payload.chain_id);
+        self.sdk.set_value(ADMIN_KEY.to_owned(), payload.admin);
+
+        for client in payload.clients.into_iter() {
+            self._store_client(client);
+        }
+    }
+
+    #[cycles(100_00)]
+    #[read]
+    fn get_client(
+        &self,
+        ctx: ServiceContext,
+        payload: GetClientPayload,
+    ) -> ServiceResponse<ClientState> {
+        match self._client(&payload.chain_id) {
+            Some(client) => ServiceResponse::<ClientState>::from_succeed(client),
+            None => ServiceResponse::<ClientState>::from_error(101, "client not found".to_owned()),
+        }
+    }
+
+    #[cycles(100_00)]
+    #[read]
+    fn get_state_root(
+        &self,
+        ctx: ServiceContext,
+        payload: GetStateRootPayload,
+    ) -> ServiceResponse<MerkleRoot> {
+        match self._state_root(&payload.chain_id, payload.height) {
+            Some(root) => ServiceResponse::<MerkleRoot>::from_succeed(root),
+            None => ServiceResponse::<MerkleRoot>::from_error(
+                111,
+                "state root not tracked at height".to_owned(),
+            ),
+        }
+    }
+
+    // Sequence of next packet sent to the chain
+    #[cycles(100_00)]
+    #[read]
+    fn get_send_sequence(
+        &self,
+        ctx: ServiceContext,
+        payload: GetSequencePayload,
+    ) -> ServiceResponse<u64> {
+        ServiceResponse::<u64>::from_succeed(self._sequence(&send_sequence_key(&payload.chain_id)))
+    }
+
+    // Sequence of next packet expected from the chain
+    #[cycles(100_00)]
+    #[read]
+    fn get_receive_sequence(
+        &self,
+        ctx: ServiceContext,
+        payload: GetSequencePayload,
+    ) -> ServiceResponse<u64> {
+        ServiceResponse::<u64>::from_succeed(
+            self._sequence(&receive_sequence_key(&payload.chain_id)),
+        )
+    }
+
+    #[cycles(100_00)]
+    #[read]
+    fn get_packet(
+        &self,
+        ctx: ServiceContext,
+        payload: GetPacketPayload,
+    ) -> ServiceResponse<Packet> {
+        match self
+            .sdk
+            .get_value(&packet_key(&payload.dest_chain, payload.sequence))
+        {
+            Some(packet) => ServiceResponse::<Packet>::from_succeed(packet),
+            None => ServiceResponse::<Packet>::from_error(112, "packet not found".to_owned()),
+        }
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn create_client(
+        &mut self,
+        ctx: ServiceContext,
+        payload: CreateClientPayload,
+    ) -> ServiceResponse<()> {
+        let admin: Option<Address> = self.sdk.get_value(&ADMIN_KEY.to_owned());
+        if admin != Some(ctx.get_caller()) {
+            return ServiceResponse::<()>::from_error(
+                102,
+                "only admin can create client".to_owned(),
+            );
+        }
+
+        if self._client(&payload.client.chain_id).is_some() {
+            return ServiceResponse::<()>::from_error(104, "client existed".to_owned());
+        }
+
+        self._store_client(payload.client);
+        ServiceResponse::<()>::from_succeed(())
+    }
+
+    // Anyone can update client, the block is verified by its proof
+    #[cycles(210_00)]
+    #[write]
+    fn update_client(
+        &mut self,
+        ctx: ServiceContext,
+        payload: UpdateClientPayload,
+    ) -> ServiceResponse<()> {
+        let mut client = match self._client(&payload.chain_id) {
+            Some(client) => client,
+            None => return ServiceResponse::<()>::from_error(101, "client not found".to_owned()),
+        };
+
+        // Validators of counterparty are changed, the block is signed by the
+        // proved new ones
+        let mut changed = None;
+        if let Some(metadata_proof) = payload.metadata_proof.as_ref() {
+            let validators = match self._prove_validators(&client, metadata_proof) {
+                Ok(validators) => validators,
+                Err(e) => return ServiceResponse::<()>::from_error(113, e),
+            };
+            // Bls keys may be rotated with the same addresses
+            if validators != client.validators {
+                client.validators = validators.clone();
+                changed = Some(validators);
+            }
+        }
+
+        let block = match verify_block(&client, payload.block.as_bytes(), &payload.proof) {
+            Ok(block) => block,
+            Err(e @ LightClientError::NoQuorum) | Err(e @ LightClientError::Signature) => {
+                return ServiceResponse::<()>::from_error(106, e.to_string())
+            }
+            Err(e @ LightClientError::ValidatorsChanged) => {
+                return ServiceResponse::<()>::from_error(113, e.to_string())
+            }
+            Err(e) => return ServiceResponse::<()>::from_error(105, e.to_string()),
+        };
+
+        client.height = block.header.height;
+        client.exec_height = block.header.exec_height;
+        client.state_root = block.header.state_root.clone();
+        self._store_client(client);
+
+        let event = UpdateClientEvent {
+            chain_id:    payload.chain_id,
+            height:      block.header.height,
+            exec_height: block.header.exec_height,
+            state_root:  block.header.state_root,
+            validators:  changed,
+        };
+        self._emit_event(&ctx, &event)
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn transfer(&mut self, ctx: ServiceContext, payload: TransferPayload) -> ServiceResponse<()> {
+        if self._client(&payload.dest_chain).is_none() {
+            return ServiceResponse::<()>::from_error(101, "client not found".to_owned());
+        }
+
+        let chain_id = self._chain_id();
+        let sender = ctx.get_caller();
+
+        let voucher: Option<Voucher> = self.sdk.get_value(&voucher_key(&payload.asset_id));
+        let (origin_chain, asset_id, call_res) = match voucher {
+            // Voucher goes back to its origin chain, it's burnt here
+            Some(voucher) => {
+                if voucher.origin_chain != payload.dest_chain {
+                    return ServiceResponse::<()>::from_error(
+                        110,
+                        "voucher can only be sent to its origin chain".to_owned(),
+                    );
+                }
+
+                let call_payload = json!({
+                    "asset_id": payload.asset_id,
+                    "value": payload.value,
+                });
+                let res = self._call_asset(&ctx, "burn", call_payload);
+                (voucher.origin_chain, voucher.asset_id, res)
+            }
+            None => {
+                let call_payload = json!({
+                    "asset_id": payload.asset_id,
+                    "to": service_address(ctx.get_service_name()),
+                    "value": payload.value,
+                });
+                let res = self._call_asset(&ctx, "transfer", call_payload);
+                (chain_id.clone(), payload.asset_id, res)
+            }
+        };
+        if call_res.is_error() {
+            return ServiceResponse::<()>::from_error(109, call_res.error_message);
+        }
+
+        let sequence_key = send_sequence_key(&payload.dest_chain);
+        let sequence = self._sequence(&sequence_key);
+        let packet = Packet {
+            source_chain: chain_id,
+            dest_chain: payload.dest_chain.clone(),
+            sequence,
+            origin_chain,
+            asset_id,
+            sender,
+            receiver: payload.receiver,
+            value: payload.value,
+        };
+
+        let commitment = match packet.commitment() {
+            Ok(commitment) => commitment,
+            Err(e) => return ServiceResponse::<()>::from_error(103, format!("{:?}", e)),
+        };
+        self.sdk
+            .set_value(commitment_key(&payload.dest_chain, sequence), commitment);
+        self.sdk
+            .set_value(packet_key(&payload.dest_chain, sequence), packet.clone());
+        self.sdk.set_value(sequence_key, sequence + 1);
+
+        self._emit_event(&ctx, &SendPacketEvent { packet })
+    }
+
+    // Relayer proves commitment of the packet in counterparty state
+    #[cycles(210_00)]
+    #[write]
+    fn receive_packet(
+        &mut self,
+        ctx: ServiceContext,
+        payload: ReceivePacketPayload,
+    ) -> ServiceResponse<()> {
+        let packet = payload.packet;
+        let chain_id = self._chain_id();
+        if packet.dest_chain != chain_id {
+            return ServiceResponse::<()>::from_error(
+                108,
+                "packet is not for this chain".to_owned(),
+            );
+        }
+
+        let state_root = match self._state_root(&packet.source_chain, payload.proof_height) {
+            Some(root) => root,
+            None => {
+                return ServiceResponse::<()>::from_error(
+                    111,
+                    "state root not tracked at height".to_owned(),
+                )
+            }
+        };
+
+        // Counterparty runs the bridge under the same service name
+        let key = match commitment_key(&chain_id, packet.sequence).encode_fixed() {
+            Ok(key) => key,
+            Err(e) => return ServiceResponse::<()>::from_error(103, format!("{:?}", e)),
+        };
+        let expect_commitment = match packet.commitment() {
+            Ok(commitment) => commitment,
+            Err(e) => return ServiceResponse::<()>::from_error(103, format!("{:?}", e)),
+        };
+        if payload.proof.service_name != ctx.get_service_name() || payload.proof.key != key {
+            return ServiceResponse::<()>::from_error(
+                107,
+                "proof is not for the packet".to_owned(),
+            );
+        }
+
+        let proved = payload
+            .proof
+            .verify(&state_root)
+            .ok()
+            .and_then(|value| value)
+            .and_then(|value| Hash::decode_fixed(value).ok());
+        if proved != Some(expect_commitment) {
+            return ServiceResponse::<()>::from_error(
+                107,
+                "packet commitment is not proved".to_owned(),
+            );
+        }
+
+        let sequence_key = receive_sequence_key(&packet.source_chain);
+        if packet.sequence != self._sequence(&sequence_key) {
+            return ServiceResponse::<()>::from_error(108, "unexpected sequence".to_owned());
+        }
+
+        let service_ctx = ServiceContext::with_service_caller(&ctx);
+        let asset_id = if packet.origin_chain == chain_id {
+            // Asset of this chain comes back, release it from escrow
+            let call_payload = json!({
+                "asset_id": packet.asset_id,
+                "to": packet.receiver,
+                "value": packet.value,
+            });
+            let res = self._call_asset(&service_ctx, "transfer", call_payload);
+            if res.is_error() {
+                return ServiceResponse::<()>::from_error(109, res.error_message);
+            }
+            packet.asset_id.clone()
+        } else {
+            let voucher_id = match self._voucher_id(&service_ctx, &packet) {
+                Ok(voucher_id) => voucher_id,
+                Err(e) => return ServiceResponse::<()>::from_error(109, e),
+            };
+
+            let call_payload = json!({
+                "asset_id": voucher_id,
+                "to": packet.receiver,
+                "value": packet.value,
+            });
+            let res = self._call_asset(&service_ctx, "mint", call_payload);
+            if res.is_error() {
+                return ServiceResponse::<()>::from_error(109, res.error_message);
+            }
+            voucher_id
+        };
+
+        self.sdk.set_value(sequence_key, packet.sequence + 1);
+        self._emit_event(&ctx, &ReceivePacketEvent { packet, asset_id })
+    }
+
+    // Voucher asset is created by the service on first receipt, so only the
+    // service can mint it. Asset service reserves the `BRIDGE-` symbols for
+    // the service, no one can take the symbol of a voucher before.
+    fn _voucher_id(
+        &mut self,
+        service_ctx: &ServiceContext,
+        packet: &Packet,
+    ) -> Result<Hash, String> {
+        let voucher = Voucher {
+            origin_chain: packet.origin_chain.clone(),
+            asset_id:     packet.asset_id.clone(),
+        };
+        let origin = voucher_origin(&voucher);
+        let origin_key = format!("origin_{}", origin.as_hex());
+        if let Some(voucher_id) = self.sdk.get_value(&origin_key) {
+            return Ok(voucher_id);
+        }
+
+        let symbol = format!("BRIDGE-{}", origin.as_hex()[2..10].to_uppercase());
+        let call_payload = json!({
+            "name": format!("Bridged {}", packet.asset_id.as_hex()),
+            "symbol": symbol,
+            "supply": 0,
+        });
+        let res = self._call_asset(service_ctx, "create_asset", call_payload);
+        if res.is_error() {
+            return Err(res.error_message);
+        }
+
+        let created: serde_json::Value =
+            serde_json::from_str(&res.succeed_data).map_err(|e| format!("{:?}", e))?;
+        let voucher_id = created["id"]
+            .as_str()
+            .and_then(|id| Hash::from_hex(id).ok())
+            .ok_or_else(|| "invalid asset id".to_owned())?;
+
+        self.sdk.set_value(origin_key, voucher_id.clone());
+        self.sdk.set_value(voucher_key(&voucher_id), voucher);
+        Ok(voucher_id)
+    }
+
+    fn _call_asset(
+        &mut self,
+        ctx: &ServiceContext,
+        method: &str,
+        payload: serde_json::Value,
+    ) -> ServiceResponse<String> {
+        self.sdk
+            .write(ctx, None, ASSET_SERVICE, method, &payload.to_string())
+    }
+
+    fn _chain_id(&self) -> Hash {
+        self.sdk
+            .get_value(&CHAIN_ID_KEY.to_owned())
+            .expect("chain id should not be none")
+    }
+
+    fn _client(&self, chain_id: &Hash) -> Option<ClientState> {
+        self.sdk.get_value(&client_key(chain_id))
+    }
+
+    // Root of an exec height takes the slot of the root `TRACKED_ROOTS`
+    // heights before, so state of the client doesn't grow
+    fn _store_client(&mut self, client: ClientState) {
+        let tracked = TrackedRoot {
+            exec_height: client.exec_height,
+            state_root:  client.state_root.clone(),
+        };
+        self.sdk.set_value(
+            state_root_key(&client.chain_id, client.exec_height),
+            tracked,
+        );
+        self.sdk.set_value(client_key(&client.chain_id), client);
+    }
+
+    fn _state_root(&self, chain_id: &Hash, exec_height: u64) -> Option<MerkleRoot> {
+        self.sdk
+            .get_value::<_, TrackedRoot>(&state_root_key(chain_id, exec_height))
+            .filter(|tracked| tracked.exec_height == exec_height)
+            .map(|tracked| tracked.state_root)
+    }
+
+    // Verifier list in metadata of counterparty, proved at a tracked root
+    fn _prove_validators(
+        &self,
+        client: &ClientState,
+        metadata_proof: &MetadataProof,
+    ) -> Result<Vec<ValidatorExtend>, String> {
+        let state_root = self
+            ._state_root(&client.chain_id, metadata_proof.proof_height)
+            .ok_or_else(|| "state root not tracked at height".to_owned())?;
+
+        let proof = &metadata_proof.proof;
+        let key = METADATA_KEY
+            .to_owned()
+            .encode_fixed()
+            .map_err(|e| format!("{:?}", e))?;
+        if proof.service_name != METADATA_SERVICE || proof.key != key {
+            return Err("proof is not for metadata".to_owned());
+        }
+
+        let metadata = proof
+            .verify(&state_root)
+            .ok()
+            .and_then(|value| value)
+            .and_then(|value| Metadata::decode_fixed(value).ok())
+            .ok_or_else(|| "metadata is not proved".to_owned())?;
+        if metadata.chain_id != client.chain_id {
+            return Err("metadata is from other chain".to_owned());
+        }
+        if metadata.verifier_list.is_empty() {
+            return Err("verifier list is empty".to_owned());
+        }
+        Ok(metadata.verifier_list)
+    }
+
+    fn _sequence(&self, key: &str) -> u64 {
+        self.sdk.get_value(&key.to_owned()).unwrap_or(0)
+    }
+
+    fn _emit_event<T: serde::Serialize>(
+        &self,
+        ctx: &ServiceContext,
+        event: &T,
+    ) -> ServiceResponse<()> {
+        let event_res = serde_json::to_string(event);
+
+        if let Err(e) = event_res {
+            return ServiceResponse::<()>::from_error(103, format!("{:?}", e));
+        };
+        let event_str = event_res.unwrap();
+        ctx.emit_event(event_str);
+
+        ServiceResponse::<()>::from_succeed(())
+    }
+}
+
+fn client_key(chain_id: &Hash) -> String {
+    format!("client_{}", chain_id.as_hex())
+}
+
+fn state_root_key(chain_id: &Hash, exec_height: u64) -> String {
+    format!(
+        "state_root_{}_{}",
+        chain_id.as_hex(),
+        exec_height % TRACKED_ROOTS
+    )
+}
+
+fn send_sequence_key(chain_id: &Hash) -> String {
+    format!("send_sequence_{}", chain_id.as_hex())
+}
+
+fn receive_sequence_key(chain_id: &Hash) -> String {
+    format!("receive_sequence_{}", chain_id.as_hex())
+}
+
+/// Key of packet commitment in state of the bridge service, relayer gets
+/// state proof of it.
+pub fn commitment_key(dest_chain: &Hash, sequence: u64) -> String {
+    format!("commitment_{}_{}", dest_chain.as_hex(), sequence)
+}
+
+fn packet_key(dest_chain: &Hash, sequence: u64) -> String {
+    format!("packet_{}_{}", dest_chain.as_hex(), sequence)
+}
+
+// Voucher id to its origin asset
+fn voucher_key(voucher_id: &Hash) -> String {
+    format!("voucher_{}", voucher_id.as_hex())
+}
+
+// Origin asset is indexed by the hash to its voucher id
+fn voucher_origin(voucher: &Voucher) -> Hash {
+    Hash::digest(Bytes::from(
+        voucher.origin_chain.as_hex() + &voucher.asset_id.as_hex(),
+    ))
+}
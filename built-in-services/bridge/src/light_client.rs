@@ -0,0 +1,154 @@
+use std::convert::TryFrom;
+
+use bytes::Bytes;
+use derive_more::Display;
+use overlord::types::{Vote, VoteType};
+
+use common_crypto::{
+    BlsCommonReference, BlsPublicKey, BlsSignature, BlsSignatureVerify, HashValue,
+};
+use protocol::fixed_codec::FixedCodec;
+use protocol::types::{
+    signing_hash, Address, Block, Hash, Proof, Validator, ValidatorExtend,
+    CONSENSUS_SIGNATURE_PURPOSE,
+};
+
+use crate::types::ClientState;
+
+#[derive(Debug, Display, PartialEq, Eq)]
+pub enum LightClientError {
+    #[display(fmt = "decode block failed")]
+    DecodeBlock,
+
+    #[display(fmt = "block is from chain {:?}", _0)]
+    ChainMismatch(Hash),
+
+    #[display(fmt = "block height {} is not above client height {}", block, client)]
+    Outdated { block: u64, client: u64 },
+
+    #[display(fmt = "proof is not for the block")]
+    ProofMismatch,
+
+    #[display(fmt = "voters don't reach quorum")]
+    NoQuorum,
+
+    #[display(fmt = "invalid bls public key of {:?}", _0)]
+    PublicKey(Address),
+
+    #[display(fmt = "invalid aggregated signature")]
+    Signature,
+
+    #[display(fmt = "validators of the block are changed")]
+    ValidatorsChanged,
+}
+
+/// Verify a counterparty block against the client, the block is final once
+/// more than 2/3 vote weight of validators precommit it.
+pub fn verify_block(
+    client: &ClientState,
+    encoded_block: Bytes,
+    proof: &Proof,
+) -> Result<Block, LightClientError> {
+    // Same as block hash of consensus
+    let block_hash = Hash::digest(encoded_block.clone());
+    let block = Block::decode_fixed(encoded_block).map_err(|_| LightClientError::DecodeBlock)?;
+
+    if block.header.chain_id != client.chain_id {
+        return Err(LightClientError::ChainMismatch(block.header.chain_id));
+    }
+    if block.header.height <= client.height {
+        return Err(LightClientError::Outdated {
+            block:  block.header.height,
+            client: client.height,
+        });
+    }
+    if proof.block_hash != block_hash || proof.height != block.header.height {
+        return Err(LightClientError::ProofMismatch);
+    }
+    if !same_validators(&client.validators, &block.header.validators) {
+        return Err(LightClientError::ValidatorsChanged);
+    }
+
+    let voters = extract_voters(&client.validators, &proof.bitmap);
+    let total_weight: u64 = client.validators.iter().map(|v| v.vote_weight as u64).sum();
+    let voted_weight: u64 = voters.iter().map(|v| v.vote_weight as u64).sum();
+    if voted_weight * 3 <= total_weight * 2 {
+        return Err(LightClientError::NoQuorum);
+    }
+
+    let mut pub_keys = Vec::with_capacity(voters.len());
+    for voter in voters.iter() {
+        let pub_key = BlsPublicKey::try_from(voter.bls_pub_key.as_bytes().as_ref())
+            .map_err(|_| LightClientError::PublicKey(voter.address.clone()))?;
+        pub_keys.push(pub_key);
+    }
+
+    let vote = Vote {
+        height:     proof.height,
+        round:      proof.round,
+        vote_type:  VoteType::Precommit,
+        block_hash: proof.block_hash.as_bytes(),
+    };
+    let vote_hash = Hash::digest(Bytes::from(rlp::encode(&vote)));
+    let vote_hash = signing_hash(
+        client.signature_domain(proof.height).as_ref(),
+        CONSENSUS_SIGNATURE_PURPOSE,
+        &vote_hash,
+    );
+    let hash = HashValue::try_from(vote_hash.as_bytes().as_ref())
+        .map_err(|_| LightClientError::Signature)?;
+    let signature = BlsSignature::try_from(proof.signature.as_ref())
+        .map_err(|_| LightClientError::Signature)?;
+
+    let common_ref = String::from_utf8(client.common_ref.as_bytes().to_vec())
+        .map_err(|_| LightClientError::Signature)?;
+    let common_ref: BlsCommonReference = common_ref.as_str().into();
+
+    signature
+        .verify(
+            &hash,
+            &BlsPublicKey::aggregate(pub_keys.iter().collect()),
+            &common_ref,
+        )
+        .map_err(|_| LightClientError::Signature)?;
+
+    Ok(block)
+}
+
+/// Whether validators the block is signed by, in its header, are the tracked
+/// ones, bls public keys aren't in header.
+pub fn same_validators(tracked: &[ValidatorExtend], validators: &[Validator]) -> bool {
+    let mut tracked = tracked
+        .iter()
+        .map(|v| (v.address.clone(), v.propose_weight, v.vote_weight))
+        .collect::<Vec<_>>();
+    let mut validators = validators
+        .iter()
+        .map(|v| (v.address.clone(), v.propose_weight, v.vote_weight))
+        .collect::<Vec<_>>();
+    tracked.sort_by(|a, b| a.0.as_bytes().cmp(&b.0.as_bytes()));
+    validators.sort_by(|a, b| a.0.as_bytes().cmp(&b.0.as_bytes()));
+
+    tracked == validators
+}
+
+// Bitmap marks voters in validators sorted by address, highest bit first
+pub fn extract_voters<'a>(
+    validators: &'a [ValidatorExtend],
+    bitmap: &Bytes,
+) -> Vec<&'a ValidatorExtend> {
+    let mut sorted = validators.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.address.as_bytes().cmp(&b.address.as_bytes()));
+
+    sorted
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            bitmap
+                .get(i / 8)
+                .map(|byte| byte & (0x80 >> (i % 8)) != 0)
+                .unwrap_or(false)
+        })
+        .map(|(_, v)| v)
+        .collect()
+}
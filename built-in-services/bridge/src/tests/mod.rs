@@ -0,0 +1,577 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use cita_trie::MemoryDB;
+
+use common_crypto::{
+    BlsCommonReference, BlsPrivateKey, BlsSignature, HashValue, PrivateKey, PublicKey, Signature,
+    ToBlsPublicKey,
+};
+use framework::binding::sdk::{DefalutServiceSDK, DefaultChainQuerier};
+use framework::binding::state::{GeneralServiceState, MPTTrie};
+use overlord::types::{Vote, VoteType};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{NoopDispatcher, StateCommitment, Storage};
+use protocol::types::{
+    Address, Block, BlockHeader, CommitmentScheme, Hash, Hex, MerkleRoot, Metadata, Proof, Receipt,
+    ServiceContext, ServiceContextParams, SignedTransaction, StateProof, Validator,
+    ValidatorExtend, METADATA_KEY,
+};
+use protocol::{types::Bytes, ProtocolResult};
+
+use crate::types::{
+    ClientState, CreateClientPayload, GetClientPayload, GetStateRootPayload, InitGenesisPayload,
+    MetadataProof, Packet, ReceivePacketPayload, UpdateClientPayload,
+};
+use crate::{commitment_key, BridgeService, TRACKED_ROOTS};
+
+const COMMON_REF: &str = "0x703873635a6b51513451";
+
+#[test]
+fn test_update_client() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let admin = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, admin.clone());
+
+    let counterparty = Hash::digest(Bytes::from("counterparty"));
+    let private_keys = mock_private_keys();
+    let client = mock_client(counterparty.clone(), &private_keys);
+
+    let mut service = new_bridge_service();
+    service.init_genesis(InitGenesisPayload {
+        chain_id: Hash::digest(Bytes::from("local")),
+        admin:    admin.clone(),
+        clients:  vec![],
+    });
+
+    let create_res = service.create_client(context.clone(), CreateClientPayload {
+        client: client.clone(),
+    });
+    assert!(!create_res.is_error());
+    let create_res = service.create_client(context.clone(), CreateClientPayload {
+        client: client.clone(),
+    });
+    assert_eq!(create_res.code, 104);
+    let other_context = mock_context(cycles_limit, Address::default());
+    let create_res = service.create_client(other_context, CreateClientPayload {
+        client: client.clone(),
+    });
+    assert_eq!(create_res.code, 102);
+
+    let state_root = Hash::digest(Bytes::from("state_root"));
+    let encoded_block = mock_block(&client, 1, state_root.clone())
+        .encode_fixed()
+        .unwrap();
+
+    // 2 of 4 validators don't reach quorum
+    let proof = mock_proof(&encoded_block, 1, &private_keys, &[0, 1]);
+    let update_res = service.update_client(
+        context.clone(),
+        mock_update_payload(counterparty.clone(), &encoded_block, proof),
+    );
+    assert_eq!(update_res.code, 106);
+
+    // Bitmap claims a voter who didn't sign
+    let mut proof = mock_proof(&encoded_block, 1, &private_keys, &[0, 1]);
+    proof.bitmap = Bytes::from(vec![0b1110_0000]);
+    let update_res = service.update_client(
+        context.clone(),
+        mock_update_payload(counterparty.clone(), &encoded_block, proof),
+    );
+    assert_eq!(update_res.code, 106);
+
+    let proof = mock_proof(&encoded_block, 1, &private_keys, &[0, 1, 2]);
+    let update_res = service.update_client(
+        context.clone(),
+        mock_update_payload(counterparty.clone(), &encoded_block, proof.clone()),
+    );
+    assert!(!update_res.is_error());
+
+    let client = service
+        .get_client(context.clone(), GetClientPayload {
+            chain_id: counterparty.clone(),
+        })
+        .succeed_data;
+    assert_eq!(client.height, 1);
+    assert_eq!(client.exec_height, 0);
+    assert_eq!(client.state_root, state_root);
+
+    // Root is of exec height
+    let root_res = service.get_state_root(context.clone(), GetStateRootPayload {
+        chain_id: counterparty.clone(),
+        height:   0,
+    });
+    assert_eq!(root_res.succeed_data, state_root);
+
+    // Outdated block
+    let update_res = service.update_client(
+        context,
+        mock_update_payload(counterparty, &encoded_block, proof),
+    );
+    assert_eq!(update_res.code, 105);
+}
+
+#[test]
+fn test_receive_packet_proof() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let relayer = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, relayer.clone());
+
+    let local = Hash::digest(Bytes::from("local"));
+    let counterparty = Hash::digest(Bytes::from("counterparty"));
+    let packet = Packet {
+        source_chain: counterparty.clone(),
+        dest_chain:   local.clone(),
+        sequence:     1,
+        origin_chain: counterparty.clone(),
+        asset_id:     Hash::digest(Bytes::from("asset")),
+        sender:       relayer.clone(),
+        receiver:     relayer.clone(),
+        value:        100,
+    };
+
+    // Bridge state of counterparty, with commitment of the packet
+    let (state_root, proof) = mock_state_proof(
+        "bridge",
+        commitment_key(&local, 1).encode_fixed().unwrap(),
+        packet.commitment().unwrap().encode_fixed().unwrap(),
+    );
+
+    let mut client = mock_client(counterparty, &mock_private_keys());
+    client.height = 6;
+    client.exec_height = 5;
+    client.state_root = state_root;
+
+    let mut service = new_bridge_service();
+    service.init_genesis(InitGenesisPayload {
+        chain_id: local,
+        admin:    relayer,
+        clients:  vec![client],
+    });
+
+    // Root is not tracked at the height
+    let receive_res = service.receive_packet(context.clone(), ReceivePacketPayload {
+        packet:       packet.clone(),
+        proof_height: 4,
+        proof:        proof.clone(),
+    });
+    assert_eq!(receive_res.code, 111);
+
+    let mut forged = packet.clone();
+    forged.value = 1000;
+    let receive_res = service.receive_packet(context.clone(), ReceivePacketPayload {
+        packet:       forged,
+        proof_height: 5,
+        proof:        proof.clone(),
+    });
+    assert_eq!(receive_res.code, 107);
+
+    // Proof passes, but sequence 0 is expected
+    let receive_res = service.receive_packet(context, ReceivePacketPayload {
+        packet,
+        proof_height: 5,
+        proof,
+    });
+    assert_eq!(receive_res.code, 108);
+}
+
+#[test]
+fn test_create_client_without_admin() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let admin = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, admin);
+
+    let counterparty = Hash::digest(Bytes::from("counterparty"));
+    let client = mock_client(counterparty, &mock_private_keys());
+
+    // Genesis is not initialized
+    let mut service = new_bridge_service();
+    let create_res = service.create_client(context, CreateClientPayload { client });
+    assert_eq!(create_res.code, 102);
+}
+
+#[test]
+fn test_update_validators() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let relayer = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, relayer.clone());
+
+    let counterparty = Hash::digest(Bytes::from("counterparty"));
+    let private_keys = mock_private_keys();
+    let new_keys = private_keys[1..].to_vec();
+    let new_client = mock_client(counterparty.clone(), &new_keys);
+
+    // Metadata of counterparty with the new validators, under the old ones
+    let metadata = Metadata {
+        chain_id: counterparty.clone(),
+        verifier_list: new_client.validators.clone(),
+        ..Default::default()
+    };
+    let (state_root, state_proof) = mock_state_proof(
+        "metadata",
+        METADATA_KEY.to_owned().encode_fixed().unwrap(),
+        metadata.encode_fixed().unwrap(),
+    );
+
+    let mut client = mock_client(counterparty.clone(), &private_keys);
+    client.height = 6;
+    client.exec_height = 5;
+    client.state_root = state_root;
+
+    let mut service = new_bridge_service();
+    service.init_genesis(InitGenesisPayload {
+        chain_id: Hash::digest(Bytes::from("local")),
+        admin:    relayer,
+        clients:  vec![client.clone()],
+    });
+
+    let next_root = Hash::digest(Bytes::from("next_root"));
+    let encoded_block = mock_block(&new_client, 7, next_root.clone())
+        .encode_fixed()
+        .unwrap();
+    let proof = mock_proof(&encoded_block, 7, &new_keys, &[0, 1, 2]);
+
+    // Signed by validators the client doesn't track
+    let update_res = service.update_client(
+        context.clone(),
+        mock_update_payload(counterparty.clone(), &encoded_block, proof.clone()),
+    );
+    assert_eq!(update_res.code, 113);
+
+    // Metadata proved at a root not tracked
+    let mut payload = mock_update_payload(counterparty.clone(), &encoded_block, proof.clone());
+    payload.metadata_proof = Some(MetadataProof {
+        proof_height: 4,
+        proof:        state_proof.clone(),
+    });
+    let update_res = service.update_client(context.clone(), payload);
+    assert_eq!(update_res.code, 113);
+
+    // Old validators can't sign for the new ones
+    let old_proof = mock_proof(&encoded_block, 7, &private_keys, &[0, 1, 2]);
+    let mut payload = mock_update_payload(counterparty.clone(), &encoded_block, old_proof);
+    payload.metadata_proof = Some(MetadataProof {
+        proof_height: 5,
+        proof:        state_proof.clone(),
+    });
+    let update_res = service.update_client(context.clone(), payload);
+    assert_eq!(update_res.code, 106);
+
+    let mut payload = mock_update_payload(counterparty.clone(), &encoded_block, proof);
+    payload.metadata_proof = Some(MetadataProof {
+        proof_height: 5,
+        proof:        state_proof,
+    });
+    let update_res = service.update_client(context.clone(), payload);
+    assert!(!update_res.is_error());
+
+    let client = service
+        .get_client(context, GetClientPayload {
+            chain_id: counterparty,
+        })
+        .succeed_data;
+    assert_eq!(client.height, 7);
+    assert_eq!(client.validators, new_client.validators);
+    assert_eq!(client.state_root, next_root);
+}
+
+#[test]
+fn test_prune_state_roots() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let relayer = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, relayer.clone());
+
+    let counterparty = Hash::digest(Bytes::from("counterparty"));
+    let private_keys = mock_private_keys();
+    let client = mock_client(counterparty.clone(), &private_keys);
+
+    let mut service = new_bridge_service();
+    service.init_genesis(InitGenesisPayload {
+        chain_id: Hash::digest(Bytes::from("local")),
+        admin:    relayer,
+        clients:  vec![client.clone()],
+    });
+
+    let get_root = |service: &BridgeService<_>, height| {
+        service.get_state_root(context.clone(), GetStateRootPayload {
+            chain_id: counterparty.clone(),
+            height,
+        })
+    };
+    assert!(!get_root(&service, 0).is_error());
+
+    // Root of the exec height takes the slot of genesis one
+    let state_root = Hash::digest(Bytes::from("state_root"));
+    let encoded_block = mock_block(&client, TRACKED_ROOTS + 1, state_root.clone())
+        .encode_fixed()
+        .unwrap();
+    let proof = mock_proof(&encoded_block, TRACKED_ROOTS + 1, &private_keys, &[0, 1, 2]);
+    let update_res = service.update_client(
+        context.clone(),
+        mock_update_payload(counterparty.clone(), &encoded_block, proof),
+    );
+    assert!(!update_res.is_error());
+
+    assert_eq!(get_root(&service, 0).code, 111);
+    assert_eq!(get_root(&service, TRACKED_ROOTS).succeed_data, state_root);
+}
+
+fn new_bridge_service() -> BridgeService<
+    DefalutServiceSDK<
+        GeneralServiceState<MPTTrie<MemoryDB>>,
+        DefaultChainQuerier<MockStorage>,
+        NoopDispatcher,
+    >,
+> {
+    let chain_db = DefaultChainQuerier::new(Arc::new(MockStorage {}));
+    let trie = MPTTrie::new(Arc::new(MemoryDB::new(false)));
+    let state = GeneralServiceState::new(trie);
+
+    let sdk = DefalutServiceSDK::new(
+        Rc::new(RefCell::new(state)),
+        Rc::new(chain_db),
+        NoopDispatcher {},
+    );
+
+    BridgeService::new(sdk)
+}
+
+fn mock_private_keys() -> Vec<BlsPrivateKey> {
+    vec![
+        "000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970",
+        "00000000000000000000000000000000320b11d7c1ae66fdad1b4a75221244ae2d84903d3548c581d7d30dc135aac817",
+        "000000000000000000000000000000006a41e900d0426e615ca9d9393e6792baf9bda4398d5d407e59f77cb6c6f393cc",
+        "00000000000000000000000000000000125d81e0eb0a9c3746d868bf3b4f07760fdd430daded41d92f53b4e484ef3415",
+    ]
+    .into_iter()
+    .map(|key| BlsPrivateKey::try_from(hex::decode(key).unwrap().as_ref()).unwrap())
+    .collect()
+}
+
+fn common_ref() -> BlsCommonReference {
+    let common_ref = hex::decode(&COMMON_REF[2..]).unwrap();
+    std::str::from_utf8(&common_ref).unwrap().into()
+}
+
+// Addresses are in sorted order, same as index of private keys
+fn mock_client(chain_id: Hash, private_keys: &[BlsPrivateKey]) -> ClientState {
+    let validators = private_keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| ValidatorExtend {
+            bls_pub_key:    Hex::from_string(
+                "0x".to_owned() + &hex::encode(key.pub_key(&common_ref()).to_bytes()),
+            )
+            .unwrap(),
+            address:        Address::from_bytes(Bytes::from(vec![i as u8 + 1; 20])).unwrap(),
+            propose_weight: 1,
+            vote_weight:    1,
+        })
+        .collect();
+
+    ClientState {
+        chain_id,
+        common_ref: Hex::from_string(COMMON_REF.to_owned()).unwrap(),
+        validators,
+        height: 0,
+        exec_height: 0,
+        state_root: Hash::from_empty(),
+        network_tag: "muta-test".to_owned(),
+        domain_separation_height: 0,
+    }
+}
+
+// Signed by validators of the client
+fn mock_block(client: &ClientState, height: u64, state_root: MerkleRoot) -> Block {
+    let validators = client
+        .validators
+        .iter()
+        .map(|v| Validator {
+            address:        v.address.clone(),
+            propose_weight: v.propose_weight,
+            vote_weight:    v.vote_weight,
+        })
+        .collect();
+
+    Block {
+        header:            BlockHeader {
+            chain_id: client.chain_id.clone(),
+            height,
+            exec_height: height - 1,
+            pre_hash: Hash::from_empty(),
+            timestamp: 0,
+            logs_bloom: vec![],
+            order_root: Hash::from_empty(),
+            confirm_root: vec![],
+            state_root,
+            receipt_root: vec![],
+            cycles_used: vec![],
+            proposer: Address::default(),
+            proof: Proof {
+                height:     height - 1,
+                round:      0,
+                block_hash: Hash::from_empty(),
+                signature:  Bytes::new(),
+                bitmap:     Bytes::new(),
+            },
+            validator_version: 0,
+            validators,
+            extra_data: Bytes::new(),
+        },
+        ordered_tx_hashes: vec![],
+        vote_extensions:   vec![],
+    }
+}
+
+fn mock_proof(
+    encoded_block: &Bytes,
+    height: u64,
+    private_keys: &[BlsPrivateKey],
+    voters: &[usize],
+) -> Proof {
+    let block_hash = Hash::digest(encoded_block.clone());
+    let vote = Vote {
+        height,
+        round: 0,
+        vote_type: VoteType::Precommit,
+        block_hash: block_hash.as_bytes(),
+    };
+    let vote_hash = Hash::digest(Bytes::from(rlp::encode(&vote)));
+    let hash = HashValue::try_from(vote_hash.as_bytes().as_ref()).unwrap();
+
+    let mut bitmap = 0u8;
+    let sigs_pubkeys = voters
+        .iter()
+        .map(|i| {
+            bitmap |= 0x80 >> i;
+            let key = &private_keys[*i];
+            (key.sign_message(&hash), key.pub_key(&common_ref()))
+        })
+        .collect::<Vec<_>>();
+
+    Proof {
+        height,
+        round: 0,
+        block_hash,
+        signature: BlsSignature::combine(sigs_pubkeys).to_bytes(),
+        bitmap: Bytes::from(vec![bitmap]),
+    }
+}
+
+fn mock_update_payload(chain_id: Hash, encoded_block: &Bytes, proof: Proof) -> UpdateClientPayload {
+    UpdateClientPayload {
+        chain_id,
+        block: Hex::from_string("0x".to_owned() + &hex::encode(encoded_block)).unwrap(),
+        proof,
+        metadata_proof: None,
+    }
+}
+
+// State of counterparty with a value in the service, and the proof of it
+fn mock_state_proof(service_name: &str, key: Bytes, value: Bytes) -> (MerkleRoot, StateProof) {
+    let db = Arc::new(MemoryDB::new(false));
+    let mut service_trie = MPTTrie::new(Arc::clone(&db));
+    service_trie.insert(key.clone(), value).unwrap();
+    let service_root = service_trie.commit().unwrap();
+
+    let service_key = service_name.to_owned().encode_fixed().unwrap();
+    let mut root_trie = MPTTrie::new(Arc::clone(&db));
+    root_trie
+        .insert(service_key.clone(), service_root.encode_fixed().unwrap())
+        .unwrap();
+    let state_root = root_trie.commit().unwrap();
+
+    let proof = StateProof {
+        scheme: CommitmentScheme::MerklePatricia,
+        service_name: service_name.to_owned(),
+        key_proof: service_trie.get_proof(&key).unwrap(),
+        key,
+        service_root,
+        service_proof: root_trie.get_proof(&service_key).unwrap(),
+    };
+    (state_root, proof)
+}
+
+fn mock_context(cycles_limit: u64, caller: Address) -> ServiceContext {
+    let params = ServiceContextParams {
+        tx_hash: None,
+        nonce: None,
+        cycles_limit,
+        cycles_price: 1,
+        cycles_used: Rc::new(RefCell::new(0)),
+        caller,
+        height: 1,
+        timestamp: 0,
+        service_name: "bridge".to_owned(),
+        service_method: "service_method".to_owned(),
+        service_payload: "service_payload".to_owned(),
+        extra: None,
+        events: Rc::new(RefCell::new(vec![])),
+    };
+
+    ServiceContext::new(params)
+}
+
+struct MockStorage;
+
+#[async_trait]
+impl Storage for MockStorage {
+    async fn insert_transactions(&self, _: Vec<SignedTransaction>) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn insert_block(&self, _: Block) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn insert_receipts(&self, _: Vec<Receipt>) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn update_latest_proof(&self, _: Proof) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn get_transaction_by_hash(&self, _: Hash) -> ProtocolResult<SignedTransaction> {
+        unimplemented!()
+    }
+
+    async fn get_transactions(&self, _: Vec<Hash>) -> ProtocolResult<Vec<SignedTransaction>> {
+        unimplemented!()
+    }
+
+    async fn get_latest_block(&self) -> ProtocolResult<Block> {
+        unimplemented!()
+    }
+
+    async fn get_block_by_height(&self, _: u64) -> ProtocolResult<Block> {
+        unimplemented!()
+    }
+
+    async fn get_block_by_hash(&self, _: Hash) -> ProtocolResult<Block> {
+        unimplemented!()
+    }
+
+    async fn get_receipt(&self, _: Hash) -> ProtocolResult<Receipt> {
+        unimplemented!()
+    }
+
+    async fn get_receipts(&self, _: Vec<Hash>) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
+    async fn get_latest_proof(&self) -> ProtocolResult<Proof> {
+        unimplemented!()
+    }
+
+    async fn update_overlord_wal(&self, _info: Bytes) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn load_overlord_wal(&self) -> ProtocolResult<Bytes> {
+        unimplemented!()
+    }
+}
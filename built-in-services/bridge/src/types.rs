@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+
+use bytes::Bytes;
+
+use protocol::fixed_codec::{FixedCodec, FixedCodecError};
+use protocol::types::{
+    Address, Hash, Hex, MerkleRoot, Proof, SignatureDomain, StateProof, ValidatorExtend,
+};
+use protocol::ProtocolResult;
+
+/// Payload
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct InitGenesisPayload {
+    // Id of this chain, packets are sent from it
+    pub chain_id: Hash,
+    pub admin:    Address,
+    #[serde(default)]
+    pub clients:  Vec<ClientState>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CreateClientPayload {
+    pub client: ClientState,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct UpdateClientPayload {
+    pub chain_id:       Hash,
+    // Fixed encoded block of counterparty chain
+    pub block:          Hex,
+    // Proof of the block, it's in the header of next block
+    pub proof:          Proof,
+    // Validators of the block differ from the client, the new ones are
+    // proved by metadata of counterparty at a tracked state root
+    #[serde(default)]
+    pub metadata_proof: Option<MetadataProof>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct MetadataProof {
+    // Exec height of the tracked state root
+    pub proof_height: u64,
+    pub proof:        StateProof,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct UpdateClientEvent {
+    pub chain_id:    Hash,
+    pub height:      u64,
+    pub exec_height: u64,
+    pub state_root:  MerkleRoot,
+    // Validators are changed by the block
+    pub validators:  Option<Vec<ValidatorExtend>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GetClientPayload {
+    pub chain_id: Hash,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GetStateRootPayload {
+    pub chain_id: Hash,
+    // Exec height of counterparty
+    pub height:   u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TransferPayload {
+    pub dest_chain: Hash,
+    pub asset_id:   Hash,
+    pub receiver:   Address,
+    pub value:      u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SendPacketEvent {
+    pub packet: Packet,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ReceivePacketPayload {
+    pub packet:       Packet,
+    // Exec height of counterparty block tracked by client, commitment of the
+    // packet is proved against state root of this block
+    pub proof_height: u64,
+    pub proof:        StateProof,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ReceivePacketEvent {
+    pub packet:   Packet,
+    // Asset credited to receiver, a voucher if the asset is from
+    // counterparty chain
+    pub asset_id: Hash,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GetSequencePayload {
+    pub chain_id: Hash,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GetPacketPayload {
+    pub dest_chain: Hash,
+    pub sequence:   u64,
+}
+
+/// Light client of a counterparty chain. Blocks are verified by aggregated
+/// signatures of tracked validators, a block signed by changed validators is
+/// accepted once the new validator set is proved by counterparty metadata.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct ClientState {
+    pub chain_id:    Hash,
+    pub common_ref:  Hex,
+    pub validators:  Vec<ValidatorExtend>,
+    // Latest verified block
+    pub height:      u64,
+    // State root in header of the block is the one after its exec height
+    #[serde(default)]
+    pub exec_height: u64,
+    pub state_root:  MerkleRoot,
+
+    // Signature domain of counterparty, same as its metadata
+    #[serde(default)]
+    pub network_tag:              String,
+    #[serde(default)]
+    pub domain_separation_height: u64,
+}
+
+impl ClientState {
+    pub fn signature_domain(&self, height: u64) -> Option<SignatureDomain> {
+        if self.domain_separation_height == 0 || height < self.domain_separation_height {
+            return None;
+        }
+
+        Some(SignatureDomain {
+            network_tag: self.network_tag.clone(),
+            chain_id:    self.chain_id.clone(),
+        })
+    }
+}
+
+/// State root of counterparty kept by the client, roots are kept in a ring
+/// of slots so old ones are overwritten.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrackedRoot {
+    pub exec_height: u64,
+    pub state_root:  MerkleRoot,
+}
+
+/// Token transfer packet, sequence is ordered per destination chain.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct Packet {
+    pub source_chain: Hash,
+    pub dest_chain:   Hash,
+    pub sequence:     u64,
+    // Chain where the asset is issued, the asset is escrowed there and
+    // represented by vouchers on other chains
+    pub origin_chain: Hash,
+    pub asset_id:     Hash,
+    pub sender:       Address,
+    pub receiver:     Address,
+    pub value:        u64,
+}
+
+/// Asset of counterparty chain represented by a voucher on this chain
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct Voucher {
+    pub origin_chain: Hash,
+    pub asset_id:     Hash,
+}
+
+impl Packet {
+    // Counterparty proves packet by hash of it in state
+    pub fn commitment(&self) -> ProtocolResult<Hash> {
+        Ok(Hash::digest(self.encode_fixed()?))
+    }
+}
+
+impl rlp::Decodable for ClientState {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            chain_id:                 rlp.at(0)?.as_val()?,
+            common_ref:               rlp.at(1)?.as_val()?,
+            validators:               rlp.at(2)?.as_list()?,
+            height:                   rlp.at(3)?.as_val()?,
+            state_root:               rlp.at(4)?.as_val()?,
+            network_tag:              rlp.at(5)?.as_val()?,
+            domain_separation_height: rlp.at(6)?.as_val()?,
+            exec_height:              rlp.at(7)?.as_val()?,
+        })
+    }
+}
+
+impl rlp::Encodable for ClientState {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(8)
+            .append(&self.chain_id)
+            .append(&self.common_ref)
+            .append_list(&self.validators)
+            .append(&self.height)
+            .append(&self.state_root)
+            .append(&self.network_tag)
+            .append(&self.domain_separation_height)
+            .append(&self.exec_height);
+    }
+}
+
+impl FixedCodec for ClientState {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::from(rlp::encode(self)))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
+}
+
+impl rlp::Decodable for TrackedRoot {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            exec_height: rlp.at(0)?.as_val()?,
+            state_root:  rlp.at(1)?.as_val()?,
+        })
+    }
+}
+
+impl rlp::Encodable for TrackedRoot {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(2)
+            .append(&self.exec_height)
+            .append(&self.state_root);
+    }
+}
+
+impl FixedCodec for TrackedRoot {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::from(rlp::encode(self)))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
+}
+
+impl rlp::Decodable for Packet {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            source_chain: rlp.at(0)?.as_val()?,
+            dest_chain:   rlp.at(1)?.as_val()?,
+            sequence:     rlp.at(2)?.as_val()?,
+            origin_chain: rlp.at(3)?.as_val()?,
+            asset_id:     rlp.at(4)?.as_val()?,
+            sender:       rlp.at(5)?.as_val()?,
+            receiver:     rlp.at(6)?.as_val()?,
+            value:        rlp.at(7)?.as_val()?,
+        })
+    }
+}
+
+impl rlp::Encodable for Packet {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(8)
+            .append(&self.source_chain)
+            .append(&self.dest_chain)
+            .append(&self.sequence)
+            .append(&self.origin_chain)
+            .append(&self.asset_id)
+            .append(&self.sender)
+            .append(&self.receiver)
+            .append(&self.value);
+    }
+}
+
+impl FixedCodec for Packet {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::from(rlp::encode(self)))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
+}
+
+impl rlp::Decodable for Voucher {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            origin_chain: rlp.at(0)?.as_val()?,
+            asset_id:     rlp.at(1)?.as_val()?,
+        })
+    }
+}
+
+impl rlp::Encodable for Voucher {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(2)
+            .append(&self.origin_chain)
+            .append(&self.asset_id);
+    }
+}
+
+impl FixedCodec for Voucher {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::from(rlp::encode(self)))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
+}
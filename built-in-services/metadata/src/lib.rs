@@ -1,10 +1,45 @@
 #[cfg(test)]
 mod tests;
+pub mod types;
 
-use binding_macro::{cycles, genesis, service};
+use binding_macro::{access, cycles, genesis, hook_after, service};
 use protocol::traits::{ExecutorParams, ServiceResponse, ServiceSDK};
-use protocol::types::{Metadata, ServiceContext, METADATA_KEY};
+use protocol::types::{
+    is_upgrade_signal, service_admins_key, Address, Bytes, Hash, Metadata, ServiceContext,
+    METADATA_KEY,
+};
 
+use crate::types::{
+    EmergencyVote, EmergencyVoteEvent, GetServiceAdminsPayload, HaltChainPayload, MetadataGenesis,
+    ServiceAdmins, UpdateValidatorsPayload, ValidatorsVote, ValidatorsVoteEvent,
+};
+
+// Votes of validators to halt or resume the chain
+const EMERGENCY_VOTES_KEY: &str = "emergency_votes";
+// Heights of recent blocks signaling the upgrade in their extra data
+const UPGRADE_SIGNALS_KEY: &str = "upgrade_signals";
+// Votes of validators to replace the validator list
+const VALIDATORS_VOTES_KEY: &str = "validators_votes";
+
+/// Validators halt the chain in emergencies, such as critical bugs on a live
+/// chain. Once votes of more than 2/3 weight of them agree on a halt height,
+/// transactions other than the ones calling this service are neither
+/// packaged nor executed from the height on, until they vote to resume it.
+///
+/// Validators get ready for a hard fork by proposing blocks with extra data
+/// `ready:<upgrade_name>`. The fork activates from the next height once
+/// `upgrade_threshold` of the latest `upgrade_window` blocks signal it.
+///
+/// Features of the chain spec are scheduled by admins updating metadata.
+/// Activations already reached are never changed, and new ones activate
+/// after the block updating them.
+///
+/// Admins of services are kept in state of this service, methods with
+/// `#[access(admin)]` are called by them only. Admins of this service update
+/// metadata and admins of every service. Fields consensus depends on are
+/// never updated by admins, the validator list is replaced once votes of
+/// more than 2/3 weight of validators agree on it, and the chain id, common
+/// ref and signature domain are fixed at genesis.
 pub struct MetadataService<SDK> {
     sdk: SDK,
 }
@@ -16,8 +51,15 @@ impl<SDK: ServiceSDK> MetadataService<SDK> {
     }
 
     #[genesis]
-    fn init_genesis(&mut self, metadata: Metadata) {
-        self.sdk.set_value(METADATA_KEY.to_string(), metadata)
+    fn init_genesis(&mut self, genesis: MetadataGenesis) {
+        self.sdk
+            .set_value(METADATA_KEY.to_string(), genesis.metadata);
+        for service_admins in genesis.service_admins.into_iter() {
+            self.sdk.set_value(
+                service_admins_key(&service_admins.service),
+                service_admins.admins,
+            )
+        }
     }
 
     #[cycles(210_00)]
@@ -29,4 +71,259 @@ impl<SDK: ServiceSDK> MetadataService<SDK> {
             .expect("metadata should not be none");
         ServiceResponse::<Metadata>::from_succeed(metadata)
     }
+
+    #[cycles(210_00)]
+    #[read]
+    fn get_service_admins(
+        &self,
+        ctx: ServiceContext,
+        payload: GetServiceAdminsPayload,
+    ) -> ServiceResponse<Vec<Address>> {
+        let admins: Vec<Address> = self
+            .sdk
+            .get_value(&service_admins_key(&payload.service))
+            .unwrap_or_default();
+        ServiceResponse::<Vec<Address>>::from_succeed(admins)
+    }
+
+    #[cycles(210_00)]
+    #[access(admin)]
+    #[write]
+    fn set_service_admins(
+        &mut self,
+        ctx: ServiceContext,
+        payload: ServiceAdmins,
+    ) -> ServiceResponse<()> {
+        self.sdk
+            .set_value(service_admins_key(&payload.service), payload.admins);
+        ServiceResponse::<()>::from_succeed(())
+    }
+
+    // Halt height and validators are voted by validators, they're never
+    // updated by admins. Neither is upgrade height, signaling starts over for
+    // a new upgrade.
+    #[cycles(210_00)]
+    #[access(admin)]
+    #[write]
+    fn update_metadata(&mut self, ctx: ServiceContext, payload: Metadata) -> ServiceResponse<()> {
+        let current = self._metadata();
+        if let Err(e) = payload
+            .chain_spec
+            .verify_update(&current.chain_spec, ctx.get_current_height())
+        {
+            return ServiceResponse::<()>::from_error(106, e);
+        }
+
+        let upgrade_height = if payload.upgrade_name == current.upgrade_name {
+            current.upgrade_height
+        } else {
+            self.sdk
+                .set_value(UPGRADE_SIGNALS_KEY.to_owned(), Vec::<u64>::new());
+            0
+        };
+        let metadata = Metadata {
+            chain_id: current.chain_id,
+            common_ref: current.common_ref,
+            verifier_list: current.verifier_list,
+            network_tag: current.network_tag,
+            domain_separation_height: current.domain_separation_height,
+            halt_height: current.halt_height,
+            upgrade_height,
+            ..payload
+        };
+        self.sdk.set_value(METADATA_KEY.to_owned(), metadata);
+        ServiceResponse::<()>::from_succeed(())
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn update_validators(
+        &mut self,
+        ctx: ServiceContext,
+        payload: UpdateValidatorsPayload,
+    ) -> ServiceResponse<()> {
+        if payload.verifier_list.is_empty() {
+            return ServiceResponse::<()>::from_error(107, "validator list is empty".to_owned());
+        }
+
+        let mut metadata = self._metadata();
+        let voter = ctx.get_caller();
+        if !metadata.verifier_list.iter().any(|v| v.address == voter) {
+            return ServiceResponse::<()>::from_error(101, "caller is not a validator".to_owned());
+        }
+
+        let list_hash = Hash::digest(Bytes::from(rlp::encode_list(&payload.verifier_list)));
+        let mut votes: Vec<ValidatorsVote> = self
+            .sdk
+            .get_value(&VALIDATORS_VOTES_KEY.to_owned())
+            .unwrap_or_default();
+        votes.retain(|v| v.voter != voter);
+        votes.push(ValidatorsVote {
+            voter:     voter.clone(),
+            list_hash: list_hash.clone(),
+        });
+
+        let passed = is_supermajority(&metadata, |address| {
+            votes
+                .iter()
+                .any(|vote| &vote.voter == address && vote.list_hash == list_hash)
+        });
+        if passed {
+            metadata.verifier_list = payload.verifier_list;
+            self.sdk.set_value(METADATA_KEY.to_owned(), metadata);
+            votes.clear();
+        }
+        self.sdk.set_value(VALIDATORS_VOTES_KEY.to_owned(), votes);
+
+        let event = ValidatorsVoteEvent {
+            voter,
+            list_hash,
+            passed,
+        };
+        let event_res = serde_json::to_string(&event);
+
+        if let Err(e) = event_res {
+            return ServiceResponse::<()>::from_error(105, format!("{:?}", e));
+        };
+        let event_str = event_res.unwrap();
+        ctx.emit_event(event_str);
+
+        ServiceResponse::<()>::from_succeed(())
+    }
+
+    #[hook_after]
+    fn count_upgrade_signals(&mut self, params: &ExecutorParams) {
+        let mut metadata = self._metadata();
+        if metadata.upgrade_name.is_empty()
+            || metadata.upgrade_threshold == 0
+            || metadata.upgrade_height != 0
+        {
+            return;
+        }
+
+        let signals: Vec<u64> = self
+            .sdk
+            .get_value(&UPGRADE_SIGNALS_KEY.to_owned())
+            .unwrap_or_default();
+        let window = metadata.upgrade_window;
+        let mut recent = signals
+            .iter()
+            .cloned()
+            .filter(|height| height.saturating_add(window) > params.height)
+            .collect::<Vec<_>>();
+        if is_upgrade_signal(&params.extra_data, &metadata.upgrade_name) {
+            recent.push(params.height);
+        }
+
+        if recent.len() as u64 >= metadata.upgrade_threshold {
+            metadata.upgrade_height = params.height + 1;
+            metadata
+                .chain_spec
+                .schedule(&metadata.upgrade_name, metadata.upgrade_height);
+            self.sdk.set_value(METADATA_KEY.to_owned(), metadata);
+            recent.clear();
+        }
+        if recent != signals {
+            self.sdk.set_value(UPGRADE_SIGNALS_KEY.to_owned(), recent);
+        }
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn halt_chain(
+        &mut self,
+        ctx: ServiceContext,
+        payload: HaltChainPayload,
+    ) -> ServiceResponse<()> {
+        if payload.halt_height < ctx.get_current_height() {
+            return ServiceResponse::<()>::from_error(
+                102,
+                "halt height is before current height".to_owned(),
+            );
+        }
+        if self._metadata().halt_height != 0 {
+            return ServiceResponse::<()>::from_error(103, "chain is halted".to_owned());
+        }
+
+        self._vote_emergency(&ctx, payload.halt_height)
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn resume_chain(&mut self, ctx: ServiceContext) -> ServiceResponse<()> {
+        if self._metadata().halt_height == 0 {
+            return ServiceResponse::<()>::from_error(104, "chain is not halted".to_owned());
+        }
+
+        self._vote_emergency(&ctx, 0)
+    }
+
+    fn _metadata(&self) -> Metadata {
+        self.sdk
+            .get_value(&METADATA_KEY.to_owned())
+            .expect("metadata should not be none")
+    }
+
+    // Replace former vote of the caller, votes are cleared once they pass.
+    fn _vote_emergency(&mut self, ctx: &ServiceContext, halt_height: u64) -> ServiceResponse<()> {
+        let mut metadata = self._metadata();
+        let voter = ctx.get_caller();
+        if !metadata.verifier_list.iter().any(|v| v.address == voter) {
+            return ServiceResponse::<()>::from_error(101, "caller is not a validator".to_owned());
+        }
+
+        let mut votes: Vec<EmergencyVote> = self
+            .sdk
+            .get_value(&EMERGENCY_VOTES_KEY.to_owned())
+            .unwrap_or_default();
+        votes.retain(|v| v.voter != voter);
+        votes.push(EmergencyVote {
+            voter: voter.clone(),
+            halt_height,
+        });
+
+        let passed = is_supermajority(&metadata, |address| {
+            votes
+                .iter()
+                .any(|vote| &vote.voter == address && vote.halt_height == halt_height)
+        });
+        if passed {
+            metadata.halt_height = halt_height;
+            self.sdk.set_value(METADATA_KEY.to_owned(), metadata);
+            votes.clear();
+        }
+        self.sdk.set_value(EMERGENCY_VOTES_KEY.to_owned(), votes);
+
+        let event = EmergencyVoteEvent {
+            voter,
+            halt_height,
+            passed,
+        };
+        let event_res = serde_json::to_string(&event);
+
+        if let Err(e) = event_res {
+            return ServiceResponse::<()>::from_error(105, format!("{:?}", e));
+        };
+        let event_str = event_res.unwrap();
+        ctx.emit_event(event_str);
+
+        ServiceResponse::<()>::from_succeed(())
+    }
+}
+
+// Whether validators voted have more than 2/3 of the vote weight
+fn is_supermajority<F: Fn(&Address) -> bool>(metadata: &Metadata, voted: F) -> bool {
+    let total_weight: u64 = metadata
+        .verifier_list
+        .iter()
+        .map(|v| u64::from(v.vote_weight))
+        .sum();
+    let voted_weight: u64 = metadata
+        .verifier_list
+        .iter()
+        .filter(|v| voted(&v.address))
+        .map(|v| u64::from(v.vote_weight))
+        .sum();
+
+    voted_weight * 3 > total_weight * 2
 }
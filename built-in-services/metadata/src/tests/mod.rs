@@ -7,13 +7,17 @@ use cita_trie::MemoryDB;
 
 use framework::binding::sdk::{DefalutServiceSDK, DefaultChainQuerier};
 use framework::binding::state::{GeneralServiceState, MPTTrie};
-use protocol::traits::{NoopDispatcher, ServiceSDK, Storage};
+use protocol::traits::{ExecutorParams, NoopDispatcher, ServiceSDK, StateCommitment, Storage};
 use protocol::types::{
-    Address, Block, Hash, Hex, Metadata, Proof, Receipt, ServiceContext, ServiceContextParams,
-    SignedTransaction, ValidatorExtend, METADATA_KEY,
+    upgrade_signal, Address, Block, ChainSpec, FeatureActivation, Hash, Hex, MerkleRoot, Metadata,
+    Proof, Receipt, ServiceContext, ServiceContextParams, SignedTransaction, ValidatorExtend,
+    METADATA_KEY, MIN_ACTIVATION_LEAD,
 };
 use protocol::{types::Bytes, ProtocolResult};
 
+use crate::types::{
+    GetServiceAdminsPayload, HaltChainPayload, ServiceAdmins, UpdateValidatorsPayload,
+};
 use crate::MetadataService;
 
 #[test]
@@ -30,11 +34,305 @@ fn test_get_metadata() {
     assert_eq!(metadata, init_metadata);
 }
 
+#[test]
+fn test_emergency_halt() {
+    let validators = (1..=4u8)
+        .map(|n| Address::from_bytes(Bytes::from(vec![n; 20])).unwrap())
+        .collect::<Vec<_>>();
+    let mut init_metadata = mock_metadata();
+    init_metadata.verifier_list = validators
+        .iter()
+        .map(|address| ValidatorExtend {
+            address: address.clone(),
+            ..init_metadata.verifier_list[0].clone()
+        })
+        .collect();
+
+    let mut service = new_metadata_service_with_metadata(init_metadata);
+    let halt_height = |service: &MetadataService<_>| {
+        service
+            .get_metadata(mock_context(u64::max_value(), validators[0].clone()))
+            .succeed_data
+            .halt_height
+    };
+    let halt = |service: &mut MetadataService<_>, voter: &Address, height: u64| {
+        service.halt_chain(
+            mock_context(u64::max_value(), voter.clone()),
+            HaltChainPayload {
+                halt_height: height,
+            },
+        )
+    };
+
+    let outsider = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    assert_eq!(halt(&mut service, &outsider, 10).code, 101);
+    assert_eq!(halt(&mut service, &validators[0], 0).code, 102);
+
+    // Votes for another height don't count
+    assert!(!halt(&mut service, &validators[0], 10).is_error());
+    assert!(!halt(&mut service, &validators[1], 11).is_error());
+    assert!(!halt(&mut service, &validators[2], 10).is_error());
+    assert_eq!(halt_height(&service), 0);
+
+    // Vote of a validator is replaced by its latest one
+    assert!(!halt(&mut service, &validators[1], 10).is_error());
+    assert_eq!(halt_height(&service), 10);
+    assert_eq!(halt(&mut service, &validators[3], 10).code, 103);
+
+    for (i, voter) in validators.iter().take(3).enumerate() {
+        let resp = service.resume_chain(mock_context(u64::max_value(), voter.clone()));
+        assert!(!resp.is_error());
+        assert_eq!(halt_height(&service), if i < 2 { 10 } else { 0 });
+    }
+    let resp = service.resume_chain(mock_context(u64::max_value(), validators[3].clone()));
+    assert_eq!(resp.code, 104);
+}
+
+#[test]
+fn test_update_validators() {
+    let validators = (1..=4u8)
+        .map(|n| Address::from_bytes(Bytes::from(vec![n; 20])).unwrap())
+        .collect::<Vec<_>>();
+    let validator_of = |address: &Address| ValidatorExtend {
+        address: address.clone(),
+        ..mock_metadata().verifier_list[0].clone()
+    };
+    let mut init_metadata = mock_metadata();
+    init_metadata.verifier_list = validators.iter().map(validator_of).collect();
+
+    let mut service = new_metadata_service_with_metadata(init_metadata.clone());
+    let verifier_list = |service: &MetadataService<_>| {
+        service
+            .get_metadata(mock_context(u64::max_value(), validators[0].clone()))
+            .succeed_data
+            .verifier_list
+    };
+    let update = |service: &mut MetadataService<_>, voter: &Address, list: &[Address]| {
+        service.update_validators(
+            mock_context(u64::max_value(), voter.clone()),
+            UpdateValidatorsPayload {
+                verifier_list: list.iter().map(validator_of).collect(),
+            },
+        )
+    };
+
+    let outsider = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    assert_eq!(update(&mut service, &outsider, &validators[..1]).code, 101);
+    assert_eq!(update(&mut service, &validators[0], &[]).code, 107);
+
+    // Admins never update validators
+    let resp = service.update_metadata(mock_context(u64::max_value(), outsider), Metadata {
+        chain_id: Hash::digest(Bytes::from("other")),
+        verifier_list: vec![],
+        ..init_metadata.clone()
+    });
+    assert!(!resp.is_error());
+    assert_eq!(verifier_list(&service), init_metadata.verifier_list);
+    assert_eq!(
+        service
+            .get_metadata(mock_context(u64::max_value(), validators[0].clone()))
+            .succeed_data
+            .chain_id,
+        init_metadata.chain_id
+    );
+
+    // Votes for another list don't count
+    assert!(!update(&mut service, &validators[0], &validators[..3]).is_error());
+    assert!(!update(&mut service, &validators[1], &validators[..2]).is_error());
+    assert!(!update(&mut service, &validators[2], &validators[..3]).is_error());
+    assert_eq!(verifier_list(&service), init_metadata.verifier_list);
+
+    assert!(!update(&mut service, &validators[1], &validators[..3]).is_error());
+    assert_eq!(
+        verifier_list(&service),
+        validators[..3].iter().map(validator_of).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_upgrade_signaling() {
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let mut init_metadata = mock_metadata();
+    init_metadata.upgrade_name = "fork-1".to_owned();
+    init_metadata.upgrade_window = 4;
+    init_metadata.upgrade_threshold = 3;
+
+    let mut service = new_metadata_service_with_metadata(init_metadata.clone());
+    let upgrade_height = |service: &MetadataService<_>| {
+        service
+            .get_metadata(mock_context(u64::max_value(), caller.clone()))
+            .succeed_data
+            .upgrade_height
+    };
+    let commit = |service: &mut MetadataService<_>, height: u64, extra_data: Bytes| {
+        service.count_upgrade_signals(&ExecutorParams {
+            state_root: MerkleRoot::from_empty(),
+            height,
+            timestamp: 0,
+            cycles_limit: u64::max_value(),
+            proposer: caller.clone(),
+            vote_extensions: vec![],
+            extra_data,
+        })
+    };
+
+    // Signals of another upgrade don't count, neither do ones out of window
+    commit(&mut service, 1, upgrade_signal("fork-1"));
+    commit(&mut service, 2, upgrade_signal("fork-2"));
+    commit(&mut service, 3, Bytes::new());
+    commit(&mut service, 4, upgrade_signal("fork-1"));
+    commit(&mut service, 5, upgrade_signal("fork-1"));
+    assert_eq!(upgrade_height(&service), 0);
+
+    commit(&mut service, 6, upgrade_signal("fork-1"));
+    assert_eq!(upgrade_height(&service), 7);
+
+    // The fork activates from the height by the chain spec
+    let upgraded = service
+        .get_metadata(mock_context(u64::max_value(), caller.clone()))
+        .succeed_data;
+    assert_eq!(upgraded.chain_spec.activations, vec![FeatureActivation {
+        name:   "fork-1".to_owned(),
+        height: 7,
+    }]);
+
+    // Upgrade height is kept until admins start another upgrade
+    let resp = service.update_metadata(mock_context(u64::max_value(), caller.clone()), Metadata {
+        upgrade_height: 0,
+        ..upgraded.clone()
+    });
+    assert!(!resp.is_error());
+    assert_eq!(upgrade_height(&service), 7);
+
+    let resp = service.update_metadata(mock_context(u64::max_value(), caller.clone()), Metadata {
+        upgrade_name: "fork-2".to_owned(),
+        upgrade_height: 7,
+        ..upgraded
+    });
+    assert!(!resp.is_error());
+    assert_eq!(upgrade_height(&service), 0);
+}
+
+#[test]
+fn test_schedule_features() {
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let activation = |name: &str, height: u64| FeatureActivation {
+        name: name.to_owned(),
+        height,
+    };
+    let mut init_metadata = mock_metadata();
+    init_metadata.chain_spec = ChainSpec {
+        activations: vec![
+            activation("signature_domain", 1),
+            activation("fork", 2 + MIN_ACTIVATION_LEAD),
+        ],
+    };
+    let lead = 1 + MIN_ACTIVATION_LEAD;
+
+    let mut service = new_metadata_service_with_metadata(init_metadata.clone());
+    let mut update = |activations: Vec<FeatureActivation>| {
+        service.update_metadata(mock_context(u64::max_value(), caller.clone()), Metadata {
+            chain_spec: ChainSpec { activations },
+            ..init_metadata.clone()
+        })
+    };
+
+    // Activated at the current height
+    assert!(update(vec![]).is_error());
+    assert!(update(vec![activation("signature_domain", 2)]).is_error());
+    assert!(update(vec![
+        activation("signature_domain", 1),
+        activation("fork", 1)
+    ])
+    .is_error());
+
+    // Names unknown to the node are kept, but never added
+    assert!(!update(vec![
+        activation("signature_domain", 1),
+        activation("fork", 2 + MIN_ACTIVATION_LEAD)
+    ])
+    .is_error());
+    assert!(!update(vec![
+        activation("signature_domain", 1),
+        activation("fork", lead)
+    ])
+    .is_error());
+    assert!(update(vec![
+        activation("signature_domain", 1),
+        activation("frok", lead)
+    ])
+    .is_error());
+
+    // Scheduled after it
+    assert!(update(vec![
+        activation("signature_domain", 1),
+        activation("tx_cycles", lead),
+        activation("tx_cycles", lead + 1)
+    ])
+    .is_error());
+    assert!(!update(vec![
+        activation("signature_domain", 1),
+        activation("tx_cycles", lead)
+    ])
+    .is_error());
+    assert!(!update(vec![activation("signature_domain", 1)]).is_error());
+
+    // Validators get the lead to upgrade
+    assert!(update(vec![
+        activation("signature_domain", 1),
+        activation("tx_cycles", lead - 1)
+    ])
+    .is_error());
+}
+
+#[test]
+fn test_service_admins() {
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let mut init_metadata = mock_metadata();
+    init_metadata.halt_height = 10;
+
+    let mut service = new_metadata_service_with_metadata(init_metadata.clone());
+    let get_admins = |service: &MetadataService<_>| {
+        service
+            .get_service_admins(
+                mock_context(u64::max_value(), caller.clone()),
+                GetServiceAdminsPayload {
+                    service: "asset".to_owned(),
+                },
+            )
+            .succeed_data
+    };
+    assert_eq!(get_admins(&service), vec![]);
+
+    let resp = service.set_service_admins(
+        mock_context(u64::max_value(), caller.clone()),
+        ServiceAdmins {
+            service: "asset".to_owned(),
+            admins:  vec![caller.clone()],
+        },
+    );
+    assert!(!resp.is_error());
+    assert_eq!(get_admins(&service), vec![caller.clone()]);
+
+    // Halt height is kept
+    let resp = service.update_metadata(mock_context(u64::max_value(), caller.clone()), Metadata {
+        cycles_limit: 1,
+        halt_height: 0,
+        ..init_metadata
+    });
+    assert!(!resp.is_error());
+    let metadata = service
+        .get_metadata(mock_context(u64::max_value(), caller))
+        .succeed_data;
+    assert_eq!(metadata.cycles_limit, 1);
+    assert_eq!(metadata.halt_height, 10);
+}
+
 fn new_metadata_service_with_metadata(
     metadata: Metadata,
 ) -> MetadataService<
     DefalutServiceSDK<
-        GeneralServiceState<MemoryDB>,
+        GeneralServiceState<MPTTrie<MemoryDB>>,
         DefaultChainQuerier<MockStorage>,
         NoopDispatcher,
     >,
@@ -75,6 +373,22 @@ fn mock_metadata() -> Metadata {
         brake_ratio:     7,
         tx_num_limit: 20000,
         max_tx_size: 1_073_741_824,
+        proposer_fee_ratio: 8,
+        treasury_fee_ratio: 2,
+        treasury: Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap(),
+        network_tag: "muta-test".to_owned(),
+        domain_separation_height: 0,
+        max_block_size: 0,
+        tx_base_cycles: 0,
+        storage_deposit_per_byte: 0,
+        rent_epoch: 0,
+        rent_per_byte: 0,
+        halt_height: 0,
+        upgrade_name: String::new(),
+        upgrade_window: 0,
+        upgrade_threshold: 0,
+        upgrade_height: 0,
+        chain_spec: ChainSpec::default(),
     }
 }
 
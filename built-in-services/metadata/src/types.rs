@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use bytes::Bytes;
+
+use protocol::fixed_codec::{FixedCodec, FixedCodecError};
+use protocol::types::{Address, Hash, Metadata, ValidatorExtend};
+use protocol::ProtocolResult;
+
+/// Genesis
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct MetadataGenesis {
+    #[serde(flatten)]
+    pub metadata:       Metadata,
+    // Admins of services at genesis, admins of this service change them later
+    #[serde(default)]
+    pub service_admins: Vec<ServiceAdmins>,
+}
+
+/// Payload
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct HaltChainPayload {
+    pub halt_height: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct UpdateValidatorsPayload {
+    pub verifier_list: Vec<ValidatorExtend>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ServiceAdmins {
+    pub service: String,
+    pub admins:  Vec<Address>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GetServiceAdminsPayload {
+    pub service: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct EmergencyVoteEvent {
+    pub voter:       Address,
+    // Zero if the vote is for resuming the chain
+    pub halt_height: u64,
+    // Whether votes of the same target reached supermajority
+    pub passed:      bool,
+}
+
+// Vote of a validator to halt the chain at the height, zero to resume it.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct EmergencyVote {
+    pub voter:       Address,
+    pub halt_height: u64,
+}
+
+impl rlp::Decodable for EmergencyVote {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            voter:       rlp.at(0)?.as_val()?,
+            halt_height: rlp.at(1)?.as_val()?,
+        })
+    }
+}
+
+impl rlp::Encodable for EmergencyVote {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(2)
+            .append(&self.voter)
+            .append(&self.halt_height);
+    }
+}
+
+impl FixedCodec for EmergencyVote {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::from(rlp::encode(self)))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ValidatorsVoteEvent {
+    pub voter:     Address,
+    // Hash of rlp encoded validator list voted for
+    pub list_hash: Hash,
+    // Whether votes of the same list reached supermajority
+    pub passed:    bool,
+}
+
+// Vote of a validator to replace the validator list by the one of the hash.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ValidatorsVote {
+    pub voter:     Address,
+    pub list_hash: Hash,
+}
+
+impl rlp::Decodable for ValidatorsVote {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            voter:     rlp.at(0)?.as_val()?,
+            list_hash: rlp.at(1)?.as_val()?,
+        })
+    }
+}
+
+impl rlp::Encodable for ValidatorsVote {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(2).append(&self.voter).append(&self.list_hash);
+    }
+}
+
+impl FixedCodec for ValidatorsVote {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::from(rlp::encode(self)))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
+}
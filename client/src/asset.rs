@@ -0,0 +1,107 @@
+use asset::types::{
+    ApprovePayload, Asset, BurnPayload, CreateAssetPayload, GetAllowancePayload,
+    GetAllowanceResponse, GetAssetPayload, GetBalancePayload, GetBalanceResponse,
+    GetClaimableFeePayload, GetClaimableFeeResponse, MintPayload, TransferFromPayload,
+    TransferPayload,
+};
+use protocol::types::{Address, Hash};
+
+use crate::client::Client;
+use crate::error::ClientError;
+
+const SERVICE: &str = "asset";
+
+/// Calls of the asset service, writes wait for their receipts.
+pub struct AssetClient<'a> {
+    client: &'a Client,
+}
+
+impl Client {
+    pub fn asset(&self) -> AssetClient<'_> {
+        AssetClient { client: self }
+    }
+}
+
+impl<'a> AssetClient<'a> {
+    pub fn get_asset(&self, id: Hash) -> Result<Asset, ClientError> {
+        self.client
+            .query_service(SERVICE, "get_asset", &GetAssetPayload { id })
+    }
+
+    pub fn get_balance(&self, asset_id: Hash, user: Address) -> Result<u64, ClientError> {
+        let resp: GetBalanceResponse =
+            self.client
+                .query_service(SERVICE, "get_balance", &GetBalancePayload {
+                    asset_id,
+                    user,
+                })?;
+        Ok(resp.balance)
+    }
+
+    pub fn get_allowance(
+        &self,
+        asset_id: Hash,
+        grantor: Address,
+        grantee: Address,
+    ) -> Result<u64, ClientError> {
+        let resp: GetAllowanceResponse =
+            self.client
+                .query_service(SERVICE, "get_allowance", &GetAllowancePayload {
+                    asset_id,
+                    grantor,
+                    grantee,
+                })?;
+        Ok(resp.value)
+    }
+
+    pub fn get_claimable_fee(&self, user: Address) -> Result<u64, ClientError> {
+        let resp: GetClaimableFeeResponse =
+            self.client
+                .query_service(SERVICE, "get_claimable_fee", &GetClaimableFeePayload {
+                    user,
+                })?;
+        Ok(resp.value)
+    }
+
+    pub fn create_asset(&self, payload: CreateAssetPayload) -> Result<Asset, ClientError> {
+        self.client.call(SERVICE, "create_asset", &payload)
+    }
+
+    pub fn transfer(&self, asset_id: Hash, to: Address, value: u64) -> Result<(), ClientError> {
+        self.client.call(SERVICE, "transfer", &TransferPayload {
+            asset_id,
+            to,
+            value,
+        })
+    }
+
+    pub fn approve(&self, asset_id: Hash, to: Address, value: u64) -> Result<(), ClientError> {
+        self.client.call(SERVICE, "approve", &ApprovePayload {
+            asset_id,
+            to,
+            value,
+        })
+    }
+
+    pub fn transfer_from(&self, payload: TransferFromPayload) -> Result<(), ClientError> {
+        self.client.call(SERVICE, "transfer_from", &payload)
+    }
+
+    pub fn mint(&self, asset_id: Hash, to: Address, value: u64) -> Result<(), ClientError> {
+        self.client.call(SERVICE, "mint", &MintPayload {
+            asset_id,
+            to,
+            value,
+        })
+    }
+
+    pub fn burn(&self, asset_id: Hash, value: u64) -> Result<(), ClientError> {
+        self.client
+            .call(SERVICE, "burn", &BurnPayload { asset_id, value })
+    }
+
+    // Fee is paid in the native asset
+    pub fn claim_fee(&self) -> Result<u64, ClientError> {
+        self.client.call(SERVICE, "claim_fee", &())
+    }
+}
@@ -0,0 +1,925 @@
+use std::convert::TryFrom;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use common_crypto::{
+    Crypto, HashValue, PrivateKey, PublicKey, Secp256k1, Secp256k1PrivateKey, Signature,
+    ToPublicKey,
+};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::ServiceResponse;
+use protocol::types::{
+    signing_hash, Address, Block, Event, Hash, Metadata, Proof, QueryAttestation, RawTransaction,
+    Receipt, ReceiptResponse, TransactionRequest, TRANSACTION_SIGNATURE_PURPOSE,
+};
+use protocol::Bytes;
+
+use crate::error::ClientError;
+use crate::transport::{HttpTransport, Transport};
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub cycles_limit:    u64,
+    pub cycles_price:    u64,
+    // Transactions expire after these blocks, at most the timeout gap of
+    // the chain
+    pub tx_timeout:      u64,
+    pub poll_interval:   Duration,
+    pub receipt_timeout: Duration,
+    // Requests are resent these times while the node is unavailable
+    pub retries:         u32,
+    pub retry_interval:  Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            cycles_limit:    1_000_000,
+            cycles_price:    1,
+            tx_timeout:      10,
+            poll_interval:   Duration::from_millis(500),
+            receipt_timeout: Duration::from_secs(30),
+            retries:         3,
+            retry_interval:  Duration::from_secs(1),
+        }
+    }
+}
+
+struct Signer {
+    private_key: Secp256k1PrivateKey,
+    address:     Address,
+}
+
+/// Talks to a node through its GraphQL api. Transactions are signed by the
+/// private key of the client, reads are sent by its address if it has one.
+pub struct Client {
+    url:       String,
+    transport: Box<dyn Transport>,
+    signer:    Option<Signer>,
+    config:    ClientConfig,
+}
+
+impl Client {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            transport: Box::new(HttpTransport::new()),
+            signer: None,
+            config: ClientConfig::default(),
+        }
+    }
+
+    pub fn private_key(mut self, private_key: &[u8]) -> Result<Self, ClientError> {
+        let private_key = Secp256k1PrivateKey::try_from(private_key)
+            .map_err(|e| ClientError::PrivateKey(format!("{:?}", e)))?;
+        let address = Address::from_pubkey_bytes(private_key.pub_key().to_bytes())?;
+
+        self.signer = Some(Signer {
+            private_key,
+            address,
+        });
+        Ok(self)
+    }
+
+    pub fn config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Send requests by the transport instead of http.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Box::new(transport);
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn address(&self) -> Option<&Address> {
+        self.signer.as_ref().map(|signer| &signer.address)
+    }
+
+    pub fn get_block(&self, height: Option<u64>) -> Result<Block, ClientError> {
+        let data = self.request(
+            "query($height: Uint64) { getBlock(height: $height) { encoded } }",
+            json!({ "height": height.map(to_uint64) }),
+        )?;
+
+        let encoded = from_hex(&data["getBlock"]["encoded"])?;
+        Ok(Block::decode_fixed(encoded)?)
+    }
+
+    /// Follow blocks of the chain from the height. The GraphQL api pushes no
+    /// blocks, each one is polled every poll interval until it's committed.
+    pub fn subscribe_blocks(&self, from_height: u64) -> BlockSubscription<'_> {
+        BlockSubscription {
+            client: self,
+            height: from_height,
+        }
+    }
+
+    pub fn get_metadata(&self) -> Result<Metadata, ClientError> {
+        self.query_service("metadata", "get_metadata", &())
+    }
+
+    pub fn get_receipt(&self, tx_hash: &Hash) -> Result<Receipt, ClientError> {
+        let data = self.request(
+            "query($txHash: Hash!) { getReceipt(txHash: $txHash) { \
+             stateRoot height txHash cyclesUsed events { service data } \
+             response { serviceName method response { code succeedData errorMessage } } } }",
+            json!({ "txHash": tx_hash.as_hex() }),
+        )?;
+
+        let receipt = &data["getReceipt"];
+        let events = receipt["events"]
+            .as_array()
+            .ok_or_else(|| ClientError::Response(receipt.to_string()))?
+            .iter()
+            .map(|event| Event {
+                service: to_string(&event["service"]),
+                data:    to_string(&event["data"]),
+            })
+            .collect();
+        let response = &receipt["response"];
+
+        Ok(Receipt {
+            state_root: from_hash(&receipt["stateRoot"])?,
+            height: from_uint64(&receipt["height"])?,
+            tx_hash: from_hash(&receipt["txHash"])?,
+            cycles_used: from_uint64(&receipt["cyclesUsed"])?,
+            events,
+            response: ReceiptResponse {
+                service_name: to_string(&response["serviceName"]),
+                method:       to_string(&response["method"]),
+                response:     from_service_response(&response["response"])?,
+            },
+        })
+    }
+
+    /// Poll the receipt of the transaction until it's executed, or the
+    /// receipt timeout of config passes.
+    pub fn wait_receipt(&self, tx_hash: &Hash) -> Result<Receipt, ClientError> {
+        let deadline = Instant::now() + self.config.receipt_timeout;
+
+        loop {
+            match self.get_receipt(tx_hash) {
+                Ok(receipt) => return Ok(receipt),
+                Err(ClientError::GraphQL(ref errors)) if is_receipt_pending(errors) => (),
+                Err(e) => return Err(e),
+            }
+
+            if Instant::now() + self.config.poll_interval > deadline {
+                return Err(ClientError::ReceiptTimeout(tx_hash.clone()));
+            }
+            thread::sleep(self.config.poll_interval);
+        }
+    }
+
+    pub fn query_service<P: Serialize, T: DeserializeOwned>(
+        &self,
+        service: &str,
+        method: &str,
+        payload: &P,
+    ) -> Result<T, ClientError> {
+        let data = self.request(
+            "query($service: String!, $method: String!, $payload: String!, $caller: Address!) { \
+             queryService(serviceName: $service, method: $method, payload: $payload, caller: $caller) \
+             { code succeedData errorMessage } }",
+            json!({
+                "service": service,
+                "method": method,
+                "payload": serde_json::to_string(payload)?,
+                "caller": self.address().cloned().unwrap_or_default().as_hex(),
+            }),
+        )?;
+
+        decode_response(from_service_response(&data["queryService"])?)
+    }
+
+    /// Query the service by `signedQueryService` of a node signing queries,
+    /// and verify the response with validators of the trusted metadata
+    /// instead of trusting the node: the response is signed by the node for
+    /// this query, at the state root of a block committed by the validators.
+    /// Returns the response and the public key of the node accountable for
+    /// it.
+    pub fn verified_query_service<P: Serialize, T: DeserializeOwned>(
+        &self,
+        service: &str,
+        method: &str,
+        payload: &P,
+        trusted: &Metadata,
+    ) -> Result<(T, Bytes), ClientError> {
+        let caller = self.address().cloned().unwrap_or_default();
+        let request = TransactionRequest {
+            service_name: service.to_owned(),
+            method:       method.to_owned(),
+            payload:      serde_json::to_string(payload)?,
+        };
+        let data = self.request(
+            "query($service: String!, $method: String!, $payload: String!, $caller: Address!) { \
+             signedQueryService(serviceName: $service, method: $method, payload: $payload, caller: $caller) \
+             { response { code succeedData errorMessage } \
+             attestation { height stateRoot resultHash pubkey signature block proof } } }",
+            json!({
+                "service": service,
+                "method": method,
+                "payload": request.payload,
+                "caller": caller.as_hex(),
+            }),
+        )?;
+
+        let signed = &data["signedQueryService"];
+        let resp = from_service_response(&signed["response"])?;
+        let pubkey = verify_attestation(&signed["attestation"], &caller, &request, &resp, trusted)?;
+
+        Ok((decode_response(resp)?, pubkey))
+    }
+
+    /// Sign and send a transaction expiring after the tx timeout of config.
+    pub fn send_transaction<P: Serialize>(
+        &self,
+        service: &str,
+        method: &str,
+        payload: &P,
+    ) -> Result<Hash, ClientError> {
+        let signer = self.signer.as_ref().ok_or(ClientError::NoSigner)?;
+        let block = self.get_block(None)?;
+
+        let mut nonce = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let nonce = Hash::digest(Bytes::from(nonce.to_vec()));
+        let timeout = block.header.height + self.config.tx_timeout;
+
+        let raw = RawTransaction {
+            chain_id: block.header.chain_id.clone(),
+            nonce: nonce.clone(),
+            timeout,
+            cycles_price: self.config.cycles_price,
+            cycles_limit: self.config.cycles_limit,
+            request: TransactionRequest {
+                service_name: service.to_owned(),
+                method:       method.to_owned(),
+                payload:      serde_json::to_string(payload)?,
+            },
+            sender: None,
+            valid_until_timestamp: None,
+        };
+        let tx_hash = Hash::digest(raw.encode_fixed()?);
+
+        let metadata = self.get_metadata()?;
+        let domain = metadata.signature_domain(block.header.height + 1);
+        let signing = signing_hash(domain.as_ref(), TRANSACTION_SIGNATURE_PURPOSE, &tx_hash);
+        let hash_value = HashValue::try_from(signing.as_bytes().as_ref())
+            .map_err(|e| ClientError::PrivateKey(format!("{:?}", e)))?;
+        let signature = signer.private_key.sign_message(&hash_value);
+
+        self.request(
+            "mutation($raw: InputRawTransaction!, $encryption: InputTransactionEncryption!) { \
+             sendTransaction(inputRaw: $raw, inputEncryption: $encryption) }",
+            json!({
+                "raw": {
+                    "chainId": raw.chain_id.as_hex(),
+                    "cyclesLimit": to_uint64(raw.cycles_limit),
+                    "cyclesPrice": to_uint64(raw.cycles_price),
+                    "nonce": nonce.as_hex(),
+                    "timeout": to_uint64(timeout),
+                    "serviceName": service,
+                    "method": method,
+                    "payload": raw.request.payload,
+                },
+                "encryption": {
+                    "txHash": tx_hash.as_hex(),
+                    "pubkey": "0x".to_owned() + &hex::encode(signer.private_key.pub_key().to_bytes()),
+                    "signature": "0x".to_owned() + &hex::encode(signature.to_bytes()),
+                },
+            }),
+        )?;
+
+        Ok(tx_hash)
+    }
+
+    /// Send a transaction and decode the response of its receipt, failed
+    /// responses are service errors.
+    pub fn call<P: Serialize, T: DeserializeOwned>(
+        &self,
+        service: &str,
+        method: &str,
+        payload: &P,
+    ) -> Result<T, ClientError> {
+        let tx_hash = self.send_transaction(service, method, payload)?;
+        let receipt = self.wait_receipt(&tx_hash)?;
+
+        decode_response(receipt.response.response)
+    }
+
+    fn request(&self, query: &str, variables: Value) -> Result<Value, ClientError> {
+        let body = json!({ "query": query, "variables": variables });
+        let mut retries = self.config.retries;
+        let resp = loop {
+            match self.transport.post(&self.url, &body) {
+                Err(ClientError::Unavailable(_)) if retries > 0 => {
+                    retries -= 1;
+                    thread::sleep(self.config.retry_interval);
+                }
+                resp => break resp?,
+            }
+        };
+
+        if let Some(errors) = resp.get("errors") {
+            return Err(ClientError::GraphQL(errors.to_string()));
+        }
+        resp.get("data")
+            .cloned()
+            .ok_or_else(|| ClientError::Response(resp.to_string()))
+    }
+}
+
+/// Blocks of the chain in order of height, it ends at the first error.
+pub struct BlockSubscription<'a> {
+    client: &'a Client,
+    height: u64,
+}
+
+impl<'a> Iterator for BlockSubscription<'a> {
+    type Item = Result<Block, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.client.get_block(Some(self.height)) {
+                Ok(block) => {
+                    self.height += 1;
+                    return Some(Ok(block));
+                }
+                Err(ClientError::GraphQL(ref errors)) if errors.contains("GetNone") => {
+                    thread::sleep(self.client.config.poll_interval)
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+// Methods returning nothing succeed with an empty string
+fn decode_response<T: DeserializeOwned>(resp: ServiceResponse<String>) -> Result<T, ClientError> {
+    if resp.is_error() {
+        return Err(ClientError::Service {
+            code:    resp.code,
+            message: resp.error_message,
+        });
+    }
+
+    let succeed_data = if resp.succeed_data.is_empty() {
+        "null"
+    } else {
+        &resp.succeed_data
+    };
+    Ok(serde_json::from_str(succeed_data)?)
+}
+
+// Receipts of transactions not committed are not found in storage, the ones
+// of committed blocks not executed yet are reported as unexecuted
+fn is_receipt_pending(errors: &str) -> bool {
+    errors.contains("GetNone") || errors.contains("UnExecedError")
+}
+
+fn verify_attestation(
+    v: &Value,
+    caller: &Address,
+    request: &TransactionRequest,
+    resp: &ServiceResponse<String>,
+    trusted: &Metadata,
+) -> Result<Bytes, ClientError> {
+    let unverified = |reason: &str| ClientError::Unverified(reason.to_owned());
+
+    let height = from_uint64(&v["height"])?;
+    let block = Block::decode_fixed(from_hex(&v["block"])?)?;
+    let proof = Proof::decode_fixed(from_hex(&v["proof"])?)?;
+    if block.header.height != height {
+        return Err(unverified("block is not of the queried height"));
+    }
+
+    // Recomputed from the query, the response can't be of another one
+    let attestation = QueryAttestation::new(
+        height,
+        block.header.state_root.clone(),
+        caller,
+        request,
+        resp,
+    );
+    if from_hash(&v["stateRoot"])? != attestation.state_root
+        || from_hash(&v["resultHash"])? != attestation.result_hash
+    {
+        return Err(unverified("attestation is not of the query"));
+    }
+
+    let pubkey = from_hex(&v["pubkey"])?;
+    let signing = attestation.signing_hash(trusted.signature_domain(height).as_ref());
+    Secp256k1::verify_signature(
+        signing.as_bytes().as_ref(),
+        from_hex(&v["signature"])?.as_ref(),
+        pubkey.as_ref(),
+    )
+    .map_err(|_| unverified("invalid signature of the node"))?;
+
+    // Quorum of the proof is counted by validators of the header, they must
+    // be the trusted ones
+    let validators_match = block.header.validators.len() == trusted.verifier_list.len()
+        && block.header.validators.iter().all(|validator| {
+            trusted.verifier_list.iter().any(|v| {
+                v.address == validator.address
+                    && v.propose_weight == validator.propose_weight
+                    && v.vote_weight == validator.vote_weight
+            })
+        });
+    if !validators_match {
+        return Err(unverified("validators of the block are not trusted"));
+    }
+    core_consensus::util::verify_proof(&block, &proof, trusted)?;
+
+    Ok(pubkey)
+}
+
+fn from_service_response(v: &Value) -> Result<ServiceResponse<String>, ClientError> {
+    Ok(ServiceResponse {
+        code:          from_uint64(&v["code"])?,
+        succeed_data:  to_string(&v["succeedData"]),
+        error_message: to_string(&v["errorMessage"]),
+    })
+}
+
+fn to_uint64(n: u64) -> String {
+    format!("0x{:x}", n)
+}
+
+fn to_string(v: &Value) -> String {
+    v.as_str().unwrap_or_default().to_owned()
+}
+
+fn from_uint64(v: &Value) -> Result<u64, ClientError> {
+    let s = v
+        .as_str()
+        .ok_or_else(|| ClientError::Response(v.to_string()))?;
+    u64::from_str_radix(s.trim_start_matches("0x"), 16)
+        .map_err(|_| ClientError::Response(v.to_string()))
+}
+
+fn from_hash(v: &Value) -> Result<Hash, ClientError> {
+    let s = v
+        .as_str()
+        .ok_or_else(|| ClientError::Response(v.to_string()))?;
+    Ok(Hash::from_hex(s)?)
+}
+
+fn from_hex(v: &Value) -> Result<Bytes, ClientError> {
+    let s = v
+        .as_str()
+        .ok_or_else(|| ClientError::Response(v.to_string()))?;
+    let bytes = hex::decode(s.trim_start_matches("0x"))
+        .map_err(|_| ClientError::Response(v.to_string()))?;
+    Ok(Bytes::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::convert::TryFrom;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+
+    use common_crypto::{
+        BlsPrivateKey, BlsPublicKey, BlsSignature, HashValue, PrivateKey, PublicKey,
+        Secp256k1PrivateKey, Signature, ToPublicKey,
+    };
+    use core_consensus::util::precommit_hash;
+    use protocol::fixed_codec::FixedCodec;
+    use protocol::traits::ServiceResponse;
+    use protocol::types::{
+        Address, Block, BlockHeader, Hash, Hex, Metadata, Proof, QueryAttestation,
+        TransactionRequest, Validator, ValidatorExtend,
+    };
+    use protocol::Bytes;
+
+    use super::{Client, ClientConfig};
+    use crate::error::ClientError;
+    use crate::transport::Transport;
+
+    // Responses are returned in order, requests are recorded
+    #[derive(Clone, Default)]
+    struct MockTransport {
+        responses: Arc<Mutex<VecDeque<Result<Value, ClientError>>>>,
+        requests:  Arc<Mutex<Vec<Value>>>,
+    }
+
+    impl MockTransport {
+        fn respond(&self, resp: Result<Value, ClientError>) {
+            self.responses.lock().unwrap().push_back(resp);
+        }
+
+        fn requests(&self) -> usize {
+            self.requests.lock().unwrap().len()
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn post(&self, _url: &str, body: &Value) -> Result<Value, ClientError> {
+            self.requests.lock().unwrap().push(body.clone());
+            self.responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("unexpected request")
+        }
+    }
+
+    fn client(transport: &MockTransport) -> Client {
+        Client::new("http://127.0.0.1:8000/graphql".to_owned())
+            .config(ClientConfig {
+                poll_interval: Duration::from_millis(1),
+                receipt_timeout: Duration::from_millis(200),
+                retry_interval: Duration::from_millis(1),
+                ..ClientConfig::default()
+            })
+            .transport(transport.clone())
+    }
+
+    const BLS_PRIVATE_KEYS: [&str; 4] = [
+        "000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970",
+        "00000000000000000000000000000000320b11d7c1ae66fdad1b4a75221244ae2d84903d3548c581d7d30dc135aac817",
+        "000000000000000000000000000000006a41e900d0426e615ca9d9393e6792baf9bda4398d5d407e59f77cb6c6f393cc",
+        "00000000000000000000000000000000125d81e0eb0a9c3746d868bf3b4f07760fdd430daded41d92f53b4e484ef3415",
+    ];
+
+    const BLS_PUBLIC_KEYS: [&str; 4] = [
+        "041054fe9a65be0891094ed37fb3655e3ffb12353bc0a1b4f8673b52ad65d1ca481780cf7e988eb8dcdc05d8352f03605b0d11afb2525b3f1b55ec694509248bcfead39cbb292725d710e2a509c77ed051d1d49e15e429cf6d12b9be7c02179612",
+        "040c15c82ed07dc866ab7c3af3a070eb4340ac0439bf12bb49cbed5797d52707e009f7c17414777b0213b9a55c8a5c08290ce40c366d59322db418b7ff41277090bd25614174763c9fd725ede1f65f3e61ca9acdb35f59e33d556e738add14d536",
+        "040b3118acefdfbb11ded262a7f3c90dfca4fbc0200a92b4f6bb80210ab85e39f79458f7d47f7cb06864df0571e7591a4e0858df0b52a4c3ae19ae3adc32e1da0ec4cbdca108365ee433becdb1ccebb1b339647788dfad94ebae1cbd770fcfa4e5",
+        "040709f204e3ec5b8bdd9f2bb6edc9cb1704fc1e4952661ba7532ea8e37f3b159b8d41987ee6707d32bdf494e2deb00b7f049a4670a5ce1ad8e429fcacc5bbc69cb03b71a7f1d831d0b47dda5e62642d420ff0a545950cb1db19d42fe04e2c91d2",
+    ];
+
+    // Metadata of four validators, the first ones sorted by address
+    fn trusted_metadata(keys: usize) -> Metadata {
+        let verifier_list = BLS_PUBLIC_KEYS[..keys]
+            .iter()
+            .enumerate()
+            .map(|(i, pk)| ValidatorExtend {
+                bls_pub_key:    Hex::from_bytes(Bytes::from(hex::decode(pk).unwrap())),
+                address:        Address::from_bytes(Bytes::from(vec![i as u8 + 1; 20])).unwrap(),
+                propose_weight: 1,
+                vote_weight:    1,
+            })
+            .collect();
+
+        Metadata {
+            chain_id: Hash::digest(Bytes::from("chain")),
+            common_ref: Hex::from_bytes(Bytes::from("muta")),
+            domain_separation_height: 1,
+            verifier_list,
+            ..Default::default()
+        }
+    }
+
+    fn committed_block(metadata: &Metadata) -> Block {
+        let validators = metadata
+            .verifier_list
+            .iter()
+            .map(|v| Validator {
+                address:        v.address.clone(),
+                propose_weight: v.propose_weight,
+                vote_weight:    v.vote_weight,
+            })
+            .collect();
+
+        Block {
+            header:            BlockHeader {
+                chain_id: metadata.chain_id.clone(),
+                height: 5,
+                exec_height: 5,
+                pre_hash: Hash::from_empty(),
+                timestamp: 0,
+                logs_bloom: vec![],
+                order_root: Hash::from_empty(),
+                confirm_root: vec![],
+                state_root: Hash::digest(Bytes::from("state")),
+                receipt_root: vec![],
+                cycles_used: vec![],
+                proposer: Address::default(),
+                proof: Proof {
+                    height:     0,
+                    round:      0,
+                    block_hash: Hash::from_empty(),
+                    signature:  Bytes::new(),
+                    bitmap:     Bytes::new(),
+                },
+                validator_version: 0,
+                validators,
+                extra_data: Bytes::new(),
+            },
+            ordered_tx_hashes: vec![],
+            vote_extensions:   vec![],
+        }
+    }
+
+    // Precommits of the first voters, whose bits are the highest in the bitmap
+    fn commit_proof(block: &Block, metadata: &Metadata, voters: usize) -> Proof {
+        let mut proof = Proof {
+            height:     block.header.height,
+            round:      1,
+            block_hash: Hash::digest(block.encode_fixed().unwrap()),
+            signature:  Bytes::new(),
+            bitmap:     Bytes::from(vec![!(0xffu8 >> voters)]),
+        };
+        let domain = metadata.signature_domain(block.header.height);
+        let vote_hash = precommit_hash(&proof, domain.as_ref());
+        let hash = HashValue::try_from(vote_hash.as_bytes().as_ref()).unwrap();
+
+        let sigs_and_pub_keys = (0..voters)
+            .map(|i| {
+                let priv_key =
+                    BlsPrivateKey::try_from(hex::decode(BLS_PRIVATE_KEYS[i]).unwrap().as_ref())
+                        .unwrap();
+                let pub_key =
+                    BlsPublicKey::try_from(hex::decode(BLS_PUBLIC_KEYS[i]).unwrap().as_ref())
+                        .unwrap();
+                (priv_key.sign_message(&hash), pub_key)
+            })
+            .collect::<Vec<_>>();
+        proof.signature = BlsSignature::combine(sigs_and_pub_keys).to_bytes();
+        proof
+    }
+
+    fn balance_request() -> TransactionRequest {
+        TransactionRequest {
+            service_name: "asset".to_owned(),
+            method:       "get_balance".to_owned(),
+            payload:      "null".to_owned(),
+        }
+    }
+
+    // Response of `signedQueryService` for the balance query, attested by
+    // the node at the state root of the block
+    fn signed_balance(block: &Block, proof: &Proof, metadata: &Metadata) -> Value {
+        let node_key = Secp256k1PrivateKey::try_from(&[1u8; 32][..]).unwrap();
+        let resp = ServiceResponse {
+            code:          0,
+            succeed_data:  "{\"balance\":100}".to_owned(),
+            error_message: "".to_owned(),
+        };
+        let attestation = QueryAttestation::new(
+            block.header.height,
+            block.header.state_root.clone(),
+            &Address::default(),
+            &balance_request(),
+            &resp,
+        );
+        let signing =
+            attestation.signing_hash(metadata.signature_domain(block.header.height).as_ref());
+        let hash = HashValue::try_from(signing.as_bytes().as_ref()).unwrap();
+        let to_hex = |bytes: Bytes| format!("0x{}", hex::encode(bytes));
+
+        json!({ "data": { "signedQueryService": {
+            "response": { "code": "0x0", "succeedData": resp.succeed_data, "errorMessage": "" },
+            "attestation": {
+                "height": format!("0x{:x}", attestation.height),
+                "stateRoot": attestation.state_root.as_hex(),
+                "resultHash": attestation.result_hash.as_hex(),
+                "pubkey": to_hex(node_key.pub_key().to_bytes()),
+                "signature": to_hex(node_key.sign_message(&hash).to_bytes()),
+                "block": to_hex(block.encode_fixed().unwrap()),
+                "proof": to_hex(proof.encode_fixed().unwrap()),
+            },
+        } } })
+    }
+
+    fn verified_balance(
+        transport: &MockTransport,
+        trusted: &Metadata,
+    ) -> Result<Value, ClientError> {
+        client(transport)
+            .verified_query_service("asset", "get_balance", &(), trusted)
+            .map(|(resp, _)| resp)
+    }
+
+    fn errors(message: &str) -> Result<Value, ClientError> {
+        Ok(json!({ "errors": [{ "message": message }] }))
+    }
+
+    fn receipt(tx_hash: &Hash) -> Result<Value, ClientError> {
+        Ok(json!({ "data": { "getReceipt": {
+            "stateRoot": Hash::from_empty().as_hex(),
+            "height": "0x2",
+            "txHash": tx_hash.as_hex(),
+            "cyclesUsed": "0x64",
+            "events": [],
+            "response": {
+                "serviceName": "asset",
+                "method": "transfer",
+                "response": { "code": "0x0", "succeedData": "", "errorMessage": "" },
+            },
+        } } }))
+    }
+
+    #[test]
+    fn test_wait_receipt_until_found() {
+        let transport = MockTransport::default();
+        let tx_hash = Hash::digest(protocol::Bytes::from("tx"));
+        transport.respond(errors("[ProtocolError] Kind: Storage Error: GetNone"));
+        transport.respond(errors(
+            "[ProtocolError] Kind: API Error: UnExecedError { expect: 2, real: 1 }",
+        ));
+        transport.respond(receipt(&tx_hash));
+
+        let receipt = client(&transport).wait_receipt(&tx_hash).unwrap();
+        assert_eq!(receipt.tx_hash, tx_hash);
+        assert_eq!(receipt.height, 2);
+        assert_eq!(receipt.cycles_used, 100);
+        assert_eq!(transport.requests(), 3);
+    }
+
+    #[test]
+    fn test_wait_receipt_returns_other_errors() {
+        let transport = MockTransport::default();
+        let tx_hash = Hash::digest(protocol::Bytes::from("tx"));
+        transport.respond(errors("Unknown field \"getReceipt\""));
+
+        match client(&transport).wait_receipt(&tx_hash) {
+            Err(ClientError::GraphQL(errors)) => assert!(errors.contains("Unknown field")),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(transport.requests(), 1);
+    }
+
+    #[test]
+    fn test_wait_receipt_timeout() {
+        let transport = MockTransport::default();
+        let tx_hash = Hash::digest(protocol::Bytes::from("tx"));
+        for _ in 0..1000 {
+            transport.respond(errors("[ProtocolError] Kind: Storage Error: GetNone"));
+        }
+
+        match client(&transport).wait_receipt(&tx_hash) {
+            Err(ClientError::ReceiptTimeout(hash)) => assert_eq!(hash, tx_hash),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_retry_unavailable_node() {
+        let transport = MockTransport::default();
+        let tx_hash = Hash::digest(protocol::Bytes::from("tx"));
+        transport.respond(Err(ClientError::Unavailable("connect".to_owned())));
+        transport.respond(Err(ClientError::Unavailable("503".to_owned())));
+        transport.respond(receipt(&tx_hash));
+
+        let receipt = client(&transport).get_receipt(&tx_hash).unwrap();
+        assert_eq!(receipt.tx_hash, tx_hash);
+        assert_eq!(transport.requests(), 3);
+    }
+
+    #[test]
+    fn test_retry_gives_up() {
+        let transport = MockTransport::default();
+        let tx_hash = Hash::digest(protocol::Bytes::from("tx"));
+        for _ in 0..4 {
+            transport.respond(Err(ClientError::Unavailable("connect".to_owned())));
+        }
+
+        match client(&transport).get_receipt(&tx_hash) {
+            Err(ClientError::Unavailable(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        // The first request and 3 retries of the default config
+        assert_eq!(transport.requests(), 4);
+    }
+
+    #[test]
+    fn test_query_service() {
+        let transport = MockTransport::default();
+        transport.respond(Ok(json!({ "data": { "queryService": {
+            "code": "0x0", "succeedData": "{\"balance\":100}", "errorMessage": "",
+        } } })));
+        transport.respond(Ok(json!({ "data": { "queryService": {
+            "code": "0x65", "succeedData": "", "errorMessage": "asset not found",
+        } } })));
+        let client = client(&transport);
+
+        let resp: Value = client.query_service("asset", "get_balance", &()).unwrap();
+        assert_eq!(resp["balance"], 100);
+
+        match client.query_service::<_, Value>("asset", "get_balance", &()) {
+            Err(ClientError::Service { code, message }) => {
+                assert_eq!(code, 101);
+                assert_eq!(message, "asset not found");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_verified_query_service() {
+        let transport = MockTransport::default();
+        let trusted = trusted_metadata(4);
+        let block = committed_block(&trusted);
+        let proof = commit_proof(&block, &trusted, 3);
+        transport.respond(Ok(signed_balance(&block, &proof, &trusted)));
+
+        let (resp, pubkey) = client(&transport)
+            .verified_query_service::<_, Value>("asset", "get_balance", &(), &trusted)
+            .unwrap();
+        assert_eq!(resp["balance"], 100);
+        let node_key = Secp256k1PrivateKey::try_from(&[1u8; 32][..]).unwrap();
+        assert_eq!(pubkey, node_key.pub_key().to_bytes());
+    }
+
+    #[test]
+    fn test_reject_tampered_response() {
+        let transport = MockTransport::default();
+        let trusted = trusted_metadata(4);
+        let block = committed_block(&trusted);
+        let proof = commit_proof(&block, &trusted, 3);
+
+        let mut signed = signed_balance(&block, &proof, &trusted);
+        signed["data"]["signedQueryService"]["response"]["succeedData"] =
+            json!("{\"balance\":1000}");
+        transport.respond(Ok(signed));
+        match verified_balance(&transport, &trusted) {
+            Err(ClientError::Unverified(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Signed by another node than the one of the public key
+        let mut signed = signed_balance(&block, &proof, &trusted);
+        let other_key = Secp256k1PrivateKey::try_from(&[2u8; 32][..]).unwrap();
+        signed["data"]["signedQueryService"]["attestation"]["pubkey"] =
+            json!(format!("0x{}", hex::encode(other_key.pub_key().to_bytes())));
+        transport.respond(Ok(signed));
+        match verified_balance(&transport, &trusted) {
+            Err(ClientError::Unverified(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reject_forged_block() {
+        let transport = MockTransport::default();
+        let trusted = trusted_metadata(4);
+        let block = committed_block(&trusted);
+        let proof = commit_proof(&block, &trusted, 3);
+
+        // The node attests a state root the validators never committed
+        let mut forged = block;
+        forged.header.state_root = Hash::digest(Bytes::from("forged"));
+        transport.respond(Ok(signed_balance(&forged, &proof, &trusted)));
+
+        assert!(verified_balance(&transport, &trusted).is_err());
+    }
+
+    #[test]
+    fn test_reject_wrong_proof() {
+        let transport = MockTransport::default();
+        let trusted = trusted_metadata(4);
+        let block = committed_block(&trusted);
+
+        // Votes of two validators don't reach quorum
+        let proof = commit_proof(&block, &trusted, 2);
+        transport.respond(Ok(signed_balance(&block, &proof, &trusted)));
+        assert!(verified_balance(&transport, &trusted).is_err());
+
+        // Signatures are for another round
+        let mut proof = commit_proof(&block, &trusted, 3);
+        proof.round = 2;
+        transport.respond(Ok(signed_balance(&block, &proof, &trusted)));
+        assert!(verified_balance(&transport, &trusted).is_err());
+
+        // Signatures are for another block
+        let mut other = committed_block(&trusted);
+        other.header.height = 6;
+        let mut proof = commit_proof(&other, &trusted, 3);
+        proof.height = 5;
+        transport.respond(Ok(signed_balance(&block, &proof, &trusted)));
+        assert!(verified_balance(&transport, &trusted).is_err());
+    }
+
+    #[test]
+    fn test_reject_untrusted_validators() {
+        let transport = MockTransport::default();
+
+        // Three of the validators commit the block by themselves
+        let untrusted = trusted_metadata(3);
+        let block = committed_block(&untrusted);
+        let proof = commit_proof(&block, &untrusted, 3);
+        transport.respond(Ok(signed_balance(&block, &proof, &untrusted)));
+
+        match verified_balance(&transport, &trusted_metadata(4)) {
+            Err(ClientError::Unverified(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
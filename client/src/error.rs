@@ -0,0 +1,50 @@
+use derive_more::{Display, From};
+
+use protocol::types::Hash;
+use protocol::ProtocolError;
+
+#[derive(Debug, Display, From)]
+pub enum ClientError {
+    #[display(fmt = "http {}", _0)]
+    Http(reqwest::Error),
+
+    #[display(fmt = "node unavailable {}", _0)]
+    #[from(ignore)]
+    Unavailable(String),
+
+    #[display(fmt = "json {}", _0)]
+    Json(serde_json::Error),
+
+    #[display(fmt = "{}", _0)]
+    Protocol(ProtocolError),
+
+    #[display(fmt = "graphql errors {}", _0)]
+    #[from(ignore)]
+    GraphQL(String),
+
+    #[display(fmt = "unexpected response {}", _0)]
+    #[from(ignore)]
+    Response(String),
+
+    #[display(fmt = "invalid private key {}", _0)]
+    #[from(ignore)]
+    PrivateKey(String),
+
+    #[display(fmt = "no private key to sign transactions")]
+    #[from(ignore)]
+    NoSigner,
+
+    #[display(fmt = "service error {} {}", code, message)]
+    #[from(ignore)]
+    Service { code: u64, message: String },
+
+    #[display(fmt = "receipt of {:?} is not found before timeout", _0)]
+    #[from(ignore)]
+    ReceiptTimeout(Hash),
+
+    #[display(fmt = "unverified query response {}", _0)]
+    #[from(ignore)]
+    Unverified(String),
+}
+
+impl std::error::Error for ClientError {}
@@ -0,0 +1,28 @@
+//! Client of muta nodes through their GraphQL api. Transactions are built
+//! and signed locally, calls of built-in services are typed by the payloads
+//! and responses of the services.
+//!
+//! ```no_run
+//! use muta_client::Client;
+//! # fn main() -> Result<(), muta_client::ClientError> {
+//! # let private_key = [0u8; 32];
+//! # let asset_id = protocol::types::Hash::from_empty();
+//! # let to = protocol::types::Address::default();
+//! let client = Client::new("http://127.0.0.1:8000/graphql".to_owned())
+//!     .private_key(&private_key)?;
+//! client.asset().transfer(asset_id, to, 100)?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod asset;
+mod client;
+mod error;
+mod metadata;
+mod transport;
+
+pub use crate::asset::AssetClient;
+pub use crate::client::{BlockSubscription, Client, ClientConfig};
+pub use crate::error::ClientError;
+pub use crate::metadata::MetadataClient;
+pub use crate::transport::{HttpTransport, Transport};
@@ -0,0 +1,86 @@
+use metadata::types::{
+    GetServiceAdminsPayload, HaltChainPayload, ServiceAdmins, UpdateValidatorsPayload,
+};
+use protocol::types::{Address, FeatureActivation, Metadata, ValidatorExtend};
+
+use crate::client::Client;
+use crate::error::ClientError;
+
+const SERVICE: &str = "metadata";
+
+/// Calls of the metadata service, writes wait for their receipts.
+pub struct MetadataClient<'a> {
+    client: &'a Client,
+}
+
+impl Client {
+    pub fn metadata(&self) -> MetadataClient<'_> {
+        MetadataClient { client: self }
+    }
+}
+
+impl<'a> MetadataClient<'a> {
+    pub fn get_metadata(&self) -> Result<Metadata, ClientError> {
+        self.client.get_metadata()
+    }
+
+    pub fn get_service_admins(&self, service: &str) -> Result<Vec<Address>, ClientError> {
+        self.client
+            .query_service(SERVICE, "get_service_admins", &GetServiceAdminsPayload {
+                service: service.to_owned(),
+            })
+    }
+
+    /// Called by admins of the metadata service only
+    pub fn set_service_admins(
+        &self,
+        service: &str,
+        admins: Vec<Address>,
+    ) -> Result<(), ClientError> {
+        self.client
+            .call(SERVICE, "set_service_admins", &ServiceAdmins {
+                service: service.to_owned(),
+                admins,
+            })
+    }
+
+    /// Called by admins of the metadata service only
+    pub fn update_metadata(&self, metadata: Metadata) -> Result<(), ClientError> {
+        self.client.call(SERVICE, "update_metadata", &metadata)
+    }
+
+    /// Schedule the feature of the chain spec to activate from the height,
+    /// `MIN_ACTIVATION_LEAD` blocks after the current one at the earliest.
+    /// Called by admins of the metadata service only.
+    pub fn schedule_feature(&self, name: &str, height: u64) -> Result<(), ClientError> {
+        let mut metadata = self.get_metadata()?;
+        metadata.chain_spec.activations.push(FeatureActivation {
+            name: name.to_owned(),
+            height,
+        });
+        self.update_metadata(metadata)
+    }
+
+    /// Vote of a validator, the list replaces validators once votes of more
+    /// than 2/3 weight agree on it.
+    pub fn update_validators(
+        &self,
+        verifier_list: Vec<ValidatorExtend>,
+    ) -> Result<(), ClientError> {
+        self.client
+            .call(SERVICE, "update_validators", &UpdateValidatorsPayload {
+                verifier_list,
+            })
+    }
+
+    /// Vote of a validator, the chain halts once votes of more than 2/3
+    /// weight agree on the height.
+    pub fn halt_chain(&self, halt_height: u64) -> Result<(), ClientError> {
+        self.client
+            .call(SERVICE, "halt_chain", &HaltChainPayload { halt_height })
+    }
+
+    pub fn resume_chain(&self) -> Result<(), ClientError> {
+        self.client.call(SERVICE, "resume_chain", &())
+    }
+}
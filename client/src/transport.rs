@@ -0,0 +1,66 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use crate::error::ClientError;
+
+/// Posts GraphQL requests of the client to the node and returns the json
+/// responses. Requests failing with `ClientError::Unavailable` are retried by
+/// the client.
+pub trait Transport: Send + Sync {
+    fn post(&self, url: &str, body: &Value) -> Result<Value, ClientError>;
+}
+
+pub struct HttpTransport {
+    http:    reqwest::blocking::Client,
+    api_key: Option<String>,
+}
+
+impl HttpTransport {
+    pub fn new() -> Self {
+        Self {
+            http:    reqwest::blocking::Client::new(),
+            api_key: None,
+        }
+    }
+
+    /// Send the key in the `x-api-key` header, requests get the limits of
+    /// it on the node, such as `signed_query`.
+    pub fn api_key(mut self, api_key: String) -> Self {
+        self.api_key = Some(api_key);
+        self
+    }
+}
+
+impl Default for HttpTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for HttpTransport {
+    fn post(&self, url: &str, body: &Value) -> Result<Value, ClientError> {
+        let mut req = self.http.post(url).json(body);
+        if let Some(api_key) = &self.api_key {
+            req = req.header("x-api-key", api_key.as_str());
+        }
+
+        let resp = req.send().map_err(|e| {
+            // Requests not connected are never seen by the node, resending
+            // them doesn't duplicate transactions
+            if e.is_connect() {
+                ClientError::Unavailable(e.to_string())
+            } else {
+                ClientError::Http(e)
+            }
+        })?;
+
+        match resp.status() {
+            StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => {
+                Err(ClientError::Unavailable(resp.status().to_string()))
+            }
+            _ => Ok(resp.json()?),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use aes_gcm::aead::{generic_array::GenericArray, Aead, NewAead, Payload};
+use aes_gcm::Aes256Gcm;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use common_crypto::{PublicKey, Secp256k1PrivateKey, ToPublicKey};
+use protocol::types::{Address, Hex};
+use protocol::{Bytes, ProtocolResult};
+
+use crate::KeystoreError;
+
+const KEYSTORE_VERSION: u32 = 1;
+const CIPHER: &str = "aes-256-gcm";
+const KDF: &str = "scrypt";
+
+/// 32 MiB of memory with r 8, it takes about a second to unlock.
+pub const DEFAULT_SCRYPT_LOG_N: u8 = 15;
+
+/// A secp256k1 private key encrypted by a passphrase, saved as json. The
+/// address of the key is readable without the passphrase, and it's
+/// authenticated by the cipher as associated data.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Keystore {
+    pub version: u32,
+    pub address: Address,
+    pub crypto:  KeystoreCrypto,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct KeystoreCrypto {
+    pub cipher:     String,
+    pub nonce:      Hex,
+    pub ciphertext: Hex,
+    pub kdf:        String,
+    pub kdfparams:  ScryptParams,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ScryptParams {
+    pub log_n: u8,
+    pub r:     u32,
+    pub p:     u32,
+    pub salt:  Hex,
+}
+
+impl Keystore {
+    pub fn encrypt(privkey: &[u8], passphrase: &str) -> ProtocolResult<Self> {
+        Self::encrypt_with(privkey, passphrase, DEFAULT_SCRYPT_LOG_N)
+    }
+
+    /// Encrypt with the cost of scrypt, a larger `log_n` is slower to brute
+    /// force and to unlock.
+    pub fn encrypt_with(privkey: &[u8], passphrase: &str, log_n: u8) -> ProtocolResult<Self> {
+        let address = address_of(privkey)?;
+
+        let mut salt = [0u8; 32];
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let kdfparams = ScryptParams {
+            log_n,
+            r: 8,
+            p: 1,
+            salt: Hex::from_bytes(Bytes::from(salt.to_vec())),
+        };
+
+        let cipher = cipher(passphrase, &kdfparams)?;
+        let ciphertext = cipher
+            .encrypt(GenericArray::from_slice(&nonce), Payload {
+                msg: privkey,
+                aad: address.as_bytes().as_ref(),
+            })
+            .map_err(|_| KeystoreError::Key("failed to encrypt".to_owned()))?;
+
+        Ok(Keystore {
+            version: KEYSTORE_VERSION,
+            address,
+            crypto: KeystoreCrypto {
+                cipher: CIPHER.to_owned(),
+                nonce: Hex::from_bytes(Bytes::from(nonce.to_vec())),
+                ciphertext: Hex::from_bytes(Bytes::from(ciphertext)),
+                kdf: KDF.to_owned(),
+                kdfparams,
+            },
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> ProtocolResult<Bytes> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::Unsupported(format!("version {}", self.version)).into());
+        }
+        if self.crypto.cipher != CIPHER || self.crypto.kdf != KDF {
+            return Err(KeystoreError::Unsupported(format!(
+                "{} with {}",
+                self.crypto.cipher, self.crypto.kdf
+            ))
+            .into());
+        }
+
+        let nonce = self.crypto.nonce.as_bytes();
+        if nonce.len() != 12 {
+            return Err(KeystoreError::Decrypt.into());
+        }
+        let cipher = cipher(passphrase, &self.crypto.kdfparams)?;
+        let privkey = cipher
+            .decrypt(GenericArray::from_slice(&nonce), Payload {
+                msg: self.crypto.ciphertext.as_bytes().as_ref(),
+                aad: self.address.as_bytes().as_ref(),
+            })
+            .map_err(|_| KeystoreError::Decrypt)?;
+
+        if address_of(&privkey)? != self.address {
+            return Err(KeystoreError::Decrypt.into());
+        }
+        Ok(Bytes::from(privkey))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> ProtocolResult<Self> {
+        let json = fs::read_to_string(path).map_err(KeystoreError::Io)?;
+        let keystore = serde_json::from_str(&json).map_err(KeystoreError::Json)?;
+        Ok(keystore)
+    }
+
+    /// Save to a new file, it's readable by the owner only on unix.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ProtocolResult<()> {
+        let json = serde_json::to_string_pretty(self).map_err(KeystoreError::Json)?;
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(path).map_err(KeystoreError::Io)?;
+        file.write_all(json.as_bytes()).map_err(KeystoreError::Io)?;
+        Ok(())
+    }
+}
+
+/// Passphrase in the env var, or prompted in the terminal if it's unset.
+pub fn read_passphrase(env: &str) -> ProtocolResult<String> {
+    if let Ok(passphrase) = std::env::var(env) {
+        return Ok(passphrase);
+    }
+
+    let passphrase = rpassword::read_password_from_tty(Some("keystore passphrase: "))
+        .map_err(|_| KeystoreError::Passphrase(env.to_owned()))?;
+    Ok(passphrase)
+}
+
+fn cipher(passphrase: &str, kdfparams: &ScryptParams) -> ProtocolResult<Aes256Gcm> {
+    let params = scrypt::ScryptParams::new(kdfparams.log_n, kdfparams.r, kdfparams.p)
+        .map_err(|_| KeystoreError::Unsupported("scrypt params".to_owned()))?;
+
+    let mut key = [0u8; 32];
+    scrypt::scrypt(
+        passphrase.as_bytes(),
+        kdfparams.salt.as_bytes().as_ref(),
+        &params,
+        &mut key,
+    )
+    .map_err(|_| KeystoreError::Unsupported("scrypt output".to_owned()))?;
+
+    Ok(Aes256Gcm::new(*GenericArray::from_slice(&key)))
+}
+
+fn address_of(privkey: &[u8]) -> ProtocolResult<Address> {
+    let privkey = Secp256k1PrivateKey::try_from(privkey)
+        .map_err(|e| KeystoreError::Key(format!("{:?}", e)))?;
+    Address::from_pubkey_bytes(privkey.pub_key().to_bytes())
+}
@@ -0,0 +1,412 @@
+//! Keys of validators kept out of plaintext configs. A `Keystore` is the
+//! secp256k1 private key encrypted by a passphrase, with scrypt and
+//! AES-256-GCM. A `Signer` signs hashes by the secp256k1 or bls key of the
+//! validator in process by `LocalSigner`. Consensus messages and vote
+//! extensions are signed by the bls key, which may be of a signer process
+//! serving the remote signer protocol to `RemoteSigner`, keeping its key in
+//! an HSM.
+
+mod keystore;
+mod remote;
+
+use std::convert::TryFrom;
+
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+use common_crypto::{
+    BlsPrivateKey, HashValue, PrivateKey, PublicKey, Secp256k1PrivateKey, Signature, ToBlsPublicKey,
+};
+use protocol::types::{
+    signing_hash, Hash, Hex, SignatureDomain, VoteExtension, CONSENSUS_SIGNATURE_PURPOSE,
+};
+use protocol::{Bytes, ProtocolError, ProtocolErrorKind, ProtocolResult};
+
+pub use self::keystore::{
+    read_passphrase, Keystore, KeystoreCrypto, ScryptParams, DEFAULT_SCRYPT_LOG_N,
+};
+pub use self::remote::{serve_signer, RemoteSigner, Watermark};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyType {
+    #[display(fmt = "secp256k1")]
+    Secp256k1,
+    #[display(fmt = "bls")]
+    Bls,
+}
+
+/// A message of consensus signed by the bls key, such as a vote, in rlp of
+/// overlord. Its hash is signed in the signature domain, the same as
+/// signatures of overlord messages verified by nodes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConsensusMessage {
+    pub message: Hex,
+    pub domain:  Option<SignatureDomain>,
+}
+
+impl ConsensusMessage {
+    pub fn signing_hash(&self) -> Hash {
+        signing_hash(
+            self.domain.as_ref(),
+            CONSENSUS_SIGNATURE_PURPOSE,
+            &Hash::digest(self.message.as_bytes()),
+        )
+    }
+}
+
+/// A vote extension signed by the bls key over the block precommitted. Its
+/// signing hash is signed in the signature domain, the same as consensus
+/// messages.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ExtensionMessage {
+    pub extension: VoteExtension,
+    pub domain:    Option<SignatureDomain>,
+}
+
+impl ExtensionMessage {
+    pub fn signing_hash(&self) -> Hash {
+        signing_hash(
+            self.domain.as_ref(),
+            CONSENSUS_SIGNATURE_PURPOSE,
+            &self.extension.signing_hash(),
+        )
+    }
+}
+
+/// Signs hashes by keys of a validator. Calls are blocking, consensus signs
+/// its votes through it.
+pub trait Signer: Send + Sync {
+    fn sign(&self, key_type: KeyType, hash: &Hash) -> ProtocolResult<Bytes>;
+
+    /// Sign the consensus message by the bls key. Remote signers only sign
+    /// these, checking votes in them against their watermarks.
+    fn sign_consensus(&self, msg: &ConsensusMessage) -> ProtocolResult<Bytes> {
+        self.sign(KeyType::Bls, &msg.signing_hash())
+    }
+
+    /// Sign the vote extension by the bls key, remote signers check it
+    /// against their watermarks as well.
+    fn sign_vote_extension(&self, msg: &ExtensionMessage) -> ProtocolResult<Bytes> {
+        self.sign(KeyType::Bls, &msg.signing_hash())
+    }
+
+    /// Public key of the bls key under the common reference of the chain.
+    fn bls_pub_key(&self, common_ref: &str) -> ProtocolResult<Bytes>;
+
+    /// Whether signing waits on io, callers in async tasks move it off the
+    /// runtime.
+    fn is_blocking(&self) -> bool {
+        false
+    }
+}
+
+/// Keys of the validator held in process.
+pub struct LocalSigner {
+    secp256k1: Secp256k1PrivateKey,
+    bls:       BlsPrivateKey,
+}
+
+impl LocalSigner {
+    /// The bls key is derived from the secp256k1 private key, the same as
+    /// keys generated by `muta-cli keygen`.
+    pub fn from_privkey(privkey: &[u8]) -> ProtocolResult<Self> {
+        let secp256k1 = Secp256k1PrivateKey::try_from(privkey)
+            .map_err(|e| KeystoreError::Key(format!("{:?}", e)))?;
+        let bls = BlsPrivateKey::try_from([&[0u8; 16], privkey].concat().as_ref())
+            .map_err(|e| KeystoreError::Key(format!("{:?}", e)))?;
+
+        Ok(LocalSigner { secp256k1, bls })
+    }
+}
+
+impl Signer for LocalSigner {
+    fn sign(&self, key_type: KeyType, hash: &Hash) -> ProtocolResult<Bytes> {
+        let hash = hash_value(hash)?;
+        let signature = match key_type {
+            KeyType::Secp256k1 => self.secp256k1.sign_message(&hash).to_bytes(),
+            KeyType::Bls => self.bls.sign_message(&hash).to_bytes(),
+        };
+        Ok(signature)
+    }
+
+    fn bls_pub_key(&self, common_ref: &str) -> ProtocolResult<Bytes> {
+        self.bls.bls_pub_key(common_ref)
+    }
+}
+
+// A bls key alone, nodes without secp256k1 keys of signers only sign votes
+impl Signer for BlsPrivateKey {
+    fn sign(&self, key_type: KeyType, hash: &Hash) -> ProtocolResult<Bytes> {
+        match key_type {
+            KeyType::Bls => Ok(self.sign_message(&hash_value(hash)?).to_bytes()),
+            KeyType::Secp256k1 => Err(KeystoreError::NoKey(key_type).into()),
+        }
+    }
+
+    fn bls_pub_key(&self, common_ref: &str) -> ProtocolResult<Bytes> {
+        Ok(self.pub_key(&common_ref.into()).to_bytes())
+    }
+}
+
+fn hash_value(hash: &Hash) -> ProtocolResult<HashValue> {
+    let hash = HashValue::try_from(hash.as_bytes().as_ref())
+        .map_err(|_| KeystoreError::Key("hash is not 32 bytes".to_owned()))?;
+    Ok(hash)
+}
+
+#[derive(Debug, Display)]
+pub enum KeystoreError {
+    #[display(fmt = "io error {:?}", _0)]
+    Io(std::io::Error),
+
+    #[display(fmt = "json error {:?}", _0)]
+    Json(serde_json::Error),
+
+    #[display(fmt = "unsupported keystore {}", _0)]
+    Unsupported(String),
+
+    #[display(fmt = "wrong passphrase or corrupted keystore")]
+    Decrypt,
+
+    #[display(fmt = "no passphrase in env {} or terminal", _0)]
+    Passphrase(String),
+
+    #[display(fmt = "invalid key {}", _0)]
+    Key(String),
+
+    #[display(fmt = "no {} key to sign", _0)]
+    NoKey(KeyType),
+
+    #[display(fmt = "remote signer {}", _0)]
+    Remote(String),
+
+    #[display(fmt = "remote signer handshake {}", _0)]
+    Handshake(String),
+
+    #[display(fmt = "refuse to sign {}", _0)]
+    Watermark(String),
+}
+
+impl std::error::Error for KeystoreError {}
+
+impl From<KeystoreError> for ProtocolError {
+    fn from(err: KeystoreError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Keystore, Box::new(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::net::{SocketAddr, TcpListener};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use common_crypto::{
+        BlsPrivateKey, Crypto, PublicKey, Secp256k1, Secp256k1PrivateKey, ToPublicKey,
+    };
+    use protocol::types::{Address, Hash, Hex, VoteExtension};
+    use protocol::Bytes;
+
+    use super::{
+        serve_signer, ConsensusMessage, ExtensionMessage, KeyType, Keystore, LocalSigner,
+        RemoteSigner, Signer, Watermark,
+    };
+
+    const PRIVKEY: [u8; 32] = [7u8; 32];
+
+    #[test]
+    fn test_keystore_decrypt() {
+        // Cheap scrypt for tests
+        let keystore = Keystore::encrypt_with(&PRIVKEY, "passphrase", 4).unwrap();
+        assert_eq!(keystore.decrypt("passphrase").unwrap().as_ref(), &PRIVKEY);
+        assert!(keystore.decrypt("wrong").is_err());
+
+        let json = serde_json::to_string(&keystore).unwrap();
+        let loaded: Keystore = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, keystore);
+
+        // The address is authenticated
+        let mut swapped = keystore;
+        swapped.address = Address::from_bytes(Bytes::from(vec![1u8; 20])).unwrap();
+        assert!(swapped.decrypt("passphrase").is_err());
+    }
+
+    #[test]
+    fn test_local_signer() {
+        let signer = LocalSigner::from_privkey(&PRIVKEY).unwrap();
+        let hash = Hash::digest(Bytes::from_static(b"vote"));
+        let keystore = Keystore::encrypt_with(&PRIVKEY, "", 4).unwrap();
+
+        let signature = signer.sign(KeyType::Secp256k1, &hash).unwrap();
+        let pubkey = Secp256k1PrivateKey::try_from(&PRIVKEY[..])
+            .unwrap()
+            .pub_key()
+            .to_bytes();
+        let address = Address::from_pubkey_bytes(pubkey.clone()).unwrap();
+        assert_eq!(address, keystore.address);
+        assert!(Secp256k1::verify_signature(hash.as_bytes().as_ref(), &signature, &pubkey).is_ok());
+
+        let bls = BlsPrivateKey::try_from([&[0u8; 16], &PRIVKEY[..]].concat().as_ref()).unwrap();
+        assert_eq!(
+            signer.sign(KeyType::Bls, &hash).unwrap(),
+            bls.sign(KeyType::Bls, &hash).unwrap()
+        );
+        assert!(bls.sign(KeyType::Secp256k1, &hash).is_err());
+    }
+
+    fn vote(height: u64, round: u64, vote_type: u8, block_hash: &[u8]) -> ConsensusMessage {
+        let mut stream = rlp::RlpStream::new_list(4);
+        stream
+            .append(&height)
+            .append(&round)
+            .append(&vote_type)
+            .append(&block_hash.to_vec());
+
+        ConsensusMessage {
+            message: Hex::from_bytes(Bytes::from(stream.out())),
+            domain:  None,
+        }
+    }
+
+    fn extension(height: u64, payload: &'static str) -> ExtensionMessage {
+        ExtensionMessage {
+            extension: VoteExtension {
+                height,
+                voter: Address::default(),
+                block_hash: Hash::digest(Bytes::from_static(b"block")),
+                payload: Bytes::from_static(payload.as_bytes()),
+                signature: Bytes::new(),
+            },
+            domain:    None,
+        }
+    }
+
+    fn serve(shared_key: &'static [u8], watermark: &Path) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let watermark = Watermark::load(watermark).unwrap();
+        let bls = BlsPrivateKey::try_from([&[0u8; 16], &PRIVKEY[..]].concat().as_ref()).unwrap();
+        let shared_key = Bytes::from_static(shared_key);
+        thread::spawn(move || serve_signer(listener, Arc::new(bls), shared_key, watermark));
+        addr
+    }
+
+    fn remote(addr: SocketAddr, shared_key: &'static [u8]) -> RemoteSigner {
+        RemoteSigner::new(addr, Duration::from_secs(5), Bytes::from_static(shared_key))
+    }
+
+    fn watermark_path() -> PathBuf {
+        std::env::temp_dir().join(format!("muta-watermark-{}.json", rand::random::<u64>()))
+    }
+
+    #[test]
+    fn test_remote_signer() {
+        let path = watermark_path();
+        let addr = serve(b"shared key", &path);
+        let local = BlsPrivateKey::try_from([&[0u8; 16], &PRIVKEY[..]].concat().as_ref()).unwrap();
+
+        let remote = remote(addr, b"shared key");
+        assert_eq!(
+            remote.bls_pub_key("muta").unwrap(),
+            local.bls_pub_key("muta").unwrap()
+        );
+        for round in 0..3 {
+            let msg = vote(1, round, 1, b"block");
+            assert_eq!(
+                remote.sign_consensus(&msg).unwrap(),
+                local.sign_consensus(&msg).unwrap()
+            );
+        }
+
+        // Hashes and secp256k1 signatures are never signed remotely
+        let hash = Hash::digest(Bytes::from_static(b"tx"));
+        assert!(remote.sign(KeyType::Secp256k1, &hash).is_err());
+        assert!(remote.sign(KeyType::Bls, &hash).is_err());
+        let mut msg = vote(1, 3, 1, b"block");
+        msg.message = Hex::from_bytes(hash.as_bytes());
+        assert!(remote.sign_consensus(&msg).is_err());
+
+        // Errors of the signer are returned, the connection is kept
+        assert!(remote.sign_consensus(&vote(1, 3, 1, b"block")).is_ok());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_remote_signer_handshake() {
+        let path = watermark_path();
+        let addr = serve(b"shared key", &path);
+
+        let remote = remote(addr, b"other key");
+        assert!(remote.bls_pub_key("muta").is_err());
+        assert!(remote.sign_consensus(&vote(1, 0, 1, b"block")).is_err());
+
+        // Signers not knowing the key aren't trusted by the node either
+        let other = serve(b"other key", &watermark_path());
+        let remote = remote(other, b"shared key");
+        assert!(remote.bls_pub_key("muta").is_err());
+    }
+
+    #[test]
+    fn test_watermark() {
+        let path = watermark_path();
+        let addr = serve(b"shared key", &path);
+        let remote = remote(addr, b"shared key");
+
+        assert!(remote.sign_consensus(&vote(5, 1, 1, b"block")).is_ok());
+        // The same vote is signed again, conflicting ones and earlier ones
+        // are not
+        assert!(remote.sign_consensus(&vote(5, 1, 1, b"block")).is_ok());
+        assert!(remote.sign_consensus(&vote(5, 1, 1, b"other")).is_err());
+        assert!(remote.sign_consensus(&vote(5, 0, 1, b"block")).is_err());
+        assert!(remote.sign_consensus(&vote(4, 2, 1, b"block")).is_err());
+
+        // Watermarks are by vote type
+        assert!(remote.sign_consensus(&vote(5, 1, 2, b"block")).is_ok());
+        assert!(remote.sign_consensus(&vote(5, 2, 1, b"other")).is_ok());
+
+        // A restarted signer keeps them
+        let restarted = serve(b"shared key", &path);
+        let remote = remote(restarted, b"shared key");
+        assert!(remote.sign_consensus(&vote(5, 2, 1, b"block")).is_err());
+        assert!(remote.sign_consensus(&vote(5, 1, 2, b"other")).is_err());
+        assert!(remote.sign_consensus(&vote(6, 0, 1, b"block")).is_ok());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_watermark_of_vote_extensions() {
+        let path = watermark_path();
+        let addr = serve(b"shared key", &path);
+        let remote = remote(addr, b"shared key");
+        let local = BlsPrivateKey::try_from([&[0u8; 16], &PRIVKEY[..]].concat().as_ref()).unwrap();
+
+        let msg = extension(5, "price 42");
+        assert_eq!(
+            remote.sign_vote_extension(&msg).unwrap(),
+            local.sign_vote_extension(&msg).unwrap()
+        );
+        // Signed once a height, the same extension is signed again
+        assert!(remote.sign_vote_extension(&msg).is_ok());
+        assert!(remote
+            .sign_vote_extension(&extension(5, "price 1"))
+            .is_err());
+        assert!(remote
+            .sign_vote_extension(&extension(4, "price 42"))
+            .is_err());
+
+        // Votes of the height are still signed
+        assert!(remote.sign_consensus(&vote(5, 0, 1, b"block")).is_ok());
+
+        let restarted = serve(b"shared key", &path);
+        let remote = remote(restarted, b"shared key");
+        assert!(remote
+            .sign_vote_extension(&extension(5, "price 1"))
+            .is_err());
+        assert!(remote.sign_vote_extension(&extension(6, "price 1")).is_ok());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use protocol::types::{Hash, Hex};
+use protocol::{Bytes, ProtocolResult};
+
+use crate::{ConsensusMessage, ExtensionMessage, KeyType, KeystoreError, Signer};
+
+// The remote signer protocol: a request is a line of json sent over tcp, and
+// the signer answers each with a line of json, in order. Connections start
+// by a handshake proving both sides know the shared key.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+enum SignRequest {
+    BlsPubKey { common_ref: String },
+    Consensus(ConsensusMessage),
+    VoteExtension(ExtensionMessage),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SignResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Hex>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error:  Option<String>,
+}
+
+// The signer sends its nonce first, the node answers with its nonce and the
+// mac of both, and the signer proves the key by its mac of them
+#[derive(Serialize, Deserialize, Debug)]
+struct Hello {
+    nonce: Hex,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac:   Option<Hash>,
+}
+
+const NONCE_SIZE: usize = 32;
+// Vote extensions are kept in the watermark by their signing hashes
+const EXTENSION_WATERMARK: &str = "vote_extension";
+
+/// Requests signatures of consensus messages and vote extensions from a
+/// signer process. The connection is kept and reconnected when it fails.
+/// Connections are authenticated by the shared key, messages are not
+/// encrypted.
+pub struct RemoteSigner {
+    addr:       SocketAddr,
+    timeout:    Duration,
+    shared_key: Bytes,
+    conn:       Mutex<Option<BufReader<TcpStream>>>,
+}
+
+impl RemoteSigner {
+    pub fn new(addr: SocketAddr, timeout: Duration, shared_key: Bytes) -> Self {
+        RemoteSigner {
+            addr,
+            timeout,
+            shared_key,
+            conn: Mutex::new(None),
+        }
+    }
+
+    fn connect(&self) -> std::io::Result<BufReader<TcpStream>> {
+        let stream = TcpStream::connect_timeout(&self.addr, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.set_nodelay(true)?;
+        let mut conn = BufReader::new(stream);
+
+        let signer_nonce = read_hello(&mut conn)?.nonce.as_bytes();
+        let nonce = random_nonce();
+        write_line(conn.get_mut(), &Hello {
+            nonce: Hex::from_bytes(nonce.clone()),
+            mac:   Some(handshake_mac(
+                &self.shared_key,
+                "node",
+                &signer_nonce,
+                &nonce,
+            )),
+        })?;
+
+        let expected = handshake_mac(&self.shared_key, "signer", &signer_nonce, &nonce);
+        match read_hello(&mut conn)?.mac {
+            Some(mac) if mac == expected => Ok(conn),
+            _ => Err(handshake_error("signer doesn't know the shared key")),
+        }
+    }
+
+    fn request(&self, line: &str) -> std::io::Result<String> {
+        let mut conn = self.conn.lock();
+        if conn.is_none() {
+            *conn = Some(self.connect()?);
+        }
+        let stream = conn.as_mut().expect("connected");
+
+        stream.get_mut().write_all(line.as_bytes())?;
+        let mut resp = String::new();
+        if stream.read_line(&mut resp)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(resp)
+    }
+
+    fn call(&self, req: &SignRequest) -> ProtocolResult<Bytes> {
+        let line = serde_json::to_string(req).map_err(KeystoreError::Json)? + "\n";
+
+        // Retry once by a new connection, the kept one may be closed by the
+        // signer since the last request
+        let resp = match self.request(&line) {
+            Ok(resp) => resp,
+            Err(_) => {
+                self.conn.lock().take();
+                self.request(&line).map_err(|e| {
+                    self.conn.lock().take();
+                    KeystoreError::Remote(format!("{} {:?}", self.addr, e))
+                })?
+            }
+        };
+
+        let resp: SignResponse = serde_json::from_str(&resp).map_err(KeystoreError::Json)?;
+        match (resp.result, resp.error) {
+            (Some(result), None) => Ok(result.as_bytes()),
+            (_, Some(error)) => Err(KeystoreError::Remote(error).into()),
+            (None, None) => Err(KeystoreError::Remote("empty response".to_owned()).into()),
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    // Hashes are never signed remotely, the signer can't tell what they're of
+    fn sign(&self, key_type: KeyType, _hash: &Hash) -> ProtocolResult<Bytes> {
+        Err(KeystoreError::NoKey(key_type).into())
+    }
+
+    fn sign_consensus(&self, msg: &ConsensusMessage) -> ProtocolResult<Bytes> {
+        self.call(&SignRequest::Consensus(msg.clone()))
+    }
+
+    fn sign_vote_extension(&self, msg: &ExtensionMessage) -> ProtocolResult<Bytes> {
+        self.call(&SignRequest::VoteExtension(msg.clone()))
+    }
+
+    fn bls_pub_key(&self, common_ref: &str) -> ProtocolResult<Bytes> {
+        self.call(&SignRequest::BlsPubKey {
+            common_ref: common_ref.to_owned(),
+        })
+    }
+
+    // Each signature waits up to two timeouts on the signer
+    fn is_blocking(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct SignedVote {
+    height:     u64,
+    round:      u64,
+    block_hash: Hex,
+}
+
+/// Votes of the highest height and round signed by the signer, by vote type,
+/// kept in a file. A vote is signed if it's after the watermark of its type,
+/// or the same as it, so a restarted signer never signs conflicting votes.
+/// Other consensus messages are signed from the height of the watermark.
+/// Vote extensions are signed once a height, the same way.
+pub struct Watermark {
+    path:  PathBuf,
+    votes: Mutex<BTreeMap<String, SignedVote>>,
+}
+
+impl Watermark {
+    /// Load the watermark file, or start one if it doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> ProtocolResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let votes = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).map_err(KeystoreError::Json)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(KeystoreError::Io(e).into()),
+        };
+
+        Ok(Watermark {
+            path,
+            votes: Mutex::new(votes),
+        })
+    }
+
+    /// Sign the message if it passes the watermark, which is saved before
+    /// the signature is returned.
+    fn sign<S: Signer + ?Sized>(
+        &self,
+        signer: &S,
+        msg: &ConsensusMessage,
+    ) -> ProtocolResult<Bytes> {
+        let refuse = KeystoreError::Watermark;
+        let (height, round, vote) = decode_message(&msg.message.as_bytes())
+            .ok_or_else(|| refuse("message is not of consensus".to_owned()))?;
+
+        let mut votes = self.votes.lock();
+        let highest = votes.values().map(|v| v.height).max().unwrap_or(0);
+        if height < highest {
+            return Err(refuse(format!("height {} is below {}", height, highest)).into());
+        }
+
+        let (vote_type, block_hash) = match vote {
+            Some(vote) => vote,
+            None => return signer.sign_consensus(msg),
+        };
+        if let Some(signed) = votes.get(&vote_type) {
+            let conflicting =
+                (height, round) == (signed.height, signed.round) && block_hash != signed.block_hash;
+            if (height, round) < (signed.height, signed.round) || conflicting {
+                return Err(refuse(format!(
+                    "vote {} of {} round {} after {} round {}",
+                    vote_type, height, round, signed.height, signed.round
+                ))
+                .into());
+            }
+        }
+
+        let signature = signer.sign_consensus(msg)?;
+        let mut updated = votes.clone();
+        updated.insert(vote_type, SignedVote {
+            height,
+            round,
+            block_hash,
+        });
+        self.save(&updated)?;
+        *votes = updated;
+        Ok(signature)
+    }
+
+    /// Sign the vote extension if it's of a height after the last one signed,
+    /// or the same as it.
+    fn sign_extension<S: Signer + ?Sized>(
+        &self,
+        signer: &S,
+        msg: &ExtensionMessage,
+    ) -> ProtocolResult<Bytes> {
+        let refuse = KeystoreError::Watermark;
+        let height = msg.extension.height;
+        let signing_hash = Hex::from_bytes(msg.signing_hash().as_bytes());
+
+        let mut votes = self.votes.lock();
+        if let Some(signed) = votes.get(EXTENSION_WATERMARK) {
+            let conflicting = height == signed.height && signing_hash != signed.block_hash;
+            if height < signed.height || conflicting {
+                return Err(refuse(format!(
+                    "vote extension of {} after {}",
+                    height, signed.height
+                ))
+                .into());
+            }
+        }
+
+        let signature = signer.sign_vote_extension(msg)?;
+        let mut updated = votes.clone();
+        updated.insert(EXTENSION_WATERMARK.to_owned(), SignedVote {
+            height,
+            round: 0,
+            block_hash: signing_hash,
+        });
+        self.save(&updated)?;
+        *votes = updated;
+        Ok(signature)
+    }
+
+    fn save(&self, votes: &BTreeMap<String, SignedVote>) -> ProtocolResult<()> {
+        let json = serde_json::to_string(votes).map_err(KeystoreError::Json)?;
+        let tmp = self.path.with_extension("tmp");
+
+        let mut file = fs::File::create(&tmp).map_err(KeystoreError::Io)?;
+        file.write_all(json.as_bytes()).map_err(KeystoreError::Io)?;
+        file.sync_all().map_err(KeystoreError::Io)?;
+        fs::rename(&tmp, &self.path).map_err(KeystoreError::Io)?;
+        Ok(())
+    }
+}
+
+// Messages of overlord are rlp lists starting with the height and round,
+// votes are lists of height, round, vote type and block hash
+fn decode_message(message: &[u8]) -> Option<(u64, u64, Option<(String, Hex)>)> {
+    let rlp = rlp::Rlp::new(message);
+    if !rlp.is_list() {
+        return None;
+    }
+    let height = rlp.val_at::<u64>(0).ok()?;
+    let round = rlp.val_at::<u64>(1).ok()?;
+
+    if rlp.item_count().ok()? != 4 {
+        return Some((height, round, None));
+    }
+    let vote_type = rlp.val_at::<u8>(2).ok()?;
+    let block_hash = rlp.val_at::<Vec<u8>>(3).ok()?;
+    Some((
+        height,
+        round,
+        Some((
+            vote_type.to_string(),
+            Hex::from_bytes(Bytes::from(block_hash)),
+        )),
+    ))
+}
+
+/// Serve the remote signer protocol by the signer, a thread per connection.
+/// Signer processes call it with signers of their keys, such as ones backed
+/// by HSMs. Only nodes knowing the shared key are served, and only bls
+/// signatures of consensus messages and vote extensions passing the
+/// watermark are returned.
+pub fn serve_signer<S: Signer + ?Sized + 'static>(
+    listener: TcpListener,
+    signer: Arc<S>,
+    shared_key: Bytes,
+    watermark: Watermark,
+) -> std::io::Result<()> {
+    let watermark = Arc::new(watermark);
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let signer = Arc::clone(&signer);
+        let watermark = Arc::clone(&watermark);
+        let shared_key = shared_key.clone();
+
+        thread::spawn(move || {
+            let peer = stream.peer_addr().ok();
+            if let Err(e) = serve_conn(stream, &*signer, &shared_key, &watermark) {
+                log::warn!("remote signer: connection {:?} closed {:?}", peer, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn serve_conn<S: Signer + ?Sized>(
+    stream: TcpStream,
+    signer: &S,
+    shared_key: &Bytes,
+    watermark: &Watermark,
+) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let nonce = random_nonce();
+    write_line(&mut writer, &Hello {
+        nonce: Hex::from_bytes(nonce.clone()),
+        mac:   None,
+    })?;
+    let hello = read_hello(&mut reader)?;
+    let node_nonce = hello.nonce.as_bytes();
+    if hello.mac != Some(handshake_mac(shared_key, "node", &nonce, &node_nonce)) {
+        return Err(handshake_error("node doesn't know the shared key"));
+    }
+    write_line(&mut writer, &Hello {
+        nonce: Hex::from_bytes(nonce.clone()),
+        mac:   Some(handshake_mac(shared_key, "signer", &nonce, &node_nonce)),
+    })?;
+
+    for line in reader.lines() {
+        let result = match serde_json::from_str::<SignRequest>(&line?) {
+            Ok(SignRequest::BlsPubKey { common_ref }) => signer.bls_pub_key(&common_ref),
+            Ok(SignRequest::Consensus(msg)) => watermark.sign(signer, &msg),
+            Ok(SignRequest::VoteExtension(msg)) => watermark.sign_extension(signer, &msg),
+            Err(e) => Err(KeystoreError::Remote(format!("invalid request {}", e)).into()),
+        };
+        let resp = match result {
+            Ok(bytes) => SignResponse {
+                result: Some(Hex::from_bytes(bytes)),
+                error:  None,
+            },
+            Err(e) => SignResponse {
+                result: None,
+                error:  Some(e.to_string()),
+            },
+        };
+
+        write_line(&mut writer, &resp)?;
+    }
+    Ok(())
+}
+
+fn random_nonce() -> Bytes {
+    let mut nonce = [0u8; NONCE_SIZE];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    Bytes::from(nonce.to_vec())
+}
+
+// Keccak isn't extendable by length, the key prefixed hash is a mac of the
+// nonces of fixed sizes
+fn handshake_mac(shared_key: &Bytes, side: &str, signer_nonce: &Bytes, node_nonce: &Bytes) -> Hash {
+    Hash::digest(Bytes::from(
+        [
+            shared_key.as_ref(),
+            side.as_bytes(),
+            signer_nonce.as_ref(),
+            node_nonce.as_ref(),
+        ]
+        .concat(),
+    ))
+}
+
+fn handshake_error(reason: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        KeystoreError::Handshake(reason.to_owned()).to_string(),
+    )
+}
+
+fn read_hello<R: BufRead>(reader: &mut R) -> std::io::Result<Hello> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+
+    let hello: Hello = serde_json::from_str(&line).map_err(|e| handshake_error(&e.to_string()))?;
+    if hello.nonce.as_bytes().len() != NONCE_SIZE {
+        return Err(handshake_error("invalid nonce"));
+    }
+    Ok(hello)
+}
+
+fn write_line<W: Write, T: Serialize>(writer: &mut W, value: &T) -> std::io::Result<()> {
+    let line = serde_json::to_string(value).expect("json of message") + "\n";
+    writer.write_all(line.as_bytes())
+}
@@ -1,19 +1,26 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Mutex;
 use std::{panic, thread};
 
 use backtrace::Backtrace;
 use json::JsonValue;
+use lazy_static::lazy_static;
 use log::LevelFilter;
 use log4rs::append::console::ConsoleAppender;
 use log4rs::append::file::FileAppender;
 use log4rs::config::{Appender, Config, Logger, Root};
 use log4rs::encode::json::JsonEncoder;
 use log4rs::encode::pattern::PatternEncoder;
+use log4rs::Handle;
 
 pub use json::array;
 pub use json::object;
 
+lazy_static! {
+    static ref LOGGER_HANDLE: Mutex<Option<Handle>> = Mutex::new(None);
+}
+
 pub fn init<S: ::std::hash::BuildHasher>(
     filter: String,
     log_to_console: bool,
@@ -25,6 +32,59 @@ pub fn init<S: ::std::hash::BuildHasher>(
 ) {
     setup_panic_logger();
 
+    let config = build_config(
+        filter,
+        log_to_console,
+        console_show_file_and_line,
+        log_to_file,
+        metrics,
+        log_path,
+        modules_level,
+    );
+
+    let handle = log4rs::init_config(config).unwrap();
+    *LOGGER_HANDLE.lock().unwrap() = Some(handle);
+}
+
+/// Replace the config of an initialized logger, takes the same params as
+/// `init`.
+pub fn reload<S: ::std::hash::BuildHasher>(
+    filter: String,
+    log_to_console: bool,
+    console_show_file_and_line: bool,
+    log_to_file: bool,
+    metrics: bool,
+    log_path: PathBuf,
+    modules_level: HashMap<String, String, S>,
+) -> Result<(), String> {
+    let config = build_config(
+        filter,
+        log_to_console,
+        console_show_file_and_line,
+        log_to_file,
+        metrics,
+        log_path,
+        modules_level,
+    );
+
+    match LOGGER_HANDLE.lock().unwrap().as_ref() {
+        Some(handle) => {
+            handle.set_config(config);
+            Ok(())
+        }
+        None => Err("logger is not initialized".to_owned()),
+    }
+}
+
+fn build_config<S: ::std::hash::BuildHasher>(
+    filter: String,
+    log_to_console: bool,
+    console_show_file_and_line: bool,
+    log_to_file: bool,
+    metrics: bool,
+    log_path: PathBuf,
+    modules_level: HashMap<String, String, S>,
+) -> Config {
     let console = ConsoleAppender::builder()
         .encoder(Box::new(PatternEncoder::new(
             if console_show_file_and_line {
@@ -76,9 +136,7 @@ pub fn init<S: ::std::hash::BuildHasher>(
             .build(module, convert_level(&level));
         config_builder = config_builder.logger(module_logger);
     }
-    let config = config_builder.build(root).unwrap();
-
-    log4rs::init_config(config).unwrap();
+    config_builder.build(root).unwrap()
 }
 
 fn convert_level(level: &str) -> LevelFilter {
@@ -1,7 +1,245 @@
+mod memory;
+
+use std::time::Instant;
+
+use lazy_static::lazy_static;
+use prometheus::core::Collector;
+use prometheus::{Encoder, Registry, TextEncoder};
+
+pub use prometheus::{
+    exponential_buckets, Error as MetricsError, Histogram, HistogramOpts, HistogramTimer,
+    HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+};
+
+pub use memory::{MemoryAccount, MemoryReservation};
+
+/// The registry of all metrics exported by the `/metrics` endpoint. Besides
+/// the built-in metrics below, services and other modules can register their
+/// own collectors through it.
+pub trait MetricsRegistry: Send + Sync {
+    fn register(&self, collector: Box<dyn Collector>) -> Result<(), MetricsError>;
+
+    /// Encode all registered metrics in the prometheus text format.
+    fn encode(&self) -> Result<String, MetricsError>;
+}
+
+pub struct PrometheusRegistry {
+    inner: Registry,
+}
+
+impl PrometheusRegistry {
+    fn new() -> Self {
+        PrometheusRegistry {
+            inner: Registry::new_custom(Some("muta".to_owned()), None)
+                .expect("muta is a valid prefix"),
+        }
+    }
+}
+
+impl MetricsRegistry for PrometheusRegistry {
+    fn register(&self, collector: Box<dyn Collector>) -> Result<(), MetricsError> {
+        self.inner.register(collector)
+    }
+
+    fn encode(&self) -> Result<String, MetricsError> {
+        let mut buf = vec![];
+        TextEncoder::new().encode(&self.inner.gather(), &mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+}
+
+pub fn registry() -> &'static dyn MetricsRegistry {
+    &*REGISTRY
+}
+
+/// Seconds elapsed since the giving instant, used to observe histograms.
+pub fn duration_secs(start: Instant) -> f64 {
+    let elapsed = start.elapsed();
+    elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1_000_000_000.0
+}
+
+fn register<C: Collector + Clone + 'static>(collector: C) -> C {
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("built-in metrics are registered once");
+    collector
+}
+
+fn int_gauge(name: &str, help: &str) -> IntGauge {
+    register(IntGauge::new(name, help).expect("valid metric"))
+}
+
+fn int_gauge_vec(name: &str, help: &str, labels: &[&str]) -> IntGaugeVec {
+    register(IntGaugeVec::new(Opts::new(name, help), labels).expect("valid metric"))
+}
+
+fn int_counter(name: &str, help: &str) -> IntCounter {
+    register(IntCounter::new(name, help).expect("valid metric"))
+}
+
+fn int_counter_vec(name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    register(IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric"))
+}
+
+fn histogram(name: &str, help: &str, buckets: Vec<f64>) -> Histogram {
+    register(
+        Histogram::with_opts(HistogramOpts::new(name, help).buckets(buckets))
+            .expect("valid metric"),
+    )
+}
+
+fn histogram_vec(name: &str, help: &str, labels: &[&str]) -> HistogramVec {
+    register(HistogramVec::new(HistogramOpts::new(name, help), labels).expect("valid metric"))
+}
+
+lazy_static! {
+    static ref REGISTRY: PrometheusRegistry = PrometheusRegistry::new();
+
+    // mempool
+    pub static ref MEMPOOL_SIZE: IntGauge =
+        int_gauge("mempool_size", "Number of transactions in mempool");
+    pub static ref MEMPOOL_REBROADCAST_TXS: IntCounter = int_counter(
+        "mempool_rebroadcast_txs",
+        "Number of pending transactions announced again"
+    );
+    pub static ref MEMPOOL_INSERT_SECONDS: Histogram = histogram(
+        "mempool_insert_seconds",
+        "Latency of inserting a transaction into mempool",
+        exponential_buckets(0.0001, 2.0, 16).expect("valid buckets"),
+    );
+
+    // consensus
+    pub static ref CONSENSUS_HEIGHT: IntGauge =
+        int_gauge("consensus_height", "Height of the latest committed block");
+    pub static ref CONSENSUS_ROUND_SECONDS: Histogram = histogram(
+        "consensus_round_seconds",
+        "Duration between two committed blocks",
+        exponential_buckets(0.1, 2.0, 12).expect("valid buckets"),
+    );
+    pub static ref CONSENSUS_VIEW_CHANGE_TOTAL: IntCounter = int_counter(
+        "consensus_view_change_total",
+        "Number of rounds which failed to commit a block"
+    );
+
+    // executor
+    pub static ref EXECUTOR_CYCLES_PER_BLOCK: Histogram = histogram(
+        "executor_cycles_per_block",
+        "Cycles used by the transactions of a block",
+        exponential_buckets(1000.0, 4.0, 12).expect("valid buckets"),
+    );
+    pub static ref EXECUTOR_SECONDS: Histogram = histogram(
+        "executor_seconds",
+        "Duration of executing a block",
+        exponential_buckets(0.001, 2.0, 16).expect("valid buckets"),
+    );
+    pub static ref EXECUTOR_TRIE_CACHE_TOTAL: IntCounterVec = int_counter_vec(
+        "executor_trie_cache_total",
+        "Number of trie node reads by cache result",
+        &["result"]
+    );
+    pub static ref EXECUTOR_TRIE_CACHE_BYTES: IntGauge = int_gauge(
+        "executor_trie_cache_bytes",
+        "Bytes of trie nodes in cache"
+    );
+
+    // storage
+    pub static ref STORAGE_READ_SECONDS: HistogramVec = histogram_vec(
+        "storage_read_seconds",
+        "Latency of storage reads",
+        &["category"]
+    );
+    pub static ref STORAGE_WRITE_SECONDS: HistogramVec = histogram_vec(
+        "storage_write_seconds",
+        "Latency of storage writes",
+        &["category"]
+    );
+
+    // network
+    pub static ref NETWORK_CONNECTED_PEERS: IntGauge =
+        int_gauge("network_connected_peers", "Number of connected peers");
+    pub static ref NETWORK_SENT_BYTES: IntCounter =
+        int_counter("network_sent_bytes", "Bytes of messages sent to peers");
+    pub static ref NETWORK_RECEIVED_BYTES: IntCounter =
+        int_counter("network_received_bytes", "Bytes of messages received from peers");
+    pub static ref NETWORK_LANE_SENT_BYTES: IntCounterVec = int_counter_vec(
+        "network_lane_sent_bytes",
+        "Bytes of messages sent to peers by traffic lane",
+        &["lane"]
+    );
+    pub static ref NETWORK_THROTTLED_BYTES: IntCounterVec = int_counter_vec(
+        "network_throttled_bytes",
+        "Bytes of messages not sent to peers over bandwidth by traffic",
+        &["traffic"]
+    );
+    pub static ref NETWORK_LANE_RECEIVED_BYTES: IntCounterVec = int_counter_vec(
+        "network_lane_received_bytes",
+        "Bytes of messages received from peers by traffic lane",
+        &["lane"]
+    );
+    pub static ref NETWORK_DROPPED_OVERSIZED_MESSAGES: IntCounterVec = int_counter_vec(
+        "network_dropped_oversized_messages",
+        "Number of received messages dropped for exceeding the size limit of endpoint",
+        &["endpoint"]
+    );
+    pub static ref NETWORK_EVICTED_SLOW_PEERS: IntCounter = int_counter(
+        "network_evicted_slow_peers",
+        "Number of sessions evicted for sending too slow"
+    );
+
+    // api
+    pub static ref API_REQUEST_SECONDS: HistogramVec = histogram_vec(
+        "api_request_seconds",
+        "Latency of api requests",
+        &["uri"]
+    );
+    pub static ref API_REQUEST_TOTAL: IntCounterVec = int_counter_vec(
+        "api_request_total",
+        "Number of api requests",
+        &["uri", "status"]
+    );
+    pub static ref API_QUERY_CACHE_TOTAL: IntCounterVec = int_counter_vec(
+        "api_query_cache_total",
+        "Number of cached service queries by cache result",
+        &["service", "result"]
+    );
+
+    // memory
+    pub static ref MEMORY_USED_BYTES: IntGaugeVec = int_gauge_vec(
+        "memory_used_bytes",
+        "Estimated bytes held by subsystem",
+        &["subsystem"]
+    );
+    pub static ref MEMORY_CAP_BYTES: IntGaugeVec = int_gauge_vec(
+        "memory_cap_bytes",
+        "Hard cap of bytes held by subsystem, zero means no cap",
+        &["subsystem"]
+    );
+    pub static ref MEMORY_SHED_TOTAL: IntCounterVec = int_counter_vec(
+        "memory_shed_total",
+        "Number of reservations rejected for exceeding the memory cap of subsystem",
+        &["subsystem"]
+    );
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn test_encode_registered_metrics() {
+        MEMPOOL_SIZE.set(3);
+
+        let counter = IntCounter::new("test_counter", "A counter registered by test").unwrap();
+        registry().register(Box::new(counter.clone())).unwrap();
+        counter.inc();
+
+        let text = registry().encode().unwrap();
+        assert!(text.contains("muta_mempool_size 3"));
+        assert!(text.contains("muta_test_counter 1"));
+    }
 }
@@ -0,0 +1,168 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::{IntCounter, IntGauge, MEMORY_CAP_BYTES, MEMORY_SHED_TOTAL, MEMORY_USED_BYTES};
+
+/// Bytes held by a subsystem, reported by `memory_used_bytes` with the
+/// subsystem label. Data which can be dropped is reserved by `try_reserve`,
+/// it fails once the hard cap would be exceeded, so that the subsystem sheds
+/// the data instead of growing till out of memory. Sizes are estimated by
+/// subsystems, allocator overhead is not counted.
+#[derive(Clone)]
+pub struct MemoryAccount {
+    inner: Arc<AccountInner>,
+}
+
+struct AccountInner {
+    used:       AtomicUsize,
+    // Zero means no cap
+    cap:        AtomicUsize,
+    used_gauge: IntGauge,
+    cap_gauge:  IntGauge,
+    shed_total: IntCounter,
+}
+
+impl MemoryAccount {
+    pub fn new(subsystem: &str) -> Self {
+        let used_gauge = MEMORY_USED_BYTES.with_label_values(&[subsystem]);
+        used_gauge.set(0);
+
+        MemoryAccount {
+            inner: Arc::new(AccountInner {
+                used: AtomicUsize::new(0),
+                cap: AtomicUsize::new(0),
+                used_gauge,
+                cap_gauge: MEMORY_CAP_BYTES.with_label_values(&[subsystem]),
+                shed_total: MEMORY_SHED_TOTAL.with_label_values(&[subsystem]),
+            }),
+        }
+    }
+
+    /// Set the hard cap, zero removes it. Bytes already reserved are kept if
+    /// the new cap is smaller.
+    pub fn set_cap(&self, cap: usize) {
+        self.inner.cap.store(cap, Ordering::SeqCst);
+        self.inner.cap_gauge.set(cap as i64);
+    }
+
+    pub fn cap(&self) -> usize {
+        self.inner.cap.load(Ordering::SeqCst)
+    }
+
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::SeqCst)
+    }
+
+    /// Reserve bytes unless the cap would be exceeded, they are released when
+    /// the reservation is dropped.
+    pub fn try_reserve(&self, bytes: usize) -> Option<MemoryReservation> {
+        let cap = self.cap();
+        let mut used = self.used();
+
+        loop {
+            let next = used.saturating_add(bytes);
+            if cap != 0 && next > cap {
+                self.inner.shed_total.inc();
+                return None;
+            }
+
+            match self
+                .inner
+                .used
+                .compare_exchange(used, next, Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => break,
+                Err(actual) => used = actual,
+            }
+        }
+
+        self.inner.used_gauge.add(bytes as i64);
+        Some(MemoryReservation {
+            account: self.clone(),
+            bytes,
+        })
+    }
+
+    /// Reserve bytes regardless of the cap, for data which can't be shed.
+    pub fn reserve(&self, bytes: usize) -> MemoryReservation {
+        self.inner.used.fetch_add(bytes, Ordering::SeqCst);
+        self.inner.used_gauge.add(bytes as i64);
+
+        MemoryReservation {
+            account: self.clone(),
+            bytes,
+        }
+    }
+
+    /// Report the bytes of a subsystem which bounds its size by itself, it
+    /// should not be mixed with reservations.
+    pub fn set_used(&self, bytes: usize) {
+        self.inner.used.store(bytes, Ordering::SeqCst);
+        self.inner.used_gauge.set(bytes as i64);
+    }
+
+    fn release(&self, bytes: usize) {
+        self.inner.used.fetch_sub(bytes, Ordering::SeqCst);
+        self.inner.used_gauge.sub(bytes as i64);
+    }
+}
+
+impl fmt::Debug for MemoryAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryAccount")
+            .field("used", &self.used())
+            .field("cap", &self.cap())
+            .finish()
+    }
+}
+
+/// Bytes reserved from a `MemoryAccount`, released on drop.
+#[derive(Debug)]
+pub struct MemoryReservation {
+    account: MemoryAccount,
+    bytes:   usize,
+}
+
+impl MemoryReservation {
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.account.release(self.bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryAccount;
+
+    #[test]
+    fn test_reserve_under_cap() {
+        let account = MemoryAccount::new("test_reserve");
+        account.set_cap(100);
+
+        let first = account.try_reserve(60).unwrap();
+        assert!(account.try_reserve(50).is_none());
+        let second = account.try_reserve(40).unwrap();
+        assert_eq!(account.used(), 100);
+
+        drop(first);
+        assert_eq!(account.used(), 40);
+
+        // Data that can't be shed goes over the cap
+        let forced = account.reserve(100);
+        assert_eq!(account.used(), 140);
+        assert!(account.try_reserve(1).is_none());
+
+        drop(forced);
+        drop(second);
+        assert_eq!(account.used(), 0);
+
+        account.set_cap(0);
+        assert!(account.try_reserve(usize::max_value()).is_some());
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use lru::LruCache;
+use parking_lot::Mutex;
+
+use common_metrics::API_QUERY_CACHE_TOTAL;
+use protocol::traits::{CommitListener, ServiceResponse};
+use protocol::types::{Address, Hash};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryKey {
+    pub state_root:   Hash,
+    pub method:       String,
+    pub payload:      String,
+    // Reads may depend on the caller, and fail by out of cycles
+    pub caller:       Address,
+    pub cycles_limit: u64,
+}
+
+struct ServiceCache {
+    // Height of the latest state root seen, entries are of it only
+    height:    u64,
+    responses: LruCache<QueryKey, ServiceResponse<String>>,
+}
+
+/// Responses of read queries of services with a cache, by the max number of
+/// responses kept of each service. Only queries of the latest height are
+/// cached, responses of former heights are cleared once a newer block is
+/// committed, or queried first.
+pub struct QueryCache {
+    services: HashMap<String, Mutex<ServiceCache>>,
+}
+
+impl QueryCache {
+    pub fn new(caps: HashMap<String, usize>) -> Self {
+        let services = caps
+            .into_iter()
+            .filter(|(_, cap)| *cap > 0)
+            .map(|(service, cap)| {
+                let cache = ServiceCache {
+                    height:    0,
+                    responses: LruCache::new(cap),
+                };
+                (service, Mutex::new(cache))
+            })
+            .collect();
+
+        QueryCache { services }
+    }
+
+    pub fn get(
+        &self,
+        service: &str,
+        height: u64,
+        key: &QueryKey,
+    ) -> Option<ServiceResponse<String>> {
+        let mut cache = self.services.get(service)?.lock();
+        cache.rollover(height);
+
+        let resp = cache.responses.get(key).cloned();
+        let result = if resp.is_some() { "hit" } else { "miss" };
+        API_QUERY_CACHE_TOTAL
+            .with_label_values(&[service, result])
+            .inc();
+        resp
+    }
+
+    pub fn insert(&self, service: &str, height: u64, key: QueryKey, resp: ServiceResponse<String>) {
+        if let Some(cache) = self.services.get(service) {
+            let mut cache = cache.lock();
+            // Committed blocks are not cached again once a newer one is seen
+            if height == cache.height {
+                cache.responses.put(key, resp);
+            }
+        }
+    }
+}
+
+impl ServiceCache {
+    fn rollover(&mut self, height: u64) {
+        if height > self.height {
+            self.height = height;
+            self.responses.clear();
+        }
+    }
+}
+
+impl CommitListener for QueryCache {
+    fn on_commit(&self, height: u64) {
+        for cache in self.services.values() {
+            cache.lock().rollover(height);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use protocol::traits::{CommitListener, ServiceResponse};
+    use protocol::types::{Address, Hash};
+
+    use super::{QueryCache, QueryKey};
+
+    fn key(payload: &str) -> QueryKey {
+        QueryKey {
+            state_root:   Hash::from_empty(),
+            method:       "get_balance".to_owned(),
+            payload:      payload.to_owned(),
+            caller:       Address::default(),
+            cycles_limit: 100,
+        }
+    }
+
+    fn resp(data: &str) -> ServiceResponse<String> {
+        ServiceResponse::<String>::from_succeed(data.to_owned())
+    }
+
+    fn cache() -> QueryCache {
+        let mut caps = HashMap::new();
+        caps.insert("asset".to_owned(), 2);
+        caps.insert("metadata".to_owned(), 0);
+        let cache = QueryCache::new(caps);
+        cache.on_commit(1);
+        cache
+    }
+
+    #[test]
+    fn test_hit_and_miss() {
+        let cache = cache();
+        assert_eq!(cache.get("asset", 1, &key("a")), None);
+
+        cache.insert("asset", 1, key("a"), resp("1"));
+        assert_eq!(cache.get("asset", 1, &key("a")), Some(resp("1")));
+        assert_eq!(cache.get("asset", 1, &key("b")), None);
+
+        // Least recently used responses are evicted beyond the cap
+        cache.insert("asset", 1, key("b"), resp("2"));
+        cache.get("asset", 1, &key("a"));
+        cache.insert("asset", 1, key("c"), resp("3"));
+        assert_eq!(cache.get("asset", 1, &key("b")), None);
+        assert_eq!(cache.get("asset", 1, &key("a")), Some(resp("1")));
+    }
+
+    #[test]
+    fn test_services_not_cached() {
+        let cache = cache();
+
+        for service in ["metadata", "bridge"].iter() {
+            cache.insert(service, 1, key("a"), resp("1"));
+            assert_eq!(cache.get(service, 1, &key("a")), None);
+        }
+    }
+
+    #[test]
+    fn test_height_rollover() {
+        let cache = cache();
+        cache.insert("asset", 1, key("a"), resp("1"));
+
+        // Responses of former heights are never cached again
+        assert_eq!(cache.get("asset", 2, &key("a")), None);
+        cache.insert("asset", 1, key("a"), resp("1"));
+        assert_eq!(cache.get("asset", 2, &key("a")), None);
+        assert_eq!(cache.get("asset", 1, &key("a")), None);
+
+        cache.insert("asset", 2, key("a"), resp("2"));
+        assert_eq!(cache.get("asset", 2, &key("a")), Some(resp("2")));
+    }
+
+    #[test]
+    fn test_invalidate_on_commit() {
+        let cache = cache();
+        cache.insert("asset", 1, key("a"), resp("1"));
+
+        cache.on_commit(2);
+        assert_eq!(cache.get("asset", 1, &key("a")), None);
+        cache.insert("asset", 1, key("a"), resp("1"));
+        assert_eq!(cache.get("asset", 1, &key("a")), None);
+
+        // Blocks synced behind the latest seen one clear nothing
+        cache.insert("asset", 2, key("a"), resp("2"));
+        cache.on_commit(1);
+        assert_eq!(cache.get("asset", 2, &key("a")), Some(resp("2")));
+    }
+}
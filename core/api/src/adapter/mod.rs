@@ -1,3 +1,6 @@
+mod cache;
+
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::sync::Arc;
 
@@ -6,11 +9,17 @@ use derive_more::Display;
 use async_trait::async_trait;
 use protocol::traits::ExecutorFactory;
 use protocol::traits::{
-    APIAdapter, Context, ExecutorParams, MemPool, ServiceMapping, ServiceResponse, Storage,
+    executed_state_root, APIAdapter, CommitListener, Context, ExecutorParams, MemPool,
+    ServiceMapping, ServiceResponse, Storage,
+};
+use protocol::types::{
+    Address, Block, Bytes, Hash, Metadata, Proof, Receipt, SignedTransaction, StateProof,
+    TransactionRequest, TxTrace,
 };
-use protocol::types::{Address, Block, Hash, Receipt, SignedTransaction, TransactionRequest};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
+use cache::{QueryCache, QueryKey};
+
 #[derive(Debug, Display)]
 pub enum APIError {
     #[display(
@@ -19,6 +28,42 @@ pub enum APIError {
         expect
     )]
     UnExecedError { expect: u64, real: u64 },
+
+    #[display(fmt = "State root of height {:?} is not found", height)]
+    NotFoundStateRoot { height: u64 },
+
+    #[display(fmt = "Decode metadata {:?}", _0)]
+    DecodeMetadata(serde_json::Error),
+
+    #[display(fmt = "Invalid cursor {:?}", _0)]
+    InvalidCursor(String),
+
+    #[display(
+        fmt = "Height range {:?} to {:?} is empty or exceeds {:?} blocks",
+        from,
+        to,
+        limit
+    )]
+    HeightRange { from: u64, to: u64, limit: u64 },
+
+    #[display(
+        fmt = "Height {:?} is above snapshot height {:?} of the request",
+        height,
+        snapshot
+    )]
+    AboveSnapshot { height: u64, snapshot: u64 },
+
+    #[display(fmt = "Blocks are sealed by consensus, only dev chains seal on demand")]
+    SealUnsupported,
+
+    #[display(fmt = "Query responses are not signed by this node")]
+    UnsignedQuery,
+
+    #[display(fmt = "Tracing transactions is not allowed by limits of the request")]
+    TraceNotAllowed,
+
+    #[display(fmt = "Signed queries are not allowed by limits of the request")]
+    SignedQueryNotAllowed,
 }
 
 impl std::error::Error for APIError {}
@@ -28,6 +73,7 @@ pub struct DefaultAPIAdapter<EF, M, S, DB, Mapping> {
     storage:         Arc<S>,
     trie_db:         Arc<DB>,
     service_mapping: Arc<Mapping>,
+    query_cache:     Option<Arc<QueryCache>>,
 
     pin_ef: PhantomData<EF>,
 }
@@ -51,9 +97,27 @@ impl<
             storage,
             trie_db,
             service_mapping,
+            query_cache: None,
             pin_ef: PhantomData,
         }
     }
+
+    /// Cache responses of read queries of services, by the max number of
+    /// responses kept of each service. Services not listed are not cached.
+    pub fn query_cache(mut self, caps: HashMap<String, usize>) -> Self {
+        if !caps.is_empty() {
+            self.query_cache = Some(Arc::new(QueryCache::new(caps)));
+        }
+        self
+    }
+
+    /// Listener clearing the query cache of former heights on commit, none
+    /// if there's no cache.
+    pub fn commit_listener(&self) -> Option<Arc<dyn CommitListener>> {
+        self.query_cache
+            .clone()
+            .map(|cache| cache as Arc<dyn CommitListener>)
+    }
 }
 
 #[async_trait]
@@ -86,6 +150,16 @@ impl<
         Ok(block)
     }
 
+    async fn get_commit_proof(&self, _ctx: Context, height: u64) -> ProtocolResult<Proof> {
+        let proof = self.storage.get_latest_proof().await?;
+        if proof.height == height {
+            return Ok(proof);
+        }
+
+        let next = self.storage.get_block_by_height(height + 1).await?;
+        Ok(next.header.proof)
+    }
+
     async fn get_receipt_by_tx_hash(
         &self,
         _ctx: Context,
@@ -106,6 +180,25 @@ impl<
         ))
     }
 
+    async fn get_receipts_by_height(
+        &self,
+        _ctx: Context,
+        height: u64,
+    ) -> ProtocolResult<Vec<Receipt>> {
+        let block = self.storage.get_block_by_height(height).await?;
+        let tx_hashes = block.ordered_tx_hashes;
+        let receipts = self.storage.get_receipts(tx_hashes.clone()).await?;
+
+        if receipts.len() != tx_hashes.len() {
+            let exec_height = self.storage.get_latest_block().await?.header.exec_height;
+            return Err(api_error(APIError::UnExecedError {
+                real:   exec_height,
+                expect: height,
+            }));
+        }
+        Ok(receipts)
+    }
+
     async fn get_transaction_by_hash(
         &self,
         _: Context,
@@ -127,6 +220,19 @@ impl<
     ) -> ProtocolResult<ServiceResponse<String>> {
         let block = self.get_block_by_height(ctx.clone(), Some(height)).await?;
 
+        let cache_key = self.query_cache.as_ref().map(|_| QueryKey {
+            state_root: block.header.state_root.clone(),
+            method: method.clone(),
+            payload: payload.clone(),
+            caller: caller.clone(),
+            cycles_limit,
+        });
+        if let (Some(cache), Some(key)) = (self.query_cache.as_ref(), cache_key.as_ref()) {
+            if let Some(resp) = cache.get(&service_name, height, key) {
+                return Ok(resp);
+            }
+        }
+
         let executor = EF::from_root(
             block.header.state_root.clone(),
             Arc::clone(&self.trie_db),
@@ -139,11 +245,99 @@ impl<
             height,
             timestamp: block.header.timestamp,
             cycles_limit,
+            proposer: block.header.proposer,
+            vote_extensions: vec![],
+            extra_data: Bytes::new(),
         };
-        executor.read(&params, &caller, cycles_price, &TransactionRequest {
-            service_name,
+        let resp = executor.read(&params, &caller, cycles_price, &TransactionRequest {
+            service_name: service_name.clone(),
             method,
             payload,
-        })
+        })?;
+
+        if let (Some(cache), Some(key)) = (self.query_cache.as_ref(), cache_key) {
+            cache.insert(&service_name, height, key, resp.clone());
+        }
+        Ok(resp)
+    }
+
+    async fn get_state_proof(
+        &self,
+        ctx: Context,
+        height: u64,
+        service_name: String,
+        key: Bytes,
+    ) -> ProtocolResult<StateProof> {
+        let block = self.get_block_by_height(ctx, Some(height)).await?;
+
+        let executor = EF::from_root(
+            block.header.state_root,
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            Arc::clone(&self.service_mapping),
+        )?;
+        executor.get_state_proof(&service_name, &key)
     }
+
+    async fn trace_transaction(&self, ctx: Context, tx_hash: Hash) -> ProtocolResult<TxTrace> {
+        let receipt = self.get_receipt_by_tx_hash(ctx.clone(), tx_hash).await?;
+        let block = self.get_block_by_height(ctx, Some(receipt.height)).await?;
+        let parent_height = receipt.height.saturating_sub(1);
+        let parent_state_root = executed_state_root(&*self.storage, parent_height)
+            .await?
+            .ok_or_else(|| {
+                api_error(APIError::NotFoundStateRoot {
+                    height: parent_height,
+                })
+            })?;
+        let txs = self
+            .storage
+            .get_transactions(block.ordered_tx_hashes.clone())
+            .await?;
+
+        let mut executor = EF::from_root(
+            parent_state_root.clone(),
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            Arc::clone(&self.service_mapping),
+        )?;
+
+        // Same cycles limit as consensus, it's from metadata of the parent state
+        let caller = Address::default();
+        let metadata_params = ExecutorParams {
+            state_root:      parent_state_root.clone(),
+            height:          parent_height,
+            timestamp:       block.header.timestamp,
+            cycles_limit:    u64::max_value(),
+            proposer:        caller.clone(),
+            vote_extensions: vec![],
+            extra_data:      Bytes::new(),
+        };
+        let resp = executor.read(&metadata_params, &caller, 1, &TransactionRequest {
+            service_name: "metadata".to_owned(),
+            method:       "get_metadata".to_owned(),
+            payload:      "".to_owned(),
+        })?;
+        let metadata: Metadata = serde_json::from_str(&resp.succeed_data)
+            .map_err(|e| api_error(APIError::DecodeMetadata(e)))?;
+
+        let params = ExecutorParams {
+            state_root:      parent_state_root,
+            height:          block.header.height,
+            timestamp:       block.header.timestamp,
+            cycles_limit:    metadata.cycles_limit,
+            proposer:        block.header.proposer.clone(),
+            vote_extensions: vec![],
+            extra_data:      Bytes::new(),
+        };
+        executor.trace(&params, &txs, &receipt.tx_hash)
+    }
+
+    async fn seal_block(&self, _ctx: Context) -> ProtocolResult<Block> {
+        Err(api_error(APIError::SealUnsupported))
+    }
+}
+
+pub(crate) fn api_error(err: APIError) -> ProtocolError {
+    ProtocolError::new(ProtocolErrorKind::API, Box::new(err))
 }
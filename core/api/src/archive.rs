@@ -0,0 +1,205 @@
+use protocol::traits::{APIAdapter, Context};
+use protocol::types::{Event, Hash, Receipt};
+use protocol::ProtocolResult;
+
+use crate::adapter::{api_error, APIError};
+
+/// Server side limits of the archive queries `getReceipts` and `getEvents`,
+/// of `traceTransaction` re-executing transactions and of
+/// `signedQueryService` signing responses. Indexers doing backfill can be
+/// given higher ones by api keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveLimits {
+    // Items returned by one page at most
+    pub max_page_size:    u64,
+    // Blocks scanned by one `getEvents` query at most
+    pub max_height_range: u64,
+    // Whether `traceTransaction` is served
+    pub trace:            bool,
+    // Whether `signedQueryService` is served
+    pub signed_query:     bool,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_page_size:    100,
+            max_height_range: 1000,
+            trace:            false,
+            signed_query:     false,
+        }
+    }
+}
+
+impl ArchiveLimits {
+    fn page_size(&self, limit: Option<i32>) -> u64 {
+        match limit {
+            Some(limit) if limit > 0 => (limit as u64).min(self.max_page_size),
+            _ => self.max_page_size,
+        }
+    }
+}
+
+pub struct EventRecord {
+    pub height:  u64,
+    pub tx_hash: Hash,
+    // Index of the event in its receipt
+    pub index:   u64,
+    // Method of the transaction emitting the event
+    pub method:  String,
+    pub event:   Event,
+}
+
+// Position of the next item to return, encoded as `height:tx_index` for
+// receipts and `height:tx_index:event_index` for events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cursor {
+    height:      u64,
+    tx_index:    u64,
+    event_index: u64,
+}
+
+impl Cursor {
+    fn parse(cursor: &str, parts: usize) -> ProtocolResult<Self> {
+        let numbers = cursor
+            .split(':')
+            .map(|n| n.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| api_error(APIError::InvalidCursor(cursor.to_owned())))?;
+        if numbers.len() != parts {
+            return Err(api_error(APIError::InvalidCursor(cursor.to_owned())));
+        }
+
+        Ok(Cursor {
+            height:      numbers[0],
+            tx_index:    numbers[1],
+            event_index: numbers.get(2).cloned().unwrap_or(0),
+        })
+    }
+}
+
+/// Receipts of the block in transaction order, from the cursor on.
+pub async fn get_receipts(
+    adapter: &dyn APIAdapter,
+    limits: &ArchiveLimits,
+    height: u64,
+    cursor: Option<String>,
+    limit: Option<i32>,
+) -> ProtocolResult<(Vec<Receipt>, Option<String>)> {
+    let start = match cursor {
+        Some(cursor) => {
+            let cursor = Cursor::parse(&cursor, 2)?;
+            if cursor.height != height {
+                return Err(api_error(APIError::InvalidCursor(format!(
+                    "{}:{}",
+                    cursor.height, cursor.tx_index
+                ))));
+            }
+            cursor.tx_index as usize
+        }
+        None => 0,
+    };
+    let page_size = limits.page_size(limit) as usize;
+
+    let receipts = adapter
+        .get_receipts_by_height(Context::new(), height)
+        .await?;
+    let end = receipts.len().min(start.saturating_add(page_size));
+    let next_cursor = if end < receipts.len() {
+        Some(format!("{}:{}", height, end))
+    } else {
+        None
+    };
+
+    let page = receipts.into_iter().take(end).skip(start).collect();
+    Ok((page, next_cursor))
+}
+
+/// Events emitted in the blocks from `from_height` to `to_height`, filtered
+/// by service and by the method of the transaction emitting them. Blocks not
+/// executed yet are not scanned.
+pub async fn get_events(
+    adapter: &dyn APIAdapter,
+    limits: &ArchiveLimits,
+    from_height: u64,
+    to_height: u64,
+    service: Option<String>,
+    method: Option<String>,
+    cursor: Option<String>,
+    limit: Option<i32>,
+) -> ProtocolResult<(Vec<EventRecord>, Option<String>)> {
+    if to_height < from_height || to_height - from_height >= limits.max_height_range {
+        return Err(api_error(APIError::HeightRange {
+            from:  from_height,
+            to:    to_height,
+            limit: limits.max_height_range,
+        }));
+    }
+
+    let mut cursor = match cursor {
+        Some(cursor) => Cursor::parse(&cursor, 3)?,
+        None => Cursor {
+            height:      from_height,
+            tx_index:    0,
+            event_index: 0,
+        },
+    };
+    if cursor.height < from_height {
+        return Err(api_error(APIError::InvalidCursor(format!(
+            "{}:{}:{}",
+            cursor.height, cursor.tx_index, cursor.event_index
+        ))));
+    }
+
+    let exec_height = adapter
+        .get_block_by_height(Context::new(), None)
+        .await?
+        .header
+        .exec_height;
+    let to_height = to_height.min(exec_height);
+    let page_size = limits.page_size(limit) as usize;
+    let mut events = vec![];
+
+    while cursor.height <= to_height {
+        let receipts = adapter
+            .get_receipts_by_height(Context::new(), cursor.height)
+            .await?;
+
+        for (tx_index, receipt) in receipts.iter().enumerate().skip(cursor.tx_index as usize) {
+            let skip = if tx_index as u64 == cursor.tx_index {
+                cursor.event_index as usize
+            } else {
+                0
+            };
+            if method.is_some() && method.as_ref() != Some(&receipt.response.method) {
+                continue;
+            }
+
+            for (index, event) in receipt.events.iter().enumerate().skip(skip) {
+                if events.len() == page_size {
+                    let next = format!("{}:{}:{}", cursor.height, tx_index, index);
+                    return Ok((events, Some(next)));
+                }
+                if service.is_some() && service.as_ref() != Some(&event.service) {
+                    continue;
+                }
+
+                events.push(EventRecord {
+                    height:  cursor.height,
+                    tx_hash: receipt.tx_hash.clone(),
+                    index:   index as u64,
+                    method:  receipt.response.method.clone(),
+                    event:   event.clone(),
+                });
+            }
+        }
+
+        cursor = Cursor {
+            height:      cursor.height + 1,
+            tx_index:    0,
+            event_index: 0,
+        };
+    }
+
+    Ok((events, None))
+}
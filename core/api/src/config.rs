@@ -1,4 +1,10 @@
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bytes::Bytes;
+
+use crate::archive::ArchiveLimits;
 
 #[derive(Debug, Clone)]
 pub struct GraphQLConfig {
@@ -6,6 +12,9 @@ pub struct GraphQLConfig {
 
     pub graphql_uri:  String,
     pub graphiql_uri: String,
+    pub metrics_uri:  String,
+    pub health_uri:   String,
+    pub ready_uri:    String,
 
     // Set number of workers to start.
     // By default http server uses number of available logical cpu as threads count.
@@ -18,6 +27,26 @@ pub struct GraphQLConfig {
     // It is used to prevent DOS attacking through memory exhaustion.
     // The default value is 1024 * 1024, which is 1MB.
     pub max_payload_size: usize,
+
+    // The node is ready when it is at most `ready_sync_gap` blocks behind the
+    // best known peer height.
+    pub ready_sync_gap: u64,
+
+    // Prefix of the admin endpoints, such as `{admin_uri}/peers`. They expose
+    // node internals, so they are disabled by default.
+    pub admin_uri:   Option<String>,
+    // Token admin requests carry in the `x-admin-token` header. Without it,
+    // admin endpoints only serve requests from loopback addresses.
+    pub admin_token: Option<String>,
+
+    // Limits of archive queries for requests without a known api key, and
+    // limits of api keys sent in the `x-api-key` header.
+    pub archive_limits: ArchiveLimits,
+    pub api_keys:       HashMap<String, ArchiveLimits>,
+
+    // Secp256k1 private key signing responses of `signedQueryService`, the
+    // query is disabled without it.
+    pub query_signing_key: Option<Bytes>,
 }
 
 impl Default for GraphQLConfig {
@@ -29,9 +58,43 @@ impl Default for GraphQLConfig {
 
             graphql_uri:      "/graphql".to_owned(),
             graphiql_uri:     "/graphiql".to_owned(),
+            metrics_uri:      "/metrics".to_owned(),
+            health_uri:       "/health".to_owned(),
+            ready_uri:        "/ready".to_owned(),
             workers:          num_cpus::get(),
             maxconn:          25000,
             max_payload_size: 1024 * 1024, // 1MB
+            ready_sync_gap:   5,
+            admin_uri:        None,
+            admin_token:      None,
+            archive_limits:   ArchiveLimits::default(),
+            api_keys:         HashMap::new(),
+
+            query_signing_key: None,
+        }
+    }
+}
+
+/// Limits of the graphql interface which can be changed while the server is
+/// running. The `max_payload_size` in `GraphQLConfig` is still the hard limit
+/// of the http server, so a larger value has no effect until restart.
+#[derive(Debug)]
+pub struct GraphQLLimits {
+    max_payload_size: AtomicUsize,
+}
+
+impl GraphQLLimits {
+    pub fn new(max_payload_size: usize) -> Self {
+        Self {
+            max_payload_size: AtomicUsize::new(max_payload_size),
         }
     }
+
+    pub fn max_payload_size(&self) -> usize {
+        self.max_payload_size.load(Ordering::Acquire)
+    }
+
+    pub fn set_max_payload_size(&self, max_payload_size: usize) {
+        self.max_payload_size.store(max_payload_size, Ordering::Release);
+    }
 }
@@ -1,27 +1,50 @@
 pub mod adapter;
+pub mod archive;
 pub mod config;
 mod schema;
+mod snapshot;
+#[cfg(test)]
+mod tests;
 
-use actix_web::{web, App, Error, FromRequest, HttpResponse, HttpServer};
-use futures::executor::block_on;
+use actix_web::error::{ErrorBadRequest, PayloadError};
+use actix_web::{web, App, Error, FromRequest, HttpRequest, HttpResponse, HttpServer};
 use juniper::http::GraphQLRequest;
 use juniper::FieldResult;
 use lazy_static::lazy_static;
+use serde_json::json;
 use std::cmp;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::sync::Arc;
+use tracing::{field, info_span};
+use tracing_futures::Instrument;
 
 use common_crypto::{
-    HashValue, PrivateKey, PublicKey, Secp256k1PrivateKey, Signature, ToPublicKey,
+    HashValue, PrivateKey, PublicKey, Secp256k1PrivateKey, Secp256k1PublicKey, Signature,
+    ToPublicKey,
 };
+use common_metrics::{API_REQUEST_SECONDS, API_REQUEST_TOTAL};
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::{APIAdapter, Context};
+use protocol::traits::{
+    APIAdapter, AdminAdapter, Context, PrivateTxContext, ReadinessAdapter, TraceContext,
+};
+use protocol::ProtocolResult;
 
-use crate::config::GraphQLConfig;
+use crate::adapter::{api_error, APIError};
+use crate::archive::ArchiveLimits;
+use crate::config::{GraphQLConfig, GraphQLLimits};
 use crate::schema::{
-    to_signed_transaction, to_transaction, Address, Block, Bytes, Hash, InputRawTransaction,
-    InputTransactionEncryption, Receipt, ServiceResponse, SignedTransaction, Uint64,
+    to_signed_transaction, to_transaction, Address, Block, Bytes, EventPage, FeatureActivation,
+    HaltStatus, Hash, InputRawTransaction, InputTransactionEncryption, QueryAttestation, Receipt,
+    ReceiptPage, SchemaError, ServiceResponse, SignedServiceResponse, SignedTransaction,
+    StateProof, TxTrace, Uint64,
 };
+use crate::snapshot::SnapshotAdapter;
+
+// Header carrying the api key, which selects archive limits of the request
+const API_KEY_HEADER: &str = "x-api-key";
+// Header carrying the token of admin requests
+const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
 
 lazy_static! {
     static ref GRAPHIQL_HTML: &'static str = include_str!("../source/graphiql.html");
@@ -32,6 +55,19 @@ lazy_static! {
 struct State {
     adapter: Arc<Box<dyn APIAdapter>>,
     schema:  Arc<Schema>,
+    limits:  Arc<GraphQLLimits>,
+
+    readiness:      Arc<dyn ReadinessAdapter>,
+    ready_sync_gap: u64,
+
+    admin:       Arc<dyn AdminAdapter>,
+    admin_token: Option<Arc<String>>,
+
+    // Limits of the request, chosen by its api key
+    archive_limits: ArchiveLimits,
+    api_keys:       Arc<HashMap<String, ArchiveLimits>>,
+
+    query_signer: Option<Arc<Secp256k1PrivateKey>>,
 }
 
 // We define `Query` unit struct here. GraphQL queries will refer to this
@@ -84,6 +120,64 @@ impl Query {
         Ok(Receipt::from(receipt))
     }
 
+    #[graphql(
+        name = "getReceipts",
+        description = "Get receipts of an executed block page by page"
+    )]
+    async fn get_receipts(
+        state_ctx: &State,
+        height: Uint64,
+        cursor: Option<String>,
+        limit: Option<i32>,
+    ) -> FieldResult<ReceiptPage> {
+        let (receipts, next_cursor) = archive::get_receipts(
+            &**state_ctx.adapter,
+            &state_ctx.archive_limits,
+            height.try_into_u64()?,
+            cursor,
+            limit,
+        )
+        .await?;
+
+        Ok(ReceiptPage {
+            receipts: receipts.into_iter().map(Receipt::from).collect(),
+            next_cursor,
+        })
+    }
+
+    #[graphql(
+        name = "getEvents",
+        description = "Get events of executed blocks in a height range page by page. Events \
+                       have no topics of their own, `topic` matches the method of the \
+                       transaction emitting them"
+    )]
+    async fn get_events(
+        state_ctx: &State,
+        from_height: Uint64,
+        to_height: Uint64,
+        service: Option<String>,
+        topic: Option<String>,
+        cursor: Option<String>,
+        limit: Option<i32>,
+    ) -> FieldResult<EventPage> {
+        let (events, next_cursor) = archive::get_events(
+            &**state_ctx.adapter,
+            &state_ctx.archive_limits,
+            from_height.try_into_u64()?,
+            to_height.try_into_u64()?,
+            service,
+            topic,
+            cursor,
+            limit,
+        )
+        .await?;
+
+        Ok(EventPage {
+            events: events.into_iter().map(Into::into).collect(),
+            next_cursor,
+        })
+    }
+
     #[graphql(name = "queryService", description = "query service")]
     async fn query_service(
         state_ctx: &State,
@@ -95,40 +189,187 @@ impl Query {
         method: String,
         payload: String,
     ) -> FieldResult<ServiceResponse> {
-        let height = match height {
-            Some(id) => id.try_into_u64()?,
-            None => {
-                block_on(state_ctx.adapter.get_block_by_height(Context::new(), None))?
-                    .header
-                    .height
-            }
-        };
-        let cycles_limit = match cycles_limit {
-            Some(cycles_limit) => cycles_limit.try_into_u64()?,
-            None => std::u64::MAX,
+        let caller = protocol::types::Address::from_hex(&caller.as_hex())?;
+        let request = protocol::types::TransactionRequest {
+            service_name,
+            method,
+            payload,
         };
 
-        let cycles_price = match cycles_price {
-            Some(cycles_price) => cycles_price.try_into_u64()?,
-            None => 1,
+        let (_, exec_resp) = query(
+            state_ctx,
+            height,
+            cycles_limit,
+            cycles_price,
+            &caller,
+            request,
+        )
+        .await?;
+        Ok(ServiceResponse::from(exec_resp))
+    }
+
+    #[graphql(
+        name = "signedQueryService",
+        description = "query service, the response is signed by the node along with the proof \
+                       of the queried block, only if the node signs queries"
+    )]
+    async fn signed_query_service(
+        state_ctx: &State,
+        height: Option<Uint64>,
+        cycles_limit: Option<Uint64>,
+        cycles_price: Option<Uint64>,
+        caller: Address,
+        service_name: String,
+        method: String,
+        payload: String,
+    ) -> FieldResult<SignedServiceResponse> {
+        // Each response costs a signature of the node
+        if !state_ctx.archive_limits.signed_query {
+            return Err(api_error(APIError::SignedQueryNotAllowed).into());
+        }
+        let signer = state_ctx
+            .query_signer
+            .clone()
+            .ok_or_else(|| api_error(APIError::UnsignedQuery))?;
+        let caller = protocol::types::Address::from_hex(&caller.as_hex())?;
+        let request = protocol::types::TransactionRequest {
+            service_name,
+            method,
+            payload,
         };
 
-        let address = protocol::types::Address::from_hex(&caller.as_hex())?;
+        let (height, exec_resp) = query(
+            state_ctx,
+            height,
+            cycles_limit,
+            cycles_price,
+            &caller,
+            request.clone(),
+        )
+        .await?;
+        let block = state_ctx
+            .adapter
+            .get_block_by_height(Context::new(), Some(height))
+            .await?;
+        let proof = state_ctx
+            .adapter
+            .get_commit_proof(Context::new(), height)
+            .await?;
+        let (_, metadata) = latest_metadata(state_ctx).await?;
+
+        let attestation = protocol::types::QueryAttestation::new(
+            height,
+            block.header.state_root.clone(),
+            &caller,
+            &request,
+            &exec_resp,
+        );
+        let signing = attestation.signing_hash(metadata.signature_domain(height).as_ref());
+        let hash_value = HashValue::try_from(signing.as_bytes().as_ref())?;
+        let signature = signer.sign_message(&hash_value);
+
+        Ok(SignedServiceResponse {
+            response:    ServiceResponse::from(exec_resp),
+            attestation: QueryAttestation {
+                height:      Uint64::from(height),
+                state_root:  Hash::from(attestation.state_root),
+                result_hash: Hash::from(attestation.result_hash),
+                pubkey:      Bytes::from(signer.pub_key().to_bytes()),
+                signature:   Bytes::from(signature.to_bytes()),
+                block:       Bytes::from(block.encode_fixed()?),
+                proof:       Bytes::from(proof.encode_fixed()?),
+            },
+        })
+    }
+
+    #[graphql(
+        name = "getStateProof",
+        description = "Get merkle proof of a fixed codec encoded key in service state"
+    )]
+    async fn get_state_proof(
+        state_ctx: &State,
+        height: Option<Uint64>,
+        service_name: String,
+        key: Bytes,
+    ) -> FieldResult<StateProof> {
+        let height = match height {
+            Some(id) => Some(id.try_into_u64()?),
+            None => None,
+        };
+        let block = state_ctx
+            .adapter
+            .get_block_by_height(Context::new(), height)
+            .await?;
 
-        let exec_resp = state_ctx
+        let proof = state_ctx
             .adapter
-            .query_service(
+            .get_state_proof(
                 Context::new(),
-                height,
-                cycles_limit,
-                cycles_price,
-                address,
+                block.header.height,
                 service_name,
-                method,
-                payload,
+                protocol::Bytes::from(key.to_vec()?),
             )
             .await?;
-        Ok(ServiceResponse::from(exec_resp))
+        Ok(StateProof::new(
+            block.header.height,
+            block.header.state_root,
+            proof,
+        ))
+    }
+
+    #[graphql(
+        name = "traceTransaction",
+        description = "Re-execute an executed transaction, get its calls, storage access, events \
+                       and cycles used step by step"
+    )]
+    async fn trace_transaction(state_ctx: &State, tx_hash: Hash) -> FieldResult<TxTrace> {
+        if !state_ctx.archive_limits.trace {
+            return Err(api_error(APIError::TraceNotAllowed).into());
+        }
+        let hash = protocol::types::Hash::from_hex(&tx_hash.as_hex())?;
+
+        let trace = state_ctx
+            .adapter
+            .trace_transaction(Context::new(), hash)
+            .await?;
+        Ok(TxTrace::from(trace))
+    }
+
+    #[graphql(
+        name = "getHaltStatus",
+        description = "Get emergency halt of the chain, by metadata of the latest block"
+    )]
+    async fn get_halt_status(state_ctx: &State) -> FieldResult<HaltStatus> {
+        let (height, metadata) = latest_metadata(state_ctx).await?;
+
+        Ok(HaltStatus {
+            halted:      metadata.is_halted(height + 1),
+            halt_height: match metadata.halt_height {
+                0 => None,
+                halt_height => Some(Uint64::from(halt_height)),
+            },
+        })
+    }
+
+    #[graphql(
+        name = "getChainSpec",
+        description = "Get features of the chain spec and their activation heights, by metadata \
+                       of the latest block"
+    )]
+    async fn get_chain_spec(state_ctx: &State) -> FieldResult<Vec<FeatureActivation>> {
+        let (height, metadata) = latest_metadata(state_ctx).await?;
+
+        Ok(metadata
+            .chain_spec
+            .activations
+            .into_iter()
+            .map(|activation| FeatureActivation {
+                active:    height + 1 >= activation.height,
+                supported: protocol::types::Feature::from_name(&activation.name).is_some(),
+                height:    Uint64::from(activation.height),
+                name:      activation.name,
+            })
+            .collect())
     }
 }
 
@@ -136,19 +377,20 @@ struct Mutation;
 // Switch to async/await fn https://github.com/graphql-rust/juniper/issues/2
 #[juniper::graphql_object(Context = State)]
 impl Mutation {
-    #[graphql(name = "sendTransaction", description = "send transaction")]
+    #[graphql(
+        name = "sendTransaction",
+        description = "send transaction, it's not broadcast to peers if `broadcast` is false"
+    )]
     async fn send_transaction(
         state_ctx: &State,
         input_raw: InputRawTransaction,
         input_encryption: InputTransactionEncryption,
+        broadcast: Option<bool>,
     ) -> FieldResult<Hash> {
         let stx = to_signed_transaction(input_raw, input_encryption)?;
         let tx_hash = stx.tx_hash.clone();
 
-        state_ctx
-            .adapter
-            .insert_signed_txs(Context::new(), stx)
-            .await?;
+        insert_signed_txs(state_ctx, stx, broadcast.unwrap_or(true)).await?;
 
         Ok(Hash::from(tx_hash))
     }
@@ -161,13 +403,20 @@ impl Mutation {
         state_ctx: &State,
         input_raw: InputRawTransaction,
         input_privkey: Bytes,
+        broadcast: Option<bool>,
     ) -> FieldResult<Hash> {
         let raw_tx = to_transaction(input_raw)?;
         let tx_hash = protocol::types::Hash::digest(raw_tx.encode_fixed()?);
 
         let privkey = Secp256k1PrivateKey::try_from(input_privkey.to_vec()?.as_ref())?;
         let pubkey = privkey.pub_key();
-        let hash_value = HashValue::try_from(tx_hash.as_bytes().as_ref())?;
+        let domain = signature_domain(state_ctx).await?;
+        let signing = protocol::types::signing_hash(
+            domain.as_ref(),
+            protocol::types::TRANSACTION_SIGNATURE_PURPOSE,
+            &tx_hash,
+        );
+        let hash_value = HashValue::try_from(signing.as_bytes().as_ref())?;
         let signature = privkey.sign_message(&hash_value);
 
         let stx = protocol::types::SignedTransaction {
@@ -176,13 +425,126 @@ impl Mutation {
             signature: signature.to_bytes(),
             pubkey:    pubkey.to_bytes(),
         };
-        state_ctx
-            .adapter
-            .insert_signed_txs(Context::new(), stx)
-            .await?;
+        insert_signed_txs(state_ctx, stx, broadcast.unwrap_or(true)).await?;
 
         Ok(Hash::from(tx_hash))
     }
+
+    #[graphql(
+        name = "sealBlock",
+        description = "Seal pending transactions into a block, only dev chains support it"
+    )]
+    async fn seal_block(state_ctx: &State) -> FieldResult<Block> {
+        let block = state_ctx.adapter.seal_block(Context::new()).await?;
+
+        Ok(Block::from(block))
+    }
+}
+
+// Every transaction sent through api starts a new trace, it follows the
+// transaction to mempool and other nodes. Transactions not broadcast are
+// private, they're only packaged by this node.
+async fn insert_signed_txs(
+    state_ctx: &State,
+    stx: protocol::types::SignedTransaction,
+    broadcast: bool,
+) -> ProtocolResult<()> {
+    let mut ctx = Context::new().with_new_trace();
+    if !broadcast {
+        ctx = ctx.with_private_tx();
+    }
+    let span = info_span!(
+        "api_send_transaction",
+        trace_id = field::debug(ctx.trace_id()),
+        tx_hash = field::display(stx.tx_hash.as_hex())
+    );
+
+    state_ctx
+        .adapter
+        .insert_signed_txs(ctx, stx)
+        .instrument(span)
+        .await
+}
+
+// Domain transactions sent now are signed in, by metadata of the latest
+// block.
+// Read the service at the height, the latest height by default. Returns the
+// height read at.
+async fn query(
+    state_ctx: &State,
+    height: Option<Uint64>,
+    cycles_limit: Option<Uint64>,
+    cycles_price: Option<Uint64>,
+    caller: &protocol::types::Address,
+    request: protocol::types::TransactionRequest,
+) -> FieldResult<(u64, protocol::traits::ServiceResponse<String>)> {
+    let height = match height {
+        Some(id) => id.try_into_u64()?,
+        None => {
+            state_ctx
+                .adapter
+                .get_block_by_height(Context::new(), None)
+                .await?
+                .header
+                .height
+        }
+    };
+    let cycles_limit = match cycles_limit {
+        Some(cycles_limit) => cycles_limit.try_into_u64()?,
+        None => std::u64::MAX,
+    };
+
+    let cycles_price = match cycles_price {
+        Some(cycles_price) => cycles_price.try_into_u64()?,
+        None => 1,
+    };
+
+    let exec_resp = state_ctx
+        .adapter
+        .query_service(
+            Context::new(),
+            height,
+            cycles_limit,
+            cycles_price,
+            caller.clone(),
+            request.service_name,
+            request.method,
+            request.payload,
+        )
+        .await?;
+    Ok((height, exec_resp))
+}
+
+async fn signature_domain(
+    state_ctx: &State,
+) -> ProtocolResult<Option<protocol::types::SignatureDomain>> {
+    let (height, metadata) = latest_metadata(state_ctx).await?;
+    Ok(metadata.signature_domain(height + 1))
+}
+
+// Height of the latest block and metadata of it
+async fn latest_metadata(state_ctx: &State) -> ProtocolResult<(u64, protocol::types::Metadata)> {
+    let block = state_ctx
+        .adapter
+        .get_block_by_height(Context::new(), None)
+        .await?;
+    let exec_resp = state_ctx
+        .adapter
+        .query_service(
+            Context::new(),
+            block.header.height,
+            u64::max_value(),
+            1,
+            protocol::types::Address::default(),
+            "metadata".to_owned(),
+            "get_metadata".to_owned(),
+            "".to_owned(),
+        )
+        .await?;
+
+    let metadata: protocol::types::Metadata =
+        serde_json::from_str(&exec_resp.succeed_data).map_err(SchemaError::from)?;
+    Ok((block.header.height, metadata))
 }
 
 // Adding `Query` and `Mutation` together we get `Schema`, which describes,
@@ -195,11 +557,174 @@ async fn graphiql() -> HttpResponse {
         .body(GRAPHIQL_HTML.to_owned())
 }
 
+async fn metrics() -> HttpResponse {
+    match common_metrics::registry().encode() {
+        Ok(text) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(text),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+// The process is up and the latest block can be read from storage.
+async fn health(st: web::Data<State>) -> HttpResponse {
+    match st.adapter.get_block_by_height(Context::new(), None).await {
+        Ok(block) => HttpResponse::Ok().json(json!({
+            "status": "ok",
+            "height": block.header.height,
+        })),
+        Err(e) => HttpResponse::ServiceUnavailable().json(json!({
+            "status": "error",
+            "error": e.to_string(),
+        })),
+    }
+}
+
+// The node is synced within `ready_sync_gap` blocks of the best known peer
+// height, and consensus is not paused by synchronization.
+async fn ready(st: web::Data<State>) -> HttpResponse {
+    let current_height = st.readiness.current_height();
+    let best_peer_height = st.readiness.best_peer_height();
+    let synced = best_peer_height.saturating_sub(current_height) <= st.ready_sync_gap;
+    let participating = st.readiness.is_consensus_participating();
+
+    let body = json!({
+        "ready": synced && participating,
+        "current_height": current_height,
+        "best_peer_height": best_peer_height,
+        "sync_gap": st.ready_sync_gap,
+        "consensus_participating": participating,
+    });
+
+    if synced && participating {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
+
+// Admin requests carry the configured token, or come from localhost if there
+// is no token. Returns the response rejecting the request.
+fn authorize_admin(st: &State, req: &HttpRequest) -> Option<HttpResponse> {
+    let authorized = match &st.admin_token {
+        Some(token) => req
+            .headers()
+            .get(ADMIN_TOKEN_HEADER)
+            .map(|header| constant_time_eq(header.as_bytes(), token.as_bytes()))
+            .unwrap_or(false),
+        None => req
+            .peer_addr()
+            .map(|addr| addr.ip().is_loopback())
+            .unwrap_or(false),
+    };
+
+    if authorized {
+        None
+    } else {
+        Some(HttpResponse::Unauthorized().json(json!({ "error": "unauthorized" })))
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Scores of all known peers, peers are banned for a while once their scores
+// run out.
+async fn admin_peers(st: web::Data<State>, req: HttpRequest) -> HttpResponse {
+    if let Some(rejected) = authorize_admin(&st, &req) {
+        return rejected;
+    }
+
+    HttpResponse::Ok().json(st.admin.peer_scores())
+}
+
+// Chain addresses of allowed peers
+async fn admin_allowlist(st: web::Data<State>, req: HttpRequest) -> HttpResponse {
+    if let Some(rejected) = authorize_admin(&st, &req) {
+        return rejected;
+    }
+
+    HttpResponse::Ok().json(st.admin.allowlist())
+}
+
+// Allow or disallow a peer by its hex encoded secp256k1 public key
+async fn admin_allow_peer(
+    st: web::Data<State>,
+    req: HttpRequest,
+    pubkey: web::Path<String>,
+) -> HttpResponse {
+    if let Some(rejected) = authorize_admin(&st, &req) {
+        return rejected;
+    }
+
+    admin_update_allowlist(pubkey.as_str(), |pubkey| st.admin.allow_peer(pubkey))
+}
+
+async fn admin_disallow_peer(
+    st: web::Data<State>,
+    req: HttpRequest,
+    pubkey: web::Path<String>,
+) -> HttpResponse {
+    if let Some(rejected) = authorize_admin(&st, &req) {
+        return rejected;
+    }
+
+    admin_update_allowlist(pubkey.as_str(), |pubkey| st.admin.disallow_peer(pubkey))
+}
+
+fn admin_update_allowlist<F>(pubkey: &str, update: F) -> HttpResponse
+where
+    F: FnOnce(bytes::Bytes) -> ProtocolResult<()>,
+{
+    let pubkey = match hex::decode(pubkey.trim_start_matches("0x")) {
+        Ok(bytes) => bytes::Bytes::from(bytes),
+        Err(e) => return HttpResponse::BadRequest().json(json!({ "error": e.to_string() })),
+    };
+    if let Err(e) = Secp256k1PublicKey::try_from(pubkey.as_ref()) {
+        return HttpResponse::BadRequest().json(json!({ "error": e.to_string() }));
+    }
+
+    match update(pubkey) {
+        Ok(()) => HttpResponse::Ok().json(json!({ "status": "ok" })),
+        Err(e) => HttpResponse::ServiceUnavailable().json(json!({ "error": e.to_string() })),
+    }
+}
+
 async fn graphql(
     st: web::Data<State>,
-    data: web::Json<GraphQLRequest>,
+    req: HttpRequest,
+    body: web::Bytes,
 ) -> Result<HttpResponse, Error> {
-    let result = data.execute_async(&st.schema, &st).await;
+    let _timer = API_REQUEST_SECONDS
+        .with_label_values(&["graphql"])
+        .start_timer();
+
+    // The http server only knows the payload limit at startup, check the
+    // current one here.
+    if body.len() > st.limits.max_payload_size() {
+        return Err(PayloadError::Overflow.into());
+    }
+    let data = serde_json::from_slice::<GraphQLRequest>(&body).map_err(ErrorBadRequest)?;
+
+    // Reads of the request are of one snapshot. Requests without a known api
+    // key get the default limits.
+    let mut state = st.get_ref().clone();
+    state.adapter = Arc::new(Box::new(SnapshotAdapter::new(Arc::clone(&st.adapter))));
+    if let Some(limits) = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|key| key.to_str().ok())
+        .and_then(|key| st.api_keys.get(key))
+    {
+        state.archive_limits = *limits;
+    }
+
+    let result = data.execute_async(&st.schema, &state).await;
+    let status = if result.is_ok() { "ok" } else { "error" };
+    API_REQUEST_TOTAL
+        .with_label_values(&["graphql", status])
+        .inc();
     let res = Ok::<_, serde_json::error::Error>(serde_json::to_string(&result)?)?;
 
     Ok(HttpResponse::Ok()
@@ -207,16 +732,44 @@ async fn graphql(
         .body(res))
 }
 
-pub async fn start_graphql<Adapter: APIAdapter + 'static>(cfg: GraphQLConfig, adapter: Adapter) {
+pub async fn start_graphql<Adapter: APIAdapter + 'static>(
+    cfg: GraphQLConfig,
+    limits: Arc<GraphQLLimits>,
+    adapter: Adapter,
+    readiness: Arc<dyn ReadinessAdapter>,
+    admin: Arc<dyn AdminAdapter>,
+) {
     let schema = Schema::new(Query, Mutation);
 
+    let query_signer = cfg.query_signing_key.as_ref().map(|key| {
+        Arc::new(Secp256k1PrivateKey::try_from(key.as_ref()).expect("invalid query signing key"))
+    });
     let state = State {
         adapter: Arc::new(Box::new(adapter)),
-        schema:  Arc::new(schema),
+        schema: Arc::new(schema),
+        limits,
+
+        readiness,
+        ready_sync_gap: cfg.ready_sync_gap,
+
+        admin,
+        admin_token: cfg.admin_token.clone().map(Arc::new),
+
+        archive_limits: cfg.archive_limits,
+        api_keys: Arc::new(cfg.api_keys.clone()),
+
+        query_signer,
     };
 
     let path_graphql_uri = cfg.graphql_uri.to_owned();
     let path_graphiql_uri = cfg.graphiql_uri.to_owned();
+    let path_metrics_uri = cfg.metrics_uri.to_owned();
+    let path_health_uri = cfg.health_uri.to_owned();
+    let path_ready_uri = cfg.ready_uri.to_owned();
+    let path_admin_uri = cfg
+        .admin_uri
+        .as_ref()
+        .map(|uri| uri.trim_end_matches('/').to_owned());
     let workers = cfg.workers;
     let maxconn = cfg.maxconn;
     let add_listening_address = cfg.listening_address;
@@ -228,12 +781,30 @@ pub async fn start_graphql<Adapter: APIAdapter + 'static>(cfg: GraphQLConfig, ad
             .data(state.clone())
             .service(
                 web::resource(&path_graphql_uri)
-                    .app_data(web::Json::<GraphQLRequest>::configure(|cfg| {
-                        cfg.limit(max_payload_size)
-                    }))
+                    .app_data(web::Bytes::configure(|cfg| cfg.limit(max_payload_size)))
                     .route(web::post().to(graphql)),
             )
             .service(web::resource(&path_graphiql_uri).route(web::get().to(graphiql)))
+            .service(web::resource(&path_metrics_uri).route(web::get().to(metrics)))
+            .service(web::resource(&path_health_uri).route(web::get().to(health)))
+            .service(web::resource(&path_ready_uri).route(web::get().to(ready)))
+            .configure(|app| {
+                if let Some(uri) = &path_admin_uri {
+                    let peers_uri = format!("{}/peers", uri);
+                    let allowlist_uri = format!("{}/allowlist", uri);
+                    let allowlist_peer_uri = format!("{}/allowlist/{{pubkey}}", uri);
+
+                    app.service(web::resource(&peers_uri).route(web::get().to(admin_peers)))
+                        .service(
+                            web::resource(&allowlist_uri).route(web::get().to(admin_allowlist)),
+                        )
+                        .service(
+                            web::resource(&allowlist_peer_uri)
+                                .route(web::put().to(admin_allow_peer))
+                                .route(web::delete().to(admin_disallow_peer)),
+                        );
+                }
+            })
     })
     .workers(workers)
     .maxconn(cmp::max(maxconn / workers, 1))
@@ -17,6 +17,8 @@ pub struct Block {
     ordered_tx_hashes: Vec<Hash>,
     #[graphql(description = "Hash of the block")]
     hash:              Hash,
+    #[graphql(description = "Fixed codec encoded block, hash of it is the block hash")]
+    encoded:           Bytes,
 }
 
 #[derive(juniper::GraphQLObject, Clone)]
@@ -50,6 +52,8 @@ pub struct BlockHeader {
     #[graphql(description = "The version of validator is designed for cross chain")]
     pub validator_version: Uint64,
     pub validators:        Vec<Validator>,
+    #[graphql(description = "Bytes of the proposer, `ready:<fork>` signals an upgrade")]
+    pub extra_data:        Bytes,
 }
 
 #[derive(juniper::GraphQLObject, Clone)]
@@ -103,12 +107,15 @@ impl From<protocol::types::BlockHeader> for BlockHeader {
                 .into_iter()
                 .map(Validator::from)
                 .collect(),
+            extra_data:        Bytes::from(block_header.extra_data),
         }
     }
 }
 
 impl From<protocol::types::Block> for Block {
     fn from(block: protocol::types::Block) -> Self {
+        let encoded = block.encode_fixed().expect("rlp encode never fail");
+
         Block {
             header:            BlockHeader::from(block.header.clone()),
             ordered_tx_hashes: block
@@ -117,9 +124,8 @@ impl From<protocol::types::Block> for Block {
                 .into_iter()
                 .map(MerkleRoot::from)
                 .collect(),
-            hash:              Hash::from(PHash::digest(
-                block.encode_fixed().expect("rlp encode never fail"),
-            )),
+            hash:              Hash::from(PHash::digest(encoded.clone())),
+            encoded:           Bytes::from(encoded),
         }
     }
 }
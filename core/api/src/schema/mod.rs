@@ -1,5 +1,7 @@
 mod block;
 mod receipt;
+mod state_proof;
+mod trace;
 mod transaction;
 
 use std::convert::From;
@@ -10,7 +12,9 @@ use std::num::ParseIntError;
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 pub use block::{Block, BlockHeader};
-pub use receipt::{Event, Receipt, ReceiptResponse};
+pub use receipt::{Event, EventPage, Receipt, ReceiptPage, ReceiptResponse};
+pub use state_proof::{CommitmentScheme, StateProof};
+pub use trace::TxTrace;
 pub use transaction::{
     to_signed_transaction, to_transaction, InputRawTransaction, InputTransactionEncryption,
     SignedTransaction,
@@ -21,18 +25,100 @@ pub struct ServiceResponse {
     pub code:          Uint64,
     pub succeed_data:  String,
     pub error_message: String,
+    #[graphql(description = "Class of the error derived from the code, null if succeed")]
+    pub error_class:   Option<ErrorClass>,
 }
 
 impl From<protocol::traits::ServiceResponse<String>> for ServiceResponse {
     fn from(resp: protocol::traits::ServiceResponse<String>) -> Self {
         Self {
             code:          Uint64::from(resp.code),
+            error_class:   resp.error_class().map(ErrorClass::from),
             succeed_data:  resp.succeed_data,
             error_message: resp.error_message,
         }
     }
 }
 
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(description = "Response of a service query signed by the api node")]
+pub struct SignedServiceResponse {
+    pub response:    ServiceResponse,
+    pub attestation: QueryAttestation,
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(
+    description = "Response read at state root of the block of the height, signed by the node"
+)]
+pub struct QueryAttestation {
+    pub height:      Uint64,
+    pub state_root:  Hash,
+    #[graphql(description = "Hash of the caller, service, method, payload and response")]
+    pub result_hash: Hash,
+    pub pubkey:      Bytes,
+    pub signature:   Bytes,
+    #[graphql(description = "Fixed codec encoded block of the height")]
+    pub block:       Bytes,
+    #[graphql(description = "Fixed codec encoded proof committing the block")]
+    pub proof:       Bytes,
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(description = "Emergency halt of the chain voted by validators")]
+pub struct HaltStatus {
+    #[graphql(description = "Whether only governance transactions are packaged now")]
+    pub halted:      bool,
+    #[graphql(description = "Height the chain halts at, null if no halt is passed")]
+    pub halt_height: Option<Uint64>,
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(description = "Height a feature of the chain spec activates from")]
+pub struct FeatureActivation {
+    pub name:      String,
+    pub height:    Uint64,
+    #[graphql(description = "Whether the feature is active from the next height")]
+    pub active:    bool,
+    #[graphql(description = "Whether the node supports it, or stops at its height")]
+    pub supported: bool,
+}
+
+#[derive(juniper::GraphQLEnum, Clone)]
+pub enum ErrorClass {
+    InvalidPayload,
+    MethodNotFound,
+    OutOfCycles,
+    Unauthorized,
+    ServiceNotFound,
+    #[graphql(description = "The chain is halted, only governance transactions are executed")]
+    Halted,
+    #[graphql(description = "Caller has no access to the method")]
+    Forbidden,
+    #[graphql(description = "Caller can't pay the fee, writes were reverted")]
+    InsufficientFee,
+    #[graphql(description = "Error of a code defined by the service")]
+    Reverted,
+}
+
+impl From<protocol::traits::ErrorClass> for ErrorClass {
+    fn from(class: protocol::traits::ErrorClass) -> Self {
+        use protocol::traits::ErrorClass as Class;
+
+        match class {
+            Class::InvalidPayload => ErrorClass::InvalidPayload,
+            Class::MethodNotFound => ErrorClass::MethodNotFound,
+            Class::OutOfCycles => ErrorClass::OutOfCycles,
+            Class::Unauthorized => ErrorClass::Unauthorized,
+            Class::ServiceNotFound => ErrorClass::ServiceNotFound,
+            Class::Halted => ErrorClass::Halted,
+            Class::Forbidden => ErrorClass::Forbidden,
+            Class::InsufficientFee => ErrorClass::InsufficientFee,
+            Class::Reverted => ErrorClass::Reverted,
+        }
+    }
+}
+
 #[derive(juniper::GraphQLScalarValue, Clone)]
 #[graphql(description = "The output digest of Keccak hash function")]
 pub struct Hash(String);
@@ -126,6 +212,9 @@ pub enum SchemaError {
 
     #[display(fmt = "hex should start with 0x")]
     HexPrefix,
+
+    #[display(fmt = "decode json {:?}", _0)]
+    Json(serde_json::Error),
 }
 
 impl std::error::Error for SchemaError {}
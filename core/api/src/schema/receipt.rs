@@ -16,6 +16,33 @@ pub struct Event {
     pub data:    String,
 }
 
+#[derive(juniper::GraphQLObject, Clone)]
+pub struct ReceiptPage {
+    pub receipts:    Vec<Receipt>,
+    #[graphql(description = "Pass it to get the next page, null at the last page")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(description = "An event with the block and transaction emitting it")]
+pub struct EventRecord {
+    pub height:  Uint64,
+    pub tx_hash: Hash,
+    #[graphql(description = "Index of the event in the receipt")]
+    pub index:   Uint64,
+    #[graphql(description = "Method of the transaction")]
+    pub method:  String,
+    pub service: String,
+    pub data:    String,
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+pub struct EventPage {
+    pub events:      Vec<EventRecord>,
+    #[graphql(description = "Pass it to get the next page, null at the last page")]
+    pub next_cursor: Option<String>,
+}
+
 #[derive(juniper::GraphQLObject, Clone)]
 pub struct ReceiptResponse {
     pub service_name: String,
@@ -45,6 +72,19 @@ impl From<protocol::types::Event> for Event {
     }
 }
 
+impl From<crate::archive::EventRecord> for EventRecord {
+    fn from(record: crate::archive::EventRecord) -> Self {
+        Self {
+            height:  Uint64::from(record.height),
+            tx_hash: Hash::from(record.tx_hash),
+            index:   Uint64::from(record.index),
+            method:  record.method,
+            service: record.event.service,
+            data:    record.event.data,
+        }
+    }
+}
+
 impl From<protocol::types::ReceiptResponse> for ReceiptResponse {
     fn from(response: protocol::types::ReceiptResponse) -> Self {
         Self {
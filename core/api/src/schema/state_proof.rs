@@ -0,0 +1,56 @@
+use crate::schema::{Bytes, Hash, MerkleRoot, Uint64};
+
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(
+    description = "Merkle proof of a key in service state. Block state maps service names \
+                   to service roots, so the service root is proved in block state first, \
+                   then the key in service state."
+)]
+pub struct StateProof {
+    #[graphql(description = "Scheme the state of the chain is committed in")]
+    pub scheme:        CommitmentScheme,
+    #[graphql(description = "Height of the block proved against")]
+    pub height:        Uint64,
+    #[graphql(description = "State root of the block")]
+    pub state_root:    MerkleRoot,
+    pub service_name:  String,
+    #[graphql(description = "Fixed codec encoded key")]
+    pub key:           Bytes,
+    pub service_root:  MerkleRoot,
+    #[graphql(description = "Trie nodes proving service root in block state")]
+    pub service_proof: Vec<Bytes>,
+    #[graphql(description = "Trie nodes proving the key in service state")]
+    pub key_proof:     Vec<Bytes>,
+}
+
+impl StateProof {
+    pub fn new(
+        height: u64,
+        state_root: protocol::types::MerkleRoot,
+        proof: protocol::types::StateProof,
+    ) -> Self {
+        StateProof {
+            scheme:        CommitmentScheme::from(proof.scheme),
+            height:        Uint64::from(height),
+            state_root:    Hash::from(state_root),
+            service_name:  proof.service_name,
+            key:           Bytes::from(proof.key),
+            service_root:  Hash::from(proof.service_root),
+            service_proof: proof.service_proof.into_iter().map(Bytes::from).collect(),
+            key_proof:     proof.key_proof.into_iter().map(Bytes::from).collect(),
+        }
+    }
+}
+
+#[derive(juniper::GraphQLEnum, Clone)]
+pub enum CommitmentScheme {
+    MerklePatricia,
+}
+
+impl From<protocol::types::CommitmentScheme> for CommitmentScheme {
+    fn from(scheme: protocol::types::CommitmentScheme) -> Self {
+        match scheme {
+            protocol::types::CommitmentScheme::MerklePatricia => CommitmentScheme::MerklePatricia,
+        }
+    }
+}
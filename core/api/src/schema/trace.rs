@@ -0,0 +1,89 @@
+use crate::schema::{Bytes, ErrorClass, Hash, Uint64};
+
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(description = "Steps of a transaction re-executed against the state of its block")]
+pub struct TxTrace {
+    pub tx_hash:       Hash,
+    pub height:        Uint64,
+    pub cycles_used:   Uint64,
+    pub service_name:  String,
+    pub method:        String,
+    pub code:          Uint64,
+    pub error_message: String,
+    pub error_class:   Option<ErrorClass>,
+    pub steps:         Vec<TraceStep>,
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+pub struct TraceStep {
+    #[graphql(description = "Calls made by the transaction are at depth 0")]
+    pub depth:       Uint64,
+    pub kind:        TraceStepKind,
+    pub service:     String,
+    #[graphql(description = "Method of calls and returns")]
+    pub method:      String,
+    #[graphql(description = "Fixed codec encoded key of storage access")]
+    pub key:         Option<Bytes>,
+    #[graphql(description = "Payload of calls, response of returns and data of events")]
+    pub data:        String,
+    #[graphql(description = "Response code of returns")]
+    pub code:        Option<Uint64>,
+    #[graphql(description = "Bytes of the value read or written")]
+    pub size:        Uint64,
+    #[graphql(description = "Cycles used by the transaction up to the end of the step")]
+    pub cycles_used: Uint64,
+}
+
+#[derive(juniper::GraphQLEnum, Clone)]
+pub enum TraceStepKind {
+    Call,
+    Return,
+    Read,
+    Write,
+    Event,
+}
+
+impl From<protocol::types::TxTrace> for TxTrace {
+    fn from(trace: protocol::types::TxTrace) -> Self {
+        Self {
+            tx_hash:       Hash::from(trace.tx_hash),
+            height:        Uint64::from(trace.height),
+            cycles_used:   Uint64::from(trace.cycles_used),
+            service_name:  trace.service_name,
+            method:        trace.method,
+            code:          Uint64::from(trace.code),
+            error_message: trace.error_message,
+            error_class:   protocol::traits::ErrorClass::from_code(trace.code)
+                .map(ErrorClass::from),
+            steps:         trace.steps.into_iter().map(TraceStep::from).collect(),
+        }
+    }
+}
+
+impl From<protocol::types::TraceStep> for TraceStep {
+    fn from(step: protocol::types::TraceStep) -> Self {
+        Self {
+            depth:       Uint64::from(step.depth),
+            kind:        TraceStepKind::from(step.kind),
+            service:     step.service,
+            method:      step.method,
+            key:         step.key.map(Bytes::from),
+            data:        step.data,
+            code:        step.code.map(Uint64::from),
+            size:        Uint64::from(step.size),
+            cycles_used: Uint64::from(step.cycles_used),
+        }
+    }
+}
+
+impl From<protocol::types::TraceStepKind> for TraceStepKind {
+    fn from(kind: protocol::types::TraceStepKind) -> Self {
+        match kind {
+            protocol::types::TraceStepKind::Call => TraceStepKind::Call,
+            protocol::types::TraceStepKind::Return => TraceStepKind::Return,
+            protocol::types::TraceStepKind::Read => TraceStepKind::Read,
+            protocol::types::TraceStepKind::Write => TraceStepKind::Write,
+            protocol::types::TraceStepKind::Event => TraceStepKind::Event,
+        }
+    }
+}
@@ -1,36 +1,40 @@
 use protocol::ProtocolResult;
 
-use crate::schema::{Bytes, Hash, SchemaError, Uint64};
+use crate::schema::{Address, Bytes, Hash, SchemaError, Uint64};
 
 #[derive(juniper::GraphQLObject, Clone)]
 pub struct SignedTransaction {
-    pub chain_id:     Hash,
-    pub cycles_limit: Uint64,
-    pub cycles_price: Uint64,
-    pub nonce:        Hash,
-    pub timeout:      Uint64,
-    pub service_name: String,
-    pub method:       String,
-    pub payload:      String,
-    pub tx_hash:      Hash,
-    pub pubkey:       Bytes,
-    pub signature:    Bytes,
+    pub chain_id:              Hash,
+    pub cycles_limit:          Uint64,
+    pub cycles_price:          Uint64,
+    pub nonce:                 Hash,
+    pub timeout:               Uint64,
+    pub service_name:          String,
+    pub method:                String,
+    pub payload:               String,
+    pub sender:                Option<Address>,
+    pub valid_until_timestamp: Option<Uint64>,
+    pub tx_hash:               Hash,
+    pub pubkey:                Bytes,
+    pub signature:             Bytes,
 }
 
 impl From<protocol::types::SignedTransaction> for SignedTransaction {
     fn from(stx: protocol::types::SignedTransaction) -> Self {
         Self {
-            chain_id:     Hash::from(stx.raw.chain_id),
-            cycles_limit: Uint64::from(stx.raw.cycles_limit),
-            cycles_price: Uint64::from(stx.raw.cycles_price),
-            nonce:        Hash::from(stx.raw.nonce),
-            timeout:      Uint64::from(stx.raw.timeout),
-            service_name: stx.raw.request.service_name,
-            method:       stx.raw.request.method,
-            payload:      stx.raw.request.payload,
-            tx_hash:      Hash::from(stx.tx_hash),
-            pubkey:       Bytes::from(stx.pubkey),
-            signature:    Bytes::from(stx.signature),
+            chain_id:              Hash::from(stx.raw.chain_id),
+            cycles_limit:          Uint64::from(stx.raw.cycles_limit),
+            cycles_price:          Uint64::from(stx.raw.cycles_price),
+            nonce:                 Hash::from(stx.raw.nonce),
+            timeout:               Uint64::from(stx.raw.timeout),
+            service_name:          stx.raw.request.service_name,
+            method:                stx.raw.request.method,
+            payload:               stx.raw.request.payload,
+            sender:                stx.raw.sender.map(Address::from),
+            valid_until_timestamp: stx.raw.valid_until_timestamp.map(Uint64::from),
+            tx_hash:               Hash::from(stx.tx_hash),
+            pubkey:                Bytes::from(stx.pubkey),
+            signature:             Bytes::from(stx.signature),
         }
     }
 }
@@ -46,26 +50,36 @@ impl From<protocol::types::SignedTransaction> for SignedTransaction {
                          InputRawTransaction describes information above")]
 pub struct InputRawTransaction {
     #[graphql(description = "Identifier of the chain.")]
-    pub chain_id:     Hash,
+    pub chain_id:              Hash,
     #[graphql(
         description = "Mostly like the gas limit in Ethereum, describes the fee that \
                        you are willing to pay the highest price for the transaction"
     )]
-    pub cycles_limit: Uint64,
-    pub cycles_price: Uint64,
+    pub cycles_limit:          Uint64,
+    pub cycles_price:          Uint64,
     #[graphql(
         description = "Every transaction has its own id, unlike Ethereum's nonce,\
                        the nonce in Muta is an hash"
     )]
-    pub nonce:        Hash,
+    pub nonce:                 Hash,
     #[graphql(description = "For security and performance reasons, \
     Muta will only deal with trade request over a period of time,\
     the `timeout` should be `timeout > current_block_height` and `timeout < current_block_height + timeout_gap`,\
     the `timeout_gap` generally equal to 20.")]
-    pub timeout:      Uint64,
-    pub service_name: String,
-    pub method:       String,
-    pub payload:      String,
+    pub timeout:               Uint64,
+    pub service_name:          String,
+    pub method:                String,
+    pub payload:               String,
+    #[graphql(
+        description = "Account the transaction acts for, the signer by default. \
+                             The signer must be authorized by the account service."
+    )]
+    pub sender:                Option<Address>,
+    #[graphql(
+        description = "Unix timestamp in milliseconds, the transaction is rejected \
+                       once the latest block is after it, no matter how far `timeout` is."
+    )]
+    pub valid_until_timestamp: Option<Uint64>,
 }
 
 #[derive(juniper::GraphQLInputObject, Clone)]
@@ -97,15 +111,23 @@ pub fn to_signed_transaction(
 
 pub fn to_transaction(raw: InputRawTransaction) -> ProtocolResult<protocol::types::RawTransaction> {
     Ok(protocol::types::RawTransaction {
-        chain_id:     protocol::types::Hash::from_hex(&raw.chain_id.as_hex())?,
-        nonce:        protocol::types::Hash::from_hex(&raw.nonce.as_hex())?,
-        timeout:      raw.timeout.try_into_u64()?,
-        cycles_price: raw.cycles_price.try_into_u64()?,
-        cycles_limit: raw.cycles_limit.try_into_u64()?,
-        request:      protocol::types::TransactionRequest {
+        chain_id:              protocol::types::Hash::from_hex(&raw.chain_id.as_hex())?,
+        nonce:                 protocol::types::Hash::from_hex(&raw.nonce.as_hex())?,
+        timeout:               raw.timeout.try_into_u64()?,
+        cycles_price:          raw.cycles_price.try_into_u64()?,
+        cycles_limit:          raw.cycles_limit.try_into_u64()?,
+        request:               protocol::types::TransactionRequest {
             service_name: raw.service_name.to_owned(),
             method:       raw.method.to_owned(),
             payload:      raw.payload.to_owned(),
         },
+        sender:                match raw.sender {
+            Some(sender) => Some(protocol::types::Address::from_hex(&sender.as_hex())?),
+            None => None,
+        },
+        valid_until_timestamp: match raw.valid_until_timestamp {
+            Some(valid_until) => Some(valid_until.try_into_u64()?),
+            None => None,
+        },
     })
 }
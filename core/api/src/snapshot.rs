@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::lock::Mutex;
+
+use protocol::traits::{APIAdapter, Context, ServiceResponse};
+use protocol::types::{
+    Address, Block, Bytes, Hash, Proof, Receipt, SignedTransaction, StateProof, TxTrace,
+};
+use protocol::ProtocolResult;
+
+use crate::adapter::{api_error, APIError};
+
+/// Reads of a request see the chain at one height. The latest block is
+/// pinned by the first read, later blocks committed during the request are
+/// invisible to it. Header, state and receipts are then of the same block,
+/// state under a pinned root never changes since trie nodes are keyed by
+/// their hash.
+///
+/// Transactions are not pinned, they're stored without the height of their
+/// block. A transaction committed after the pinned block is still returned
+/// by `get_transaction_by_hash`, while its receipt is not.
+pub struct SnapshotAdapter {
+    inner:  Arc<Box<dyn APIAdapter>>,
+    pinned: Mutex<Option<Block>>,
+}
+
+impl SnapshotAdapter {
+    pub fn new(inner: Arc<Box<dyn APIAdapter>>) -> Self {
+        SnapshotAdapter {
+            inner,
+            pinned: Mutex::new(None),
+        }
+    }
+
+    async fn pinned(&self, ctx: Context) -> ProtocolResult<Block> {
+        let mut pinned = self.pinned.lock().await;
+        if pinned.is_none() {
+            *pinned = Some(self.inner.get_block_by_height(ctx, None).await?);
+        }
+
+        Ok(pinned.clone().expect("snapshot is pinned"))
+    }
+
+    async fn check_height(&self, ctx: Context, height: u64) -> ProtocolResult<Block> {
+        let pinned = self.pinned(ctx).await?;
+        if height > pinned.header.height {
+            return Err(api_error(APIError::AboveSnapshot {
+                height,
+                snapshot: pinned.header.height,
+            }));
+        }
+
+        Ok(pinned)
+    }
+
+    // Receipts of blocks executed after the pinned one are not visible
+    async fn check_executed(&self, ctx: Context, height: u64) -> ProtocolResult<()> {
+        let exec_height = self.pinned(ctx).await?.header.exec_height;
+        if height > exec_height {
+            return Err(api_error(APIError::UnExecedError {
+                real:   exec_height,
+                expect: height,
+            }));
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl APIAdapter for SnapshotAdapter {
+    async fn insert_signed_txs(
+        &self,
+        ctx: Context,
+        signed_tx: SignedTransaction,
+    ) -> ProtocolResult<()> {
+        self.inner.insert_signed_txs(ctx, signed_tx).await
+    }
+
+    async fn get_block_by_height(
+        &self,
+        ctx: Context,
+        height: Option<u64>,
+    ) -> ProtocolResult<Block> {
+        match height {
+            Some(height) => {
+                let pinned = self.check_height(ctx.clone(), height).await?;
+                if height == pinned.header.height {
+                    Ok(pinned)
+                } else {
+                    self.inner.get_block_by_height(ctx, Some(height)).await
+                }
+            }
+            None => self.pinned(ctx).await,
+        }
+    }
+
+    async fn get_commit_proof(&self, ctx: Context, height: u64) -> ProtocolResult<Proof> {
+        self.check_height(ctx.clone(), height).await?;
+        self.inner.get_commit_proof(ctx, height).await
+    }
+
+    async fn get_receipt_by_tx_hash(&self, ctx: Context, tx_hash: Hash) -> ProtocolResult<Receipt> {
+        let receipt = self
+            .inner
+            .get_receipt_by_tx_hash(ctx.clone(), tx_hash)
+            .await?;
+        self.check_executed(ctx, receipt.height).await?;
+        Ok(receipt)
+    }
+
+    async fn get_receipts_by_height(
+        &self,
+        ctx: Context,
+        height: u64,
+    ) -> ProtocolResult<Vec<Receipt>> {
+        self.check_executed(ctx.clone(), height).await?;
+        self.inner.get_receipts_by_height(ctx, height).await
+    }
+
+    // Not pinned, see the doc of the adapter
+    async fn get_transaction_by_hash(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<SignedTransaction> {
+        self.inner.get_transaction_by_hash(ctx, tx_hash).await
+    }
+
+    async fn query_service(
+        &self,
+        ctx: Context,
+        height: u64,
+        cycles_limit: u64,
+        cycles_price: u64,
+        caller: Address,
+        service_name: String,
+        method: String,
+        payload: String,
+    ) -> ProtocolResult<ServiceResponse<String>> {
+        self.check_height(ctx.clone(), height).await?;
+        self.inner
+            .query_service(
+                ctx,
+                height,
+                cycles_limit,
+                cycles_price,
+                caller,
+                service_name,
+                method,
+                payload,
+            )
+            .await
+    }
+
+    async fn get_state_proof(
+        &self,
+        ctx: Context,
+        height: u64,
+        service_name: String,
+        key: Bytes,
+    ) -> ProtocolResult<StateProof> {
+        self.check_height(ctx.clone(), height).await?;
+        self.inner
+            .get_state_proof(ctx, height, service_name, key)
+            .await
+    }
+
+    async fn trace_transaction(&self, ctx: Context, tx_hash: Hash) -> ProtocolResult<TxTrace> {
+        let trace = self.inner.trace_transaction(ctx.clone(), tx_hash).await?;
+        self.check_executed(ctx, trace.height).await?;
+        Ok(trace)
+    }
+
+    async fn seal_block(&self, ctx: Context) -> ProtocolResult<Block> {
+        self.inner.seal_block(ctx).await
+    }
+}
@@ -0,0 +1,263 @@
+use futures::executor::block_on;
+
+use protocol::types::Receipt;
+
+use crate::archive::{get_events, get_receipts, ArchiveLimits, EventRecord};
+use crate::tests::{mock_receipt, MockAPIAdapter};
+
+fn limits(max_page_size: u64, max_height_range: u64) -> ArchiveLimits {
+    ArchiveLimits {
+        max_page_size,
+        max_height_range,
+        trace: false,
+        signed_query: false,
+    }
+}
+
+// Receipts with events of `transfer` to asset and of `approve` to asset and
+// bridge, alternately
+fn mock_receipts(height: u64, count: u64) -> Vec<Receipt> {
+    (0..count)
+        .map(|index| {
+            if index % 2 == 0 {
+                mock_receipt(height, index, "transfer", &["asset"])
+            } else {
+                mock_receipt(height, index, "approve", &["asset", "bridge"])
+            }
+        })
+        .collect()
+}
+
+fn event_keys(events: &[EventRecord]) -> Vec<(u64, String, u64)> {
+    events
+        .iter()
+        .map(|e| (e.height, e.tx_hash.as_hex(), e.index))
+        .collect()
+}
+
+// Follow next cursors until the last page, pages are checked to be full
+// except the last
+fn all_events(
+    adapter: &MockAPIAdapter,
+    limits: &ArchiveLimits,
+    from: u64,
+    to: u64,
+    service: Option<&str>,
+    method: Option<&str>,
+    limit: i32,
+) -> Vec<EventRecord> {
+    let mut events = vec![];
+    let mut cursor = None;
+
+    loop {
+        let (page, next) = block_on(get_events(
+            adapter,
+            limits,
+            from,
+            to,
+            service.map(ToOwned::to_owned),
+            method.map(ToOwned::to_owned),
+            cursor,
+            Some(limit),
+        ))
+        .unwrap();
+        if next.is_some() {
+            assert_eq!(page.len(), limit as usize);
+        }
+        events.extend(page);
+
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return events,
+        }
+    }
+}
+
+#[test]
+fn test_receipts_pages() {
+    let adapter = MockAPIAdapter::new(vec![vec![], mock_receipts(1, 5)], 1);
+    let limits = limits(2, 10);
+
+    let (page, next) = block_on(get_receipts(&adapter, &limits, 1, None, None)).unwrap();
+    assert_eq!(page, mock_receipts(1, 5)[0..2].to_vec());
+    assert_eq!(next, Some("1:2".to_owned()));
+
+    let (page, next) = block_on(get_receipts(&adapter, &limits, 1, next, None)).unwrap();
+    assert_eq!(page, mock_receipts(1, 5)[2..4].to_vec());
+    assert_eq!(next, Some("1:4".to_owned()));
+
+    let (page, next) = block_on(get_receipts(&adapter, &limits, 1, next, None)).unwrap();
+    assert_eq!(page, mock_receipts(1, 5)[4..].to_vec());
+    assert_eq!(next, None);
+
+    // Limits above the max page size are capped, below it are kept
+    let (page, _) = block_on(get_receipts(&adapter, &limits, 1, None, Some(100))).unwrap();
+    assert_eq!(page.len(), 2);
+    let (page, next) = block_on(get_receipts(&adapter, &limits, 1, None, Some(1))).unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(next, Some("1:1".to_owned()));
+
+    // A page ending at the last receipt has no next cursor
+    let adapter = MockAPIAdapter::new(vec![vec![], mock_receipts(1, 4)], 1);
+    let (_, next) = block_on(get_receipts(
+        &adapter,
+        &limits,
+        1,
+        Some("1:2".to_owned()),
+        None,
+    ))
+    .unwrap();
+    assert_eq!(next, None);
+
+    // A cursor past the last receipt returns an empty page
+    let (page, next) = block_on(get_receipts(
+        &adapter,
+        &limits,
+        1,
+        Some("1:9".to_owned()),
+        None,
+    ))
+    .unwrap();
+    assert!(page.is_empty());
+    assert_eq!(next, None);
+}
+
+#[test]
+fn test_receipts_invalid_cursor() {
+    let adapter = MockAPIAdapter::new(vec![mock_receipts(0, 3), mock_receipts(1, 3)], 1);
+    let limits = limits(2, 10);
+
+    for cursor in &["0:1", "1", "1:1:0", "1:x", ""] {
+        let res = block_on(get_receipts(
+            &adapter,
+            &limits,
+            1,
+            Some((*cursor).to_owned()),
+            None,
+        ));
+        assert!(res.is_err(), "cursor {:?} is accepted", cursor);
+    }
+}
+
+#[test]
+fn test_events_pages_equal_one_query() {
+    let receipts = (0..5).map(|height| mock_receipts(height, height)).collect();
+    let adapter = MockAPIAdapter::new(receipts, 4);
+    let limits = limits(100, 10);
+
+    let (expected, next) =
+        block_on(get_events(&adapter, &limits, 0, 4, None, None, None, None)).unwrap();
+    assert_eq!(next, None);
+    // Heights 1 to 4 have 1, 3, 4 and 6 events
+    assert_eq!(expected.len(), 14);
+
+    // Page boundaries fall inside receipts as well as between blocks
+    for limit in 1..=15 {
+        let events = all_events(&adapter, &limits, 0, 4, None, None, limit);
+        assert_eq!(
+            event_keys(&events),
+            event_keys(&expected),
+            "limit {}",
+            limit
+        );
+    }
+}
+
+#[test]
+fn test_events_pages_with_filters() {
+    let receipts = (0..5).map(|height| mock_receipts(height, height)).collect();
+    let adapter = MockAPIAdapter::new(receipts, 4);
+    let limits = limits(100, 10);
+
+    for limit in 1..=4 {
+        let bridge = all_events(&adapter, &limits, 0, 4, Some("bridge"), None, limit);
+        assert_eq!(bridge.len(), 4);
+        assert!(bridge
+            .iter()
+            .all(|e| e.event.service == "bridge" && e.index == 1));
+
+        let transfer = all_events(&adapter, &limits, 0, 4, None, Some("transfer"), limit);
+        assert_eq!(transfer.len(), 6);
+        assert!(transfer.iter().all(|e| e.method == "transfer"));
+
+        let asset_approve = all_events(
+            &adapter,
+            &limits,
+            0,
+            4,
+            Some("asset"),
+            Some("approve"),
+            limit,
+        );
+        assert_eq!(asset_approve.len(), 4);
+        assert!(asset_approve.iter().all(|e| e.index == 0));
+    }
+}
+
+#[test]
+fn test_events_cursor_resumes_inside_receipt() {
+    let adapter = MockAPIAdapter::new(vec![vec![], mock_receipts(1, 2)], 1);
+    let limits = limits(100, 10);
+
+    // The second event of the second receipt
+    let (events, next) = block_on(get_events(
+        &adapter,
+        &limits,
+        1,
+        1,
+        None,
+        None,
+        Some("1:1:1".to_owned()),
+        None,
+    ))
+    .unwrap();
+    assert_eq!(next, None);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event.service, "bridge");
+    assert_eq!(events[0].index, 1);
+}
+
+#[test]
+fn test_events_unexecuted_blocks_not_scanned() {
+    // Receipts of block 3 are not saved yet
+    let mut receipts = (0..3)
+        .map(|height| mock_receipts(height, 1))
+        .collect::<Vec<_>>();
+    receipts.push(vec![]);
+    let mut adapter = MockAPIAdapter::new(receipts, 2);
+    adapter.receipts.remove(&3);
+    let limits = limits(100, 10);
+
+    let (events, next) =
+        block_on(get_events(&adapter, &limits, 0, 3, None, None, None, None)).unwrap();
+    assert_eq!(next, None);
+    assert_eq!(events.iter().map(|e| e.height).collect::<Vec<_>>(), vec![
+        0, 1, 2
+    ]);
+}
+
+#[test]
+fn test_events_invalid_range_and_cursor() {
+    let receipts = (0..5).map(|height| mock_receipts(height, 1)).collect();
+    let adapter = MockAPIAdapter::new(receipts, 4);
+    let limits = limits(100, 3);
+
+    // Empty, and over the max height range
+    assert!(block_on(get_events(&adapter, &limits, 2, 1, None, None, None, None)).is_err());
+    assert!(block_on(get_events(&adapter, &limits, 0, 3, None, None, None, None)).is_err());
+    assert!(block_on(get_events(&adapter, &limits, 0, 2, None, None, None, None)).is_ok());
+
+    for cursor in &["0:0:0", "2:0", "2:0:0:0", "a:b:c"] {
+        let res = block_on(get_events(
+            &adapter,
+            &limits,
+            1,
+            3,
+            None,
+            None,
+            Some((*cursor).to_owned()),
+            None,
+        ));
+        assert!(res.is_err(), "cursor {:?} is accepted", cursor);
+    }
+}
@@ -0,0 +1,204 @@
+mod archive;
+mod snapshot;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use parking_lot::RwLock;
+
+use protocol::traits::{APIAdapter, Context, ServiceResponse};
+use protocol::types::{
+    Address, Block, BlockHeader, Event, Hash, Proof, Receipt, ReceiptResponse, SignedTransaction,
+    StateProof, TxTrace,
+};
+use protocol::ProtocolResult;
+
+use crate::adapter::{api_error, APIError};
+
+// Blocks and receipts in memory, the latest block is the last one. Blocks
+// are shared, they're committed while the adapter is boxed.
+#[derive(Default)]
+pub struct MockAPIAdapter {
+    pub blocks:   Arc<RwLock<Vec<Block>>>,
+    pub receipts: HashMap<u64, Vec<Receipt>>,
+}
+
+impl MockAPIAdapter {
+    // Blocks from 0 to `exec_height` are executed, with the receipts
+    pub fn new(receipts: Vec<Vec<Receipt>>, exec_height: u64) -> Self {
+        let blocks = (0..receipts.len() as u64)
+            .map(|height| mock_block(height, exec_height.min(height)))
+            .collect();
+        let receipts = receipts
+            .into_iter()
+            .enumerate()
+            .map(|(height, receipts)| (height as u64, receipts))
+            .collect();
+
+        MockAPIAdapter {
+            blocks: Arc::new(RwLock::new(blocks)),
+            receipts,
+        }
+    }
+}
+
+// Append a block executed to the height
+pub fn commit_block(blocks: &RwLock<Vec<Block>>, exec_height: u64) {
+    let mut blocks = blocks.write();
+    let height = blocks.len() as u64;
+    blocks.push(mock_block(height, exec_height));
+}
+
+#[async_trait]
+impl APIAdapter for MockAPIAdapter {
+    async fn insert_signed_txs(&self, _: Context, _: SignedTransaction) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn get_block_by_height(&self, _: Context, height: Option<u64>) -> ProtocolResult<Block> {
+        let blocks = self.blocks.read();
+        let block = match height {
+            Some(height) => blocks.get(height as usize),
+            None => blocks.last(),
+        };
+        let real = blocks.len() as u64;
+        block.cloned().ok_or_else(|| {
+            api_error(APIError::UnExecedError {
+                expect: height.unwrap_or(0),
+                real,
+            })
+        })
+    }
+
+    async fn get_commit_proof(&self, _: Context, _: u64) -> ProtocolResult<Proof> {
+        unimplemented!()
+    }
+
+    async fn get_receipt_by_tx_hash(&self, _: Context, tx_hash: Hash) -> ProtocolResult<Receipt> {
+        self.receipts
+            .values()
+            .flatten()
+            .find(|receipt| receipt.tx_hash == tx_hash)
+            .cloned()
+            .ok_or_else(|| {
+                api_error(APIError::UnExecedError {
+                    expect: 0,
+                    real:   self.blocks.read().len() as u64,
+                })
+            })
+    }
+
+    async fn get_receipts_by_height(
+        &self,
+        _: Context,
+        height: u64,
+    ) -> ProtocolResult<Vec<Receipt>> {
+        self.receipts.get(&height).cloned().ok_or_else(|| {
+            api_error(APIError::UnExecedError {
+                expect: height,
+                real:   self.blocks.read().len() as u64,
+            })
+        })
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        _: Context,
+        _: Hash,
+    ) -> ProtocolResult<SignedTransaction> {
+        unimplemented!()
+    }
+
+    async fn query_service(
+        &self,
+        _: Context,
+        _: u64,
+        _: u64,
+        _: u64,
+        _: Address,
+        _: String,
+        _: String,
+        _: String,
+    ) -> ProtocolResult<ServiceResponse<String>> {
+        unimplemented!()
+    }
+
+    async fn get_state_proof(
+        &self,
+        _: Context,
+        _: u64,
+        _: String,
+        _: Bytes,
+    ) -> ProtocolResult<StateProof> {
+        unimplemented!()
+    }
+
+    async fn trace_transaction(&self, _: Context, _: Hash) -> ProtocolResult<TxTrace> {
+        unimplemented!()
+    }
+
+    async fn seal_block(&self, _: Context) -> ProtocolResult<Block> {
+        unimplemented!()
+    }
+}
+
+// A receipt of the method emitting an event for each of the services
+pub fn mock_receipt(height: u64, index: u64, method: &str, services: &[&str]) -> Receipt {
+    let events = services
+        .iter()
+        .map(|service| Event {
+            service: (*service).to_owned(),
+            data:    "{}".to_owned(),
+        })
+        .collect();
+
+    Receipt {
+        state_root: Hash::from_empty(),
+        height,
+        tx_hash: Hash::digest(Bytes::from(format!("{}:{}", height, index))),
+        cycles_used: 10,
+        events,
+        response: ReceiptResponse {
+            service_name: "asset".to_owned(),
+            method:       method.to_owned(),
+            response:     ServiceResponse::<String> {
+                code:          0,
+                succeed_data:  "".to_owned(),
+                error_message: "".to_owned(),
+            },
+        },
+    }
+}
+
+pub fn mock_block(height: u64, exec_height: u64) -> Block {
+    Block {
+        header:            BlockHeader {
+            chain_id: Hash::from_empty(),
+            height,
+            exec_height,
+            pre_hash: Hash::from_empty(),
+            timestamp: 0,
+            logs_bloom: vec![],
+            order_root: Hash::from_empty(),
+            confirm_root: vec![],
+            state_root: Hash::from_empty(),
+            receipt_root: vec![],
+            cycles_used: vec![],
+            proposer: Address::default(),
+            proof: Proof {
+                height:     0,
+                round:      0,
+                block_hash: Hash::from_empty(),
+                signature:  Bytes::new(),
+                bitmap:     Bytes::new(),
+            },
+            validator_version: 0,
+            validators: vec![],
+            extra_data: Bytes::new(),
+        },
+        ordered_tx_hashes: vec![],
+        vote_extensions:   vec![],
+    }
+}
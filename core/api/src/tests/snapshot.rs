@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use futures::executor::block_on;
+
+use protocol::traits::{APIAdapter, Context};
+
+use crate::snapshot::SnapshotAdapter;
+use crate::tests::{commit_block, mock_receipt, MockAPIAdapter};
+
+// Blocks 0 to 2 with a receipt each, executed to 1
+fn mock_snapshot() -> (SnapshotAdapter, MockAPIAdapter) {
+    let receipts = (0..3)
+        .map(|height| vec![mock_receipt(height, 0, "transfer", &["asset"])])
+        .collect::<Vec<_>>();
+    let adapter = MockAPIAdapter::new(receipts, 1);
+    let shared = MockAPIAdapter {
+        blocks:   Arc::clone(&adapter.blocks),
+        receipts: adapter.receipts.clone(),
+    };
+
+    let inner: Arc<Box<dyn APIAdapter>> = Arc::new(Box::new(adapter));
+    (SnapshotAdapter::new(inner), shared)
+}
+
+#[test]
+fn test_snapshot_pins_latest_block() {
+    let (snapshot, adapter) = mock_snapshot();
+    let latest = block_on(snapshot.get_block_by_height(Context::new(), None)).unwrap();
+    assert_eq!(latest.header.height, 2);
+
+    // Blocks committed after the first read are invisible
+    commit_block(&adapter.blocks, 2);
+    let latest = block_on(snapshot.get_block_by_height(Context::new(), None)).unwrap();
+    assert_eq!(latest.header.height, 2);
+    assert_eq!(latest.header.exec_height, 1);
+    assert!(block_on(snapshot.get_block_by_height(Context::new(), Some(3))).is_err());
+    assert!(block_on(snapshot.get_commit_proof(Context::new(), 3)).is_err());
+
+    let block = block_on(snapshot.get_block_by_height(Context::new(), Some(1))).unwrap();
+    assert_eq!(block.header.height, 1);
+
+    // A new request sees them
+    let inner: Arc<Box<dyn APIAdapter>> = Arc::new(Box::new(adapter));
+    let snapshot = SnapshotAdapter::new(inner);
+    let latest = block_on(snapshot.get_block_by_height(Context::new(), None)).unwrap();
+    assert_eq!(latest.header.height, 3);
+}
+
+#[test]
+fn test_snapshot_pins_receipts() {
+    let (snapshot, adapter) = mock_snapshot();
+    block_on(snapshot.get_block_by_height(Context::new(), None)).unwrap();
+
+    // Receipts of blocks executed after the pinned one are invisible, even
+    // though they're stored
+    commit_block(&adapter.blocks, 2);
+    let receipts = block_on(snapshot.get_receipts_by_height(Context::new(), 1)).unwrap();
+    assert_eq!(receipts.len(), 1);
+    assert!(block_on(snapshot.get_receipts_by_height(Context::new(), 2)).is_err());
+
+    let pinned = adapter.receipts[&1][0].tx_hash.clone();
+    let receipt = block_on(snapshot.get_receipt_by_tx_hash(Context::new(), pinned)).unwrap();
+    assert_eq!(receipt.height, 1);
+    let unpinned = adapter.receipts[&2][0].tx_hash.clone();
+    assert!(block_on(snapshot.get_receipt_by_tx_hash(Context::new(), unpinned)).is_err());
+}
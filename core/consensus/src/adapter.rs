@@ -8,28 +8,39 @@ use overlord::types::OverlordMsg;
 use overlord::OverlordHandler;
 use parking_lot::RwLock;
 use tokio::sync::mpsc::error::TrySendError;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::mpsc::{channel, Receiver, Sender, UnboundedSender};
+use tracing::info_span;
+use tracing_futures::Instrument;
 
 use common_merkle::Merkle;
+use common_metrics::{
+    duration_secs, MemoryAccount, MemoryReservation, EXECUTOR_CYCLES_PER_BLOCK, EXECUTOR_SECONDS,
+};
 use protocol::traits::{
-    CommonConsensusAdapter, ConsensusAdapter, Context, ExecutorFactory, ExecutorParams,
-    ExecutorResp, Gossip, MemPool, MessageTarget, MixedTxHashes, Priority, Rpc, ServiceMapping,
-    Storage, SynchronizationAdapter,
+    CommitListener, CommonConsensusAdapter, ConsensusAdapter, Context, ExecutorFactory,
+    ExecutorParams, ExecutorResp, Gossip, MemPool, MessageTarget, MixedTxHashes, PeerMisbehavior,
+    PeerReporter, Priority, Rpc, RpcContext, ServiceMapping, Storage, SynchronizationAdapter,
 };
 use protocol::types::{
-    Address, Block, Bytes, Hash, MerkleRoot, Metadata, Proof, Receipt, SignedTransaction,
-    TransactionRequest, Validator,
+    Address, Block, Bytes, Hash, MerkleRoot, Metadata, Proof, Receipt, SignatureDomain,
+    SignedTransaction, TransactionRequest, Validator, VoteExtension,
 };
 use protocol::{fixed_codec::FixedCodec, ProtocolResult};
 
 use crate::consensus::gen_overlord_status;
-use crate::fixed_types::{FixedBlock, FixedHeight, FixedPill, FixedSignedTxs, PullTxsRequest};
-use crate::message::{BROADCAST_HEIGHT, RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_TXS};
+use crate::fixed_types::{
+    FixedBlock, FixedHeight, FixedPill, FixedProof, FixedSignedTxs, PullTxsRequest,
+};
+use crate::message::{
+    BROADCAST_HEIGHT, RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_PROOF, RPC_SYNC_PULL_TXS,
+};
 use crate::status::{ExecutedInfo, StatusAgent};
-use crate::util::ExecuteInfo;
+use crate::util::{txs_memory_size, ExecuteInfo};
 use crate::ConsensusError;
 
 const OVERLORD_GAP: usize = 10;
+// Synced blocks and transactions can be pulled from any peer
+const SYNC_RPC_RETRIES: usize = 2;
 
 pub struct OverlordConsensusAdapter<
     EF: ExecutorFactory<DB, S, Mapping>,
@@ -47,9 +58,19 @@ pub struct OverlordConsensusAdapter<
     trie_db:          Arc<DB>,
     service_mapping:  Arc<Mapping>,
     overlord_handler: RwLock<Option<OverlordHandler<FixedPill>>>,
+    commit_listeners: RwLock<Vec<Arc<dyn CommitListener>>>,
 
-    exec_queue:  Sender<ExecuteInfo>,
+    exec_queue:  Sender<QueuedExecute>,
     exec_demons: Option<ExecDemons<S, DB, EF, Mapping>>,
+    // Committed blocks can't be shed, their memory is only reported
+    exec_memory: MemoryAccount,
+}
+
+// A committed block waiting for execution, its memory is held till executed.
+#[derive(Debug)]
+pub struct QueuedExecute {
+    info:    ExecuteInfo,
+    _memory: MemoryReservation,
 }
 
 #[async_trait]
@@ -57,7 +78,7 @@ impl<EF, G, M, R, S, DB, Mapping> ConsensusAdapter
     for OverlordConsensusAdapter<EF, G, M, R, S, DB, Mapping>
 where
     EF: ExecutorFactory<DB, S, Mapping>,
-    G: Gossip + Sync + Send,
+    G: Gossip + PeerReporter + Sync + Send,
     R: Rpc + Sync + Send,
     M: MemPool + 'static,
     S: Storage + 'static,
@@ -70,8 +91,11 @@ where
         _height: u64,
         cycle_limit: u64,
         tx_num_limit: u64,
+        max_block_size: u64,
     ) -> ProtocolResult<MixedTxHashes> {
-        self.mempool.package(ctx, cycle_limit, tx_num_limit).await
+        self.mempool
+            .package(ctx, cycle_limit, tx_num_limit, max_block_size)
+            .await
     }
 
     async fn check_txs(&self, ctx: Context, check_txs: Vec<Hash>) -> ProtocolResult<()> {
@@ -123,6 +147,8 @@ where
         signed_txs: Vec<SignedTransaction>,
         cycles_limit: u64,
         timestamp: u64,
+        vote_extensions: Vec<VoteExtension>,
+        extra_data: Bytes,
     ) -> ProtocolResult<()> {
         let exec_info = ExecuteInfo {
             height,
@@ -134,10 +160,20 @@ where
             coinbase,
             cycles_limit,
             timestamp,
+            vote_extensions,
+            extra_data,
+            span: tracing::Span::current(),
         };
+        let memory = self
+            .exec_memory
+            .reserve(txs_memory_size(&exec_info.signed_txs));
 
         let mut tx = self.exec_queue.clone();
-        tx.try_send(exec_info).map_err(|e| match e {
+        let queued = QueuedExecute {
+            info:    exec_info,
+            _memory: memory,
+        };
+        tx.try_send(queued).map_err(|e| match e {
             TrySendError::Closed(_) => panic!("exec queue dropped!"),
             _ => ConsensusError::ExecuteErr(e.to_string()),
         })?;
@@ -161,6 +197,10 @@ where
         self.storage.load_overlord_wal().await
     }
 
+    fn report_bad(&self, ctx: Context, misbehavior: PeerMisbehavior) {
+        self.network.report(ctx, misbehavior);
+    }
+
     async fn pull_block(&self, ctx: Context, height: u64, end: &str) -> ProtocolResult<Block> {
         log::debug!("consensus: send rpc pull block {}", height);
         let res = self
@@ -219,10 +259,14 @@ where
         brake_ratio: u64,
         validators: Vec<Validator>,
     ) -> ProtocolResult<()> {
-        self.overlord_handler
-            .read()
-            .as_ref()
-            .expect("Please set the overlord handle first")
+        // Nodes not running consensus, such as full nodes, have no handler
+        let overlord_handler = self.overlord_handler.read();
+        let overlord_handler = match overlord_handler.as_ref() {
+            Some(handler) => handler,
+            None => return Ok(()),
+        };
+
+        overlord_handler
             .send_msg(
                 ctx,
                 OverlordMsg::RichStatus(gen_overlord_status(
@@ -261,7 +305,7 @@ where
         let res = self
             .rpc
             .call::<FixedHeight, FixedBlock>(
-                ctx,
+                ctx.with_rpc_retries(SYNC_RPC_RETRIES),
                 RPC_SYNC_PULL_BLOCK,
                 FixedHeight::new(height),
                 Priority::High,
@@ -270,6 +314,20 @@ where
         Ok(res.inner)
     }
 
+    /// Pull the proof of the block at the height from other nodes.
+    async fn get_proof_from_remote(&self, ctx: Context, height: u64) -> ProtocolResult<Proof> {
+        let res = self
+            .rpc
+            .call::<FixedHeight, FixedProof>(
+                ctx.with_rpc_retries(SYNC_RPC_RETRIES),
+                RPC_SYNC_PULL_PROOF,
+                FixedHeight::new(height),
+                Priority::High,
+            )
+            .await?;
+        Ok(res.inner)
+    }
+
     /// Pull signed transactions corresponding to the given hashes from other
     /// nodes.
     async fn get_txs_from_remote(
@@ -280,7 +338,7 @@ where
         let res = self
             .rpc
             .call::<PullTxsRequest, FixedSignedTxs>(
-                ctx,
+                ctx.with_rpc_retries(SYNC_RPC_RETRIES),
                 RPC_SYNC_PULL_TXS,
                 PullTxsRequest::new(hashes.to_vec()),
                 Priority::High,
@@ -304,7 +362,13 @@ where
 {
     /// Save a block to the database.
     async fn save_block(&self, _: Context, block: Block) -> ProtocolResult<()> {
-        self.storage.insert_block(block).await
+        let height = block.header.height;
+        self.storage.insert_block(block).await?;
+
+        for listener in self.commit_listeners.read().iter() {
+            listener.on_commit(height);
+        }
+        Ok(())
     }
 
     async fn save_proof(&self, _: Context, proof: Proof) -> ProtocolResult<()> {
@@ -376,6 +440,9 @@ where
             height,
             timestamp,
             cycles_limit: u64::max_value(),
+            proposer: caller.clone(),
+            vote_extensions: vec![],
+            extra_data: Bytes::new(),
         };
         let exec_resp = executor.read(&params, &caller, 1, &TransactionRequest {
             service_name: "metadata".to_string(),
@@ -386,9 +453,22 @@ where
         Ok(serde_json::from_str(&exec_resp.succeed_data).expect("Decode metadata failed!"))
     }
 
-    fn set_args(&self, _context: Context, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64) {
-        self.mempool
-            .set_args(timeout_gap, cycles_limit, max_tx_size);
+    fn set_args(
+        &self,
+        _context: Context,
+        timeout_gap: u64,
+        cycles_limit: u64,
+        max_tx_size: u64,
+        signature_domain: Option<SignatureDomain>,
+        halt_height: u64,
+    ) {
+        self.mempool.set_args(
+            timeout_gap,
+            cycles_limit,
+            max_tx_size,
+            signature_domain,
+            halt_height,
+        );
     }
 }
 
@@ -428,8 +508,10 @@ where
             trie_db,
             service_mapping,
             overlord_handler: RwLock::new(None),
+            commit_listeners: RwLock::new(vec![]),
             exec_queue,
             exec_demons,
+            exec_memory: MemoryAccount::new("consensus_exec"),
         };
 
         Ok(adapter)
@@ -440,9 +522,15 @@ where
         self.exec_demons.take().unwrap()
     }
 
+    /// Set by validators, status of overlord is updated through it.
     pub fn set_overlord_handler(&self, handler: OverlordHandler<FixedPill>) {
         *self.overlord_handler.write() = Some(handler)
     }
+
+    /// Notify the listener of every block saved from now on.
+    pub fn add_commit_listener(&self, listener: Arc<dyn CommitListener>) {
+        self.commit_listeners.write().push(listener)
+    }
 }
 
 #[derive(Debug)]
@@ -452,8 +540,9 @@ pub struct ExecDemons<S, DB, EF, Mapping> {
     service_mapping: Arc<Mapping>,
 
     pin_ef: PhantomData<EF>,
-    queue:  Receiver<ExecuteInfo>,
+    queue:  Receiver<QueuedExecute>,
     status: StatusAgent,
+    hooks:  Vec<UnboundedSender<u64>>,
 }
 
 impl<S, DB, EF, Mapping> ExecDemons<S, DB, EF, Mapping>
@@ -467,7 +556,7 @@ where
         storage: Arc<S>,
         trie_db: Arc<DB>,
         service_mapping: Arc<Mapping>,
-        rx: Receiver<ExecuteInfo>,
+        rx: Receiver<QueuedExecute>,
         status_agent: StatusAgent,
     ) -> Self {
         ExecDemons {
@@ -477,9 +566,16 @@ where
             queue: rx,
             pin_ef: PhantomData,
             status: status_agent,
+            hooks: vec![],
         }
     }
 
+    /// Heights are sent to the hook once their receipts are saved, a closed
+    /// hook is dropped.
+    pub fn on_executed(&mut self, hook: UnboundedSender<u64>) {
+        self.hooks.push(hook);
+    }
+
     pub async fn run(mut self) {
         loop {
             if let Err(e) = self.process().await {
@@ -489,8 +585,9 @@ where
     }
 
     async fn process(&mut self) -> ProtocolResult<()> {
-        if let Some(info) = self.queue.recv().await {
+        if let Some(QueuedExecute { info, _memory }) = self.queue.recv().await {
             let height = info.height;
+            let span = info_span!(parent: &info.span, "block_execute", height = height);
             let txs = info.signed_txs.clone();
             let order_root = info.order_root.clone();
             let state_root = self.status.to_inner().get_latest_state_root();
@@ -507,8 +604,13 @@ where
                 height,
                 timestamp: info.timestamp,
                 cycles_limit: info.cycles_limit,
+                proposer: info.coinbase.clone(),
+                vote_extensions: info.vote_extensions.clone(),
+                extra_data: info.extra_data.clone(),
             };
-            let resp = executor.exec(&exec_params, &txs)?;
+            let resp = span.in_scope(|| executor.exec(&exec_params, &txs))?;
+            EXECUTOR_SECONDS.observe(duration_secs(now));
+            EXECUTOR_CYCLES_PER_BLOCK.observe(resp.all_cycles_used as f64);
             log::info!(
                 "[consensus-adapter]: exec transactions cost {:?} transactions len {:?}",
                 now.elapsed(),
@@ -516,7 +618,13 @@ where
             );
 
             let now = Instant::now();
-            self.save_receipts(resp.receipts.clone()).await?;
+            self.save_receipts(resp.receipts.clone())
+                .instrument(info_span!(
+                    parent: &span,
+                    "block_store_receipts",
+                    height = height
+                ))
+                .await?;
             log::info!(
                 "[consensus-adapter]: save receipts cost {:?} receipts len {:?}",
                 now.elapsed(),
@@ -524,6 +632,7 @@ where
             );
             self.status
                 .update_by_executed(gen_executed_info(resp.clone(), height, order_root));
+            self.hooks.retain(|hook| hook.send(height).is_ok());
         } else {
             return Err(ConsensusError::Other("Queue disconnect".to_string()).into());
         }
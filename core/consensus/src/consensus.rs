@@ -8,9 +8,9 @@ use overlord::types::{
 };
 use overlord::{DurationConfig, Overlord, OverlordHandler};
 
-use protocol::traits::{Consensus, ConsensusAdapter, NodeInfo};
-use protocol::types::Validator;
-use protocol::ProtocolResult;
+use protocol::traits::{Consensus, ConsensusAdapter, NodeInfo, VoteExtender};
+use protocol::types::{PayloadLimits, Validator, VoteExtension};
+use protocol::{Bytes, ProtocolResult};
 
 use crate::engine::ConsensusEngine;
 use crate::fixed_types::FixedPill;
@@ -27,6 +27,8 @@ pub struct OverlordConsensus<Adapter: ConsensusAdapter + 'static> {
     >,
     /// An overlord consensus protocol handler.
     handler: OverlordHandler<FixedPill>,
+    /// Vote extensions are handled by the engine rather than overlord.
+    engine:  Arc<ConsensusEngine<Adapter>>,
 }
 
 #[async_trait]
@@ -66,6 +68,12 @@ impl<Adapter: ConsensusAdapter + 'static> Consensus for OverlordConsensus<Adapte
             .map_err(|e| ConsensusError::OverlordErr(Box::new(e)))?;
         Ok(())
     }
+
+    async fn set_vote_extension(&self, ctx: Context, extension: Vec<u8>) -> ProtocolResult<()> {
+        let extension: VoteExtension = rlp::decode(&extension)
+            .map_err(|_| ConsensusError::DecodeErr(ConsensusType::VoteExtension))?;
+        self.engine.receive_vote_extension(ctx, extension)
+    }
 }
 
 impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
@@ -76,6 +84,10 @@ impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
         txs_wal: Arc<SignedTxsWAL>,
         adapter: Arc<Adapter>,
         lock: Arc<Mutex<()>>,
+        max_timestamp_drift: u64,
+        extra_data: Bytes,
+        payload_limits: PayloadLimits,
+        vote_extender: Option<Arc<dyn VoteExtender>>,
     ) -> Self {
         let engine = Arc::new(ConsensusEngine::new(
             status_agent.clone(),
@@ -84,6 +96,10 @@ impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
             Arc::clone(&adapter),
             Arc::clone(&crypto),
             lock,
+            max_timestamp_drift,
+            extra_data,
+            payload_limits,
+            vote_extender,
         ));
 
         let overlord = Overlord::new(
@@ -113,8 +129,9 @@ impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
         }
 
         Self {
-            inner:   Arc::new(overlord),
+            inner: Arc::new(overlord),
             handler: overlord_handler,
+            engine,
         }
     }
 
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::error::Error;
 use std::sync::Arc;
@@ -7,36 +7,50 @@ use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use async_trait::async_trait;
 use futures::lock::Mutex;
 use futures_timer::Delay;
-use log::error;
+use log::{error, warn};
 use moodyblues_sdk::trace;
-use overlord::types::{Commit, Node, OverlordMsg, Status};
-use overlord::{Consensus as Engine, DurationConfig, Wal};
+use overlord::types::{Commit, Node, OverlordMsg, Status, VoteType};
+use overlord::{Consensus as Engine, Crypto, DurationConfig, Wal};
 use parking_lot::RwLock;
 use rlp::Encodable;
 use serde_json::json;
+use tokio::task::JoinHandle;
+use tracing::{field, info_span};
+use tracing_futures::Instrument;
 
 use common_crypto::BlsPublicKey;
 use common_merkle::Merkle;
+use common_metrics::{CONSENSUS_HEIGHT, CONSENSUS_ROUND_SECONDS, CONSENSUS_VIEW_CHANGE_TOTAL};
 
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::{ConsensusAdapter, Context, MessageTarget, NodeInfo};
+use protocol::traits::{
+    ConsensusAdapter, Context, MessageTarget, MixedTxHashes, NodeInfo, PeerMisbehavior,
+    TraceContext, VoteExtender,
+};
 use protocol::types::{
-    Address, Block, BlockHeader, Hash, MerkleRoot, Metadata, Pill, Proof, SignedTransaction,
-    Validator,
+    Address, Block, BlockHeader, ChainSpec, Feature, Hash, MerkleRoot, Metadata, PayloadLimits,
+    Pill, Proof, SignedTransaction, Validator, VoteExtension,
 };
 use protocol::{Bytes, ProtocolError, ProtocolResult};
 
 use crate::fixed_types::FixedPill;
 use crate::message::{
     END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE, END_GOSSIP_SIGNED_PROPOSAL,
-    END_GOSSIP_SIGNED_VOTE,
+    END_GOSSIP_SIGNED_VOTE, END_GOSSIP_VOTE_EXTENSION,
 };
 use crate::status::StatusAgent;
-use crate::util::{check_list_roots, OverlordCrypto};
+use crate::util::{
+    check_extra_data, check_features, check_list_roots, check_timestamp, is_expired, proof_voters,
+    txs_size, OverlordCrypto,
+};
 use crate::wal::SignedTxsWAL;
 use crate::ConsensusError;
 
 const RETRY_COMMIT_INTERVAL: u64 = 1000;
+/// Max bytes of the payload of a vote extension.
+pub const MAX_VOTE_EXTENSION_SIZE: usize = 1024;
+/// Default max milliseconds timestamps of proposals are off the local clock.
+pub const DEFAULT_MAX_TIMESTAMP_DRIFT: u64 = 30_000;
 
 /// validator is for create new block, and authority is for build overlord
 /// status.
@@ -44,11 +58,36 @@ pub struct ConsensusEngine<Adapter> {
     status_agent:   StatusAgent,
     node_info:      NodeInfo,
     exemption_hash: RwLock<HashSet<Bytes>>,
+    // Blocks of proposals at the current height with timestamps within the
+    // drift, locked validators re-propose them in later rounds once the drift
+    // may be exceeded.
+    timely_blocks:  RwLock<HashSet<Hash>>,
 
     adapter: Arc<Adapter>,
     txs_wal: Arc<SignedTxsWAL>,
     crypto:  Arc<OverlordCrypto>,
     lock:    Arc<Mutex<()>>,
+
+    // Max milliseconds timestamps of proposals are off the local clock
+    max_timestamp_drift: u64,
+    // Extra data of headers proposed by self
+    extra_data:          Bytes,
+    // Max payload sizes of methods declared by services
+    payload_limits:      PayloadLimits,
+
+    // Transactions of next height packaged in background once a block is
+    // committed, the proposal of next height takes them instead of waiting.
+    pipelined: RwLock<Option<PipelinedPackage>>,
+
+    vote_extender:   Option<Arc<dyn VoteExtender>>,
+    // Vote extensions of recent heights by their voters and precommitted
+    // blocks, the ones of the previous block are aggregated into proposals.
+    vote_extensions: RwLock<BTreeMap<u64, HashMap<(Address, Hash), VoteExtension>>>,
+}
+
+struct PipelinedPackage {
+    height: u64,
+    handle: JoinHandle<ProtocolResult<MixedTxHashes>>,
 }
 
 #[async_trait]
@@ -58,18 +97,453 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         ctx: Context,
         next_height: u64,
     ) -> Result<(FixedPill, Bytes), Box<dyn Error + Send>> {
-        let current_consensus_status = self.status_agent.to_inner();
+        let span = info_span!(
+            "block_propose",
+            height = next_height,
+            trace_id = field::debug(ctx.trace_id())
+        );
+
+        self.propose_block(ctx, next_height).instrument(span).await
+    }
+
+    async fn check_block(
+        &self,
+        ctx: Context,
+        next_height: u64,
+        hash: Bytes,
+        block: FixedPill,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        let span = info_span!(
+            "block_check",
+            height = next_height,
+            trace_id = field::debug(ctx.trace_id())
+        );
+
+        self.check_proposal(ctx, next_height, hash, block)
+            .instrument(span)
+            .await
+    }
+
+    /// **TODO:** the overlord interface and process needs to be changed.
+    /// Get the `FixedSignedTxs` from the argument rather than get it from
+    /// mempool.
+    async fn commit(
+        &self,
+        ctx: Context,
+        current_height: u64,
+        commit: Commit<FixedPill>,
+    ) -> Result<Status, Box<dyn Error + Send>> {
+        let span = info_span!(
+            "block_commit",
+            height = current_height,
+            trace_id = field::debug(ctx.trace_id())
+        );
+
+        self.commit_block(ctx, current_height, commit)
+            .instrument(span)
+            .await
+    }
+
+    /// Only signed proposal and aggregated vote will be broadcast to others.
+    async fn broadcast_to_other(
+        &self,
+        ctx: Context,
+        msg: OverlordMsg<FixedPill>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        let (end, msg) = match msg {
+            OverlordMsg::SignedProposal(sp) => {
+                let bytes = sp.rlp_bytes();
+                (END_GOSSIP_SIGNED_PROPOSAL, bytes)
+            }
+
+            OverlordMsg::AggregatedVote(av) => {
+                let bytes = av.rlp_bytes();
+                (END_GOSSIP_AGGREGATED_VOTE, bytes)
+            }
+
+            OverlordMsg::SignedChoke(sc) => {
+                let bytes = sc.rlp_bytes();
+                (END_GOSSIP_SIGNED_CHOKE, bytes)
+            }
+
+            _ => unreachable!(),
+        };
+
+        // Other nodes trace the message by the id carried in the network header.
+        let ctx = ctx.with_new_trace();
+        self.adapter
+            .transmit(ctx, msg, end, MessageTarget::Broadcast)
+            .await?;
+        Ok(())
+    }
+
+    /// Only signed vote will be transmit to the relayer.
+    async fn transmit_to_relayer(
+        &self,
+        ctx: Context,
+        addr: Bytes,
+        msg: OverlordMsg<FixedPill>,
+    ) -> Result<(), Box<dyn Error + Send>> {
+        match msg {
+            OverlordMsg::SignedVote(sv) => {
+                let msg = sv.rlp_bytes();
+                self.adapter
+                    .transmit(
+                        ctx.clone(),
+                        msg,
+                        END_GOSSIP_SIGNED_VOTE,
+                        MessageTarget::Specified(Address::from_bytes(addr)?),
+                    )
+                    .await?;
+
+                // Extensions are attached to precommits, they're not part of
+                // the vote since overlord doesn't carry them, but signed over
+                // the precommitted block. Nil precommits carry nothing.
+                if sv.vote.vote_type == VoteType::Precommit && !sv.vote.block_hash.is_empty() {
+                    let block_hash = Hash::from_bytes(sv.vote.block_hash.clone())?;
+                    if let Err(e) = self.extend_vote(ctx, sv.vote.height, block_hash).await {
+                        warn!("extend vote of height {} {}", sv.vote.height, e);
+                    }
+                }
+            }
+            OverlordMsg::AggregatedVote(av) => {
+                let msg = av.rlp_bytes();
+                self.adapter
+                    .transmit(
+                        ctx,
+                        msg,
+                        END_GOSSIP_AGGREGATED_VOTE,
+                        MessageTarget::Specified(Address::from_bytes(addr)?),
+                    )
+                    .await?;
+            }
+            _ => unreachable!(),
+        };
+        Ok(())
+    }
+
+    /// This function is rarely used, so get the authority list from the
+    /// RocksDB.
+    async fn get_authority_list(
+        &self,
+        ctx: Context,
+        next_height: u64,
+    ) -> Result<Vec<Node>, Box<dyn Error + Send>> {
+        if next_height == 0 {
+            return Ok(vec![]);
+        }
 
-        let (ordered_tx_hashes, propose_hashes) = self
+        let old_block = self
             .adapter
-            .get_txs_from_mempool(
+            .get_block_by_height(ctx.clone(), next_height - 1)
+            .await?;
+        let old_metadata = self.adapter.get_metadata(
+            ctx,
+            old_block.header.state_root.clone(),
+            old_block.header.timestamp,
+            old_block.header.height,
+        )?;
+        let mut old_validators = old_metadata
+            .verifier_list
+            .into_iter()
+            .map(|v| Node {
+                address:        v.address.as_bytes(),
+                propose_weight: v.propose_weight,
+                vote_weight:    v.vote_weight,
+            })
+            .collect::<Vec<_>>();
+        old_validators.sort();
+        Ok(old_validators)
+    }
+}
+
+#[async_trait]
+impl<Adapter: ConsensusAdapter + 'static> Wal for ConsensusEngine<Adapter> {
+    async fn save(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>> {
+        self.adapter
+            .save_overlord_wal(Context::new(), info)
+            .await
+            .map_err(|e| ProtocolError::from(ConsensusError::Other(e.to_string())))?;
+        Ok(())
+    }
+
+    async fn load(&self) -> Result<Option<Bytes>, Box<dyn Error + Send>> {
+        let res = self.adapter.load_overlord_wal(Context::new()).await.ok();
+        Ok(res)
+    }
+}
+
+impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
+    pub fn new(
+        status_agent: StatusAgent,
+        node_info: NodeInfo,
+        wal: Arc<SignedTxsWAL>,
+        adapter: Arc<Adapter>,
+        crypto: Arc<OverlordCrypto>,
+        lock: Arc<Mutex<()>>,
+        max_timestamp_drift: u64,
+        extra_data: Bytes,
+        payload_limits: PayloadLimits,
+        vote_extender: Option<Arc<dyn VoteExtender>>,
+    ) -> Self {
+        Self {
+            status_agent,
+            node_info,
+            exemption_hash: RwLock::new(HashSet::new()),
+            timely_blocks: RwLock::new(HashSet::new()),
+            txs_wal: wal,
+            adapter,
+            crypto,
+            lock,
+            max_timestamp_drift,
+            extra_data,
+            payload_limits,
+            pipelined: RwLock::new(None),
+            vote_extender,
+            vote_extensions: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Sign the payload of the height from the extender over the block
+    /// precommitted and broadcast it to validators, once a block.
+    async fn extend_vote(&self, ctx: Context, height: u64, block_hash: Hash) -> ProtocolResult<()> {
+        let extender = match self.vote_extender.as_ref() {
+            Some(extender) => extender,
+            None => return Ok(()),
+        };
+        // Extensions are carried by blocks of the next height
+        let status = self.status_agent.to_inner();
+        if !status
+            .chain_spec
+            .is_active(Feature::VoteExtension, height + 1)
+        {
+            return Ok(());
+        }
+        let voter = self.node_info.self_address.clone();
+        let is_validator = status.validators.iter().any(|v| v.address == voter);
+        if !is_validator {
+            return Ok(());
+        }
+        let key = (voter.clone(), block_hash.clone());
+        let extended = self
+            .vote_extensions
+            .read()
+            .get(&height)
+            .map_or(false, |extensions| extensions.contains_key(&key));
+        if extended {
+            return Ok(());
+        }
+
+        let payload = match extender.extend_vote(ctx.clone(), height).await? {
+            Some(payload) => payload,
+            None => return Ok(()),
+        };
+        let mut extension = VoteExtension {
+            height,
+            voter,
+            block_hash,
+            payload,
+            signature: Bytes::new(),
+        };
+        extension.signature = self.crypto.sign_vote_extension(&extension)?;
+        self.verify_vote_extension(&extension)?;
+
+        let msg = extension.rlp_bytes();
+        self.insert_vote_extension(extension);
+        self.adapter
+            .transmit(
                 ctx,
-                next_height,
-                current_consensus_status.cycles_limit,
-                current_consensus_status.tx_num_limit,
+                msg,
+                END_GOSSIP_VOTE_EXTENSION,
+                MessageTarget::Broadcast,
             )
-            .await?
-            .clap();
+            .await
+    }
+
+    /// Keep vote extensions of the current height and the next one from
+    /// validators, others are ignored.
+    pub fn receive_vote_extension(
+        &self,
+        ctx: Context,
+        extension: VoteExtension,
+    ) -> ProtocolResult<()> {
+        let status = self.status_agent.to_inner();
+        let current_height = status.current_height;
+        if extension.height < current_height || extension.height > current_height + 1 {
+            return Ok(());
+        }
+        if !status
+            .chain_spec
+            .is_active(Feature::VoteExtension, extension.height + 1)
+        {
+            return Ok(());
+        }
+
+        if let Err(e) = self.verify_vote_extension(&extension) {
+            self.adapter
+                .report_bad(ctx, PeerMisbehavior::InvalidVoteExtension);
+            return Err(e);
+        }
+        self.insert_vote_extension(extension);
+        Ok(())
+    }
+
+    fn insert_vote_extension(&self, extension: VoteExtension) {
+        self.vote_extensions
+            .write()
+            .entry(extension.height)
+            .or_insert_with(HashMap::new)
+            .insert(
+                (extension.voter.clone(), extension.block_hash.clone()),
+                extension,
+            );
+    }
+
+    fn verify_vote_extension(&self, extension: &VoteExtension) -> ProtocolResult<()> {
+        let invalid = |reason: String| ConsensusError::InvalidVoteExtension {
+            voter: extension.voter.clone(),
+            reason,
+        };
+
+        if extension.payload.len() > MAX_VOTE_EXTENSION_SIZE {
+            return Err(invalid(format!(
+                "payload of {} bytes exceeds {}",
+                extension.payload.len(),
+                MAX_VOTE_EXTENSION_SIZE
+            ))
+            .into());
+        }
+        self.crypto
+            .verify_signature(
+                extension.signature.clone(),
+                extension.signing_hash().as_bytes(),
+                extension.voter.as_bytes(),
+            )
+            .map_err(|e| invalid(e.to_string()))?;
+
+        Ok(())
+    }
+
+    // Voters of the previous block precommitting it in the proof of the
+    // header, only their extensions are carried.
+    async fn precommit_voters(
+        &self,
+        ctx: Context,
+        header: &BlockHeader,
+    ) -> ProtocolResult<HashSet<Address>> {
+        let previous = self
+            .adapter
+            .get_block_by_height(ctx, header.height - 1)
+            .await?;
+        let voters = proof_voters(&previous.header.validators, &header.proof)
+            .into_iter()
+            .map(|v| v.address.clone())
+            .collect();
+
+        Ok(voters)
+    }
+
+    // Extensions of precommits in the proof of the header ordered by voters,
+    // the ones failing to verify under the current validators are dropped.
+    async fn aggregate_vote_extensions(
+        &self,
+        ctx: Context,
+        header: &BlockHeader,
+    ) -> ProtocolResult<Vec<VoteExtension>> {
+        let status = self.status_agent.to_inner();
+        if !status
+            .chain_spec
+            .is_active(Feature::VoteExtension, header.height)
+        {
+            return Ok(vec![]);
+        }
+
+        let voters = self.precommit_voters(ctx, header).await?;
+        let mut extensions = self
+            .vote_extensions
+            .read()
+            .get(&(header.height - 1))
+            .map(|extensions| {
+                extensions
+                    .values()
+                    .filter(|e| e.block_hash == header.pre_hash && voters.contains(&e.voter))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        extensions.retain(|extension| self.verify_vote_extension(extension).is_ok());
+        extensions.sort_by(|a, b| a.voter.cmp(&b.voter));
+        Ok(extensions)
+    }
+
+    async fn check_vote_extensions(&self, ctx: Context, block: &Block) -> ProtocolResult<()> {
+        if block.vote_extensions.is_empty() {
+            return Ok(());
+        }
+
+        let header = &block.header;
+        let invalid = |extension: &VoteExtension, reason: String| {
+            ProtocolError::from(ConsensusError::InvalidVoteExtension {
+                voter: extension.voter.clone(),
+                reason,
+            })
+        };
+        let status = self.status_agent.to_inner();
+        if !status
+            .chain_spec
+            .is_active(Feature::VoteExtension, header.height)
+        {
+            let reason = format!("not activated at {}", header.height);
+            return Err(invalid(&block.vote_extensions[0], reason));
+        }
+
+        let height = header.height - 1;
+        let voters = self.precommit_voters(ctx, header).await?;
+        for (i, extension) in block.vote_extensions.iter().enumerate() {
+            if extension.height != height {
+                let reason = format!("height {} expect {}", extension.height, height);
+                return Err(invalid(extension, reason));
+            }
+            // Bound to a precommit of the previous block in the proof
+            if extension.block_hash != header.pre_hash || !voters.contains(&extension.voter) {
+                let reason = "no precommit in the proof".to_owned();
+                return Err(invalid(extension, reason));
+            }
+            // Sorted by voters, so that a voter has at most one extension
+            if i > 0 && block.vote_extensions[i - 1].voter >= extension.voter {
+                let reason = "unordered or duplicated voter".to_owned();
+                return Err(invalid(extension, reason));
+            }
+            self.verify_vote_extension(extension)?;
+        }
+
+        Ok(())
+    }
+
+    async fn propose_block(
+        &self,
+        ctx: Context,
+        next_height: u64,
+    ) -> Result<(FixedPill, Bytes), Box<dyn Error + Send>> {
+        let current_consensus_status = self.status_agent.to_inner();
+        check_features(&current_consensus_status.chain_spec, next_height)?;
+
+        let (ordered_tx_hashes, propose_hashes) = match self.take_pipelined(next_height).await {
+            Some(mixed_tx_hashes) => mixed_tx_hashes,
+            None => {
+                self.adapter
+                    .get_txs_from_mempool(
+                        ctx.clone(),
+                        next_height,
+                        current_consensus_status.cycles_limit,
+                        current_consensus_status.tx_num_limit,
+                        current_consensus_status.max_block_size,
+                    )
+                    .await?
+            }
+        }
+        .clap();
 
         if current_consensus_status.current_height != next_height - 1 {
             return Err(ProtocolError::from(ConsensusError::MissingBlockHeader(
@@ -78,6 +552,11 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             .into());
         }
 
+        // Block time never goes back, even if the local clock does
+        let timestamp = time_now().max(current_consensus_status.current_timestamp + 1);
+        let ordered_tx_hashes = self
+            .drop_expired_txs(ctx.clone(), ordered_tx_hashes, timestamp)
+            .await?;
         let order_root = Merkle::from_hashes(ordered_tx_hashes.clone()).get_root_hash();
 
         let state_root = current_consensus_status.get_latest_state_root();
@@ -86,7 +565,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             pre_hash: current_consensus_status.current_hash,
             height: next_height,
             exec_height: current_consensus_status.exec_height,
-            timestamp: time_now(),
+            timestamp,
             logs_bloom: current_consensus_status.list_logs_bloom,
             order_root: order_root.unwrap_or_else(Hash::from_empty),
             confirm_root: current_consensus_status.list_confirm_root,
@@ -97,10 +576,13 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             proof: current_consensus_status.current_proof.clone(),
             validator_version: 0u64,
             validators: current_consensus_status.validators.clone(),
+            extra_data: self.extra_data_at(&current_consensus_status.chain_spec, next_height),
         };
+        let vote_extensions = self.aggregate_vote_extensions(ctx.clone(), &header).await?;
         let block = Block {
             header,
             ordered_tx_hashes,
+            vote_extensions,
         };
 
         let pill = Pill {
@@ -117,7 +599,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         Ok((fixed_pill, hash))
     }
 
-    async fn check_block(
+    async fn check_proposal(
         &self,
         ctx: Context,
         next_height: u64,
@@ -125,6 +607,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         block: FixedPill,
     ) -> Result<(), Box<dyn Error + Send>> {
         let time = Instant::now();
+        check_features(&self.status_agent.to_inner().chain_spec, next_height)?;
 
         let order_hashes = block.get_ordered_hashes();
         let order_hashes_len = order_hashes.len();
@@ -134,7 +617,29 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         // If the block is proposed by self, it does not need to check. Get full signed
         // transactions directly.
         if !exemption {
-            self.check_block_roots(&block.inner.block.header)?;
+            if let Err(e) = self.check_block_roots(&block.inner.block.header) {
+                self.adapter.report_bad(ctx, PeerMisbehavior::InvalidBlock);
+                return Err(e.into());
+            }
+            if let Err(e) = self.check_proposal_timestamp(next_height, &block.inner.block) {
+                self.adapter.report_bad(ctx, PeerMisbehavior::InvalidBlock);
+                return Err(e.into());
+            }
+            if let Err(e) = self
+                .check_vote_extensions(ctx.clone(), &block.inner.block)
+                .await
+            {
+                self.adapter.report_bad(ctx, PeerMisbehavior::InvalidBlock);
+                return Err(e.into());
+            }
+            if let Err(e) = check_extra_data(
+                &self.status_agent.to_inner().chain_spec,
+                next_height,
+                &block.inner.block.header.extra_data,
+            ) {
+                self.adapter.report_bad(ctx, PeerMisbehavior::InvalidBlock);
+                return Err(e.into());
+            }
             self.adapter
                 .check_txs(ctx.clone(), order_hashes.clone())
                 .await?;
@@ -153,7 +658,46 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             Instant::now() - time
         );
         let time = Instant::now();
-        let txs = self.adapter.get_full_txs(ctx, order_hashes).await?;
+        let txs = self.adapter.get_full_txs(ctx.clone(), order_hashes).await?;
+
+        if !exemption {
+            let timestamp = block.inner.block.header.timestamp;
+            if let Some(stx) = txs.iter().find(|stx| is_expired(stx, timestamp)) {
+                self.adapter.report_bad(ctx, PeerMisbehavior::InvalidBlock);
+                return Err(
+                    ProtocolError::from(ConsensusError::ExpiredTx(stx.tx_hash.clone())).into(),
+                );
+            }
+            // Limits of upgraded binaries are enforced once the chain
+            // activates them, proposals stay valid for old validators before
+            let chain_spec = self.status_agent.to_inner().chain_spec;
+            let exceeded = if chain_spec.is_active(Feature::PayloadLimits, next_height) {
+                txs.iter().find_map(|stx| {
+                    self.payload_limits
+                        .exceeded_by(&stx.raw.request)
+                        .map(|limit| (stx, limit))
+                })
+            } else {
+                None
+            };
+            if let Some((stx, limit)) = exceeded {
+                self.adapter.report_bad(ctx, PeerMisbehavior::InvalidBlock);
+                return Err(ProtocolError::from(ConsensusError::ExceedPayloadLimit {
+                    tx_hash: stx.tx_hash.clone(),
+                    limit,
+                })
+                .into());
+            }
+
+            let limit = self.status_agent.to_inner().max_block_size;
+            let size = txs_size(&txs)?;
+            if limit != 0 && size > limit {
+                self.adapter.report_bad(ctx, PeerMisbehavior::InvalidBlock);
+                return Err(
+                    ProtocolError::from(ConsensusError::ExceedBlockSize { size, limit }).into(),
+                );
+            }
+        }
 
         log::info!(
             "[consensus-engine]: get txs cost {:?}",
@@ -171,10 +715,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         Ok(())
     }
 
-    /// **TODO:** the overlord interface and process needs to be changed.
-    /// Get the `FixedSignedTxs` from the argument rather than get it from
-    /// mempool.
-    async fn commit(
+    async fn commit_block(
         &self,
         ctx: Context,
         current_height: u64,
@@ -209,7 +750,6 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         let signature = commit.proof.signature.signature.clone();
         let bitmap = commit.proof.signature.address_bitmap.clone();
 
-        // Sorage save the lastest proof.
         let proof = Proof {
             height: commit.proof.height,
             round: commit.proof.round,
@@ -218,8 +758,6 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             bitmap,
         };
 
-        self.adapter.save_proof(ctx.clone(), proof.clone()).await?;
-
         // Get full transactions from mempool. If is error, try get from wal.
         let ordered_tx_hashes = pill.block.ordered_tx_hashes.clone();
         let signed_txs = match self
@@ -241,6 +779,8 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
                     pill.block.header.timestamp,
                     Hash::digest(pill.block.encode_fixed()?),
                     signed_txs.clone(),
+                    pill.block.vote_extensions.clone(),
+                    pill.block.header.extra_data.clone(),
                 )
                 .await
                 .is_ok()
@@ -252,6 +792,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         }
 
         trace_block(&pill.block);
+        observe_commit(&pill.block.header, proof.round);
         let block_exec_height = pill.block.header.exec_height;
         let metadata = self.adapter.get_metadata(
             ctx.clone(),
@@ -266,17 +807,34 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         );
 
         self.update_status(metadata, pill.block, proof, signed_txs)
+            .instrument(info_span!("block_store", height = current_height))
             .await?;
 
         self.adapter
             .flush_mempool(ctx.clone(), &ordered_tx_hashes)
             .await?;
+        self.pipeline_package(ctx.clone(), current_height + 1);
 
         self.adapter
             .broadcast_height(ctx.clone(), current_height)
             .await?;
         self.txs_wal.remove(block_exec_height)?;
 
+        // Proposer of the next height aggregates extensions of this one, ours
+        // might not be sent if overlord didn't transmit our precommit.
+        let committed_hash = Hash::from_bytes(commit.proof.block_hash.clone())?;
+        if let Err(e) = self
+            .extend_vote(ctx.clone(), current_height, committed_hash)
+            .await
+        {
+            warn!("extend vote of height {} {}", current_height, e);
+        }
+        {
+            let mut extensions = self.vote_extensions.write();
+            *extensions = extensions.split_off(&current_height);
+        }
+
+        self.timely_blocks.write().clear();
         let mut set = self.exemption_hash.write();
         set.clear();
 
@@ -296,140 +854,68 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         Ok(status)
     }
 
-    /// Only signed proposal and aggregated vote will be broadcast to others.
-    async fn broadcast_to_other(
-        &self,
-        ctx: Context,
-        msg: OverlordMsg<FixedPill>,
-    ) -> Result<(), Box<dyn Error + Send>> {
-        let (end, msg) = match msg {
-            OverlordMsg::SignedProposal(sp) => {
-                let bytes = sp.rlp_bytes();
-                (END_GOSSIP_SIGNED_PROPOSAL, bytes)
-            }
-
-            OverlordMsg::AggregatedVote(av) => {
-                let bytes = av.rlp_bytes();
-                (END_GOSSIP_AGGREGATED_VOTE, bytes)
-            }
-
-            OverlordMsg::SignedChoke(sc) => {
-                let bytes = sc.rlp_bytes();
-                (END_GOSSIP_SIGNED_CHOKE, bytes)
-            }
-
-            _ => unreachable!(),
-        };
-
-        self.adapter
-            .transmit(ctx, msg, end, MessageTarget::Broadcast)
-            .await?;
-        Ok(())
-    }
+    /// Package transactions of next height in background, while the commit
+    /// returns and overlord steps into next height. It starts once committed
+    /// transactions are flushed from mempool, execution of the block is up to
+    /// exec demons and isn't waited for either way.
+    fn pipeline_package(&self, ctx: Context, next_height: u64) {
+        let status = self.status_agent.to_inner();
+        let adapter = Arc::clone(&self.adapter);
+
+        let handle = tokio::spawn(async move {
+            adapter
+                .get_txs_from_mempool(
+                    ctx,
+                    next_height,
+                    status.cycles_limit,
+                    status.tx_num_limit,
+                    status.max_block_size,
+                )
+                .await
+        });
 
-    /// Only signed vote will be transmit to the relayer.
-    async fn transmit_to_relayer(
-        &self,
-        ctx: Context,
-        addr: Bytes,
-        msg: OverlordMsg<FixedPill>,
-    ) -> Result<(), Box<dyn Error + Send>> {
-        match msg {
-            OverlordMsg::SignedVote(sv) => {
-                let msg = sv.rlp_bytes();
-                self.adapter
-                    .transmit(
-                        ctx,
-                        msg,
-                        END_GOSSIP_SIGNED_VOTE,
-                        MessageTarget::Specified(Address::from_bytes(addr)?),
-                    )
-                    .await?;
-            }
-            OverlordMsg::AggregatedVote(av) => {
-                let msg = av.rlp_bytes();
-                self.adapter
-                    .transmit(
-                        ctx,
-                        msg,
-                        END_GOSSIP_AGGREGATED_VOTE,
-                        MessageTarget::Specified(Address::from_bytes(addr)?),
-                    )
-                    .await?;
-            }
-            _ => unreachable!(),
+        let pipelined = PipelinedPackage {
+            height: next_height,
+            handle,
         };
-        Ok(())
+        self.pipelined.write().replace(pipelined);
     }
 
-    /// This function is rarely used, so get the authority list from the
-    /// RocksDB.
-    async fn get_authority_list(
+    // Transactions may be packaged before the block timestamp, by pipelined
+    // packaging for example, and expire in between.
+    async fn drop_expired_txs(
         &self,
         ctx: Context,
-        next_height: u64,
-    ) -> Result<Vec<Node>, Box<dyn Error + Send>> {
-        if next_height == 0 {
-            return Ok(vec![]);
-        }
+        tx_hashes: Vec<Hash>,
+        timestamp: u64,
+    ) -> ProtocolResult<Vec<Hash>> {
+        let txs = self.adapter.get_full_txs(ctx, tx_hashes).await?;
 
-        let old_block = self
-            .adapter
-            .get_block_by_height(ctx.clone(), next_height - 1)
-            .await?;
-        let old_metadata = self.adapter.get_metadata(
-            ctx,
-            old_block.header.state_root.clone(),
-            old_block.header.timestamp,
-            old_block.header.height,
-        )?;
-        let mut old_validators = old_metadata
-            .verifier_list
+        Ok(txs
             .into_iter()
-            .map(|v| Node {
-                address:        v.address.as_bytes(),
-                propose_weight: v.propose_weight,
-                vote_weight:    v.vote_weight,
-            })
-            .collect::<Vec<_>>();
-        old_validators.sort();
-        Ok(old_validators)
+            .filter(|stx| !is_expired(stx, timestamp))
+            .map(|stx| stx.tx_hash)
+            .collect())
     }
-}
 
-#[async_trait]
-impl<Adapter: ConsensusAdapter + 'static> Wal for ConsensusEngine<Adapter> {
-    async fn save(&self, info: Bytes) -> Result<(), Box<dyn Error + Send>> {
-        self.adapter
-            .save_overlord_wal(Context::new(), info)
-            .await
-            .map_err(|e| ProtocolError::from(ConsensusError::Other(e.to_string())))?;
-        Ok(())
-    }
-
-    async fn load(&self) -> Result<Option<Bytes>, Box<dyn Error + Send>> {
-        let res = self.adapter.load_overlord_wal(Context::new()).await.ok();
-        Ok(res)
-    }
-}
+    /// Pipelined package is taken only once, proposal of later rounds should
+    /// include transactions received since then.
+    async fn take_pipelined(&self, next_height: u64) -> Option<MixedTxHashes> {
+        let pipelined = self.pipelined.write().take()?;
+        if pipelined.height != next_height {
+            return None;
+        }
 
-impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
-    pub fn new(
-        status_agent: StatusAgent,
-        node_info: NodeInfo,
-        wal: Arc<SignedTxsWAL>,
-        adapter: Arc<Adapter>,
-        crypto: Arc<OverlordCrypto>,
-        lock: Arc<Mutex<()>>,
-    ) -> Self {
-        Self {
-            status_agent,
-            node_info,
-            exemption_hash: RwLock::new(HashSet::new()),
-            txs_wal: wal,
-            adapter,
-            crypto,
-            lock,
+        match pipelined.handle.await {
+            Ok(Ok(mixed_tx_hashes)) => Some(mixed_tx_hashes),
+            Ok(Err(e)) => {
+                error!("[consensus-engine]: pipelined package error {}", e);
+                None
+            }
+            Err(e) => {
+                error!("[consensus-engine]: pipelined package join error {}", e);
+                None
+            }
         }
     }
 
@@ -441,6 +927,8 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
         timestamp: u64,
         block_hash: Hash,
         txs: Vec<SignedTransaction>,
+        vote_extensions: Vec<VoteExtension>,
+        extra_data: Bytes,
     ) -> ProtocolResult<()> {
         let status = self.status_agent.to_inner();
 
@@ -455,10 +943,47 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
                 txs,
                 status.cycles_limit,
                 timestamp,
+                vote_extensions,
+                extra_data,
             )
             .await
     }
 
+    // Extra data of config is proposed once headers carry it
+    fn extra_data_at(&self, chain_spec: &ChainSpec, height: u64) -> Bytes {
+        if chain_spec.is_active(Feature::ExtraData, height) {
+            self.extra_data.clone()
+        } else {
+            Bytes::new()
+        }
+    }
+
+    // A block re-proposed by a locked validator keeps its timestamp,
+    // rejecting it by the drift later would stall the round. The clock is
+    // checked once for each block at the height.
+    fn check_proposal_timestamp(&self, height: u64, block: &Block) -> ProtocolResult<()> {
+        let status = self.status_agent.to_inner();
+        if !status.chain_spec.is_active(Feature::BlockTimestamp, height) {
+            return Ok(());
+        }
+
+        let block_hash = Hash::digest(block.encode_fixed()?);
+        let max_drift = if self.timely_blocks.read().contains(&block_hash) {
+            0
+        } else {
+            self.max_timestamp_drift
+        };
+        check_timestamp(
+            block.header.timestamp,
+            status.current_timestamp,
+            time_now(),
+            max_drift,
+        )?;
+
+        self.timely_blocks.write().insert(block_hash);
+        Ok(())
+    }
+
     fn check_block_roots(&self, block: &BlockHeader) -> ProtocolResult<()> {
         let status = self.status_agent.to_inner();
 
@@ -575,9 +1100,12 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
     /// After get the signed transactions:
     /// 1. Execute the signed transactions.
     /// 2. Save the signed transactions.
-    /// 3. Save the latest proof.
-    /// 4. Save the new block.
+    /// 3. Save the new block.
+    /// 4. Save the latest proof.
     /// 5. Save the receipt.
+    ///
+    /// The proof is saved after the block, so that the latest proof is never
+    /// ahead of the latest block if the node exits in between.
     pub async fn update_status(
         &self,
         metadata: Metadata,
@@ -593,22 +1121,34 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
             .save_block(Context::new(), block.clone())
             .await?;
 
+        // Save the lastest proof.
+        self.adapter
+            .save_proof(Context::new(), proof.clone())
+            .await?;
+
+        // Transactions and votes from now on are for the next height
+        let next_height = block.header.height + 1;
+
         // update timeout_gap of mempool
         self.adapter.set_args(
             Context::new(),
             metadata.timeout_gap,
             metadata.cycles_limit,
             metadata.max_tx_size,
+            metadata.signature_domain(next_height),
+            metadata.halt_height,
         );
 
         let block_hash = Hash::digest(block.encode_fixed()?);
         self.status_agent
             .update_by_commited(metadata.clone(), block, block_hash, proof);
-        self.update_overlord_crypto(metadata)?;
+        self.update_overlord_crypto(metadata, next_height)?;
         Ok(())
     }
 
-    fn update_overlord_crypto(&self, metadata: Metadata) -> ProtocolResult<()> {
+    fn update_overlord_crypto(&self, metadata: Metadata, next_height: u64) -> ProtocolResult<()> {
+        self.crypto
+            .update_signature_domain(metadata.signature_domain(next_height));
         self.crypto.update(generate_new_crypto_map(metadata)?);
         Ok(())
     }
@@ -618,10 +1158,7 @@ pub fn generate_new_crypto_map(metadata: Metadata) -> ProtocolResult<HashMap<Byt
     let mut new_addr_pubkey_map = HashMap::new();
     for validator in metadata.verifier_list.into_iter() {
         let addr = validator.address.as_bytes();
-        let hex_pubkey = hex::decode(validator.bls_pub_key.as_string_trim0x()).map_err(|err| {
-            ConsensusError::Other(format!("hex decode metadata bls pubkey error {:?}", err))
-        })?;
-        let pubkey = BlsPublicKey::try_from(hex_pubkey.as_ref())
+        let pubkey = BlsPublicKey::try_from(validator.bls_pub_key.as_bytes().as_ref())
             .map_err(|err| ConsensusError::Other(format!("try from bls pubkey error {:?}", err)))?;
         new_addr_pubkey_map.insert(addr, pubkey);
     }
@@ -669,6 +1206,16 @@ pub fn trace_block(block: &Block) {
     );
 }
 
+/// The round duration is measured from the proposal timestamp to commit, every
+/// round before the committed one is a view change.
+fn observe_commit(header: &BlockHeader, round: u64) {
+    let elapsed_ms = time_now().saturating_sub(header.timestamp);
+
+    CONSENSUS_HEIGHT.set(header.height as i64);
+    CONSENSUS_ROUND_SECONDS.observe(elapsed_ms as f64 / 1000.0);
+    CONSENSUS_VIEW_CHANGE_TOTAL.inc_by(round as i64);
+}
+
 fn time_now() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
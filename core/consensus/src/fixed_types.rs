@@ -5,7 +5,7 @@ use overlord::Codec;
 
 use protocol::codec::{Deserialize, ProtocolCodecSync, Serialize};
 use protocol::fixed_codec::FixedCodec;
-use protocol::types::{Block, Hash, Pill, SignedTransaction};
+use protocol::types::{Block, Hash, Pill, Proof, SignedTransaction};
 use protocol::{traits::MessageCodec, Bytes, BytesMut, ProtocolResult};
 
 use crate::{ConsensusError, ConsensusType};
@@ -115,6 +115,29 @@ impl FixedBlock {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixedProof {
+    pub inner: Proof,
+}
+
+#[async_trait]
+impl MessageCodec for FixedProof {
+    async fn encode(&mut self) -> ProtocolResult<Bytes> {
+        self.inner.encode_sync()
+    }
+
+    async fn decode(bytes: Bytes) -> ProtocolResult<Self> {
+        let inner: Proof = ProtocolCodecSync::decode_sync(bytes)?;
+        Ok(FixedProof::new(inner))
+    }
+}
+
+impl FixedProof {
+    pub fn new(inner: Proof) -> Self {
+        FixedProof { inner }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct FixedHeight {
     pub inner: u64,
@@ -184,11 +207,13 @@ mod test {
             proof: mock_proof(block_hash),
             validator_version: 1,
             validators: Vec::new(),
+            extra_data: Bytes::new(),
         };
 
         Block {
             header,
             ordered_tx_hashes: Vec::new(),
+            vote_extensions: vec![],
         }
     }
 
@@ -223,6 +248,8 @@ mod test {
             cycles_price: 1,
             cycles_limit: random::<u64>(),
             request,
+            sender: None,
+            valid_until_timestamp: None,
         };
 
         let raw_bytes = executor::block_on(async { raw.encode().await.unwrap() });
@@ -5,6 +5,7 @@ pub mod consensus;
 mod engine;
 pub mod fixed_types;
 pub mod message;
+pub mod orphan;
 pub mod status;
 pub mod synchronization;
 #[cfg(test)]
@@ -16,7 +17,8 @@ mod wal_proto;
 
 pub use crate::adapter::OverlordConsensusAdapter;
 pub use crate::consensus::OverlordConsensus;
-pub use crate::synchronization::{OverlordSynchronization, RichBlock};
+pub use crate::engine::{DEFAULT_MAX_TIMESTAMP_DRIFT, MAX_VOTE_EXTENSION_SIZE};
+pub use crate::synchronization::{OverlordSynchronization, RichBlock, SyncProgress};
 pub use crate::wal::SignedTxsWAL;
 pub use overlord::{types::Node, DurationConfig};
 
@@ -26,7 +28,7 @@ use derive_more::Display;
 
 use common_crypto::Error as CryptoError;
 
-use protocol::types::Hash;
+use protocol::types::{Address, Hash};
 use protocol::{ProtocolError, ProtocolErrorKind};
 
 #[derive(Clone, Debug, Display, PartialEq, Eq)]
@@ -54,6 +56,9 @@ pub enum ConsensusType {
 
     #[display(fmt = "WAL Signed Transactions")]
     WALSignedTxs,
+
+    #[display(fmt = "Vote Extension")]
+    VoteExtension,
 }
 
 /// Consensus errors defines here.
@@ -114,6 +119,30 @@ pub enum ConsensusError {
     #[display(fmt = "Execute transactions error {:?}", _0)]
     ExecuteErr(String),
 
+    #[display(fmt = "Transaction {:?} expired before the block", _0)]
+    ExpiredTx(Hash),
+
+    #[display(fmt = "Transactions of {} bytes exceed block size {}", size, limit)]
+    ExceedBlockSize { size: u64, limit: u64 },
+
+    #[display(fmt = "Payload of transaction {:?} exceeds limit {}", tx_hash, limit)]
+    ExceedPayloadLimit { tx_hash: Hash, limit: u64 },
+
+    #[display(fmt = "Invalid proof of {} block, {}", height, reason)]
+    InvalidProof { height: u64, reason: String },
+
+    #[display(fmt = "Invalid timestamp {} of proposal, {}", timestamp, reason)]
+    InvalidTimestamp { timestamp: u64, reason: String },
+
+    #[display(fmt = "Invalid vote extension of {:?}, {}", voter, reason)]
+    InvalidVoteExtension { voter: Address, reason: String },
+
+    #[display(fmt = "Extra data of {} bytes exceeds limit {}", size, limit)]
+    ExceedExtraDataSize { size: usize, limit: usize },
+
+    #[display(fmt = "Feature {:?} activated at {} is not supported", name, height)]
+    UnsupportedFeature { name: String, height: u64 },
+
     ///
     WALErr(std::io::Error),
 
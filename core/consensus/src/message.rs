@@ -8,20 +8,26 @@ use overlord::types::{AggregatedVote, SignedChoke, SignedProposal, SignedVote};
 use overlord::Codec;
 use rlp::Encodable;
 use serde::{Deserialize, Serialize};
+use tracing::{field, info_span};
+use tracing_futures::Instrument;
 
 use protocol::traits::{
-    Consensus, Context, MessageHandler, Priority, Rpc, Storage, Synchronization,
+    Consensus, Context, MessageHandler, Priority, Rpc, Storage, Synchronization, TraceContext,
 };
-use protocol::ProtocolError;
+use protocol::types::{Proof, VoteExtension};
+use protocol::{ProtocolError, ProtocolResult};
 
-use crate::fixed_types::{FixedBlock, FixedHeight, FixedSignedTxs, PullTxsRequest};
+use crate::fixed_types::{FixedBlock, FixedHeight, FixedProof, FixedSignedTxs, PullTxsRequest};
 
 pub const END_GOSSIP_SIGNED_PROPOSAL: &str = "/gossip/consensus/signed_proposal";
 pub const END_GOSSIP_SIGNED_VOTE: &str = "/gossip/consensus/signed_vote";
 pub const END_GOSSIP_AGGREGATED_VOTE: &str = "/gossip/consensus/qc";
 pub const END_GOSSIP_SIGNED_CHOKE: &str = "/gossip/consensus/signed_choke";
+pub const END_GOSSIP_VOTE_EXTENSION: &str = "/gossip/consensus/vote_extension";
 pub const RPC_SYNC_PULL_BLOCK: &str = "/rpc_call/consensus/sync_pull_block";
 pub const RPC_RESP_SYNC_PULL_BLOCK: &str = "/rpc_resp/consensus/sync_pull_block";
+pub const RPC_SYNC_PULL_PROOF: &str = "/rpc_call/consensus/sync_pull_proof";
+pub const RPC_RESP_SYNC_PULL_PROOF: &str = "/rpc_resp/consensus/sync_pull_proof";
 pub const RPC_SYNC_PULL_TXS: &str = "/rpc_call/consensus/sync_pull_txs";
 pub const RPC_RESP_SYNC_PULL_TXS: &str = "/rpc_resp/consensus/sync_pull_txs";
 pub const BROADCAST_HEIGHT: &str = "/gossip/consensus/broadcast_height";
@@ -71,6 +77,15 @@ impl From<SignedChoke> for Choke {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Extension(pub Vec<u8>);
+
+impl From<VoteExtension> for Extension {
+    fn from(extension: VoteExtension) -> Self {
+        Extension(extension.rlp_bytes())
+    }
+}
+
 pub struct ProposalMessageHandler<C> {
     consensus: Arc<C>,
 }
@@ -86,7 +101,17 @@ impl<C: Consensus + 'static> MessageHandler for ProposalMessageHandler<C> {
     type Message = Proposal;
 
     async fn process(&self, ctx: Context, msg: Self::Message) {
-        if let Err(e) = self.consensus.set_proposal(ctx, msg.0).await {
+        let span = info_span!(
+            "consensus_proposal",
+            trace_id = field::debug(ctx.trace_id())
+        );
+
+        if let Err(e) = self
+            .consensus
+            .set_proposal(ctx, msg.0)
+            .instrument(span)
+            .await
+        {
             warn!("set proposal {}", e);
         }
     }
@@ -107,7 +132,9 @@ impl<C: Consensus + 'static> MessageHandler for VoteMessageHandler<C> {
     type Message = Vote;
 
     async fn process(&self, ctx: Context, msg: Self::Message) {
-        if let Err(e) = self.consensus.set_vote(ctx, msg.0).await {
+        let span = info_span!("consensus_vote", trace_id = field::debug(ctx.trace_id()));
+
+        if let Err(e) = self.consensus.set_vote(ctx, msg.0).instrument(span).await {
             warn!("set vote {}", e);
         }
     }
@@ -128,7 +155,9 @@ impl<C: Consensus + 'static> MessageHandler for QCMessageHandler<C> {
     type Message = QC;
 
     async fn process(&self, ctx: Context, msg: Self::Message) {
-        if let Err(e) = self.consensus.set_qc(ctx, msg.0).await {
+        let span = info_span!("consensus_qc", trace_id = field::debug(ctx.trace_id()));
+
+        if let Err(e) = self.consensus.set_qc(ctx, msg.0).instrument(span).await {
             warn!("set qc {}", e);
         }
     }
@@ -155,6 +184,27 @@ impl<C: Consensus + 'static> MessageHandler for ChokeMessageHandler<C> {
     }
 }
 
+pub struct VoteExtensionMessageHandler<C> {
+    consensus: Arc<C>,
+}
+
+impl<C: Consensus + 'static> VoteExtensionMessageHandler<C> {
+    pub fn new(consensus: Arc<C>) -> Self {
+        Self { consensus }
+    }
+}
+
+#[async_trait]
+impl<C: Consensus + 'static> MessageHandler for VoteExtensionMessageHandler<C> {
+    type Message = Extension;
+
+    async fn process(&self, ctx: Context, msg: Self::Message) {
+        if let Err(e) = self.consensus.set_vote_extension(ctx, msg.0).await {
+            warn!("set vote extension {}", e);
+        }
+    }
+}
+
 pub struct RemoteHeightMessageHandler<Sy> {
     synchronization: Arc<Sy>,
 }
@@ -211,6 +261,46 @@ impl<R: Rpc + 'static, S: Storage + 'static> MessageHandler for PullBlockRpcHand
     }
 }
 
+#[derive(Debug)]
+pub struct PullProofRpcHandler<R, S> {
+    rpc:     Arc<R>,
+    storage: Arc<S>,
+}
+
+impl<R, S> PullProofRpcHandler<R, S>
+where
+    R: Rpc + 'static,
+    S: Storage + 'static,
+{
+    pub fn new(rpc: Arc<R>, storage: Arc<S>) -> Self {
+        PullProofRpcHandler { rpc, storage }
+    }
+
+    // Proof of a block is carried by the next one, or stored alone for the
+    // latest block.
+    async fn get_proof(&self, height: u64) -> ProtocolResult<Proof> {
+        let latest = self.storage.get_latest_proof().await?;
+        if latest.height == height {
+            return Ok(latest);
+        }
+        let next = self.storage.get_block_by_height(height + 1).await?;
+        Ok(next.header.proof)
+    }
+}
+
+#[async_trait]
+impl<R: Rpc + 'static, S: Storage + 'static> MessageHandler for PullProofRpcHandler<R, S> {
+    type Message = FixedHeight;
+
+    async fn process(&self, ctx: Context, msg: FixedHeight) {
+        let ret = self.get_proof(msg.inner).await.map(FixedProof::new);
+        self.rpc
+            .response(ctx, RPC_RESP_SYNC_PULL_PROOF, ret, Priority::High)
+            .unwrap_or_else(move |e: ProtocolError| warn!("[core_consensus] push proof {}", e))
+            .await;
+    }
+}
+
 #[derive(Debug)]
 pub struct PullTxsRpcHandler<R, S> {
     rpc:     Arc<R>,
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+
+use common_metrics::{MemoryAccount, MemoryReservation};
+use protocol::types::{Block, Hash};
+
+use crate::util::block_memory_size;
+
+/// Blocks received ahead of the local chain. They are buffered by height and
+/// linked to the chain by parent hash once the blocks before them are
+/// committed, so they don't have to be pulled again.
+pub struct OrphanPool {
+    blocks:   BTreeMap<u64, Vec<Orphan>>,
+    len:      usize,
+    capacity: usize,
+    memory:   MemoryAccount,
+}
+
+struct Orphan {
+    block:   Block,
+    _memory: MemoryReservation,
+}
+
+impl OrphanPool {
+    pub fn new(capacity: usize) -> Self {
+        OrphanPool {
+            blocks: BTreeMap::new(),
+            len: 0,
+            capacity,
+            memory: MemoryAccount::new("consensus_orphans"),
+        }
+    }
+
+    /// Limit bytes of buffered blocks, zero removes the limit. Blocks are not
+    /// buffered while the limit is reached, they are pulled again later.
+    pub fn set_memory_cap(&mut self, cap: usize) {
+        self.memory.set_cap(cap);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Buffer a block. While the pool is full, the highest block is evicted
+    /// to make room for a lower one, since lower blocks are linked sooner.
+    pub fn insert(&mut self, block: Block) {
+        let height = block.header.height;
+        if self
+            .blocks
+            .get(&height)
+            .map(|orphans| orphans.iter().any(|orphan| orphan.block == block))
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        if self.len >= self.capacity {
+            match self.blocks.keys().next_back().cloned() {
+                Some(highest) if highest > height => self.remove_one(highest),
+                _ => return,
+            }
+        }
+
+        let memory = match self.memory.try_reserve(block_memory_size(&block)) {
+            Some(memory) => memory,
+            None => return,
+        };
+
+        self.blocks
+            .entry(height)
+            .or_insert_with(Vec::new)
+            .push(Orphan {
+                block,
+                _memory: memory,
+            });
+        self.len += 1;
+    }
+
+    /// Take the block of the height built on the parent, blocks of other
+    /// forks at the height stay until pruned.
+    pub fn take(&mut self, height: u64, parent_hash: &Hash) -> Option<Block> {
+        let orphans = self.blocks.get_mut(&height)?;
+        let index = orphans
+            .iter()
+            .position(|orphan| &orphan.block.header.pre_hash == parent_hash)?;
+        let orphan = orphans.remove(index);
+
+        if orphans.is_empty() {
+            self.blocks.remove(&height);
+        }
+        self.len -= 1;
+        Some(orphan.block)
+    }
+
+    /// Remove blocks at or below the committed height.
+    pub fn prune(&mut self, height: u64) {
+        let above = self.blocks.split_off(&(height + 1));
+        let pruned: usize = self.blocks.values().map(Vec::len).sum();
+
+        self.blocks = above;
+        self.len -= pruned;
+    }
+
+    fn remove_one(&mut self, height: u64) {
+        if let Some(orphans) = self.blocks.get_mut(&height) {
+            orphans.pop();
+            if orphans.is_empty() {
+                self.blocks.remove(&height);
+            }
+            self.len -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::fixed_codec::FixedCodec;
+    use protocol::types::{Address, Block, BlockHeader, Hash, Proof};
+    use protocol::Bytes;
+
+    use super::OrphanPool;
+    use crate::util::block_memory_size;
+
+    fn mock_block(height: u64, pre_hash: Hash) -> Block {
+        Block {
+            header:            BlockHeader {
+                chain_id: Hash::from_empty(),
+                height,
+                exec_height: 0,
+                pre_hash,
+                timestamp: 0,
+                logs_bloom: vec![],
+                order_root: Hash::from_empty(),
+                confirm_root: vec![],
+                state_root: Hash::from_empty(),
+                receipt_root: vec![],
+                cycles_used: vec![],
+                proposer: Address::default(),
+                proof: Proof {
+                    height:     0,
+                    round:      0,
+                    block_hash: Hash::from_empty(),
+                    signature:  Bytes::new(),
+                    bitmap:     Bytes::new(),
+                },
+                validator_version: 0,
+                validators: vec![],
+                extra_data: Bytes::new(),
+            },
+            ordered_tx_hashes: vec![],
+            vote_extensions:   vec![],
+        }
+    }
+
+    fn hash(block: &Block) -> Hash {
+        Hash::digest(block.encode_fixed().unwrap())
+    }
+
+    #[test]
+    fn test_link_orphans() {
+        let block_1 = mock_block(1, Hash::from_empty());
+        let block_2 = mock_block(2, hash(&block_1));
+        let fork_2 = mock_block(2, Hash::digest(Bytes::from("fork")));
+        let hash_2 = hash(&block_2);
+        let block_3 = mock_block(3, hash_2.clone());
+
+        let mut pool = OrphanPool::new(10);
+        pool.insert(block_3.clone());
+        pool.insert(fork_2);
+        pool.insert(block_2.clone());
+        pool.insert(block_2.clone());
+        assert_eq!(pool.len(), 3);
+
+        assert_eq!(pool.take(2, &hash(&block_1)), Some(block_2));
+        assert_eq!(pool.take(3, &hash(&block_1)), None);
+        assert_eq!(pool.take(3, &hash_2), Some(block_3));
+
+        pool.prune(2);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_evict_highest() {
+        let mut pool = OrphanPool::new(2);
+        pool.insert(mock_block(5, Hash::from_empty()));
+        pool.insert(mock_block(4, Hash::from_empty()));
+        pool.insert(mock_block(6, Hash::from_empty()));
+        assert!(pool.take(6, &Hash::from_empty()).is_none());
+
+        pool.insert(mock_block(3, Hash::from_empty()));
+        assert_eq!(pool.len(), 2);
+        assert!(pool.take(5, &Hash::from_empty()).is_none());
+        assert!(pool.take(3, &Hash::from_empty()).is_some());
+        assert!(pool.take(4, &Hash::from_empty()).is_some());
+    }
+
+    #[test]
+    fn test_memory_cap() {
+        let block_size = block_memory_size(&mock_block(1, Hash::from_empty()));
+        let mut pool = OrphanPool::new(10);
+        pool.set_memory_cap(block_size * 2);
+
+        pool.insert(mock_block(1, Hash::from_empty()));
+        pool.insert(mock_block(2, Hash::from_empty()));
+        pool.insert(mock_block(3, Hash::from_empty()));
+        assert_eq!(pool.len(), 2);
+        assert!(pool.take(3, &Hash::from_empty()).is_none());
+
+        // Memory is released once a block is taken
+        assert!(pool.take(1, &Hash::from_empty()).is_some());
+        pool.insert(mock_block(3, Hash::from_empty()));
+        assert_eq!(pool.len(), 2);
+    }
+}
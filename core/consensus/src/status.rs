@@ -9,7 +9,7 @@ use serde_json::json;
 use common_merkle::Merkle;
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::ExecutorResp;
-use protocol::types::{Block, Bloom, Hash, MerkleRoot, Metadata, Proof, Validator};
+use protocol::types::{Block, Bloom, ChainSpec, Hash, MerkleRoot, Metadata, Proof, Validator};
 
 use crate::util::check_list_roots;
 
@@ -49,6 +49,7 @@ impl StatusAgent {
         status.current_height = new_status.current_height;
         status.exec_height = new_status.exec_height;
         status.current_hash = new_status.current_hash;
+        status.current_timestamp = new_status.current_timestamp;
         status.latest_commited_state_root = new_status.latest_commited_state_root;
         status.list_logs_bloom = new_status.list_logs_bloom;
         status.list_confirm_root = new_status.list_confirm_root;
@@ -84,6 +85,9 @@ pub struct CurrentConsensusStatus {
     pub current_height:             u64,
     pub exec_height:                u64,
     pub current_hash:               Hash,
+    // Timestamp of the current block, proposals must be after it
+    #[serde(default)]
+    pub current_timestamp:          u64,
     pub latest_commited_state_root: MerkleRoot,
     pub list_logs_bloom:            Vec<Bloom>,
     pub list_confirm_root:          Vec<MerkleRoot>,
@@ -99,6 +103,9 @@ pub struct CurrentConsensusStatus {
     pub brake_ratio:                u64,
     pub tx_num_limit:               u64,
     pub max_tx_size:                u64,
+    pub max_block_size:             u64,
+    #[serde(default)]
+    pub chain_spec:                 ChainSpec,
 }
 
 impl CurrentConsensusStatus {
@@ -147,6 +154,7 @@ impl CurrentConsensusStatus {
         assert!(block.header.height == self.current_height + 1);
         self.current_height = block.header.height;
         self.current_hash = block_hash;
+        self.current_timestamp = block.header.timestamp;
         self.current_proof = current_proof;
         self.latest_commited_state_root = block.header.state_root.clone();
 
@@ -170,6 +178,8 @@ impl CurrentConsensusStatus {
         self.propose_ratio = metadata.propose_ratio;
         self.prevote_ratio = metadata.prevote_ratio;
         self.precommit_ratio = metadata.precommit_ratio;
+        self.max_block_size = metadata.max_block_size;
+        self.chain_spec = metadata.chain_spec;
     }
 
     fn split_off(&mut self, block: &Block) {
@@ -1,24 +1,29 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
 use futures::lock::Mutex;
 use futures_timer::Delay;
+use parking_lot::Mutex as SyncMutex;
 
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::{
-    Context, ExecutorParams, ExecutorResp, Synchronization, SynchronizationAdapter,
+    Context, ExecutorParams, ExecutorResp, ReadinessAdapter, RpcTimeout, Synchronization,
+    SynchronizationAdapter,
 };
-use protocol::types::{Block, Hash, Receipt, SignedTransaction};
+use protocol::types::{Block, ChainSpec, Feature, Hash, Proof, Receipt, SignedTransaction};
 use protocol::ProtocolResult;
 
 use crate::engine::generate_new_crypto_map;
+use crate::orphan::OrphanPool;
 use crate::status::{ExecutedInfo, StatusAgent};
-use crate::util::OverlordCrypto;
+use crate::util::{check_extra_data, check_timestamp, is_expired, verify_proof, OverlordCrypto};
 use crate::ConsensusError;
 
 const POLLING_BROADCAST: u64 = 2000;
 const WAIT_EXECUTION: u64 = 1000;
+const MAX_ORPHAN_BLOCKS: usize = 64;
 
 #[derive(Clone, Debug)]
 pub struct RichBlock {
@@ -26,12 +31,76 @@ pub struct RichBlock {
     pub txs:   Vec<SignedTransaction>,
 }
 
+/// The sync progress of the node, it is shared with the readiness probe of
+/// api.
+#[derive(Clone, Debug)]
+pub struct SyncProgress {
+    status:           StatusAgent,
+    best_peer_height: Arc<AtomicU64>,
+    syncing:          Arc<AtomicBool>,
+}
+
+impl SyncProgress {
+    pub fn new(status: StatusAgent) -> Self {
+        Self {
+            status,
+            best_peer_height: Arc::new(AtomicU64::new(0)),
+            syncing: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn update_best_peer_height(&self, height: u64) {
+        let mut best = self.best_peer_height.load(Ordering::Acquire);
+        while height > best {
+            match self.best_peer_height.compare_exchange_weak(
+                best,
+                height,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(current) => best = current,
+            }
+        }
+    }
+
+    fn start_syncing(&self) -> SyncingGuard<'_> {
+        self.syncing.store(true, Ordering::Release);
+        SyncingGuard(&self.syncing)
+    }
+}
+
+impl ReadinessAdapter for SyncProgress {
+    fn current_height(&self) -> u64 {
+        self.status.to_inner().current_height
+    }
+
+    fn best_peer_height(&self) -> u64 {
+        self.best_peer_height.load(Ordering::Acquire)
+    }
+
+    fn is_consensus_participating(&self) -> bool {
+        !self.syncing.load(Ordering::Acquire)
+    }
+}
+
+// Clear the syncing flag however the sync ends.
+struct SyncingGuard<'a>(&'a AtomicBool);
+
+impl Drop for SyncingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
 pub struct OverlordSynchronization<Adapter: SynchronizationAdapter> {
-    adapter: Arc<Adapter>,
-    status:  StatusAgent,
-    crypto:  Arc<OverlordCrypto>,
-    lock:    Arc<Mutex<()>>,
-    syncing: Mutex<()>,
+    adapter:  Arc<Adapter>,
+    status:   StatusAgent,
+    crypto:   Arc<OverlordCrypto>,
+    lock:     Arc<Mutex<()>>,
+    syncing:  Mutex<()>,
+    progress: SyncProgress,
+    orphans:  SyncMutex<OrphanPool>,
 
     sync_txs_chunk_size: usize,
 }
@@ -39,6 +108,8 @@ pub struct OverlordSynchronization<Adapter: SynchronizationAdapter> {
 #[async_trait]
 impl<Adapter: SynchronizationAdapter> Synchronization for OverlordSynchronization<Adapter> {
     async fn receive_remote_block(&self, ctx: Context, remote_height: u64) -> ProtocolResult<()> {
+        self.progress.update_best_peer_height(remote_height);
+
         let syncing_lock = self.syncing.try_lock();
         if syncing_lock.is_none() {
             return Ok(());
@@ -60,6 +131,7 @@ impl<Adapter: SynchronizationAdapter> Synchronization for OverlordSynchronizatio
             return Ok(());
         }
 
+        let _syncing = self.progress.start_syncing();
         log::info!(
             "[synchronization]: start, remote block height {:?} current block height {:?}",
             remote_height,
@@ -78,11 +150,19 @@ impl<Adapter: SynchronizationAdapter> Synchronization for OverlordSynchronizatio
         let sync_status = sync_status_agent.to_inner();
 
         if let Err(e) = sync_resp {
-            log::error!(
-                "[synchronization]: err, current_height {:?} err_msg: {:?}",
-                sync_status.current_height,
-                e
-            );
+            if let Some(timeout) = e.downcast_ref::<RpcTimeout>() {
+                log::warn!(
+                    "[synchronization]: peers unresponsive, current_height {:?} {}",
+                    sync_status.current_height,
+                    timeout
+                );
+            } else {
+                log::error!(
+                    "[synchronization]: err, current_height {:?} err_msg: {:?}",
+                    sync_status.current_height,
+                    e
+                );
+            }
         }
 
         self.status.replace(sync_status.clone());
@@ -115,6 +195,7 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
         lock: Arc<Mutex<()>>,
     ) -> Self {
         let syncing = Mutex::new(());
+        let progress = SyncProgress::new(status.clone());
 
         Self {
             adapter,
@@ -122,11 +203,23 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             crypto,
             lock,
             syncing,
+            progress,
+            orphans: SyncMutex::new(OrphanPool::new(MAX_ORPHAN_BLOCKS)),
 
             sync_txs_chunk_size,
         }
     }
 
+    /// Limit bytes of blocks buffered ahead of the chain, zero removes the
+    /// limit.
+    pub fn set_orphan_memory_cap(&self, cap: usize) {
+        self.orphans.lock().set_memory_cap(cap);
+    }
+
+    pub fn progress(&self) -> SyncProgress {
+        self.progress.clone()
+    }
+
     pub async fn polling_broadcast(&self) -> ProtocolResult<()> {
         loop {
             let current_height = self.status.to_inner().current_height;
@@ -153,17 +246,47 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
                 .adapter
                 .get_block_by_height(ctx.clone(), current_height)
                 .await?;
+            let current_hash = Hash::digest(current_block.encode_fixed()?);
 
             let next_height = current_height + 1;
 
-            let next_rich_block = self
-                .get_rich_block_from_remote(ctx.clone(), next_height)
+            let orphan = self.orphans.lock().take(next_height, &current_hash);
+            let next_block = match orphan {
+                Some(block) => block,
+                None => self.get_block_from_remote(ctx.clone(), next_height).await?,
+            };
+
+            self.verify_block(
+                &current_block,
+                &next_block,
+                &sync_status_agent.to_inner().chain_spec,
+            )?;
+
+            // The proof is verified with validators of the current state, the
+            // one the block is voted with.
+            let proof = self
+                .adapter
+                .get_proof_from_remote(ctx.clone(), next_height)
                 .await?;
+            let metadata = self.adapter.get_metadata(
+                ctx.clone(),
+                sync_status_agent.to_inner().get_latest_state_root(),
+                current_height,
+                current_block.header.timestamp,
+            )?;
+            verify_proof(&next_block, &proof, &metadata)?;
 
-            self.verify_block(&current_block, &next_rich_block.block)?;
+            let next_rich_block = self.get_rich_block(ctx.clone(), next_block).await?;
+            self.verify_txs(&next_rich_block)?;
 
-            self.commit_block(ctx.clone(), next_rich_block, sync_status_agent.clone())
-                .await?;
+            self.commit_block(
+                ctx.clone(),
+                next_rich_block,
+                proof,
+                sync_status_agent.clone(),
+            )
+            .await?;
+            self.orphans.lock().prune(next_height);
 
             current_height = next_height;
 
@@ -173,23 +296,49 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
         }
     }
 
-    // TODO(yejiayu):
-    // - Verify the proof
-    // - Verify the block header
-    // - Verify the transaction list
-    fn verify_block(&self, current_block: &Block, next_block: &Block) -> ProtocolResult<()> {
+    fn verify_block(
+        &self,
+        current_block: &Block,
+        next_block: &Block,
+        chain_spec: &ChainSpec,
+    ) -> ProtocolResult<()> {
         let block_hash = Hash::digest(current_block.encode_fixed()?);
 
         if block_hash != next_block.header.pre_hash {
             return Err(ConsensusError::SyncBlockHashErr(next_block.header.height).into());
         }
-        Ok(())
+        // Synced blocks may be far behind the local clock, only their parents
+        // bound their timestamps
+        if chain_spec.is_active(Feature::BlockTimestamp, next_block.header.height) {
+            check_timestamp(
+                next_block.header.timestamp,
+                current_block.header.timestamp,
+                0,
+                0,
+            )?;
+        }
+        check_extra_data(
+            chain_spec,
+            next_block.header.height,
+            &next_block.header.extra_data,
+        )
+    }
+
+    // Proposals with expired transactions are voted down, blocks from remote
+    // are checked the same way.
+    fn verify_txs(&self, rich_block: &RichBlock) -> ProtocolResult<()> {
+        let timestamp = rich_block.block.header.timestamp;
+        match rich_block.txs.iter().find(|stx| is_expired(stx, timestamp)) {
+            Some(stx) => Err(ConsensusError::ExpiredTx(stx.tx_hash.clone()).into()),
+            None => Ok(()),
+        }
     }
 
     async fn commit_block(
         &self,
         ctx: Context,
         rich_block: RichBlock,
+        proof: Proof,
         status_agent: StatusAgent,
     ) -> ProtocolResult<()> {
         let executor_resp = self
@@ -206,29 +355,29 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             block.header.timestamp,
         )?;
 
+        let next_height = block.header.height + 1;
         self.crypto
             .update(generate_new_crypto_map(metadata.clone())?);
+        self.crypto
+            .update_signature_domain(metadata.signature_domain(next_height));
 
         self.adapter.set_args(
             ctx.clone(),
             metadata.timeout_gap,
             metadata.cycles_limit,
             metadata.max_tx_size,
+            metadata.signature_domain(next_height),
+            metadata.halt_height,
         );
 
-        status_agent.update_by_commited(
-            metadata,
-            block.clone(),
-            block_hash,
-            // TODO(@yejiayu): need to get proof for the next block
-            block.header.proof.clone(),
-        );
+        status_agent.update_by_commited(metadata, block.clone(), block_hash, proof.clone());
 
         self.save_chain_data(
             ctx.clone(),
             rich_block.txs.clone(),
             executor_resp.receipts.clone(),
             rich_block.block.clone(),
+            proof,
         )
         .await?;
 
@@ -241,13 +390,7 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
         Ok(())
     }
 
-    async fn get_rich_block_from_remote(
-        &self,
-        ctx: Context,
-        height: u64,
-    ) -> ProtocolResult<RichBlock> {
-        let block = self.get_block_from_remote(ctx.clone(), height).await?;
-
+    async fn get_rich_block(&self, ctx: Context, block: Block) -> ProtocolResult<RichBlock> {
         let mut txs = Vec::with_capacity(block.ordered_tx_hashes.len());
 
         for tx_hashes in block.ordered_tx_hashes.chunks(self.sync_txs_chunk_size) {
@@ -274,13 +417,12 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
         txs: Vec<SignedTransaction>,
         receipts: Vec<Receipt>,
         block: Block,
+        proof: Proof,
     ) -> ProtocolResult<()> {
         self.adapter.save_signed_txs(ctx.clone(), txs).await?;
         self.adapter.save_receipts(ctx.clone(), receipts).await?;
-        self.adapter
-            .save_proof(ctx.clone(), block.header.proof.clone())
-            .await?;
         self.adapter.save_block(ctx.clone(), block).await?;
+        self.adapter.save_proof(ctx.clone(), proof).await?;
         Ok(())
     }
 
@@ -298,6 +440,9 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             height: rich_block.block.header.height,
             timestamp: rich_block.block.header.timestamp,
             cycles_limit,
+            proposer: rich_block.block.header.proposer.clone(),
+            vote_extensions: rich_block.block.vote_extensions.clone(),
+            extra_data: rich_block.block.header.extra_data.clone(),
         };
         let resp = self.adapter.sync_exec(ctx, &exec_params, &rich_block.txs)?;
 
@@ -357,6 +502,7 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             log::error!("[synchronization]: block that doesn't match is found");
             return Ok(false);
         }
+        self.orphans.lock().insert(block);
 
         Ok(true)
     }
@@ -0,0 +1,667 @@
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use futures::lock::Mutex;
+use futures_timer::Delay;
+use overlord::types::{AggregatedSignature, Commit, Proof as OverlordProof};
+use overlord::Consensus as Engine;
+use parking_lot::RwLock;
+
+use common_keystore::{serve_signer, RemoteSigner, Watermark};
+use protocol::traits::{
+    CommonConsensusAdapter, ConsensusAdapter, Context, MessageTarget, MixedTxHashes, NodeInfo,
+    PeerMisbehavior, VoteExtender,
+};
+use protocol::types::{
+    Address, Block, Bytes, ChainSpec, FeatureActivation, Hash, MerkleRoot, Metadata, PayloadLimits,
+    Proof, Receipt, SignatureDomain, SignedTransaction, Validator, VoteExtension,
+};
+use protocol::ProtocolResult;
+
+use crate::engine::{ConsensusEngine, DEFAULT_MAX_TIMESTAMP_DRIFT};
+use crate::fixed_types::FixedPill;
+use crate::status::StatusAgent;
+use crate::util::OverlordCrypto;
+use crate::wal::SignedTxsWAL;
+
+use super::synchronization::{
+    mock_chained_rich_block, mock_crypto, mock_crypto_with, mock_private_key, mock_status,
+    mock_tx_list, MockCommonConsensusAdapter,
+};
+
+const SELF_ADDRESS: &str = "0x1c9776983b2f251fa5c9cc562c1b667d1f05ff83";
+
+// Storage is the common mock adapter, mempool is a list of transactions
+// packaged in order.
+pub struct MockConsensusAdapter {
+    common:     MockCommonConsensusAdapter,
+    mempool:    RwLock<Vec<SignedTransaction>>,
+    packages:   AtomicUsize,
+    // Chain spec of the metadata of every height
+    chain_spec: RwLock<ChainSpec>,
+}
+
+impl Default for MockConsensusAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockConsensusAdapter {
+    pub fn new() -> Self {
+        let common = MockCommonConsensusAdapter::new(
+            0,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        MockConsensusAdapter {
+            common,
+            mempool: RwLock::new(vec![]),
+            packages: AtomicUsize::new(0),
+            chain_spec: RwLock::new(ChainSpec::default()),
+        }
+    }
+
+    pub fn set_chain_spec(&self, chain_spec: ChainSpec) {
+        *self.chain_spec.write() = chain_spec;
+    }
+
+    pub fn insert_txs(&self, txs: Vec<SignedTransaction>) {
+        self.mempool.write().extend(txs);
+    }
+
+    // Times transactions are packaged from mempool
+    pub fn packages(&self) -> usize {
+        self.packages.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl CommonConsensusAdapter for MockConsensusAdapter {
+    async fn save_block(&self, ctx: Context, block: Block) -> ProtocolResult<()> {
+        self.common.save_block(ctx, block).await
+    }
+
+    async fn save_proof(&self, ctx: Context, proof: Proof) -> ProtocolResult<()> {
+        self.common.save_proof(ctx, proof).await
+    }
+
+    async fn save_signed_txs(
+        &self,
+        ctx: Context,
+        signed_txs: Vec<SignedTransaction>,
+    ) -> ProtocolResult<()> {
+        self.common.save_signed_txs(ctx, signed_txs).await
+    }
+
+    async fn save_receipts(&self, ctx: Context, receipts: Vec<Receipt>) -> ProtocolResult<()> {
+        self.common.save_receipts(ctx, receipts).await
+    }
+
+    async fn flush_mempool(&self, _: Context, ordered_tx_hashes: &[Hash]) -> ProtocolResult<()> {
+        self.mempool
+            .write()
+            .retain(|stx| !ordered_tx_hashes.contains(&stx.tx_hash));
+        Ok(())
+    }
+
+    async fn get_block_by_height(&self, ctx: Context, height: u64) -> ProtocolResult<Block> {
+        self.common.get_block_by_height(ctx, height).await
+    }
+
+    async fn get_current_height(&self, ctx: Context) -> ProtocolResult<u64> {
+        CommonConsensusAdapter::get_current_height(&self.common, ctx).await
+    }
+
+    async fn get_txs_from_storage(
+        &self,
+        ctx: Context,
+        tx_hashes: &[Hash],
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        self.common.get_txs_from_storage(ctx, tx_hashes).await
+    }
+
+    async fn broadcast_height(&self, _: Context, _: u64) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    fn get_metadata(
+        &self,
+        context: Context,
+        state_root: MerkleRoot,
+        height: u64,
+        timestamp: u64,
+    ) -> ProtocolResult<Metadata> {
+        let mut metadata = self
+            .common
+            .get_metadata(context, state_root, height, timestamp)?;
+        metadata.chain_spec = self.chain_spec.read().clone();
+        Ok(metadata)
+    }
+
+    fn set_args(
+        &self,
+        _context: Context,
+        _timeout_gap: u64,
+        _cycles_limit: u64,
+        _max_tx_size: u64,
+        _signature_domain: Option<SignatureDomain>,
+        _halt_height: u64,
+    ) {
+    }
+}
+
+#[async_trait]
+impl ConsensusAdapter for MockConsensusAdapter {
+    async fn get_txs_from_mempool(
+        &self,
+        _: Context,
+        _: u64,
+        _: u64,
+        _: u64,
+        _: u64,
+    ) -> ProtocolResult<MixedTxHashes> {
+        self.packages.fetch_add(1, Ordering::SeqCst);
+
+        Ok(MixedTxHashes {
+            order_tx_hashes:   self
+                .mempool
+                .read()
+                .iter()
+                .map(|stx| stx.tx_hash.clone())
+                .collect(),
+            propose_tx_hashes: vec![],
+        })
+    }
+
+    async fn check_txs(&self, _: Context, _: Vec<Hash>) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn sync_txs(&self, _: Context, _: Vec<Hash>) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn get_full_txs(
+        &self,
+        _: Context,
+        order_txs: Vec<Hash>,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        let mempool = self.mempool.read();
+
+        Ok(order_txs
+            .iter()
+            .filter_map(|hash| mempool.iter().find(|stx| &stx.tx_hash == hash).cloned())
+            .collect())
+    }
+
+    async fn transmit(
+        &self,
+        _: Context,
+        _: Vec<u8>,
+        _: &str,
+        _: MessageTarget,
+    ) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn execute(
+        &self,
+        _: Hash,
+        _: MerkleRoot,
+        _: u64,
+        _: u64,
+        _: Address,
+        _: Hash,
+        _: Vec<SignedTransaction>,
+        _: u64,
+        _: u64,
+        _: Vec<VoteExtension>,
+        _: Bytes,
+    ) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn get_last_validators(&self, _: Context, _: u64) -> ProtocolResult<Vec<Validator>> {
+        Ok(vec![])
+    }
+
+    async fn get_current_height(&self, ctx: Context) -> ProtocolResult<u64> {
+        CommonConsensusAdapter::get_current_height(&self.common, ctx).await
+    }
+
+    async fn pull_block(&self, _: Context, _: u64, _: &str) -> ProtocolResult<Block> {
+        unimplemented!()
+    }
+
+    async fn pull_txs(
+        &self,
+        _: Context,
+        _: Vec<Hash>,
+        _: &str,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        unimplemented!()
+    }
+
+    async fn save_overlord_wal(&self, _: Context, _: Bytes) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn load_overlord_wal(&self, _: Context) -> ProtocolResult<Bytes> {
+        Ok(Bytes::new())
+    }
+
+    fn report_bad(&self, _: Context, _: PeerMisbehavior) {}
+}
+
+pub struct EngineHarness {
+    pub engine:  ConsensusEngine<MockConsensusAdapter>,
+    pub adapter: Arc<MockConsensusAdapter>,
+    pub status:  StatusAgent,
+    pub chain:   Vec<Block>,
+}
+
+// Oracle of a constant price
+struct MockVoteExtender;
+
+#[async_trait]
+impl VoteExtender for MockVoteExtender {
+    async fn extend_vote(&self, _: Context, _: u64) -> ProtocolResult<Option<Bytes>> {
+        Ok(Some(Bytes::from("price 42")))
+    }
+}
+
+pub fn engine_harness(name: &str) -> EngineHarness {
+    engine_harness_with(name, ChainSpec::default(), None)
+}
+
+pub fn engine_harness_with(
+    name: &str,
+    chain_spec: ChainSpec,
+    vote_extender: Option<Arc<dyn VoteExtender>>,
+) -> EngineHarness {
+    engine_harness_with_limits(name, chain_spec, vote_extender, PayloadLimits::default())
+}
+
+pub fn engine_harness_with_limits(
+    name: &str,
+    chain_spec: ChainSpec,
+    vote_extender: Option<Arc<dyn VoteExtender>>,
+    payload_limits: PayloadLimits,
+) -> EngineHarness {
+    engine_harness_with_crypto(
+        name,
+        chain_spec,
+        vote_extender,
+        payload_limits,
+        mock_crypto(),
+    )
+}
+
+pub fn engine_harness_with_crypto(
+    name: &str,
+    chain_spec: ChainSpec,
+    vote_extender: Option<Arc<dyn VoteExtender>>,
+    payload_limits: PayloadLimits,
+    crypto: OverlordCrypto,
+) -> EngineHarness {
+    let chain = mock_chained_rich_block(2, 1)
+        .into_iter()
+        .map(|rich_block| rich_block.block)
+        .collect::<Vec<_>>();
+    let status = StatusAgent::new(mock_status(chain[0].clone()));
+    let adapter = Arc::new(MockConsensusAdapter::new());
+    adapter.set_chain_spec(chain_spec);
+
+    let node_info = NodeInfo {
+        chain_id:     Hash::from_empty(),
+        self_address: Address::from_hex(SELF_ADDRESS).unwrap(),
+    };
+    let wal = SignedTxsWAL::new(std::env::temp_dir().join(format!("engine_{}", name)));
+
+    let engine = ConsensusEngine::new(
+        status.clone(),
+        node_info,
+        Arc::new(wal),
+        Arc::clone(&adapter),
+        Arc::new(crypto),
+        Arc::new(Mutex::new(())),
+        DEFAULT_MAX_TIMESTAMP_DRIFT,
+        Bytes::new(),
+        payload_limits,
+        vote_extender,
+    );
+
+    EngineHarness {
+        engine,
+        adapter,
+        status,
+        chain,
+    }
+}
+
+impl EngineHarness {
+    pub async fn propose(&self, height: u64) -> (FixedPill, Bytes) {
+        self.engine
+            .get_block(Context::new(), height)
+            .await
+            .expect("propose")
+    }
+
+    // The only validator precommits the block
+    pub async fn commit(&self, height: u64, pill: FixedPill, hash: Bytes) {
+        let proof = OverlordProof {
+            height,
+            round: 0,
+            block_hash: hash,
+            signature: AggregatedSignature {
+                signature:      Bytes::new(),
+                address_bitmap: Bytes::from(vec![0b1000_0000u8]),
+            },
+        };
+        let commit = Commit {
+            height,
+            content: pill,
+            proof,
+        };
+
+        self.engine
+            .commit(Context::new(), height, commit)
+            .await
+            .expect("commit");
+    }
+}
+
+#[tokio::test]
+async fn test_propose_pipelined_package() {
+    let harness = engine_harness("propose_pipelined_package");
+
+    let (pill, hash) = harness.propose(1).await;
+    assert_eq!(harness.adapter.packages(), 1);
+
+    let txs = mock_tx_list(3, 1);
+    harness.adapter.insert_txs(txs.clone());
+    harness.commit(1, pill, hash).await;
+
+    // Packaged while committing, not again by the proposal
+    let (pill, _) = harness.propose(2).await;
+    assert_eq!(harness.adapter.packages(), 2);
+    assert_eq!(
+        pill.inner.block.ordered_tx_hashes,
+        txs.iter()
+            .map(|stx| stx.tx_hash.clone())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_package_again_for_later_round() {
+    let harness = engine_harness("package_again_for_later_round");
+
+    let (pill, hash) = harness.propose(1).await;
+    harness.commit(1, pill, hash).await;
+    harness.propose(2).await;
+    assert_eq!(harness.adapter.packages(), 2);
+
+    // Transactions received since the last proposal are included
+    let txs = mock_tx_list(2, 2);
+    harness.adapter.insert_txs(txs.clone());
+    let (pill, _) = harness.propose(2).await;
+    assert_eq!(harness.adapter.packages(), 3);
+    assert_eq!(pill.inner.block.ordered_tx_hashes.len(), txs.len());
+}
+
+#[tokio::test]
+async fn test_drop_pipelined_package_of_other_height() {
+    let harness = engine_harness("drop_pipelined_package_of_other_height");
+
+    let (pill, hash) = harness.propose(1).await;
+    harness.commit(1, pill, hash).await;
+
+    // Height 2 is synced from peers instead, the package of it is stale
+    let block = harness.chain[2].clone();
+    let metadata = harness.adapter.get_metadata(
+        Context::new(),
+        block.header.state_root.clone(),
+        block.header.height,
+        block.header.timestamp,
+    );
+    let proof = block.header.proof.clone();
+    harness
+        .status
+        .update_by_commited(metadata.unwrap(), block, Hash::from_empty(), proof);
+
+    let txs = mock_tx_list(2, 3);
+    harness.adapter.insert_txs(txs.clone());
+    let (pill, _) = harness.propose(3).await;
+    assert_eq!(harness.adapter.packages(), 3);
+    assert_eq!(
+        pill.inner.block.ordered_tx_hashes,
+        txs.iter()
+            .map(|stx| stx.tx_hash.clone())
+            .collect::<Vec<_>>()
+    );
+}
+
+fn vote_extension_spec() -> ChainSpec {
+    ChainSpec {
+        activations: vec![FeatureActivation {
+            name:   "vote_extension".to_owned(),
+            height: 0,
+        }],
+    }
+}
+
+async fn check(harness: &EngineHarness, height: u64, pill: FixedPill) -> bool {
+    // Not exempted as a proposal of self
+    let hash = Hash::digest(Bytes::from("other")).as_bytes();
+
+    harness
+        .engine
+        .check_block(Context::new(), height, hash, pill)
+        .await
+        .is_ok()
+}
+
+#[tokio::test]
+async fn test_aggregate_vote_extensions_of_precommits() {
+    let extender: Arc<dyn VoteExtender> = Arc::new(MockVoteExtender);
+    let harness = engine_harness_with(
+        "aggregate_vote_extensions_of_precommits",
+        vote_extension_spec(),
+        Some(extender),
+    );
+
+    let (pill, hash) = harness.propose(1).await;
+    harness.commit(1, pill, hash.clone()).await;
+
+    // Signed over the committed block by the voter in the proof
+    let (pill, _) = harness.propose(2).await;
+    let extensions = pill.inner.block.vote_extensions.clone();
+    assert_eq!(extensions.len(), 1);
+    assert_eq!(extensions[0].height, 1);
+    assert_eq!(extensions[0].voter.as_hex(), SELF_ADDRESS);
+    assert_eq!(extensions[0].block_hash, Hash::from_bytes(hash).unwrap());
+    assert_eq!(extensions[0].payload, Bytes::from("price 42"));
+    assert!(check(&harness, 2, pill.clone()).await);
+
+    // Bound to another block, the signature doesn't cover it either
+    let mut forged = pill.clone();
+    forged.inner.block.vote_extensions[0].block_hash = Hash::digest(Bytes::from("fork"));
+    assert!(!check(&harness, 2, forged).await);
+
+    let mut forged = pill.clone();
+    forged.inner.block.vote_extensions[0].payload = Bytes::from("price 1");
+    assert!(!check(&harness, 2, forged).await);
+
+    // The voter isn't in the proof of the previous block
+    let mut forged = pill;
+    forged.inner.block.header.proof.bitmap = Bytes::from(vec![0u8]);
+    assert!(!check(&harness, 2, forged).await);
+}
+
+#[tokio::test]
+async fn test_vote_extensions_before_activation() {
+    let extender: Arc<dyn VoteExtender> = Arc::new(MockVoteExtender);
+    let activated = engine_harness_with(
+        "vote_extensions_activated",
+        vote_extension_spec(),
+        Some(extender.clone()),
+    );
+    let (pill, hash) = activated.propose(1).await;
+    activated.commit(1, pill, hash).await;
+    let (extended, _) = activated.propose(2).await;
+    assert_eq!(extended.inner.block.vote_extensions.len(), 1);
+
+    let harness = engine_harness_with(
+        "vote_extensions_before_activation",
+        ChainSpec::default(),
+        Some(extender),
+    );
+    let (pill, hash) = harness.propose(1).await;
+    harness.commit(1, pill, hash).await;
+
+    // Nothing is extended nor accepted until the feature activates
+    let (pill, _) = harness.propose(2).await;
+    assert!(pill.inner.block.vote_extensions.is_empty());
+    assert!(check(&harness, 2, pill.clone()).await);
+
+    let mut forged = pill;
+    forged.inner.block.vote_extensions = extended.inner.block.vote_extensions;
+    assert!(!check(&harness, 2, forged).await);
+}
+
+// Signing blocks on the remote signer, which needs workers to move tasks to
+#[tokio::test(threaded_scheduler)]
+async fn test_vote_extensions_by_remote_signer() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let path = std::env::temp_dir().join("engine_vote_extensions_by_remote_signer.json");
+    let _ = std::fs::remove_file(&path);
+    let watermark = Watermark::load(&path).unwrap();
+    thread::spawn(move || {
+        serve_signer(
+            listener,
+            Arc::new(mock_private_key()),
+            Bytes::from_static(b"shared key"),
+            watermark,
+        )
+    });
+    let signer = RemoteSigner::new(
+        addr,
+        Duration::from_secs(5),
+        Bytes::from_static(b"shared key"),
+    );
+
+    let extender: Arc<dyn VoteExtender> = Arc::new(MockVoteExtender);
+    let harness = engine_harness_with_crypto(
+        "vote_extensions_by_remote_signer",
+        vote_extension_spec(),
+        Some(extender),
+        PayloadLimits::default(),
+        mock_crypto_with(Arc::new(signer)),
+    );
+    let (pill, hash) = harness.propose(1).await;
+    harness.commit(1, pill, hash.clone()).await;
+
+    // Extension is signed by the remote signer, past its watermark
+    let (pill, _) = harness.propose(2).await;
+    let extensions = pill.inner.block.vote_extensions.clone();
+    assert_eq!(extensions.len(), 1);
+    assert_eq!(extensions[0].block_hash, Hash::from_bytes(hash).unwrap());
+    assert_eq!(extensions[0].payload, Bytes::from("price 42"));
+    assert!(check(&harness, 2, pill).await);
+    let _ = std::fs::remove_file(path);
+}
+
+fn block_timestamp_spec() -> ChainSpec {
+    ChainSpec {
+        activations: vec![FeatureActivation {
+            name:   "block_timestamp".to_owned(),
+            height: 0,
+        }],
+    }
+}
+
+fn time_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[tokio::test]
+async fn test_check_timestamp_of_proposals() {
+    let harness = engine_harness_with("check_timestamp_of_proposals", block_timestamp_spec(), None);
+    let parent_timestamp = time_now() - 2 * DEFAULT_MAX_TIMESTAMP_DRIFT;
+    let (mut pill, hash) = harness.propose(1).await;
+    pill.inner.block.header.timestamp = parent_timestamp;
+    harness.commit(1, pill, hash).await;
+
+    let (pill, _) = harness.propose(2).await;
+    assert!(check(&harness, 2, pill.clone()).await);
+
+    let mut stale = pill.clone();
+    stale.inner.block.header.timestamp = parent_timestamp;
+    assert!(!check(&harness, 2, stale).await);
+
+    // Re-proposed by a locked validator after the drift passes
+    let mut locked = pill.clone();
+    locked.inner.block.header.timestamp = time_now() - DEFAULT_MAX_TIMESTAMP_DRIFT + 1000;
+    assert!(check(&harness, 2, locked.clone()).await);
+    Delay::new(Duration::from_millis(1500)).await;
+    assert!(check(&harness, 2, locked.clone()).await);
+
+    let mut late = locked;
+    late.inner.block.header.validator_version = 1;
+    assert!(!check(&harness, 2, late).await);
+}
+
+#[tokio::test]
+async fn test_timestamp_before_activation() {
+    let harness = engine_harness("timestamp_before_activation");
+    let (pill, hash) = harness.propose(1).await;
+    let parent_timestamp = pill.inner.block.header.timestamp;
+    harness.commit(1, pill, hash).await;
+
+    let (mut pill, _) = harness.propose(2).await;
+    pill.inner.block.header.timestamp = parent_timestamp;
+    assert!(check(&harness, 2, pill).await);
+}
+
+#[tokio::test]
+async fn test_check_payload_limits_from_activation() {
+    let mut payload_limits = PayloadLimits::default();
+    payload_limits.insert("test", "test", 1);
+    let chain_spec = ChainSpec {
+        activations: vec![FeatureActivation {
+            name:   "payload_limits".to_owned(),
+            height: 3,
+        }],
+    };
+    let harness = engine_harness_with_limits(
+        "check_payload_limits_from_activation",
+        chain_spec,
+        None,
+        payload_limits,
+    );
+    let (pill, hash) = harness.propose(1).await;
+    harness.commit(1, pill, hash).await;
+
+    // Accepted the same as by validators without the limits
+    harness.adapter.insert_txs(mock_tx_list(1, 2));
+    let (pill, hash) = harness.propose(2).await;
+    assert!(check(&harness, 2, pill.clone()).await);
+    harness.commit(2, pill, hash).await;
+
+    harness.adapter.insert_txs(mock_tx_list(1, 3));
+    let (pill, _) = harness.propose(3).await;
+    assert!(!check(&harness, 3, pill).await);
+}
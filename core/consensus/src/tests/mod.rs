@@ -1 +1,3 @@
+mod engine;
+mod simulation;
 mod synchronization;
@@ -0,0 +1,789 @@
+// Simulation of validators running the real overlord engine over an
+// in-memory network. Each node has its own `OverlordConsensus`,
+// `OverlordSynchronization` and storage, consensus messages and height
+// broadcasts go through `SimNetwork`, which delays, drops and partitions them.
+//
+// Overlord runs on real timers, so runs take real time and aren't reproduced
+// exactly. The seed decides latency and drops of messages in the same order.
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::lock::Mutex;
+use futures_timer::Delay;
+use overlord::types::OverlordMsg;
+use overlord::OverlordHandler;
+use parking_lot::{Mutex as SyncMutex, RwLock};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use common_crypto::{BlsCommonReference, BlsPrivateKey, BlsPublicKey, PublicKey, ToBlsPublicKey};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{
+    CommonConsensusAdapter, Consensus, ConsensusAdapter, Context, ExecutorParams, ExecutorResp,
+    MessageTarget, MixedTxHashes, NodeInfo, PeerMisbehavior, Synchronization,
+    SynchronizationAdapter,
+};
+use protocol::types::{
+    Address, Block, Bytes, Hash, Hex, MerkleRoot, Metadata, PayloadLimits, Proof, Receipt,
+    SignatureDomain, SignedTransaction, Validator, ValidatorExtend, VoteExtension,
+};
+use protocol::ProtocolResult;
+
+use crate::consensus::{gen_overlord_status, OverlordConsensus};
+use crate::engine::DEFAULT_MAX_TIMESTAMP_DRIFT;
+use crate::fixed_types::FixedPill;
+use crate::message::{
+    END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE, END_GOSSIP_SIGNED_PROPOSAL,
+    END_GOSSIP_SIGNED_VOTE, END_GOSSIP_VOTE_EXTENSION,
+};
+use crate::status::{ExecutedInfo, StatusAgent};
+use crate::synchronization::OverlordSynchronization;
+use crate::util::OverlordCrypto;
+use crate::wal::SignedTxsWAL;
+use crate::ConsensusError;
+
+use super::synchronization::{
+    exec_txs, mock_genesis_rich_block, mock_status, mock_tx_list, MockCommonConsensusAdapter,
+    SafeHashMap,
+};
+
+const INTERVAL: u64 = 200;
+const POLL_INTERVAL: u64 = 20;
+
+const PRIVATE_KEYS: [&str; 4] = [
+    "000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970",
+    "00000000000000000000000000000000320b11d7c1ae66fdad1b4a75221244ae2d84903d3548c581d7d30dc135aac817",
+    "000000000000000000000000000000006a41e900d0426e615ca9d9393e6792baf9bda4398d5d407e59f77cb6c6f393cc",
+    "00000000000000000000000000000000125d81e0eb0a9c3746d868bf3b4f07760fdd430daded41d92f53b4e484ef3415",
+];
+
+enum SimMessage {
+    Proposal(Vec<u8>),
+    Vote(Vec<u8>),
+    Qc(Vec<u8>),
+    Choke(Vec<u8>),
+    VoteExtension(Vec<u8>),
+    Height(u64),
+}
+
+#[derive(Clone)]
+struct SimPeer {
+    address:   Address,
+    consensus: Arc<OverlordConsensus<SimAdapter>>,
+    sync:      Arc<OverlordSynchronization<SimAdapter>>,
+    status:    StatusAgent,
+    blocks:    SafeHashMap<u64, Block>,
+    txs:       SafeHashMap<Hash, SignedTransaction>,
+}
+
+/// Network of the nodes. Latency and drops are drawn from the seeded rng,
+/// partitions are checked when a message is sent, messages in flight are
+/// still delivered.
+pub struct SimNetwork {
+    min_latency: u64,
+    max_latency: u64,
+    drop_rate:   f64,
+    rng:         SyncMutex<StdRng>,
+    // Partition of each node, messages only cross nodes in the same one
+    groups:      RwLock<Vec<usize>>,
+    peers:       RwLock<Vec<SimPeer>>,
+    // Transactions gossiped to every node before they're proposed
+    pool:        RwLock<Vec<SignedTransaction>>,
+    dropped:     AtomicUsize,
+}
+
+impl SimNetwork {
+    pub fn new(seed: u64) -> Self {
+        SimNetwork {
+            min_latency: 1,
+            max_latency: 1,
+            drop_rate:   0.0,
+            rng:         SyncMutex::new(StdRng::seed_from_u64(seed)),
+            groups:      RwLock::new(vec![]),
+            peers:       RwLock::new(vec![]),
+            pool:        RwLock::new(vec![]),
+            dropped:     AtomicUsize::new(0),
+        }
+    }
+
+    pub fn latency(mut self, min: u64, max: u64) -> Self {
+        self.min_latency = min;
+        self.max_latency = max;
+        self
+    }
+
+    pub fn drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Split nodes into the groups, nodes not listed form another one.
+    pub fn partition(&self, groups: &[&[usize]]) {
+        self.heal();
+        let mut partition = self.groups.write();
+        for (index, group) in groups.iter().enumerate() {
+            for id in group.iter() {
+                partition[*id] = index + 1;
+            }
+        }
+    }
+
+    pub fn heal(&self) {
+        self.groups.write().iter_mut().for_each(|group| *group = 0);
+    }
+
+    pub fn connected(&self, a: usize, b: usize) -> bool {
+        let groups = self.groups.read();
+        groups[a] == groups[b]
+    }
+
+    pub fn insert_txs(&self, txs: Vec<SignedTransaction>) {
+        self.pool.write().extend(txs);
+    }
+
+    // Messages dropped by loss or partitions
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::SeqCst)
+    }
+
+    fn nodes(&self) -> usize {
+        self.peers.read().len()
+    }
+
+    fn node_of(&self, address: &Address) -> Option<usize> {
+        self.peers
+            .read()
+            .iter()
+            .position(|peer| &peer.address == address)
+    }
+
+    fn broadcast(&self, from: usize, msg: impl Fn() -> SimMessage) {
+        for to in (0..self.nodes()).filter(|to| *to != from) {
+            self.send(from, to, msg());
+        }
+    }
+
+    fn send(&self, from: usize, to: usize, msg: SimMessage) {
+        let (dropped, latency) = {
+            let mut rng = self.rng.lock();
+            let dropped = rng.gen_bool(self.drop_rate);
+            (
+                dropped,
+                rng.gen_range(self.min_latency, self.max_latency + 1),
+            )
+        };
+        if dropped || !self.connected(from, to) {
+            self.dropped.fetch_add(1, Ordering::SeqCst);
+            return;
+        }
+
+        let peer = self.peers.read()[to].clone();
+        tokio::spawn(async move {
+            Delay::new(Duration::from_millis(latency)).await;
+
+            let ctx = Context::new();
+            let res = match msg {
+                SimMessage::Proposal(msg) => peer.consensus.set_proposal(ctx, msg).await,
+                SimMessage::Vote(msg) => peer.consensus.set_vote(ctx, msg).await,
+                SimMessage::Qc(msg) => peer.consensus.set_qc(ctx, msg).await,
+                SimMessage::Choke(msg) => peer.consensus.set_choke(ctx, msg).await,
+                SimMessage::VoteExtension(msg) => peer.consensus.set_vote_extension(ctx, msg).await,
+                SimMessage::Height(height) => peer.sync.receive_remote_block(ctx, height).await,
+            };
+            if let Err(e) = res {
+                log::debug!("[simulation]: node {} handles message {}", to, e);
+            }
+        });
+    }
+
+    // Stored data of a connected peer, peers are tried in random order
+    fn find_in_peers<T, F>(&self, from: usize, find: F) -> Option<T>
+    where
+        F: Fn(&SimPeer) -> Option<T>,
+    {
+        let peers = self.peers.read();
+        let start = self.rng.lock().gen_range(0, peers.len());
+
+        (0..peers.len())
+            .map(|i| (start + i) % peers.len())
+            .filter(|to| *to != from && self.connected(from, *to))
+            .find_map(|to| find(&peers[to]))
+    }
+}
+
+/// Adapter of a node, blocks and transactions are stored in memory by the
+/// common mock adapter, execution is the same mock as synchronization tests.
+pub struct SimAdapter {
+    id:       usize,
+    common:   MockCommonConsensusAdapter,
+    network:  Arc<SimNetwork>,
+    status:   StatusAgent,
+    // Metadata of every height, validators are the simulated ones
+    metadata: Metadata,
+    handler:  RwLock<Option<OverlordHandler<FixedPill>>>,
+    wal:      RwLock<Option<Bytes>>,
+    flushed:  RwLock<HashSet<Hash>>,
+}
+
+#[async_trait]
+impl CommonConsensusAdapter for SimAdapter {
+    async fn save_block(&self, ctx: Context, block: Block) -> ProtocolResult<()> {
+        self.common.save_block(ctx, block).await
+    }
+
+    async fn save_proof(&self, ctx: Context, proof: Proof) -> ProtocolResult<()> {
+        self.common.save_proof(ctx, proof).await
+    }
+
+    async fn save_signed_txs(
+        &self,
+        ctx: Context,
+        signed_txs: Vec<SignedTransaction>,
+    ) -> ProtocolResult<()> {
+        self.common.save_signed_txs(ctx, signed_txs).await
+    }
+
+    async fn save_receipts(&self, ctx: Context, receipts: Vec<Receipt>) -> ProtocolResult<()> {
+        self.common.save_receipts(ctx, receipts).await
+    }
+
+    async fn flush_mempool(&self, _: Context, ordered_tx_hashes: &[Hash]) -> ProtocolResult<()> {
+        self.flushed
+            .write()
+            .extend(ordered_tx_hashes.iter().cloned());
+        Ok(())
+    }
+
+    async fn get_block_by_height(&self, ctx: Context, height: u64) -> ProtocolResult<Block> {
+        self.common.get_block_by_height(ctx, height).await
+    }
+
+    async fn get_current_height(&self, ctx: Context) -> ProtocolResult<u64> {
+        CommonConsensusAdapter::get_current_height(&self.common, ctx).await
+    }
+
+    async fn get_txs_from_storage(
+        &self,
+        ctx: Context,
+        tx_hashes: &[Hash],
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        self.common.get_txs_from_storage(ctx, tx_hashes).await
+    }
+
+    async fn broadcast_height(&self, _: Context, height: u64) -> ProtocolResult<()> {
+        self.network
+            .broadcast(self.id, || SimMessage::Height(height));
+        Ok(())
+    }
+
+    fn get_metadata(
+        &self,
+        _context: Context,
+        _state_root: MerkleRoot,
+        _height: u64,
+        _timestamp: u64,
+    ) -> ProtocolResult<Metadata> {
+        Ok(self.metadata.clone())
+    }
+
+    fn set_args(
+        &self,
+        _context: Context,
+        _timeout_gap: u64,
+        _cycles_limit: u64,
+        _max_tx_size: u64,
+        _signature_domain: Option<SignatureDomain>,
+        _halt_height: u64,
+    ) {
+    }
+}
+
+#[async_trait]
+impl ConsensusAdapter for SimAdapter {
+    async fn get_txs_from_mempool(
+        &self,
+        _: Context,
+        _: u64,
+        _: u64,
+        _: u64,
+        _: u64,
+    ) -> ProtocolResult<MixedTxHashes> {
+        let flushed = self.flushed.read();
+
+        Ok(MixedTxHashes {
+            order_tx_hashes:   self
+                .network
+                .pool
+                .read()
+                .iter()
+                .map(|stx| stx.tx_hash.clone())
+                .filter(|hash| !flushed.contains(hash))
+                .collect(),
+            propose_tx_hashes: vec![],
+        })
+    }
+
+    async fn check_txs(&self, _: Context, _: Vec<Hash>) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn sync_txs(&self, _: Context, _: Vec<Hash>) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn get_full_txs(
+        &self,
+        _: Context,
+        order_txs: Vec<Hash>,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        let pool = self.network.pool.read();
+
+        order_txs
+            .iter()
+            .map(|hash| {
+                pool.iter()
+                    .find(|stx| &stx.tx_hash == hash)
+                    .cloned()
+                    .ok_or_else(|| ConsensusError::Other(format!("missing tx {:?}", hash)).into())
+            })
+            .collect()
+    }
+
+    async fn transmit(
+        &self,
+        _: Context,
+        msg: Vec<u8>,
+        end: &str,
+        target: MessageTarget,
+    ) -> ProtocolResult<()> {
+        let wrap: fn(Vec<u8>) -> SimMessage = match end {
+            END_GOSSIP_SIGNED_PROPOSAL => SimMessage::Proposal,
+            END_GOSSIP_SIGNED_VOTE => SimMessage::Vote,
+            END_GOSSIP_AGGREGATED_VOTE => SimMessage::Qc,
+            END_GOSSIP_SIGNED_CHOKE => SimMessage::Choke,
+            END_GOSSIP_VOTE_EXTENSION => SimMessage::VoteExtension,
+            _ => unreachable!(),
+        };
+
+        match target {
+            MessageTarget::Broadcast => self.network.broadcast(self.id, || wrap(msg.clone())),
+            MessageTarget::Specified(address) => match self.network.node_of(&address) {
+                Some(to) if to == self.id => {}
+                Some(to) => self.network.send(self.id, to, wrap(msg)),
+                None => {}
+            },
+        }
+        Ok(())
+    }
+
+    async fn execute(
+        &self,
+        _: Hash,
+        order_root: MerkleRoot,
+        height: u64,
+        _: u64,
+        _: Address,
+        _: Hash,
+        signed_txs: Vec<SignedTransaction>,
+        _: u64,
+        _: u64,
+        _: Vec<VoteExtension>,
+        _: Bytes,
+    ) -> ProtocolResult<()> {
+        let (resp, _) = exec_txs(height, &signed_txs);
+        self.status
+            .update_by_executed(ExecutedInfo::new(height, order_root, resp));
+        Ok(())
+    }
+
+    async fn get_last_validators(&self, _: Context, _: u64) -> ProtocolResult<Vec<Validator>> {
+        Ok(self.status.to_inner().validators)
+    }
+
+    async fn get_current_height(&self, ctx: Context) -> ProtocolResult<u64> {
+        CommonConsensusAdapter::get_current_height(&self.common, ctx).await
+    }
+
+    async fn pull_block(&self, ctx: Context, height: u64, _: &str) -> ProtocolResult<Block> {
+        self.get_block_from_remote(ctx, height).await
+    }
+
+    async fn pull_txs(
+        &self,
+        ctx: Context,
+        hashes: Vec<Hash>,
+        _: &str,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        self.get_txs_from_remote(ctx, &hashes).await
+    }
+
+    async fn save_overlord_wal(&self, _: Context, info: Bytes) -> ProtocolResult<()> {
+        self.wal.write().replace(info);
+        Ok(())
+    }
+
+    async fn load_overlord_wal(&self, _: Context) -> ProtocolResult<Bytes> {
+        self.wal
+            .read()
+            .clone()
+            .ok_or_else(|| ConsensusError::Other("no overlord wal".to_owned()).into())
+    }
+
+    fn report_bad(&self, _: Context, _: PeerMisbehavior) {}
+}
+
+#[async_trait]
+impl SynchronizationAdapter for SimAdapter {
+    fn update_status(
+        &self,
+        ctx: Context,
+        height: u64,
+        consensus_interval: u64,
+        propose_ratio: u64,
+        prevote_ratio: u64,
+        precommit_ratio: u64,
+        brake_ratio: u64,
+        validators: Vec<Validator>,
+    ) -> ProtocolResult<()> {
+        let handler = self.handler.read();
+        let handler = match handler.as_ref() {
+            Some(handler) => handler,
+            None => return Ok(()),
+        };
+
+        handler
+            .send_msg(
+                ctx,
+                OverlordMsg::RichStatus(gen_overlord_status(
+                    height + 1,
+                    consensus_interval,
+                    propose_ratio,
+                    prevote_ratio,
+                    precommit_ratio,
+                    brake_ratio,
+                    validators,
+                )),
+            )
+            .map_err(|e| ConsensusError::OverlordErr(Box::new(e)))?;
+        Ok(())
+    }
+
+    fn sync_exec(
+        &self,
+        _: Context,
+        params: &ExecutorParams,
+        txs: &[SignedTransaction],
+    ) -> ProtocolResult<ExecutorResp> {
+        Ok(exec_txs(params.height, txs).0)
+    }
+
+    async fn get_block_from_remote(&self, _: Context, height: u64) -> ProtocolResult<Block> {
+        self.network
+            .find_in_peers(self.id, |peer| peer.blocks.read().get(&height).cloned())
+            .ok_or_else(|| ConsensusError::MissingBlockHeader(height).into())
+    }
+
+    // Proof of a block is carried by the next one, or kept in the status for
+    // the latest block.
+    async fn get_proof_from_remote(&self, _: Context, height: u64) -> ProtocolResult<Proof> {
+        self.network
+            .find_in_peers(self.id, |peer| {
+                let proof = peer.status.to_inner().current_proof;
+                if proof.height == height {
+                    return Some(proof);
+                }
+                peer.blocks
+                    .read()
+                    .get(&(height + 1))
+                    .map(|block| block.header.proof.clone())
+            })
+            .ok_or_else(|| ConsensusError::Other("missing proof on peers".to_owned()).into())
+    }
+
+    async fn get_txs_from_remote(
+        &self,
+        _: Context,
+        tx_hashes: &[Hash],
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        self.network
+            .find_in_peers(self.id, |peer| {
+                let txs = peer.txs.read();
+                tx_hashes
+                    .iter()
+                    .map(|hash| txs.get(hash).cloned())
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| ConsensusError::Other("missing txs on peers".to_owned()).into())
+    }
+}
+
+pub struct SimNode {
+    status: StatusAgent,
+    blocks: SafeHashMap<u64, Block>,
+}
+
+impl SimNode {
+    pub fn height(&self) -> u64 {
+        self.status.to_inner().current_height
+    }
+
+    pub fn block_hash(&self, height: u64) -> Option<Hash> {
+        self.blocks
+            .read()
+            .get(&height)
+            .map(|block| Hash::digest(block.encode_fixed().unwrap()))
+    }
+}
+
+pub struct SimCluster {
+    pub network: Arc<SimNetwork>,
+    pub nodes:   Vec<SimNode>,
+}
+
+impl SimCluster {
+    /// Start validators of the keys, each runs overlord in background.
+    pub fn start(name: &str, validators: usize, network: SimNetwork) -> Self {
+        assert!(validators <= PRIVATE_KEYS.len());
+
+        let common_ref: BlsCommonReference = "muta".into();
+        let keys = PRIVATE_KEYS[..validators]
+            .iter()
+            .map(|key| BlsPrivateKey::try_from(hex::decode(key).unwrap().as_ref()).unwrap())
+            .collect::<Vec<_>>();
+        let pub_keys = keys
+            .iter()
+            .map(|key| key.pub_key(&common_ref))
+            .collect::<Vec<BlsPublicKey>>();
+        let verifier_list = pub_keys
+            .iter()
+            .map(|pub_key| ValidatorExtend {
+                bls_pub_key:    Hex::from_string(format!("0x{}", hex::encode(pub_key.to_bytes())))
+                    .unwrap(),
+                address:        Address::from_pubkey_bytes(pub_key.to_bytes()).unwrap(),
+                propose_weight: 1,
+                vote_weight:    1,
+            })
+            .collect::<Vec<_>>();
+        let validator_list = verifier_list
+            .iter()
+            .map(|v| Validator {
+                address:        v.address.clone(),
+                propose_weight: v.propose_weight,
+                vote_weight:    v.vote_weight,
+            })
+            .collect::<Vec<_>>();
+        let addr_pubkey = verifier_list
+            .iter()
+            .zip(pub_keys.iter())
+            .map(|(v, pub_key)| (v.address.as_bytes(), pub_key.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let mut genesis = mock_genesis_rich_block().block;
+        genesis.header.validators = validator_list.clone();
+
+        let network = Arc::new(network);
+        *network.groups.write() = vec![0; validators];
+
+        let mut nodes = vec![];
+        let mut peers = vec![];
+        let mut runs = vec![];
+        for (id, key) in keys.into_iter().enumerate() {
+            let blocks = Arc::new(RwLock::new(HashMap::new()));
+            blocks.write().insert(0, genesis.clone());
+            let txs = Arc::new(RwLock::new(HashMap::new()));
+            let common = MockCommonConsensusAdapter::new(
+                0,
+                Arc::clone(&blocks),
+                Arc::new(RwLock::new(HashMap::new())),
+                Arc::clone(&txs),
+                Arc::new(RwLock::new(HashMap::new())),
+            );
+
+            let mut status = mock_status(genesis.clone());
+            status.consensus_interval = INTERVAL;
+            let status = StatusAgent::new(status);
+
+            // Metadata of the mock adapter, with simulated validators
+            let mut metadata = common
+                .get_metadata(Context::new(), genesis.header.state_root.clone(), 0, 0)
+                .unwrap();
+            metadata.verifier_list = verifier_list.clone();
+            metadata.interval = INTERVAL;
+
+            let adapter = Arc::new(SimAdapter {
+                id,
+                common,
+                network: Arc::clone(&network),
+                status: status.clone(),
+                metadata,
+                handler: RwLock::new(None),
+                wal: RwLock::new(None),
+                flushed: RwLock::new(HashSet::new()),
+            });
+
+            let address = verifier_list[id].address.clone();
+            let crypto = Arc::new(OverlordCrypto::new(
+                Arc::new(key),
+                addr_pubkey.clone(),
+                common_ref.clone(),
+            ));
+            let lock = Arc::new(Mutex::new(()));
+            let wal = SignedTxsWAL::new(std::env::temp_dir().join(format!("sim_{}_{}", name, id)));
+
+            let consensus = Arc::new(OverlordConsensus::new(
+                status.clone(),
+                NodeInfo {
+                    chain_id:     Hash::from_empty(),
+                    self_address: address.clone(),
+                },
+                Arc::clone(&crypto),
+                Arc::new(wal),
+                Arc::clone(&adapter),
+                Arc::clone(&lock),
+                DEFAULT_MAX_TIMESTAMP_DRIFT,
+                Bytes::new(),
+                PayloadLimits::default(),
+                None,
+            ));
+            adapter
+                .handler
+                .write()
+                .replace(consensus.get_overlord_handler());
+            let sync = Arc::new(OverlordSynchronization::new(
+                5000,
+                Arc::clone(&adapter),
+                status.clone(),
+                crypto,
+                lock,
+            ));
+
+            peers.push(SimPeer {
+                address,
+                consensus: Arc::clone(&consensus),
+                sync,
+                status: status.clone(),
+                blocks: Arc::clone(&blocks),
+                txs,
+            });
+            nodes.push(SimNode { status, blocks });
+            runs.push(consensus);
+        }
+        *network.peers.write() = peers;
+
+        for consensus in runs.into_iter() {
+            let overlord_status =
+                gen_overlord_status(1, INTERVAL, 15, 10, 10, 3, validator_list.clone());
+            tokio::spawn(async move {
+                let authority_list = overlord_status.authority_list;
+                let timer_config = overlord_status.timer_config;
+                if let Err(e) = consensus.run(INTERVAL, authority_list, timer_config).await {
+                    log::error!("[simulation]: overlord stops {}", e);
+                }
+            });
+        }
+
+        SimCluster { network, nodes }
+    }
+
+    /// Wait until the nodes satisfy the condition, return false if it's not
+    /// met in time.
+    pub async fn wait_until<F: Fn(&[SimNode]) -> bool>(&self, timeout: u64, done: F) -> bool {
+        let start = Instant::now();
+
+        while start.elapsed() < Duration::from_millis(timeout) {
+            if done(&self.nodes) {
+                return true;
+            }
+            Delay::new(Duration::from_millis(POLL_INTERVAL)).await;
+        }
+        done(&self.nodes)
+    }
+
+    /// Nodes committed the same blocks up to their common height.
+    pub fn assert_agreement(&self) {
+        let height = self.nodes.iter().map(SimNode::height).min().unwrap_or(0);
+
+        for h in 1..=height {
+            let hash = self.nodes[0].block_hash(h);
+            assert!(hash.is_some());
+            assert!(self.nodes.iter().all(|node| node.block_hash(h) == hash));
+        }
+    }
+}
+
+fn all_reach(height: u64) -> impl Fn(&[SimNode]) -> bool {
+    move |nodes| nodes.iter().all(|node| node.height() >= height)
+}
+
+#[tokio::test]
+async fn sim_validators_commit_blocks() {
+    let network = SimNetwork::new(1).latency(5, 30);
+    let cluster = SimCluster::start("commit_blocks", 4, network);
+
+    let txs = mock_tx_list(5, 1);
+    cluster.network.insert_txs(txs.clone());
+    assert!(cluster.wait_until(30_000, all_reach(5)).await);
+    cluster.assert_agreement();
+
+    // Transactions are committed once
+    let committed = (1..=5)
+        .filter_map(|h| cluster.nodes[0].blocks.read().get(&h).cloned())
+        .flat_map(|block| block.ordered_tx_hashes)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        committed,
+        txs.iter()
+            .map(|stx| stx.tx_hash.clone())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn sim_validators_commit_with_packet_loss() {
+    let network = SimNetwork::new(2).latency(5, 50).drop_rate(0.05);
+    let cluster = SimCluster::start("packet_loss", 4, network);
+
+    assert!(cluster.wait_until(60_000, all_reach(5)).await);
+    assert!(cluster.network.dropped() > 0);
+    cluster.assert_agreement();
+}
+
+#[tokio::test]
+async fn sim_partitioned_validator_catches_up() {
+    let network = SimNetwork::new(3).latency(5, 30);
+    let cluster = SimCluster::start("partitioned_validator", 4, network);
+
+    cluster.network.partition(&[&[0, 1, 2], &[3]]);
+    assert!(
+        cluster
+            .wait_until(30_000, |nodes| nodes[..3]
+                .iter()
+                .all(|node| node.height() >= 3))
+            .await
+    );
+    assert_eq!(cluster.nodes[3].height(), 0);
+
+    // Synced from height broadcasts of peers, then back to consensus
+    cluster.network.heal();
+    let target = cluster.nodes[0].height() + 3;
+    assert!(cluster.wait_until(60_000, all_reach(target)).await);
+    cluster.assert_agreement();
+}
+
+#[tokio::test]
+async fn sim_minority_partitions_stall() {
+    let network = SimNetwork::new(4).latency(5, 30);
+    let cluster = SimCluster::start("minority_partitions", 4, network);
+
+    cluster.network.partition(&[&[0, 1], &[2, 3]]);
+    Delay::new(Duration::from_millis(INTERVAL * 10)).await;
+    assert!(cluster.nodes.iter().all(|node| node.height() == 0));
+
+    cluster.network.heal();
+    assert!(cluster.wait_until(60_000, all_reach(2)).await);
+    cluster.assert_agreement();
+}
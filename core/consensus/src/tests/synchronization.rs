@@ -1,27 +1,34 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use futures::executor::block_on;
 use futures::lock::Mutex;
+use overlord::types::{Vote, VoteType};
+use overlord::Crypto;
 use parking_lot::RwLock;
 
 use common_crypto::BlsPrivateKey;
+use common_keystore::Signer;
 use common_merkle::Merkle;
 
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::{CommonConsensusAdapter, Synchronization, SynchronizationAdapter};
+use protocol::traits::{
+    CommonConsensusAdapter, ReadinessAdapter, Synchronization, SynchronizationAdapter,
+};
 use protocol::traits::{Context, ExecutorParams, ExecutorResp, ServiceResponse};
 use protocol::types::{
-    Address, Block, BlockHeader, Bytes, Hash, Hex, MerkleRoot, Metadata, Proof, RawTransaction,
-    Receipt, ReceiptResponse, SignedTransaction, TransactionRequest, Validator, ValidatorExtend,
+    Address, Block, BlockHeader, Bytes, ChainSpec, FeatureActivation, Hash, Hex, MerkleRoot,
+    Metadata, Proof, RawTransaction, Receipt, ReceiptResponse, SignatureDomain, SignedTransaction,
+    TransactionRequest, Validator, ValidatorExtend,
 };
 use protocol::ProtocolResult;
 
 use crate::status::{CurrentConsensusStatus, StatusAgent};
 use crate::synchronization::{OverlordSynchronization, RichBlock};
 use crate::util::OverlordCrypto;
+use crate::ConsensusError;
 
 // Test the blocks gap from 1 to 10.
 #[test]
@@ -49,30 +56,7 @@ fn sync_gap_test() {
             local_transactions,
             remote_transactions,
         ));
-        let block_hash = Hash::digest(genesis_block.encode_fixed().unwrap());
-        let status = CurrentConsensusStatus {
-            cycles_price:               1,
-            cycles_limit:               300_000_000,
-            current_height:             genesis_block.header.height,
-            exec_height:                genesis_block.header.exec_height,
-            current_hash:               block_hash,
-            list_logs_bloom:            vec![],
-            list_confirm_root:          vec![],
-            latest_commited_state_root: genesis_block.header.state_root.clone(),
-            list_state_root:            vec![],
-            list_receipt_root:          vec![],
-            list_cycles_used:           vec![],
-            current_proof:              genesis_block.header.proof,
-            validators:                 genesis_block.header.validators,
-            consensus_interval:         3000,
-            propose_ratio:              15,
-            prevote_ratio:              10,
-            precommit_ratio:            10,
-            brake_ratio:                3,
-            tx_num_limit:               20000,
-            max_tx_size:                1_073_741_824,
-        };
-        let status_agent = StatusAgent::new(status);
+        let status_agent = StatusAgent::new(mock_status(genesis_block));
         let lock = Arc::new(Mutex::new(()));
         let sync = OverlordSynchronization::new(
             5000,
@@ -93,7 +77,212 @@ fn sync_gap_test() {
         let block =
             block_on(adapter.get_block_by_height(Context::new(), status.current_height)).unwrap();
         assert_sync(status, block);
+
+        let progress = sync.progress();
+        assert_eq!(
+            progress.current_height(),
+            status_agent.to_inner().current_height
+        );
+        assert_eq!(progress.best_peer_height(), max_height);
+        assert!(progress.is_consensus_participating());
+    }
+}
+
+// Remote chain forks from local chain after height 3, committed blocks are
+// final so sync stops at the fork.
+#[test]
+fn sync_fork_test() {
+    let max_height = 20;
+    let list_rich_block = mock_chained_rich_block(max_height, 1);
+    let fork_rich_block = mock_fork_rich_block(list_rich_block.clone(), 4);
+
+    let remote_blocks = gen_remote_block_hashmap(list_rich_block.clone());
+    let genesis_block = remote_blocks.read().get(&0).unwrap().clone();
+
+    let loacl_blocks = Arc::new(RwLock::new(HashMap::new()));
+    loacl_blocks
+        .write()
+        .insert(genesis_block.header.height, genesis_block.clone());
+
+    let local_transactions = Arc::new(RwLock::new(HashMap::new()));
+    let remote_transactions = gen_remote_tx_hashmap(list_rich_block.clone());
+
+    let adapter = Arc::new(MockCommonConsensusAdapter::new(
+        0,
+        loacl_blocks,
+        Arc::clone(&remote_blocks),
+        local_transactions,
+        remote_transactions,
+    ));
+    let status_agent = StatusAgent::new(mock_status(genesis_block));
+    let lock = Arc::new(Mutex::new(()));
+    let sync = OverlordSynchronization::new(
+        5000,
+        Arc::clone(&adapter),
+        status_agent.clone(),
+        Arc::new(mock_crypto()),
+        lock,
+    );
+    block_on(sync.receive_remote_block(Context::new(), 6)).unwrap();
+    assert_eq!(status_agent.to_inner().current_height, 6);
+
+    *remote_blocks.write() = gen_remote_block_hashmap(fork_rich_block.clone())
+        .read()
+        .clone();
+    block_on(sync.receive_remote_block(Context::new(), max_height)).unwrap();
+
+    let status = status_agent.to_inner();
+    let block =
+        block_on(adapter.get_block_by_height(Context::new(), status.current_height)).unwrap();
+    assert_eq!(status.current_height, 6);
+    assert_eq!(block, list_rich_block[6].block);
+    assert!(adapter.loacl_blocks.read().get(&7).is_none());
+}
+
+// Remote block at height 3 has a transaction expired before its timestamp.
+#[test]
+fn sync_expired_tx_test() {
+    let max_height = 5;
+    let mut list_rich_block = mock_chained_rich_block(max_height, 1);
+    {
+        let rich_block = &mut list_rich_block[3];
+        let stx = &mut rich_block.txs[0];
+        stx.raw.valid_until_timestamp = Some(1);
+        stx.tx_hash = Hash::digest(stx.raw.encode_fixed().unwrap());
+        rich_block.block.ordered_tx_hashes[0] = stx.tx_hash.clone();
+        rich_block.block.header.timestamp = 2;
     }
+
+    let remote_blocks = gen_remote_block_hashmap(list_rich_block.clone());
+    let genesis_block = remote_blocks.read().get(&0).unwrap().clone();
+
+    let loacl_blocks = Arc::new(RwLock::new(HashMap::new()));
+    loacl_blocks
+        .write()
+        .insert(genesis_block.header.height, genesis_block.clone());
+
+    let local_transactions = Arc::new(RwLock::new(HashMap::new()));
+    let remote_transactions = gen_remote_tx_hashmap(list_rich_block);
+
+    let adapter = Arc::new(MockCommonConsensusAdapter::new(
+        0,
+        loacl_blocks,
+        remote_blocks,
+        local_transactions,
+        remote_transactions,
+    ));
+    let status_agent = StatusAgent::new(mock_status(genesis_block));
+    let lock = Arc::new(Mutex::new(()));
+    let sync = OverlordSynchronization::new(
+        5000,
+        Arc::clone(&adapter),
+        status_agent.clone(),
+        Arc::new(mock_crypto()),
+        lock,
+    );
+    block_on(sync.receive_remote_block(Context::new(), max_height)).unwrap();
+
+    assert_eq!(status_agent.to_inner().current_height, 2);
+    assert!(adapter.loacl_blocks.read().get(&3).is_none());
+}
+
+// Remote block at height 3 is not after its parent, it's rejected once block
+// timestamps activate.
+#[test]
+fn sync_timestamp_test() {
+    let max_height = 5;
+    let mut list_rich_block = mock_chained_rich_block(max_height, 1);
+    for height in 1..list_rich_block.len() {
+        let pre_hash = Hash::digest(list_rich_block[height - 1].block.encode_fixed().unwrap());
+        let header = &mut list_rich_block[height].block.header;
+        header.timestamp = if height == 3 { 2 } else { height as u64 };
+        header.pre_hash = pre_hash;
+    }
+
+    let chain_spec = ChainSpec {
+        activations: vec![FeatureActivation {
+            name:   "block_timestamp".to_owned(),
+            height: 3,
+        }],
+    };
+    for (spec, synced_height) in [(ChainSpec::default(), 5), (chain_spec, 2)].iter() {
+        let remote_blocks = gen_remote_block_hashmap(list_rich_block.clone());
+        let genesis_block = remote_blocks.read().get(&0).unwrap().clone();
+
+        let loacl_blocks = Arc::new(RwLock::new(HashMap::new()));
+        loacl_blocks
+            .write()
+            .insert(genesis_block.header.height, genesis_block.clone());
+
+        let local_transactions = Arc::new(RwLock::new(HashMap::new()));
+        let remote_transactions = gen_remote_tx_hashmap(list_rich_block.clone());
+
+        let adapter = Arc::new(MockCommonConsensusAdapter::new(
+            0,
+            loacl_blocks,
+            remote_blocks,
+            local_transactions,
+            remote_transactions,
+        ));
+        adapter.set_chain_spec(spec.clone());
+        let mut status = mock_status(genesis_block);
+        status.chain_spec = spec.clone();
+        let status_agent = StatusAgent::new(status);
+        let lock = Arc::new(Mutex::new(()));
+        let sync = OverlordSynchronization::new(
+            5000,
+            Arc::clone(&adapter),
+            status_agent.clone(),
+            Arc::new(mock_crypto()),
+            lock,
+        );
+        block_on(sync.receive_remote_block(Context::new(), max_height)).unwrap();
+
+        assert_eq!(status_agent.to_inner().current_height, *synced_height);
+    }
+}
+
+// Proof of the remote block at height 4 is not signed for it, sync stops
+// before the block.
+#[test]
+fn sync_invalid_proof_test() {
+    let max_height = 6;
+    let list_rich_block = mock_chained_rich_block(max_height, 1);
+
+    let remote_blocks = gen_remote_block_hashmap(list_rich_block.clone());
+    let genesis_block = remote_blocks.read().get(&0).unwrap().clone();
+
+    let loacl_blocks = Arc::new(RwLock::new(HashMap::new()));
+    loacl_blocks
+        .write()
+        .insert(genesis_block.header.height, genesis_block.clone());
+
+    let local_transactions = Arc::new(RwLock::new(HashMap::new()));
+    let remote_transactions = gen_remote_tx_hashmap(list_rich_block);
+
+    let adapter = Arc::new(MockCommonConsensusAdapter::new(
+        0,
+        loacl_blocks,
+        remote_blocks,
+        local_transactions,
+        remote_transactions,
+    ));
+    adapter.forge_proof(4);
+    let status_agent = StatusAgent::new(mock_status(genesis_block));
+    let lock = Arc::new(Mutex::new(()));
+    let sync = OverlordSynchronization::new(
+        5000,
+        Arc::clone(&adapter),
+        status_agent.clone(),
+        Arc::new(mock_crypto()),
+        lock,
+    );
+    block_on(sync.receive_remote_block(Context::new(), max_height)).unwrap();
+
+    let status = status_agent.to_inner();
+    assert_eq!(status.current_height, 3);
+    assert_eq!(status.current_proof.height, 3);
+    assert!(adapter.loacl_blocks.read().get(&4).is_none());
 }
 
 pub type SafeHashMap<K, V> = Arc<RwLock<HashMap<K, V>>>;
@@ -104,6 +293,10 @@ pub struct MockCommonConsensusAdapter {
     remote_blocks:       SafeHashMap<u64, Block>,
     local_transactions:  SafeHashMap<Hash, SignedTransaction>,
     remote_transactions: SafeHashMap<Hash, SignedTransaction>,
+    // Heights remote proofs of are signed for another round
+    forged_proofs:       RwLock<HashSet<u64>>,
+    // Chain spec of the metadata of every height
+    chain_spec:          RwLock<ChainSpec>,
 }
 
 impl MockCommonConsensusAdapter {
@@ -120,8 +313,18 @@ impl MockCommonConsensusAdapter {
             remote_blocks,
             local_transactions,
             remote_transactions,
+            forged_proofs: RwLock::new(HashSet::new()),
+            chain_spec: RwLock::new(ChainSpec::default()),
         }
     }
+
+    pub fn forge_proof(&self, height: u64) {
+        self.forged_proofs.write().insert(height);
+    }
+
+    pub fn set_chain_spec(&self, chain_spec: ChainSpec) {
+        *self.chain_spec.write() = chain_spec;
+    }
 }
 
 #[async_trait]
@@ -151,7 +354,20 @@ impl SynchronizationAdapter for MockCommonConsensusAdapter {
 
     /// Pull some blocks from other nodes from `begin` to `end`.
     async fn get_block_from_remote(&self, _: Context, height: u64) -> ProtocolResult<Block> {
-        Ok(self.remote_blocks.read().get(&height).unwrap().clone())
+        self.remote_blocks
+            .read()
+            .get(&height)
+            .cloned()
+            .ok_or_else(|| ConsensusError::MissingBlockHeader(height).into())
+    }
+
+    async fn get_proof_from_remote(&self, ctx: Context, height: u64) -> ProtocolResult<Proof> {
+        let block = self.get_block_from_remote(ctx, height).await?;
+        let mut proof = mock_proof(&block);
+        if self.forged_proofs.read().contains(&height) {
+            proof.round += 1;
+        }
+        Ok(proof)
     }
 
     /// Pull signed transactions corresponding to the given hashes from other
@@ -247,25 +463,45 @@ impl CommonConsensusAdapter for MockCommonConsensusAdapter {
         _timestamp: u64,
     ) -> ProtocolResult<Metadata> {
         Ok(Metadata {
-            chain_id:        Hash::from_empty(),
-            common_ref:      Hex::from_string("0x703873635a6b51513451".to_string()).unwrap(),
-            timeout_gap:     20,
-            cycles_limit:    9999,
-            cycles_price:    1,
-            interval:        3000,
-            verifier_list:   vec![ValidatorExtend {
-                bls_pub_key: Hex::from_string("0x04188ef9488c19458a963cc57b567adde7db8f8b6bec392d5cb7b67b0abc1ed6cd966edc451f6ac2ef38079460eb965e890d1f576e4039a20467820237cda753f07a8b8febae1ec052190973a1bcf00690ea8fc0168b3fbbccd1c4e402eda5ef22".to_owned()).unwrap(),
+            chain_id:                 Hash::from_empty(),
+            common_ref:               Hex::from_bytes(Bytes::from(MOCK_COMMON_REF)),
+            timeout_gap:              20,
+            cycles_limit:             9999,
+            cycles_price:             1,
+            interval:                 3000,
+            verifier_list:            vec![ValidatorExtend {
+                bls_pub_key:    Hex::from_bytes(
+                    mock_private_key()
+                        .pub_key(&MOCK_COMMON_REF.into())
+                        .to_bytes(),
+                ),
                 address:        Address::from_hex("0x1c9776983b2f251fa5c9cc562c1b667d1f05ff83")
                     .unwrap(),
                 propose_weight: 0,
-                vote_weight:    0,
+                vote_weight:    1,
             }],
-            propose_ratio:   10,
-            prevote_ratio:   10,
-            precommit_ratio: 10,
-            brake_ratio:     10,
-            tx_num_limit: 20000,
-            max_tx_size: 1_073_741_824,
+            propose_ratio:            10,
+            prevote_ratio:            10,
+            precommit_ratio:          10,
+            brake_ratio:              10,
+            tx_num_limit:             20000,
+            max_tx_size:              1_073_741_824,
+            proposer_fee_ratio:       0,
+            treasury_fee_ratio:       0,
+            treasury:                 Address::default(),
+            network_tag:              "muta-test".to_owned(),
+            domain_separation_height: 0,
+            max_block_size:           0,
+            tx_base_cycles:           0,
+            storage_deposit_per_byte: 0,
+            rent_epoch:               0,
+            rent_per_byte:            0,
+            halt_height:              0,
+            upgrade_name:             String::new(),
+            upgrade_window:           0,
+            upgrade_threshold:        0,
+            upgrade_height:           0,
+            chain_spec:               self.chain_spec.read().clone(),
         })
     }
 
@@ -275,13 +511,46 @@ impl CommonConsensusAdapter for MockCommonConsensusAdapter {
         _timeout_gap: u64,
         _cycles_limit: u64,
         _max_tx_size: u64,
+        _signature_domain: Option<SignatureDomain>,
+        _halt_height: u64,
     ) {
     }
 }
 
-fn mock_crypto() -> OverlordCrypto {
-    let priv_key = BlsPrivateKey::try_from(hex::decode("000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970").unwrap().as_ref()).unwrap();
-    OverlordCrypto::new(priv_key, HashMap::new(), "muta".into())
+const MOCK_COMMON_REF: &str = "muta";
+
+pub(super) fn mock_private_key() -> BlsPrivateKey {
+    BlsPrivateKey::try_from(hex::decode("000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970").unwrap().as_ref()).unwrap()
+}
+
+pub(super) fn mock_crypto() -> OverlordCrypto {
+    mock_crypto_with(Arc::new(mock_private_key()))
+}
+
+// Crypto of the key of mock blocks held by the signer
+pub(super) fn mock_crypto_with(signer: Arc<dyn Signer>) -> OverlordCrypto {
+    OverlordCrypto::new(signer, HashMap::new(), MOCK_COMMON_REF.into())
+}
+
+// The only validator of mock blocks precommits the block
+pub(super) fn mock_proof(block: &Block) -> Proof {
+    let height = block.header.height;
+    let block_hash = Hash::digest(block.encode_fixed().unwrap());
+    let vote = Vote {
+        height,
+        round: 0,
+        vote_type: VoteType::Precommit,
+        block_hash: block_hash.as_bytes(),
+    };
+    let vote_hash = Hash::digest(Bytes::from(rlp::encode(&vote)));
+
+    Proof {
+        height,
+        round: 0,
+        block_hash,
+        signature: mock_crypto().sign(vote_hash.as_bytes()).unwrap(),
+        bitmap: Bytes::from(vec![0b1000_0000u8]),
+    }
 }
 
 fn gen_remote_tx_hashmap(list: Vec<RichBlock>) -> SafeHashMap<Hash, SignedTransaction> {
@@ -305,7 +574,7 @@ fn gen_remote_block_hashmap(list: Vec<RichBlock>) -> SafeHashMap<u64, Block> {
     Arc::new(RwLock::new(remote_blocks))
 }
 
-fn mock_chained_rich_block(len: u64, gap: u64) -> Vec<RichBlock> {
+pub(super) fn mock_chained_rich_block(len: u64, gap: u64) -> Vec<RichBlock> {
     let mut list = vec![];
 
     let genesis_rich_block = mock_genesis_rich_block();
@@ -351,8 +620,9 @@ fn mock_chained_rich_block(len: u64, gap: u64) -> Vec<RichBlock> {
                 address:        Address::from_hex("0x1c9776983b2f251fa5c9cc562c1b667d1f05ff83")
                     .unwrap(),
                 propose_weight: 0,
-                vote_weight:    0,
+                vote_weight:    1,
             }],
+            extra_data: Bytes::new(),
         };
 
         if last_header.height != 0 && current_height % gap == 0 {
@@ -379,6 +649,7 @@ fn mock_chained_rich_block(len: u64, gap: u64) -> Vec<RichBlock> {
         let block = Block {
             header,
             ordered_tx_hashes: tx_hashes,
+            vote_extensions: vec![],
         };
 
         let rich_block = RichBlock { block, txs };
@@ -396,7 +667,49 @@ fn mock_chained_rich_block(len: u64, gap: u64) -> Vec<RichBlock> {
     list
 }
 
-fn mock_genesis_rich_block() -> RichBlock {
+pub(super) fn mock_status(genesis_block: Block) -> CurrentConsensusStatus {
+    let block_hash = Hash::digest(genesis_block.encode_fixed().unwrap());
+    CurrentConsensusStatus {
+        cycles_price:               1,
+        cycles_limit:               300_000_000,
+        current_height:             genesis_block.header.height,
+        exec_height:                genesis_block.header.exec_height,
+        current_hash:               block_hash,
+        current_timestamp:          genesis_block.header.timestamp,
+        list_logs_bloom:            vec![],
+        list_confirm_root:          vec![],
+        latest_commited_state_root: genesis_block.header.state_root.clone(),
+        list_state_root:            vec![],
+        list_receipt_root:          vec![],
+        list_cycles_used:           vec![],
+        current_proof:              genesis_block.header.proof,
+        validators:                 genesis_block.header.validators,
+        consensus_interval:         3000,
+        propose_ratio:              15,
+        prevote_ratio:              10,
+        precommit_ratio:            10,
+        brake_ratio:                3,
+        tx_num_limit:               20000,
+        max_tx_size:                1_073_741_824,
+        max_block_size:             0,
+        chain_spec:                 ChainSpec::default(),
+    }
+}
+
+// Rebuild blocks from the height with another timestamp, they have the same
+// transactions as the original chain.
+fn mock_fork_rich_block(mut list: Vec<RichBlock>, from: u64) -> Vec<RichBlock> {
+    for height in from as usize..list.len() {
+        let pre_hash = Hash::digest(list[height - 1].block.encode_fixed().unwrap());
+        let header = &mut list[height].block.header;
+        header.timestamp = 1;
+        header.pre_hash = pre_hash;
+    }
+
+    list
+}
+
+pub(super) fn mock_genesis_rich_block() -> RichBlock {
     let header = BlockHeader {
         chain_id:          Hash::from_empty(),
         height:            0,
@@ -424,10 +737,12 @@ fn mock_genesis_rich_block() -> RichBlock {
             propose_weight: 0,
             vote_weight:    0,
         }],
+        extra_data:        Bytes::new(),
     };
     let genesis_block = Block {
         header,
         ordered_tx_hashes: vec![],
+        vote_extensions: vec![],
     };
 
     RichBlock {
@@ -455,21 +770,23 @@ fn get_receipt(tx: &SignedTransaction, height: u64) -> Receipt {
     }
 }
 
-fn mock_tx_list(num: usize, height: u64) -> Vec<SignedTransaction> {
+pub(super) fn mock_tx_list(num: usize, height: u64) -> Vec<SignedTransaction> {
     let mut txs = vec![];
 
     for i in 0..num {
         let raw = RawTransaction {
-            chain_id:     Hash::from_empty(),
-            nonce:        Hash::digest(Bytes::from(format!("{}", i))),
-            timeout:      height,
-            cycles_price: 1,
-            cycles_limit: 1,
-            request:      TransactionRequest {
+            chain_id:              Hash::from_empty(),
+            nonce:                 Hash::digest(Bytes::from(format!("{}", i))),
+            timeout:               height,
+            cycles_price:          1,
+            cycles_limit:          1,
+            request:               TransactionRequest {
                 service_name: "test".to_owned(),
                 method:       "test".to_owned(),
                 payload:      "test".to_owned(),
             },
+            sender:                None,
+            valid_until_timestamp: None,
         };
 
         let bytes = raw.encode_fixed().unwrap();
@@ -486,7 +803,7 @@ fn mock_tx_list(num: usize, height: u64) -> Vec<SignedTransaction> {
     txs
 }
 
-fn exec_txs(height: u64, txs: &[SignedTransaction]) -> (ExecutorResp, MerkleRoot) {
+pub(super) fn exec_txs(height: u64, txs: &[SignedTransaction]) -> (ExecutorResp, MerkleRoot) {
     let mut receipts = vec![];
     let mut all_cycles_used = 0;
 
@@ -1,37 +1,82 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::error::Error;
+use std::mem;
+use std::sync::Arc;
 
+use overlord::types::{Vote, VoteType};
 use overlord::Crypto;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 
 use crate::ConsensusError;
 use common_crypto::{
-    BlsCommonReference, BlsPrivateKey, BlsPublicKey, BlsSignature, BlsSignatureVerify, HashValue,
-    PrivateKey, Signature,
+    BlsCommonReference, BlsPublicKey, BlsSignature, BlsSignatureVerify, HashValue, Signature,
 };
-use protocol::types::{Address, Hash, MerkleRoot, SignedTransaction};
-use protocol::{Bytes, ProtocolError};
+use common_keystore::{ConsensusMessage, ExtensionMessage, KeyType, Signer};
+use protocol::fixed_codec::FixedCodec;
+use protocol::types::{
+    signing_hash, Address, Block, ChainSpec, Feature, Hash, Hex, MerkleRoot, Metadata, Proof,
+    SignatureDomain, SignedTransaction, Validator, VoteExtension, CONSENSUS_SIGNATURE_PURPOSE,
+    MAX_EXTRA_DATA_SIZE,
+};
+use protocol::{Bytes, ProtocolError, ProtocolResult};
 
 pub struct OverlordCrypto {
-    private_key: BlsPrivateKey,
+    // The bls key of this validator, in process or of a remote signer
+    signer:      Arc<dyn Signer>,
     addr_pubkey: RwLock<HashMap<Bytes, BlsPublicKey>>,
     common_ref:  BlsCommonReference,
+    // Domain of consensus messages, the block hash stays the same
+    domain:      RwLock<Option<SignatureDomain>>,
+    // Messages of the latest hashes, overlord hashes a message right before
+    // signing it, which is signed as the message
+    preimages:   Mutex<VecDeque<(Bytes, Bytes)>>,
 }
 
+// Messages of peers are hashed to verify as well
+const MAX_PREIMAGES: usize = 256;
+
 impl Crypto for OverlordCrypto {
     fn hash(&self, msg: Bytes) -> Bytes {
-        Hash::digest(msg).as_bytes()
+        let hash = Hash::digest(msg.clone()).as_bytes();
+
+        let mut preimages = self.preimages.lock();
+        if preimages.len() >= MAX_PREIMAGES {
+            preimages.pop_front();
+        }
+        preimages.push_back((hash.clone(), msg));
+        hash
     }
 
+    // Remote signers check votes in messages against their watermarks, they
+    // never sign bare hashes
     fn sign(&self, hash: Bytes) -> Result<Bytes, Box<dyn Error + Send>> {
-        let hash = HashValue::try_from(hash.as_ref()).map_err(|_| {
-            ProtocolError::from(ConsensusError::Other(
-                "failed to convert hash value".to_string(),
-            ))
-        })?;
-        let sig = self.private_key.sign_message(&hash);
-        Ok(sig.to_bytes())
+        let preimage = self
+            .preimages
+            .lock()
+            .iter()
+            .rev()
+            .find(|(h, _)| *h == hash)
+            .map(|(_, msg)| msg.clone());
+
+        let domain = self.domain.read().clone();
+        let sign = || match preimage {
+            Some(message) => self.signer.sign_consensus(&ConsensusMessage {
+                message: Hex::from_bytes(message),
+                domain,
+            }),
+            None => self.signer.sign(KeyType::Bls, &self.domain_hash(hash)?),
+        };
+
+        // Overlord signs synchronously in its tasks, so signing can't be
+        // awaited on the blocking pool. Tasks of the worker are moved to
+        // other workers while it waits on the signer instead.
+        let signature = if self.signer.is_blocking() {
+            tokio::task::block_in_place(sign)?
+        } else {
+            sign()?
+        };
+        Ok(signature)
     }
 
     fn verify_signature(
@@ -41,11 +86,7 @@ impl Crypto for OverlordCrypto {
         voter: Bytes,
     ) -> Result<(), Box<dyn Error + Send>> {
         let map = self.addr_pubkey.read();
-        let hash = HashValue::try_from(hash.as_ref()).map_err(|_| {
-            ProtocolError::from(ConsensusError::Other(
-                "failed to convert hash value".to_string(),
-            ))
-        })?;
+        let hash = self.signing_hash(hash)?;
         let pub_key = map.get(&voter).ok_or_else(|| {
             ProtocolError::from(ConsensusError::Other("lose public key".to_string()))
         })?;
@@ -105,11 +146,7 @@ impl Crypto for OverlordCrypto {
         let aggregate_key = BlsPublicKey::aggregate(pub_keys);
         let aggregated_signature = BlsSignature::try_from(aggregated_signature.as_ref())
             .map_err(|e| ProtocolError::from(ConsensusError::CryptoErr(Box::new(e))))?;
-        let hash = HashValue::try_from(hash.as_ref()).map_err(|_| {
-            ProtocolError::from(ConsensusError::Other(
-                "failed to convert hash value".to_string(),
-            ))
-        })?;
+        let hash = self.signing_hash(hash)?;
 
         aggregated_signature
             .verify(&hash, &aggregate_key, &self.common_ref)
@@ -120,14 +157,16 @@ impl Crypto for OverlordCrypto {
 
 impl OverlordCrypto {
     pub fn new(
-        private_key: BlsPrivateKey,
+        signer: Arc<dyn Signer>,
         addr_pubkey: HashMap<Bytes, BlsPublicKey>,
         common_ref: BlsCommonReference,
     ) -> Self {
         OverlordCrypto {
             addr_pubkey: RwLock::new(addr_pubkey),
-            private_key,
+            signer,
             common_ref,
+            domain: RwLock::new(None),
+            preimages: Mutex::new(VecDeque::new()),
         }
     }
 
@@ -136,19 +175,254 @@ impl OverlordCrypto {
 
         *map = new_addr_pubkey;
     }
+
+    pub fn update_signature_domain(&self, domain: Option<SignatureDomain>) {
+        *self.domain.write() = domain;
+    }
+
+    /// Sign the vote extension in the signature domain, remote signers check
+    /// it against their watermarks, the same as votes.
+    pub fn sign_vote_extension(&self, extension: &VoteExtension) -> ProtocolResult<Bytes> {
+        let msg = ExtensionMessage {
+            extension: extension.clone(),
+            domain:    self.domain.read().clone(),
+        };
+
+        if self.signer.is_blocking() {
+            tokio::task::block_in_place(|| self.signer.sign_vote_extension(&msg))
+        } else {
+            self.signer.sign_vote_extension(&msg)
+        }
+    }
+
+    // Hash actually signed for a message hash from overlord
+    fn domain_hash(&self, hash: Bytes) -> ProtocolResult<Hash> {
+        let hash = Hash::from_bytes(hash)?;
+        Ok(signing_hash(
+            self.domain.read().as_ref(),
+            CONSENSUS_SIGNATURE_PURPOSE,
+            &hash,
+        ))
+    }
+
+    fn signing_hash(&self, hash: Bytes) -> Result<HashValue, Box<dyn Error + Send>> {
+        let signing = self.domain_hash(hash)?;
+
+        HashValue::try_from(signing.as_bytes().as_ref()).map_err(|_| {
+            ProtocolError::from(ConsensusError::Other(
+                "failed to convert hash value".to_string(),
+            ))
+            .into()
+        })
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct ExecuteInfo {
-    pub height:       u64,
-    pub chain_id:     Hash,
-    pub block_hash:   Hash,
-    pub signed_txs:   Vec<SignedTransaction>,
-    pub order_root:   MerkleRoot,
-    pub cycles_price: u64,
-    pub coinbase:     Address,
-    pub timestamp:    u64,
-    pub cycles_limit: u64,
+    pub height:          u64,
+    pub chain_id:        Hash,
+    pub block_hash:      Hash,
+    pub signed_txs:      Vec<SignedTransaction>,
+    pub order_root:      MerkleRoot,
+    pub cycles_price:    u64,
+    pub coinbase:        Address,
+    pub timestamp:       u64,
+    pub cycles_limit:    u64,
+    pub vote_extensions: Vec<VoteExtension>,
+    pub extra_data:      Bytes,
+    // The commit span which the execution follows.
+    pub span:            tracing::Span,
+}
+
+/// Whether the transaction can't be executed in a block at the timestamp.
+pub fn is_expired(stx: &SignedTransaction, timestamp: u64) -> bool {
+    match stx.raw.valid_until_timestamp {
+        Some(valid_until) => valid_until < timestamp,
+        None => false,
+    }
+}
+
+/// Timestamps of proposals increase strictly from the parent block, and are
+/// within the max drift of the local clock, so the proposer can't skew block
+/// time further than that. Zero drift skips the clock check.
+pub fn check_timestamp(
+    timestamp: u64,
+    parent_timestamp: u64,
+    now: u64,
+    max_drift: u64,
+) -> ProtocolResult<()> {
+    let reason = if timestamp <= parent_timestamp {
+        format!("not after parent block at {}", parent_timestamp)
+    } else if max_drift != 0 && timestamp > now.saturating_add(max_drift) {
+        format!("ahead of local clock {} by over {} ms", now, max_drift)
+    } else if max_drift != 0 && timestamp < now.saturating_sub(max_drift) {
+        format!("behind local clock {} by over {} ms", now, max_drift)
+    } else {
+        return Ok(());
+    };
+
+    Err(ConsensusError::InvalidTimestamp { timestamp, reason }.into())
+}
+
+/// Blocks from the activation of a feature unknown to this node are neither
+/// proposed nor voted by it, it must be upgraded to follow the chain.
+pub fn check_features(chain_spec: &ChainSpec, height: u64) -> ProtocolResult<()> {
+    match chain_spec.unsupported_at(height) {
+        Some(activation) => Err(ConsensusError::UnsupportedFeature {
+            name:   activation.name.clone(),
+            height: activation.height,
+        }
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Extra data of headers is empty before `Feature::ExtraData` activates at
+/// the height.
+pub fn check_extra_data(
+    chain_spec: &ChainSpec,
+    height: u64,
+    extra_data: &[u8],
+) -> ProtocolResult<()> {
+    let limit = if chain_spec.is_active(Feature::ExtraData, height) {
+        MAX_EXTRA_DATA_SIZE
+    } else {
+        0
+    };
+    if extra_data.len() > limit {
+        return Err(ConsensusError::ExceedExtraDataSize {
+            size: extra_data.len(),
+            limit,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Total bytes of fixed encoded transactions, same as mempool counts while
+/// packaging.
+pub fn txs_size(txs: &[SignedTransaction]) -> ProtocolResult<u64> {
+    let mut size = 0u64;
+    for stx in txs.iter() {
+        size += stx.encode_fixed()?.len() as u64;
+    }
+    Ok(size)
+}
+
+/// Estimated bytes of a block in memory, counted by memory accounting.
+pub fn block_memory_size(block: &Block) -> usize {
+    let header = &block.header;
+    let hashes =
+        block.ordered_tx_hashes.len() + header.confirm_root.len() + header.receipt_root.len();
+
+    mem::size_of::<Block>()
+        + hashes * mem::size_of::<Hash>()
+        + header.proof.signature.len()
+        + header.proof.bitmap.len()
+}
+
+/// Estimated bytes of transactions in memory, counted by memory accounting.
+pub fn txs_memory_size(txs: &[SignedTransaction]) -> usize {
+    txs.iter()
+        .map(|stx| {
+            let request = &stx.raw.request;
+            mem::size_of::<SignedTransaction>()
+                + request.service_name.len()
+                + request.method.len()
+                + request.payload.len()
+                + stx.pubkey.len()
+                + stx.signature.len()
+        })
+        .sum()
+}
+
+/// Hash validators sign to precommit the block of the proof.
+pub fn precommit_hash(proof: &Proof, domain: Option<&SignatureDomain>) -> Hash {
+    let vote = Vote {
+        height:     proof.height,
+        round:      proof.round,
+        vote_type:  VoteType::Precommit,
+        block_hash: proof.block_hash.as_bytes(),
+    };
+    let vote_hash = Hash::digest(Bytes::from(rlp::encode(&vote)));
+    signing_hash(domain, CONSENSUS_SIGNATURE_PURPOSE, &vote_hash)
+}
+
+/// Validators of the block precommitting it in the proof. Bitmap marks
+/// voters in validators sorted by address, highest bit first.
+pub fn proof_voters<'a>(validators: &'a [Validator], proof: &Proof) -> Vec<&'a Validator> {
+    let mut validators = validators.iter().collect::<Vec<_>>();
+    validators.sort_by(|a, b| a.address.as_bytes().cmp(&b.address.as_bytes()));
+
+    validators
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            proof
+                .bitmap
+                .get(i / 8)
+                .map(|byte| byte & (0x80 >> (i % 8)) != 0)
+                .unwrap_or(false)
+        })
+        .map(|(_, v)| v)
+        .collect()
+}
+
+/// Verify the proof of a committed block, the block is final once more than
+/// 2/3 vote weight of its validators precommit it. Public keys and the
+/// signature domain are from the metadata the block is voted with.
+pub fn verify_proof(block: &Block, proof: &Proof, metadata: &Metadata) -> ProtocolResult<()> {
+    let height = block.header.height;
+    let invalid =
+        |reason: String| ProtocolError::from(ConsensusError::InvalidProof { height, reason });
+
+    let block_hash = Hash::digest(block.encode_fixed()?);
+    if proof.height != height || proof.block_hash != block_hash {
+        return Err(invalid("proof is not for the block".to_owned()));
+    }
+
+    let validators = &block.header.validators;
+    let voters = proof_voters(validators, proof);
+
+    let total_weight: u64 = validators.iter().map(|v| u64::from(v.vote_weight)).sum();
+    let voted_weight: u64 = voters.iter().map(|v| u64::from(v.vote_weight)).sum();
+    if voted_weight * 3 <= total_weight * 2 {
+        return Err(invalid(format!(
+            "voted weight {} of {} doesn't reach quorum",
+            voted_weight, total_weight
+        )));
+    }
+
+    let mut pub_keys = Vec::with_capacity(voters.len());
+    for voter in voters.iter() {
+        let extend = metadata
+            .verifier_list
+            .iter()
+            .find(|v| v.address == voter.address)
+            .ok_or_else(|| invalid(format!("no public key of {:?}", voter.address)))?;
+        let pub_key = BlsPublicKey::try_from(extend.bls_pub_key.as_bytes().as_ref())
+            .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
+        pub_keys.push(pub_key);
+    }
+
+    let vote_hash = precommit_hash(proof, metadata.signature_domain(height).as_ref());
+    let hash = HashValue::try_from(vote_hash.as_bytes().as_ref())
+        .map_err(|_| invalid("failed to convert hash value".to_owned()))?;
+    let signature = BlsSignature::try_from(proof.signature.as_ref())
+        .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
+    let common_ref: BlsCommonReference =
+        std::str::from_utf8(metadata.common_ref.as_bytes().as_ref())
+            .map_err(|_| invalid("common_ref is not utf8".to_owned()))?
+            .into();
+
+    signature
+        .verify(
+            &hash,
+            &BlsPublicKey::aggregate(pub_keys.iter().collect()),
+            &common_ref,
+        )
+        .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
+    Ok(())
 }
 
 pub fn check_list_roots<T: Eq>(cache_roots: &[T], block_roots: &[T]) -> bool {
@@ -161,23 +435,35 @@ pub fn check_list_roots<T: Eq>(cache_roots: &[T], block_roots: &[T]) -> bool {
 
 #[cfg(test)]
 mod test {
+    use common_crypto::{BlsPrivateKey, PrivateKey};
+    use protocol::types::{BlockHeader, FeatureActivation, Hex, Validator, ValidatorExtend};
+
     use super::*;
 
+    const PRIVATE_KEYS: [&str; 4] = [
+        "000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970",
+        "00000000000000000000000000000000320b11d7c1ae66fdad1b4a75221244ae2d84903d3548c581d7d30dc135aac817",
+        "000000000000000000000000000000006a41e900d0426e615ca9d9393e6792baf9bda4398d5d407e59f77cb6c6f393cc",
+        "00000000000000000000000000000000125d81e0eb0a9c3746d868bf3b4f07760fdd430daded41d92f53b4e484ef3415",
+    ];
+
+    const PUBLIC_KEYS: [&str; 4] = [
+        "041054fe9a65be0891094ed37fb3655e3ffb12353bc0a1b4f8673b52ad65d1ca481780cf7e988eb8dcdc05d8352f03605b0d11afb2525b3f1b55ec694509248bcfead39cbb292725d710e2a509c77ed051d1d49e15e429cf6d12b9be7c02179612",
+        "040c15c82ed07dc866ab7c3af3a070eb4340ac0439bf12bb49cbed5797d52707e009f7c17414777b0213b9a55c8a5c08290ce40c366d59322db418b7ff41277090bd25614174763c9fd725ede1f65f3e61ca9acdb35f59e33d556e738add14d536",
+        "040b3118acefdfbb11ded262a7f3c90dfca4fbc0200a92b4f6bb80210ab85e39f79458f7d47f7cb06864df0571e7591a4e0858df0b52a4c3ae19ae3adc32e1da0ec4cbdca108365ee433becdb1ccebb1b339647788dfad94ebae1cbd770fcfa4e5",
+        "040709f204e3ec5b8bdd9f2bb6edc9cb1704fc1e4952661ba7532ea8e37f3b159b8d41987ee6707d32bdf494e2deb00b7f049a4670a5ce1ad8e429fcacc5bbc69cb03b71a7f1d831d0b47dda5e62642d420ff0a545950cb1db19d42fe04e2c91d2",
+    ];
+
     #[test]
     fn test_bls_amcl() {
-        let private_keys = vec![
-            hex::decode("000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970").unwrap(),
-            hex::decode("00000000000000000000000000000000320b11d7c1ae66fdad1b4a75221244ae2d84903d3548c581d7d30dc135aac817").unwrap(),
-            hex::decode("000000000000000000000000000000006a41e900d0426e615ca9d9393e6792baf9bda4398d5d407e59f77cb6c6f393cc").unwrap(),
-            hex::decode("00000000000000000000000000000000125d81e0eb0a9c3746d868bf3b4f07760fdd430daded41d92f53b4e484ef3415").unwrap(),
-        ];
-
-        let public_keys = vec![
-            hex::decode("041054fe9a65be0891094ed37fb3655e3ffb12353bc0a1b4f8673b52ad65d1ca481780cf7e988eb8dcdc05d8352f03605b0d11afb2525b3f1b55ec694509248bcfead39cbb292725d710e2a509c77ed051d1d49e15e429cf6d12b9be7c02179612").unwrap(),
-            hex::decode("040c15c82ed07dc866ab7c3af3a070eb4340ac0439bf12bb49cbed5797d52707e009f7c17414777b0213b9a55c8a5c08290ce40c366d59322db418b7ff41277090bd25614174763c9fd725ede1f65f3e61ca9acdb35f59e33d556e738add14d536").unwrap(),
-            hex::decode("040b3118acefdfbb11ded262a7f3c90dfca4fbc0200a92b4f6bb80210ab85e39f79458f7d47f7cb06864df0571e7591a4e0858df0b52a4c3ae19ae3adc32e1da0ec4cbdca108365ee433becdb1ccebb1b339647788dfad94ebae1cbd770fcfa4e5").unwrap(),
-            hex::decode("040709f204e3ec5b8bdd9f2bb6edc9cb1704fc1e4952661ba7532ea8e37f3b159b8d41987ee6707d32bdf494e2deb00b7f049a4670a5ce1ad8e429fcacc5bbc69cb03b71a7f1d831d0b47dda5e62642d420ff0a545950cb1db19d42fe04e2c91d2").unwrap(),
-        ];
+        let private_keys = PRIVATE_KEYS
+            .iter()
+            .map(|k| hex::decode(k).unwrap())
+            .collect::<Vec<_>>();
+        let public_keys = PUBLIC_KEYS
+            .iter()
+            .map(|k| hex::decode(k).unwrap())
+            .collect::<Vec<_>>();
 
         let msg = Hash::digest(Bytes::from("muta-consensus"));
         let hash = HashValue::try_from(msg.as_bytes().as_ref()).unwrap();
@@ -199,6 +485,239 @@ mod test {
         assert!(res.is_ok());
     }
 
+    #[test]
+    fn test_signature_domain() {
+        let priv_key = BlsPrivateKey::try_from(hex::decode("000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970").unwrap().as_ref()).unwrap();
+        let pub_key = BlsPublicKey::try_from(hex::decode("041054fe9a65be0891094ed37fb3655e3ffb12353bc0a1b4f8673b52ad65d1ca481780cf7e988eb8dcdc05d8352f03605b0d11afb2525b3f1b55ec694509248bcfead39cbb292725d710e2a509c77ed051d1d49e15e429cf6d12b9be7c02179612").unwrap().as_ref()).unwrap();
+        let voter = Bytes::from("voter");
+        let mut addr_pubkey = HashMap::new();
+        addr_pubkey.insert(voter.clone(), pub_key);
+
+        let crypto = OverlordCrypto::new(Arc::new(priv_key), addr_pubkey, "muta".into());
+        let domain = |chain: &'static str| {
+            Some(SignatureDomain {
+                network_tag: "mainnet".to_owned(),
+                chain_id:    Hash::digest(Bytes::from(chain)),
+            })
+        };
+        let hash = Hash::digest(Bytes::from("vote")).as_bytes();
+
+        crypto.update_signature_domain(domain("chain"));
+        let signature = crypto.sign(hash.clone()).unwrap();
+        assert!(crypto
+            .verify_signature(signature.clone(), hash.clone(), voter.clone())
+            .is_ok());
+
+        // Replayed on a forked chain, or before domain separation
+        crypto.update_signature_domain(domain("fork"));
+        assert!(crypto
+            .verify_signature(signature.clone(), hash.clone(), voter.clone())
+            .is_err());
+        crypto.update_signature_domain(None);
+        assert!(crypto.verify_signature(signature, hash, voter).is_err());
+    }
+
+    // Signs consensus messages only, like remote signers
+    struct MessageSigner(BlsPrivateKey);
+
+    impl Signer for MessageSigner {
+        fn sign(&self, key_type: KeyType, _hash: &Hash) -> ProtocolResult<Bytes> {
+            Err(ProtocolError::from(ConsensusError::Other(format!(
+                "bare hash by {}",
+                key_type
+            ))))
+        }
+
+        fn sign_consensus(&self, msg: &ConsensusMessage) -> ProtocolResult<Bytes> {
+            self.0.sign_consensus(msg)
+        }
+
+        fn bls_pub_key(&self, common_ref: &str) -> ProtocolResult<Bytes> {
+            self.0.bls_pub_key(common_ref)
+        }
+    }
+
+    #[test]
+    fn test_sign_consensus_message() {
+        let priv_key =
+            BlsPrivateKey::try_from(hex::decode(PRIVATE_KEYS[0]).unwrap().as_ref()).unwrap();
+        let pub_key =
+            BlsPublicKey::try_from(hex::decode(PUBLIC_KEYS[0]).unwrap().as_ref()).unwrap();
+        let voter = Bytes::from("voter");
+        let mut addr_pubkey = HashMap::new();
+        addr_pubkey.insert(voter.clone(), pub_key);
+
+        let crypto = OverlordCrypto::new(
+            Arc::new(MessageSigner(priv_key)),
+            addr_pubkey,
+            "muta".into(),
+        );
+        crypto.update_signature_domain(Some(SignatureDomain {
+            network_tag: "mainnet".to_owned(),
+            chain_id:    Hash::digest(Bytes::from("chain")),
+        }));
+
+        // Messages hashed by overlord are signed as themselves
+        let vote = Vote {
+            height:     5,
+            round:      1,
+            vote_type:  VoteType::Prevote,
+            block_hash: Hash::digest(Bytes::from("block")).as_bytes(),
+        };
+        let hash = crypto.hash(Bytes::from(rlp::encode(&vote)));
+        let signature = crypto.sign(hash.clone()).unwrap();
+        assert!(crypto.verify_signature(signature, hash, voter).is_ok());
+
+        let unknown = Hash::digest(Bytes::from("unknown")).as_bytes();
+        assert!(crypto.sign(unknown).is_err());
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        let verifier_list = PUBLIC_KEYS
+            .iter()
+            .enumerate()
+            .map(|(i, pk)| ValidatorExtend {
+                bls_pub_key:    Hex::from_bytes(Bytes::from(hex::decode(pk).unwrap())),
+                address:        Address::from_bytes(Bytes::from(vec![i as u8 + 1; 20])).unwrap(),
+                propose_weight: 1,
+                vote_weight:    1,
+            })
+            .collect::<Vec<_>>();
+        let validators = verifier_list
+            .iter()
+            .map(|v| Validator {
+                address:        v.address.clone(),
+                propose_weight: v.propose_weight,
+                vote_weight:    v.vote_weight,
+            })
+            .collect();
+        let metadata = Metadata {
+            common_ref: Hex::from_bytes(Bytes::from("muta")),
+            verifier_list,
+            ..Default::default()
+        };
+        let block = Block {
+            header:            BlockHeader {
+                chain_id: Hash::from_empty(),
+                height: 5,
+                exec_height: 4,
+                pre_hash: Hash::from_empty(),
+                timestamp: 0,
+                logs_bloom: vec![],
+                order_root: Hash::from_empty(),
+                confirm_root: vec![],
+                state_root: Hash::from_empty(),
+                receipt_root: vec![],
+                cycles_used: vec![],
+                proposer: Address::default(),
+                proof: Proof {
+                    height:     0,
+                    round:      0,
+                    block_hash: Hash::from_empty(),
+                    signature:  Bytes::new(),
+                    bitmap:     Bytes::new(),
+                },
+                validator_version: 0,
+                validators,
+                extra_data: Bytes::new(),
+            },
+            ordered_tx_hashes: vec![],
+            vote_extensions:   vec![],
+        };
+        let block_hash = Hash::digest(block.encode_fixed().unwrap());
+
+        // Three of four validators precommit
+        let vote = Vote {
+            height:     5,
+            round:      1,
+            vote_type:  VoteType::Precommit,
+            block_hash: block_hash.as_bytes(),
+        };
+        let vote_hash = Hash::digest(Bytes::from(rlp::encode(&vote)));
+        let hash = HashValue::try_from(vote_hash.as_bytes().as_ref()).unwrap();
+        let sigs_and_pub_keys = (0..3)
+            .map(|i| {
+                let priv_key =
+                    BlsPrivateKey::try_from(hex::decode(PRIVATE_KEYS[i]).unwrap().as_ref())
+                        .unwrap();
+                let pub_key =
+                    BlsPublicKey::try_from(hex::decode(PUBLIC_KEYS[i]).unwrap().as_ref()).unwrap();
+                (priv_key.sign_message(&hash), pub_key)
+            })
+            .collect::<Vec<_>>();
+        let mut proof = Proof {
+            height: 5,
+            round: 1,
+            block_hash,
+            signature: BlsSignature::combine(sigs_and_pub_keys).to_bytes(),
+            bitmap: Bytes::from(vec![0b1110_0000u8]),
+        };
+        assert!(verify_proof(&block, &proof, &metadata).is_ok());
+
+        // Votes of two validators don't reach quorum
+        proof.bitmap = Bytes::from(vec![0b1100_0000u8]);
+        assert!(verify_proof(&block, &proof, &metadata).is_err());
+
+        // Signature is for another round
+        proof.bitmap = Bytes::from(vec![0b1110_0000u8]);
+        proof.round = 2;
+        assert!(verify_proof(&block, &proof, &metadata).is_err());
+    }
+
+    #[test]
+    fn test_check_timestamp() {
+        let now = 1_600_000_000_000;
+
+        assert!(check_timestamp(now, now - 3000, now, 1000).is_ok());
+        assert!(check_timestamp(now + 1000, now - 3000, now, 1000).is_ok());
+        assert!(check_timestamp(now - 1000, now - 3000, now, 1000).is_ok());
+
+        // Not monotonic
+        assert!(check_timestamp(now, now, now, 1000).is_err());
+        assert!(check_timestamp(now - 4000, now - 3000, now - 4000, 0).is_err());
+
+        // Skewed by the proposer
+        assert!(check_timestamp(now + 1001, now - 3000, now, 1000).is_err());
+        assert!(check_timestamp(now - 1001, now - 3000, now, 1000).is_err());
+        assert!(check_timestamp(now + 60_000, now - 3000, now, 0).is_ok());
+    }
+
+    #[test]
+    fn test_check_extra_data() {
+        let chain_spec = ChainSpec {
+            activations: vec![FeatureActivation {
+                name:   "extra_data".to_owned(),
+                height: 5,
+            }],
+        };
+        assert!(check_extra_data(&chain_spec, 5, &[]).is_ok());
+        assert!(check_extra_data(&chain_spec, 5, &[1u8; MAX_EXTRA_DATA_SIZE]).is_ok());
+        assert!(check_extra_data(&chain_spec, 5, &[1u8; MAX_EXTRA_DATA_SIZE + 1]).is_err());
+
+        assert!(check_extra_data(&chain_spec, 4, &[]).is_ok());
+        assert!(check_extra_data(&chain_spec, 4, &[1u8]).is_err());
+    }
+
+    #[test]
+    fn test_check_features() {
+        let chain_spec = ChainSpec {
+            activations: vec![
+                FeatureActivation {
+                    name:   "signature_domain".to_owned(),
+                    height: 5,
+                },
+                FeatureActivation {
+                    name:   "unknown_fork".to_owned(),
+                    height: 10,
+                },
+            ],
+        };
+        assert!(check_features(&chain_spec, 9).is_ok());
+        assert!(check_features(&chain_spec, 10).is_err());
+        assert!(check_features(&ChainSpec::default(), 10).is_ok());
+    }
+
     #[test]
     fn test_zip_roots() {
         let roots_1 = vec![1, 2, 3, 4, 5];
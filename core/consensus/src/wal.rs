@@ -112,12 +112,14 @@ mod test {
 
     pub fn mock_raw_tx() -> RawTransaction {
         RawTransaction {
-            chain_id:     mock_hash(),
-            nonce:        mock_hash(),
-            timeout:      100,
-            cycles_price: 1,
-            cycles_limit: 100,
-            request:      mock_transaction_request(),
+            chain_id:              mock_hash(),
+            nonce:                 mock_hash(),
+            timeout:               100,
+            cycles_price:          1,
+            cycles_limit:          100,
+            request:               mock_transaction_request(),
+            sender:                None,
+            valid_until_timestamp: None,
         }
     }
 
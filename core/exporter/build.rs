@@ -0,0 +1,3 @@
+fn main() {
+    tonic_build::compile_protos("proto/stream.proto").expect("compile stream.proto");
+}
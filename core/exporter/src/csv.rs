@@ -0,0 +1,204 @@
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{Context, ExportedBlock, Exporter};
+use protocol::types::{Address, Hash};
+use protocol::ProtocolResult;
+
+use crate::ExporterError;
+
+const BLOCK_COLUMNS: &[&str] = &[
+    "height",
+    "block_hash",
+    "pre_hash",
+    "timestamp",
+    "proposer",
+    "exec_height",
+    "state_root",
+    "order_root",
+    "tx_count",
+];
+const TRANSACTION_COLUMNS: &[&str] = &[
+    "tx_hash",
+    "height",
+    "index",
+    "sender",
+    "service_name",
+    "method",
+    "payload",
+    "cycles_price",
+    "cycles_limit",
+    "nonce",
+    "timeout",
+];
+const RECEIPT_COLUMNS: &[&str] = &[
+    "tx_hash",
+    "height",
+    "service_name",
+    "method",
+    "code",
+    "cycles_used",
+    "succeed_data",
+    "error_message",
+];
+const EVENT_COLUMNS: &[&str] = &["tx_hash", "height", "index", "service", "data"];
+
+/// Appends rows to `blocks.csv`, `transactions.csv`, `receipts.csv` and
+/// `events.csv` in the directory. Hashes and addresses are 0x prefixed hex.
+pub struct CsvExporter {
+    files: Mutex<CsvFiles>,
+}
+
+struct CsvFiles {
+    blocks:       BufWriter<File>,
+    transactions: BufWriter<File>,
+    receipts:     BufWriter<File>,
+    events:       BufWriter<File>,
+}
+
+impl CsvExporter {
+    pub fn new(dir: PathBuf) -> ProtocolResult<Self> {
+        fs::create_dir_all(&dir).map_err(ExporterError::Io)?;
+
+        let files = CsvFiles {
+            blocks:       open_table(&dir, "blocks.csv", BLOCK_COLUMNS)?,
+            transactions: open_table(&dir, "transactions.csv", TRANSACTION_COLUMNS)?,
+            receipts:     open_table(&dir, "receipts.csv", RECEIPT_COLUMNS)?,
+            events:       open_table(&dir, "events.csv", EVENT_COLUMNS)?,
+        };
+
+        Ok(CsvExporter {
+            files: Mutex::new(files),
+        })
+    }
+
+    fn write(&self, exported: &ExportedBlock) -> ProtocolResult<()> {
+        let header = &exported.block.header;
+        let height = header.height.to_string();
+        let block_hash = Hash::digest(exported.block.encode_fixed()?);
+        let mut files = self.files.lock();
+
+        write_row(&mut files.blocks, &[
+            &height,
+            &block_hash.as_hex(),
+            &header.pre_hash.as_hex(),
+            &header.timestamp.to_string(),
+            &header.proposer.as_hex(),
+            &header.exec_height.to_string(),
+            &header.state_root.as_hex(),
+            &header.order_root.as_hex(),
+            &exported.txs.len().to_string(),
+        ])?;
+
+        for (index, tx) in exported.txs.iter().enumerate() {
+            let sender = match &tx.raw.sender {
+                Some(sender) => sender.clone(),
+                None => Address::from_pubkey_bytes(tx.pubkey.clone())?,
+            };
+
+            write_row(&mut files.transactions, &[
+                &tx.tx_hash.as_hex(),
+                &height,
+                &index.to_string(),
+                &sender.as_hex(),
+                &tx.raw.request.service_name,
+                &tx.raw.request.method,
+                &tx.raw.request.payload,
+                &tx.raw.cycles_price.to_string(),
+                &tx.raw.cycles_limit.to_string(),
+                &tx.raw.nonce.as_hex(),
+                &tx.raw.timeout.to_string(),
+            ])?;
+        }
+
+        for receipt in exported.receipts.iter() {
+            let tx_hash = receipt.tx_hash.as_hex();
+            let response = &receipt.response;
+
+            write_row(&mut files.receipts, &[
+                &tx_hash,
+                &height,
+                &response.service_name,
+                &response.method,
+                &response.response.code.to_string(),
+                &receipt.cycles_used.to_string(),
+                &response.response.succeed_data,
+                &response.response.error_message,
+            ])?;
+
+            for (index, event) in receipt.events.iter().enumerate() {
+                write_row(&mut files.events, &[
+                    &tx_hash,
+                    &height,
+                    &index.to_string(),
+                    &event.service,
+                    &event.data,
+                ])?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Exporter for CsvExporter {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    async fn export(&self, _ctx: Context, block: &ExportedBlock) -> ProtocolResult<()> {
+        self.write(block)
+    }
+
+    async fn flush(&self, _ctx: Context) -> ProtocolResult<()> {
+        let mut files = self.files.lock();
+        files.blocks.flush().map_err(ExporterError::Io)?;
+        files.transactions.flush().map_err(ExporterError::Io)?;
+        files.receipts.flush().map_err(ExporterError::Io)?;
+        files.events.flush().map_err(ExporterError::Io)?;
+        Ok(())
+    }
+}
+
+// Header is written when the file is created
+fn open_table(dir: &Path, name: &str, columns: &[&str]) -> ProtocolResult<BufWriter<File>> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(name))
+        .map_err(ExporterError::Io)?;
+    let is_empty = file.metadata().map_err(ExporterError::Io)?.len() == 0;
+
+    let mut writer = BufWriter::new(file);
+    if is_empty {
+        write_row(&mut writer, columns)?;
+        writer.flush().map_err(ExporterError::Io)?;
+    }
+    Ok(writer)
+}
+
+fn write_row<W: Write, T: AsRef<str>>(writer: &mut W, fields: &[T]) -> ProtocolResult<()> {
+    let row = fields
+        .iter()
+        .map(|field| escape(field.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(writer, "{}", row).map_err(ExporterError::Io)?;
+    Ok(())
+}
+
+// Quote a field with separators, quotes or line breaks, as RFC 4180
+pub(crate) fn escape(field: &str) -> Cow<str> {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
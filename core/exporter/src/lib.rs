@@ -0,0 +1,215 @@
+mod csv;
+mod stream;
+#[cfg(test)]
+mod tests;
+
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use derive_more::{Display, From};
+use futures_timer::Delay;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use protocol::traits::{Context, ExportedBlock, Exporter, Storage};
+use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
+
+pub use crate::csv::CsvExporter;
+pub use crate::stream::{pb, StateChange, StateDiffSource, StreamServer};
+
+pub const DEFAULT_EXPORT_INTERVAL: u64 = 1000;
+// Blocks exported between two saves of progress
+const EXPORT_BATCH_SIZE: u64 = 100;
+
+/// Hands executed blocks to registered exporters in height order. Progress
+/// is saved to the cursor file after exporters flush, so exporting resumes
+/// from there after a restart.
+///
+/// Exporting is woken by heights sent to the hook from `executed_hook`, such
+/// as by the executor of consensus, and polls every interval for blocks
+/// executed without it, such as by synchronization. Only the csv exporter is
+/// built in, other sinks like Parquet files or a Kafka topic implement
+/// `Exporter` and are registered on the daemon.
+pub struct ExportDaemon<S> {
+    storage:     Arc<S>,
+    exporters:   Vec<Box<dyn Exporter>>,
+    cursor_path: PathBuf,
+    next_height: u64,
+    interval:    Duration,
+    executed:    Option<UnboundedReceiver<u64>>,
+}
+
+impl<S: Storage> ExportDaemon<S> {
+    /// Start from the height if nothing is exported yet, a later height can
+    /// skip exported blocks but an earlier one doesn't export them again.
+    pub fn new(storage: Arc<S>, cursor_path: PathBuf, start_height: u64) -> ProtocolResult<Self> {
+        let next_height = match read_cursor(&cursor_path)? {
+            Some(height) => start_height.max(height + 1),
+            None => start_height,
+        };
+
+        Ok(ExportDaemon {
+            storage,
+            exporters: vec![],
+            cursor_path,
+            next_height,
+            interval: Duration::from_millis(DEFAULT_EXPORT_INTERVAL),
+            executed: None,
+        })
+    }
+
+    pub fn interval(mut self, interval: u64) -> Self {
+        self.interval = Duration::from_millis(interval);
+        self
+    }
+
+    pub fn register(&mut self, exporter: Box<dyn Exporter>) {
+        self.exporters.push(exporter);
+    }
+
+    /// Executed heights sent to the hook wake the daemon before the interval
+    /// ends.
+    pub fn executed_hook(&mut self) -> UnboundedSender<u64> {
+        let (hook, executed) = unbounded_channel();
+        self.executed = Some(executed);
+        hook
+    }
+
+    pub fn next_height(&self) -> u64 {
+        self.next_height
+    }
+
+    pub async fn run(mut self) {
+        loop {
+            match self.export_available().await {
+                Ok(0) => self.wait_executed().await,
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!(
+                        "[exporter]: export block {} failed: {:?}",
+                        self.next_height,
+                        e
+                    );
+                    Delay::new(self.interval).await
+                }
+            }
+        }
+    }
+
+    // Wait for an executed height or the interval to end
+    async fn wait_executed(&mut self) {
+        let executed = match self.executed.as_mut() {
+            Some(executed) => executed,
+            None => return Delay::new(self.interval).await,
+        };
+
+        if let Ok(None) = tokio::time::timeout(self.interval, executed.recv()).await {
+            log::warn!("[exporter]: executed hook is closed, poll every interval");
+            self.executed = None;
+        }
+    }
+
+    /// Export executed blocks not exported yet, return the number of them.
+    pub async fn export_available(&mut self) -> ProtocolResult<u64> {
+        let latest_height = self.storage.get_latest_block().await?.header.height;
+        let mut exported = 0;
+
+        while self.next_height <= latest_height && exported < EXPORT_BATCH_SIZE {
+            let block = match executed_block(&*self.storage, self.next_height).await? {
+                Some(block) => block,
+                None => break,
+            };
+
+            for exporter in self.exporters.iter() {
+                exporter.export(Context::new(), &block).await?;
+            }
+            self.next_height += 1;
+            exported += 1;
+        }
+
+        if exported > 0 {
+            for exporter in self.exporters.iter() {
+                exporter.flush(Context::new()).await?;
+            }
+            write_cursor(&self.cursor_path, self.next_height - 1)?;
+            log::info!(
+                "[exporter]: exported {} blocks to height {}",
+                exported,
+                self.next_height - 1
+            );
+        }
+        Ok(exported)
+    }
+}
+
+// None if receipts of the block are not saved yet
+async fn executed_block<S: Storage>(
+    storage: &S,
+    height: u64,
+) -> ProtocolResult<Option<ExportedBlock>> {
+    let block = storage.get_block_by_height(height).await?;
+    let tx_hashes = block.ordered_tx_hashes.clone();
+
+    let receipts = storage.get_receipts(tx_hashes.clone()).await?;
+    if receipts.len() != tx_hashes.len() {
+        return Ok(None);
+    }
+    let txs = storage.get_transactions(tx_hashes.clone()).await?;
+    if txs.len() != tx_hashes.len() {
+        return Err(ExporterError::MissingTransactions(height).into());
+    }
+
+    Ok(Some(ExportedBlock {
+        block,
+        txs,
+        receipts,
+    }))
+}
+
+// Replace the cursor by rename, so a crash never leaves it half written
+fn write_cursor(path: &Path, height: u64) -> ProtocolResult<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, height.to_string()).map_err(ExporterError::Io)?;
+    fs::rename(&tmp_path, path).map_err(ExporterError::Io)?;
+    Ok(())
+}
+
+fn read_cursor(path: &Path) -> ProtocolResult<Option<u64>> {
+    match fs::read_to_string(path) {
+        Ok(cursor) => {
+            let height = cursor
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| ExporterError::Cursor(cursor))?;
+            Ok(Some(height))
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ExporterError::Io(e).into()),
+    }
+}
+
+#[derive(Debug, Display, From)]
+pub enum ExporterError {
+    #[display(fmt = "io error {:?}", _0)]
+    Io(io::Error),
+
+    #[display(fmt = "invalid cursor {:?}", _0)]
+    Cursor(String),
+
+    #[display(fmt = "transactions of block {} are missing", _0)]
+    MissingTransactions(u64),
+
+    #[display(fmt = "stream server error {:?}", _0)]
+    Stream(tonic::transport::Error),
+}
+
+impl Error for ExporterError {}
+
+impl From<ExporterError> for ProtocolError {
+    fn from(err: ExporterError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Exporter, Box::new(err))
+    }
+}
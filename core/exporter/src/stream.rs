@@ -0,0 +1,240 @@
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_timer::Delay;
+use tokio::sync::mpsc;
+use tonic::{Request, Response, Status};
+
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::Storage;
+use protocol::types::Hash;
+use protocol::{Bytes, ProtocolResult};
+
+use crate::{executed_block, read_cursor, write_cursor, ExporterError, DEFAULT_EXPORT_INTERVAL};
+
+use self::pb::execution_stream_server::{ExecutionStream, ExecutionStreamServer};
+use self::pb::{AckRequest, AckResponse, ExecutedHeight, StateWrite, SubscribeRequest};
+
+/// Messages and the client of the stream, generated from
+/// `proto/stream.proto`.
+pub mod pb {
+    tonic::include_proto!("muta.stream");
+}
+
+// Executed heights buffered for a consumer before pushing waits for it
+const SUBSCRIPTION_BUFFER: usize = 16;
+const MAX_CONSUMER_NAME_LEN: usize = 64;
+
+/// A write to the state by a committed height, tx hash is none for writes of
+/// block hooks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateChange {
+    pub tx_hash:    Option<Hash>,
+    pub service:    String,
+    pub key:        Bytes,
+    pub value_hash: Hash,
+}
+
+/// Writes to the state by committed heights, such as by re-executing blocks.
+#[async_trait]
+pub trait StateDiffSource: Send + Sync {
+    async fn state_diff(&self, height: u64) -> ProtocolResult<Vec<StateChange>>;
+}
+
+/// Pushes executed heights to consumers of the gRPC `ExecutionStream`
+/// service. Consumers subscribe from a height, or resume after their cursor
+/// saved by acks under the cursor directory. Heights are pushed once they are
+/// executed, polled every interval.
+pub struct StreamServer<S> {
+    storage:     Arc<S>,
+    cursor_dir:  PathBuf,
+    state_diffs: Option<Arc<dyn StateDiffSource>>,
+    interval:    Duration,
+}
+
+impl<S: Storage + 'static> StreamServer<S> {
+    pub fn new(storage: Arc<S>, cursor_dir: PathBuf) -> ProtocolResult<Self> {
+        fs::create_dir_all(&cursor_dir).map_err(ExporterError::Io)?;
+
+        Ok(StreamServer {
+            storage,
+            cursor_dir,
+            state_diffs: None,
+            interval: Duration::from_millis(DEFAULT_EXPORT_INTERVAL),
+        })
+    }
+
+    pub fn interval(mut self, interval: u64) -> Self {
+        self.interval = Duration::from_millis(interval);
+        self
+    }
+
+    /// Subscriptions requesting state diffs are rejected without a source.
+    pub fn state_diffs(mut self, source: Arc<dyn StateDiffSource>) -> Self {
+        self.state_diffs = Some(source);
+        self
+    }
+
+    pub async fn serve(self, addr: SocketAddr) -> ProtocolResult<()> {
+        tonic::transport::Server::builder()
+            .add_service(ExecutionStreamServer::new(self))
+            .serve(addr)
+            .await
+            .map_err(ExporterError::Stream)?;
+        Ok(())
+    }
+
+    // Names are file names of cursors, so only word characters are allowed
+    fn cursor_path(&self, consumer: &str) -> Result<PathBuf, Status> {
+        let valid = !consumer.is_empty()
+            && consumer.len() <= MAX_CONSUMER_NAME_LEN
+            && consumer
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(Status::invalid_argument(format!(
+                "invalid consumer name {:?}",
+                consumer
+            )));
+        }
+
+        Ok(self.cursor_dir.join(format!("{}.cursor", consumer)))
+    }
+}
+
+#[async_trait]
+impl<S: Storage + 'static> ExecutionStream for StreamServer<S> {
+    type SubscribeStream = mpsc::Receiver<Result<ExecutedHeight, Status>>;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let req = request.into_inner();
+
+        let state_diffs = match (req.state_diff, &self.state_diffs) {
+            (true, None) => {
+                return Err(Status::failed_precondition(
+                    "state diffs are not served by this node",
+                ))
+            }
+            (true, Some(source)) => Some(Arc::clone(source)),
+            (false, _) => None,
+        };
+
+        let mut next_height = req.from_height;
+        if req.resume {
+            let path = self.cursor_path(&req.consumer)?;
+            if let Some(acked) = read_cursor(&path).map_err(internal)? {
+                next_height = acked + 1;
+            }
+        }
+        log::info!(
+            "[exporter]: consumer {:?} subscribed from height {}",
+            req.consumer,
+            next_height
+        );
+
+        let (tx, rx) = mpsc::channel(SUBSCRIPTION_BUFFER);
+        tokio::spawn(push(
+            Arc::clone(&self.storage),
+            state_diffs,
+            next_height,
+            self.interval,
+            tx,
+        ));
+        Ok(Response::new(rx))
+    }
+
+    async fn ack(&self, request: Request<AckRequest>) -> Result<Response<AckResponse>, Status> {
+        let req = request.into_inner();
+        let path = self.cursor_path(&req.consumer)?;
+
+        write_cursor(&path, req.height).map_err(internal)?;
+        Ok(Response::new(AckResponse { height: req.height }))
+    }
+}
+
+// Push heights until the consumer disconnects, errors end the subscription
+async fn push<S: Storage>(
+    storage: Arc<S>,
+    state_diffs: Option<Arc<dyn StateDiffSource>>,
+    mut next_height: u64,
+    interval: Duration,
+    mut tx: mpsc::Sender<Result<ExecutedHeight, Status>>,
+) {
+    loop {
+        match executed_height(&*storage, state_diffs.as_ref(), next_height).await {
+            Ok(Some(executed)) => {
+                if tx.send(Ok(executed)).await.is_err() {
+                    return;
+                }
+                next_height += 1;
+            }
+            Ok(None) => Delay::new(interval).await,
+            Err(e) => {
+                log::error!("[exporter]: stream height {} failed: {:?}", next_height, e);
+                let _ = tx.send(Err(internal(e))).await;
+                return;
+            }
+        }
+    }
+}
+
+// None if the height is not executed yet
+async fn executed_height<S: Storage>(
+    storage: &S,
+    state_diffs: Option<&Arc<dyn StateDiffSource>>,
+    height: u64,
+) -> ProtocolResult<Option<ExecutedHeight>> {
+    if storage.get_latest_block().await?.header.height < height {
+        return Ok(None);
+    }
+    let exported = match executed_block(storage, height).await? {
+        Some(exported) => exported,
+        None => return Ok(None),
+    };
+
+    let state_diff = match state_diffs {
+        Some(source) => source
+            .state_diff(height)
+            .await?
+            .into_iter()
+            .map(|change| StateWrite {
+                tx_hash:    change
+                    .tx_hash
+                    .map(|hash| hash.as_bytes().to_vec())
+                    .unwrap_or_default(),
+                service:    change.service,
+                key:        change.key.to_vec(),
+                value_hash: change.value_hash.as_bytes().to_vec(),
+            })
+            .collect(),
+        None => vec![],
+    };
+
+    let mut txs = Vec::with_capacity(exported.txs.len());
+    for tx in exported.txs.iter() {
+        txs.push(tx.encode_fixed()?.to_vec());
+    }
+    let mut receipts = Vec::with_capacity(exported.receipts.len());
+    for receipt in exported.receipts.iter() {
+        receipts.push(receipt.encode_fixed()?.to_vec());
+    }
+
+    Ok(Some(ExecutedHeight {
+        height,
+        block: exported.block.encode_fixed()?.to_vec(),
+        txs,
+        receipts,
+        state_diff,
+    }))
+}
+
+fn internal<E: std::fmt::Debug>(err: E) -> Status {
+    Status::internal(format!("{:?}", err))
+}
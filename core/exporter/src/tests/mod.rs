@@ -0,0 +1,300 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::executor::block_on;
+use tonic::Request;
+
+use core_storage::{adapter::memory::MemoryAdapter, ImplStorage};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{ServiceResponse, Storage};
+use protocol::types::{
+    Address, Block, BlockHeader, Event, Hash, Proof, RawTransaction, Receipt, ReceiptResponse,
+    SignedTransaction, TransactionRequest,
+};
+use protocol::Bytes;
+
+use crate::csv::escape;
+use crate::pb::execution_stream_server::ExecutionStream;
+use crate::pb::{AckRequest, SubscribeRequest};
+use crate::{CsvExporter, ExportDaemon, StreamServer};
+
+type MemoryStorage = ImplStorage<MemoryAdapter>;
+
+#[test]
+fn test_export_executed_blocks() {
+    let dir = temp_dir("export_executed_blocks");
+    let storage = Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new())));
+
+    let txs = vec![mock_tx(1), mock_tx(2)];
+    block_on(storage.insert_block(mock_block(0, vec![]))).unwrap();
+    block_on(storage.insert_block(mock_block(1, txs.clone()))).unwrap();
+    block_on(storage.insert_transactions(txs.clone())).unwrap();
+
+    let mut daemon = new_daemon(&storage, &dir, 0);
+    // Block 1 is not executed
+    assert_eq!(block_on(daemon.export_available()).unwrap(), 1);
+    assert_eq!(daemon.next_height(), 1);
+
+    let receipts = txs.iter().map(|tx| mock_receipt(1, tx)).collect();
+    block_on(storage.insert_receipts(receipts)).unwrap();
+    assert_eq!(block_on(daemon.export_available()).unwrap(), 1);
+    assert_eq!(block_on(daemon.export_available()).unwrap(), 0);
+
+    assert_eq!(read_table(&dir, "blocks.csv").len(), 3);
+    let transactions = read_table(&dir, "transactions.csv");
+    assert_eq!(transactions.len(), 3);
+    assert!(transactions[1].starts_with(&format!("{},1,0,", txs[0].tx_hash.as_hex())));
+    assert!(transactions[1].contains(",asset,transfer,\"{\"\"value\"\":1}\","));
+    assert_eq!(read_table(&dir, "receipts.csv").len(), 3);
+    let events = read_table(&dir, "events.csv");
+    assert_eq!(events[0], "tx_hash,height,index,service,data");
+    assert_eq!(
+        events[1],
+        format!(
+            "{},1,0,asset,\"{{\"\"value\"\":1}}\"",
+            txs[0].tx_hash.as_hex()
+        )
+    );
+
+    // Resume from cursor, exported blocks are not written again
+    let mut daemon = new_daemon(&storage, &dir, 0);
+    assert_eq!(daemon.next_height(), 2);
+    assert_eq!(block_on(daemon.export_available()).unwrap(), 0);
+    assert_eq!(read_table(&dir, "blocks.csv").len(), 3);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_start_height() {
+    let dir = temp_dir("start_height");
+    let storage = Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new())));
+    for height in 0..5 {
+        block_on(storage.insert_block(mock_block(height, vec![]))).unwrap();
+    }
+
+    let mut daemon = new_daemon(&storage, &dir, 3);
+    assert_eq!(block_on(daemon.export_available()).unwrap(), 2);
+    let blocks = read_table(&dir, "blocks.csv");
+    assert!(blocks[1].starts_with("3,"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_export_on_executed_hook() {
+    let dir = temp_dir("export_on_executed_hook");
+    let storage = Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new())));
+    storage.insert_block(mock_block(0, vec![])).await.unwrap();
+
+    // Polls once an hour, only the hook wakes it in the test
+    let mut daemon = new_daemon(&storage, &dir, 0).interval(3_600_000);
+    let hook = daemon.executed_hook();
+    tokio::spawn(daemon.run());
+    wait_rows(&dir, "blocks.csv", 2).await;
+
+    storage.insert_block(mock_block(1, vec![])).await.unwrap();
+    hook.send(1).unwrap();
+    wait_rows(&dir, "blocks.csv", 3).await;
+    assert!(read_table(&dir, "blocks.csv")[2].starts_with("1,"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_stream_executed_heights() {
+    let dir = temp_dir("stream_executed_heights");
+    let storage = Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new())));
+
+    let txs = vec![mock_tx(1)];
+    let receipts = txs.iter().map(|tx| mock_receipt(1, tx)).collect();
+    storage.insert_block(mock_block(0, vec![])).await.unwrap();
+    storage
+        .insert_block(mock_block(1, txs.clone()))
+        .await
+        .unwrap();
+    storage.insert_transactions(txs.clone()).await.unwrap();
+    storage.insert_receipts(receipts).await.unwrap();
+
+    let server = StreamServer::new(Arc::clone(&storage), dir.clone())
+        .unwrap()
+        .interval(10);
+    let mut stream = server
+        .subscribe(Request::new(subscribe_request(0, false)))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(stream.recv().await.unwrap().unwrap().height, 0);
+
+    let executed = stream.recv().await.unwrap().unwrap();
+    assert_eq!(executed.height, 1);
+    assert_eq!(
+        Block::decode_fixed(Bytes::from(executed.block)).unwrap(),
+        mock_block(1, txs.clone())
+    );
+    assert_eq!(
+        SignedTransaction::decode_fixed(Bytes::from(executed.txs[0].clone())).unwrap(),
+        txs[0]
+    );
+    assert_eq!(executed.receipts.len(), 1);
+
+    // Heights are pushed once they are executed
+    storage.insert_block(mock_block(2, vec![])).await.unwrap();
+    assert_eq!(stream.recv().await.unwrap().unwrap().height, 2);
+
+    // Resume after the acked cursor
+    let ack = |consumer: &str| AckRequest {
+        consumer: consumer.to_owned(),
+        height:   1,
+    };
+    assert_eq!(
+        server
+            .ack(Request::new(ack("indexer")))
+            .await
+            .unwrap()
+            .into_inner()
+            .height,
+        1
+    );
+    let mut resumed = server
+        .subscribe(Request::new(subscribe_request(0, true)))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resumed.recv().await.unwrap().unwrap().height, 2);
+
+    assert!(server.ack(Request::new(ack("../indexer"))).await.is_err());
+    // No source of state diffs
+    let mut request = subscribe_request(0, false);
+    request.state_diff = true;
+    assert!(server.subscribe(Request::new(request)).await.is_err());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(escape("transfer"), "transfer");
+    assert_eq!(escape("a,b"), "\"a,b\"");
+    assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    assert_eq!(escape("line\nbreak"), "\"line\nbreak\"");
+}
+
+fn new_daemon(storage: &Arc<MemoryStorage>, dir: &Path, start: u64) -> ExportDaemon<MemoryStorage> {
+    let mut daemon = ExportDaemon::new(Arc::clone(storage), dir.join("cursor"), start).unwrap();
+    daemon.register(Box::new(CsvExporter::new(dir.to_path_buf()).unwrap()));
+    daemon
+}
+
+fn subscribe_request(from_height: u64, resume: bool) -> SubscribeRequest {
+    SubscribeRequest {
+        consumer: "indexer".to_owned(),
+        from_height,
+        resume,
+        state_diff: false,
+    }
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("muta_exporter_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn read_table(dir: &Path, name: &str) -> Vec<String> {
+    fs::read_to_string(dir.join(name))
+        .unwrap()
+        .lines()
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+async fn wait_rows(dir: &Path, name: &str, rows: usize) {
+    for _ in 0..100 {
+        if dir.join(name).exists() && read_table(dir, name).len() >= rows {
+            return;
+        }
+        tokio::time::delay_for(Duration::from_millis(20)).await;
+    }
+    panic!("{} has less than {} rows", name, rows);
+}
+
+fn mock_tx(nonce: u64) -> SignedTransaction {
+    let raw = RawTransaction {
+        chain_id:              Hash::from_empty(),
+        nonce:                 Hash::digest(Bytes::from(nonce.to_string())),
+        timeout:               10,
+        cycles_price:          1,
+        cycles_limit:          100,
+        request:               TransactionRequest {
+            service_name: "asset".to_owned(),
+            method:       "transfer".to_owned(),
+            payload:      "{\"value\":1}".to_owned(),
+        },
+        sender:                Some(Address::default()),
+        valid_until_timestamp: None,
+    };
+
+    SignedTransaction {
+        raw,
+        tx_hash: Hash::digest(Bytes::from(format!("tx{}", nonce))),
+        pubkey: Bytes::new(),
+        signature: Bytes::new(),
+    }
+}
+
+fn mock_receipt(height: u64, tx: &SignedTransaction) -> Receipt {
+    Receipt {
+        state_root: Hash::from_empty(),
+        height,
+        tx_hash: tx.tx_hash.clone(),
+        cycles_used: 10,
+        events: vec![Event {
+            service: "asset".to_owned(),
+            data:    "{\"value\":1}".to_owned(),
+        }],
+        response: ReceiptResponse {
+            service_name: "asset".to_owned(),
+            method:       "transfer".to_owned(),
+            response:     ServiceResponse::<String> {
+                code:          0,
+                succeed_data:  "".to_owned(),
+                error_message: "".to_owned(),
+            },
+        },
+    }
+}
+
+fn mock_block(height: u64, txs: Vec<SignedTransaction>) -> Block {
+    Block {
+        header:            BlockHeader {
+            chain_id: Hash::from_empty(),
+            height,
+            exec_height: height,
+            pre_hash: Hash::from_empty(),
+            timestamp: 0,
+            logs_bloom: vec![],
+            order_root: Hash::from_empty(),
+            confirm_root: vec![],
+            state_root: Hash::from_empty(),
+            receipt_root: vec![],
+            cycles_used: vec![],
+            proposer: Address::default(),
+            proof: Proof {
+                height:     0,
+                round:      0,
+                block_hash: Hash::from_empty(),
+                signature:  Bytes::new(),
+                bitmap:     Bytes::new(),
+            },
+            validator_version: 0,
+            validators: vec![],
+            extra_data: Bytes::new(),
+        },
+        ordered_tx_hashes: txs.into_iter().map(|tx| tx.tx_hash).collect(),
+        vote_extensions:   vec![],
+    }
+}
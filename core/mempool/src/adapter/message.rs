@@ -11,6 +11,7 @@ use serde_derive::{Deserialize, Serialize};
 use crate::context::TxContext;
 
 pub const END_GOSSIP_NEW_TXS: &str = "/gossip/mempool/new_txs";
+pub const END_GOSSIP_NEW_TX_HASHES: &str = "/gossip/mempool/new_tx_hashes";
 pub const RPC_PULL_TXS: &str = "/rpc_call/mempool/pull_txs";
 pub const RPC_RESP_PULL_TXS: &str = "/rpc_resp/mempool/pull_txs";
 
@@ -66,6 +67,43 @@ where
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MsgNewTxHashes {
+    #[serde(with = "core_network::serde_multi")]
+    pub hashes: Vec<Hash>,
+}
+
+// New transactions are announced by hashes, we only pull unknown ones from
+// the announcer.
+pub struct NewTxHashesHandler<M> {
+    mem_pool: Arc<M>,
+}
+
+impl<M> NewTxHashesHandler<M>
+where
+    M: MemPool,
+{
+    pub fn new(mem_pool: Arc<M>) -> Self {
+        NewTxHashesHandler { mem_pool }
+    }
+}
+
+#[async_trait]
+impl<M> MessageHandler for NewTxHashesHandler<M>
+where
+    M: MemPool + 'static,
+{
+    type Message = MsgNewTxHashes;
+
+    async fn process(&self, ctx: Context, msg: Self::Message) {
+        let ctx = ctx.mark_network_origin_new_txs();
+
+        if let Err(err) = self.mem_pool.pull_announced_txs(ctx, msg.hashes).await {
+            log::warn!("[core_mempool] pull announced txs {}", err);
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MsgPullTxs {
     #[serde(with = "core_network::serde_multi")]
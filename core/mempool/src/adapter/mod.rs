@@ -20,17 +20,24 @@ use futures::{
 };
 use futures_timer::Delay;
 use log::{debug, error};
+use parking_lot::RwLock;
 
 use common_crypto::Crypto;
 use protocol::{
     fixed_codec::FixedCodec,
-    traits::{Context, Gossip, MemPoolAdapter, Priority, Rpc, Storage},
-    types::{Hash, SignedTransaction},
+    traits::{
+        executed_state_root, Context, ExecutorFactory, ExecutorParams, Gossip, MemPoolAdapter,
+        PeerMisbehavior, PeerReporter, Priority, Rpc, ServiceMapping, Storage,
+    },
+    types::{
+        signing_hash, Hash, SignatureDomain, SignedTransaction, TRANSACTION_SIGNATURE_PURPOSE,
+    },
     ProtocolError, ProtocolErrorKind, ProtocolResult,
 };
 
 use crate::adapter::message::{
-    MsgNewTxs, MsgPullTxs, MsgPushTxs, END_GOSSIP_NEW_TXS, RPC_PULL_TXS,
+    MsgNewTxHashes, MsgNewTxs, MsgPullTxs, MsgPushTxs, END_GOSSIP_NEW_TXS,
+    END_GOSSIP_NEW_TX_HASHES, RPC_PULL_TXS,
 };
 use crate::MemPoolError;
 
@@ -108,13 +115,13 @@ impl IntervalTxsBroadcaster {
             return;
         }
 
+        // Announce hashes, peers pull transactions they don't have. Peers
+        // not pulling hashes receive full transactions, network sends each
+        // message to its own peers only.
         let batch_stxs = txs_cache.drain(..).collect::<Vec<_>>();
-        let gossip_msg = MsgNewTxs { batch_stxs };
-
-        let ctx = Context::new();
-        let end = END_GOSSIP_NEW_TXS;
+        let hashes = batch_stxs.iter().map(|stx| stx.tx_hash.clone()).collect();
 
-        let report_if_err = move |ret: ProtocolResult<()>| {
+        let report_if_err = |ret: ProtocolResult<()>| {
             if let Err(err) = ret {
                 if err_tx.unbounded_send(err).is_err() {
                     error!("mempool: default mempool adapter dropped");
@@ -122,37 +129,63 @@ impl IntervalTxsBroadcaster {
             }
         };
 
-        report_if_err(
-            gossip
-                .broadcast(ctx, end, gossip_msg, Priority::Normal)
-                .await,
-        )
+        let announce = MsgNewTxHashes { hashes };
+        let ret = gossip
+            .broadcast(
+                Context::new(),
+                END_GOSSIP_NEW_TX_HASHES,
+                announce,
+                Priority::Normal,
+            )
+            .await;
+        report_if_err(ret);
+
+        let full_txs = MsgNewTxs { batch_stxs };
+        let ret = gossip
+            .broadcast(
+                Context::new(),
+                END_GOSSIP_NEW_TXS,
+                full_txs,
+                Priority::Normal,
+            )
+            .await;
+        report_if_err(ret);
     }
 }
 
-pub struct DefaultMemPoolAdapter<C, N, S> {
-    network: N,
-    storage: Arc<S>,
+pub struct DefaultMemPoolAdapter<C, N, S, DB, Mapping, EF> {
+    network:         N,
+    storage:         Arc<S>,
+    trie_db:         Arc<DB>,
+    service_mapping: Arc<Mapping>,
 
-    timeout_gap:  AtomicU64,
-    cycles_limit: AtomicU64,
-    max_tx_size:  AtomicU64,
+    timeout_gap:      AtomicU64,
+    cycles_limit:     AtomicU64,
+    max_tx_size:      AtomicU64,
+    // Domain of signatures of transactions in next blocks
+    signature_domain: RwLock<Option<SignatureDomain>>,
 
     stx_tx: UnboundedSender<SignedTransaction>,
     err_rx: Mutex<UnboundedReceiver<ProtocolError>>,
 
-    pin_c: PhantomData<C>,
+    pin_c:  PhantomData<C>,
+    pin_ef: PhantomData<EF>,
 }
 
-impl<C, N, S> DefaultMemPoolAdapter<C, N, S>
+impl<C, N, S, DB, Mapping, EF> DefaultMemPoolAdapter<C, N, S, DB, Mapping, EF>
 where
     C: Crypto,
     N: Rpc + Gossip + Clone + Unpin + 'static,
     S: Storage,
+    DB: cita_trie::DB,
+    Mapping: ServiceMapping,
+    EF: ExecutorFactory<DB, S, Mapping>,
 {
     pub fn new(
         network: N,
         storage: Arc<S>,
+        trie_db: Arc<DB>,
+        service_mapping: Arc<Mapping>,
         broadcast_txs_size: usize,
         broadcast_txs_interval: u64,
     ) -> Self {
@@ -176,25 +209,32 @@ where
         DefaultMemPoolAdapter {
             network,
             storage,
+            trie_db,
+            service_mapping,
 
             timeout_gap: AtomicU64::new(0),
             cycles_limit: AtomicU64::new(0),
             max_tx_size: AtomicU64::new(0),
+            signature_domain: RwLock::new(None),
 
             stx_tx,
             err_rx: Mutex::new(err_rx),
 
             pin_c: PhantomData,
+            pin_ef: PhantomData,
         }
     }
 }
 
 #[async_trait]
-impl<C, N, S> MemPoolAdapter for DefaultMemPoolAdapter<C, N, S>
+impl<C, N, S, DB, Mapping, EF> MemPoolAdapter for DefaultMemPoolAdapter<C, N, S, DB, Mapping, EF>
 where
     C: Crypto + Send + Sync + 'static,
-    N: Rpc + Gossip + Clone + Unpin + 'static,
+    N: Rpc + Gossip + PeerReporter + Clone + Unpin + 'static,
     S: Storage + 'static,
+    DB: cita_trie::DB + 'static,
+    Mapping: ServiceMapping + 'static,
+    EF: ExecutorFactory<DB, S, Mapping> + 'static,
 {
     async fn pull_txs(
         &self,
@@ -228,13 +268,54 @@ where
     }
 
     async fn check_signature(&self, _ctx: Context, tx: SignedTransaction) -> ProtocolResult<()> {
-        let hash = tx.tx_hash.as_bytes();
+        let hash = signing_hash(
+            self.signature_domain.read().as_ref(),
+            TRANSACTION_SIGNATURE_PURPOSE,
+            &tx.tx_hash,
+        )
+        .as_bytes();
         let pub_key = tx.pubkey.as_ref();
         let sig = tx.signature.as_ref();
 
-        C::verify_signature(hash.as_ref(), sig, pub_key).map_err(|_| {
-            MemPoolError::CheckSig {
-                tx_hash: tx.tx_hash,
+        C::verify_signature(hash.as_ref(), sig, pub_key).map_err(|_| MemPoolError::CheckSig {
+            tx_hash: tx.tx_hash.clone(),
+        })?;
+
+        // Signer acts for another account, ask executor whether the account
+        // authorized it or not, against latest state.
+        if tx.raw.sender.is_none() {
+            return Ok(());
+        }
+
+        // Header of latest block carries the state of an earlier executed
+        // block, receipts of latest block have the state after it once it's
+        // executed.
+        let block = self.storage.get_latest_block().await?;
+        let state_root = executed_state_root(&*self.storage, block.header.height)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| block.header.state_root.clone());
+        let params = ExecutorParams {
+            state_root:      state_root.clone(),
+            height:          block.header.height,
+            timestamp:       block.header.timestamp,
+            cycles_limit:    tx.raw.cycles_limit,
+            proposer:        block.header.proposer,
+            vote_extensions: vec![],
+            extra_data:      Bytes::new(),
+        };
+
+        let executor = EF::from_root(
+            state_root,
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            Arc::clone(&self.service_mapping),
+        )?;
+        executor.verify_authorization(&params, &tx).map_err(|e| {
+            MemPoolError::CheckAuthorization {
+                tx_hash: tx.tx_hash.clone(),
+                reason:  e.to_string(),
             }
             .into()
         })
@@ -312,6 +393,18 @@ where
             return Err(timeout.into());
         }
 
+        // Verify expiration, by time of the latest block, since next blocks
+        // are after it
+        if let Some(valid_until) = stx.raw.valid_until_timestamp {
+            if valid_until < latest_block.header.timestamp {
+                return Err(MemPoolError::Expired {
+                    tx_hash: stx.tx_hash,
+                    valid_until,
+                }
+                .into());
+            }
+        }
+
         Ok(())
     }
 
@@ -334,10 +427,21 @@ where
         Ok(height)
     }
 
-    fn set_args(&self, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64) {
+    fn report_bad(&self, ctx: Context, misbehavior: PeerMisbehavior) {
+        self.network.report(ctx, misbehavior);
+    }
+
+    fn set_args(
+        &self,
+        timeout_gap: u64,
+        cycles_limit: u64,
+        max_tx_size: u64,
+        signature_domain: Option<SignatureDomain>,
+    ) {
         self.timeout_gap.store(timeout_gap, Ordering::Relaxed);
         self.cycles_limit.store(cycles_limit, Ordering::Relaxed);
         self.max_tx_size.store(max_tx_size, Ordering::Relaxed);
+        *self.signature_domain.write() = signature_domain;
     }
 }
 
@@ -365,7 +469,7 @@ impl From<AdapterError> for ProtocolError {
 mod tests {
     use super::IntervalTxsBroadcaster;
 
-    use crate::{adapter::message::MsgNewTxs, tests::default_mock_txs};
+    use crate::{adapter::message::MsgNewTxHashes, tests::default_mock_txs};
 
     use protocol::{
         traits::{Context, Gossip, MessageCodec, Priority},
@@ -441,7 +545,9 @@ mod tests {
     macro_rules! pop_msg {
         ($msgs:expr) => {{
             let msg = $msgs.pop().expect("should have one message");
-            MsgNewTxs::decode(msg).await.expect("decode MsgNewTxs fail")
+            MsgNewTxHashes::decode(msg)
+                .await
+                .expect("decode MsgNewTxHashes fail")
         }};
     }
 
@@ -483,7 +589,7 @@ mod tests {
         assert_eq!(msgs.len(), 1, "should only have one message");
 
         let msg = pop_msg!(msgs);
-        assert_eq!(msg.batch_stxs.len(), 10, "should only have 10 stx");
+        assert_eq!(msg.hashes.len(), 10, "should only have 10 stx");
     }
 
     #[tokio::test]
@@ -513,7 +619,7 @@ mod tests {
         assert_eq!(msgs.len(), 1, "should only have one message");
 
         let msg = pop_msg!(msgs);
-        assert_eq!(msg.batch_stxs.len(), 9, "should only have 9 stx");
+        assert_eq!(msg.hashes.len(), 9, "should only have 9 stx");
     }
 
     #[tokio::test]
@@ -546,15 +652,11 @@ mod tests {
         assert_eq!(msgs.len(), 2, "should only have two messages");
 
         let msg = pop_msg!(msgs);
-        assert_eq!(
-            msg.batch_stxs.len(),
-            9,
-            "last message should only have 9 stx"
-        );
+        assert_eq!(msg.hashes.len(), 9, "last message should only have 9 stx");
 
         let msg = pop_msg!(msgs);
         assert_eq!(
-            msg.batch_stxs.len(),
+            msg.hashes.len(),
             10,
             "first message should only have 10 stx"
         );
@@ -8,41 +8,74 @@ mod tests;
 mod tx_cache;
 
 pub use adapter::message::{
-    MsgPushTxs, NewTxsHandler, PullTxsHandler, END_GOSSIP_NEW_TXS, RPC_PULL_TXS, RPC_RESP_PULL_TXS,
+    MsgPushTxs, NewTxHashesHandler, NewTxsHandler, PullTxsHandler, END_GOSSIP_NEW_TXS,
+    END_GOSSIP_NEW_TX_HASHES, RPC_PULL_TXS, RPC_RESP_PULL_TXS,
 };
 pub use adapter::DefaultMemPoolAdapter;
 pub use adapter::{DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE};
 
 use std::error::Error;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
 use async_trait::async_trait;
 use derive_more::Display;
+use parking_lot::RwLock as SyncRwLock;
 use tokio::sync::RwLock;
+use tracing::{field, info_span};
+use tracing_futures::Instrument;
 
-use protocol::traits::{Context, MemPool, MemPoolAdapter, MixedTxHashes};
-use protocol::types::{Hash, SignedTransaction};
+use common_metrics::{MEMPOOL_INSERT_SECONDS, MEMPOOL_REBROADCAST_TXS, MEMPOOL_SIZE};
+use protocol::traits::{
+    Context, MemPool, MemPoolAdapter, MixedTxHashes, PeerMisbehavior, PrivateTxContext,
+    TraceContext,
+};
+use protocol::types::{is_halted, Hash, PayloadLimits, SignatureDomain, SignedTransaction};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 use crate::context::TxContext;
 use crate::map::Map;
 use crate::tx_cache::TxCache;
 
+pub const DEFAULT_REBROADCAST_AFTER: u64 = 20; // blocks
+
 /// Memory pool for caching transactions.
 pub struct HashMemPool<Adapter: MemPoolAdapter> {
     /// Pool size limit.
-    pool_size:      usize,
+    pool_size:      AtomicUsize,
+    /// The pool size when the mempool was created, caches are allocated by it,
+    /// so the pool size can not be set larger than this.
+    pool_capacity:  usize,
     /// A system param limits the life time of an off-chain transaction.
     timeout_gap:    AtomicU64,
+    /// Height the chain halts at by emergency governance, zero if not.
+    halt_height:    AtomicU64,
     /// A structure for caching new transactions and responsible transactions of
     /// propose-sync.
     tx_cache:       TxCache,
     /// A structure for caching fresh transactions in order transaction hashes.
     callback_cache: Map<SignedTransaction>,
+    /// Hashes of announced transactions being pulled, so that a transaction
+    /// announced by many peers is pulled only once.
+    pulling_cache:  Map<()>,
     /// Supply necessary functions from outer modules.
     adapter:        Adapter,
     /// exclusive flush_memory and insert_tx to avoid repeat txs insertion.
     flush_lock:     RwLock<()>,
+    /// Set when the node is shutting down, new transactions are rejected.
+    closed:         AtomicBool,
+    /// Private transactions are only packaged by this node, they're rejected
+    /// unless it proposes blocks.
+    allow_private:  AtomicBool,
+    /// Transactions still in the pool after these blocks are announced again,
+    /// in case they never reached the proposers. Zero disables it.
+    rebroadcast:    AtomicU64,
+    /// Max payload sizes of methods declared by services.
+    payload_limits: SyncRwLock<PayloadLimits>,
+    /// Domain transactions are signed in from the next height.
+    sig_domain:     SyncRwLock<Option<SignatureDomain>>,
+    /// Set when the signature domain changes, signatures of transactions in
+    /// the pool are checked again before the next package.
+    recheck_sigs:   AtomicBool,
 }
 
 impl<Adapter> HashMemPool<Adapter>
@@ -51,12 +84,21 @@ where
 {
     pub fn new(pool_size: usize, adapter: Adapter) -> Self {
         HashMemPool {
-            pool_size,
+            pool_size: AtomicUsize::new(pool_size),
+            pool_capacity: pool_size,
             timeout_gap: AtomicU64::new(0),
+            halt_height: AtomicU64::new(0),
             tx_cache: TxCache::new(pool_size * 2),
             callback_cache: Map::new(pool_size),
+            pulling_cache: Map::new(pool_size),
             adapter,
             flush_lock: RwLock::new(()),
+            closed: AtomicBool::new(false),
+            allow_private: AtomicBool::new(false),
+            rebroadcast: AtomicU64::new(0),
+            payload_limits: SyncRwLock::new(PayloadLimits::default()),
+            sig_domain: SyncRwLock::new(None),
+            recheck_sigs: AtomicBool::new(false),
         }
     }
 
@@ -72,6 +114,78 @@ where
         &self.adapter
     }
 
+    pub fn get_pool_size(&self) -> usize {
+        self.pool_size.load(Ordering::SeqCst)
+    }
+
+    /// Update the pool size limit at runtime. Transactions already in the pool
+    /// are kept if the new limit is smaller than the current size.
+    pub fn set_pool_size(&self, pool_size: usize) -> ProtocolResult<()> {
+        if pool_size == 0 || pool_size > self.pool_capacity {
+            return Err(MemPoolError::InvalidPoolSize {
+                pool_size,
+                capacity: self.pool_capacity,
+            }
+            .into());
+        }
+
+        self.pool_size.store(pool_size, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Limit bytes of cached transactions, zero removes the limit. New
+    /// transactions are rejected while the limit is reached.
+    pub fn set_memory_cap(&self, cap: usize) {
+        self.tx_cache.memory().set_cap(cap);
+    }
+
+    /// Accept private transactions, which are never broadcast.
+    pub fn set_allow_private_txs(&self, allow: bool) {
+        self.allow_private.store(allow, Ordering::SeqCst);
+    }
+
+    /// Announce transactions again once they're in the pool for these blocks,
+    /// intervals double after each time. Zero disables it.
+    pub fn set_rebroadcast_after(&self, blocks: u64) {
+        self.rebroadcast.store(blocks, Ordering::SeqCst);
+    }
+
+    /// Reject transactions whose payloads exceed limits of their methods.
+    pub fn set_payload_limits(&self, limits: PayloadLimits) {
+        *self.payload_limits.write() = limits;
+    }
+
+    /// Stop accepting transactions, those already in the pool can still be
+    /// packaged and flushed.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    // Transactions signed in the previous domain can't be committed once the
+    // domain is switched, such as at `domain_separation_height`, remove them
+    // instead of packaging them into invalid proposals.
+    async fn remove_invalid_signatures(&self, ctx: Context) {
+        if !self.recheck_sigs.swap(false, Ordering::SeqCst) {
+            return;
+        }
+
+        let mut invalid = Vec::new();
+        for tx in self.tx_cache.txs().into_iter() {
+            let tx_hash = tx.tx_hash.clone();
+            if self.adapter.check_signature(ctx.clone(), tx).await.is_err() {
+                invalid.push(tx_hash);
+            }
+        }
+        if !invalid.is_empty() {
+            log::info!(
+                "[core_mempool]: remove {:?} txs signed in previous domain",
+                invalid.len()
+            );
+            self.tx_cache.remove_txs(&invalid);
+            MEMPOOL_SIZE.set(self.tx_cache.len() as i64);
+        }
+    }
+
     fn show_unknown_txs(&self, tx_hashes: Vec<Hash>) -> Vec<Hash> {
         self.tx_cache
             .show_unknown(tx_hashes)
@@ -89,8 +203,35 @@ where
         let _lock = self.flush_lock.read().await;
 
         let tx_hash = &tx.tx_hash;
-        self.tx_cache.check_reach_limit(self.pool_size)?;
-        self.tx_cache.check_exist(tx_hash)?;
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(MemPoolError::Closed {
+                tx_hash: tx_hash.clone(),
+            }
+            .into());
+        }
+        if ctx.is_private_tx() && !self.allow_private.load(Ordering::SeqCst) {
+            return Err(MemPoolError::PrivateTx {
+                tx_hash: tx_hash.clone(),
+            }
+            .into());
+        }
+        let exceeded = self.payload_limits.read().exceeded_by(&tx.raw.request);
+        if let Some(limit) = exceeded {
+            return Err(MemPoolError::ExceedPayloadLimit {
+                tx_hash: tx_hash.clone(),
+                limit,
+                size: tx.raw.request.payload.len() as u64,
+            }
+            .into());
+        }
+        self.tx_cache.check_reach_limit(self.get_pool_size())?;
+        if let Err(e) = self.tx_cache.check_exist(tx_hash) {
+            if ctx.is_network_origin_txs() {
+                self.adapter
+                    .report_bad(ctx, PeerMisbehavior::DuplicateTx(tx_hash.clone()));
+            }
+            return Err(e);
+        }
         self.adapter
             .check_signature(ctx.clone(), tx.clone())
             .await?;
@@ -100,12 +241,22 @@ where
         self.adapter
             .check_storage_exist(ctx.clone(), tx_hash.clone())
             .await?;
-        match tx_type {
-            TxType::NewTx => self.tx_cache.insert_new_tx(tx.clone())?,
-            TxType::ProposeTx => self.tx_cache.insert_propose_tx(tx.clone())?,
-        }
+        // Transactions of proposals are pulled by validators, private ones
+        // among them must not be gossiped before they're committed.
+        let broadcast = match tx_type {
+            TxType::NewTx => {
+                self.tx_cache
+                    .insert_new_tx(tx.clone(), ctx.is_private_tx())?;
+                !ctx.is_network_origin_txs() && !ctx.is_private_tx()
+            }
+            TxType::ProposeTx => {
+                self.tx_cache.insert_propose_tx(tx.clone())?;
+                false
+            }
+        };
+        MEMPOOL_SIZE.set(self.tx_cache.len() as i64);
 
-        if !ctx.is_network_origin_txs() {
+        if broadcast {
             self.adapter.broadcast_tx(ctx, tx).await?;
         }
 
@@ -119,7 +270,16 @@ where
     Adapter: MemPoolAdapter,
 {
     async fn insert(&self, ctx: Context, tx: SignedTransaction) -> ProtocolResult<()> {
-        self.insert_tx(ctx, tx, TxType::NewTx).await
+        let span = info_span!(
+            "mempool_insert",
+            trace_id = field::debug(ctx.trace_id()),
+            tx_hash = field::display(tx.tx_hash.as_hex())
+        );
+
+        let _timer = MEMPOOL_INSERT_SECONDS.start_timer();
+        self.insert_tx(ctx, tx, TxType::NewTx)
+            .instrument(span)
+            .await
     }
 
     async fn package(
@@ -127,18 +287,33 @@ where
         ctx: Context,
         cycles_limit: u64,
         tx_num_limit: u64,
+        max_block_size: u64,
     ) -> ProtocolResult<MixedTxHashes> {
-        let current_height = self.adapter.get_latest_height(ctx.clone()).await?;
+        let span = info_span!("mempool_package", trace_id = field::debug(ctx.trace_id()));
+
+        let current_height = self
+            .adapter
+            .get_latest_height(ctx.clone())
+            .instrument(span.clone())
+            .await?;
+        self.remove_invalid_signatures(ctx.clone())
+            .instrument(span.clone())
+            .await;
+        let _enter = span.enter();
         log::info!(
             "[core_mempool]: {:?} txs in map and {:?} txs in queue while package",
             self.tx_cache.len(),
             self.tx_cache.queue_len(),
         );
+        // Transactions are packaged for the next block
+        let halted = is_halted(self.halt_height.load(Ordering::Relaxed), current_height + 1);
         self.tx_cache.package(
             cycles_limit,
             tx_num_limit,
+            max_block_size,
             current_height,
             current_height + self.timeout_gap.load(Ordering::Relaxed),
+            halted,
         )
     }
 
@@ -150,12 +325,28 @@ where
             "[core_mempool]: flush mempool with {:?} tx_hashes",
             tx_hashes.len(),
         );
-        self.tx_cache.flush(
+        let rebroadcast_txs = self.tx_cache.flush(
             &tx_hashes,
             current_height,
             current_height + self.timeout_gap.load(Ordering::Relaxed),
+            self.rebroadcast.load(Ordering::SeqCst),
         );
         self.callback_cache.clear();
+        MEMPOOL_SIZE.set(self.tx_cache.len() as i64);
+
+        // Only hashes are announced, peers having them don't pull again
+        if !rebroadcast_txs.is_empty() {
+            log::info!(
+                "[core_mempool]: rebroadcast {:?} pending txs",
+                rebroadcast_txs.len()
+            );
+            MEMPOOL_REBROADCAST_TXS.inc_by(rebroadcast_txs.len() as i64);
+        }
+        for tx in rebroadcast_txs.into_iter() {
+            if let Err(e) = self.adapter.broadcast_tx(ctx.clone(), tx).await {
+                log::warn!("[core_mempool]: rebroadcast txs failed {}", e);
+            }
+        }
 
         Ok(())
     }
@@ -230,10 +421,57 @@ where
         Ok(())
     }
 
-    fn set_args(&self, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64) {
+    async fn pull_announced_txs(&self, ctx: Context, tx_hashes: Vec<Hash>) -> ProtocolResult<()> {
+        let unknown_hashes = self
+            .show_unknown_txs(tx_hashes)
+            .into_iter()
+            .filter(|tx_hash| self.pulling_cache.insert(tx_hash.clone(), ()).is_none())
+            .collect::<Vec<_>>();
+        if unknown_hashes.is_empty() {
+            return Ok(());
+        }
+
+        let pulled = self
+            .adapter
+            .pull_txs(ctx.clone(), unknown_hashes.clone())
+            .await;
+        let ret = match pulled {
+            Ok(txs) => {
+                for tx in txs.into_iter() {
+                    let tx_hash = tx.tx_hash.clone();
+                    if let Err(e) = self.insert_tx(ctx.clone(), tx, TxType::NewTx).await {
+                        log::warn!("[core_mempool]: insert pulled tx {:?} {}", tx_hash, e);
+                    }
+                }
+                Ok(())
+            }
+            Err(e) => Err(e),
+        };
+
+        // Remove after insertion, otherwise others may pull them again
+        self.pulling_cache.deletes(&unknown_hashes);
+        ret
+    }
+
+    fn set_args(
+        &self,
+        timeout_gap: u64,
+        cycles_limit: u64,
+        max_tx_size: u64,
+        signature_domain: Option<SignatureDomain>,
+        halt_height: u64,
+    ) {
+        {
+            let mut domain = self.sig_domain.write();
+            if *domain != signature_domain {
+                *domain = signature_domain.clone();
+                self.recheck_sigs.store(true, Ordering::SeqCst);
+            }
+        }
         self.adapter
-            .set_args(timeout_gap, cycles_limit, max_tx_size);
+            .set_args(timeout_gap, cycles_limit, max_tx_size, signature_domain);
         self.timeout_gap.store(timeout_gap, Ordering::Relaxed);
+        self.halt_height.store(halt_height, Ordering::Relaxed);
     }
 }
 
@@ -256,6 +494,18 @@ pub enum MemPoolError {
         size:        u64,
     },
 
+    #[display(
+        fmt = "Tx: {:?} payload exceeds limit of its method, now: {}, limit: {} Bytes",
+        tx_hash,
+        size,
+        limit
+    )]
+    ExceedPayloadLimit {
+        tx_hash: Hash,
+        limit:   u64,
+        size:    u64,
+    },
+
     #[display(
         fmt = "Tx: {:?} exceeds cycle limit, tx: {}, config: {}",
         tx_hash,
@@ -289,6 +539,9 @@ pub enum MemPoolError {
     #[display(fmt = "Tx: {:?} check_sig failed", tx_hash)]
     CheckSig { tx_hash: Hash },
 
+    #[display(fmt = "Tx: {:?} check_authorization failed: {}", tx_hash, reason)]
+    CheckAuthorization { tx_hash: Hash, reason: String },
+
     #[display(fmt = "Check_hash failed, expect: {:?}, get: {:?}", expect, actual)]
     CheckHash { expect: Hash, actual: Hash },
 
@@ -303,6 +556,28 @@ pub enum MemPoolError {
 
     #[display(fmt = "Tx: {:?} invalid timeout", tx_hash)]
     InvalidTimeout { tx_hash: Hash },
+
+    #[display(fmt = "Tx: {:?} expired at {}", tx_hash, valid_until)]
+    Expired { tx_hash: Hash, valid_until: u64 },
+
+    #[display(
+        fmt = "Pool size {} is invalid, it should be in 1..={}",
+        pool_size,
+        capacity
+    )]
+    InvalidPoolSize { pool_size: usize, capacity: usize },
+
+    #[display(fmt = "Tx: {:?} rejected, mempool reaches memory cap {}", tx_hash, cap)]
+    MemoryLimit { tx_hash: Hash, cap: usize },
+
+    #[display(fmt = "Tx: {:?} rejected, mempool is closed", tx_hash)]
+    Closed { tx_hash: Hash },
+
+    #[display(
+        fmt = "Tx: {:?} rejected, private transactions are not allowed",
+        tx_hash
+    )]
+    PrivateTx { tx_hash: Hash },
 }
 
 impl Error for MemPoolError {}
@@ -70,6 +70,14 @@ where
         len
     }
 
+    pub fn values(&self) -> Vec<V> {
+        let mut values = Vec::with_capacity(self.len());
+        for bucket in self.buckets.iter() {
+            values.extend(bucket.values());
+        }
+        values
+    }
+
     // TODO: concurrently clear
     pub fn clear(&self) {
         for bucket in self.buckets.iter() {
@@ -127,6 +135,10 @@ where
         self.store.read().len()
     }
 
+    fn values(&self) -> Vec<V> {
+        self.store.read().values().cloned().collect()
+    }
+
     fn clear(&self) {
         self.store.write().clear();
     }
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use test::Bencher;
 
-use protocol::types::Hash;
+use protocol::types::{Hash, PayloadLimits};
 
 use super::*;
 
@@ -40,6 +40,140 @@ fn test_insert() {
     insert!(invalid(80, 10, 80));
 }
 
+#[test]
+fn test_set_pool_size() {
+    let mempool = Arc::new(new_mempool(100, TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE));
+    assert!(mempool.set_pool_size(0).is_err());
+    assert!(mempool.set_pool_size(101).is_err());
+
+    mempool.set_pool_size(50).unwrap();
+    assert_eq!(mempool.get_pool_size(), 50);
+
+    for tx in default_mock_txs(100).iter() {
+        exec_insert(tx, Arc::clone(&mempool));
+    }
+    assert_eq!(mempool.get_tx_cache().len(), 50);
+}
+
+#[test]
+fn test_memory_cap() {
+    let mempool = Arc::new(default_mempool());
+    let txs = default_mock_txs(10);
+    exec_insert(&txs[0], Arc::clone(&mempool));
+
+    let tx_memory = mempool.get_tx_cache().memory().used();
+    mempool.set_memory_cap(tx_memory * 3);
+    for tx in txs.iter().skip(1) {
+        exec_insert(tx, Arc::clone(&mempool));
+    }
+    assert_eq!(mempool.get_tx_cache().len(), 3);
+    assert_eq!(mempool.get_tx_cache().memory().used(), tx_memory * 3);
+
+    // Memory is released once transactions are flushed
+    exec_flush(vec![txs[0].tx_hash.clone()], Arc::clone(&mempool));
+    assert_eq!(mempool.get_tx_cache().memory().used(), tx_memory * 2);
+}
+
+#[test]
+fn test_close() {
+    let mempool = Arc::new(default_mempool());
+    let txs = default_mock_txs(2);
+    exec_insert(&txs[0], Arc::clone(&mempool));
+
+    mempool.close();
+    let res = executor::block_on(mempool.insert(Context::new(), txs[1].clone()));
+    assert!(res.is_err());
+    assert_eq!(mempool.get_tx_cache().len(), 1);
+}
+
+#[test]
+fn test_private_tx() {
+    let mempool = Arc::new(default_mempool());
+    let txs = default_mock_txs(2);
+    let ctx = Context::new().with_private_tx();
+    let res = executor::block_on(mempool.insert(ctx.clone(), txs[0].clone()));
+    assert!(res.is_err());
+
+    // Private transactions are packaged but never broadcast
+    mempool.set_allow_private_txs(true);
+    executor::block_on(mempool.insert(ctx, txs[0].clone())).unwrap();
+    exec_insert(&txs[1], Arc::clone(&mempool));
+    assert!(mempool
+        .get_adapter()
+        .network_txs
+        .get(&txs[0].tx_hash)
+        .is_none());
+    assert!(mempool
+        .get_adapter()
+        .network_txs
+        .get(&txs[1].tx_hash)
+        .is_some());
+
+    let mixed_tx_hashes = exec_package(Arc::clone(&mempool), CYCLE_LIMIT, TX_NUM_LIMIT);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes.len(), 2);
+}
+
+#[test]
+fn test_remove_txs_signed_in_previous_domain() {
+    let mempool = Arc::new(default_mempool());
+    let priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let domain = SignatureDomain {
+        network_tag: "muta-test".to_owned(),
+        chain_id:    Hash::digest(Bytes::from("chain")),
+    };
+    let mock_tx = || mock_signed_tx(&priv_key, &pub_key, TIMEOUT, true);
+
+    // Before the switch, only legacy signatures are valid
+    let legacy_txs = vec![mock_tx(), mock_tx()];
+    for tx in legacy_txs.iter() {
+        exec_insert(tx, Arc::clone(&mempool));
+    }
+    let domain_tx = sign_in(Some(&domain), &priv_key, mock_tx());
+    let res = executor::block_on(mempool.insert(Context::new(), domain_tx.clone()));
+    assert!(res.is_err());
+
+    // Same domain again, nothing is checked again
+    mempool.set_args(TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE, None, 0);
+    let mixed_tx_hashes = exec_package(Arc::clone(&mempool), CYCLE_LIMIT, TX_NUM_LIMIT);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes.len(), 2);
+
+    // From the switch, legacy ones are removed before package
+    mempool.set_args(
+        TIMEOUT_GAP,
+        CYCLE_LIMIT,
+        MAX_TX_SIZE,
+        Some(domain.clone()),
+        0,
+    );
+    exec_insert(&domain_tx, Arc::clone(&mempool));
+    let mixed_tx_hashes = exec_package(Arc::clone(&mempool), CYCLE_LIMIT, TX_NUM_LIMIT);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, vec![domain_tx.tx_hash]);
+    assert_eq!(mempool.get_tx_cache().len(), 1);
+
+    let res = executor::block_on(mempool.insert(Context::new(), mock_tx()));
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_payload_limits() {
+    let mempool = Arc::new(default_mempool());
+    let txs = default_mock_txs(2);
+
+    // Payloads of mock transactions are 4 bytes
+    let mut limits = PayloadLimits::default();
+    limits.insert("test", "test", 3);
+    mempool.set_payload_limits(limits.clone());
+    let res = executor::block_on(mempool.insert(Context::new(), txs[0].clone()));
+    assert!(res.is_err());
+
+    limits.insert("test", "test", 4);
+    mempool.set_payload_limits(limits);
+    exec_insert(&txs[0], Arc::clone(&mempool));
+    exec_insert(&txs[1], Arc::clone(&mempool));
+    assert_eq!(mempool.get_tx_cache().len(), 2);
+}
+
 macro_rules! package {
     (normal($tx_num_limit: expr, $insert: expr, $expect_order: expr, $expect_propose: expr)) => {
         package!(inner(
@@ -190,6 +324,24 @@ fn test_sync_propose_txs() {
     assert_eq!(mempool.get_tx_cache().len(), 50);
 }
 
+#[test]
+fn test_pull_announced_txs() {
+    let mempool = &Arc::new(default_mempool());
+
+    let txs = &default_mock_txs(50);
+    let (exist_txs, announced_txs) = txs.split_at(20);
+    concurrent_insert(exist_txs.to_vec(), Arc::clone(mempool));
+    concurrent_broadcast(announced_txs.to_vec(), Arc::clone(mempool));
+
+    let tx_hashes: Vec<Hash> = txs.iter().map(|tx| tx.tx_hash.clone()).collect();
+    exec_pull_announced_txs(tx_hashes.clone(), Arc::clone(mempool));
+
+    assert_eq!(mempool.get_tx_cache().len(), 50);
+    assert!(tx_hashes
+        .iter()
+        .all(|tx_hash| !mempool.pulling_cache.contains_key(tx_hash)));
+}
+
 #[bench]
 fn bench_insert(b: &mut Bencher) {
     let mempool = &Arc::new(default_mempool());
@@ -8,6 +8,7 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use chashmap::CHashMap;
 use futures::executor;
+use parking_lot::RwLock;
 use rand::random;
 use rand::rngs::OsRng;
 use rayon::iter::IntoParallelRefIterator;
@@ -18,8 +19,13 @@ use common_crypto::{
     Secp256k1Signature, Signature, ToPublicKey,
 };
 use protocol::codec::ProtocolCodec;
-use protocol::traits::{Context, MemPool, MemPoolAdapter, MixedTxHashes};
-use protocol::types::{Hash, RawTransaction, SignedTransaction, TransactionRequest};
+use protocol::traits::{
+    Context, MemPool, MemPoolAdapter, MixedTxHashes, PeerMisbehavior, PrivateTxContext,
+};
+use protocol::types::{
+    signing_hash, Hash, RawTransaction, SignatureDomain, SignedTransaction, TransactionRequest,
+    TRANSACTION_SIGNATURE_PURPOSE,
+};
 use protocol::{Bytes, ProtocolResult};
 
 use crate::{HashMemPool, MemPoolError};
@@ -34,13 +40,15 @@ const TIMEOUT_GAP: u64 = 100;
 const TX_CYCLE: u64 = 1;
 
 pub struct HashMemPoolAdapter {
-    network_txs: CHashMap<Hash, SignedTransaction>,
+    network_txs:      CHashMap<Hash, SignedTransaction>,
+    signature_domain: RwLock<Option<SignatureDomain>>,
 }
 
 impl HashMemPoolAdapter {
     fn new() -> HashMemPoolAdapter {
         HashMemPoolAdapter {
-            network_txs: CHashMap::new(),
+            network_txs:      CHashMap::new(),
+            signature_domain: RwLock::new(None),
         }
     }
 }
@@ -68,7 +76,7 @@ impl MemPoolAdapter for HashMemPoolAdapter {
 
     async fn check_signature(&self, _ctx: Context, tx: SignedTransaction) -> ProtocolResult<()> {
         check_hash(tx.clone()).await?;
-        check_sig(&tx)
+        check_sig_in(self.signature_domain.read().as_ref(), &tx)
     }
 
     async fn check_transaction(&self, _ctx: Context, _tx: SignedTransaction) -> ProtocolResult<()> {
@@ -83,7 +91,17 @@ impl MemPoolAdapter for HashMemPoolAdapter {
         Ok(CURRENT_HEIGHT)
     }
 
-    fn set_args(&self, _timeout_gap: u64, _cycles_limit: u64, _max_tx_size: u64) {}
+    fn report_bad(&self, _ctx: Context, _misbehavior: PeerMisbehavior) {}
+
+    fn set_args(
+        &self,
+        _timeout_gap: u64,
+        _cycles_limit: u64,
+        _max_tx_size: u64,
+        signature_domain: Option<SignatureDomain>,
+    ) {
+        *self.signature_domain.write() = signature_domain;
+    }
 }
 
 pub fn default_mock_txs(size: usize) -> Vec<SignedTransaction> {
@@ -112,7 +130,7 @@ fn new_mempool(
 ) -> HashMemPool<HashMemPoolAdapter> {
     let adapter = HashMemPoolAdapter::new();
     let mempool = HashMemPool::new(pool_size, adapter);
-    mempool.set_args(timeout_gap, cycles_limit, max_tx_size);
+    mempool.set_args(timeout_gap, cycles_limit, max_tx_size, None, 0);
     mempool
 }
 
@@ -131,7 +149,12 @@ async fn check_hash(tx: SignedTransaction) -> ProtocolResult<()> {
 }
 
 fn check_sig(tx: &SignedTransaction) -> ProtocolResult<()> {
-    if Secp256k1::verify_signature(&tx.tx_hash.as_bytes(), &tx.signature, &tx.pubkey).is_err() {
+    check_sig_in(None, tx)
+}
+
+fn check_sig_in(domain: Option<&SignatureDomain>, tx: &SignedTransaction) -> ProtocolResult<()> {
+    let hash = signing_hash(domain, TRANSACTION_SIGNATURE_PURPOSE, &tx.tx_hash);
+    if Secp256k1::verify_signature(&hash.as_bytes(), &tx.signature, &tx.pubkey).is_err() {
         return Err(MemPoolError::CheckSig {
             tx_hash: tx.tx_hash.clone(),
         }
@@ -185,7 +208,7 @@ fn exec_package(
 ) -> MixedTxHashes {
     executor::block_on(async {
         mempool
-            .package(Context::new(), cycle_limit, tx_num_limit)
+            .package(Context::new(), cycle_limit, tx_num_limit, 0)
             .await
             .unwrap()
     })
@@ -209,6 +232,18 @@ fn exec_sync_propose_txs(require_hashes: Vec<Hash>, mempool: Arc<HashMemPool<Has
     })
 }
 
+fn exec_pull_announced_txs(
+    announced_hashes: Vec<Hash>,
+    mempool: Arc<HashMemPool<HashMemPoolAdapter>>,
+) {
+    executor::block_on(async {
+        mempool
+            .pull_announced_txs(Context::new(), announced_hashes)
+            .await
+            .unwrap();
+    })
+}
+
 fn exec_get_full_txs(
     require_hashes: Vec<Hash>,
     mempool: Arc<HashMemPool<HashMemPoolAdapter>>,
@@ -241,6 +276,8 @@ fn mock_signed_tx(
         cycles_limit: TX_CYCLE,
         cycles_price: 1,
         request,
+        sender: None,
+        valid_until_timestamp: None,
     };
 
     let raw_bytes = executor::block_on(async { raw.encode().await.unwrap() });
@@ -260,6 +297,19 @@ fn mock_signed_tx(
     }
 }
 
+// Sign the transaction again in the domain
+fn sign_in(
+    domain: Option<&SignatureDomain>,
+    priv_key: &Secp256k1PrivateKey,
+    mut tx: SignedTransaction,
+) -> SignedTransaction {
+    let hash = signing_hash(domain, TRANSACTION_SIGNATURE_PURPOSE, &tx.tx_hash);
+    tx.signature = Secp256k1::sign_message(&hash.as_bytes(), &priv_key.to_bytes())
+        .unwrap()
+        .to_bytes();
+    tx
+}
+
 fn get_random_bytes(len: usize) -> Vec<u8> {
     (0..len).map(|_| random::<u8>()).collect()
 }
@@ -1,8 +1,13 @@
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crossbeam_queue::ArrayQueue;
 
+use common_metrics::{MemoryAccount, MemoryReservation};
+
+use protocol::fixed_codec::FixedCodec;
 use protocol::traits::MixedTxHashes;
 use protocol::types::{Hash, SignedTransaction};
 use protocol::ProtocolResult;
@@ -10,6 +15,9 @@ use protocol::ProtocolResult;
 use crate::map::Map;
 use crate::MemPoolError;
 
+// Intervals of rebroadcast double up to this many times
+const MAX_REBROADCAST_BACKOFF: u64 = 6;
+
 /// Wrap `SignedTransaction` with two marks for mempool management.
 ///
 /// Each new transaction inserting into mempool will set `removed` false,
@@ -30,23 +38,49 @@ pub struct TxWrapper {
     /// While collecting propose_tx_hashes during package,
     /// it will skips transactions which marks 'proposed` true.
     proposed: AtomicBool,
+    /// Bytes of fixed encoded transaction, counted against block size
+    /// while packaging.
+    size:     u64,
+    /// Memory reserved for the transaction, released when the last
+    /// collection drops it.
+    memory:   Option<MemoryReservation>,
+
+    /// Private transactions and the ones of proposals are never rebroadcast.
+    rebroadcast:      bool,
+    /// Height to rebroadcast the transaction at, zero until a flush first
+    /// sees it.
+    next_rebroadcast: AtomicU64,
+    /// Times rebroadcast, the interval doubles each time.
+    rebroadcasts:     AtomicU64,
 }
 
 impl TxWrapper {
     #[allow(dead_code)]
     pub(crate) fn new(tx: SignedTransaction) -> Self {
+        let size = encoded_size(&tx);
         TxWrapper {
             tx,
             removed: AtomicBool::new(false),
             proposed: AtomicBool::new(false),
+            size,
+            memory: None,
+            rebroadcast: true,
+            next_rebroadcast: AtomicU64::new(0),
+            rebroadcasts: AtomicU64::new(0),
         }
     }
 
     pub(crate) fn propose(tx: SignedTransaction) -> Self {
+        let size = encoded_size(&tx);
         TxWrapper {
             tx,
             removed: AtomicBool::new(false),
             proposed: AtomicBool::new(true),
+            size,
+            memory: None,
+            rebroadcast: false,
+            next_rebroadcast: AtomicU64::new(0),
+            rebroadcasts: AtomicU64::new(0),
         }
     }
 
@@ -65,36 +99,49 @@ impl TxWrapper {
     }
 
     #[inline]
-    fn is_timeout(&self, current_height: u64, timeout: u64) -> bool {
-        let tx_timeout = self.tx.raw.timeout;
-        tx_timeout <= current_height || tx_timeout > timeout
+    fn memory_size(&self) -> usize {
+        self.size as usize + mem::size_of::<TxWrapper>()
     }
-}
 
-/// Share `TxWrapper` for collections in `TxCache`.
-pub type SharedTx = Arc<TxWrapper>;
-
-/// An enum stands for package stage
-#[derive(PartialEq, Eq)]
-enum Stage {
-    /// Packing order_tx_hashes
-    OrderTxs,
-    /// Packing propose_tx_hashes
-    ProposeTxs,
-    /// Packing finished. Only insert transactions into temp queue.
-    Finished,
-}
+    /// Transactions stay in the pool for `after` blocks before the first
+    /// rebroadcast, then the interval doubles after each one.
+    fn is_rebroadcast_due(&self, current_height: u64, after: u64) -> bool {
+        if !self.rebroadcast || after == 0 {
+            return false;
+        }
 
-impl Stage {
-    fn next(&self) -> Self {
-        match self {
-            Stage::OrderTxs => Stage::ProposeTxs,
-            Stage::ProposeTxs => Stage::Finished,
-            Stage::Finished => panic!("There is no next stage after finished stage!"),
+        let next = self.next_rebroadcast.load(Ordering::SeqCst);
+        if next == 0 {
+            self.next_rebroadcast
+                .store(current_height + after, Ordering::SeqCst);
+            return false;
         }
+        if next > current_height {
+            return false;
+        }
+
+        let times = self.rebroadcasts.fetch_add(1, Ordering::SeqCst) + 1;
+        let interval = after.saturating_mul(1 << times.min(MAX_REBROADCAST_BACKOFF));
+        self.next_rebroadcast
+            .store(current_height.saturating_add(interval), Ordering::SeqCst);
+        true
+    }
+
+    #[inline]
+    fn is_timeout(&self, current_height: u64, timeout: u64, now: u64) -> bool {
+        let tx_timeout = self.tx.raw.timeout;
+        let expired = match self.tx.raw.valid_until_timestamp {
+            Some(valid_until) => valid_until < now,
+            None => false,
+        };
+
+        expired || tx_timeout <= current_height || tx_timeout > timeout
     }
 }
 
+/// Share `TxWrapper` for collections in `TxCache`.
+pub type SharedTx = Arc<TxWrapper>;
+
 /// Queue role. Incumbent is for insertion and package.
 struct QueueRole<'a> {
     incumbent: &'a ArrayQueue<SharedTx>,
@@ -127,6 +174,8 @@ pub struct TxCache {
     /// still insert into the old queue. We use this state to make sure
     /// switch insertions *happen-before* old queue re-pop.
     concurrent_count: AtomicUsize,
+    /// Memory held by cached transactions.
+    memory:           MemoryAccount,
 }
 
 impl TxCache {
@@ -137,6 +186,7 @@ impl TxCache {
             map:              Map::new(pool_size * 2),
             is_zero:          AtomicBool::new(true),
             concurrent_count: AtomicUsize::new(0),
+            memory:           MemoryAccount::new("mempool"),
         }
     }
 
@@ -144,16 +194,38 @@ impl TxCache {
         self.map.len()
     }
 
-    pub fn insert_new_tx(&self, signed_tx: SignedTransaction) -> ProtocolResult<()> {
+    pub fn memory(&self) -> &MemoryAccount {
+        &self.memory
+    }
+
+    /// New transactions are rejected once the memory cap is reached.
+    /// Private ones are never rebroadcast.
+    pub fn insert_new_tx(&self, signed_tx: SignedTransaction, private: bool) -> ProtocolResult<()> {
         let tx_hash = signed_tx.tx_hash.clone();
-        let tx_wrapper = TxWrapper::new(signed_tx);
+        let mut tx_wrapper = TxWrapper::new(signed_tx);
+        tx_wrapper.rebroadcast = !private;
+        match self.memory.try_reserve(tx_wrapper.memory_size()) {
+            Some(memory) => tx_wrapper.memory = Some(memory),
+            None => {
+                return Err(MemPoolError::MemoryLimit {
+                    tx_hash,
+                    cap: self.memory.cap(),
+                }
+                .into())
+            }
+        }
+
         let shared_tx = Arc::new(tx_wrapper);
         self.insert(tx_hash, shared_tx)
     }
 
+    /// Transactions of proposals are always accepted, consensus can't go on
+    /// without them.
     pub fn insert_propose_tx(&self, signed_tx: SignedTransaction) -> ProtocolResult<()> {
         let tx_hash = signed_tx.tx_hash.clone();
-        let tx_wrapper = TxWrapper::propose(signed_tx);
+        let mut tx_wrapper = TxWrapper::propose(signed_tx);
+        tx_wrapper.memory = Some(self.memory.reserve(tx_wrapper.memory_size()));
+
         let shared_tx = Arc::new(tx_wrapper);
         self.insert(tx_hash, shared_tx)
     }
@@ -165,7 +237,16 @@ impl TxCache {
             .collect()
     }
 
-    pub fn flush(&self, tx_hashes: &[Hash], current_height: u64, timeout: u64) {
+    /// Remove committed and timeout transactions. Return the ones left due
+    /// for rebroadcast, which are in the pool for `rebroadcast_after` blocks,
+    /// zero disables rebroadcast.
+    pub fn flush(
+        &self,
+        tx_hashes: &[Hash],
+        current_height: u64,
+        timeout: u64,
+        rebroadcast_after: u64,
+    ) -> Vec<SignedTransaction> {
         for tx_hash in tx_hashes {
             let opt = self.map.get(tx_hash);
             if let Some(shared_tx) = opt {
@@ -174,24 +255,51 @@ impl TxCache {
         }
         // Dividing set removed and remove into two loops is to avoid lock competition.
         self.map.deletes(tx_hashes);
-        self.flush_incumbent_queue(current_height, timeout);
+        self.flush_incumbent_queue(current_height, timeout, rebroadcast_after)
     }
 
+    /// Transactions in the pool, in no particular order.
+    pub fn txs(&self) -> Vec<SignedTransaction> {
+        self.map
+            .values()
+            .into_iter()
+            .filter(|shared_tx| !shared_tx.is_removed())
+            .map(|shared_tx| shared_tx.tx.clone())
+            .collect()
+    }
+
+    /// Remove transactions no longer valid, their queue entries are skipped
+    /// by the next package or flush.
+    pub fn remove_txs(&self, tx_hashes: &[Hash]) {
+        for tx_hash in tx_hashes {
+            if let Some(shared_tx) = self.map.get(tx_hash) {
+                shared_tx.set_removed();
+            }
+        }
+        self.map.deletes(tx_hashes);
+    }
+
+    /// Package transactions for a block. Valid transactions are selected
+    /// greedily by higher cycles price first, then smaller size and cycles
+    /// limit, until `tx_num_limit`, `cycles_limit` or `max_block_size` is
+    /// reached. Zero `max_block_size` means no size limit. Transactions left
+    /// out become propose transactions for the following blocks. Only
+    /// governance transactions are packaged if the chain is `halted`, others
+    /// are kept in the pool until it's resumed.
     pub fn package(
         &self,
-        _cycles_limit: u64,
+        cycles_limit: u64,
         tx_num_limit: u64,
+        max_block_size: u64,
         current_height: u64,
         timeout: u64,
+        halted: bool,
     ) -> ProtocolResult<MixedTxHashes> {
         let queue_role = self.get_queue_role();
 
-        let mut order_tx_hashes = Vec::new();
-        let mut propose_tx_hashes = Vec::new();
+        let mut candidates = Vec::new();
         let mut timeout_tx_hashes = Vec::new();
-
-        let mut tx_count: u64 = 0;
-        let mut stage = Stage::OrderTxs;
+        let now = time_now();
 
         loop {
             if let Ok(shared_tx) = queue_role.incumbent.pop() {
@@ -200,7 +308,7 @@ impl TxCache {
                 if shared_tx.is_removed() {
                     continue;
                 }
-                if shared_tx.is_timeout(current_height, timeout) {
+                if shared_tx.is_timeout(current_height, timeout, now) {
                     timeout_tx_hashes.push(tx_hash.clone());
                     continue;
                 }
@@ -216,23 +324,10 @@ impl TxCache {
                     );
                     self.map.remove(&shared_tx.tx.tx_hash);
                 }
-
-                if stage == Stage::Finished
-                    || (stage == Stage::ProposeTxs && shared_tx.is_proposed())
-                {
+                if halted && !shared_tx.tx.raw.request.is_governance() {
                     continue;
                 }
-                tx_count += 1;
-                if tx_count > tx_num_limit {
-                    stage = stage.next();
-                    tx_count = 1;
-                }
-
-                match stage {
-                    Stage::OrderTxs => order_tx_hashes.push(tx_hash.clone()),
-                    Stage::ProposeTxs => propose_tx_hashes.push(tx_hash.clone()),
-                    Stage::Finished => {}
-                }
+                candidates.push(shared_tx);
             } else {
                 // Switch queue_roles
                 let new_role = self.switch_queue_role();
@@ -244,10 +339,12 @@ impl TxCache {
         // Remove timeout tx in map
         self.map.deletes(&timeout_tx_hashes);
 
-        Ok(MixedTxHashes {
-            order_tx_hashes,
-            propose_tx_hashes,
-        })
+        Ok(select_txs(
+            candidates,
+            cycles_limit,
+            tx_num_limit,
+            max_block_size,
+        ))
     }
 
     #[inline]
@@ -340,9 +437,16 @@ impl TxCache {
         }
     }
 
-    fn flush_incumbent_queue(&self, current_height: u64, timeout: u64) {
+    fn flush_incumbent_queue(
+        &self,
+        current_height: u64,
+        timeout: u64,
+        rebroadcast_after: u64,
+    ) -> Vec<SignedTransaction> {
         let queue_role = self.get_queue_role();
         let mut timeout_tx_hashes = Vec::new();
+        let mut rebroadcast_txs = Vec::new();
+        let now = time_now();
 
         loop {
             if let Ok(shared_tx) = queue_role.incumbent.pop() {
@@ -351,7 +455,7 @@ impl TxCache {
                 if shared_tx.is_removed() {
                     continue;
                 }
-                if shared_tx.is_timeout(current_height, timeout) {
+                if shared_tx.is_timeout(current_height, timeout, now) {
                     timeout_tx_hashes.push(tx_hash.clone());
                     continue;
                 }
@@ -366,6 +470,8 @@ impl TxCache {
                         &shared_tx.tx.tx_hash
                     );
                     self.map.remove(&shared_tx.tx.tx_hash);
+                } else if shared_tx.is_rebroadcast_due(current_height, rebroadcast_after) {
+                    rebroadcast_txs.push(shared_tx.tx.clone());
                 }
             } else {
                 // Switch queue_roles
@@ -377,6 +483,7 @@ impl TxCache {
         }
         // Remove timeout tx in map
         self.map.deletes(&timeout_tx_hashes);
+        rebroadcast_txs
     }
 
     fn switch_queue_role(&self) -> QueueRole {
@@ -398,6 +505,62 @@ impl TxCache {
     }
 }
 
+// Stable sort keeps insertion order among transactions of same price, size
+// and cycles limit.
+fn select_txs(
+    mut candidates: Vec<SharedTx>,
+    cycles_limit: u64,
+    tx_num_limit: u64,
+    max_block_size: u64,
+) -> MixedTxHashes {
+    candidates.sort_by(|a, b| {
+        b.tx.raw
+            .cycles_price
+            .cmp(&a.tx.raw.cycles_price)
+            .then(a.size.cmp(&b.size))
+            .then(a.tx.raw.cycles_limit.cmp(&b.tx.raw.cycles_limit))
+    });
+
+    let mut order_tx_hashes = Vec::new();
+    let mut propose_tx_hashes = Vec::new();
+    let mut total_cycles: u64 = 0;
+    let mut total_size: u64 = 0;
+
+    for shared_tx in candidates.iter() {
+        let cycles = total_cycles.saturating_add(shared_tx.tx.raw.cycles_limit);
+        let size = total_size.saturating_add(shared_tx.size);
+
+        if (order_tx_hashes.len() as u64) < tx_num_limit
+            && cycles <= cycles_limit
+            && (max_block_size == 0 || size <= max_block_size)
+        {
+            order_tx_hashes.push(shared_tx.tx.tx_hash.clone());
+            total_cycles = cycles;
+            total_size = size;
+        } else if !shared_tx.is_proposed() && (propose_tx_hashes.len() as u64) < tx_num_limit {
+            propose_tx_hashes.push(shared_tx.tx.tx_hash.clone());
+        }
+    }
+
+    MixedTxHashes {
+        order_tx_hashes,
+        propose_tx_hashes,
+    }
+}
+
+fn encoded_size(tx: &SignedTransaction) -> u64 {
+    tx.encode_fixed().map(|b| b.len() as u64).unwrap_or(0)
+}
+
+// Expired transactions are dropped by local time, block verification
+// checks them against block timestamp.
+fn time_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
 #[cfg(test)]
 mod tests {
     extern crate test;
@@ -410,7 +573,9 @@ mod tests {
     use rayon::prelude::*;
     use test::Bencher;
 
-    use protocol::types::{Hash, RawTransaction, SignedTransaction, TransactionRequest};
+    use protocol::types::{
+        Hash, RawTransaction, SignedTransaction, TransactionRequest, GOVERNANCE_SERVICE,
+    };
     use protocol::Bytes;
 
     use crate::map::Map;
@@ -459,6 +624,8 @@ mod tests {
             cycles_limit: TX_CYCLE,
             cycles_price: 1,
             request,
+            sender: None,
+            valid_until_timestamp: None,
         };
         SignedTransaction {
             raw,
@@ -470,7 +637,7 @@ mod tests {
 
     fn concurrent_insert(txs: Vec<SignedTransaction>, tx_cache: &TxCache) {
         txs.par_iter().for_each(|signed_tx| {
-            let _ = tx_cache.insert_new_tx(signed_tx.clone());
+            let _ = tx_cache.insert_new_tx(signed_tx.clone(), false);
         });
     }
 
@@ -482,7 +649,7 @@ mod tests {
         let tx_cache_clone = Arc::<TxCache>::clone(tx_cache);
 
         thread::spawn(move || {
-            tx_cache_clone.flush(&tx_hashes, height, height + TIMEOUT);
+            tx_cache_clone.flush(&tx_hashes, height, height + TIMEOUT, 0);
         })
     }
 
@@ -490,7 +657,7 @@ mod tests {
         let tx_cache_clone = Arc::<TxCache>::clone(tx_cache);
         thread::spawn(move || {
             tx_cache_clone
-                .package(CYCLE_LIMIT, TX_NUM_LIMIT, CURRENT_H, TIMEOUT)
+                .package(CYCLE_LIMIT, TX_NUM_LIMIT, 0, CURRENT_H, TIMEOUT, false)
                 .unwrap();
         })
     }
@@ -529,6 +696,100 @@ mod tests {
         assert!(shared_tx_1.is_removed());
     }
 
+    #[test]
+    fn test_package_drops_expired() {
+        let mut txs = gen_signed_txs(2);
+        txs[0].raw.valid_until_timestamp = Some(1);
+        txs[1].raw.valid_until_timestamp = Some(super::time_now() + 60_000);
+        let tx_cache = TxCache::new(POOL_SIZE);
+        concurrent_insert(txs.clone(), &tx_cache);
+
+        let mixed_tx_hashes = tx_cache
+            .package(CYCLE_LIMIT, TX_NUM_LIMIT, 0, CURRENT_H, TIMEOUT, false)
+            .unwrap();
+        assert_eq!(mixed_tx_hashes.order_tx_hashes, vec![txs[1]
+            .tx_hash
+            .clone()]);
+        assert!(!tx_cache.contain(&txs[0].tx_hash));
+    }
+
+    #[test]
+    fn test_package_by_block_size() {
+        let mut txs = gen_signed_txs(3);
+        txs[0].raw.cycles_price = 1;
+        txs[1].raw.cycles_price = 3;
+        txs[2].raw.cycles_price = 2;
+        let tx_size = TxWrapper::new(txs[0].clone()).size;
+        let tx_cache = TxCache::new(POOL_SIZE);
+        concurrent_insert(txs.clone(), &tx_cache);
+
+        let mixed_tx_hashes = tx_cache
+            .package(
+                CYCLE_LIMIT,
+                TX_NUM_LIMIT,
+                tx_size * 2,
+                CURRENT_H,
+                TIMEOUT,
+                false,
+            )
+            .unwrap();
+        assert_eq!(mixed_tx_hashes.order_tx_hashes, vec![
+            txs[1].tx_hash.clone(),
+            txs[2].tx_hash.clone()
+        ]);
+        assert_eq!(mixed_tx_hashes.propose_tx_hashes, vec![txs[0]
+            .tx_hash
+            .clone()]);
+    }
+
+    #[test]
+    fn test_package_while_halted() {
+        let mut txs = gen_signed_txs(2);
+        txs[1].raw.request.service_name = GOVERNANCE_SERVICE.to_owned();
+        let tx_cache = TxCache::new(POOL_SIZE);
+        concurrent_insert(txs.clone(), &tx_cache);
+
+        let mixed_tx_hashes = tx_cache
+            .package(CYCLE_LIMIT, TX_NUM_LIMIT, 0, CURRENT_H, TIMEOUT, true)
+            .unwrap();
+        assert_eq!(mixed_tx_hashes.order_tx_hashes, vec![txs[1]
+            .tx_hash
+            .clone()]);
+        assert!(mixed_tx_hashes.propose_tx_hashes.is_empty());
+
+        // Kept until the chain is resumed
+        let mixed_tx_hashes = tx_cache
+            .package(CYCLE_LIMIT, TX_NUM_LIMIT, 0, CURRENT_H, TIMEOUT, false)
+            .unwrap();
+        assert_eq!(mixed_tx_hashes.order_tx_hashes.len(), 2);
+    }
+
+    #[test]
+    fn test_rebroadcast_with_backoff() {
+        let txs = gen_signed_txs(3);
+        let tx_cache = TxCache::new(POOL_SIZE);
+        tx_cache.insert_new_tx(txs[0].clone(), false).unwrap();
+        tx_cache.insert_new_tx(txs[1].clone(), true).unwrap();
+        tx_cache.insert_propose_tx(txs[2].clone()).unwrap();
+
+        let flush = |height: u64| {
+            tx_cache
+                .flush(&[], height, height + TIMEOUT, 5)
+                .into_iter()
+                .map(|tx| tx.tx_hash)
+                .collect::<Vec<_>>()
+        };
+
+        // Scheduled by the first flush seeing them
+        assert!(flush(100).is_empty());
+        assert!(flush(104).is_empty());
+        assert_eq!(flush(105), vec![txs[0].tx_hash.clone()]);
+        assert!(flush(114).is_empty());
+        assert_eq!(flush(115), vec![txs[0].tx_hash.clone()]);
+        assert!(flush(134).is_empty());
+        assert_eq!(tx_cache.len(), 3);
+    }
+
     #[bench]
     fn bench_gen_txs(b: &mut Bencher) {
         b.iter(|| {
@@ -559,7 +820,7 @@ mod tests {
             concurrent_insert(txs.clone(), &tx_cache);
             assert_eq!(tx_cache.len(), TX_NUM);
             assert_eq!(tx_cache.queue_len(), TX_NUM);
-            tx_cache.flush(tx_hashes.as_slice(), CURRENT_H, CURRENT_H + TIMEOUT);
+            tx_cache.flush(tx_hashes.as_slice(), CURRENT_H, CURRENT_H + TIMEOUT, 0);
             assert_eq!(tx_cache.len(), 0);
             assert_eq!(tx_cache.queue_len(), 0);
         });
@@ -591,7 +852,7 @@ mod tests {
         concurrent_insert(txs, &tx_cache);
         b.iter(|| {
             let mixed_tx_hashes = tx_cache
-                .package(TX_NUM_LIMIT, CYCLE_LIMIT, CURRENT_H, TIMEOUT)
+                .package(TX_NUM_LIMIT, CYCLE_LIMIT, 0, CURRENT_H, TIMEOUT, false)
                 .unwrap();
             assert_eq!(
                 mixed_tx_hashes.order_tx_hashes.len(),
@@ -1,2 +1,137 @@
 mod snappy;
+mod zstandard;
 pub use snappy::Snappy;
+pub use zstandard::Zstd;
+
+use std::io::{self, Read};
+
+use protocol::Bytes;
+use serde_derive::Deserialize;
+
+use crate::{error::NetworkError, traits::Compression, version::Features};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionKind {
+    Snappy,
+    Zstd,
+}
+
+impl Default for CompressionKind {
+    fn default() -> Self {
+        CompressionKind::Snappy
+    }
+}
+
+// Messages are compressed by our configured kind, but decompressed by the
+// format they carry. So that every peer can choose its own compression, zstd
+// is only used for peers negotiated the zstd feature, others get snappy.
+#[derive(Clone)]
+pub struct AutoCompression {
+    kind: CompressionKind,
+}
+
+impl AutoCompression {
+    pub fn new(kind: CompressionKind) -> Self {
+        AutoCompression { kind }
+    }
+}
+
+impl Compression for AutoCompression {
+    fn compress(&self, bytes: Bytes) -> Result<Bytes, NetworkError> {
+        match self.kind {
+            CompressionKind::Snappy => Snappy.compress(bytes),
+            CompressionKind::Zstd => Zstd.compress(bytes),
+        }
+    }
+
+    fn compress_for(&self, bytes: Bytes, features: Features) -> Result<Bytes, NetworkError> {
+        match self.kind {
+            CompressionKind::Zstd if features.contains(Features::ZSTD) => Zstd.compress(bytes),
+            _ => Snappy.compress(bytes),
+        }
+    }
+
+    fn decompress(&self, bytes: Bytes, max_size: usize) -> Result<Bytes, NetworkError> {
+        if Zstd::is_compressed(bytes.as_ref()) {
+            Zstd.decompress(bytes, max_size)
+        } else {
+            Snappy.decompress(bytes, max_size)
+        }
+    }
+}
+
+// A small frame may decompress to gigabytes, stop reading once it's beyond
+// the max size.
+fn read_limited<R: Read>(reader: R, max_size: usize) -> Result<Bytes, NetworkError> {
+    let mut vec_bytes = vec![];
+    reader
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut vec_bytes)?;
+
+    if vec_bytes.len() > max_size {
+        let kind = io::ErrorKind::InvalidData;
+        let msg = format!("decompressed message exceeds {} bytes", max_size);
+
+        return Err(io::Error::new(kind, msg).into());
+    }
+
+    Ok(Bytes::from(vec_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::Bytes;
+
+    use super::{AutoCompression, CompressionKind, Zstd};
+    use crate::{traits::Compression, version::Features};
+
+    #[test]
+    fn should_decompress_message_from_peer_using_other_compression() {
+        let msg = Bytes::from(vec![7u8; 4096]);
+        let snappy = AutoCompression::new(CompressionKind::Snappy);
+        let zstd = AutoCompression::new(CompressionKind::Zstd);
+
+        let compressed = zstd.compress(msg.clone()).expect("zstd compress");
+        assert!(compressed.len() < msg.len());
+        assert_eq!(
+            snappy.decompress(compressed, 4096).expect("decompress"),
+            msg
+        );
+
+        let compressed = snappy.compress(msg.clone()).expect("snappy compress");
+        assert_eq!(zstd.decompress(compressed, 4096).expect("decompress"), msg);
+    }
+
+    #[test]
+    fn should_compress_zstd_only_for_peers_of_zstd_feature() {
+        let msg = Bytes::from(vec![7u8; 4096]);
+        let zstd = AutoCompression::new(CompressionKind::Zstd);
+
+        let compressed = zstd
+            .compress_for(msg.clone(), Features::local())
+            .expect("compress");
+        assert!(Zstd::is_compressed(compressed.as_ref()));
+
+        let compressed = zstd
+            .compress_for(msg.clone(), Features::TX_HASHES)
+            .expect("compress");
+        assert!(!Zstd::is_compressed(compressed.as_ref()));
+        assert_eq!(zstd.decompress(compressed, 4096).expect("decompress"), msg);
+    }
+
+    #[test]
+    fn should_reject_message_decompressed_beyond_max_size() {
+        let msg = Bytes::from(vec![0u8; 1024 * 1024]);
+
+        for kind in [CompressionKind::Snappy, CompressionKind::Zstd].iter() {
+            let compression = AutoCompression::new(*kind);
+            let compressed = compression.compress(msg.clone()).expect("compress");
+
+            assert!(compression
+                .decompress(compressed.clone(), msg.len())
+                .is_ok());
+            assert!(compression.decompress(compressed, msg.len() - 1).is_err());
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::io;
 
 use protocol::Bytes;
 
+use super::read_limited;
 use crate::{error::NetworkError, traits::Compression};
 
 #[derive(Clone)]
@@ -25,12 +26,9 @@ impl Compression for Snappy {
         Ok(Bytes::from(vec_bytes))
     }
 
-    fn decompress(&self, bytes: Bytes) -> Result<Bytes, NetworkError> {
-        let mut vec_bytes = vec![];
-        let mut reader = snap::Reader::new(bytes.as_ref());
+    fn decompress(&self, bytes: Bytes, max_size: usize) -> Result<Bytes, NetworkError> {
+        let reader = snap::Reader::new(bytes.as_ref());
 
-        let _ = io::copy(&mut reader, &mut vec_bytes)? as usize;
-
-        Ok(Bytes::from(vec_bytes))
+        read_limited(reader, max_size)
     }
 }
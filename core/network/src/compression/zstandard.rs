@@ -0,0 +1,31 @@
+use protocol::Bytes;
+
+use super::read_limited;
+use crate::{error::NetworkError, traits::Compression};
+
+// Frame magic number, little endian 0xFD2FB528
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Clone)]
+pub struct Zstd;
+
+impl Zstd {
+    pub fn is_compressed(bytes: &[u8]) -> bool {
+        bytes.starts_with(&ZSTD_MAGIC)
+    }
+}
+
+impl Compression for Zstd {
+    fn compress(&self, bytes: Bytes) -> Result<Bytes, NetworkError> {
+        let vec_bytes = zstd::stream::encode_all(bytes.as_ref(), ZSTD_LEVEL)?;
+
+        Ok(Bytes::from(vec_bytes))
+    }
+
+    fn decompress(&self, bytes: Bytes, max_size: usize) -> Result<Bytes, NetworkError> {
+        let reader = zstd::stream::read::Decoder::new(bytes.as_ref())?;
+
+        read_limited(reader, max_size)
+    }
+}
@@ -6,18 +6,21 @@ use std::{
     time::Duration,
 };
 
+use bytes::Bytes;
 use log::error;
 use protocol::{types::Address, ProtocolResult};
 use tentacle::{
     multiaddr::{multiaddr, Multiaddr, Protocol},
-    secio::{PublicKey, SecioKeyPair},
+    secio::{PeerId, PublicKey, SecioKeyPair},
 };
 
 use crate::{
     common::socket_to_multi_addr,
-    connection::ConnectionConfig,
+    compression::CompressionKind,
+    connection::{Bandwidth, ConnectionConfig, ThrottleConfig},
     error::NetworkError,
     peer_manager::{ArcPeer, PeerManagerConfig, SharedSessionsConfig},
+    role::NodeRole,
     selfcheck::SelfCheckConfig,
     traits::MultiaddrExt,
 };
@@ -36,6 +39,13 @@ pub const DEFAULT_BUFFER_SIZE: usize = 24 * 1024 * 1024; // same as tentacle
 pub const DEFAULT_MAX_WAIT_STREAMS: usize = 256;
 // Default write timeout
 pub const DEFAULT_WRITE_TIMEOUT: u64 = 10; // seconds
+                                           // Default slow session eviction
+pub const DEFAULT_MAX_PENDING_DATA_SIZE: usize = DEFAULT_BUFFER_SIZE;
+pub const DEFAULT_MAX_BLOCKED_DURATION: u64 = 60; // seconds
+
+// Default bytes of received messages being handled, zero means no cap
+pub const DEFAULT_MAX_INBOUND_MEMORY: usize = 0;
+pub const DEFAULT_MAX_INBOUND_CONSENSUS_MEMORY: usize = 0;
 
 // Default peer data persistent path
 pub const DEFAULT_PEER_FILE_NAME: &str = "peers";
@@ -104,9 +114,25 @@ pub struct NetworkConfig {
     pub recv_buffer_size: usize,
     pub max_wait_streams: usize,
     pub write_timeout:    u64,
+    pub compression:      CompressionKind,
+
+    // slow session eviction
+    pub max_pending_data_size: usize,
+    pub max_blocked_duration:  Duration,
+
+    // received messages over the cap are dropped, consensus and sync
+    // messages have their own cap
+    pub max_inbound_memory:           usize,
+    pub max_inbound_consensus_memory: usize,
+
+    // sent messages over bandwidth are dropped, zero is unlimited
+    pub throttle: ThrottleConfig,
 
     // peer manager
     pub bootstraps:           Vec<ArcPeer>,
+    pub private_peers:        Vec<ArcPeer>,
+    pub relay_peers:          Vec<PeerId>,
+    pub dns_seeds:            Vec<String>,
     pub whitelist:            Vec<Address>,
     pub whitelist_peers_only: bool,
     pub enable_save_restore:  bool,
@@ -114,6 +140,7 @@ pub struct NetworkConfig {
 
     // identity and encryption
     pub secio_keypair: SecioKeyPair,
+    pub node_role:     NodeRole,
 
     // protocol
     pub ping_interval:           Duration,
@@ -147,14 +174,27 @@ impl NetworkConfig {
             recv_buffer_size: DEFAULT_BUFFER_SIZE,
             max_wait_streams: DEFAULT_MAX_WAIT_STREAMS,
             write_timeout:    DEFAULT_WRITE_TIMEOUT,
+            compression:      CompressionKind::default(),
+
+            max_pending_data_size: DEFAULT_MAX_PENDING_DATA_SIZE,
+            max_blocked_duration:  Duration::from_secs(DEFAULT_MAX_BLOCKED_DURATION),
+
+            max_inbound_memory:           DEFAULT_MAX_INBOUND_MEMORY,
+            max_inbound_consensus_memory: DEFAULT_MAX_INBOUND_CONSENSUS_MEMORY,
+
+            throttle: ThrottleConfig::default(),
 
             bootstraps:           Default::default(),
+            private_peers:        Default::default(),
+            relay_peers:          Default::default(),
+            dns_seeds:            Default::default(),
             whitelist:            Default::default(),
             whitelist_peers_only: false,
             enable_save_restore:  false,
             peer_dat_file:        PathBuf::from(DEFAULT_PEER_DAT_FILE.to_owned()),
 
             secio_keypair: SecioKeyPair::secp256k1_generated(),
+            node_role:     NodeRole::default(),
 
             ping_interval:           Duration::from_secs(DEFAULT_PING_INTERVAL),
             ping_timeout:            Duration::from_secs(DEFAULT_PING_TIMEOUT),
@@ -169,6 +209,13 @@ impl NetworkConfig {
         }
     }
 
+    /// Role advertised to peers
+    pub fn node_role(mut self, role: NodeRole) -> Self {
+        self.node_role = role;
+
+        self
+    }
+
     pub fn max_connections(mut self, max: Option<usize>) -> Self {
         if let Some(max) = max {
             self.max_connections = max;
@@ -185,6 +232,14 @@ impl NetworkConfig {
         self
     }
 
+    pub fn compression(mut self, kind: Option<CompressionKind>) -> Self {
+        if let Some(kind) = kind {
+            self.compression = kind;
+        }
+
+        self
+    }
+
     pub fn send_buffer_size(mut self, size: Option<usize>) -> Self {
         if let Some(size) = size {
             self.send_buffer_size = size;
@@ -217,42 +272,116 @@ impl NetworkConfig {
         self
     }
 
+    pub fn max_pending_data_size(mut self, size: Option<usize>) -> Self {
+        if let Some(size) = size {
+            self.max_pending_data_size = size;
+        }
+
+        self
+    }
+
+    pub fn max_blocked_duration(mut self, duration: Option<u64>) -> Self {
+        if let Some(duration) = duration {
+            self.max_blocked_duration = Duration::from_secs(duration);
+        }
+
+        self
+    }
+
+    pub fn max_inbound_memory(mut self, size: Option<usize>) -> Self {
+        if let Some(size) = size {
+            self.max_inbound_memory = size;
+        }
+
+        self
+    }
+
+    pub fn max_inbound_consensus_memory(mut self, size: Option<usize>) -> Self {
+        if let Some(size) = size {
+            self.max_inbound_consensus_memory = size;
+        }
+
+        self
+    }
+
+    /// Bytes per second of consensus messages sent to all peers and to each
+    /// peer.
+    pub fn consensus_bandwidth(mut self, total: Option<u64>, per_peer: Option<u64>) -> Self {
+        self.throttle.consensus = Bandwidth {
+            total:    total.unwrap_or_default(),
+            per_peer: per_peer.unwrap_or_default(),
+        };
+
+        self
+    }
+
+    /// Bytes per second of other messages sent to all peers and to each peer,
+    /// transaction gossip, synchronization and rpc responses.
+    pub fn gossip_bandwidth(mut self, total: Option<u64>, per_peer: Option<u64>) -> Self {
+        self.throttle.gossip = Bandwidth {
+            total:    total.unwrap_or_default(),
+            per_peer: per_peer.unwrap_or_default(),
+        };
+
+        self
+    }
+
     pub fn bootstraps(
         mut self,
         pairs: Vec<(PublicKeyHexStr, PeerAddrStr)>,
     ) -> ProtocolResult<Self> {
-        let to_peer = |(pk_hex, peer_addr): (PublicKeyHexStr, PeerAddrStr)| -> _ {
-            let pk = hex::decode(pk_hex)
-                .map(PublicKey::Secp256k1)
-                .map_err(|_| NetworkError::InvalidPublicKey)?;
-            let peer_id = pk.peer_id();
-
-            let mut multiaddr = Self::parse_peer_addr(peer_addr)?;
-            let peer = ArcPeer::from_pubkey(pk).map_err(NetworkError::from)?;
-
-            if let Some(id_bytes) = multiaddr.id_bytes() {
-                if id_bytes != peer_id.as_bytes() {
-                    error!("network: pubkey doesn't match peer id in {}", multiaddr);
-                    return Ok(peer);
-                }
-            }
-            if !multiaddr.has_id() {
-                multiaddr.push_id(peer_id);
-            }
-
-            peer.multiaddrs.insert_raw(multiaddr);
-            Ok(peer)
-        };
-
         let bootstrap_peers = pairs
             .into_iter()
-            .map(to_peer)
+            .map(|(pk_hex, peer_addr)| Self::parse_peer(pk_hex, Some(peer_addr)))
             .collect::<ProtocolResult<Vec<_>>>()?;
 
         self.bootstraps = bootstrap_peers;
         Ok(self)
     }
 
+    /// Private peers are never shared through discovery, and they're always
+    /// kept connected regardless of their scores, bans and max connections.
+    /// Peers without address are accepted but never dialed, e.g. validators
+    /// behind sentries.
+    pub fn private_peers(
+        mut self,
+        pairs: Vec<(PublicKeyHexStr, Option<PeerAddrStr>)>,
+    ) -> ProtocolResult<Self> {
+        let private_peers = pairs
+            .into_iter()
+            .map(|(pk_hex, peer_addr)| Self::parse_peer(pk_hex, peer_addr))
+            .collect::<ProtocolResult<Vec<_>>>()?;
+
+        self.private_peers = private_peers;
+        Ok(self)
+    }
+
+    /// Consensus messages received from relay peers are relayed to other
+    /// peers, and the ones from other peers are relayed to relay peers. The
+    /// peers of sentries and their validators relay each other.
+    pub fn relay_peers(mut self, pk_hex_strs: Vec<PublicKeyHexStr>) -> ProtocolResult<Self> {
+        let to_peer_id = |pk_hex: PublicKeyHexStr| -> Result<PeerId, NetworkError> {
+            let pk = hex::decode(pk_hex).map_err(|_| NetworkError::InvalidPublicKey)?;
+
+            Ok(PublicKey::Secp256k1(pk).peer_id())
+        };
+
+        let relay_peers = pk_hex_strs
+            .into_iter()
+            .map(to_peer_id)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.relay_peers = relay_peers;
+        Ok(self)
+    }
+
+    /// Seed domains, their TXT records are resolved to peer multiaddrs
+    pub fn dns_seeds(mut self, seeds: Vec<String>) -> Self {
+        self.dns_seeds = seeds;
+
+        self
+    }
+
     pub fn whitelist(mut self, chain_addr_strs: Vec<String>) -> ProtocolResult<Self> {
         let chain_addrs = chain_addr_strs
             .into_iter()
@@ -263,6 +392,25 @@ impl NetworkConfig {
         Ok(self)
     }
 
+    /// Allowlist peers by their secp256k1 public keys, which they present in
+    /// secio handshake. Combine with `whitelist_peers_only` for a closed
+    /// network.
+    pub fn allowlist(mut self, pk_hex_strs: Vec<PublicKeyHexStr>) -> ProtocolResult<Self> {
+        let to_chain_addr = |pk_hex: PublicKeyHexStr| -> ProtocolResult<Address> {
+            let pk = hex::decode(pk_hex).map_err(|_| NetworkError::InvalidPublicKey)?;
+
+            Address::from_pubkey_bytes(Bytes::from(pk))
+        };
+
+        let chain_addrs = pk_hex_strs
+            .into_iter()
+            .map(to_chain_addr)
+            .collect::<ProtocolResult<Vec<_>>>()?;
+
+        self.whitelist.extend(chain_addrs);
+        Ok(self)
+    }
+
     pub fn whitelist_peers_only(mut self, flag: Option<bool>) -> Self {
         if let Some(flag) = flag {
             self.whitelist_peers_only = flag;
@@ -270,6 +418,12 @@ impl NetworkConfig {
         self
     }
 
+    pub fn enable_save_restore(mut self, flag: bool) -> Self {
+        self.enable_save_restore = flag;
+
+        self
+    }
+
     pub fn peer_dat_file<P: AsRef<Path>>(mut self, path: P) -> Self {
         let mut path = path.as_ref().to_owned();
         path.push(DEFAULT_PEER_FILE_NAME);
@@ -338,6 +492,35 @@ impl NetworkConfig {
         self
     }
 
+    fn parse_peer(
+        pk_hex: PublicKeyHexStr,
+        peer_addr: Option<PeerAddrStr>,
+    ) -> ProtocolResult<ArcPeer> {
+        let pk = hex::decode(pk_hex)
+            .map(PublicKey::Secp256k1)
+            .map_err(|_| NetworkError::InvalidPublicKey)?;
+        let peer_id = pk.peer_id();
+        let peer = ArcPeer::from_pubkey(pk).map_err(NetworkError::from)?;
+
+        let mut multiaddr = match peer_addr {
+            Some(peer_addr) => Self::parse_peer_addr(peer_addr)?,
+            None => return Ok(peer),
+        };
+
+        if let Some(id_bytes) = multiaddr.id_bytes() {
+            if id_bytes != peer_id.as_bytes() {
+                error!("network: pubkey doesn't match peer id in {}", multiaddr);
+                return Ok(peer);
+            }
+        }
+        if !multiaddr.has_id() {
+            multiaddr.push_id(peer_id);
+        }
+
+        peer.multiaddrs.insert_raw(multiaddr);
+        Ok(peer)
+    }
+
     fn parse_peer_addr(addr: PeerAddrStr) -> ProtocolResult<Multiaddr> {
         if let Ok(socket_addr) = addr.parse::<SocketAddr>() {
             Ok(socket_to_multi_addr(socket_addr))
@@ -364,6 +547,7 @@ impl From<&NetworkConfig> for ConnectionConfig {
             recv_buffer_size: Some(config.recv_buffer_size),
             max_wait_streams: Some(config.max_wait_streams),
             write_timeout:    Some(config.write_timeout),
+            throttle:         config.throttle,
         }
     }
 }
@@ -374,11 +558,15 @@ impl From<&NetworkConfig> for PeerManagerConfig {
             our_id:                   config.secio_keypair.peer_id(),
             pubkey:                   config.secio_keypair.public_key(),
             bootstraps:               config.bootstraps.clone(),
+            private_peers:            config.private_peers.clone(),
+            relay_peers:              config.relay_peers.clone(),
             whitelist_by_chain_addrs: config.whitelist.clone(),
             whitelist_peers_only:     config.whitelist_peers_only,
             max_connections:          config.max_connections,
             routine_interval:         config.peer_manager_heart_beat_interval,
             peer_dat_file:            config.peer_dat_file.clone(),
+            max_pending_data_size:    config.max_pending_data_size,
+            max_blocked_duration:     config.max_blocked_duration,
         }
     }
 }
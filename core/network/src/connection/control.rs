@@ -1,6 +1,7 @@
-use std::{io, marker::PhantomData};
+use std::{io, marker::PhantomData, sync::Arc};
 
 use async_trait::async_trait;
+use common_metrics::NETWORK_SENT_BYTES;
 use futures::channel::mpsc::UnboundedSender;
 use log::error;
 use protocol::{traits::Priority, types::Address, Bytes};
@@ -10,6 +11,7 @@ use tentacle::{
     SessionId,
 };
 
+use super::BandwidthThrottle;
 use crate::{
     error::NetworkError,
     event::PeerManagerEvent,
@@ -20,6 +22,7 @@ pub struct ConnectionServiceControl<P: NetworkProtocol, B: SessionBook> {
     inner:    ServiceControl,
     mgr_srv:  UnboundedSender<PeerManagerEvent>,
     sessions: B,
+    throttle: Arc<BandwidthThrottle>,
 
     // Indicate which protocol this connection service control
     pin_protocol: PhantomData<fn() -> P>,
@@ -30,11 +33,13 @@ impl<P: NetworkProtocol, B: SessionBook> ConnectionServiceControl<P, B> {
         control: ServiceControl,
         mgr_srv: UnboundedSender<PeerManagerEvent>,
         book: B,
+        throttle: Arc<BandwidthThrottle>,
     ) -> Self {
         ConnectionServiceControl {
             inner: control,
             mgr_srv,
             sessions: book,
+            throttle,
 
             pin_protocol: PhantomData,
         }
@@ -80,27 +85,14 @@ impl<P: NetworkProtocol, B: SessionBook> ConnectionServiceControl<P, B> {
             }
         }
     }
-}
 
-impl<P: NetworkProtocol, B: SessionBook + Clone> Clone for ConnectionServiceControl<P, B> {
-    fn clone(&self) -> Self {
-        ConnectionServiceControl {
-            inner:    self.inner.clone(),
-            mgr_srv:  self.mgr_srv.clone(),
-            sessions: self.sessions.clone(),
-
-            pin_protocol: PhantomData,
-        }
-    }
-}
-
-#[async_trait]
-impl<P, B> MessageSender for ConnectionServiceControl<P, B>
-where
-    P: NetworkProtocol,
-    B: SessionBook + Send + Sync + Unpin + 'static,
-{
-    fn send(&self, tar: TargetSession, msg: Bytes, pri: Priority) -> Result<(), NetworkError> {
+    fn send_to(
+        &self,
+        tar: TargetSession,
+        msg: Bytes,
+        pri: Priority,
+        response: bool,
+    ) -> Result<(), NetworkError> {
         let proto_id = P::message_proto_id();
 
         let (tar, opt_blocked) = match self.filter_blocked(tar) {
@@ -113,7 +105,21 @@ where
             }
             (Some(tar), opt_blocked) => (tar, opt_blocked),
         };
+        // Messages to peers over budget are dropped, they're only counted
+        // in metrics. Responses are charged instead, peers would request
+        // dropped ones again after timeout.
+        let tar = match tar {
+            TargetSession::Single(sid) if response => {
+                self.throttle.charge(sid, msg.len(), pri);
+                TargetSession::Single(sid)
+            }
+            tar => match self.filter_throttled(tar, msg.len(), pri) {
+                Some(tar) => tar,
+                None => return Err(NetworkError::Throttled),
+            },
+        };
 
+        NETWORK_SENT_BYTES.inc_by(msg.len() as i64);
         let ret = match pri {
             Priority::High => self.inner.quick_filter_broadcast(tar, proto_id, msg),
             Priority::Normal => self.inner.filter_broadcast(tar, proto_id, msg),
@@ -139,6 +145,63 @@ where
         Ok(())
     }
 
+    /// Sessions within bandwidth of the priority, None if every session is
+    /// throttled.
+    pub fn filter_throttled(
+        &self,
+        tar: TargetSession,
+        size: usize,
+        pri: Priority,
+    ) -> Option<TargetSession> {
+        if !self.throttle.is_limited(pri) {
+            return Some(tar);
+        }
+
+        let sids = match tar {
+            TargetSession::Single(sid) => vec![sid],
+            TargetSession::Multi(sids) => sids,
+            TargetSession::All => self.sessions.all_sendable(),
+        };
+        if sids.is_empty() {
+            return Some(TargetSession::Multi(sids));
+        }
+
+        let admitted = self.throttle.admit(sids, size, pri);
+        if admitted.is_empty() {
+            None
+        } else {
+            Some(TargetSession::Multi(admitted))
+        }
+    }
+}
+
+impl<P: NetworkProtocol, B: SessionBook + Clone> Clone for ConnectionServiceControl<P, B> {
+    fn clone(&self) -> Self {
+        ConnectionServiceControl {
+            inner:    self.inner.clone(),
+            mgr_srv:  self.mgr_srv.clone(),
+            sessions: self.sessions.clone(),
+            throttle: Arc::clone(&self.throttle),
+
+            pin_protocol: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<P, B> MessageSender for ConnectionServiceControl<P, B>
+where
+    P: NetworkProtocol,
+    B: SessionBook + Send + Sync + Unpin + 'static,
+{
+    fn send(&self, tar: TargetSession, msg: Bytes, pri: Priority) -> Result<(), NetworkError> {
+        self.send_to(tar, msg, pri, false)
+    }
+
+    fn respond(&self, sid: SessionId, msg: Bytes, pri: Priority) -> Result<(), NetworkError> {
+        self.send_to(TargetSession::Single(sid), msg, pri, true)
+    }
+
     async fn users_send(
         &self,
         chain_addrs: Vec<Address>,
@@ -146,7 +209,11 @@ where
         pri: Priority,
     ) -> Result<(), NetworkError> {
         let (connected, unconnected) = self.sessions.by_chain(chain_addrs.clone());
-        let send_ret = self.send(TargetSession::Multi(connected), msg, pri);
+        let send_ret = if connected.is_empty() {
+            Ok(())
+        } else {
+            self.send(TargetSession::Multi(connected), msg.clone(), pri)
+        };
 
         let whitelist_peers = PeerManagerEvent::WhitelistPeersByChainAddr { chain_addrs };
         if self.mgr_srv.unbounded_send(whitelist_peers).is_err() {
@@ -168,6 +235,13 @@ where
             .filter(|a| !unknown.contains(a))
             .collect::<Vec<_>>();
 
+        // Validators behind sentries are only reachable through relay peers
+        let relays = self.sessions.relays();
+        if !relays.is_empty() {
+            let relay_ret = self.send(TargetSession::Multi(relays), msg, pri);
+            return send_ret.and(relay_ret);
+        }
+
         if send_ret.is_err() || !unconnected.is_empty() || !unknown.is_empty() {
             let other = send_ret.err().map(NetworkError::boxed);
             let unconnected = if unconnected.is_empty() {
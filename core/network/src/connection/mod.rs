@@ -1,13 +1,16 @@
 mod control;
 mod keeper;
+mod throttle;
 pub use control::ConnectionServiceControl;
 pub use keeper::ConnectionServiceKeeper;
+pub use throttle::{Bandwidth, BandwidthThrottle, ThrottleConfig};
 
 use std::{
     collections::VecDeque,
     future::Future,
     marker::PhantomData,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
@@ -45,6 +48,9 @@ pub struct ConnectionConfig {
 
     /// Write timeout
     pub write_timeout: Option<u64>,
+
+    /// Bandwidth of messages sent to peers
+    pub throttle: ThrottleConfig,
 }
 
 pub struct ConnectionService<P: NetworkProtocol> {
@@ -54,6 +60,9 @@ pub struct ConnectionService<P: NetworkProtocol> {
     // Temporary store events for later processing under high load
     pending_events: VecDeque<ConnectionEvent>,
 
+    // Shared by controls sending messages
+    throttle: Arc<BandwidthThrottle>,
+
     // Indicate which protocol this connection service tries to open
     pin_protocol: PhantomData<P>,
 }
@@ -104,6 +113,8 @@ impl<P: NetworkProtocol> ConnectionService<P> {
             event_rx,
             pending_events: Default::default(),
 
+            throttle: Arc::new(BandwidthThrottle::new(config.throttle)),
+
             pin_protocol: PhantomData,
         }
     }
@@ -121,7 +132,12 @@ impl<P: NetworkProtocol> ConnectionService<P> {
     ) -> ConnectionServiceControl<P, B> {
         let control_ref = self.inner.control();
 
-        ConnectionServiceControl::new(control_ref.clone(), mgr_tx, book)
+        ConnectionServiceControl::new(
+            control_ref.clone(),
+            mgr_tx,
+            book,
+            Arc::clone(&self.throttle),
+        )
     }
 
     // NOTE: control.dial() and control.disconnect() both return same two
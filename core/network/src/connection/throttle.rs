@@ -0,0 +1,363 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use common_metrics::NETWORK_THROTTLED_BYTES;
+use parking_lot::Mutex;
+use protocol::traits::Priority;
+use tentacle::SessionId;
+
+// Idle buckets of peers are full, they're dropped at this interval
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Bytes per second sent to all peers and to each peer, zero is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bandwidth {
+    pub total:    u64,
+    pub per_peer: u64,
+}
+
+impl Bandwidth {
+    fn is_unlimited(&self) -> bool {
+        self.total == 0 && self.per_peer == 0
+    }
+}
+
+/// Consensus traffic is sent through tentacle quick queue, it has a budget
+/// apart from gossip, so that transactions can't use up bandwidth of votes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThrottleConfig {
+    pub consensus: Bandwidth,
+    pub gossip:    Bandwidth,
+}
+
+// Burst is one second of bandwidth. Messages are sent while there are
+// tokens left, so that ones larger than the burst still get through, the
+// debt is paid before next message.
+struct TokenBucket {
+    rate:   f64,
+    tokens: f64,
+    last:   Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, now: Instant) -> Self {
+        TokenBucket {
+            rate:   rate as f64,
+            tokens: rate as f64,
+            last:   now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+        self.last = now;
+    }
+
+    fn has_tokens(&self) -> bool {
+        self.tokens > 0.0
+    }
+
+    fn is_full(&self) -> bool {
+        self.tokens >= self.rate
+    }
+
+    fn consume(&mut self, size: usize) {
+        self.tokens -= size as f64;
+    }
+}
+
+struct Budget {
+    total:      Option<TokenBucket>,
+    per_peer:   u64,
+    peers:      HashMap<SessionId, TokenBucket>,
+    last_prune: Instant,
+}
+
+impl Budget {
+    fn new(bandwidth: Bandwidth, now: Instant) -> Self {
+        let total = if bandwidth.total == 0 {
+            None
+        } else {
+            Some(TokenBucket::new(bandwidth.total, now))
+        };
+
+        Budget {
+            total,
+            per_peer: bandwidth.per_peer,
+            peers: HashMap::new(),
+            last_prune: now,
+        }
+    }
+
+    fn take(&mut self, sid: SessionId, size: usize, now: Instant) -> bool {
+        if let Some(total) = self.total.as_mut() {
+            total.refill(now);
+            if !total.has_tokens() {
+                return false;
+            }
+        }
+
+        if self.per_peer != 0 {
+            let per_peer = self.per_peer;
+            let peer = self
+                .peers
+                .entry(sid)
+                .or_insert_with(|| TokenBucket::new(per_peer, now));
+
+            peer.refill(now);
+            if !peer.has_tokens() {
+                return false;
+            }
+            peer.consume(size);
+        }
+
+        if let Some(total) = self.total.as_mut() {
+            total.consume(size);
+        }
+        true
+    }
+
+    // Tokens are taken even if there're none left, the debt delays messages
+    // sent later
+    fn charge(&mut self, sid: SessionId, size: usize, now: Instant) {
+        if let Some(total) = self.total.as_mut() {
+            total.refill(now);
+            total.consume(size);
+        }
+
+        if self.per_peer != 0 {
+            let per_peer = self.per_peer;
+            let peer = self
+                .peers
+                .entry(sid)
+                .or_insert_with(|| TokenBucket::new(per_peer, now));
+
+            peer.refill(now);
+            peer.consume(size);
+        }
+    }
+
+    // Full buckets are the same as new ones, also of disconnected peers
+    fn prune(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.last_prune) < PRUNE_INTERVAL {
+            return;
+        }
+
+        self.peers.retain(|_, bucket| {
+            bucket.refill(now);
+            !bucket.is_full()
+        });
+        self.last_prune = now;
+    }
+}
+
+/// Token buckets of bandwidth sent to peers. Messages to peers over budget
+/// are dropped, and counted in throttled bytes metrics. Responses are charged
+/// instead, peers would request dropped ones again after timeout.
+pub struct BandwidthThrottle {
+    consensus: Option<Mutex<Budget>>,
+    gossip:    Option<Mutex<Budget>>,
+}
+
+impl BandwidthThrottle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        let now = Instant::now();
+        let budget = |bandwidth: Bandwidth| {
+            if bandwidth.is_unlimited() {
+                None
+            } else {
+                Some(Mutex::new(Budget::new(bandwidth, now)))
+            }
+        };
+
+        BandwidthThrottle {
+            consensus: budget(config.consensus),
+            gossip:    budget(config.gossip),
+        }
+    }
+
+    pub fn is_limited(&self, pri: Priority) -> bool {
+        self.budget(pri).0.is_some()
+    }
+
+    /// Sessions within budgets to send the message of size to.
+    pub fn admit(&self, sids: Vec<SessionId>, size: usize, pri: Priority) -> Vec<SessionId> {
+        self.admit_at(sids, size, pri, Instant::now())
+    }
+
+    fn admit_at(
+        &self,
+        sids: Vec<SessionId>,
+        size: usize,
+        pri: Priority,
+        now: Instant,
+    ) -> Vec<SessionId> {
+        let (budget, traffic) = match self.budget(pri) {
+            (Some(budget), traffic) => (budget, traffic),
+            (None, _) => return sids,
+        };
+        let mut budget = budget.lock();
+
+        let count = sids.len();
+        let admitted = sids
+            .into_iter()
+            .filter(|sid| budget.take(*sid, size, now))
+            .collect::<Vec<_>>();
+        budget.prune(now);
+
+        let throttled = (count - admitted.len()) * size;
+        if throttled != 0 {
+            NETWORK_THROTTLED_BYTES
+                .with_label_values(&[traffic])
+                .inc_by(throttled as i64);
+        }
+        admitted
+    }
+
+    /// Charge the message of size sent to the session, even if it's over
+    /// budget.
+    pub fn charge(&self, sid: SessionId, size: usize, pri: Priority) {
+        self.charge_at(sid, size, pri, Instant::now())
+    }
+
+    fn charge_at(&self, sid: SessionId, size: usize, pri: Priority, now: Instant) {
+        if let (Some(budget), _) = self.budget(pri) {
+            let mut budget = budget.lock();
+            budget.charge(sid, size, now);
+            budget.prune(now);
+        }
+    }
+
+    fn budget(&self, pri: Priority) -> (Option<&Mutex<Budget>>, &'static str) {
+        match pri {
+            Priority::High => (self.consensus.as_ref(), "consensus"),
+            Priority::Normal => (self.gossip.as_ref(), "gossip"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use protocol::traits::Priority;
+    use tentacle::SessionId;
+
+    use super::{Bandwidth, BandwidthThrottle, ThrottleConfig};
+
+    fn sids(ids: &[usize]) -> Vec<SessionId> {
+        ids.iter().map(|id| SessionId::new(*id)).collect()
+    }
+
+    #[test]
+    fn should_throttle_each_peer_within_its_bandwidth() {
+        let throttle = BandwidthThrottle::new(ThrottleConfig {
+            consensus: Bandwidth::default(),
+            gossip:    Bandwidth {
+                total:    0,
+                per_peer: 1000,
+            },
+        });
+        let now = Instant::now();
+
+        assert_eq!(
+            throttle.admit_at(sids(&[1, 2]), 600, Priority::Normal, now),
+            sids(&[1, 2])
+        );
+        // Debt of the large message is paid before next one
+        assert_eq!(
+            throttle.admit_at(sids(&[1]), 1600, Priority::Normal, now),
+            sids(&[1])
+        );
+        assert_eq!(
+            throttle.admit_at(sids(&[1, 2, 3]), 100, Priority::Normal, now),
+            sids(&[2, 3])
+        );
+
+        let later = now + Duration::from_secs(1);
+        assert_eq!(
+            throttle.admit_at(sids(&[1]), 100, Priority::Normal, later),
+            sids(&[])
+        );
+        let later = now + Duration::from_secs(2);
+        assert_eq!(
+            throttle.admit_at(sids(&[1]), 100, Priority::Normal, later),
+            sids(&[1])
+        );
+    }
+
+    #[test]
+    fn should_charge_responses_over_budget() {
+        let throttle = BandwidthThrottle::new(ThrottleConfig {
+            consensus: Bandwidth::default(),
+            gossip:    Bandwidth {
+                total:    3000,
+                per_peer: 1000,
+            },
+        });
+        let now = Instant::now();
+
+        assert_eq!(
+            throttle.admit_at(sids(&[1]), 600, Priority::Normal, now),
+            sids(&[1])
+        );
+        // Never dropped, the debt is paid by messages sent later
+        throttle.charge_at(SessionId::new(1), 1600, Priority::Normal, now);
+        throttle.charge_at(SessionId::new(1), 1600, Priority::Normal, now);
+        assert_eq!(
+            throttle.admit_at(sids(&[1, 2]), 100, Priority::Normal, now),
+            sids(&[])
+        );
+
+        let later = now + Duration::from_secs(2);
+        assert_eq!(
+            throttle.admit_at(sids(&[1, 2]), 100, Priority::Normal, later),
+            sids(&[2])
+        );
+        let later = now + Duration::from_secs(3);
+        assert_eq!(
+            throttle.admit_at(sids(&[1]), 100, Priority::Normal, later),
+            sids(&[1])
+        );
+    }
+
+    #[test]
+    fn should_keep_consensus_budget_apart_from_gossip() {
+        let throttle = BandwidthThrottle::new(ThrottleConfig {
+            consensus: Bandwidth {
+                total:    2000,
+                per_peer: 0,
+            },
+            gossip:    Bandwidth {
+                total:    1000,
+                per_peer: 0,
+            },
+        });
+        let now = Instant::now();
+
+        // Gossip uses up its total bandwidth
+        assert_eq!(
+            throttle.admit_at(sids(&[1, 2, 3]), 600, Priority::Normal, now),
+            sids(&[1, 2])
+        );
+        assert!(throttle
+            .admit_at(sids(&[1]), 1, Priority::Normal, now)
+            .is_empty());
+
+        assert_eq!(
+            throttle.admit_at(sids(&[1, 2, 3]), 600, Priority::High, now),
+            sids(&[1, 2, 3])
+        );
+
+        let unlimited = BandwidthThrottle::new(ThrottleConfig::default());
+        assert!(!unlimited.is_limited(Priority::High));
+        assert!(!unlimited.is_limited(Priority::Normal));
+        assert_eq!(
+            unlimited.admit_at(sids(&[1, 2]), usize::max_value(), Priority::Normal, now),
+            sids(&[1, 2])
+        );
+    }
+}
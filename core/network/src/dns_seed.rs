@@ -0,0 +1,95 @@
+use futures::channel::mpsc::UnboundedSender;
+use log::{error, info, warn};
+use tentacle::multiaddr::Multiaddr;
+use trust_dns_resolver::TokioAsyncResolver;
+
+use crate::{event::PeerManagerEvent, traits::MultiaddrExt};
+
+// Same as libp2p dnsaddr, prefix is optional
+const DNSADDR_PREFIX: &str = "dnsaddr=";
+
+// Resolve seed domains to peer multiaddrs through TXT records. Every record
+// is a multiaddr with peer id, for example:
+//  dnsaddr=/ip4/10.0.0.1/tcp/1337/p2p/QmbfhMqTTYo34mEn5xqU1Ymf5LXqXe7KR...
+pub(crate) struct DnsSeeds {
+    seeds:  Vec<String>,
+    mgr_tx: UnboundedSender<PeerManagerEvent>,
+}
+
+impl DnsSeeds {
+    pub fn new(seeds: Vec<String>, mgr_tx: UnboundedSender<PeerManagerEvent>) -> Self {
+        DnsSeeds { seeds, mgr_tx }
+    }
+
+    pub async fn resolve(self) {
+        let resolver = match TokioAsyncResolver::tokio_from_system_conf().await {
+            Ok(resolver) => resolver,
+            Err(err) => {
+                error!("network: dns seed: create resolver failed {}", err);
+                return;
+            }
+        };
+
+        for seed in self.seeds.iter() {
+            let lookup = match resolver.txt_lookup(seed.as_str()).await {
+                Ok(lookup) => lookup,
+                Err(err) => {
+                    warn!("network: dns seed: lookup {} failed {}", seed, err);
+                    continue;
+                }
+            };
+
+            let addrs = lookup
+                .iter()
+                .map(|txt| txt.txt_data().iter())
+                .flatten()
+                .filter_map(|data| parse_txt_record(data.as_ref()))
+                .collect::<Vec<_>>();
+            info!("network: dns seed: {} resolved {:?}", seed, addrs);
+
+            if addrs.is_empty() {
+                continue;
+            }
+
+            let discover = PeerManagerEvent::DiscoverMultiAddrs { addrs };
+            if self.mgr_tx.unbounded_send(discover).is_err() {
+                error!("network: dns seed: peer manager offline");
+                return;
+            }
+        }
+    }
+}
+
+// Only multiaddr with peer id is accepted, peer manager dials peer by id.
+fn parse_txt_record(data: &[u8]) -> Option<Multiaddr> {
+    let record = std::str::from_utf8(data).ok()?.trim();
+    let record = record.trim_start_matches(DNSADDR_PREFIX);
+
+    match record.parse::<Multiaddr>() {
+        Ok(ma) if ma.has_id() => Some(ma),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tentacle::secio::SecioKeyPair;
+
+    use super::parse_txt_record;
+    use crate::traits::MultiaddrExt;
+
+    #[test]
+    fn should_parse_multiaddr_with_peer_id_in_txt_record() {
+        let peer_id = SecioKeyPair::secp256k1_generated().peer_id();
+        let record = format!("dnsaddr=/ip4/10.0.0.1/tcp/1337/p2p/{}", peer_id.to_base58());
+
+        let ma = parse_txt_record(record.as_bytes()).expect("valid record");
+        assert_eq!(ma.id_bytes().unwrap().as_ref(), peer_id.as_bytes());
+
+        let record = format!("/dns4/seed.muta.dev/tcp/1337/p2p/{}", peer_id.to_base58());
+        assert!(parse_txt_record(record.as_bytes()).is_some());
+
+        assert!(parse_txt_record(b"dnsaddr=/ip4/10.0.0.1/tcp/1337").is_none());
+        assert!(parse_txt_record(b"v=spf1 -all").is_none());
+    }
+}
@@ -52,12 +52,21 @@ pub enum ErrorKind {
     #[display(fmt = "kind: rpc future dropped {:?}", _0)]
     RpcDropped(Option<ConnectedAddr>),
 
-    #[display(fmt = "kind: rpc timeout {:?}", _0)]
-    RpcTimeout(Option<ConnectedAddr>),
-
     #[display(fmt = "kind: not reactor register for {}", _0)]
     NoReactor(String),
 
+    #[display(
+        fmt = "kind: {} message size {} exceeds limit {}",
+        endpoint,
+        size,
+        limit
+    )]
+    OversizedMessage {
+        endpoint: String,
+        size:     usize,
+        limit:    usize,
+    },
+
     #[display(
         fmt = "kind: cannot create chain address from bytes {:?} {}",
         pubkey,
@@ -70,6 +79,20 @@ pub enum ErrorKind {
 
     #[display(fmt = "kind: public key {:?} not match {:?}", pubkey, id)]
     PublicKeyNotMatchId { pubkey: PublicKey, id: PeerId },
+
+    #[display(
+        fmt = "kind: session {} of protocol v{} doesn't support {}, requires v{}",
+        sid,
+        version,
+        endpoint,
+        required
+    )]
+    UnsupportedMessage {
+        sid:      SessionId,
+        version:  u32,
+        endpoint: String,
+        required: u32,
+    },
 }
 
 impl Error for ErrorKind {}
@@ -88,6 +111,9 @@ pub enum NetworkError {
     #[display(fmt = "temporary unavailable, try again later")]
     Busy,
 
+    #[display(fmt = "bandwidth to peers is used up")]
+    Throttled,
+
     #[display(fmt = "send incompletely, blocked {:?}, other {:?}", blocked, other)]
     Send {
         blocked: Option<Vec<SessionId>>,
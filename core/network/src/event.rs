@@ -1,7 +1,10 @@
 use std::{error::Error, sync::Arc};
 
 use derive_more::Display;
-use protocol::types::Address;
+use protocol::{
+    traits::PeerMisbehavior,
+    types::{Address, Hash},
+};
 #[cfg(not(test))]
 use tentacle::context::SessionContext;
 use tentacle::{
@@ -13,6 +16,7 @@ use tentacle::{
 
 #[cfg(test)]
 use crate::test::mock::SessionContext;
+use crate::{role::NodeRole, version::PeerProtocol};
 
 #[derive(Debug, Display)]
 pub enum ConnectionEvent {
@@ -80,6 +84,32 @@ pub enum MisbehaviorKind {
     // Maybe message codec or nonce incorrect
     #[display(fmt = "ping unexpect")]
     PingUnexpect,
+
+    #[display(fmt = "undecodable message")]
+    UndecodableMessage,
+
+    #[display(fmt = "invalid block")]
+    InvalidBlock,
+
+    #[display(fmt = "invalid vote extension")]
+    InvalidVoteExtension,
+
+    #[display(fmt = "duplicate transaction {:?}", _0)]
+    DuplicateTx(Hash),
+
+    #[display(fmt = "oversized message")]
+    OversizedMessage,
+}
+
+impl From<PeerMisbehavior> for MisbehaviorKind {
+    fn from(misbehavior: PeerMisbehavior) -> MisbehaviorKind {
+        match misbehavior {
+            PeerMisbehavior::UndecodableMessage => MisbehaviorKind::UndecodableMessage,
+            PeerMisbehavior::InvalidBlock => MisbehaviorKind::InvalidBlock,
+            PeerMisbehavior::InvalidVoteExtension => MisbehaviorKind::InvalidVoteExtension,
+            PeerMisbehavior::DuplicateTx(tx_hash) => MisbehaviorKind::DuplicateTx(tx_hash),
+        }
+    }
 }
 
 #[derive(Debug, Display, PartialEq, Eq)]
@@ -147,6 +177,13 @@ pub enum PeerManagerEvent {
     #[display(fmt = "whitelist peers by chain addresses {:?}", chain_addrs)]
     WhitelistPeersByChainAddr { chain_addrs: Vec<Address> },
 
+    // Allowlist, never expired whitelist peers managed by admin
+    #[display(fmt = "allow peer by chain address {:?}", chain_addr)]
+    AllowPeer { chain_addr: Address },
+
+    #[display(fmt = "disallow peer by chain address {:?}", chain_addr)]
+    DisallowPeer { chain_addr: Address },
+
     // Address
     #[display(fmt = "discover multi addrs {:?}", addrs)]
     DiscoverMultiAddrs { addrs: Vec<Multiaddr> },
@@ -157,10 +194,23 @@ pub enum PeerManagerEvent {
         addrs: Vec<Multiaddr>,
     },
 
+    #[display(fmt = "identify pid {:?} role {}", pid, role)]
+    IdentifiedRole { pid: PeerId, role: NodeRole },
+
+    #[display(fmt = "identify pid {:?} protocol {}", pid, protocol)]
+    IdentifiedProtocol {
+        pid:      PeerId,
+        protocol: PeerProtocol,
+    },
+
     // Self
     #[display(fmt = "add listen addr {}", addr)]
     AddNewListenAddr { addr: Multiaddr },
 
     #[display(fmt = "rmeove listen addr {}", addr)]
     RemoveListenAddr { addr: Multiaddr },
+
+    // Config
+    #[display(fmt = "set max connections {}", max)]
+    SetMaxConnections { max: usize },
 }
@@ -0,0 +1,25 @@
+//! Entry of the fuzz targets in `core/network/fuzz`, messages from peers are
+//! decoded the same way as the router does.
+
+use protocol::Bytes;
+
+use crate::compression::{AutoCompression, CompressionKind};
+use crate::config::DEFAULT_MAX_FRAME_LENGTH;
+use crate::endpoint::Endpoint;
+use crate::message::NetworkMessage;
+use crate::traits::Compression;
+
+pub fn decode_message(data: &[u8]) {
+    let compression = AutoCompression::new(CompressionKind::default());
+
+    let bytes = match compression.decompress(Bytes::from(data.to_vec()), DEFAULT_MAX_FRAME_LENGTH) {
+        Ok(bytes) => bytes,
+        Err(_) => return,
+    };
+    let msg = match futures::executor::block_on(NetworkMessage::decode(bytes)) {
+        Ok(msg) => msg,
+        Err(_) => return,
+    };
+    let _ = msg.url.parse::<Endpoint>();
+    let _ = msg.trace_id();
+}
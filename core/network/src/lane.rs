@@ -0,0 +1,99 @@
+use common_metrics::{NETWORK_LANE_RECEIVED_BYTES, NETWORK_LANE_SENT_BYTES};
+use derive_more::Display;
+use protocol::traits::Priority;
+
+use crate::endpoint::{Endpoint, EndpointScheme};
+
+const CONSENSUS_NAMESPACE: &str = "consensus";
+const MEMPOOL_NAMESPACE: &str = "mempool";
+
+/// Traffic lanes share one connection. Lane of consensus messages is always
+/// sent through tentacle quick queue, so that proposals, votes and QCs are
+/// flushed ahead of transaction gossip queued before them.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    // Proposals, votes, QCs, chokes and heights
+    #[display(fmt = "consensus")]
+    Consensus,
+
+    // Blocks and transactions pulled by synchronization
+    #[display(fmt = "sync")]
+    Sync,
+
+    // Transaction gossip
+    #[display(fmt = "transaction")]
+    Transaction,
+
+    #[display(fmt = "other")]
+    Other,
+}
+
+impl Lane {
+    pub fn of(endpoint: &Endpoint) -> Self {
+        let namespace = endpoint
+            .full_url()
+            .split('/')
+            .filter(|c| !c.is_empty())
+            .nth(1);
+
+        match (endpoint.scheme(), namespace) {
+            (EndpointScheme::Gossip, Some(CONSENSUS_NAMESPACE)) => Lane::Consensus,
+            (_, Some(CONSENSUS_NAMESPACE)) => Lane::Sync,
+            (EndpointScheme::Gossip, Some(MEMPOOL_NAMESPACE)) => Lane::Transaction,
+            _ => Lane::Other,
+        }
+    }
+
+    // Blocks pulled by synchronization are large, they must not delay votes.
+    // Transactions pulled by consensus keep their requested priority.
+    pub fn priority(self, requested: Priority) -> Priority {
+        match self {
+            Lane::Consensus => Priority::High,
+            Lane::Sync | Lane::Transaction => Priority::Normal,
+            Lane::Other => requested,
+        }
+    }
+
+    pub fn record_sent(self, size: usize) {
+        NETWORK_LANE_SENT_BYTES
+            .with_label_values(&[&self.to_string()])
+            .inc_by(size as i64);
+    }
+
+    pub fn record_received(self, size: usize) {
+        NETWORK_LANE_RECEIVED_BYTES
+            .with_label_values(&[&self.to_string()])
+            .inc_by(size as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::traits::Priority;
+
+    use super::Lane;
+    use crate::endpoint::Endpoint;
+
+    fn lane(end: &str) -> Lane {
+        Lane::of(&end.parse::<Endpoint>().expect("endpoint"))
+    }
+
+    #[test]
+    fn should_put_consensus_gossip_ahead_of_transaction_gossip() {
+        assert_eq!(lane("/gossip/consensus/signed_vote"), Lane::Consensus);
+        assert_eq!(lane("/gossip/consensus/qc"), Lane::Consensus);
+        assert_eq!(lane("/rpc_call/consensus/sync_pull_block"), Lane::Sync);
+        assert_eq!(lane("/rpc_resp/consensus/sync_pull_block/7"), Lane::Sync);
+        assert_eq!(lane("/gossip/mempool/new_tx_hashes"), Lane::Transaction);
+        assert_eq!(lane("/rpc_call/mempool/pull_txs"), Lane::Other);
+
+        match Lane::Consensus.priority(Priority::Normal) {
+            Priority::High => (),
+            Priority::Normal => panic!("consensus lane should be high priority"),
+        }
+        match Lane::Transaction.priority(Priority::High) {
+            Priority::Normal => (),
+            Priority::High => panic!("transaction lane should be normal priority"),
+        }
+    }
+}
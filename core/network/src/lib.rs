@@ -2,14 +2,20 @@ mod common;
 mod compression;
 mod config;
 mod connection;
+mod dns_seed;
 mod endpoint;
 mod error;
 mod event;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
+mod lane;
 mod message;
 mod outbound;
 mod peer_manager;
 mod protocols;
 mod reactor;
+mod relay;
+mod role;
 mod rpc;
 mod rpc_map;
 mod selfcheck;
@@ -17,7 +23,10 @@ mod service;
 #[cfg(test)]
 mod test;
 mod traits;
+mod version;
 
-pub use config::NetworkConfig;
+pub use compression::CompressionKind;
+pub use config::{NetworkConfig, DEFAULT_MAX_CONNECTIONS};
 pub use message::{serde, serde_multi};
+pub use role::NodeRole;
 pub use service::{NetworkService, NetworkServiceHandle};
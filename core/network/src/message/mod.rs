@@ -1,8 +1,10 @@
 pub mod serde;
 pub mod serde_multi;
 
+use common_metrics::MemoryReservation;
 use derive_more::Constructor;
 use prost::Message;
+use protocol::traits::TraceId;
 use protocol::Bytes;
 use tentacle::{secio::PeerId, SessionId};
 
@@ -27,16 +29,24 @@ pub struct NetworkMessage {
 
     #[prost(bytes, tag = "2")]
     pub content: Vec<u8>,
+
+    #[prost(uint64, optional, tag = "3")]
+    pub trace_id: Option<u64>,
 }
 
 impl NetworkMessage {
-    pub fn new(endpoint: Endpoint, content: Bytes) -> Self {
+    pub fn new(endpoint: Endpoint, content: Bytes, trace_id: Option<TraceId>) -> Self {
         NetworkMessage {
-            url:     endpoint.full_url().to_owned(),
-            content: content.to_vec(),
+            url:      endpoint.full_url().to_owned(),
+            content:  content.to_vec(),
+            trace_id: trace_id.map(TraceId::as_u64),
         }
     }
 
+    pub fn trace_id(&self) -> Option<TraceId> {
+        self.trace_id.map(TraceId::from_u64)
+    }
+
     pub async fn encode(self) -> Result<Bytes, NetworkError> {
         let mut buf = Vec::with_capacity(self.encoded_len());
 
@@ -58,14 +68,20 @@ pub struct SessionMessage {
     pub(crate) pid:            PeerId,
     pub(crate) msg:            NetworkMessage,
     pub(crate) connected_addr: Option<ConnectedAddr>,
+    // Memory held by the message till it's handled
+    pub(crate) memory:         MemoryReservation,
 }
 
 #[cfg(test)]
 mod tests {
-    use protocol::{types::Hash, Bytes};
+    use prost::Message;
+    use protocol::{traits::TraceId, types::Hash, Bytes};
     use quickcheck_macros::quickcheck;
     use serde_derive::{Deserialize, Serialize};
 
+    use super::NetworkMessage;
+    use crate::endpoint::Endpoint;
+
     #[derive(Debug, Serialize, Deserialize)]
     struct Hashes {
         #[serde(with = "super::serde_multi")]
@@ -109,4 +125,49 @@ mod tests {
 
         bincode::deserialize::<Hashes>(&bincode::serialize(&hashes).unwrap()).is_ok()
     }
+
+    #[tokio::test]
+    async fn should_keep_trace_id_in_network_message() {
+        let endpoint = "/gossip/test/trace".parse::<Endpoint>().unwrap();
+        let trace_id = TraceId::new();
+
+        let msg = NetworkMessage::new(endpoint, Bytes::from("trace"), Some(trace_id));
+        let decoded = NetworkMessage::decode(msg.encode().await.unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(decoded.trace_id(), Some(trace_id));
+    }
+
+    // Wire format of peers before trace id
+    #[derive(Message)]
+    struct LegacyNetworkMessage {
+        #[prost(string, tag = "1")]
+        url: String,
+
+        #[prost(bytes, tag = "2")]
+        content: Vec<u8>,
+    }
+
+    #[tokio::test]
+    async fn should_decode_network_message_of_legacy_peers() {
+        let legacy = LegacyNetworkMessage {
+            url:     "/gossip/test/legacy".to_owned(),
+            content: b"legacy".to_vec(),
+        };
+        let mut buf = Vec::with_capacity(legacy.encoded_len());
+        legacy.encode(&mut buf).unwrap();
+
+        let decoded = NetworkMessage::decode(Bytes::from(buf)).await.unwrap();
+        assert_eq!(decoded.url, legacy.url);
+        assert_eq!(decoded.content, legacy.content);
+        assert_eq!(decoded.trace_id(), None);
+
+        // Legacy peers skip the unknown trace id
+        let endpoint = "/gossip/test/legacy".parse::<Endpoint>().unwrap();
+        let msg = NetworkMessage::new(endpoint, Bytes::from("legacy"), Some(TraceId::new()));
+        let decoded = LegacyNetworkMessage::decode(msg.encode().await.unwrap()).unwrap();
+        assert_eq!(decoded.url, legacy.url);
+        assert_eq!(decoded.content, legacy.content);
+    }
 }
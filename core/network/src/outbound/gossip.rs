@@ -1,51 +1,86 @@
 use async_trait::async_trait;
 use protocol::{
-    traits::{Context, Gossip, MessageCodec, Priority},
+    traits::{Context, Gossip, MessageCodec, Priority, TraceContext},
     types::Address,
     Bytes, ProtocolResult,
 };
-use tentacle::service::TargetSession;
+use tentacle::{service::TargetSession, SessionId};
 
 use crate::{
     endpoint::Endpoint,
     error::NetworkError,
+    lane::Lane,
     message::NetworkMessage,
-    traits::{Compression, MessageSender},
+    traits::{Compression, MessageSender, SessionBook},
+    version::{is_gated_broadcast, Features},
 };
 
 #[derive(Clone)]
-pub struct NetworkGossip<S, C> {
+pub struct NetworkGossip<S, C, B> {
     sender:      S,
     compression: C,
+    // Skip peers of versions not understanding the message, and compress by
+    // features common to the receivers
+    sessions:    B,
 }
 
-impl<S, C> NetworkGossip<S, C>
+impl<S, C, B> NetworkGossip<S, C, B>
 where
     S: MessageSender + Sync + Send + Clone,
     C: Compression + Sync + Send + Clone,
+    B: SessionBook + Sync + Send + Clone,
 {
-    pub fn new(sender: S, compression: C) -> Self {
+    pub fn new(sender: S, compression: C, sessions: B) -> Self {
         NetworkGossip {
             sender,
             compression,
+            sessions,
         }
     }
 
+    // None if every peer receives the message
+    fn supported_sessions(
+        &self,
+        endpoint: &Endpoint,
+        sids: Vec<SessionId>,
+    ) -> Option<Vec<SessionId>> {
+        if !is_gated_broadcast(endpoint) {
+            return None;
+        }
+
+        let supported = sids
+            .into_iter()
+            .filter(|sid| self.sessions.protocol(*sid).receives_broadcast(endpoint))
+            .collect();
+        Some(supported)
+    }
+
+    fn common_features(&self, sids: &[SessionId]) -> Features {
+        sids.iter().fold(Features::local(), |features, sid| {
+            features & self.sessions.protocol(*sid).features
+        })
+    }
+
     async fn package_message<M>(
         &self,
-        _ctx: Context,
-        end: &str,
+        ctx: Context,
+        endpoint: &Endpoint,
         mut msg: M,
-    ) -> ProtocolResult<Bytes>
+        features: Features,
+    ) -> ProtocolResult<(Lane, Bytes)>
     where
         M: MessageCodec,
     {
-        let endpoint = end.parse::<Endpoint>()?;
+        let endpoint = endpoint.to_owned();
+        let lane = Lane::of(&endpoint);
         let data = msg.encode().await?;
-        let net_msg = NetworkMessage::new(endpoint, data).encode().await?;
-        let msg = self.compression.compress(net_msg)?;
+        let net_msg = NetworkMessage::new(endpoint, data, ctx.trace_id())
+            .encode()
+            .await?;
+        let msg = self.compression.compress_for(net_msg, features)?;
 
-        Ok(msg)
+        lane.record_sent(msg.len());
+        Ok((lane, msg))
     }
 
     fn send(
@@ -70,17 +105,31 @@ where
 }
 
 #[async_trait]
-impl<S, C> Gossip for NetworkGossip<S, C>
+impl<S, C, B> Gossip for NetworkGossip<S, C, B>
 where
     S: MessageSender + Sync + Send + Clone,
     C: Compression + Sync + Send + Clone,
+    B: SessionBook + Sync + Send + Clone,
 {
     async fn broadcast<M>(&self, cx: Context, end: &str, msg: M, p: Priority) -> ProtocolResult<()>
     where
         M: MessageCodec,
     {
-        let msg = self.package_message(cx.clone(), end, msg).await?;
-        self.send(cx, TargetSession::All, msg, p)?;
+        let endpoint = end.parse::<Endpoint>()?;
+        let all = self.sessions.all();
+        let (target, features) = match self.supported_sessions(&endpoint, all.clone()) {
+            None => (TargetSession::All, self.common_features(&all)),
+            Some(sids) if sids.is_empty() => return Ok(()),
+            Some(sids) => {
+                let features = self.common_features(&sids);
+                (TargetSession::Multi(sids), features)
+            }
+        };
+
+        let (lane, msg) = self
+            .package_message(cx.clone(), &endpoint, msg, features)
+            .await?;
+        self.send(cx, target, msg, lane.priority(p))?;
 
         Ok(())
     }
@@ -96,9 +145,197 @@ where
     where
         M: MessageCodec,
     {
-        let msg = self.package_message(cx.clone(), end, msg).await?;
-        self.users_send(cx, users, msg, p).await?;
+        let endpoint = end.parse::<Endpoint>()?;
+        // Users of gated messages must be connected, versions are unknown
+        // before then
+        let (connected, _) = self.sessions.by_chain(users.clone());
+        let (target, features) = if !is_gated_broadcast(&endpoint) {
+            // Users not connected may be reached through relays
+            let mut sids = connected;
+            sids.extend(self.sessions.relays());
+            (None, self.common_features(&sids))
+        } else {
+            let sids = self
+                .supported_sessions(&endpoint, connected)
+                .unwrap_or_default();
+            if sids.is_empty() {
+                return Ok(());
+            }
+
+            let features = self.common_features(&sids);
+            (Some(TargetSession::Multi(sids)), features)
+        };
+
+        let (lane, msg) = self
+            .package_message(cx.clone(), &endpoint, msg, features)
+            .await?;
+        match target {
+            None => self.users_send(cx, users, msg, lane.priority(p)).await?,
+            Some(target) => self.send(cx, target, msg, lane.priority(p))?,
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use parking_lot::Mutex;
+    use protocol::{
+        traits::{Context, Gossip, Priority},
+        types::Address,
+        Bytes,
+    };
+    use tentacle::{secio::PeerId, service::TargetSession, SessionId};
+
+    use crate::{
+        common::ConnectedAddr,
+        compression::{AutoCompression, CompressionKind, Zstd},
+        error::NetworkError,
+        traits::{MessageSender, SessionBook},
+        version::{Features, PeerProtocol},
+    };
+
+    use super::NetworkGossip;
+
+    const END_NEW_TXS: &str = "/gossip/mempool/new_txs";
+    const END_NEW_TX_HASHES: &str = "/gossip/mempool/new_tx_hashes";
+
+    #[derive(Clone, Default)]
+    struct MockSender {
+        sent: Arc<Mutex<Vec<(Vec<SessionId>, bool)>>>,
+    }
+
+    #[async_trait]
+    impl MessageSender for MockSender {
+        fn send(&self, tar: TargetSession, msg: Bytes, _: Priority) -> Result<(), NetworkError> {
+            let sids = match tar {
+                TargetSession::Multi(sids) => sids,
+                _ => panic!("gated gossip should send to listed sessions"),
+            };
+            self.sent
+                .lock()
+                .push((sids, Zstd::is_compressed(msg.as_ref())));
+            Ok(())
+        }
+
+        async fn users_send(
+            &self,
+            _: Vec<Address>,
+            _: Bytes,
+            _: Priority,
+        ) -> Result<(), NetworkError> {
+            unimplemented!()
+        }
+    }
+
+    // Session ids index the protocols of peers
+    #[derive(Clone)]
+    struct MockSessions(Vec<PeerProtocol>);
+
+    impl SessionBook for MockSessions {
+        fn all_sendable(&self) -> Vec<SessionId> {
+            self.all()
+        }
+
+        fn all_blocked(&self) -> Vec<SessionId> {
+            vec![]
+        }
+
+        fn refresh_blocked(&self) {}
+
+        fn by_chain(&self, addrs: Vec<Address>) -> (Vec<SessionId>, Vec<Address>) {
+            (vec![], addrs)
+        }
+
+        fn peers_by_chain(&self, addrs: Vec<Address>) -> (Vec<PeerId>, Vec<Address>) {
+            (vec![], addrs)
+        }
+
+        fn all(&self) -> Vec<SessionId> {
+            (0..self.0.len()).map(SessionId::new).collect()
+        }
+
+        fn connected_addr(&self, _: SessionId) -> Option<ConnectedAddr> {
+            None
+        }
+
+        fn pending_data_size(&self, _: SessionId) -> usize {
+            0
+        }
+
+        fn whitelist(&self) -> Vec<Address> {
+            vec![]
+        }
+
+        fn relays(&self) -> Vec<SessionId> {
+            vec![]
+        }
+
+        fn protocol(&self, sid: SessionId) -> PeerProtocol {
+            self.0[sid.value()]
+        }
+    }
+
+    fn protocol(version: u32, features: Features) -> PeerProtocol {
+        PeerProtocol { version, features }
+    }
+
+    fn gossip(
+        protocols: Vec<PeerProtocol>,
+    ) -> (
+        NetworkGossip<MockSender, AutoCompression, MockSessions>,
+        MockSender,
+    ) {
+        let sender = MockSender::default();
+        let compression = AutoCompression::new(CompressionKind::Zstd);
+        let gossip = NetworkGossip::new(sender.clone(), compression, MockSessions(protocols));
+
+        (gossip, sender)
+    }
+
+    async fn broadcast(
+        gossip: &NetworkGossip<MockSender, AutoCompression, MockSessions>,
+        end: &str,
+    ) {
+        gossip
+            .broadcast(Context::new(), end, "txs".to_owned(), Priority::Normal)
+            .await
+            .expect("broadcast");
+    }
+
+    // Peers of every version stay connected, each gets the messages it
+    // understands, compressed by a format it's able to decompress
+    #[tokio::test]
+    async fn should_gossip_to_peers_of_mixed_versions() {
+        let legacy = PeerProtocol::default();
+        let no_zstd = protocol(1, Features::TX_HASHES);
+        let no_hashes = protocol(2, Features::ZSTD);
+        let local = PeerProtocol::local();
+        let (gossip, sender) = gossip(vec![legacy, no_zstd, no_hashes, local]);
+
+        broadcast(&gossip, END_NEW_TXS).await;
+        broadcast(&gossip, END_NEW_TX_HASHES).await;
+
+        let sent = sender.sent.lock().clone();
+        let sids = |ids: &[usize]| ids.iter().cloned().map(SessionId::new).collect::<Vec<_>>();
+        assert_eq!(sent, vec![(sids(&[0, 2]), false), (sids(&[1, 3]), false)]);
+    }
+
+    #[tokio::test]
+    async fn should_compress_zstd_if_every_receiver_has_feature() {
+        let (gossip, sender) = gossip(vec![protocol(2, Features::ZSTD), PeerProtocol::local()]);
+
+        broadcast(&gossip, END_NEW_TXS).await;
+        broadcast(&gossip, END_NEW_TX_HASHES).await;
+
+        let sent = sender.sent.lock().clone();
+        assert_eq!(sent, vec![
+            (vec![SessionId::new(0)], true),
+            (vec![SessionId::new(1)], true)
+        ]);
+    }
+}
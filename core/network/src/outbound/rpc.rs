@@ -1,71 +1,109 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use futures::future::{self, Either};
 use futures_timer::Delay;
+use log::debug;
 use protocol::{
-    traits::{Context, MessageCodec, Priority, Rpc},
-    Bytes, ProtocolResult,
+    traits::{Context, MessageCodec, Priority, Rpc, RpcContext, RpcTimeout, TraceContext},
+    Bytes, ProtocolError, ProtocolResult,
 };
+use rand::seq::IteratorRandom;
 use tentacle::{service::TargetSession, SessionId};
 
 use crate::{
     config::TimeoutConfig,
     endpoint::Endpoint,
     error::{ErrorKind, NetworkError},
+    lane::Lane,
     message::NetworkMessage,
     rpc::{RpcErrorMessage, RpcResponse, RpcResponseCode},
     rpc_map::RpcMap,
-    traits::{Compression, MessageSender, NetworkContext},
+    traits::{Compression, MessageSender, NetworkContext, SessionBook},
+    version::message_version,
 };
 
 #[derive(Clone)]
-pub struct NetworkRpc<S, C> {
+pub struct NetworkRpc<S, C, B> {
     sender:      S,
     compression: C,
     map:         Arc<RpcMap>,
+    // Pick alternate peers to retry
+    sessions:    B,
 
     timeout: TimeoutConfig,
 }
 
-impl<S, C> NetworkRpc<S, C>
+enum Attempt {
+    Responded(RpcResponse),
+    Dropped,
+    TimedOut,
+}
+
+impl<S, C, B> NetworkRpc<S, C, B>
 where
     S: MessageSender + Sync + Clone,
     C: Compression + Sync + Clone,
+    B: SessionBook + Sync + Clone,
 {
-    pub fn new(sender: S, compression: C, map: Arc<RpcMap>, timeout: TimeoutConfig) -> Self {
+    pub fn new(
+        sender: S,
+        compression: C,
+        map: Arc<RpcMap>,
+        sessions: B,
+        timeout: TimeoutConfig,
+    ) -> Self {
         NetworkRpc {
             sender,
             compression,
             map,
+            sessions,
 
             timeout,
         }
     }
 
-    fn send(&self, _: Context, s: SessionId, msg: Bytes, p: Priority) -> Result<(), NetworkError> {
-        let compressed_msg = self.compression.compress(msg)?;
+    fn send(
+        &self,
+        _: Context,
+        s: SessionId,
+        lane: Lane,
+        msg: Bytes,
+        p: Priority,
+    ) -> Result<(), NetworkError> {
+        let features = self.sessions.protocol(s).features;
+        let compressed_msg = self.compression.compress_for(msg, features)?;
         let target = TargetSession::Single(s);
 
-        self.sender.send(target, compressed_msg, p)
+        lane.record_sent(compressed_msg.len());
+        self.sender.send(target, compressed_msg, lane.priority(p))
     }
-}
 
-#[async_trait]
-impl<S, C> Rpc for NetworkRpc<S, C>
-where
-    S: MessageSender + Send + Sync + Clone,
-    C: Compression + Send + Sync + Clone,
-{
-    async fn call<M, R>(&self, cx: Context, end: &str, mut msg: M, p: Priority) -> ProtocolResult<R>
-    where
-        M: MessageCodec,
-        R: MessageCodec,
-    {
-        let endpoint = end.parse::<Endpoint>()?;
-        let sid = cx.session_id()?;
+    fn respond(
+        &self,
+        _: Context,
+        s: SessionId,
+        lane: Lane,
+        msg: Bytes,
+        p: Priority,
+    ) -> Result<(), NetworkError> {
+        let features = self.sessions.protocol(s).features;
+        let compressed_msg = self.compression.compress_for(msg, features)?;
+
+        lane.record_sent(compressed_msg.len());
+        self.sender.respond(s, compressed_msg, lane.priority(p))
+    }
+
+    async fn attempt(
+        &self,
+        cx: Context,
+        endpoint: &Endpoint,
+        sid: SessionId,
+        data: Bytes,
+        p: Priority,
+        timeout: Duration,
+    ) -> Result<Attempt, NetworkError> {
         let rid = self.map.next_rpc_id();
-        let connected_addr = cx.remote_connected_addr();
         let done_rx = self.map.insert::<RpcResponse>(sid, rid);
 
         struct _Guard {
@@ -76,7 +114,8 @@ where
 
         impl Drop for _Guard {
             fn drop(&mut self) {
-                // Simple take then drop if there is one
+                // Simple take then drop if there is one, also cancel the
+                // call if caller drops its future.
                 let _ = self.map.take::<RpcResponse>(self.sid, self.rid);
             }
         }
@@ -87,19 +126,99 @@ where
             rid,
         };
 
-        let data = msg.encode().await?;
+        let lane = Lane::of(endpoint);
         let endpoint = endpoint.extend(&rid.to_string())?;
-        let net_msg = NetworkMessage::new(endpoint, data).encode().await?;
+        let net_msg = NetworkMessage::new(endpoint, data, cx.trace_id())
+            .encode()
+            .await?;
+
+        self.send(cx, sid, lane, net_msg, p)?;
+
+        let timeout = Delay::new(timeout);
+        match future::select(done_rx, timeout).await {
+            Either::Left((Ok(ret), _timeout)) => Ok(Attempt::Responded(ret)),
+            Either::Left((Err(_), _timeout)) => Ok(Attempt::Dropped),
+            Either::Right((_unresolved, _timeout)) => Ok(Attempt::TimedOut),
+        }
+    }
+
+    fn check_version(&self, endpoint: &Endpoint, sid: SessionId) -> Result<(), NetworkError> {
+        let version = self.sessions.protocol(sid).version;
+        let required = message_version(endpoint);
+        if version < required {
+            return Err(ErrorKind::UnsupportedMessage {
+                sid,
+                version,
+                endpoint: endpoint.full_url().to_owned(),
+                required,
+            }
+            .into());
+        }
 
-        self.send(cx, sid, net_msg, p)?;
+        Ok(())
+    }
+
+    fn alternate_session(&self, endpoint: &Endpoint, tried: &[SessionId]) -> Option<SessionId> {
+        let sendable = self.sessions.all_sendable().into_iter();
 
-        let timeout = Delay::new(self.timeout.rpc);
-        let ret = match future::select(done_rx, timeout).await {
-            Either::Left((ret, _timeout)) => {
-                ret.map_err(|_| NetworkError::from(ErrorKind::RpcDropped(connected_addr)))?
+        sendable
+            .filter(|sid| !tried.contains(sid))
+            .filter(|sid| self.check_version(endpoint, *sid).is_ok())
+            .choose(&mut rand::thread_rng())
+    }
+}
+
+#[async_trait]
+impl<S, C, B> Rpc for NetworkRpc<S, C, B>
+where
+    S: MessageSender + Send + Sync + Clone,
+    C: Compression + Send + Sync + Clone,
+    B: SessionBook + Send + Sync + Clone,
+{
+    async fn call<M, R>(&self, cx: Context, end: &str, mut msg: M, p: Priority) -> ProtocolResult<R>
+    where
+        M: MessageCodec,
+        R: MessageCodec,
+    {
+        let endpoint = end.parse::<Endpoint>()?;
+        let timeout = cx.rpc_timeout().unwrap_or(self.timeout.rpc);
+        let data = msg.encode().await?;
+
+        let mut sid = cx.session_id()?;
+        self.check_version(&endpoint, sid)?;
+        let mut tried = Vec::with_capacity(cx.rpc_retries() + 1);
+        let ret = loop {
+            let attempt = self
+                .attempt(cx.clone(), &endpoint, sid, data.clone(), p, timeout)
+                .await?;
+            tried.push(sid);
+
+            let last_err: ProtocolError = match attempt {
+                Attempt::Responded(ret) => break ret,
+                Attempt::Dropped => {
+                    let connected_addr = if tried.len() == 1 {
+                        cx.remote_connected_addr()
+                    } else {
+                        self.sessions.connected_addr(sid)
+                    };
+                    NetworkError::from(ErrorKind::RpcDropped(connected_addr)).into()
+                }
+                Attempt::TimedOut => RpcTimeout {
+                    end:      end.to_owned(),
+                    attempts: tried.len(),
+                }
+                .into(),
+            };
+
+            if tried.len() > cx.rpc_retries() {
+                return Err(last_err);
             }
-            Either::Right((_unresolved, _timeout)) => {
-                return Err(NetworkError::from(ErrorKind::RpcTimeout(connected_addr)).into());
+            match self.alternate_session(&endpoint, &tried) {
+                Some(alternate) => {
+                    debug!("network: rpc {} retry on session {}", end, alternate);
+                    sid = alternate;
+                }
+                None => return Err(last_err),
             }
         };
 
@@ -132,11 +251,269 @@ where
         };
 
         let encoded_resp = resp.encode().await?;
+        let lane = Lane::of(&endpoint);
         let endpoint = endpoint.extend(&rid.to_string())?;
-        let net_msg = NetworkMessage::new(endpoint, encoded_resp).encode().await?;
+        let net_msg = NetworkMessage::new(endpoint, encoded_resp, cx.trace_id())
+            .encode()
+            .await?;
 
-        self.send(cx, sid, net_msg, p)?;
+        self.respond(cx, sid, lane, net_msg, p)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use async_trait::async_trait;
+    use futures::future::{self, Either};
+    use futures_timer::Delay;
+    use parking_lot::Mutex;
+    use protocol::{
+        traits::{Context, Priority, Rpc, RpcContext},
+        types::Address,
+        Bytes,
+    };
+    use tentacle::{secio::PeerId, service::TargetSession, SessionId};
+
+    use crate::{
+        common::ConnectedAddr,
+        compression::Snappy,
+        config::TimeoutConfig,
+        error::NetworkError,
+        message::NetworkMessage,
+        rpc::RpcResponse,
+        rpc_map::RpcMap,
+        traits::{Compression, MessageSender, NetworkContext, SessionBook},
+        version::PeerProtocol,
+    };
+
+    use super::NetworkRpc;
+
+    const END_PING: &str = "/rpc_call/test/ping";
+
+    // Sessions not responding never answer, their calls time out
+    #[derive(Clone)]
+    struct MockSender {
+        map:        Arc<RpcMap>,
+        responding: Vec<SessionId>,
+        sent:       Arc<Mutex<Vec<SessionId>>>,
+    }
+
+    #[async_trait]
+    impl MessageSender for MockSender {
+        fn send(&self, tar: TargetSession, msg: Bytes, _: Priority) -> Result<(), NetworkError> {
+            let sid = match tar {
+                TargetSession::Single(sid) => sid,
+                _ => panic!("rpc should send to single session"),
+            };
+            self.sent.lock().push(sid);
+            if !self.responding.contains(&sid) {
+                return Ok(());
+            }
+
+            let msg = Snappy.decompress(msg, usize::max_value())?;
+            let net_msg = futures::executor::block_on(NetworkMessage::decode(msg))?;
+            let rid = net_msg
+                .url
+                .rsplit('/')
+                .next()
+                .and_then(|rid| rid.parse::<u64>().ok())
+                .expect("rpc id in url");
+
+            let pong = bincode::serialize(&"pong".to_owned()).expect("encode pong");
+            let resp = RpcResponse::Success(Bytes::from(pong));
+            let _ = self.map.take::<RpcResponse>(sid, rid)?.send(resp);
+            Ok(())
+        }
+
+        async fn users_send(
+            &self,
+            _: Vec<Address>,
+            _: Bytes,
+            _: Priority,
+        ) -> Result<(), NetworkError> {
+            unimplemented!()
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockSessions(Vec<SessionId>);
+
+    impl SessionBook for MockSessions {
+        fn all_sendable(&self) -> Vec<SessionId> {
+            self.0.clone()
+        }
+
+        fn all_blocked(&self) -> Vec<SessionId> {
+            vec![]
+        }
+
+        fn refresh_blocked(&self) {}
+
+        fn by_chain(&self, addrs: Vec<Address>) -> (Vec<SessionId>, Vec<Address>) {
+            (vec![], addrs)
+        }
+
+        fn peers_by_chain(&self, addrs: Vec<Address>) -> (Vec<PeerId>, Vec<Address>) {
+            (vec![], addrs)
+        }
+
+        fn all(&self) -> Vec<SessionId> {
+            self.0.clone()
+        }
+
+        fn connected_addr(&self, _: SessionId) -> Option<ConnectedAddr> {
+            None
+        }
+
+        fn pending_data_size(&self, _: SessionId) -> usize {
+            0
+        }
+
+        fn whitelist(&self) -> Vec<Address> {
+            vec![]
+        }
+
+        fn relays(&self) -> Vec<SessionId> {
+            vec![]
+        }
+
+        fn protocol(&self, _: SessionId) -> PeerProtocol {
+            PeerProtocol::local()
+        }
+    }
+
+    struct Harness {
+        rpc:  NetworkRpc<MockSender, Snappy, MockSessions>,
+        map:  Arc<RpcMap>,
+        sent: Arc<Mutex<Vec<SessionId>>>,
+    }
+
+    fn harness(sessions: &[usize], responding: &[usize]) -> Harness {
+        let map = Arc::new(RpcMap::new());
+        let sent = Arc::new(Mutex::new(vec![]));
+        let sender = MockSender {
+            map:        Arc::clone(&map),
+            responding: responding.iter().cloned().map(SessionId::new).collect(),
+            sent:       Arc::clone(&sent),
+        };
+        let sessions = MockSessions(sessions.iter().cloned().map(SessionId::new).collect());
+        let timeout = TimeoutConfig {
+            rpc: Duration::from_secs(10),
+        };
+
+        Harness {
+            rpc: NetworkRpc::new(sender, Snappy, Arc::clone(&map), sessions, timeout),
+            map,
+            sent,
+        }
+    }
+
+    fn ctx(sid: usize, retries: usize) -> Context {
+        Context::new()
+            .set_session_id(SessionId::new(sid))
+            .with_rpc_timeout(Duration::from_millis(50))
+            .with_rpc_retries(retries)
+    }
+
+    fn sent(harness: &Harness) -> Vec<SessionId> {
+        harness.sent.lock().clone()
+    }
+
+    #[tokio::test]
+    async fn should_respond_without_retry() {
+        let harness = harness(&[1, 2], &[1]);
+
+        let pong = harness
+            .rpc
+            .call::<String, String>(ctx(1, 1), END_PING, "ping".to_owned(), Priority::High)
+            .await
+            .expect("pong");
+
+        assert_eq!(pong, "pong");
+        assert_eq!(sent(&harness), vec![SessionId::new(1)]);
+    }
+
+    #[tokio::test]
+    async fn should_retry_timed_out_call_on_alternate_session() {
+        let harness = harness(&[1, 2], &[2]);
+
+        let pong = harness
+            .rpc
+            .call::<String, String>(ctx(1, 1), END_PING, "ping".to_owned(), Priority::High)
+            .await
+            .expect("pong from alternate session");
+
+        assert_eq!(pong, "pong");
+        assert_eq!(sent(&harness), vec![SessionId::new(1), SessionId::new(2)]);
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_without_retries() {
+        let harness = harness(&[1, 2], &[2]);
+
+        let err = harness
+            .rpc
+            .call::<String, String>(ctx(1, 0), END_PING, "ping".to_owned(), Priority::High)
+            .await
+            .expect_err("timeout");
+
+        assert!(err.to_string().contains("timeout after 1 attempt(s)"));
+        assert_eq!(sent(&harness), vec![SessionId::new(1)]);
+    }
+
+    #[tokio::test]
+    async fn should_fail_with_timeout_after_retries_are_exhausted() {
+        let harness = harness(&[1, 2, 3], &[]);
+
+        let err = harness
+            .rpc
+            .call::<String, String>(ctx(1, 1), END_PING, "ping".to_owned(), Priority::High)
+            .await
+            .expect_err("timeout");
+
+        assert!(err.to_string().contains("timeout after 2 attempt(s)"));
+        let sent = sent(&harness);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0], SessionId::new(1));
+        assert_ne!(sent[1], SessionId::new(1));
+    }
+
+    #[tokio::test]
+    async fn should_stop_retry_without_alternate_session() {
+        let harness = harness(&[1], &[]);
+
+        let err = harness
+            .rpc
+            .call::<String, String>(ctx(1, 3), END_PING, "ping".to_owned(), Priority::High)
+            .await
+            .expect_err("timeout");
+
+        assert!(err.to_string().contains("timeout after 1 attempt(s)"));
+        assert_eq!(sent(&harness), vec![SessionId::new(1)]);
+    }
+
+    #[tokio::test]
+    async fn should_remove_pending_call_when_caller_drops_it() {
+        let harness = harness(&[1], &[]);
+        let cx = Context::new().set_session_id(SessionId::new(1));
+
+        let call =
+            harness
+                .rpc
+                .call::<String, String>(cx, END_PING, "ping".to_owned(), Priority::High);
+        let delay = Delay::new(Duration::from_millis(50));
+        match future::select(Box::pin(call), delay).await {
+            Either::Left(_) => panic!("call should still be pending"),
+            Either::Right((_, call)) => {
+                assert!(harness.map.contains(SessionId::new(1), 0));
+                drop(call);
+            }
+        }
+
+        assert!(!harness.map.contains(SessionId::new(1), 0));
+    }
+}
@@ -10,7 +10,12 @@ use tentacle::{
 };
 use tentacle_identify::{Callback, MisbehaveResult, Misbehavior};
 
-use crate::{event::PeerManagerEvent, peer_manager::PeerManagerHandle};
+use crate::{
+    event::PeerManagerEvent,
+    peer_manager::PeerManagerHandle,
+    role::NodeRole,
+    version::{identify_message, parse_identify, PeerProtocol},
+};
 
 #[derive(Clone)]
 struct AddrReporter {
@@ -44,28 +49,58 @@ impl AddrReporter {
 pub struct IdentifyCallback {
     peer_mgr: PeerManagerHandle,
     reporter: AddrReporter,
+    // Role and protocol of this node
+    identify: Vec<u8>,
 }
 
 impl IdentifyCallback {
-    pub fn new(peer_mgr: PeerManagerHandle, event_tx: UnboundedSender<PeerManagerEvent>) -> Self {
+    pub fn new(
+        peer_mgr: PeerManagerHandle,
+        event_tx: UnboundedSender<PeerManagerEvent>,
+        role: NodeRole,
+    ) -> Self {
         let reporter = AddrReporter::new(event_tx);
 
-        IdentifyCallback { peer_mgr, reporter }
+        IdentifyCallback {
+            peer_mgr,
+            reporter,
+            identify: identify_message(role, PeerProtocol::local()),
+        }
     }
 }
 
 // TODO: should ask remote peer to sign a random words
 // and verify received signature?
 impl Callback for IdentifyCallback {
+    // Advertise our role and protocol
     fn identify(&mut self) -> &[u8] {
-        b"Identify message"
+        &self.identify
     }
 
     fn received_identify(
         &mut self,
-        _context: &mut ProtocolContextMutRef,
-        _identify: &[u8],
+        context: &mut ProtocolContextMutRef,
+        identify: &[u8],
     ) -> MisbehaveResult {
+        let pid = match context.session.remote_pubkey.as_ref() {
+            Some(pubkey) => pubkey.peer_id(),
+            None => return MisbehaveResult::Continue,
+        };
+
+        let (role, protocol) = parse_identify(identify);
+        // Unknown roles are of newer versions, keep the peer
+        if let Some(role) = role {
+            self.reporter.report(PeerManagerEvent::IdentifiedRole {
+                pid: pid.clone(),
+                role,
+            });
+        }
+
+        // Malformed protocol is treated as a peer before negotiation
+        let remote = protocol.unwrap_or_default();
+        let protocol = PeerProtocol::local().negotiate(remote);
+        self.reporter
+            .report(PeerManagerEvent::IdentifiedProtocol { pid, protocol });
         MisbehaveResult::Continue
     }
 
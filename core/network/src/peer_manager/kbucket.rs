@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+
+use tentacle::secio::PeerId;
+
+use super::ArcPeer;
+
+// Max peers picked from one bucket, same as kademlia
+pub const K_BUCKET_SIZE: usize = 20;
+
+/// Kademlia style buckets, peers are grouped by the length of the common
+/// prefix of their XOR distance to our peer id. Picking peers bucket by bucket
+/// spreads our connections and discovery responses across the whole id space
+/// instead of a random corner of it.
+pub struct KBuckets {
+    // Bucket index to peers, the farthest bucket has the largest index
+    buckets: BTreeMap<usize, Vec<ArcPeer>>,
+}
+
+impl KBuckets {
+    pub fn new(local: &PeerId, peers: Vec<ArcPeer>) -> Self {
+        let mut buckets = BTreeMap::new();
+
+        for peer in peers.into_iter() {
+            if let Some(idx) = bucket_index(local, &peer.id) {
+                buckets.entry(idx).or_insert_with(Vec::new).push(peer);
+            }
+        }
+
+        // Kademlia prefers long lived peers, they are more likely to stay online
+        for bucket in buckets.values_mut() {
+            bucket.sort_by(|a, b| b.alive().cmp(&a.alive()));
+            bucket.truncate(K_BUCKET_SIZE);
+        }
+
+        KBuckets { buckets }
+    }
+
+    /// Pick at most max peers, one from each bucket in turn, starting from
+    /// the farthest bucket.
+    pub fn spread(mut self, max: usize) -> Vec<ArcPeer> {
+        let mut picked = Vec::with_capacity(max);
+
+        for bucket in self.buckets.values_mut() {
+            bucket.reverse();
+        }
+
+        while picked.len() < max && !self.buckets.is_empty() {
+            let mut emptied = vec![];
+
+            for (idx, bucket) in self.buckets.iter_mut().rev() {
+                if picked.len() >= max {
+                    break;
+                }
+
+                match bucket.pop() {
+                    Some(peer) => picked.push(peer),
+                    None => emptied.push(*idx),
+                }
+            }
+
+            for idx in emptied.into_iter() {
+                self.buckets.remove(&idx);
+            }
+        }
+
+        picked
+    }
+}
+
+/// Returns None for our own id, otherwise the bit length of the XOR distance.
+pub fn bucket_index(local: &PeerId, remote: &PeerId) -> Option<usize> {
+    let local = local.as_bytes();
+    let remote = remote.as_bytes();
+    let len = local.len().max(remote.len());
+
+    for i in 0..len {
+        let a = local.get(i).cloned().unwrap_or(0);
+        let b = remote.get(i).cloned().unwrap_or(0);
+        let distance = a ^ b;
+
+        if distance != 0 {
+            let bits = (len - i) * 8 - distance.leading_zeros() as usize;
+            return Some(bits - 1);
+        }
+    }
+
+    None
+}
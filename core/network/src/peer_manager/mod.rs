@@ -1,6 +1,7 @@
 mod addr_set;
 mod disc;
 mod ident;
+mod kbucket;
 mod peer;
 mod retry;
 mod save_restore;
@@ -8,6 +9,7 @@ mod shared;
 mod time;
 
 use addr_set::PeerAddrSet;
+use kbucket::KBuckets;
 use peer::Peer;
 use retry::Retry;
 use save_restore::{NoPeerDatFile, PeerDatFile, SaveRestore};
@@ -37,6 +39,7 @@ use std::{
     time::Duration,
 };
 
+use common_metrics::{NETWORK_CONNECTED_PEERS, NETWORK_EVICTED_SLOW_PEERS};
 use derive_more::Display;
 use futures::{
     channel::mpsc::{UnboundedReceiver, UnboundedSender},
@@ -46,8 +49,7 @@ use futures::{
 };
 use log::{debug, error, info, warn};
 use parking_lot::RwLock;
-use protocol::types::Address;
-use rand::seq::IteratorRandom;
+use protocol::{traits::PeerScore, types::Address};
 use serde_derive::{Deserialize, Serialize};
 #[cfg(not(test))]
 use tentacle::context::SessionContext;
@@ -65,7 +67,9 @@ use crate::{
         ConnectionErrorKind, ConnectionEvent, ConnectionType, MisbehaviorKind, PeerManagerEvent,
         SessionErrorKind,
     },
+    role::NodeRole,
     traits::MultiaddrExt,
+    version::PeerProtocol,
 };
 
 #[cfg(test)]
@@ -78,6 +82,11 @@ const MAX_RETRY_COUNT: u8 = 30;
 const SHORT_ALIVE_SESSION: u64 = 3; // seconds
 const WHITELIST_TIMEOUT: u64 = 2 * 60 * 60; // 2 hour
 const MAX_CONNECTING_MARGIN: usize = 10;
+const SAVE_PEERS_INTERVAL: u64 = 10 * 60; // 10 minutes
+const MAX_PEER_SCORE: i32 = 100;
+const PEER_BAN_DURATION: u64 = 10 * 60; // 10 minutes
+const SCORE_RECOVER_INTERVAL: u64 = 60; // seconds
+const SCORE_RECOVER_POINT: i32 = 5;
 
 #[derive(Debug, Clone, Display, Serialize, Deserialize)]
 #[display(fmt = "{}", _0)]
@@ -277,6 +286,13 @@ impl ArcWhitelistedPeer {
             ExpireTime::Never => false,
         }
     }
+
+    pub fn is_never_expired(&self) -> bool {
+        match &self.expire {
+            ExpireTime::At(_) => false,
+            ExpireTime::Never => true,
+        }
+    }
 }
 
 impl Borrow<Address> for ArcWhitelistedPeer {
@@ -313,6 +329,7 @@ struct Session {
     ctx:            Arc<SessionContext>,
     peer:           ArcPeer,
     blocked:        AtomicBool,
+    blocked_at:     AtomicU64,
     connected_addr: ConnectedAddr,
 }
 
@@ -327,6 +344,7 @@ impl ArcSession {
             ctx,
             peer,
             blocked: AtomicBool::new(false),
+            blocked_at: AtomicU64::new(0),
             connected_addr,
         };
 
@@ -335,6 +353,17 @@ impl ArcSession {
 
     pub fn block(&self) {
         self.blocked.store(true, Ordering::SeqCst);
+
+        // Keep the time when it was first blocked
+        let now = time::now();
+        let _ = self
+            .blocked_at
+            .compare_exchange(0, now, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    // Returns 0 if session isn't blocked
+    pub fn blocked_at(&self) -> u64 {
+        self.blocked_at.load(Ordering::SeqCst)
     }
 
     pub fn is_blocked(&self) -> bool {
@@ -343,6 +372,7 @@ impl ArcSession {
 
     pub fn unblock(&self) {
         self.blocked.store(false, Ordering::SeqCst);
+        self.blocked_at.store(0, Ordering::SeqCst);
     }
 }
 
@@ -382,10 +412,17 @@ struct Inner {
     chain:    RwLock<HashMap<Address, ArcPeer>>,
 
     listen: RwLock<HashSet<PeerMultiaddr>>,
+
+    // Private peers are never shared, relay peers exchange consensus messages
+    // relayed by us
+    private: HashSet<PeerId>,
+    relays:  HashSet<PeerId>,
+
+    our_id: PeerId,
 }
 
 impl Inner {
-    pub fn new() -> Self {
+    pub fn new(peer_id: PeerId, private_ids: HashSet<PeerId>, relay_ids: HashSet<PeerId>) -> Self {
         Inner {
             whitelist: Default::default(),
 
@@ -394,9 +431,22 @@ impl Inner {
             chain:    Default::default(),
 
             listen: Default::default(),
+
+            private: private_ids,
+            relays:  relay_ids,
+
+            our_id: peer_id,
         }
     }
 
+    pub fn is_private(&self, peer_id: &PeerId) -> bool {
+        self.private.contains(peer_id)
+    }
+
+    pub fn is_relay(&self, peer_id: &PeerId) -> bool {
+        self.relays.contains(peer_id)
+    }
+
     pub fn add_listen(&self, multiaddr: PeerMultiaddr) {
         self.listen.write().insert(multiaddr);
     }
@@ -433,14 +483,42 @@ impl Inner {
             (p.connectedness() == Connectedness::NotConnected
                 || p.connectedness() == Connectedness::CanConnect)
                 && p.retry.ready()
+                && !p.is_banned()
+                && p.multiaddrs.connectable_len() > 0
+        };
+
+        let qualified_peers = {
+            let book = self.peers.read();
+            book.iter()
+                .filter(connectable)
+                .map(ArcPeer::to_owned)
+                .collect()
+        };
+
+        KBuckets::new(&self.our_id, qualified_peers).spread(max)
+    }
+
+    // Private peers are kept connected no matter how many connections we have
+    pub fn disconnected_private_peers(&self) -> Vec<ArcPeer> {
+        let disconnected = |p: &'_ &ArcPeer| -> bool {
+            self.is_private(&p.id)
+                && (p.connectedness() == Connectedness::NotConnected
+                    || p.connectedness() == Connectedness::CanConnect)
+                && p.retry.ready()
                 && p.multiaddrs.connectable_len() > 0
         };
 
-        let mut rng = rand::thread_rng();
         let book = self.peers.read();
-        let qualified_peers = book.iter().filter(connectable).map(ArcPeer::to_owned);
+        book.iter().filter(disconnected).cloned().collect()
+    }
 
-        qualified_peers.choose_multiple(&mut rng, max)
+    // Peers shared with others through discovery
+    pub fn public_peers(&self) -> Vec<ArcPeer> {
+        let book = self.peers.read();
+        book.iter()
+            .filter(|p| !self.is_private(&p.id))
+            .cloned()
+            .collect()
     }
 
     #[allow(dead_code)]
@@ -479,6 +557,24 @@ impl Inner {
         self.whitelist.write().extend(whitelisted);
     }
 
+    // Replace expirable one if exists
+    pub fn allow_peer_by_chain_addr(&self, chain_addr: Address) {
+        let allowed = ArcWhitelistedPeer::new_never(chain_addr);
+
+        self.whitelist.write().replace(allowed);
+    }
+
+    pub fn disallow_peer_by_chain_addr(&self, chain_addr: &Address) {
+        self.whitelist.write().remove(chain_addr);
+    }
+
+    pub fn allowlist(&self) -> Vec<Address> {
+        let whitelist = self.whitelist.read();
+        let allowed = whitelist.iter().filter(|p| p.is_never_expired());
+
+        allowed.map(|p| p.owned_chain_addr()).collect()
+    }
+
     pub fn whitelisted_by_chain_addr(&self, chain_addr: &Address) -> bool {
         self.whitelist.read().contains(chain_addr)
     }
@@ -515,6 +611,16 @@ impl Inner {
         self.peers.read().iter().cloned().collect()
     }
 
+    // Peers we have connected to before and not given up yet
+    pub fn known_good_peers(&self) -> Vec<ArcPeer> {
+        let known_good = |p: &'_ &ArcPeer| -> bool {
+            p.connected_at() > 0 && p.connectedness() != Connectedness::Unconnectable
+        };
+
+        let book = self.peers.read();
+        book.iter().filter(known_good).cloned().collect()
+    }
+
     fn restore(&self, peers: Vec<ArcPeer>) {
         let chain_peers: Vec<_> = peers
             .clone()
@@ -539,6 +645,12 @@ pub struct PeerManagerConfig {
     /// Bootstrap peers
     pub bootstraps: Vec<ArcPeer>,
 
+    /// Private peers, they are never shared, always kept connected and
+    /// never punished
+    pub private_peers: Vec<ArcPeer>,
+    /// Peers relaying consensus messages with us
+    pub relay_peers:   Vec<PeerId>,
+
     /// Never expired whitelist peers by chain address
     pub whitelist_by_chain_addrs: Vec<Address>,
     /// Only allow peers in whitelist
@@ -552,6 +664,12 @@ pub struct PeerManagerConfig {
 
     /// Peer dat file path
     pub peer_dat_file: PathBuf,
+
+    /// Max pending data size of one session's send queue, session exceeds
+    /// it is evicted
+    pub max_pending_data_size: usize,
+    /// Session stays blocked longer than this duration is evicted
+    pub max_blocked_duration:  Duration,
 }
 
 #[derive(Clone)]
@@ -564,10 +682,10 @@ impl PeerManagerHandle {
         self.inner.session(sid).map(|s| s.peer.owned_id())
     }
 
+    // Peers are picked across kademlia buckets, so that the remote can reach
+    // the whole network through our response.
     pub fn random_addrs(&self, max: usize) -> Vec<Multiaddr> {
-        let mut rng = rand::thread_rng();
-        let book = self.inner.peers.read();
-        let peers = book.iter().choose_multiple(&mut rng, max);
+        let peers = KBuckets::new(&self.inner.our_id, self.inner.public_peers()).spread(max);
 
         // Should always include our self
         let our_self = self.listen_addrs();
@@ -576,6 +694,24 @@ impl PeerManagerHandle {
         our_self.into_iter().chain(condidates).take(max).collect()
     }
 
+    pub fn peer_scores(&self) -> Vec<PeerScore> {
+        let book = self.inner.peers.read();
+
+        book.iter()
+            .map(|p| PeerScore {
+                peer_id:      p.id.to_base58(),
+                chain_addr:   p.owned_chain_addr().map(|ca| ca.as_hex()),
+                role:         p.role().map(|role| role.to_string()),
+                score:        p.score(),
+                banned_until: p.banned_until(),
+            })
+            .collect()
+    }
+
+    pub fn allowlist(&self) -> Vec<Address> {
+        self.inner.allowlist()
+    }
+
     pub fn listen_addrs(&self) -> Vec<Multiaddr> {
         let listen = self.inner.listen();
         debug_assert!(!listen.is_empty(), "listen should alway be set");
@@ -611,6 +747,9 @@ pub struct PeerManager {
 
     // save restore
     peer_dat_file: Box<dyn SaveRestore>,
+    saved_at:      u64,
+
+    score_recovered_at: u64,
 }
 
 impl PeerManager {
@@ -621,7 +760,12 @@ impl PeerManager {
     ) -> Self {
         let peer_id = config.our_id.clone();
 
-        let inner = Arc::new(Inner::new());
+        let private = config.private_peers.iter().map(|p| p.owned_id()).collect();
+        let relays = HashSet::from_iter(config.relay_peers.clone());
+        let inner = Arc::new(Inner::new(peer_id.clone(), private, relays));
+        for peer in config.private_peers.iter() {
+            inner.add_peer(peer.clone());
+        }
         let bootstraps = HashSet::from_iter(config.bootstraps.clone());
         let waker = Arc::new(AtomicWaker::new());
         let heart_beat = HeartBeat::new(Arc::clone(&waker), config.routine_interval);
@@ -644,6 +788,9 @@ impl PeerManager {
             hb_waker: waker,
 
             peer_dat_file,
+            saved_at: time::now(),
+
+            score_recovered_at: time::now(),
         }
     }
 
@@ -663,6 +810,16 @@ impl PeerManager {
         self.peer_dat_file = Box::new(peer_dat_file);
     }
 
+    pub fn save_peers(&mut self) {
+        let peers = self.inner.known_good_peers();
+        debug!("network: {:?}: save {} peers", self.peer_id, peers.len());
+
+        if let Err(err) = self.peer_dat_file.save(peers) {
+            error!("network: peer dat file: {}", err);
+        }
+        self.saved_at = time::now();
+    }
+
     pub fn restore_peers(&self) -> Result<(), NetworkError> {
         let peers = self.peer_dat_file.restore()?;
         self.inner.restore(peers);
@@ -724,7 +881,18 @@ impl PeerManager {
             }
         }
 
-        if self.config.whitelist_peers_only && !self.inner.whitelisted(&remote_peer) {
+        // Private peers bypass bans, whitelist and max connections
+        let private = self.inner.is_private(&remote_peer_id);
+
+        if !private && remote_peer.is_banned() {
+            debug!("reject banned peer {:?}", remote_peer.id);
+
+            remote_peer.mark_disconnected();
+            self.disconnect_session(ctx.id);
+            return;
+        }
+
+        if !private && self.config.whitelist_peers_only && !self.inner.whitelisted(&remote_peer) {
             debug!("reject peer {:?} not in whitelist", remote_peer.id);
 
             remote_peer.mark_disconnected();
@@ -732,7 +900,7 @@ impl PeerManager {
             return;
         }
 
-        if self.inner.connected() >= self.config.max_connections {
+        if !private && self.inner.connected() >= self.config.max_connections {
             let whitelisted = match Peer::pubkey_to_chain_addr(&pubkey) {
                 Ok(ca) => self.inner.whitelisted_by_chain_addr(&ca),
                 _ => false,
@@ -835,7 +1003,7 @@ impl PeerManager {
             }
             SecioHandshake(_) | ProtocolHandle => {
                 warn!("give up peer {:?} becasue {}", peer.id, error_kind);
-                peer.set_connectedness(Connectedness::Unconnectable);
+                self.give_up_peer(&peer);
             }
         }
 
@@ -853,7 +1021,7 @@ impl PeerManager {
                 attempt.peer.set_connectedness(Connectedness::CanConnect);
 
                 if attempt.peer.retry.run_out() {
-                    attempt.peer.set_connectedness(Connectedness::Unconnectable);
+                    self.give_up_peer(&attempt.peer);
                 }
             } else {
                 // Wait for other connecting multiaddrs result
@@ -882,7 +1050,7 @@ impl PeerManager {
                 let remote_addr = &session.connected_addr;
 
                 warn!("give up peer {:?} from {} {}", pid, remote_addr, error_kind);
-                session.peer.set_connectedness(Connectedness::Unconnectable);
+                self.give_up_peer(&session.peer);
             }
         }
     }
@@ -894,7 +1062,6 @@ impl PeerManager {
         }
     }
 
-    // TODO: score system
     fn peer_misbehave(&self, pid: PeerId, kind: MisbehaviorKind) {
         use MisbehaviorKind::*;
 
@@ -906,6 +1073,18 @@ impl PeerManager {
             }
         };
 
+        let penalty = match &kind {
+            UndecodableMessage | OversizedMessage => 20,
+            InvalidBlock | InvalidVoteExtension => 50,
+            DuplicateTx(tx_hash) if peer.resend_duplicate_tx(tx_hash.clone()) => 1,
+            DuplicateTx(_) => return,
+            PingTimeout | PingUnexpect | Discovery => 0,
+        };
+        if penalty > 0 {
+            self.punish_peer(peer, penalty, kind);
+            return;
+        }
+
         let sid = peer.session_id();
         if sid == SessionId::new(0) {
             // Impossible, connected session always bigger than 0
@@ -920,22 +1099,140 @@ impl PeerManager {
 
         match kind {
             PingTimeout => peer.retry.inc(),
-            PingUnexpect | Discovery => peer.set_connectedness(Connectedness::Unconnectable), /* Give up this peer */
+            PingUnexpect | Discovery => self.give_up_peer(&peer),
+            UndecodableMessage | OversizedMessage | InvalidBlock | InvalidVoteExtension
+            | DuplicateTx(_) => unreachable!("punished by score"),
+        }
+    }
+
+    // Peer is banned once its score runs out, misbehavior may be reported
+    // after session closed, so we punish it no matter connected or not.
+    fn punish_peer(&self, peer: ArcPeer, penalty: i32, kind: MisbehaviorKind) {
+        if self.inner.is_private(&peer.id) {
+            debug!(
+                "network: skip punishing private peer {:?} {}",
+                peer.id, kind
+            );
+            return;
+        }
+
+        let score = peer.punish(penalty);
+        debug!(
+            "network: punish peer {:?} {}, score {}",
+            peer.id, kind, score
+        );
+
+        if score > 0 {
+            return;
+        }
+
+        warn!(
+            "network: ban peer {:?} {} seconds because {}",
+            peer.id, PEER_BAN_DURATION, kind
+        );
+        peer.ban(PEER_BAN_DURATION);
+
+        let sid = peer.session_id();
+        if sid != SessionId::new(0) {
+            self.inner.remove_session(sid);
+            peer.mark_disconnected();
+            self.disconnect_session(sid);
+        }
+    }
+
+    // Private peers are never given up, we keep retrying in max interval
+    fn give_up_peer(&self, peer: &ArcPeer) {
+        if !self.inner.is_private(&peer.id) {
+            peer.set_connectedness(Connectedness::Unconnectable);
+            return;
+        }
+
+        if peer.retry.run_out() {
+            peer.retry.reset();
+        }
+        peer.retry.inc();
+        if peer.connectedness() != Connectedness::Connected {
+            peer.set_connectedness(Connectedness::CanConnect);
+        }
+    }
+
+    fn recover_peer_scores(&mut self) {
+        for peer in self.inner.peers.read().iter() {
+            peer.recover(SCORE_RECOVER_POINT);
         }
+        self.score_recovered_at = time::now();
     }
 
     fn session_blocked(&self, ctx: Arc<SessionContext>) {
+        let pending_data_size = ctx.pending_data_size();
         warn!(
             "session {} blocked, pending data size {}",
-            ctx.id,
-            ctx.pending_data_size()
+            ctx.id, pending_data_size
         );
 
         if let Some(session) = self.inner.session(ctx.id) {
             session.block();
+
+            if pending_data_size > self.config.max_pending_data_size {
+                self.evict_slow_session(session);
+            }
         }
     }
 
+    fn evict_slow_sessions(&self) {
+        let max_blocked_secs = self.config.max_blocked_duration.as_secs();
+        let now = time::now();
+
+        let slow_sessions = {
+            let sessions = self.inner.sessions.read();
+            let slow = |s: &'_ &ArcSession| -> bool {
+                s.is_blocked() && s.blocked_at() > 0 && now > s.blocked_at() + max_blocked_secs
+            };
+
+            sessions.iter().filter(slow).cloned().collect::<Vec<_>>()
+        };
+
+        for session in slow_sessions {
+            self.evict_slow_session(session);
+        }
+    }
+
+    // Slow peer isn't banned, it's just too far behind us. We may connect
+    // it again later.
+    fn evict_slow_session(&self, session: ArcSession) {
+        warn!(
+            "network: evict slow session {} {:?}, pending data size {}",
+            session.id,
+            session.peer.id,
+            session.ctx.pending_data_size()
+        );
+        NETWORK_EVICTED_SLOW_PEERS.inc();
+
+        self.inner.remove_session(session.id);
+        session.peer.mark_disconnected();
+        self.disconnect_session(session.id);
+    }
+
+    fn disallow_peer(&self, chain_addr: Address) {
+        self.inner.disallow_peer_by_chain_addr(&chain_addr);
+
+        if !self.config.whitelist_peers_only {
+            return;
+        }
+
+        let opt_peer = self.inner.chain.read().get(&chain_addr).cloned();
+        let peer = match opt_peer {
+            Some(peer) if peer.connectedness() == Connectedness::Connected => peer,
+            _ => return,
+        };
+
+        info!("network: disconnect disallowed peer {:?}", peer.id);
+        let sid = peer.session_id();
+        self.inner.remove_session(sid);
+        peer.mark_disconnected();
+        self.disconnect_session(sid);
+    }
+
     fn connect_peers_now(&mut self, peers: Vec<ArcPeer>) {
         let peer_addrs = peers.into_iter().map(|peer| {
             peer.set_connectedness(Connectedness::Connecting);
@@ -961,11 +1258,17 @@ impl PeerManager {
 
     fn connect_peers(&mut self, peers: Vec<ArcPeer>) {
         let connectable = |p: ArcPeer| -> Option<ArcPeer> {
-            if self.config.whitelist_peers_only && !self.inner.whitelisted(&p) {
+            let private = self.inner.is_private(&p.id);
+            if !private && self.config.whitelist_peers_only && !self.inner.whitelisted(&p) {
                 debug!("filter peer {:?} not in whitelist", p.id);
                 return None;
             }
 
+            if !private && p.is_banned() {
+                debug!("filter banned peer {:?}", p.id);
+                return None;
+            }
+
             let connectedness = p.connectedness();
             if connectedness != Connectedness::CanConnect
                 && connectedness != Connectedness::NotConnected
@@ -1037,6 +1340,22 @@ impl PeerManager {
         }
     }
 
+    fn identified_role(&self, pid: &PeerId, role: NodeRole) {
+        info!("peer {:?} identified role {}", pid, role);
+
+        if let Some(peer) = self.inner.peer(pid) {
+            peer.set_role(role);
+        }
+    }
+
+    fn identified_protocol(&self, pid: &PeerId, protocol: PeerProtocol) {
+        info!("peer {:?} negotiated protocol {}", pid, protocol);
+
+        if let Some(peer) = self.inner.peer(pid) {
+            peer.set_protocol(protocol);
+        }
+    }
+
     fn repeated_connection(&mut self, ty: ConnectionType, sid: SessionId, addr: Multiaddr) {
         info!(
             "repeated session {:?}, ty {}, remote addr {:?}",
@@ -1079,8 +1398,16 @@ impl PeerManager {
             PeerManagerEvent::WhitelistPeersByChainAddr { chain_addrs } => {
                 self.inner.whitelist_peers_by_chain_addr(chain_addrs);
             }
+            PeerManagerEvent::AllowPeer { chain_addr } => {
+                self.inner.allow_peer_by_chain_addr(chain_addr);
+            }
+            PeerManagerEvent::DisallowPeer { chain_addr } => self.disallow_peer(chain_addr),
             PeerManagerEvent::DiscoverMultiAddrs { addrs } => self.dicover_multi_multiaddrs(addrs),
             PeerManagerEvent::IdentifiedAddrs { pid, addrs } => self.identified_addrs(&pid, addrs),
+            PeerManagerEvent::IdentifiedRole { pid, role } => self.identified_role(&pid, role),
+            PeerManagerEvent::IdentifiedProtocol { pid, protocol } => {
+                self.identified_protocol(&pid, protocol)
+            }
             PeerManagerEvent::AddNewListenAddr { addr } => {
                 let peer_addr = PeerMultiaddr::new(addr, &self.peer_id);
                 self.inner.add_listen(peer_addr);
@@ -1089,6 +1416,12 @@ impl PeerManager {
                 self.inner
                     .remove_listen(&PeerMultiaddr::new(addr, &self.peer_id));
             }
+            // NOTE: Connected sessions beyond new max connections are kept,
+            // we just stop dialing and accepting new sessions.
+            PeerManagerEvent::SetMaxConnections { max } => {
+                info!("network: {:?}: set max connections {}", self.peer_id, max);
+                self.config.max_connections = max;
+            }
         }
     }
 }
@@ -1096,11 +1429,7 @@ impl PeerManager {
 // Save peers during shutdown
 impl Drop for PeerManager {
     fn drop(&mut self) {
-        let peers = self.inner.package_peers();
-
-        if let Err(err) = self.peer_dat_file.save(peers) {
-            error!("network: peer dat file: {}", err);
-        }
+        self.save_peers();
     }
 }
 
@@ -1128,8 +1457,14 @@ impl Future for PeerManager {
             self.process_event(event);
         }
 
+        let private_peers = self.inner.disconnected_private_peers();
+        if !private_peers.is_empty() {
+            self.connect_peers(private_peers);
+        }
+
         // Check connecting count
         let connected_count = self.inner.connected();
+        NETWORK_CONNECTED_PEERS.set(connected_count as i64);
         let connection_attempts = connected_count + self.connecting.len();
         let max_connection_attempts = self.config.max_connections + MAX_CONNECTING_MARGIN;
 
@@ -1153,6 +1488,17 @@ impl Future for PeerManager {
         // Clean expired whitelisted peer
         self.inner.whitelist.write().retain(|p| !p.is_expired());
 
+        // Save peers periodically, process may be killed without drop
+        if time::now() > self.saved_at + SAVE_PEERS_INTERVAL {
+            self.save_peers();
+        }
+
+        if time::now() > self.score_recovered_at + SCORE_RECOVER_INTERVAL {
+            self.recover_peer_scores();
+        }
+
+        self.evict_slow_sessions();
+
         Poll::Pending
     }
 }
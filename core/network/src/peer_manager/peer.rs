@@ -1,28 +1,34 @@
-use super::{time, PeerAddrSet, Retry, MAX_RETRY_COUNT};
+use super::{time, PeerAddrSet, Retry, MAX_PEER_SCORE, MAX_RETRY_COUNT};
 
 use std::{
     borrow::Borrow,
+    collections::HashMap,
     fmt,
     hash::{Hash, Hasher},
     ops::Deref,
     sync::{
-        atomic::{AtomicU64, AtomicUsize, Ordering},
+        atomic::{AtomicI32, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use derive_more::Display;
-use parking_lot::RwLock;
-use protocol::{types::Address, Bytes};
+use parking_lot::{Mutex, RwLock};
+use protocol::{
+    types::{Address, Hash as TxHash},
+    Bytes,
+};
 use tentacle::{
     secio::{PeerId, PublicKey},
     SessionId,
 };
 
-use crate::error::ErrorKind;
+use crate::{error::ErrorKind, role::NodeRole, version::PeerProtocol};
 
 const CONNECTEDNESS_MASK: usize = 0b1110;
+const DUPLICATE_TX_WINDOW: u64 = 60; // seconds
+const MAX_DUPLICATE_TXS: usize = 1024;
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Display)]
 #[repr(usize)]
@@ -74,11 +80,19 @@ pub struct Peer {
     pub retry:       Retry,
     pubkey:          RwLock<Option<PublicKey>>,
     chain_addr:      RwLock<Option<Address>>,
+    // Advertised in identify message
+    role:            RwLock<Option<NodeRole>>,
+    // Negotiated in identify, peers not identified yet are of version 0
+    protocol:        RwLock<PeerProtocol>,
     connectedness:   AtomicUsize,
     session_id:      AtomicUsize,
     connected_at:    AtomicU64,
     disconnected_at: AtomicU64,
     alive:           AtomicU64,
+    score:           AtomicI32,
+    banned_until:    AtomicU64,
+    // Duplicate transactions sent by the peer, with when they're last sent
+    duplicate_txs:   Mutex<HashMap<TxHash, u64>>,
 }
 
 impl Peer {
@@ -89,11 +103,16 @@ impl Peer {
             retry:           Retry::new(MAX_RETRY_COUNT),
             pubkey:          RwLock::new(None),
             chain_addr:      RwLock::new(None),
+            role:            RwLock::new(None),
+            protocol:        RwLock::new(PeerProtocol::default()),
             connectedness:   AtomicUsize::new(Connectedness::NotConnected as usize),
             session_id:      AtomicUsize::new(0),
             connected_at:    AtomicU64::new(0),
             disconnected_at: AtomicU64::new(0),
             alive:           AtomicU64::new(0),
+            score:           AtomicI32::new(MAX_PEER_SCORE),
+            banned_until:    AtomicU64::new(0),
+            duplicate_txs:   Mutex::new(HashMap::new()),
         }
     }
 
@@ -120,6 +139,22 @@ impl Peer {
         self.chain_addr.read().clone()
     }
 
+    pub fn role(&self) -> Option<NodeRole> {
+        *self.role.read()
+    }
+
+    pub fn set_role(&self, role: NodeRole) {
+        *self.role.write() = Some(role);
+    }
+
+    pub fn protocol(&self) -> PeerProtocol {
+        *self.protocol.read()
+    }
+
+    pub fn set_protocol(&self, protocol: PeerProtocol) {
+        *self.protocol.write() = protocol;
+    }
+
     pub fn set_pubkey(&self, pubkey: PublicKey) -> Result<(), ErrorKind> {
         if pubkey.peer_id() != self.id {
             Err(ErrorKind::PublicKeyNotMatchId {
@@ -184,6 +219,53 @@ impl Peer {
         self.alive.store(live, Ordering::SeqCst);
     }
 
+    pub fn score(&self) -> i32 {
+        self.score.load(Ordering::SeqCst)
+    }
+
+    /// Returns score after punishment
+    pub fn punish(&self, penalty: i32) -> i32 {
+        self.score.fetch_sub(penalty, Ordering::SeqCst) - penalty
+    }
+
+    // Only peer manager updates score, no need to compare and swap
+    pub fn recover(&self, point: i32) {
+        let score = self.score().saturating_add(point).min(MAX_PEER_SCORE);
+        self.score.store(score, Ordering::SeqCst);
+    }
+
+    /// Record a transaction already known when the peer sent it, returns
+    /// true if the peer sent it within the window before. Honest gossip
+    /// delivers a transaction from many peers, but each of them once.
+    pub fn resend_duplicate_tx(&self, tx_hash: TxHash) -> bool {
+        let now = time::now();
+        let mut duplicate_txs = self.duplicate_txs.lock();
+
+        duplicate_txs.retain(|_, at| now.saturating_sub(*at) < DUPLICATE_TX_WINDOW);
+        if duplicate_txs.len() >= MAX_DUPLICATE_TXS && !duplicate_txs.contains_key(&tx_hash) {
+            // Flooded by distinct transactions, they're not resent
+            return false;
+        }
+
+        duplicate_txs.insert(tx_hash, now).is_some()
+    }
+
+    pub fn banned_until(&self) -> u64 {
+        self.banned_until.load(Ordering::SeqCst)
+    }
+
+    pub fn is_banned(&self) -> bool {
+        time::now() < self.banned_until()
+    }
+
+    /// Ban peer for duration seconds, score is restored, so that peer
+    /// gets a fresh start after ban.
+    pub fn ban(&self, duration: u64) {
+        self.banned_until
+            .store(time::now() + duration, Ordering::SeqCst);
+        self.score.store(MAX_PEER_SCORE, Ordering::SeqCst);
+    }
+
     pub fn pubkey_to_chain_addr(pubkey: &PublicKey) -> Result<Address, ErrorKind> {
         let pubkey_bytes = Bytes::from(pubkey.inner_ref().clone());
 
@@ -220,13 +302,14 @@ impl fmt::Display for Peer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{:?} chain addr {:?} multiaddr {:?} last connected at {} alive {} retry {} current {}",
+            "{:?} chain addr {:?} multiaddr {:?} last connected at {} alive {} retry {} score {} current {}",
             self.id,
             self.chain_addr,
             self.multiaddrs.all(),
             self.connected_at.load(Ordering::SeqCst),
             self.alive.load(Ordering::SeqCst),
             self.retry.count(),
+            self.score.load(Ordering::SeqCst),
             Connectedness::from(self.connectedness.load(Ordering::SeqCst))
         )
     }
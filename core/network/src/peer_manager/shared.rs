@@ -1,5 +1,5 @@
 use super::{ArcSession, Connectedness, Inner};
-use crate::{common::ConnectedAddr, traits::SessionBook};
+use crate::{common::ConnectedAddr, traits::SessionBook, version::PeerProtocol};
 
 use log::debug;
 use parking_lot::RwLock;
@@ -134,4 +134,21 @@ impl SessionBook for SharedSessions {
             .map(|p| p.owned_chain_addr())
             .collect()
     }
+
+    fn relays(&self) -> Vec<SessionId> {
+        self.sessions()
+            .read()
+            .iter()
+            .filter(|s| self.inner.is_relay(&s.peer.id))
+            .map(|s| s.id)
+            .collect()
+    }
+
+    fn protocol(&self, sid: SessionId) -> PeerProtocol {
+        self.sessions()
+            .read()
+            .get(&sid)
+            .map(|s| s.peer.protocol())
+            .unwrap_or_default()
+    }
 }
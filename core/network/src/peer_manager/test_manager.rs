@@ -1,21 +1,24 @@
 use super::{
-    time, ArcPeer, Connectedness, ConnectingAttempt, Inner, MisbehaviorKind, PeerManager,
-    PeerManagerConfig, PeerMultiaddr, TestExpireTime, MAX_RETRY_COUNT, REPEATED_CONNECTION_TIMEOUT,
-    SHORT_ALIVE_SESSION, WHITELIST_TIMEOUT,
+    kbucket, time, ArcPeer, Connectedness, ConnectingAttempt, Inner, MisbehaviorKind, PeerManager,
+    PeerManagerConfig, PeerMultiaddr, TestExpireTime, MAX_PEER_SCORE, MAX_RETRY_COUNT,
+    PEER_BAN_DURATION, REPEATED_CONNECTION_TIMEOUT, SHORT_ALIVE_SESSION, WHITELIST_TIMEOUT,
 };
 use crate::{
     common::ConnectedAddr,
     event::{
         ConnectionErrorKind, ConnectionEvent, ConnectionType, PeerManagerEvent, SessionErrorKind,
     },
+    role::NodeRole,
     test::mock::SessionContext,
     traits::MultiaddrExt,
+    version::PeerProtocol,
 };
 
 use futures::{
     channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
     StreamExt,
 };
+use protocol::{types::Hash, Bytes};
 use tentacle::{
     multiaddr::Multiaddr,
     secio::{PeerId, PublicKey, SecioKeyPair},
@@ -130,11 +133,15 @@ fn make_manager(
         our_id: manager_id,
         pubkey: manager_pubkey,
         bootstraps,
+        private_peers: Default::default(),
+        relay_peers: Default::default(),
         whitelist_by_chain_addrs: Default::default(),
         whitelist_peers_only: false,
         max_connections,
         routine_interval: Duration::from_secs(10),
         peer_dat_file,
+        max_pending_data_size: 1024,
+        max_blocked_duration: Duration::from_secs(60),
     };
 
     let (conn_tx, conn_rx) = unbounded();
@@ -619,6 +626,41 @@ async fn should_reject_new_connections_when_we_reach_max_connections_on_new_sess
     }
 }
 
+#[tokio::test]
+async fn should_reject_new_connections_after_lower_max_connections_on_set_max_connections() {
+    let (mut mgr, mut conn_rx) = make_manager(0, 20);
+    let _remote_peers = make_sessions(&mut mgr, 5, 7000).await;
+
+    let set_max = PeerManagerEvent::SetMaxConnections { max: 5 };
+    mgr.poll_event(set_max).await;
+    assert_eq!(mgr.inner.config.max_connections, 5, "should update max");
+
+    let remote_pubkey = make_pubkey();
+    let remote_addr = make_multiaddr(2077, Some(remote_pubkey.peer_id()));
+
+    let sess_ctx = SessionContext::make(
+        SessionId::new(99),
+        remote_addr,
+        SessionType::Outbound,
+        remote_pubkey.clone(),
+    );
+    let new_session = PeerManagerEvent::NewSession {
+        pid:    remote_pubkey.peer_id(),
+        pubkey: remote_pubkey.clone(),
+        ctx:    sess_ctx.arced(),
+    };
+    mgr.poll_event(new_session).await;
+
+    let inner = mgr.core_inner();
+    assert_eq!(inner.connected(), 5, "should keep existing sessions");
+
+    let conn_event = conn_rx.next().await.expect("should have disconnect event");
+    match conn_event {
+        ConnectionEvent::Disconnect(sid) => assert_eq!(sid, 99.into(), "should be new session id"),
+        _ => panic!("should be disconnect event"),
+    }
+}
+
 #[tokio::test]
 async fn should_remove_connecting_even_if_session_is_reject_due_to_reach_max_connections_on_new_session(
 ) {
@@ -1196,6 +1238,118 @@ async fn should_give_up_peer_for_discovery_on_misbehave() {
     );
 }
 
+#[tokio::test]
+async fn should_decrease_peer_score_for_duplicate_tx_resent_on_misbehave() {
+    let (mut mgr, _conn_rx) = make_manager(0, 20);
+    let remote_peers = make_sessions(&mut mgr, 1, 5000).await;
+
+    let test_peer = remote_peers.first().expect("get first peer");
+    let duplicate_tx = |tx_hash: &Hash| PeerManagerEvent::Misbehave {
+        pid:  test_peer.owned_id(),
+        kind: MisbehaviorKind::DuplicateTx(tx_hash.clone()),
+    };
+    let tx_hash = Hash::digest(Bytes::from("tx"));
+
+    // Gossiped by others before, honest
+    mgr.poll_event(duplicate_tx(&tx_hash)).await;
+    mgr.poll_event(duplicate_tx(&Hash::digest(Bytes::from("other tx"))))
+        .await;
+    assert_eq!(test_peer.score(), MAX_PEER_SCORE, "should keep score");
+
+    mgr.poll_event(duplicate_tx(&tx_hash)).await;
+
+    let inner = mgr.core_inner();
+    assert_eq!(inner.connected(), 1, "should keep session");
+    assert!(test_peer.score() < MAX_PEER_SCORE, "should decrease score");
+    assert!(!test_peer.is_banned(), "should not ban peer");
+}
+
+#[tokio::test]
+async fn should_ban_and_disconnect_peer_once_score_run_out_on_misbehave() {
+    let (mut mgr, mut conn_rx) = make_manager(0, 20);
+    let remote_peers = make_sessions(&mut mgr, 1, 5000).await;
+
+    let test_peer = remote_peers.first().expect("get first peer");
+    let expect_sid = test_peer.session_id();
+    for _ in 0..2 {
+        let peer_misbehave = PeerManagerEvent::Misbehave {
+            pid:  test_peer.owned_id(),
+            kind: MisbehaviorKind::InvalidBlock,
+        };
+        mgr.poll_event(peer_misbehave).await;
+    }
+
+    let inner = mgr.core_inner();
+    assert_eq!(inner.connected(), 0, "should disconnect session");
+    assert!(test_peer.is_banned(), "should ban peer");
+    assert!(test_peer.banned_until() >= time::now() + PEER_BAN_DURATION - 1);
+    assert_eq!(test_peer.score(), MAX_PEER_SCORE, "should reset score");
+
+    let conn_event = conn_rx.next().await.expect("should have disconnect event");
+    match conn_event {
+        ConnectionEvent::Disconnect(sid) => {
+            assert_eq!(sid, expect_sid, "should disconnect session")
+        }
+        _ => panic!("should be disconnect event"),
+    }
+    assert!(
+        mgr.core_inner().connectable_peers(10).is_empty(),
+        "should not connect banned peer"
+    );
+}
+
+#[tokio::test]
+async fn should_reject_banned_peer_on_new_session() {
+    let (mut mgr, mut conn_rx) = make_manager(0, 20);
+
+    let remote_pubkey = make_pubkey();
+    let remote_peer = ArcPeer::from_pubkey(remote_pubkey.clone()).expect("make peer");
+    remote_peer.ban(PEER_BAN_DURATION);
+    mgr.core_inner().add_peer(remote_peer.clone());
+
+    let remote_addr = make_multiaddr(2077, Some(remote_pubkey.peer_id()));
+    let sess_ctx = SessionContext::make(
+        SessionId::new(99),
+        remote_addr,
+        SessionType::Outbound,
+        remote_pubkey.clone(),
+    );
+    let new_session = PeerManagerEvent::NewSession {
+        pid:    remote_pubkey.peer_id(),
+        pubkey: remote_pubkey,
+        ctx:    sess_ctx.arced(),
+    };
+    mgr.poll_event(new_session).await;
+
+    let inner = mgr.core_inner();
+    assert_eq!(inner.connected(), 0, "should reject banned peer");
+
+    let conn_event = conn_rx.next().await.expect("should have disconnect event");
+    match conn_event {
+        ConnectionEvent::Disconnect(sid) => assert_eq!(sid, 99.into(), "should be new session id"),
+        _ => panic!("should be disconnect event"),
+    }
+}
+
+#[tokio::test]
+async fn should_report_peer_scores_from_manager_handle() {
+    let (mut mgr, _conn_rx) = make_manager(0, 20);
+    let remote_peers = make_sessions(&mut mgr, 2, 5000).await;
+
+    let test_peer = remote_peers.first().expect("get first peer");
+    test_peer.punish(10);
+
+    let scores = mgr.inner.handle().peer_scores();
+    assert_eq!(scores.len(), 2, "should include all peers");
+
+    let test_score = scores
+        .iter()
+        .find(|s| s.peer_id == test_peer.id.to_base58())
+        .expect("should have test peer score");
+    assert_eq!(test_score.score, MAX_PEER_SCORE - 10);
+    assert!(test_score.chain_addr.is_some(), "should have chain addr");
+}
+
 #[tokio::test]
 async fn should_mark_session_blocked_on_session_blocked() {
     let (mut mgr, _conn_rx) = make_manager(0, 20);
@@ -1220,6 +1374,44 @@ async fn should_mark_session_blocked_on_session_blocked() {
     assert!(session.is_blocked(), "should be blocked");
 }
 
+#[tokio::test]
+async fn should_evict_session_exceeds_max_pending_data_size_on_session_blocked() {
+    let (mut mgr, mut conn_rx) = make_manager(0, 20);
+    let remote_peers = make_sessions(&mut mgr, 1, 5000).await;
+
+    let test_peer = remote_peers.first().expect("get first peer");
+    let expect_sid = test_peer.session_id();
+    let sess_ctx = SessionContext::make(
+        expect_sid,
+        test_peer.multiaddrs.all_raw().pop().expect("get multiaddr"),
+        SessionType::Outbound,
+        test_peer.owned_pubkey().expect("pubkey"),
+    );
+    sess_ctx.set_pending_data_size(1025);
+
+    let session_blocked = PeerManagerEvent::SessionBlocked {
+        ctx: sess_ctx.arced(),
+    };
+    mgr.poll_event(session_blocked).await;
+
+    let inner = mgr.core_inner();
+    assert_eq!(inner.connected(), 0, "should evict session");
+    assert_eq!(
+        test_peer.connectedness(),
+        Connectedness::CanConnect,
+        "should be able to connect again"
+    );
+    assert!(!test_peer.is_banned(), "should not ban slow peer");
+
+    let conn_event = conn_rx.next().await.expect("should have disconnect event");
+    match conn_event {
+        ConnectionEvent::Disconnect(sid) => {
+            assert_eq!(sid, expect_sid, "should disconnect session")
+        }
+        _ => panic!("should be disconnect event"),
+    }
+}
+
 #[tokio::test]
 async fn should_try_all_peer_multiaddrs_on_connect_peers_now() {
     let (mut mgr, mut conn_rx) = make_manager(0, 20);
@@ -1448,6 +1640,56 @@ async fn should_add_multiaddrs_to_peer_on_identified_addrs() {
     );
 }
 
+#[tokio::test]
+async fn should_set_role_of_peer_on_identified_role() {
+    let (mut mgr, _conn_rx) = make_manager(0, 20);
+    let remote_peers = make_sessions(&mut mgr, 1, 5000).await;
+    let test_peer = remote_peers.first().expect("get first");
+    assert_eq!(
+        test_peer.role(),
+        None,
+        "should not have role before identify"
+    );
+
+    let identified_role = PeerManagerEvent::IdentifiedRole {
+        pid:  test_peer.owned_id(),
+        role: NodeRole::Seed,
+    };
+    mgr.poll_event(identified_role).await;
+
+    assert_eq!(test_peer.role(), Some(NodeRole::Seed), "should set role");
+    let scores = mgr.inner.handle().peer_scores();
+    assert_eq!(
+        scores[0].role,
+        Some("seed".to_owned()),
+        "should report role"
+    );
+}
+
+#[tokio::test]
+async fn should_set_protocol_of_peer_on_identified_protocol() {
+    let (mut mgr, _conn_rx) = make_manager(0, 20);
+    let remote_peers = make_sessions(&mut mgr, 1, 5000).await;
+    let test_peer = remote_peers.first().expect("get first");
+    assert_eq!(
+        test_peer.protocol(),
+        PeerProtocol::default(),
+        "should be of version 0 before identify"
+    );
+
+    let identified_protocol = PeerManagerEvent::IdentifiedProtocol {
+        pid:      test_peer.owned_id(),
+        protocol: PeerProtocol::local(),
+    };
+    mgr.poll_event(identified_protocol).await;
+
+    assert_eq!(
+        test_peer.protocol(),
+        PeerProtocol::local(),
+        "should set protocol"
+    );
+}
+
 #[tokio::test]
 async fn should_push_id_to_multiaddrs_if_not_included_on_identified_addrs() {
     let (mut mgr, _conn_rx) = make_manager(0, 20);
@@ -1858,11 +2100,15 @@ async fn should_never_expire_peers_from_config_whitelist() {
         our_id: manager_id,
         pubkey: manager_pubkey,
         bootstraps,
+        private_peers: Default::default(),
+        relay_peers: Default::default(),
         whitelist_by_chain_addrs: vec![test_chain_addr.clone()],
         whitelist_peers_only: false,
         max_connections: 10,
         routine_interval: Duration::from_secs(10),
         peer_dat_file,
+        max_pending_data_size: 1024,
+        max_blocked_duration: Duration::from_secs(60),
     };
 
     let (conn_tx, _conn_rx) = unbounded();
@@ -1906,11 +2152,15 @@ async fn should_not_refresh_never_expired_peers_from_config_whitelist() {
         our_id: manager_id,
         pubkey: manager_pubkey,
         bootstraps,
+        private_peers: Default::default(),
+        relay_peers: Default::default(),
         whitelist_by_chain_addrs: vec![test_chain_addr.clone()],
         whitelist_peers_only: false,
         max_connections: 10,
         routine_interval: Duration::from_secs(10),
         peer_dat_file,
+        max_pending_data_size: 1024,
+        max_blocked_duration: Duration::from_secs(60),
     };
 
     let (conn_tx, _conn_rx) = unbounded();
@@ -1965,11 +2215,15 @@ async fn should_only_connect_peers_in_whitelist_if_whitelist_only_enabled() {
         our_id: manager_id,
         pubkey: manager_pubkey,
         bootstraps: Default::default(),
+        private_peers: Default::default(),
+        relay_peers: Default::default(),
         whitelist_by_chain_addrs: vec![test_chain_addr.clone()],
         whitelist_peers_only: true,
         max_connections: 10,
         routine_interval: Duration::from_secs(10),
         peer_dat_file,
+        max_pending_data_size: 1024,
+        max_blocked_duration: Duration::from_secs(60),
     };
 
     let (conn_tx, mut conn_rx) = unbounded();
@@ -2024,11 +2278,15 @@ async fn should_only_allow_incoming_peers_in_whitelist_if_whitelist_only_enabled
         our_id: manager_id,
         pubkey: manager_pubkey,
         bootstraps: Default::default(),
+        private_peers: Default::default(),
+        relay_peers: Default::default(),
         whitelist_by_chain_addrs: vec![test_chain_addr.clone()],
         whitelist_peers_only: true,
         max_connections: 10,
         routine_interval: Duration::from_secs(10),
         peer_dat_file,
+        max_pending_data_size: 1024,
+        max_blocked_duration: Duration::from_secs(60),
     };
 
     let (conn_tx, _conn_rx) = unbounded();
@@ -2086,3 +2344,216 @@ async fn should_only_allow_incoming_peers_in_whitelist_if_whitelist_only_enabled
 
     assert_eq!(inner.connected(), 1, "should have 1 connection");
 }
+
+#[tokio::test]
+async fn should_replace_expirable_whitelisted_peer_on_allow_peer() {
+    let (mut mgr, _conn_rx) = make_manager(0, 20);
+    let test_peer = make_peer(2077);
+    let test_chain_addr = test_peer.owned_chain_addr().expect("chain addr");
+
+    let inner = mgr.core_inner();
+    inner.whitelist_peers_by_chain_addr(vec![test_chain_addr.clone()]);
+    assert!(mgr.inner.handle().allowlist().is_empty());
+
+    let allow_peer = PeerManagerEvent::AllowPeer {
+        chain_addr: test_chain_addr.clone(),
+    };
+    mgr.poll_event(allow_peer).await;
+
+    let whitelist = inner.whitelist();
+    let allowed = whitelist.get(&test_chain_addr).expect("should be allowed");
+    assert_eq!(allowed.expire_time(), TestExpireTime::Never);
+    assert_eq!(mgr.inner.handle().allowlist(), vec![test_chain_addr]);
+}
+
+#[tokio::test]
+async fn should_disconnect_peer_on_disallow_peer_if_whitelist_only_enabled() {
+    let manager_pubkey = make_pubkey();
+    let manager_id = manager_pubkey.peer_id();
+    let mut peer_dat_file = std::env::temp_dir();
+    peer_dat_file.push("peer.dat");
+
+    let test_peer = make_peer(2077);
+    let test_chain_addr = test_peer.owned_chain_addr().expect("chain addr");
+
+    let config = PeerManagerConfig {
+        our_id: manager_id,
+        pubkey: manager_pubkey,
+        bootstraps: Default::default(),
+        private_peers: Default::default(),
+        relay_peers: Default::default(),
+        whitelist_by_chain_addrs: vec![test_chain_addr.clone()],
+        whitelist_peers_only: true,
+        max_connections: 10,
+        routine_interval: Duration::from_secs(10),
+        peer_dat_file,
+        max_pending_data_size: 1024,
+        max_blocked_duration: Duration::from_secs(60),
+    };
+
+    let (conn_tx, mut conn_rx) = unbounded();
+    let (mgr_tx, mgr_rx) = unbounded();
+    let manager = PeerManager::new(config, mgr_rx, conn_tx);
+    let inner = manager.inner();
+    let mut manager = MockManager::new(manager, mgr_tx);
+
+    let sess_ctx = SessionContext::make(
+        SessionId::new(666),
+        test_peer
+            .multiaddrs
+            .all_raw()
+            .pop()
+            .expect("peer multiaddr"),
+        SessionType::Inbound,
+        test_peer.owned_pubkey().expect("pubkey"),
+    );
+    let new_session = PeerManagerEvent::NewSession {
+        pid:    test_peer.owned_id(),
+        pubkey: test_peer.owned_pubkey().expect("pubkey"),
+        ctx:    sess_ctx.arced(),
+    };
+    manager.poll_event(new_session).await;
+    assert_eq!(inner.connected(), 1, "should have 1 connection");
+
+    let disallow_peer = PeerManagerEvent::DisallowPeer {
+        chain_addr: test_chain_addr.clone(),
+    };
+    manager.poll_event(disallow_peer).await;
+
+    assert!(!inner.whitelisted_by_chain_addr(&test_chain_addr));
+    assert_eq!(inner.connected(), 0, "should disconnect disallowed peer");
+
+    let conn_event = conn_rx.next().await.expect("should have disconnect event");
+    match conn_event {
+        ConnectionEvent::Disconnect(sid) => assert_eq!(sid, 666.into(), "should be test session"),
+        _ => panic!("should be disconnect event"),
+    }
+}
+
+#[tokio::test]
+async fn should_pick_peers_from_every_kbucket_on_connectable_peers() {
+    let (mgr, _conn_rx) = make_manager(0, 20);
+    let our_id = mgr.inner.peer_id.to_owned();
+    let peers = (0..100)
+        .map(|port| make_peer(port + 6000))
+        .collect::<Vec<_>>();
+
+    let inner = mgr.core_inner();
+    for peer in peers.iter() {
+        inner.add_peer(peer.clone());
+    }
+
+    let bucket_of = |p: &ArcPeer| kbucket::bucket_index(&our_id, &p.id);
+    let all_buckets = peers.iter().map(bucket_of).collect::<HashSet<_>>();
+    assert!(
+        all_buckets.len() < 30,
+        "random ids should fall into few buckets"
+    );
+
+    let picked = inner.connectable_peers(30);
+    assert_eq!(picked.len(), 30, "should pick 30 peers");
+    assert_eq!(
+        picked.iter().map(bucket_of).collect::<HashSet<_>>(),
+        all_buckets,
+        "should pick at least one peer from every bucket"
+    );
+    assert_eq!(kbucket::bucket_index(&our_id, &our_id), None);
+}
+
+#[tokio::test]
+async fn should_only_save_known_good_peers() {
+    let (mgr, _conn_rx) = make_manager(0, 20);
+    let never_connected = make_peer(2077);
+    let connected = make_peer(2078);
+    let given_up = make_peer(2079);
+
+    connected.set_connected_at(time::now());
+    given_up.set_connected_at(time::now());
+    given_up.set_connectedness(Connectedness::Unconnectable);
+
+    let inner = mgr.core_inner();
+    inner.add_peer(never_connected);
+    inner.add_peer(connected.clone());
+    inner.add_peer(given_up);
+
+    let known_good = inner.known_good_peers();
+    assert_eq!(known_good.len(), 1, "should have 1 known good peer");
+    assert_eq!(known_good[0].id, connected.id, "should be connected peer");
+}
+
+#[tokio::test]
+async fn should_keep_private_peers_unshared_and_unpunished() {
+    let manager_pubkey = make_pubkey();
+    let manager_id = manager_pubkey.peer_id();
+    let mut peer_dat_file = std::env::temp_dir();
+    peer_dat_file.push("peer.dat");
+
+    let private_peer = make_peer(2077);
+    let config = PeerManagerConfig {
+        our_id: manager_id,
+        pubkey: manager_pubkey,
+        bootstraps: Default::default(),
+        private_peers: vec![private_peer.clone()],
+        relay_peers: vec![private_peer.owned_id()],
+        whitelist_by_chain_addrs: Default::default(),
+        whitelist_peers_only: false,
+        max_connections: 1,
+        routine_interval: Duration::from_secs(10),
+        peer_dat_file,
+        max_pending_data_size: 1024,
+        max_blocked_duration: Duration::from_secs(60),
+    };
+
+    let (conn_tx, _conn_rx) = unbounded();
+    let (mgr_tx, mgr_rx) = unbounded();
+    let manager = PeerManager::new(config, mgr_rx, conn_tx);
+    let handle = manager.handle();
+    let inner = manager.inner();
+    assert!(inner.is_private(&private_peer.id), "should be private");
+    assert!(inner.is_relay(&private_peer.id), "should be relay");
+
+    let mut manager = MockManager::new(manager, mgr_tx);
+    let other_peers = make_sessions(&mut manager, 1, 5000).await;
+    let other_peer = other_peers.first().expect("get first peer");
+    assert_eq!(inner.connected(), 1, "should reach max connections");
+
+    // Private peer bypasses max connections
+    let sess_ctx = SessionContext::make(
+        SessionId::new(99),
+        private_peer
+            .multiaddrs
+            .all_raw()
+            .pop()
+            .expect("peer multiaddr"),
+        SessionType::Outbound,
+        private_peer.owned_pubkey().expect("pubkey"),
+    );
+    let new_session = PeerManagerEvent::NewSession {
+        pid:    private_peer.owned_id(),
+        pubkey: private_peer.owned_pubkey().expect("pubkey"),
+        ctx:    sess_ctx.arced(),
+    };
+    manager.poll_event(new_session).await;
+    assert_eq!(inner.connected(), 2, "should accept private peer");
+
+    for _ in 0..10 {
+        let peer_misbehave = PeerManagerEvent::Misbehave {
+            pid:  private_peer.owned_id(),
+            kind: MisbehaviorKind::InvalidBlock,
+        };
+        manager.poll_event(peer_misbehave).await;
+    }
+    assert!(!private_peer.is_banned(), "should not ban private peer");
+    assert_eq!(inner.connected(), 2, "should keep private peer");
+
+    let private_addrs = private_peer.multiaddrs.all_raw();
+    let addrs = handle.random_addrs(100);
+    assert!(
+        !addrs.iter().any(|a| private_addrs.contains(a)),
+        "should not share private peer"
+    );
+    assert!(
+        addrs.contains(&other_peer.multiaddrs.all_raw().pop().expect("addr")),
+        "should share other peer"
+    );
+}
@@ -11,15 +11,22 @@ use std::{
 };
 
 use async_trait::async_trait;
-use futures::{channel::mpsc::UnboundedReceiver, future::TryFutureExt, pin_mut, stream::Stream};
-use log::warn;
+use futures::{
+    channel::mpsc::{UnboundedReceiver, UnboundedSender},
+    future::TryFutureExt,
+    pin_mut,
+    stream::Stream,
+};
+use log::{error, warn};
 use protocol::{
-    traits::{Context, MessageCodec, MessageHandler},
+    traits::{Context, MessageCodec, MessageHandler, TraceContext},
     Bytes, ProtocolError,
 };
+use tentacle::secio::PeerId;
 
 use crate::{
     endpoint::{Endpoint, EndpointScheme, RpcEndpoint},
+    event::{MisbehaviorKind, PeerManagerEvent},
     message::SessionMessage,
     rpc::RpcResponse,
     rpc_map::RpcMap,
@@ -30,6 +37,7 @@ pub struct Reactor<M> {
     smsg_rx: UnboundedReceiver<SessionMessage>,
     handler: Arc<Box<dyn MessageHandler<Message = M>>>,
     rpc_map: Arc<RpcMap>,
+    mgr_tx:  UnboundedSender<PeerManagerEvent>,
 }
 
 impl<M> Reactor<M>
@@ -40,19 +48,26 @@ where
         smsg_rx: UnboundedReceiver<SessionMessage>,
         boxed_handler: Box<dyn MessageHandler<Message = M>>,
         rpc_map: Arc<RpcMap>,
+        mgr_tx: UnboundedSender<PeerManagerEvent>,
     ) -> Self {
         Reactor {
             smsg_rx,
             handler: Arc::new(boxed_handler),
             rpc_map,
+            mgr_tx,
         }
     }
 
-    pub fn rpc_resp(smsg_rx: UnboundedReceiver<SessionMessage>, rpc_map: Arc<RpcMap>) -> Self {
+    pub fn rpc_resp(
+        smsg_rx: UnboundedReceiver<SessionMessage>,
+        rpc_map: Arc<RpcMap>,
+        mgr_tx: UnboundedSender<PeerManagerEvent>,
+    ) -> Self {
         Reactor {
             smsg_rx,
             handler: Arc::new(Box::new(DummyHandler::new())),
             rpc_map,
+            mgr_tx,
         }
     }
 
@@ -65,32 +80,51 @@ where
             msg: net_msg,
             pid,
             connected_addr,
+            memory,
             ..
         } = smsg;
 
         let endpoint = net_msg.url.to_owned();
+        let mgr_tx = self.mgr_tx.clone();
+        let remote_pid = pid.clone();
+        let undecodable = move |err: ProtocolError| -> ProtocolError {
+            report_undecodable(&mgr_tx, remote_pid.clone());
+            err
+        };
+
         let mut ctx = Context::new().set_session_id(sid).set_remote_peer_id(pid);
         if let Some(ref connected_addr) = connected_addr {
             ctx = ctx.set_remote_connected_addr(connected_addr.clone());
         }
+        if let Some(trace_id) = net_msg.trace_id() {
+            ctx = ctx.with_trace_id(trace_id);
+        }
 
         let react = async move {
+            // Released once the message is handled
+            let _memory = memory;
             let endpoint = net_msg.url.parse::<Endpoint>()?;
 
             match endpoint.scheme() {
                 EndpointScheme::Gossip => {
-                    let content = M::decode(Bytes::from(net_msg.content)).await?;
+                    let content = M::decode(Bytes::from(net_msg.content))
+                        .await
+                        .map_err(&undecodable)?;
                     handler.process(ctx, content).await
                 }
                 EndpointScheme::RpcCall => {
-                    let content = M::decode(Bytes::from(net_msg.content)).await?;
+                    let content = M::decode(Bytes::from(net_msg.content))
+                        .await
+                        .map_err(&undecodable)?;
                     let rpc_endpoint = RpcEndpoint::try_from(endpoint)?;
 
                     let ctx = ctx.set_rpc_id(rpc_endpoint.rpc_id().value());
                     handler.process(ctx, content).await
                 }
                 EndpointScheme::RpcResponse => {
-                    let content = RpcResponse::decode(Bytes::from(net_msg.content)).await?;
+                    let content = RpcResponse::decode(Bytes::from(net_msg.content))
+                        .await
+                        .map_err(&undecodable)?;
                     let rpc_endpoint = RpcEndpoint::try_from(endpoint)?;
                     let rpc_id = rpc_endpoint.rpc_id().value();
 
@@ -121,6 +155,23 @@ where
     }
 }
 
+// Remote peer sends us message we can't decode, peer manager will punish it
+pub(crate) fn report_undecodable(mgr_tx: &UnboundedSender<PeerManagerEvent>, pid: PeerId) {
+    report_misbehave(mgr_tx, pid, MisbehaviorKind::UndecodableMessage)
+}
+
+pub(crate) fn report_misbehave(
+    mgr_tx: &UnboundedSender<PeerManagerEvent>,
+    pid: PeerId,
+    kind: MisbehaviorKind,
+) {
+    let misbehave = PeerManagerEvent::Misbehave { pid, kind };
+
+    if mgr_tx.unbounded_send(misbehave).is_err() {
+        error!("network: reactor: peer manager offline");
+    }
+}
+
 impl<M> Future for Reactor<M>
 where
     M: MessageCodec,
@@ -6,6 +6,7 @@ use std::{
     task::{Context, Poll},
 };
 
+use common_metrics::{MemoryAccount, NETWORK_DROPPED_OVERSIZED_MESSAGES, NETWORK_RECEIVED_BYTES};
 use futures::{
     channel::mpsc::{UnboundedReceiver, UnboundedSender},
     future::TryFutureExt,
@@ -15,17 +16,38 @@ use futures::{
 use log::{error, warn};
 use parking_lot::RwLock;
 
+use super::{report_misbehave, report_undecodable};
 use crate::{
     endpoint::Endpoint,
     error::{ErrorKind, NetworkError},
+    event::{MisbehaviorKind, PeerManagerEvent},
+    lane::Lane,
     message::{NetworkMessage, RawSessionMessage, SessionMessage},
-    traits::{Compression, SessionBook},
+    relay::ConsensusRelay,
+    traits::{Compression, MessageSender, SessionBook},
 };
 
-pub struct MessageRouter<C, S> {
+// Bytes of url and trace id around content of a network message
+const MESSAGE_OVERHEAD: usize = 1024;
+
+pub struct MessageRouter<C, S, M> {
     // Endpoint to reactor channel map
     reactor_map: Arc<RwLock<HashMap<Endpoint, UnboundedSender<SessionMessage>>>>,
 
+    // Endpoint to max decompressed message content size map, endpoint
+    // without limit is only bounded by max frame length.
+    size_limits: Arc<RwLock<HashMap<Endpoint, usize>>>,
+
+    // Messages are decompressed up to the max frame length, or the largest
+    // size limit if it's larger
+    max_frame_length: usize,
+
+    // Memory of messages routed but not handled yet. Messages of consensus
+    // and sync have their own budget, gossip of transactions reaching its
+    // cap doesn't drop votes or pulled blocks.
+    memory:           MemoryAccount,
+    consensus_memory: MemoryAccount,
+
     // Receiver for compressed session message
     raw_msg_rx: UnboundedReceiver<RawSessionMessage>,
 
@@ -35,29 +57,45 @@ pub struct MessageRouter<C, S> {
     // Session book
     sessions: S,
 
+    // Relay consensus messages for sentries and validators behind them
+    relay: ConsensusRelay<S, M>,
+
     // Fatal system error reporter
     sys_tx: UnboundedSender<NetworkError>,
+
+    // Misbehavior reporter
+    mgr_tx: UnboundedSender<PeerManagerEvent>,
 }
 
-impl<C, S> MessageRouter<C, S>
+impl<C, S, M> MessageRouter<C, S, M>
 where
     C: Compression + Send + Unpin + Clone + 'static,
-    S: SessionBook + Send + Unpin + Clone + 'static,
+    S: SessionBook + Send + Sync + Unpin + Clone + 'static,
+    M: MessageSender + Send + Sync + Unpin + Clone + 'static,
 {
     pub fn new(
         raw_msg_rx: UnboundedReceiver<RawSessionMessage>,
         compression: C,
+        max_frame_length: usize,
         sessions: S,
+        sender: M,
         sys_tx: UnboundedSender<NetworkError>,
+        mgr_tx: UnboundedSender<PeerManagerEvent>,
     ) -> Self {
         MessageRouter {
             reactor_map: Default::default(),
+            size_limits: Default::default(),
+            max_frame_length,
+            memory: MemoryAccount::new("network"),
+            consensus_memory: MemoryAccount::new("network_consensus"),
 
             raw_msg_rx,
             compression,
+            relay: ConsensusRelay::new(sessions.clone(), sender),
             sessions,
 
             sys_tx,
+            mgr_tx,
         }
     }
 
@@ -69,30 +107,114 @@ where
         self.reactor_map.write().insert(endpoint, smsg_tx);
     }
 
+    pub fn set_size_limit(&mut self, endpoint: Endpoint, limit: usize) {
+        self.size_limits.write().insert(endpoint, limit);
+    }
+
+    /// Limit bytes of messages being handled, zero removes the limit.
+    /// Messages received while the limit is reached are dropped.
+    pub fn set_memory_cap(&mut self, cap: usize) {
+        self.memory.set_cap(cap);
+    }
+
+    /// Limit bytes of consensus and sync messages being handled, they're not
+    /// counted by the cap of other messages.
+    pub fn set_consensus_memory_cap(&mut self, cap: usize) {
+        self.consensus_memory.set_cap(cap);
+    }
+
     pub fn route_raw_message(&self, raw_msg: RawSessionMessage) -> impl Future<Output = ()> {
         let reactor_map = Arc::clone(&self.reactor_map);
+        let size_limits = Arc::clone(&self.size_limits);
+        let max_frame_length = self.max_frame_length;
+        let memory = self.memory.clone();
+        let consensus_memory = self.consensus_memory.clone();
         let compression = self.compression.clone();
         let sessions = self.sessions.clone();
+        let relay = self.relay.clone();
         let sys_tx = self.sys_tx.clone();
+        let mgr_tx = self.mgr_tx.clone();
 
         let route = async move {
-            let des_msg = compression.decompress(raw_msg.msg)?;
-            let net_msg = NetworkMessage::decode(des_msg).await?;
+            let RawSessionMessage { sid, pid, msg } = raw_msg;
+            let raw_size = msg.len();
+            NETWORK_RECEIVED_BYTES.inc_by(raw_size as i64);
+
+            let max_size = size_limits
+                .read()
+                .values()
+                .cloned()
+                .fold(max_frame_length, usize::max);
+            let decoded = async {
+                let des_msg = compression.decompress(msg.clone(), max_size + MESSAGE_OVERHEAD)?;
+                NetworkMessage::decode(des_msg).await
+            };
+            let net_msg = match decoded.await {
+                Ok(net_msg) => net_msg,
+                Err(err) => {
+                    report_undecodable(&mgr_tx, pid);
+                    return Err(err);
+                }
+            };
 
-            let reactor_map = reactor_map.read();
             let endpoint = net_msg.url.parse::<Endpoint>()?;
+            Lane::of(&endpoint).record_received(raw_size);
+
+            let size = net_msg.content.len();
+            let opt_limit = size_limits.read().get(&endpoint).cloned();
+
+            if let Some(limit) = opt_limit.filter(|limit| size > *limit) {
+                let endpoint = endpoint.root();
+                NETWORK_DROPPED_OVERSIZED_MESSAGES
+                    .with_label_values(&[&endpoint])
+                    .inc();
+                report_misbehave(&mgr_tx, pid, MisbehaviorKind::OversizedMessage);
+
+                return Err(ErrorKind::OversizedMessage {
+                    endpoint,
+                    size,
+                    limit,
+                }
+                .into());
+            }
+
+            // Sentries don't run consensus, no reactor handles relayed messages
+            let relayed = Lane::of(&endpoint) == Lane::Consensus && relay.relay(sid, msg);
 
-            let opt_smsg_tx = reactor_map.get(&endpoint).cloned();
-            let smsg_tx = opt_smsg_tx.ok_or_else(|| ErrorKind::NoReactor(endpoint.root()))?;
+            let memory = match Lane::of(&endpoint) {
+                Lane::Consensus | Lane::Sync => consensus_memory,
+                Lane::Transaction | Lane::Other => memory,
+            };
+            let memory = match memory.try_reserve(size) {
+                Some(memory) => memory,
+                None => {
+                    warn!(
+                        "network: drop {} message from {:?}, memory cap {} reached",
+                        endpoint.root(),
+                        pid,
+                        memory.cap()
+                    );
+                    return Ok(());
+                }
+            };
+
+            let reactor_map = reactor_map.read();
+
+            let smsg_tx = match reactor_map.get(&endpoint).cloned() {
+                Some(smsg_tx) => smsg_tx,
+                None if relayed => return Ok(()),
+                None => return Err(ErrorKind::NoReactor(endpoint.root()).into()),
+            };
 
             // Peer may disconnect when we try to fetch its connected address.
             // This connected addr is mainly for debug purpose, so no error.
-            let connected_addr = sessions.connected_addr(raw_msg.sid);
+            let connected_addr = sessions.connected_addr(sid);
             let smsg = SessionMessage {
-                sid: raw_msg.sid,
-                pid: raw_msg.pid,
+                sid,
+                pid,
                 msg: net_msg,
                 connected_addr,
+                memory,
             };
 
             if smsg_tx.unbounded_send(smsg).is_err() {
@@ -109,10 +231,11 @@ where
     }
 }
 
-impl<C, S> Future for MessageRouter<C, S>
+impl<C, S, M> Future for MessageRouter<C, S, M>
 where
     C: Compression + Send + Unpin + Clone + 'static,
-    S: SessionBook + Send + Unpin + Clone + 'static,
+    S: SessionBook + Send + Sync + Unpin + Clone + 'static,
+    M: MessageSender + Send + Sync + Unpin + Clone + 'static,
 {
     type Output = ();
 
@@ -0,0 +1,131 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+use log::{debug, warn};
+use parking_lot::Mutex;
+use protocol::{traits::Priority, types::Hash, Bytes};
+use tentacle::{service::TargetSession, SessionId};
+
+use crate::{
+    lane::Lane,
+    traits::{MessageSender, SessionBook},
+};
+
+// Hashes of consensus messages relayed recently
+const DEFAULT_SEEN_CAPACITY: usize = 4096;
+
+/// Relay consensus messages between sentries and the validator behind them.
+/// Messages from relay peers are relayed to other peers, messages from other
+/// peers are relayed to relay peers. Each message is relayed at most once,
+/// so that sentries linked to each other don't loop them.
+#[derive(Clone)]
+pub struct ConsensusRelay<S, M> {
+    sessions: S,
+    sender:   M,
+    seen:     Arc<Mutex<SeenMessages>>,
+}
+
+impl<S, M> ConsensusRelay<S, M>
+where
+    S: SessionBook,
+    M: MessageSender,
+{
+    pub fn new(sessions: S, sender: M) -> Self {
+        ConsensusRelay {
+            sessions,
+            sender,
+            seen: Arc::new(Mutex::new(SeenMessages::new(DEFAULT_SEEN_CAPACITY))),
+        }
+    }
+
+    /// Relay raw compressed message received from the session, return false
+    /// if there's no peer to relay it to. Messages relayed before are
+    /// dropped.
+    pub fn relay(&self, sid: SessionId, raw_msg: Bytes) -> bool {
+        let relays = self.sessions.relays();
+        let targets = if relays.contains(&sid) {
+            let mut sendable = self.sessions.all_sendable();
+            sendable.retain(|s| *s != sid);
+            sendable
+        } else {
+            relays
+        };
+
+        if targets.is_empty() {
+            return false;
+        }
+        if !self.seen.lock().insert(Hash::digest(raw_msg.clone())) {
+            return true;
+        }
+
+        debug!(
+            "network: relay consensus message to {} sessions",
+            targets.len()
+        );
+        Lane::Consensus.record_sent(raw_msg.len());
+        if let Err(err) = self
+            .sender
+            .send(TargetSession::Multi(targets), raw_msg, Priority::High)
+        {
+            warn!("network: relay consensus message {}", err);
+        }
+
+        true
+    }
+}
+
+struct SeenMessages {
+    hashes:   HashSet<Hash>,
+    order:    VecDeque<Hash>,
+    capacity: usize,
+}
+
+impl SeenMessages {
+    fn new(capacity: usize) -> Self {
+        SeenMessages {
+            hashes: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Return false if the hash was seen, the oldest one is forgotten once
+    // capacity is reached.
+    fn insert(&mut self, hash: Hash) -> bool {
+        if self.hashes.contains(&hash) {
+            return false;
+        }
+
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+
+        self.hashes.insert(hash.clone());
+        self.order.push_back(hash);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::{types::Hash, Bytes};
+
+    use super::SeenMessages;
+
+    #[test]
+    fn test_seen_messages() {
+        let hash = |n: u8| Hash::digest(Bytes::from(vec![n]));
+        let mut seen = SeenMessages::new(2);
+
+        assert!(seen.insert(hash(1)));
+        assert!(!seen.insert(hash(1)));
+        assert!(seen.insert(hash(2)));
+
+        // Oldest one is forgotten
+        assert!(seen.insert(hash(3)));
+        assert!(seen.insert(hash(1)));
+        assert!(!seen.insert(hash(3)));
+    }
+}
@@ -0,0 +1,76 @@
+use derive_more::Display;
+use serde_derive::Deserialize;
+
+// Identify message of peers before roles, they run every subsystem
+const LEGACY_IDENTIFY: &[u8] = b"Identify message";
+
+/// Role of a node, it's advertised to peers in identify message.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeRole {
+    /// Participates in consensus, also executes and serves api
+    #[display(fmt = "validator")]
+    Validator,
+
+    /// Syncs and executes blocks, serves api
+    #[display(fmt = "full")]
+    Full,
+
+    /// Only relays peer addresses
+    #[display(fmt = "seed")]
+    Seed,
+}
+
+impl Default for NodeRole {
+    fn default() -> Self {
+        NodeRole::Validator
+    }
+}
+
+impl NodeRole {
+    pub fn identify_message(self) -> &'static [u8] {
+        match self {
+            NodeRole::Validator => b"muta/validator",
+            NodeRole::Full => b"muta/full",
+            NodeRole::Seed => b"muta/seed",
+        }
+    }
+
+    pub fn from_identify_message(msg: &[u8]) -> Option<Self> {
+        [NodeRole::Validator, NodeRole::Full, NodeRole::Seed]
+            .iter()
+            .find(|role| role.identify_message() == msg)
+            .cloned()
+            .or_else(|| {
+                if msg == LEGACY_IDENTIFY {
+                    Some(NodeRole::Validator)
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Whether the node runs consensus, only validators vote.
+    pub fn is_validator(self) -> bool {
+        self == NodeRole::Validator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NodeRole;
+
+    #[test]
+    fn test_identify_message() {
+        for role in [NodeRole::Validator, NodeRole::Full, NodeRole::Seed].iter() {
+            let msg = role.identify_message();
+            assert_eq!(NodeRole::from_identify_message(msg), Some(*role));
+        }
+
+        assert_eq!(
+            NodeRole::from_identify_message(b"Identify message"),
+            Some(NodeRole::Validator)
+        );
+        assert_eq!(NodeRole::from_identify_message(b"unknown"), None);
+    }
+}
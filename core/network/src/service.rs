@@ -7,6 +7,7 @@ use std::{
 };
 
 use async_trait::async_trait;
+use bytes::Bytes;
 use futures::{
     channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
     pin_mut,
@@ -15,36 +16,110 @@ use futures::{
 };
 use log::{debug, error, info};
 use protocol::{
-    traits::{Context, Gossip, MessageCodec, MessageHandler, Priority, Rpc},
+    traits::{
+        AdminAdapter, Context, Gossip, MessageCodec, MessageHandler, PeerMisbehavior, PeerReporter,
+        PeerScore, Priority, Rpc,
+    },
     types::Address,
     ProtocolResult,
 };
 
 use crate::{
     common::{socket_to_multi_addr, HeartBeat},
-    compression::Snappy,
+    compression::AutoCompression,
     connection::{
         ConnectionConfig, ConnectionService, ConnectionServiceControl, ConnectionServiceKeeper,
     },
+    dns_seed::DnsSeeds,
     endpoint::{Endpoint, EndpointScheme},
     error::NetworkError,
     event::{ConnectionEvent, PeerManagerEvent},
     message::RawSessionMessage,
     outbound::{NetworkGossip, NetworkRpc},
     peer_manager::{
-        DiscoveryAddrManager, IdentifyCallback, PeerManager, PeerManagerConfig, SharedSessions,
+        DiscoveryAddrManager, IdentifyCallback, PeerManager, PeerManagerConfig, PeerManagerHandle,
+        SharedSessions,
     },
     protocols::CoreProtocol,
     reactor::{MessageRouter, Reactor},
     rpc_map::RpcMap,
     selfcheck::SelfCheck,
+    traits::NetworkContext,
     NetworkConfig,
 };
 
+type ServiceControl = ConnectionServiceControl<CoreProtocol, SharedSessions>;
+
 #[derive(Clone)]
 pub struct NetworkServiceHandle {
-    gossip: NetworkGossip<ConnectionServiceControl<CoreProtocol, SharedSessions>, Snappy>,
-    rpc:    NetworkRpc<ConnectionServiceControl<CoreProtocol, SharedSessions>, Snappy>,
+    gossip: NetworkGossip<ServiceControl, AutoCompression, SharedSessions>,
+    rpc:    NetworkRpc<ServiceControl, AutoCompression, SharedSessions>,
+    mgr_tx: UnboundedSender<PeerManagerEvent>,
+
+    peer_mgr: PeerManagerHandle,
+}
+
+impl NetworkServiceHandle {
+    pub fn set_max_connections(&self, max: usize) -> ProtocolResult<()> {
+        let event = PeerManagerEvent::SetMaxConnections { max };
+        if self.mgr_tx.unbounded_send(event).is_err() {
+            return Err(NetworkError::Shutdown.into());
+        }
+
+        Ok(())
+    }
+}
+
+impl PeerReporter for NetworkServiceHandle {
+    fn report(&self, ctx: Context, misbehavior: PeerMisbehavior) {
+        let pid = match ctx.remote_peer_id() {
+            Ok(pid) => pid,
+            // Local message, nothing to report
+            Err(_) => return,
+        };
+
+        let event = PeerManagerEvent::Misbehave {
+            pid,
+            kind: misbehavior.clone().into(),
+        };
+        if self.mgr_tx.unbounded_send(event).is_err() {
+            error!("network: report {}: peer manager offline", misbehavior);
+        }
+    }
+}
+
+impl AdminAdapter for NetworkServiceHandle {
+    fn peer_scores(&self) -> Vec<PeerScore> {
+        self.peer_mgr.peer_scores()
+    }
+
+    fn allowlist(&self) -> Vec<String> {
+        let allowlist = self.peer_mgr.allowlist().into_iter();
+
+        allowlist.map(|chain_addr| chain_addr.as_hex()).collect()
+    }
+
+    fn allow_peer(&self, pubkey: Bytes) -> ProtocolResult<()> {
+        let chain_addr = Address::from_pubkey_bytes(pubkey)?;
+
+        let event = PeerManagerEvent::AllowPeer { chain_addr };
+        if self.mgr_tx.unbounded_send(event).is_err() {
+            return Err(NetworkError::Shutdown.into());
+        }
+
+        Ok(())
+    }
+
+    fn disallow_peer(&self, pubkey: Bytes) -> ProtocolResult<()> {
+        let chain_addr = Address::from_pubkey_bytes(pubkey)?;
+
+        let event = PeerManagerEvent::DisallowPeer { chain_addr };
+        if self.mgr_tx.unbounded_send(event).is_err() {
+            return Err(NetworkError::Shutdown.into());
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -114,17 +189,23 @@ pub struct NetworkService {
     config: NetworkConfig,
 
     // Public service components
-    gossip:  NetworkGossip<ConnectionServiceControl<CoreProtocol, SharedSessions>, Snappy>,
-    rpc:     NetworkRpc<ConnectionServiceControl<CoreProtocol, SharedSessions>, Snappy>,
+    gossip:  NetworkGossip<ServiceControl, AutoCompression, SharedSessions>,
+    rpc:     NetworkRpc<ServiceControl, AutoCompression, SharedSessions>,
     rpc_map: Arc<RpcMap>,
 
+    // Peer scores query, misbehavior report
+    peer_mgr_handle: PeerManagerHandle,
+
     // Core service
     net_conn_srv: Option<NetworkConnectionService>,
     peer_mgr:     Option<PeerManager>,
-    router:       Option<MessageRouter<Snappy, SharedSessions>>,
+    router:       Option<MessageRouter<AutoCompression, SharedSessions, ServiceControl>>,
 
     // Self check
     selfcheck: Option<SelfCheck<SharedSessions>>,
+
+    // Seed domains to resolve
+    dns_seeds: Option<DnsSeeds>,
 }
 
 impl NetworkService {
@@ -157,10 +238,17 @@ impl NetworkService {
             peer_mgr.bootstrap();
         }
 
+        let dns_seeds = if !config.dns_seeds.is_empty() {
+            Some(DnsSeeds::new(config.dns_seeds.clone(), mgr_tx.clone()))
+        } else {
+            None
+        };
+
         // Build service protocol
         let disc_sync_interval = config.discovery_sync_interval;
         let disc_addr_mgr = DiscoveryAddrManager::new(peer_mgr_handle.clone(), mgr_tx.clone());
-        let ident_callback = IdentifyCallback::new(peer_mgr_handle, mgr_tx.clone());
+        let ident_callback =
+            IdentifyCallback::new(peer_mgr_handle.clone(), mgr_tx.clone(), config.node_role);
         let proto = CoreProtocol::build()
             .ping(config.ping_interval, config.ping_timeout, mgr_tx.clone())
             .identify(ident_callback)
@@ -175,10 +263,28 @@ impl NetworkService {
 
         // Build public service components
         let rpc_map = Arc::new(RpcMap::new());
-        let gossip = NetworkGossip::new(conn_ctrl.clone(), Snappy);
+        let compression = AutoCompression::new(config.compression);
+        let gossip =
+            NetworkGossip::new(conn_ctrl.clone(), compression.clone(), session_book.clone());
         let rpc_map_clone = Arc::clone(&rpc_map);
-        let rpc = NetworkRpc::new(conn_ctrl, Snappy, rpc_map_clone, (&config).into());
-        let router = MessageRouter::new(raw_msg_rx, Snappy, session_book.clone(), sys_tx);
+        let rpc = NetworkRpc::new(
+            conn_ctrl.clone(),
+            compression.clone(),
+            rpc_map_clone,
+            session_book.clone(),
+            (&config).into(),
+        );
+        let mut router = MessageRouter::new(
+            raw_msg_rx,
+            compression,
+            config.max_frame_length,
+            session_book.clone(),
+            conn_ctrl,
+            sys_tx,
+            mgr_tx.clone(),
+        );
+        router.set_memory_cap(config.max_inbound_memory);
+        router.set_consensus_memory_cap(config.max_inbound_consensus_memory);
 
         // Build selfcheck service
         let selfcheck = SelfCheck::new(session_book, (&config).into());
@@ -198,11 +304,15 @@ impl NetworkService {
             rpc,
             rpc_map,
 
+            peer_mgr_handle,
+
             net_conn_srv: Some(NetworkConnectionService::NoListen(conn_srv)),
             peer_mgr: Some(peer_mgr),
             router: Some(router),
 
             selfcheck: Some(selfcheck),
+
+            dns_seeds,
         }
     }
 
@@ -226,13 +336,26 @@ impl NetworkService {
         if let Some(router) = &mut self.router {
             router.register_reactor(endpoint, msg_tx);
 
-            let reactor = Reactor::new(msg_rx, handler, Arc::clone(&self.rpc_map));
+            let rpc_map = Arc::clone(&self.rpc_map);
+            let reactor = Reactor::new(msg_rx, handler, rpc_map, self.mgr_tx.clone());
             tokio::spawn(reactor);
         }
 
         Ok(())
     }
 
+    // Messages to this endpoint whose content is larger than limit are dropped,
+    // and the sender is punished.
+    pub fn set_message_size_limit(&mut self, end: &str, limit: usize) -> ProtocolResult<()> {
+        let endpoint = end.parse::<Endpoint>()?;
+
+        if let Some(router) = &mut self.router {
+            router.set_size_limit(endpoint, limit);
+        }
+
+        Ok(())
+    }
+
     // Currently rpc response dont invoke message handler, so we create a dummy
     // for it.
     pub fn register_rpc_response<M>(&mut self, end: &str) -> ProtocolResult<()>
@@ -249,7 +372,8 @@ impl NetworkService {
         if let Some(router) = &mut self.router {
             router.register_reactor(endpoint, msg_tx);
 
-            let reactor = Reactor::<M>::rpc_resp(msg_rx, Arc::clone(&self.rpc_map));
+            let rpc_map = Arc::clone(&self.rpc_map);
+            let reactor = Reactor::<M>::rpc_resp(msg_rx, rpc_map, self.mgr_tx.clone());
             tokio::spawn(reactor);
         }
 
@@ -260,6 +384,9 @@ impl NetworkService {
         NetworkServiceHandle {
             gossip: self.gossip.clone(),
             rpc:    self.rpc.clone(),
+            mgr_tx: self.mgr_tx.clone(),
+
+            peer_mgr: self.peer_mgr_handle.clone(),
         }
     }
 
@@ -336,6 +463,10 @@ impl Future for NetworkService {
             tokio::spawn(selfcheck);
         }
 
+        if let Some(dns_seeds) = self.dns_seeds.take() {
+            tokio::spawn(dns_seeds.resolve());
+        }
+
         // Heart beats
         if let Some(heart_beat) = self.heart_beat.take() {
             tokio::spawn(heart_beat);
@@ -29,6 +29,10 @@ impl SessionContext {
         self.pending_data_size.load(Ordering::SeqCst)
     }
 
+    pub fn set_pending_data_size(&self, size: usize) {
+        self.pending_data_size.store(size, Ordering::SeqCst);
+    }
+
     pub fn arced(self) -> Arc<SessionContext> {
         Arc::new(self)
     }
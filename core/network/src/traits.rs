@@ -17,6 +17,7 @@ use tentacle::{
 use crate::{
     common::ConnectedAddr,
     error::{ErrorKind, NetworkError},
+    version::{Features, PeerProtocol},
 };
 
 pub trait NetworkProtocol {
@@ -31,12 +32,21 @@ pub trait NetworkProtocol {
 #[async_trait]
 pub trait MessageSender {
     fn send(&self, tar: TargetSession, msg: Bytes, pri: Priority) -> Result<(), NetworkError>;
+    // Responses requested by the session, they're never throttled
+    fn respond(&self, sid: SessionId, msg: Bytes, pri: Priority) -> Result<(), NetworkError> {
+        self.send(TargetSession::Single(sid), msg, pri)
+    }
     async fn users_send(&self, users: Vec<Address>, msg: Bytes, pri: Priority) -> Result<(), NetworkError>;
 }
 
 pub trait Compression {
     fn compress(&self, bytes: Bytes) -> Result<Bytes, NetworkError>;
-    fn decompress(&self, bytes: Bytes) -> Result<Bytes, NetworkError>;
+    // Compress for peers of the negotiated features
+    fn compress_for(&self, bytes: Bytes, _features: Features) -> Result<Bytes, NetworkError> {
+        self.compress(bytes)
+    }
+    // Fails if decompressed bytes exceed max size
+    fn decompress(&self, bytes: Bytes, max_size: usize) -> Result<Bytes, NetworkError>;
 }
 
 pub trait NetworkContext: Sized {
@@ -67,6 +77,8 @@ pub trait SessionBook {
     fn connected_addr(&self, sid: SessionId) -> Option<ConnectedAddr>;
     fn pending_data_size(&self, sid: SessionId) -> usize;
     fn whitelist(&self) -> Vec<Address>;
+    fn relays(&self) -> Vec<SessionId>;
+    fn protocol(&self, sid: SessionId) -> PeerProtocol;
 }
 
 pub trait MultiaddrExt {
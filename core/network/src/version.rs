@@ -0,0 +1,292 @@
+use std::ops::BitAnd;
+
+use derive_more::Display;
+
+use crate::{endpoint::Endpoint, role::NodeRole};
+
+/// Version of the node-to-node protocol, bump it when a message changes its
+/// wire format or a new message type is added. Peers before negotiation
+/// advertise no version, they're of version 0. Peers of every version stay
+/// connected, messages they don't understand are never sent to them.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+// Messages not listed are understood by every version
+const MESSAGE_VERSIONS: [(&str, u32); 2] = [
+    ("/gossip/mempool/new_tx_hashes", 1),
+    ("/gossip/consensus/vote_extension", 2),
+];
+
+// Messages only sent to peers having the feature, besides the version
+const MESSAGE_FEATURES: [(&str, Features); 1] =
+    [("/gossip/mempool/new_tx_hashes", Features::TX_HASHES)];
+
+// Broadcasts of the first endpoint are replaced by the second for peers
+// understanding it, the others still receive the first
+const REPLACED_MESSAGES: [(&str, &str); 1] =
+    [("/gossip/mempool/new_txs", "/gossip/mempool/new_tx_hashes")];
+
+/// Optional capabilities of a node, they're negotiated with each peer, so
+/// that subsystems only use the ones both sides support.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[display(fmt = "{:x}", _0)]
+pub struct Features(u64);
+
+impl Features {
+    /// Pulls transactions announced by hashes
+    pub const TX_HASHES: Features = Features(1 << 1);
+    /// Decompresses zstd messages
+    pub const ZSTD: Features = Features(1);
+
+    /// Features supported by this node
+    pub const fn local() -> Self {
+        Features(Features::ZSTD.0 | Features::TX_HASHES.0)
+    }
+
+    pub fn contains(self, other: Features) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitAnd for Features {
+    type Output = Features;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Features(self.0 & rhs.0)
+    }
+}
+
+/// Protocol spoken with a peer, advertised in identify message after its
+/// role, such as `muta/full/v2/3`. Default is of peers before negotiation,
+/// or not identified yet.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Default)]
+#[display(fmt = "v{}/{}", version, features)]
+pub struct PeerProtocol {
+    pub version:  u32,
+    pub features: Features,
+}
+
+impl PeerProtocol {
+    pub fn local() -> Self {
+        PeerProtocol {
+            version:  PROTOCOL_VERSION,
+            features: Features::local(),
+        }
+    }
+
+    /// Both sides speak the lower version and the common features.
+    pub fn negotiate(self, remote: PeerProtocol) -> PeerProtocol {
+        PeerProtocol {
+            version:  self.version.min(remote.version),
+            features: self.features & remote.features,
+        }
+    }
+
+    pub fn supports(self, endpoint: &Endpoint) -> bool {
+        self.version >= message_version(endpoint)
+            && self.features.contains(message_features(endpoint))
+    }
+
+    /// Whether broadcasts of the endpoint are sent to the peer, they're
+    /// skipped if the peer receives their replacement instead.
+    pub fn receives_broadcast(self, endpoint: &Endpoint) -> bool {
+        let replaced = replacement(endpoint)
+            .map(|replacement| self.supports(&replacement))
+            .unwrap_or(false);
+
+        self.supports(endpoint) && !replaced
+    }
+}
+
+/// Whether broadcasts of the endpoint are sent to some peers only.
+pub fn is_gated_broadcast(endpoint: &Endpoint) -> bool {
+    message_version(endpoint) > 0
+        || message_features(endpoint) != Features::default()
+        || replacement(endpoint).is_some()
+}
+
+fn replacement(endpoint: &Endpoint) -> Option<Endpoint> {
+    let root = endpoint.root();
+
+    REPLACED_MESSAGES
+        .iter()
+        .find(|(end, _)| *end == root)
+        .and_then(|(_, replacement)| replacement.parse::<Endpoint>().ok())
+}
+
+/// Features required to understand messages of the endpoint.
+pub fn message_features(endpoint: &Endpoint) -> Features {
+    let root = endpoint.root();
+
+    MESSAGE_FEATURES
+        .iter()
+        .find(|(end, _)| *end == root)
+        .map(|(_, features)| *features)
+        .unwrap_or_default()
+}
+
+/// Lowest protocol version understanding messages of the endpoint, they're
+/// not sent to peers of lower versions.
+pub fn message_version(endpoint: &Endpoint) -> u32 {
+    let root = endpoint.root();
+
+    MESSAGE_VERSIONS
+        .iter()
+        .find(|(end, _)| *end == root)
+        .map(|(_, version)| *version)
+        .unwrap_or(0)
+}
+
+pub fn identify_message(role: NodeRole, protocol: PeerProtocol) -> Vec<u8> {
+    let mut msg = role.identify_message().to_vec();
+    msg.extend_from_slice(format!("/{}", protocol).as_bytes());
+    msg
+}
+
+/// Role and protocol of the remote peer. Role is None if it's unknown,
+/// protocol is None if it's malformed.
+pub fn parse_identify(msg: &[u8]) -> (Option<NodeRole>, Option<PeerProtocol>) {
+    if let Some(role) = NodeRole::from_identify_message(msg) {
+        return (Some(role), Some(PeerProtocol::default()));
+    }
+
+    let msg = match std::str::from_utf8(msg) {
+        Ok(msg) => msg,
+        Err(_) => return (None, None),
+    };
+    // Role of newer versions may be unknown, their protocol is still parsed
+    let mut comps = msg.rsplitn(3, '/');
+    let features = comps.next().and_then(|f| u64::from_str_radix(f, 16).ok());
+    let version = comps
+        .next()
+        .and_then(|v| v.trim_start_matches('v').parse::<u32>().ok());
+    let role = comps
+        .next()
+        .and_then(|r| NodeRole::from_identify_message(r.as_bytes()));
+
+    match (version, features) {
+        (Some(version), Some(features)) => (
+            role,
+            Some(PeerProtocol {
+                version,
+                features: Features(features),
+            }),
+        ),
+        _ => (None, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        identify_message, is_gated_broadcast, parse_identify, Features, PeerProtocol,
+        PROTOCOL_VERSION,
+    };
+    use crate::{endpoint::Endpoint, role::NodeRole};
+
+    fn protocol(version: u32, features: Features) -> PeerProtocol {
+        PeerProtocol { version, features }
+    }
+
+    #[test]
+    fn should_parse_identify_message_of_every_version() {
+        let local = PeerProtocol::local();
+        let msg = identify_message(NodeRole::Full, local);
+        assert_eq!(msg, format!("muta/full/v{}/3", PROTOCOL_VERSION).as_bytes());
+        assert_eq!(parse_identify(&msg), (Some(NodeRole::Full), Some(local)));
+
+        // Peers before negotiation
+        let legacy = Some(PeerProtocol::default());
+        assert_eq!(
+            parse_identify(b"Identify message"),
+            (Some(NodeRole::Validator), legacy)
+        );
+        assert_eq!(parse_identify(b"muta/seed"), (Some(NodeRole::Seed), legacy));
+
+        // Roles and features of newer versions
+        assert_eq!(
+            parse_identify(b"muta/light/v9/ff"),
+            (None, Some(protocol(9, Features(0xff))))
+        );
+        assert_eq!(parse_identify(b"muta/full/vx/3"), (None, None));
+        assert_eq!(parse_identify(b"unknown"), (None, None));
+    }
+
+    // Each row is a remote peer of some version against this node
+    #[test]
+    fn should_negotiate_with_peers_of_every_version() {
+        let local = PeerProtocol::local();
+        let matrix = [
+            (PeerProtocol::default(), PeerProtocol::default()),
+            (
+                protocol(1, Features::TX_HASHES),
+                protocol(1, Features::TX_HASHES),
+            ),
+            (
+                protocol(1, Features::local()),
+                protocol(1, Features::local()),
+            ),
+            (protocol(2, Features::local()), local),
+            (protocol(9, Features(0xff)), local),
+        ];
+
+        for (remote, negotiated) in matrix.iter() {
+            assert_eq!(local.negotiate(*remote), *negotiated, "remote {}", remote);
+            // Negotiation is symmetric
+            assert_eq!(remote.negotiate(local), *negotiated, "remote {}", remote);
+        }
+    }
+
+    #[test]
+    fn should_gate_messages_by_negotiated_version() {
+        let hashes = "/gossip/mempool/new_tx_hashes".parse::<Endpoint>().unwrap();
+        let txs = "/gossip/mempool/new_txs".parse::<Endpoint>().unwrap();
+        let extension = "/gossip/consensus/vote_extension"
+            .parse::<Endpoint>()
+            .unwrap();
+
+        let legacy = PeerProtocol::default();
+        assert!(!legacy.supports(&hashes));
+        assert!(legacy.supports(&txs));
+
+        let negotiated = PeerProtocol::local().negotiate(legacy);
+        assert!(!negotiated.supports(&hashes));
+        assert!(PeerProtocol::local().supports(&hashes));
+        assert_eq!(negotiated.features & Features::ZSTD, Features::default());
+
+        let v1 = protocol(1, Features::local());
+        assert!(v1.supports(&hashes));
+        assert!(!v1.supports(&extension));
+        assert!(PeerProtocol::local().supports(&extension));
+    }
+
+    #[test]
+    fn should_gate_messages_by_negotiated_features() {
+        let hashes = "/gossip/mempool/new_tx_hashes".parse::<Endpoint>().unwrap();
+        let txs = "/gossip/mempool/new_txs".parse::<Endpoint>().unwrap();
+
+        // Peers not pulling hashes receive full transactions
+        let no_hashes = protocol(PROTOCOL_VERSION, Features::ZSTD);
+        assert!(!no_hashes.supports(&hashes));
+        assert!(no_hashes.receives_broadcast(&txs));
+        assert!(!no_hashes.receives_broadcast(&hashes));
+
+        let negotiated = PeerProtocol::local().negotiate(no_hashes);
+        assert!(negotiated.features.contains(Features::ZSTD));
+        assert!(!negotiated.features.contains(Features::TX_HASHES));
+        assert!(negotiated.receives_broadcast(&txs));
+    }
+
+    #[test]
+    fn should_broadcast_full_txs_to_peers_not_pulling_hashes() {
+        let hashes = "/gossip/mempool/new_tx_hashes".parse::<Endpoint>().unwrap();
+        let txs = "/gossip/mempool/new_txs".parse::<Endpoint>().unwrap();
+        let legacy = PeerProtocol::default();
+        let local = PeerProtocol::local();
+
+        assert!(is_gated_broadcast(&txs));
+        assert!(legacy.receives_broadcast(&txs));
+        assert!(!legacy.receives_broadcast(&hashes));
+        assert!(!local.receives_broadcast(&txs));
+        assert!(local.receives_broadcast(&hashes));
+    }
+}
@@ -4,8 +4,11 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use derive_more::{Display, From};
-use rocksdb::{ColumnFamily, Options, WriteBatch, DB};
+use rocksdb::{
+    ColumnFamily, DBCompressionType, IteratorMode, Options, WriteBatch, WriteOptions, DB,
+};
 
+use common_metrics::{HistogramTimer, HistogramVec, STORAGE_READ_SECONDS, STORAGE_WRITE_SECONDS};
 use protocol::codec::ProtocolCodec;
 use protocol::traits::{StorageAdapter, StorageBatchModify, StorageCategory, StorageSchema};
 use protocol::Bytes;
@@ -18,10 +21,27 @@ pub struct RocksAdapter {
 
 impl RocksAdapter {
     pub fn new<P: AsRef<Path>>(path: P, max_open_files: i32) -> ProtocolResult<Self> {
+        Self::open(path, max_open_files, None)
+    }
+
+    /// Compressed by zstd, smaller but slower to read than `new`, for records
+    /// that are rarely read such as ones in a cold store.
+    pub fn compressed<P: AsRef<Path>>(path: P, max_open_files: i32) -> ProtocolResult<Self> {
+        Self::open(path, max_open_files, Some(DBCompressionType::Zstd))
+    }
+
+    fn open<P: AsRef<Path>>(
+        path: P,
+        max_open_files: i32,
+        compression: Option<DBCompressionType>,
+    ) -> ProtocolResult<Self> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
         opts.create_missing_column_families(true);
         opts.set_max_open_files(max_open_files);
+        if let Some(compression) = compression {
+            opts.set_compression_type(compression);
+        }
 
         let categories = [
             map_category(StorageCategory::Block),
@@ -34,6 +54,54 @@ impl RocksAdapter {
 
         Ok(RocksAdapter { db: Arc::new(db) })
     }
+
+    /// Count the keys and the bytes of every category, this walks through the
+    /// whole database and is only supposed to be used by offline tools.
+    pub fn stats(&self) -> ProtocolResult<Vec<CategoryStats>> {
+        let categories = [
+            StorageCategory::Block,
+            StorageCategory::Receipt,
+            StorageCategory::SignedTransaction,
+            StorageCategory::Wal,
+        ];
+
+        let mut stats = Vec::with_capacity(categories.len());
+        for category in categories.iter() {
+            let name = map_category(*category);
+            let column = self
+                .db
+                .cf_handle(name)
+                .ok_or_else(|| RocksAdapterError::from(name))?;
+
+            let mut stat = CategoryStats {
+                category:    *category,
+                keys:        0,
+                key_bytes:   0,
+                value_bytes: 0,
+            };
+            let iter = self
+                .db
+                .iterator_cf(column, IteratorMode::Start)
+                .map_err(RocksAdapterError::from)?;
+            for (key, value) in iter {
+                stat.keys += 1;
+                stat.key_bytes += key.len() as u64;
+                stat.value_bytes += value.len() as u64;
+            }
+
+            stats.push(stat);
+        }
+
+        Ok(stats)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CategoryStats {
+    pub category:    StorageCategory,
+    pub keys:        u64,
+    pub key_bytes:   u64,
+    pub value_bytes: u64,
 }
 
 macro_rules! db {
@@ -53,6 +121,7 @@ impl StorageAdapter for RocksAdapter {
         mut key: <S as StorageSchema>::Key,
         mut val: <S as StorageSchema>::Value,
     ) -> ProtocolResult<()> {
+        let _timer = start_timer::<S>(&STORAGE_WRITE_SECONDS);
         let column = get_column::<S>(&self.db)?;
         let key = key.encode().await?.to_vec();
         let val = val.encode().await?.to_vec();
@@ -66,6 +135,7 @@ impl StorageAdapter for RocksAdapter {
         &self,
         mut key: <S as StorageSchema>::Key,
     ) -> ProtocolResult<Option<<S as StorageSchema>::Value>> {
+        let _timer = start_timer::<S>(&STORAGE_READ_SECONDS);
         let column = get_column::<S>(&self.db)?;
         let key = key.encode().await?;
 
@@ -85,6 +155,7 @@ impl StorageAdapter for RocksAdapter {
         &self,
         mut key: <S as StorageSchema>::Key,
     ) -> ProtocolResult<()> {
+        let _timer = start_timer::<S>(&STORAGE_WRITE_SECONDS);
         let column = get_column::<S>(&self.db)?;
         let key = key.encode().await?.to_vec();
 
@@ -97,6 +168,7 @@ impl StorageAdapter for RocksAdapter {
         &self,
         mut key: <S as StorageSchema>::Key,
     ) -> ProtocolResult<bool> {
+        let _timer = start_timer::<S>(&STORAGE_READ_SECONDS);
         let column = get_column::<S>(&self.db)?;
         let key = key.encode().await?.to_vec();
         let val = db!(self.db, get_cf, column, key)?;
@@ -109,6 +181,7 @@ impl StorageAdapter for RocksAdapter {
         keys: Vec<<S as StorageSchema>::Key>,
         vals: Vec<StorageBatchModify<S>>,
     ) -> ProtocolResult<()> {
+        let _timer = start_timer::<S>(&STORAGE_WRITE_SECONDS);
         if keys.len() != vals.len() {
             return Err(RocksAdapterError::BatchLengthMismatch.into());
         }
@@ -138,6 +211,17 @@ impl StorageAdapter for RocksAdapter {
         self.db.write(batch).map_err(RocksAdapterError::from)?;
         Ok(())
     }
+
+    async fn flush(&self) -> ProtocolResult<()> {
+        // Column families share the write ahead log, a synced write persists
+        // all writes before it.
+        let mut opts = WriteOptions::default();
+        opts.set_sync(true);
+        self.db
+            .write_opt(WriteBatch::default(), &opts)
+            .map_err(RocksAdapterError::from)?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Display, From)]
@@ -177,6 +261,12 @@ fn map_category(c: StorageCategory) -> &'static str {
     }
 }
 
+fn start_timer<S: StorageSchema>(histogram: &HistogramVec) -> HistogramTimer {
+    histogram
+        .with_label_values(&[&S::category().to_string()])
+        .start_timer()
+}
+
 fn get_column<S: StorageSchema>(db: &DB) -> Result<ColumnFamily, RocksAdapterError> {
     let category = map_category(S::category());
 
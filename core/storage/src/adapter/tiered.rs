@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use protocol::codec::ProtocolCodec;
+use protocol::traits::{StorageAdapter, StorageBatchModify, StorageSchema};
+use protocol::ProtocolResult;
+
+/// Storage of two tiers. Records are written to the hot store, old ones are
+/// moved to the cold store by `move_to_cold`, and reads fall through to the
+/// cold store for records not in the hot one, so readers don't know which
+/// tier a record is in. Without a cold store, it's the hot store alone.
+#[derive(Debug)]
+pub struct TieredAdapter<Hot, Cold> {
+    hot:  Arc<Hot>,
+    cold: Option<Arc<Cold>>,
+}
+
+impl<Hot: StorageAdapter, Cold: StorageAdapter> TieredAdapter<Hot, Cold> {
+    pub fn new(hot: Arc<Hot>, cold: Option<Arc<Cold>>) -> Self {
+        TieredAdapter { hot, cold }
+    }
+
+    pub fn has_cold(&self) -> bool {
+        self.cold.is_some()
+    }
+
+    /// Copy records of the keys to the cold store, then remove them from the
+    /// hot one. Keys not in the hot store are skipped. A move interrupted
+    /// midway leaves records in both stores, and they are moved again by the
+    /// next one.
+    pub async fn move_to_cold<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+    ) -> ProtocolResult<()> {
+        let cold = match &self.cold {
+            Some(cold) => cold,
+            None => return Ok(()),
+        };
+
+        let mut moved = Vec::with_capacity(keys.len());
+        let mut removed = Vec::with_capacity(keys.len());
+        let mut vals = Vec::with_capacity(keys.len());
+        for mut key in keys.into_iter() {
+            let mut copy = copy_key::<S>(&mut key).await?;
+            if let Some(val) = self.hot.get::<S>(key).await? {
+                removed.push(copy_key::<S>(&mut copy).await?);
+                moved.push(copy);
+                vals.push(StorageBatchModify::Insert(val));
+            }
+        }
+        if moved.is_empty() {
+            return Ok(());
+        }
+
+        let removes = removed.iter().map(|_| StorageBatchModify::Remove).collect();
+        cold.batch_modify::<S>(moved, vals).await?;
+        // Records must be durable in the cold store before they're gone from
+        // the hot one
+        cold.flush().await?;
+        self.hot.batch_modify::<S>(removed, removes).await
+    }
+}
+
+#[async_trait]
+impl<Hot: StorageAdapter, Cold: StorageAdapter> StorageAdapter for TieredAdapter<Hot, Cold> {
+    async fn insert<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+        val: <S as StorageSchema>::Value,
+    ) -> ProtocolResult<()> {
+        self.hot.insert::<S>(key, val).await
+    }
+
+    async fn get<S: StorageSchema>(
+        &self,
+        mut key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<Option<<S as StorageSchema>::Value>> {
+        let cold = match &self.cold {
+            Some(cold) => cold,
+            None => return self.hot.get::<S>(key).await,
+        };
+
+        let copy = copy_key::<S>(&mut key).await?;
+        match self.hot.get::<S>(key).await? {
+            Some(val) => Ok(Some(val)),
+            None => cold.get::<S>(copy).await,
+        }
+    }
+
+    async fn remove<S: StorageSchema>(
+        &self,
+        mut key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<()> {
+        if let Some(cold) = &self.cold {
+            cold.remove::<S>(copy_key::<S>(&mut key).await?).await?;
+        }
+        self.hot.remove::<S>(key).await
+    }
+
+    async fn contains<S: StorageSchema>(
+        &self,
+        mut key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<bool> {
+        let cold = match &self.cold {
+            Some(cold) => cold,
+            None => return self.hot.contains::<S>(key).await,
+        };
+
+        let copy = copy_key::<S>(&mut key).await?;
+        if self.hot.contains::<S>(key).await? {
+            return Ok(true);
+        }
+        cold.contains::<S>(copy).await
+    }
+
+    // Batches are written to the hot store, removed records in the cold
+    // store are removed too
+    async fn batch_modify<S: StorageSchema>(
+        &self,
+        mut keys: Vec<<S as StorageSchema>::Key>,
+        vals: Vec<StorageBatchModify<S>>,
+    ) -> ProtocolResult<()> {
+        if let Some(cold) = &self.cold {
+            let removed_at = vals
+                .iter()
+                .take(keys.len())
+                .enumerate()
+                .filter_map(|(i, val)| match val {
+                    StorageBatchModify::Remove => Some(i),
+                    StorageBatchModify::Insert(_) => None,
+                })
+                .collect::<Vec<_>>();
+
+            let mut removed = Vec::with_capacity(removed_at.len());
+            for i in removed_at.into_iter() {
+                removed.push(copy_key::<S>(&mut keys[i]).await?);
+            }
+            if !removed.is_empty() {
+                let removes = removed.iter().map(|_| StorageBatchModify::Remove).collect();
+                cold.batch_modify::<S>(removed, removes).await?;
+            }
+        }
+        self.hot.batch_modify::<S>(keys, vals).await
+    }
+
+    async fn flush(&self) -> ProtocolResult<()> {
+        if let Some(cold) = &self.cold {
+            cold.flush().await?;
+        }
+        self.hot.flush().await
+    }
+}
+
+// Keys of schemas aren't `Clone`, they're copied through their encoding
+async fn copy_key<S: StorageSchema>(
+    key: &mut <S as StorageSchema>::Key,
+) -> ProtocolResult<<S as StorageSchema>::Key> {
+    let bytes = key.encode().await?;
+    <<S as StorageSchema>::Key as ProtocolCodec>::decode(bytes).await
+}
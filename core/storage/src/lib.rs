@@ -6,6 +6,7 @@ mod tests;
 pub mod adapter;
 
 use std::error::Error;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -21,12 +22,19 @@ use protocol::types::{Block, Hash, Proof, Receipt, SignedTransaction};
 use protocol::Bytes;
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
+use crate::adapter::tiered::TieredAdapter;
+
 lazy_static! {
     pub static ref LATEST_BLOCK_KEY: Hash = Hash::digest(Bytes::from("latest_hash"));
     pub static ref LATEST_PROOF_KEY: Hash = Hash::digest(Bytes::from("latest_proof"));
     pub static ref OVERLORD_WAL_KEY: Hash = Hash::digest(Bytes::from("overlord_wal"));
+    pub static ref COLD_HEIGHT_KEY: Hash = Hash::digest(Bytes::from("cold_height"));
+    pub static ref COLD_PATH_KEY: Hash = Hash::digest(Bytes::from("cold_path"));
 }
 
+// Executed heights checked for missing receipts on recovery at most
+const RECOVER_RECEIPTS_DEPTH: u64 = 64;
+
 #[derive(Debug)]
 pub struct ImplStorage<Adapter> {
     adapter: Arc<Adapter>,
@@ -41,6 +49,256 @@ impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
             latest_block: RwLock::new(None),
         }
     }
+
+    /// Repair records left by a commit interrupted before the node exited,
+    /// it's called on startup before anything reads the storage.
+    ///
+    /// Blocks are saved before the latest block record, so a saved block
+    /// above the latest one is linked back if its transactions are saved
+    /// too, with its hash index. Blocks above the executed height are
+    /// re-executed on startup, the latest block is rolled back below one
+    /// missing its transactions. Receipts are saved after their blocks by
+    /// execution, the latest block is rolled back to re-execute executed
+    /// heights missing their receipts. Blocks above a rolled back latest
+    /// block are removed, and synced again. The latest proof
+    /// is reset to the proof in the header of the latest block if it's not
+    /// for the latest block or its parent.
+    pub async fn recover(&self) -> ProtocolResult<Vec<Repair>> {
+        let mut repairs = vec![];
+        let mut latest_block = get!(self, LATEST_BLOCK_KEY.clone(), LatestBlockSchema);
+        let mut latest_hash = Hash::digest(latest_block.encode_fixed()?);
+
+        loop {
+            let height = latest_block.header.height + 1;
+            let block = match self.adapter.get::<BlockSchema>(height).await? {
+                Some(block) if block.header.pre_hash == latest_hash => block,
+                _ => break,
+            };
+            if !self.contains_transactions(&block.ordered_tx_hashes).await? {
+                break;
+            }
+
+            let block_hash = Hash::digest(block.encode_fixed()?);
+            self.adapter
+                .insert::<HashBlockSchema>(block_hash.clone(), height)
+                .await?;
+            self.adapter
+                .insert::<LatestBlockSchema>(LATEST_BLOCK_KEY.clone(), block.clone())
+                .await?;
+            repairs.push(Repair::LatestBlock {
+                from: latest_block.header.height,
+                to:   height,
+            });
+            latest_hash = block_hash;
+            latest_block = block;
+        }
+
+        while let Some((missing, height)) = self.missing_records(&latest_block).await? {
+            if height >= latest_block.header.height {
+                break;
+            }
+
+            let block = get!(self, height, BlockSchema);
+            self.adapter
+                .insert::<LatestBlockSchema>(LATEST_BLOCK_KEY.clone(), block.clone())
+                .await?;
+            // Blocks rolled back are committed again by sync, not linked
+            // back by the next recovery
+            for removed in height + 1..=latest_block.header.height {
+                let removed_block = get!(self, removed, BlockSchema);
+                self.adapter
+                    .remove::<HashBlockSchema>(Hash::digest(removed_block.encode_fixed()?))
+                    .await?;
+                self.adapter.remove::<BlockSchema>(removed).await?;
+            }
+            repairs.push(missing);
+            repairs.push(Repair::LatestBlock {
+                from: latest_block.header.height,
+                to:   height,
+            });
+            latest_hash = Hash::digest(block.encode_fixed()?);
+            latest_block = block;
+        }
+
+        let latest_proof = self
+            .adapter
+            .get::<LatestProofSchema>(LATEST_PROOF_KEY.clone())
+            .await?;
+        let header_proof = &latest_block.header.proof;
+        let consistent = match &latest_proof {
+            Some(proof) if proof == header_proof => true,
+            Some(proof) => {
+                proof.height == latest_block.header.height && proof.block_hash == latest_hash
+            }
+            None => false,
+        };
+        if !consistent {
+            self.adapter
+                .insert::<LatestProofSchema>(LATEST_PROOF_KEY.clone(), header_proof.clone())
+                .await?;
+            repairs.push(Repair::LatestProof {
+                from: latest_proof.map(|proof| proof.height),
+                to:   header_proof.height,
+            });
+        }
+
+        self.latest_block.write().await.take();
+        Ok(repairs)
+    }
+
+    // Records missing for startup to re-execute blocks above the executed
+    // height of the latest block, with the height to roll the latest block
+    // back to
+    async fn missing_records(&self, latest: &Block) -> ProtocolResult<Option<(Repair, u64)>> {
+        let header = &latest.header;
+        for height in header.exec_height + 1..=header.height {
+            let block = get!(self, height, BlockSchema);
+            if !self.contains_transactions(&block.ordered_tx_hashes).await? {
+                return Ok(Some((Repair::Transactions { height }, height - 1)));
+            }
+        }
+
+        // Receipts are saved in height order, so executed heights missing
+        // them are the highest. Empty blocks have no receipts to check.
+        let mut missing = None;
+        let lowest = header.exec_height.saturating_sub(RECOVER_RECEIPTS_DEPTH);
+        for height in (lowest..=header.exec_height).rev() {
+            let block = match self.adapter.get::<BlockSchema>(height).await? {
+                Some(block) => block,
+                None => break,
+            };
+            if block.ordered_tx_hashes.is_empty() {
+                continue;
+            }
+            if self.contains_receipts(&block.ordered_tx_hashes).await? {
+                break;
+            }
+            missing = Some(height);
+        }
+        let missing = match missing {
+            Some(missing) => missing,
+            None => return Ok(None),
+        };
+
+        // The highest block executed below the missing height
+        for height in (missing..header.height).rev() {
+            let block = get!(self, height, BlockSchema);
+            if block.header.exec_height < missing {
+                return Ok(Some((Repair::Receipts { height: missing }, height)));
+            }
+        }
+        Ok(Some((Repair::Receipts { height: missing }, missing)))
+    }
+
+    async fn contains_receipts(&self, hashes: &[Hash]) -> ProtocolResult<bool> {
+        for hash in hashes {
+            if !self.adapter.contains::<ReceiptSchema>(hash.clone()).await? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    async fn contains_transactions(&self, hashes: &[Hash]) -> ProtocolResult<bool> {
+        for hash in hashes {
+            if !self
+                .adapter
+                .contains::<TransactionSchema>(hash.clone())
+                .await?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<Hot: StorageAdapter, Cold: StorageAdapter> ImplStorage<TieredAdapter<Hot, Cold>> {
+    /// Move blocks older than `keep_heights` below the latest executed height
+    /// to the cold store, with their transactions and receipts, at most
+    /// `max_heights` blocks a call. Blocks below the returned height are in
+    /// the cold store.
+    ///
+    /// Only executed blocks are moved, so receipts are never written to the
+    /// hot store after their blocks are moved.
+    pub async fn move_to_cold(&self, keep_heights: u64, max_heights: u64) -> ProtocolResult<u64> {
+        let next = self
+            .adapter
+            .get::<ColdHeightSchema>(COLD_HEIGHT_KEY.clone())
+            .await?
+            .unwrap_or(0);
+        if !self.adapter.has_cold() {
+            return Ok(next);
+        }
+
+        let latest = self.get_latest_block().await?.header;
+        let executed = latest.exec_height.min(latest.height);
+        let until = executed
+            .saturating_sub(keep_heights)
+            .min(next.saturating_add(max_heights));
+        if until <= next {
+            return Ok(next);
+        }
+
+        let mut tx_hashes = vec![];
+        for height in next..until {
+            let block = get!(self, height, BlockSchema);
+            tx_hashes.extend(block.ordered_tx_hashes);
+        }
+
+        // Blocks are moved last, a block in the cold store has its records
+        // there too
+        self.adapter
+            .move_to_cold::<TransactionSchema>(tx_hashes.clone())
+            .await?;
+        self.adapter
+            .move_to_cold::<ReceiptSchema>(tx_hashes)
+            .await?;
+        self.adapter
+            .move_to_cold::<BlockSchema>((next..until).collect())
+            .await?;
+        self.adapter
+            .insert::<ColdHeightSchema>(COLD_HEIGHT_KEY.clone(), until)
+            .await?;
+
+        Ok(until)
+    }
+}
+
+/// Path of the cold store recorded in the hot db, blocks moved there are
+/// read from it after the config of the cold store is removed.
+pub async fn get_cold_path<Hot: StorageAdapter>(hot: &Hot) -> ProtocolResult<Option<PathBuf>> {
+    let path = hot.get::<ColdPathSchema>(COLD_PATH_KEY.clone()).await?;
+    path.map(|path| {
+        String::from_utf8(path.to_vec())
+            .map(PathBuf::from)
+            .map_err(|e| StorageError::ColdPath(e.to_string()).into())
+    })
+    .transpose()
+}
+
+pub async fn set_cold_path<Hot: StorageAdapter>(hot: &Hot, path: &Path) -> ProtocolResult<()> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| StorageError::ColdPath(format!("{:?} is not utf8", path)))?;
+    hot.insert::<ColdPathSchema>(COLD_PATH_KEY.clone(), Bytes::from(path.to_owned()))
+        .await
+}
+
+/// A record repaired by `ImplStorage::recover`.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum Repair {
+    #[display(fmt = "latest block {} -> {}", from, to)]
+    LatestBlock { from: u64, to: u64 },
+
+    #[display(fmt = "latest proof {:?} -> {}", from, to)]
+    LatestProof { from: Option<u64>, to: u64 },
+
+    #[display(fmt = "transactions of block {} are missing", height)]
+    Transactions { height: u64 },
+
+    #[display(fmt = "receipts of block {} are missing", height)]
+    Receipts { height: u64 },
 }
 
 macro_rules! impl_storage_schema_for {
@@ -70,6 +328,8 @@ impl_storage_schema_for!(HashBlockSchema, Hash, u64, Block);
 impl_storage_schema_for!(LatestBlockSchema, Hash, Block, Block);
 impl_storage_schema_for!(LatestProofSchema, Hash, Proof, Block);
 impl_storage_schema_for!(OverlordWalSchema, Hash, Bytes, Wal);
+impl_storage_schema_for!(ColdHeightSchema, Hash, u64, Block);
+impl_storage_schema_for!(ColdPathSchema, Hash, Bytes, Block);
 
 macro_rules! batch_insert {
     ($self_: ident,$vec: expr, $schema: ident) => {
@@ -200,6 +460,10 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
         let wal_info = get!(self, OVERLORD_WAL_KEY.clone(), OverlordWalSchema);
         Ok(wal_info)
     }
+
+    async fn flush(&self) -> ProtocolResult<()> {
+        self.adapter.flush().await
+    }
 }
 
 fn opts_to_flat<T>(values: Vec<Option<T>>) -> Vec<T> {
@@ -218,6 +482,9 @@ fn check_none<T>(opt: Option<T>) -> ProtocolResult<T> {
 pub enum StorageError {
     #[display(fmt = "get none")]
     GetNone,
+
+    #[display(fmt = "invalid cold store path: {}", _0)]
+    ColdPath(String),
 }
 
 impl Error for StorageError {}
@@ -34,6 +34,8 @@ fn mock_signed_tx(tx_hash: Hash) -> SignedTransaction {
         cycles_limit: 10,
         cycles_price: 1,
         request,
+        sender: None,
+        valid_until_timestamp: None,
     };
 
     SignedTransaction {
@@ -85,11 +87,13 @@ fn mock_block(height: u64, block_hash: Hash) -> Block {
         proof: mock_proof(block_hash),
         validator_version: 1,
         validators: Vec::new(),
+        extra_data: Bytes::new(),
     };
 
     Block {
         header,
         ordered_tx_hashes: Vec::new(),
+        vote_extensions: vec![],
     }
 }
 
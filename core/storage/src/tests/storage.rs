@@ -1,12 +1,19 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
+use futures::executor::block_on;
+
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::Storage;
-use protocol::types::Hash;
+use protocol::traits::{Storage, StorageAdapter};
+use protocol::types::{Block, Hash, Proof};
 
 use crate::adapter::memory::MemoryAdapter;
+use crate::adapter::tiered::TieredAdapter;
 use crate::tests::{get_random_bytes, mock_block, mock_proof, mock_receipt, mock_signed_tx};
-use crate::ImplStorage;
+use crate::{
+    get_cold_path, set_cold_path, BlockSchema, ImplStorage, ReceiptSchema, Repair,
+    TransactionSchema,
+};
 
 #[test]
 fn test_storage_block_insert() {
@@ -100,3 +107,260 @@ fn test_storage_wal_insert() {
     let info_2 = exec!(storage.load_overlord_wal());
     assert_eq!(info, info_2);
 }
+
+#[test]
+fn test_storage_recover_interrupted_proof() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let block = mock_block(10, Hash::digest(get_random_bytes(10)));
+    let header_proof = block.header.proof.clone();
+    exec!(storage.insert_block(block.clone()));
+
+    // Proof of the next height is saved but its block is not
+    let mut proof = mock_proof(Hash::digest(get_random_bytes(10)));
+    proof.height = 11;
+    exec!(storage.update_latest_proof(proof));
+
+    let repairs = exec!(storage.recover());
+    assert_eq!(repairs, vec![Repair::LatestProof {
+        from: Some(11),
+        to:   header_proof.height,
+    }]);
+    assert_eq!(exec!(storage.get_latest_proof()), header_proof);
+
+    // Proof of the latest block is kept
+    let proof = proof_of(&block);
+    exec!(storage.update_latest_proof(proof.clone()));
+    assert!(exec!(storage.recover()).is_empty());
+    assert_eq!(exec!(storage.get_latest_proof()), proof);
+}
+
+#[test]
+fn test_storage_recover_interrupted_block() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+
+    let block_10 = mock_block(10, Hash::digest(get_random_bytes(10)));
+    exec!(storage.insert_block(block_10.clone()));
+    exec!(storage.update_latest_proof(proof_of(&block_10)));
+
+    // Block of the next height is saved but the latest block record is not
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let mut block_11 = mock_block(11, Hash::digest(get_random_bytes(10)));
+    block_11.header.pre_hash = Hash::digest(block_10.encode_fixed().unwrap());
+    block_11.ordered_tx_hashes = vec![tx_hash.clone()];
+    exec!(adapter.insert::<BlockSchema>(11, block_11.clone()));
+
+    // Not linked while its transactions are missing
+    assert!(exec!(storage.recover()).is_empty());
+    assert_eq!(exec!(storage.get_latest_block()), block_10);
+
+    exec!(storage.insert_transactions(vec![mock_signed_tx(tx_hash)]));
+    let repairs = exec!(storage.recover());
+    assert_eq!(repairs, vec![
+        Repair::LatestBlock { from: 10, to: 11 },
+        Repair::LatestProof {
+            from: Some(10),
+            to:   block_11.header.proof.height,
+        },
+    ]);
+    assert_eq!(exec!(storage.get_latest_block()), block_11);
+    assert_eq!(exec!(storage.get_latest_proof()), block_11.header.proof);
+}
+
+#[test]
+fn test_storage_recover_interrupted_txs() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let blocks = mock_chain(10, 2);
+    insert_chain(&storage, &blocks[..1]);
+    exec!(storage.update_latest_proof(proof_of(&blocks[0])));
+
+    // Transactions of the next block are saved but the block is not
+    let tx_hash = blocks[1].ordered_tx_hashes[0].clone();
+    exec!(storage.insert_transactions(vec![mock_signed_tx(tx_hash)]));
+
+    assert!(exec!(storage.recover()).is_empty());
+    assert_eq!(exec!(storage.get_latest_block()), blocks[0]);
+}
+
+#[test]
+fn test_storage_recover_interrupted_block_hash() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+    let blocks = mock_chain(10, 2);
+    insert_chain(&storage, &blocks[..1]);
+    exec!(storage.update_latest_proof(proof_of(&blocks[0])));
+    let tx_hash = blocks[0].ordered_tx_hashes[0].clone();
+    exec!(storage.insert_receipts(vec![mock_receipt(tx_hash)]));
+
+    // The block is saved but its hash index and the latest block record
+    // are not
+    let tx_hash = blocks[1].ordered_tx_hashes[0].clone();
+    exec!(storage.insert_transactions(vec![mock_signed_tx(tx_hash)]));
+    exec!(adapter.insert::<BlockSchema>(11, blocks[1].clone()));
+
+    let repairs = exec!(storage.recover());
+    assert_eq!(repairs[0], Repair::LatestBlock { from: 10, to: 11 });
+    let block_hash = Hash::digest(blocks[1].encode_fixed().unwrap());
+    assert_eq!(exec!(storage.get_block_by_hash(block_hash)), blocks[1]);
+}
+
+#[test]
+fn test_storage_recover_missing_txs() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let blocks = mock_chain(10, 2);
+    insert_chain(&storage, &blocks[..1]);
+
+    // The latest block is saved without its transactions, it's synced again
+    exec!(storage.insert_block(blocks[1].clone()));
+    exec!(storage.update_latest_proof(proof_of(&blocks[1])));
+
+    let repairs = exec!(storage.recover());
+    assert_eq!(repairs, vec![
+        Repair::Transactions { height: 11 },
+        Repair::LatestBlock { from: 11, to: 10 },
+        Repair::LatestProof {
+            from: Some(11),
+            to:   blocks[0].header.proof.height,
+        },
+    ]);
+    assert_eq!(exec!(storage.get_latest_block()), blocks[0]);
+    assert!(exec!(storage.recover()).is_empty());
+}
+
+#[test]
+fn test_storage_recover_missing_receipts() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let blocks = mock_chain(10, 4);
+    insert_chain(&storage, &blocks);
+    exec!(storage.update_latest_proof(proof_of(&blocks[3])));
+
+    // Receipts of executed heights are saved
+    let receipts = |blocks: &[Block]| {
+        blocks
+            .iter()
+            .flat_map(|b| b.ordered_tx_hashes.iter().cloned().map(mock_receipt))
+            .collect::<Vec<_>>()
+    };
+    exec!(storage.insert_receipts(receipts(&blocks[..3])));
+    assert!(exec!(storage.recover()).is_empty());
+
+    // Receipts of executed height 12 are missing, block 12 executed below it
+    // is the latest to re-execute it
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    insert_chain(&storage, &blocks);
+    exec!(storage.update_latest_proof(proof_of(&blocks[3])));
+    exec!(storage.insert_receipts(receipts(&blocks[..2])));
+
+    let repairs = exec!(storage.recover());
+    assert_eq!(repairs, vec![
+        Repair::Receipts { height: 12 },
+        Repair::LatestBlock { from: 13, to: 12 },
+        Repair::LatestProof {
+            from: Some(13),
+            to:   blocks[2].header.proof.height,
+        },
+    ]);
+    let latest = exec!(storage.get_latest_block());
+    assert_eq!(latest, blocks[2]);
+    assert_eq!(latest.header.exec_height, 11);
+    assert!(block_on(storage.get_block_by_height(13)).is_err());
+    assert!(exec!(storage.recover()).is_empty());
+}
+
+#[test]
+fn test_storage_move_to_cold() {
+    let hot = Arc::new(MemoryAdapter::new());
+    let cold = Arc::new(MemoryAdapter::new());
+    let adapter = TieredAdapter::new(Arc::clone(&hot), Some(Arc::clone(&cold)));
+    let storage = ImplStorage::new(Arc::new(adapter));
+
+    let mut tx_hashes = vec![];
+    for height in 0..=10 {
+        let tx_hash = Hash::digest(get_random_bytes(10));
+        let mut block = mock_block(1, Hash::digest(get_random_bytes(10)));
+        block.header.height = height;
+        block.header.exec_height = height.saturating_sub(1);
+        block.ordered_tx_hashes = vec![tx_hash.clone()];
+
+        exec!(storage.insert_transactions(vec![mock_signed_tx(tx_hash.clone())]));
+        exec!(storage.insert_receipts(vec![mock_receipt(tx_hash.clone())]));
+        exec!(storage.insert_block(block));
+        tx_hashes.push(tx_hash);
+    }
+
+    // Executed to 9, blocks below 6 are moved, 4 a call
+    assert_eq!(exec!(storage.move_to_cold(3, 4)), 4);
+    assert_eq!(exec!(storage.move_to_cold(3, 4)), 6);
+    assert_eq!(exec!(storage.move_to_cold(3, 4)), 6);
+
+    for height in 0..=10u64 {
+        let tx_hash = tx_hashes[height as usize].clone();
+        let moved = height < 6;
+        assert_eq!(exec!(hot.contains::<BlockSchema>(height)), !moved);
+        assert_eq!(exec!(cold.contains::<BlockSchema>(height)), moved);
+        assert_eq!(
+            exec!(hot.contains::<TransactionSchema>(tx_hash.clone())),
+            !moved
+        );
+        assert_eq!(
+            exec!(cold.contains::<ReceiptSchema>(tx_hash.clone())),
+            moved
+        );
+
+        // Reads fall through to the cold store
+        let block = exec!(storage.get_block_by_height(height));
+        assert_eq!(block.header.height, height);
+        let block_hash = Hash::digest(block.encode_fixed().unwrap());
+        assert_eq!(exec!(storage.get_block_by_hash(block_hash)), block);
+        assert_eq!(
+            exec!(storage.get_transaction_by_hash(tx_hash.clone())).tx_hash,
+            tx_hash
+        );
+        assert_eq!(exec!(storage.get_receipt(tx_hash.clone())).tx_hash, tx_hash);
+    }
+}
+
+#[test]
+fn test_storage_cold_path() {
+    let hot = MemoryAdapter::new();
+    assert_eq!(exec!(get_cold_path(&hot)), None);
+
+    let path = PathBuf::from("/data/cold_block_data");
+    exec!(set_cold_path(&hot, &path));
+    assert_eq!(exec!(get_cold_path(&hot)), Some(path));
+}
+
+fn proof_of(block: &Block) -> Proof {
+    let mut proof = mock_proof(Hash::digest(block.encode_fixed().unwrap()));
+    proof.height = block.header.height;
+    proof
+}
+
+// Linked blocks from the height, each with a transaction
+fn mock_chain(height: u64, count: u64) -> Vec<Block> {
+    let mut blocks: Vec<Block> = vec![];
+    for height in height..height + count {
+        let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        if let Some(parent) = blocks.last() {
+            block.header.pre_hash = Hash::digest(parent.encode_fixed().unwrap());
+        }
+        block.ordered_tx_hashes = vec![Hash::digest(get_random_bytes(10))];
+        blocks.push(block);
+    }
+    blocks
+}
+
+// Commit the blocks with their transactions
+fn insert_chain<Adapter: StorageAdapter>(storage: &ImplStorage<Adapter>, blocks: &[Block]) {
+    for block in blocks {
+        let txs = block
+            .ordered_tx_hashes
+            .iter()
+            .cloned()
+            .map(mock_signed_tx)
+            .collect();
+        exec!(storage.insert_transactions(txs));
+        exec!(storage.insert_block(block.clone()));
+    }
+}
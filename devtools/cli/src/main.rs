@@ -0,0 +1,388 @@
+#[macro_use]
+extern crate clap;
+
+use std::convert::TryFrom;
+use std::fs;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::{App, ArgMatches};
+use derive_more::Display;
+use futures::executor::block_on;
+use rand::distributions::Alphanumeric;
+use rand::{rngs::OsRng, Rng, RngCore};
+use serde::Serialize;
+use tentacle_secio::SecioKeyPair;
+
+use asset::types::InitGenesisPayload;
+use common_crypto::{BlsPrivateKey, PublicKey, ToBlsPublicKey};
+use common_keystore::{read_passphrase, serve_signer, Keystore, LocalSigner, Watermark};
+use core_storage::adapter::{rocks::RocksAdapter, tiered::TieredAdapter};
+use core_storage::{get_cold_path, ImplStorage};
+use framework::executor::{diff_replay_logs, ReplayLog};
+use framework::genesis::{verify_genesis, GenesisBuilder};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::Storage;
+use protocol::types::{Address, Block, Genesis, Hash, Hex, Metadata, SignedTransaction};
+use protocol::{Bytes, BytesMut, ProtocolError, ProtocolErrorKind, ProtocolResult};
+
+const PASSPHRASE_ENV: &str = "MUTA_KEYSTORE_PASSPHRASE";
+const SHARED_KEY_ENV: &str = "MUTA_SIGNER_SHARED_KEY";
+
+#[derive(Default, Serialize, Debug)]
+struct Keypair {
+    pub index:          usize,
+    pub private_key:    String,
+    pub public_key:     String,
+    pub address:        String,
+    pub peer_id:        String,
+    pub bls_public_key: String,
+}
+
+#[derive(Default, Serialize, Debug)]
+struct KeygenOutput {
+    pub common_ref: String,
+    pub keypairs:   Vec<Keypair>,
+}
+
+#[derive(Serialize, Debug)]
+struct DBStats {
+    pub category:    String,
+    pub keys:        u64,
+    pub key_bytes:   u64,
+    pub value_bytes: u64,
+}
+
+pub fn main() {
+    let yml = load_yaml!("cli.yml");
+    let matches = App::from(yml).get_matches();
+
+    let res = match matches.subcommand() {
+        ("keygen", Some(m)) => keygen(m),
+        ("genesis", Some(m)) => match m.subcommand() {
+            ("build", Some(m)) => genesis_build(m),
+            ("verify", Some(m)) => genesis_verify(m),
+            _ => unreachable!(),
+        },
+        ("peer-id", Some(m)) => peer_id(m),
+        ("keystore", Some(m)) => match m.subcommand() {
+            ("encrypt", Some(m)) => keystore_encrypt(m),
+            _ => unreachable!(),
+        },
+        ("signer", Some(m)) => match m.subcommand() {
+            ("serve", Some(m)) => signer_serve(m),
+            _ => unreachable!(),
+        },
+        ("block", Some(m)) => match m.subcommand() {
+            ("get", Some(m)) => block_get(m),
+            ("decode", Some(m)) => decode::<Block>(m),
+            _ => unreachable!(),
+        },
+        ("tx", Some(m)) => match m.subcommand() {
+            ("decode", Some(m)) => decode::<SignedTransaction>(m),
+            _ => unreachable!(),
+        },
+        ("replay", Some(m)) => match m.subcommand() {
+            ("diff", Some(m)) => replay_diff(m),
+            _ => unreachable!(),
+        },
+        ("db", Some(m)) => match m.subcommand() {
+            ("stats", Some(m)) => db_stats(m),
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    };
+
+    if let Err(e) = res {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn keygen(m: &ArgMatches) -> ProtocolResult<()> {
+    let number = value_t!(m, "number", usize).map_err(|e| CliError::Args(e.to_string()))?;
+    let priv_keys = values_t!(m.values_of("private_keys"), String).unwrap_or_default();
+    if priv_keys.len() > number {
+        return Err(
+            CliError::Args("private keys length can not be larger than number".to_owned()).into(),
+        );
+    }
+
+    let common_ref = match m.value_of("common_ref") {
+        Some(encoded) => String::from_utf8(decode_hex(encoded)?.to_vec())
+            .map_err(|e| CliError::Args(format!("common_ref is not utf8: {}", e)))?,
+        None => rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .collect::<String>(),
+    };
+
+    let mut output = KeygenOutput {
+        common_ref: add_0x(hex::encode(common_ref.clone())),
+        keypairs:   vec![],
+    };
+
+    for i in 0..number {
+        let seckey = match priv_keys.get(i) {
+            Some(key) => decode_hex(key)?,
+            None => {
+                let mut seed = [0u8; 32];
+                OsRng.fill_bytes(&mut seed);
+                Hash::digest(BytesMut::from(seed.as_ref()).freeze()).as_bytes()
+            }
+        };
+
+        let keypair = secio_keypair(&seckey)?;
+        let pubkey = keypair.to_public_key().inner();
+        let address = Address::from_pubkey_bytes(Bytes::from(pubkey.clone()))?;
+
+        let bls_priv_key = BlsPrivateKey::try_from([&[0u8; 16], seckey.as_ref()].concat().as_ref())
+            .map_err(|e| CliError::Crypto(format!("{:?}", e)))?;
+        let bls_pub_key = bls_priv_key.pub_key(&common_ref.as_str().into());
+
+        output.keypairs.push(Keypair {
+            index:          i + 1,
+            private_key:    add_0x(hex::encode(seckey.as_ref())),
+            public_key:     add_0x(hex::encode(pubkey)),
+            address:        address.as_hex(),
+            peer_id:        keypair.peer_id().to_base58(),
+            bls_public_key: add_0x(hex::encode(bls_pub_key.to_bytes())),
+        });
+    }
+
+    print_json(&output)
+}
+
+fn genesis_build(m: &ArgMatches) -> ProtocolResult<()> {
+    let metadata: Metadata = read_json(m.value_of("metadata").expect("metadata is required"))?;
+    let timestamp = value_t!(m, "timestamp", u64).map_err(|e| CliError::Args(e.to_string()))?;
+
+    let mut builder = GenesisBuilder::new()
+        .timestamp(timestamp)
+        .metadata(metadata);
+
+    if let Some(path) = m.value_of("asset") {
+        let asset: InitGenesisPayload = read_json(path)?;
+        builder = builder.asset(asset);
+    }
+
+    if let Some(prevhash) = m.value_of("prevhash") {
+        builder = builder.prevhash(Hex::from_string(prevhash.to_owned())?);
+    }
+
+    let genesis = builder.build()?;
+    let toml_str = toml::to_string(&genesis).map_err(|e| CliError::Toml(e.to_string()))?;
+    println!("{}", toml_str);
+    Ok(())
+}
+
+fn genesis_verify(m: &ArgMatches) -> ProtocolResult<()> {
+    let path = m.value_of("genesis").expect("genesis is required");
+    let genesis_toml = fs::read_to_string(path).map_err(CliError::Io)?;
+    let genesis: Genesis =
+        toml::from_str(&genesis_toml).map_err(|e| CliError::Toml(e.to_string()))?;
+
+    verify_genesis(&genesis)?;
+    println!("genesis {} is valid", path);
+    Ok(())
+}
+
+fn peer_id(m: &ArgMatches) -> ProtocolResult<()> {
+    let seckey = decode_hex(m.value_of("private_key").expect("private_key is required"))?;
+    let keypair = secio_keypair(&seckey)?;
+
+    println!("{}", keypair.peer_id().to_base58());
+    Ok(())
+}
+
+fn keystore_encrypt(m: &ArgMatches) -> ProtocolResult<()> {
+    let seckey = decode_hex(m.value_of("private_key").expect("private_key is required"))?;
+    let output = m.value_of("output").expect("output is required");
+    let log_n = value_t!(m, "scrypt_log_n", u8).map_err(|e| CliError::Args(e.to_string()))?;
+
+    let passphrase = read_passphrase(PASSPHRASE_ENV)?;
+    let keystore = Keystore::encrypt_with(&seckey, &passphrase, log_n)?;
+    keystore.save(output)?;
+
+    println!(
+        "keystore of {} is saved to {}",
+        keystore.address.as_hex(),
+        output
+    );
+    Ok(())
+}
+
+fn signer_serve(m: &ArgMatches) -> ProtocolResult<()> {
+    let keystore = Keystore::load(m.value_of("keystore").expect("keystore is required"))?;
+    let listen = value_t!(m, "listen", SocketAddr).map_err(|e| CliError::Args(e.to_string()))?;
+    let watermark = Watermark::load(m.value_of("watermark").expect("watermark is required"))?;
+    let shared_key = std::env::var(SHARED_KEY_ENV)
+        .map_err(|_| CliError::Args(format!("no shared key in env {}", SHARED_KEY_ENV)))?;
+    let shared_key = decode_hex(&shared_key)?;
+
+    let passphrase = read_passphrase(PASSPHRASE_ENV)?;
+    let signer = LocalSigner::from_privkey(&keystore.decrypt(&passphrase)?)?;
+    let listener = TcpListener::bind(listen).map_err(CliError::Io)?;
+
+    println!(
+        "remote signer of {} is listening on {}",
+        keystore.address.as_hex(),
+        listen
+    );
+    serve_signer(listener, Arc::new(signer), shared_key, watermark).map_err(CliError::Io)?;
+    Ok(())
+}
+
+fn block_get(m: &ArgMatches) -> ProtocolResult<()> {
+    let hot = Arc::new(open_block_db(m, 64)?);
+    let cold = open_cold_block_db(m, &hot, 64)?.map(Arc::new);
+    let storage = ImplStorage::new(Arc::new(TieredAdapter::new(hot, cold)));
+
+    let block = match m.value_of("height") {
+        Some(height) => {
+            let height = height
+                .parse::<u64>()
+                .map_err(|e| CliError::Args(format!("invalid height: {}", e)))?;
+            block_on(storage.get_block_by_height(height))?
+        }
+        None => block_on(storage.get_latest_block())?,
+    };
+
+    print_json(&block)
+}
+
+fn decode<T: FixedCodec + Serialize>(m: &ArgMatches) -> ProtocolResult<()> {
+    let bytes = decode_hex(m.value_of("input").expect("input is required"))?;
+    let value = T::decode_fixed(bytes)?;
+
+    print_json(&value)
+}
+
+fn replay_diff(m: &ArgMatches) -> ProtocolResult<()> {
+    let local: ReplayLog = read_json(m.value_of("local").expect("local is required"))?;
+    let remote: ReplayLog = read_json(m.value_of("remote").expect("remote is required"))?;
+    if local.height != remote.height {
+        return Err(CliError::Args(format!(
+            "replay logs are of different heights {} and {}",
+            local.height, remote.height
+        ))
+        .into());
+    }
+
+    match diff_replay_logs(&local, &remote) {
+        Some(divergence) => print_json(&divergence),
+        None => {
+            println!("replay logs of height {} are the same", local.height);
+            Ok(())
+        }
+    }
+}
+
+fn db_stats(m: &ArgMatches) -> ProtocolResult<()> {
+    let max_open_files =
+        value_t!(m, "max_open_files", i32).map_err(|e| CliError::Args(e.to_string()))?;
+    let adapter = open_block_db(m, max_open_files)?;
+
+    let stats = adapter
+        .stats()?
+        .into_iter()
+        .map(|stat| DBStats {
+            category:    stat.category.to_string(),
+            keys:        stat.keys,
+            key_bytes:   stat.key_bytes,
+            value_bytes: stat.value_bytes,
+        })
+        .collect::<Vec<_>>();
+
+    print_json(&stats)
+}
+
+fn open_block_db(m: &ArgMatches, max_open_files: i32) -> ProtocolResult<RocksAdapter> {
+    let mut path = PathBuf::from(m.value_of("data_path").expect("data_path is required"));
+    path.push("rocksdb");
+    path.push("block_data");
+
+    if !path.exists() {
+        return Err(CliError::Args(format!("block database {:?} does not exist", path)).into());
+    }
+
+    RocksAdapter::new(path, max_open_files)
+}
+
+// Blocks moved to the cold store recorded in the block db, block dbs of
+// earlier versions have it at the default path
+fn open_cold_block_db(
+    m: &ArgMatches,
+    hot: &RocksAdapter,
+    max_open_files: i32,
+) -> ProtocolResult<Option<RocksAdapter>> {
+    let path = match block_on(get_cold_path(hot))? {
+        Some(path) => path,
+        None => {
+            let mut path = PathBuf::from(m.value_of("data_path").expect("data_path is required"));
+            path.push("rocksdb");
+            path.push("cold_block_data");
+            path
+        }
+    };
+
+    if !path.exists() {
+        return Ok(None);
+    }
+    RocksAdapter::compressed(path, max_open_files).map(Some)
+}
+
+fn secio_keypair(seckey: &Bytes) -> ProtocolResult<SecioKeyPair> {
+    let keypair = SecioKeyPair::secp256k1_raw_key(seckey.as_ref())
+        .map_err(|e| CliError::Crypto(format!("{:?}", e)))?;
+    Ok(keypair)
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> ProtocolResult<T> {
+    let json = fs::read_to_string(path).map_err(CliError::Io)?;
+    let value = serde_json::from_str(&json).map_err(CliError::Json)?;
+    Ok(value)
+}
+
+fn print_json<T: Serialize>(value: &T) -> ProtocolResult<()> {
+    let json = serde_json::to_string_pretty(value).map_err(CliError::Json)?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn decode_hex(s: &str) -> ProtocolResult<Bytes> {
+    let s = if s.starts_with("0x") { &s[2..] } else { s };
+    let bytes = hex::decode(s).map_err(|e| CliError::Args(format!("invalid hex: {}", e)))?;
+    Ok(Bytes::from(bytes))
+}
+
+fn add_0x(s: String) -> String {
+    "0x".to_owned() + &s
+}
+
+#[derive(Debug, Display)]
+pub enum CliError {
+    #[display(fmt = "invalid arguments: {}", _0)]
+    Args(String),
+
+    #[display(fmt = "io error: {}", _0)]
+    Io(std::io::Error),
+
+    #[display(fmt = "json error: {}", _0)]
+    Json(serde_json::Error),
+
+    #[display(fmt = "toml error: {}", _0)]
+    Toml(String),
+
+    #[display(fmt = "crypto error: {}", _0)]
+    Crypto(String),
+}
+
+impl std::error::Error for CliError {}
+
+impl From<CliError> for ProtocolError {
+    fn from(err: CliError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Main, Box::new(err))
+    }
+}
@@ -0,0 +1,369 @@
+//! Test vectors of protocol types for implementations in other languages.
+//! Each vector has the json fields of a value, its rlp and fixed codec
+//! encodings, and the hash of the encoding. Vectors of signed transactions
+//! also have the private key signing them, and the signing hash in their
+//! signature domain if any.
+//!
+//! Values are seeded, so vectors never change unless encodings of types
+//! change, and `vectors/` in this crate is checked against them.
+
+use std::convert::TryFrom;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use common_crypto::{
+    HashValue, PrivateKey, PublicKey, Secp256k1PrivateKey, Signature, ToPublicKey,
+};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::ServiceResponse;
+use protocol::types::{
+    signing_hash, upgrade_signal, Address, Block, BlockHeader, ChainSpec, Event, Hash, Hex,
+    Metadata, Pill, Proof, RawTransaction, Receipt, ReceiptResponse, SignatureDomain,
+    SignedTransaction, TransactionRequest, Validator, ValidatorExtend, VoteExtension,
+    TRANSACTION_SIGNATURE_PURPOSE,
+};
+use protocol::{Bytes, ProtocolResult};
+
+/// Vectors of a type, written to `<file>.json`.
+pub struct Fixture {
+    pub file:    &'static str,
+    pub type_:   &'static str,
+    pub vectors: Vec<Value>,
+}
+
+impl Fixture {
+    pub fn to_json(&self) -> Value {
+        json!({ "type": self.type_, "vectors": self.vectors })
+    }
+}
+
+pub fn fixtures() -> ProtocolResult<Vec<Fixture>> {
+    let fixture = |file, type_, vectors| Fixture {
+        file,
+        type_,
+        vectors,
+    };
+
+    let mut signed_txs = vec![];
+    for (name, seed, sender, valid_until, domain) in vec![
+        ("seed_1", 1, None, None, None),
+        (
+            "seed_2_with_domain",
+            2,
+            None,
+            Some(1_600_000_000_000),
+            Some(SignatureDomain {
+                network_tag: "mainnet".to_owned(),
+                chain_id:    hash(1),
+            }),
+        ),
+        ("seed_3_with_sender", 3, Some(address(7)), None, None),
+    ] {
+        let (stx, signature) = signed_tx(seed, sender, valid_until, domain)?;
+        let mut signed = vector(name, &stx)?;
+        signed["signature"] = signature;
+        signed_txs.push(signed);
+    }
+
+    let mut chain_spec = ChainSpec::default();
+    chain_spec.schedule("tx_cycles", 10);
+
+    Ok(vec![
+        fixture("hash", "Hash", vec![vector("pattern", &hash(1))?]),
+        fixture("address", "Address", vec![vector("pattern", &address(7))?]),
+        fixture("hex", "Hex", vec![vector(
+            "short",
+            &Hex::from_static(b"\x01\xab"),
+        )?]),
+        fixture("proof", "Proof", vec![vector("seeded", &proof())?]),
+        fixture("validator", "Validator", vec![vector(
+            "seeded",
+            &validator(),
+        )?]),
+        fixture("block_header", "BlockHeader", vec![
+            vector("seeded", &header(Bytes::new()))?,
+            vector("with_extra_data", &header(upgrade_signal("fork-1")))?,
+        ]),
+        fixture("vote_extension", "VoteExtension", vec![vector(
+            "seeded",
+            &vote_extension(),
+        )?]),
+        fixture("block", "Block", vec![
+            vector("legacy", &block(vec![]))?,
+            vector("with_vote_extensions", &block(vec![vote_extension()]))?,
+        ]),
+        fixture("pill", "Pill", vec![vector("seeded", &Pill {
+            block:          block(vec![]),
+            propose_hashes: vec![hash(10)],
+        })?]),
+        fixture("raw_transaction", "RawTransaction", vec![
+            vector("legacy", &raw_tx(hash(2), None, None))?,
+            vector("with_sender", &raw_tx(hash(2), Some(address(7)), None))?,
+            vector(
+                "with_expiration",
+                &raw_tx(hash(2), None, Some(1_600_000_000_000)),
+            )?,
+            vector(
+                "with_sender_and_expiration",
+                &raw_tx(hash(2), Some(address(7)), Some(1_600_000_000_000)),
+            )?,
+        ]),
+        fixture("signed_transaction", "SignedTransaction", signed_txs),
+        fixture("event", "Event", vec![rlp_vector("seeded", &event())?]),
+        fixture("receipt_response", "ReceiptResponse", vec![vector(
+            "succeed",
+            &receipt_response(),
+        )?]),
+        fixture("receipt", "Receipt", vec![vector("seeded", &Receipt {
+            state_root:  hash(1),
+            height:      10,
+            tx_hash:     hash(2),
+            cycles_used: 21000,
+            events:      vec![event()],
+            response:    receipt_response(),
+        })?]),
+        fixture("validator_extend", "ValidatorExtend", vec![rlp_vector(
+            "seeded",
+            &validator_extend(),
+        )?]),
+        fixture("metadata", "Metadata", vec![
+            vector("seeded", &metadata(ChainSpec::default()))?,
+            vector("with_chain_spec", &metadata(chain_spec))?,
+        ]),
+    ])
+}
+
+fn vector<T: Serialize + rlp::Encodable + FixedCodec>(
+    name: &str,
+    val: &T,
+) -> ProtocolResult<Value> {
+    let mut vector = rlp_vector(name, val)?;
+    vector["fixed_codec"] = json!(to_hex(&val.encode_fixed()?));
+    Ok(vector)
+}
+
+// Types encoded in others only, they have no fixed codec
+fn rlp_vector<T: Serialize + rlp::Encodable>(name: &str, val: &T) -> ProtocolResult<Value> {
+    let encoded = rlp::encode(val);
+
+    Ok(json!({
+        "name": name,
+        "fields": serde_json::to_value(val).expect("json of protocol types"),
+        "rlp": to_hex(&encoded),
+        "fixed_codec": Value::Null,
+        "hash": Hash::digest(Bytes::from(encoded)).as_hex(),
+    }))
+}
+
+fn signed_tx(
+    seed: u8,
+    sender: Option<Address>,
+    valid_until: Option<u64>,
+    domain: Option<SignatureDomain>,
+) -> ProtocolResult<(SignedTransaction, Value)> {
+    let private_key = Secp256k1PrivateKey::try_from(&[seed; 32][..]).expect("seeded private key");
+    let pubkey = private_key.pub_key().to_bytes();
+
+    let raw = raw_tx(hash(10 + seed), sender, valid_until);
+    let tx_hash = Hash::digest(raw.encode_fixed()?);
+    let signing = signing_hash(domain.as_ref(), TRANSACTION_SIGNATURE_PURPOSE, &tx_hash);
+    let hash_value = HashValue::try_from(signing.as_bytes().as_ref()).expect("32 bytes hash");
+    let signature = private_key.sign_message(&hash_value).to_bytes();
+
+    let json = json!({
+        "private_key": to_hex(&[seed; 32]),
+        "pubkey": to_hex(&pubkey),
+        "address": Address::from_pubkey_bytes(pubkey.clone())?.as_hex(),
+        "domain": domain,
+        "signing_hash": signing.as_hex(),
+        "signature": to_hex(&signature),
+    });
+    let stx = SignedTransaction {
+        raw,
+        tx_hash,
+        pubkey,
+        signature,
+    };
+    Ok((stx, json))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    "0x".to_owned() + &hex::encode(bytes)
+}
+
+fn hash(n: u8) -> Hash {
+    Hash::from_bytes(Bytes::from(vec![n; 32])).expect("32 bytes hash")
+}
+
+fn address(n: u8) -> Address {
+    Address::from_bytes(Bytes::from(vec![n; 20])).expect("20 bytes address")
+}
+
+fn proof() -> Proof {
+    Proof {
+        height:     9,
+        round:      1,
+        block_hash: hash(8),
+        signature:  Bytes::from_static(b"\xab\xcd"),
+        bitmap:     Bytes::from_static(b"\x01"),
+    }
+}
+
+fn validator() -> Validator {
+    Validator {
+        address:        address(7),
+        propose_weight: 1,
+        vote_weight:    1,
+    }
+}
+
+fn header(extra_data: Bytes) -> BlockHeader {
+    BlockHeader {
+        chain_id: hash(1),
+        // Larger than integers of javascript
+        height: 9_007_199_254_740_993,
+        exec_height: 9,
+        pre_hash: hash(2),
+        timestamp: 1_600_000_000_000,
+        logs_bloom: vec![],
+        order_root: hash(3),
+        confirm_root: vec![hash(4)],
+        state_root: hash(5),
+        receipt_root: vec![hash(6)],
+        cycles_used: vec![100],
+        proposer: address(7),
+        proof: proof(),
+        validator_version: 0,
+        validators: vec![validator()],
+        extra_data,
+    }
+}
+
+fn vote_extension() -> VoteExtension {
+    VoteExtension {
+        height:     9_007_199_254_740_992,
+        voter:      address(7),
+        block_hash: hash(8),
+        payload:    Bytes::from_static(b"price:100"),
+        signature:  Bytes::from_static(b"\x0a\x0b"),
+    }
+}
+
+fn block(vote_extensions: Vec<VoteExtension>) -> Block {
+    Block {
+        header: header(Bytes::new()),
+        ordered_tx_hashes: vec![hash(9)],
+        vote_extensions,
+    }
+}
+
+fn raw_tx(
+    nonce: Hash,
+    sender: Option<Address>,
+    valid_until_timestamp: Option<u64>,
+) -> RawTransaction {
+    RawTransaction {
+        chain_id: hash(1),
+        nonce,
+        timeout: 100,
+        cycles_price: 1,
+        cycles_limit: u64::max_value(),
+        request: TransactionRequest {
+            service_name: "asset".to_owned(),
+            method:       "transfer".to_owned(),
+            payload:      json!({
+                "asset_id": hash(4),
+                "to": address(5),
+                "value": 100,
+            })
+            .to_string(),
+        },
+        sender,
+        valid_until_timestamp,
+    }
+}
+
+fn event() -> Event {
+    Event {
+        service: "asset".to_owned(),
+        data:    "{}".to_owned(),
+    }
+}
+
+fn receipt_response() -> ReceiptResponse {
+    ReceiptResponse {
+        service_name: "asset".to_owned(),
+        method:       "transfer".to_owned(),
+        response:     ServiceResponse::from_succeed("".to_owned()),
+    }
+}
+
+fn validator_extend() -> ValidatorExtend {
+    ValidatorExtend {
+        bls_pub_key:    Hex::from_static(b"\x04\x18"),
+        address:        address(7),
+        propose_weight: 1,
+        vote_weight:    1,
+    }
+}
+
+fn metadata(spec: ChainSpec) -> Metadata {
+    Metadata {
+        chain_id:                 hash(1),
+        common_ref:               Hex::from_static(b"\x01\xab"),
+        timeout_gap:              20,
+        cycles_limit:             999_999_999_999,
+        cycles_price:             1,
+        interval:                 3000,
+        verifier_list:            vec![validator_extend()],
+        propose_ratio:            15,
+        prevote_ratio:            10,
+        precommit_ratio:          10,
+        brake_ratio:              7,
+        tx_num_limit:             20000,
+        max_tx_size:              1024,
+        proposer_fee_ratio:       0,
+        treasury_fee_ratio:       0,
+        treasury:                 address(0),
+        network_tag:              "mainnet".to_owned(),
+        domain_separation_height: 0,
+        max_block_size:           0,
+        tx_base_cycles:           0,
+        storage_deposit_per_byte: 0,
+        rent_epoch:               0,
+        rent_per_byte:            0,
+        halt_height:              0,
+        upgrade_name:             String::new(),
+        upgrade_window:           0,
+        upgrade_threshold:        0,
+        upgrade_height:           0,
+        chain_spec:               spec,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use serde_json::Value;
+
+    use super::fixtures;
+
+    // Run the binary to regenerate vectors once encodings are changed on
+    // purpose, implementations of other languages rely on them.
+    #[test]
+    fn test_vectors_are_up_to_date() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("vectors");
+        let fixtures = fixtures().unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), fixtures.len());
+
+        for fixture in fixtures.iter() {
+            let path = dir.join(format!("{}.json", fixture.file));
+            let file = fs::read_to_string(&path).unwrap();
+            let expect: Value = serde_json::from_str(&file).unwrap();
+            assert_eq!(fixture.to_json(), expect, "{:?} is outdated", path);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// Write vectors to the directory of args, or `vectors/` of this crate.
+fn main() {
+    let dir = env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("vectors"));
+    fs::create_dir_all(&dir).expect("create vectors dir");
+
+    for fixture in muta_fixtures::fixtures().expect("fixtures").iter() {
+        let json = serde_json::to_string_pretty(&fixture.to_json()).expect("json of vectors");
+        let path = dir.join(format!("{}.json", fixture.file));
+
+        fs::write(&path, json + "\n").expect("write vectors");
+        println!("{}", path.display());
+    }
+}
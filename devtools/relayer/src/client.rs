@@ -0,0 +1,258 @@
+use std::convert::TryFrom;
+
+use derive_more::{Display, From};
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use common_crypto::{
+    HashValue, PrivateKey, PublicKey, Secp256k1PrivateKey, Signature, ToPublicKey,
+};
+use protocol::fixed_codec::FixedCodec;
+use protocol::types::{
+    signing_hash, Address, Block, CommitmentScheme, Hash, Metadata, StateProof,
+    TRANSACTION_SIGNATURE_PURPOSE,
+};
+use protocol::{Bytes, ProtocolError};
+
+// Relay transactions expire after these blocks
+const TX_TIMEOUT: u64 = 10;
+
+/// Talks to a node through its GraphQL api.
+pub struct Client {
+    url:          String,
+    http:         reqwest::blocking::Client,
+    private_key:  Secp256k1PrivateKey,
+    address:      Address,
+    cycles_limit: u64,
+}
+
+impl Client {
+    pub fn new(url: String, private_key: &[u8], cycles_limit: u64) -> Result<Self, RelayerError> {
+        let private_key = Secp256k1PrivateKey::try_from(private_key)
+            .map_err(|e| RelayerError::PrivateKey(format!("{:?}", e)))?;
+        let address = Address::from_pubkey_bytes(private_key.pub_key().to_bytes())?;
+
+        Ok(Self {
+            url,
+            http: reqwest::blocking::Client::new(),
+            private_key,
+            address,
+            cycles_limit,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn get_block(&self, height: Option<u64>) -> Result<Block, RelayerError> {
+        let data = self.request(
+            "query($height: Uint64) { getBlock(height: $height) { encoded } }",
+            json!({ "height": height.map(to_uint64) }),
+        )?;
+
+        let encoded = from_hex(&data["getBlock"]["encoded"])?;
+        Ok(Block::decode_fixed(encoded)?)
+    }
+
+    pub fn query_service<T: DeserializeOwned>(
+        &self,
+        service: &str,
+        method: &str,
+        payload: Value,
+    ) -> Result<T, RelayerError> {
+        let data = self.request(
+            "query($service: String!, $method: String!, $payload: String!, $caller: Address!) { \
+             queryService(serviceName: $service, method: $method, payload: $payload, caller: $caller) \
+             { code succeedData errorMessage } }",
+            json!({
+                "service": service,
+                "method": method,
+                "payload": payload.to_string(),
+                "caller": self.address.as_hex(),
+            }),
+        )?;
+
+        let resp = &data["queryService"];
+        let code = from_uint64(&resp["code"])?;
+        if code != 0 {
+            return Err(RelayerError::Service {
+                code,
+                message: resp["errorMessage"].as_str().unwrap_or_default().to_owned(),
+            });
+        }
+
+        let succeed_data = resp["succeedData"].as_str().unwrap_or_default();
+        Ok(serde_json::from_str(succeed_data)?)
+    }
+
+    pub fn get_state_proof(
+        &self,
+        height: u64,
+        service: &str,
+        key: &Bytes,
+    ) -> Result<StateProof, RelayerError> {
+        let data = self.request(
+            "query($height: Uint64, $service: String!, $key: Bytes!) { \
+             getStateProof(height: $height, serviceName: $service, key: $key) \
+             { scheme serviceName key serviceRoot serviceProof keyProof } }",
+            json!({
+                "height": to_uint64(height),
+                "service": service,
+                "key": "0x".to_owned() + &hex::encode(key),
+            }),
+        )?;
+
+        let proof = &data["getStateProof"];
+        let nodes = |v: &Value| -> Result<Vec<Bytes>, RelayerError> {
+            v.as_array()
+                .ok_or_else(|| RelayerError::Response(v.to_string()))?
+                .iter()
+                .map(from_hex)
+                .collect()
+        };
+
+        let scheme = match proof["scheme"].as_str() {
+            Some("MERKLE_PATRICIA") => CommitmentScheme::MerklePatricia,
+            _ => return Err(RelayerError::Response(proof["scheme"].to_string())),
+        };
+
+        Ok(StateProof {
+            scheme,
+            service_name: proof["serviceName"].as_str().unwrap_or_default().to_owned(),
+            key: from_hex(&proof["key"])?,
+            service_root: Hash::from_hex(proof["serviceRoot"].as_str().unwrap_or_default())?,
+            service_proof: nodes(&proof["serviceProof"])?,
+            key_proof: nodes(&proof["keyProof"])?,
+        })
+    }
+
+    // Sign and send a transaction at the latest height of the chain
+    pub fn send_transaction(
+        &self,
+        block: &Block,
+        service: &str,
+        method: &str,
+        payload: Value,
+    ) -> Result<Hash, RelayerError> {
+        let mut nonce = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let nonce = Hash::digest(Bytes::from(nonce.to_vec()));
+        let timeout = block.header.height + TX_TIMEOUT;
+
+        let raw = protocol::types::RawTransaction {
+            chain_id: block.header.chain_id.clone(),
+            nonce: nonce.clone(),
+            timeout,
+            cycles_price: 1,
+            cycles_limit: self.cycles_limit,
+            request: protocol::types::TransactionRequest {
+                service_name: service.to_owned(),
+                method:       method.to_owned(),
+                payload:      payload.to_string(),
+            },
+            sender: None,
+            valid_until_timestamp: None,
+        };
+        let tx_hash = Hash::digest(raw.encode_fixed()?);
+
+        let metadata: Metadata = self.query_service("metadata", "get_metadata", Value::Null)?;
+        let domain = metadata.signature_domain(block.header.height + 1);
+        let signing = signing_hash(domain.as_ref(), TRANSACTION_SIGNATURE_PURPOSE, &tx_hash);
+        let hash_value = HashValue::try_from(signing.as_bytes().as_ref())
+            .map_err(|e| RelayerError::PrivateKey(format!("{:?}", e)))?;
+        let signature = self.private_key.sign_message(&hash_value);
+
+        self.request(
+            "mutation($raw: InputRawTransaction!, $encryption: InputTransactionEncryption!) { \
+             sendTransaction(inputRaw: $raw, inputEncryption: $encryption) }",
+            json!({
+                "raw": {
+                    "chainId": raw.chain_id.as_hex(),
+                    "cyclesLimit": to_uint64(raw.cycles_limit),
+                    "cyclesPrice": to_uint64(raw.cycles_price),
+                    "nonce": nonce.as_hex(),
+                    "timeout": to_uint64(timeout),
+                    "serviceName": service,
+                    "method": method,
+                    "payload": raw.request.payload,
+                },
+                "encryption": {
+                    "txHash": tx_hash.as_hex(),
+                    "pubkey": "0x".to_owned() + &hex::encode(self.private_key.pub_key().to_bytes()),
+                    "signature": "0x".to_owned() + &hex::encode(signature.to_bytes()),
+                },
+            }),
+        )?;
+
+        Ok(tx_hash)
+    }
+
+    fn request(&self, query: &str, variables: Value) -> Result<Value, RelayerError> {
+        let resp: Value = self
+            .http
+            .post(&self.url)
+            .json(&json!({ "query": query, "variables": variables }))
+            .send()?
+            .json()?;
+
+        if let Some(errors) = resp.get("errors") {
+            return Err(RelayerError::GraphQL(errors.to_string()));
+        }
+        resp.get("data")
+            .cloned()
+            .ok_or_else(|| RelayerError::Response(resp.to_string()))
+    }
+}
+
+fn to_uint64(n: u64) -> String {
+    format!("0x{:x}", n)
+}
+
+fn from_uint64(v: &Value) -> Result<u64, RelayerError> {
+    let s = v
+        .as_str()
+        .ok_or_else(|| RelayerError::Response(v.to_string()))?;
+    u64::from_str_radix(s.trim_start_matches("0x"), 16)
+        .map_err(|_| RelayerError::Response(v.to_string()))
+}
+
+fn from_hex(v: &Value) -> Result<Bytes, RelayerError> {
+    let s = v
+        .as_str()
+        .ok_or_else(|| RelayerError::Response(v.to_string()))?;
+    let bytes = hex::decode(s.trim_start_matches("0x"))
+        .map_err(|_| RelayerError::Response(v.to_string()))?;
+    Ok(Bytes::from(bytes))
+}
+
+#[derive(Debug, Display, From)]
+pub enum RelayerError {
+    #[display(fmt = "http {}", _0)]
+    Http(reqwest::Error),
+
+    #[display(fmt = "json {}", _0)]
+    Json(serde_json::Error),
+
+    #[display(fmt = "{}", _0)]
+    Protocol(ProtocolError),
+
+    #[display(fmt = "graphql errors {}", _0)]
+    #[from(ignore)]
+    GraphQL(String),
+
+    #[display(fmt = "unexpected response {}", _0)]
+    #[from(ignore)]
+    Response(String),
+
+    #[display(fmt = "invalid private key {}", _0)]
+    #[from(ignore)]
+    PrivateKey(String),
+
+    #[display(fmt = "service error {} {}", code, message)]
+    #[from(ignore)]
+    Service { code: u64, message: String },
+}
+
+impl std::error::Error for RelayerError {}
@@ -0,0 +1,46 @@
+#[macro_use]
+extern crate clap;
+
+mod client;
+mod relay;
+
+use std::thread;
+use std::time::Duration;
+
+use clap::App;
+
+use crate::client::Client;
+use crate::relay::Path;
+
+pub fn main() {
+    let yml = load_yaml!("relayer.yml");
+    let m = App::from(yml).get_matches();
+
+    let private_key = value_t!(m, "private_key", String).unwrap();
+    let private_key = hex::decode(private_key.trim_start_matches("0x"))
+        .expect("private key should be a hex string");
+    let interval = value_t!(m, "interval", u64).unwrap();
+    let cycles_limit = value_t!(m, "cycles_limit", u64).unwrap();
+
+    let new_client = |url: String| {
+        Client::new(url, &private_key, cycles_limit).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    };
+    let chain_a = new_client(value_t!(m, "chain_a", String).unwrap());
+    let chain_b = new_client(value_t!(m, "chain_b", String).unwrap());
+
+    let mut a_to_b = Path::new(&chain_a, &chain_b);
+    let mut b_to_a = Path::new(&chain_b, &chain_a);
+    loop {
+        // One direction failing doesn't stop the other
+        for path in [&mut a_to_b, &mut b_to_a].iter_mut() {
+            if let Err(e) = path.relay() {
+                eprintln!("relay failed: {}", e);
+            }
+        }
+
+        thread::sleep(Duration::from_millis(interval));
+    }
+}
@@ -0,0 +1,143 @@
+use serde_json::json;
+
+use bridge::commitment_key;
+use bridge::types::{ClientState, Packet, ReceivePacketPayload, UpdateClientPayload};
+use protocol::fixed_codec::FixedCodec;
+use protocol::types::Hex;
+
+use crate::client::{Client, RelayerError};
+
+const BRIDGE_SERVICE: &str = "bridge";
+// Sent transactions are resent once they time out
+const RESEND_AFTER: u64 = 10;
+
+/// Relays packets from source chain to destination chain. Destination
+/// tracks source by light client, so the client is updated before packets
+/// committed after it are proved.
+pub struct Path<'a> {
+    src: &'a Client,
+    dst: &'a Client,
+
+    // Height of client update and next packet sequence in flight, with
+    // destination height they are sent at
+    pending_update:  Option<(u64, u64)>,
+    pending_packets: Option<(u64, u64)>,
+}
+
+impl<'a> Path<'a> {
+    pub fn new(src: &'a Client, dst: &'a Client) -> Self {
+        Path {
+            src,
+            dst,
+            pending_update: None,
+            pending_packets: None,
+        }
+    }
+
+    pub fn relay(&mut self) -> Result<(), RelayerError> {
+        let src_latest = self.src.get_block(None)?;
+        let dst_latest = self.dst.get_block(None)?;
+        let src_chain = src_latest.header.chain_id.clone();
+        let dst_chain = dst_latest.header.chain_id.clone();
+        let dst_height = dst_latest.header.height;
+
+        let client: ClientState = self.dst.query_service(
+            BRIDGE_SERVICE,
+            "get_client",
+            json!({ "chain_id": src_chain }),
+        )?;
+
+        // Proof of a block is in header of the next block
+        let target = src_latest.header.height.saturating_sub(1);
+        if target > client.height && !in_flight(self.pending_update, client.height, dst_height) {
+            let block = self.src.get_block(Some(target))?;
+            let payload = UpdateClientPayload {
+                chain_id: src_chain.clone(),
+                block:    Hex::from_bytes(block.encode_fixed()?),
+                proof:    src_latest.header.proof.clone(),
+            };
+
+            let tx_hash = self.dst.send_transaction(
+                &dst_latest,
+                BRIDGE_SERVICE,
+                "update_client",
+                serde_json::to_value(payload)?,
+            )?;
+            println!(
+                "{} update client of {:?} to {}, tx {:?}",
+                self.dst.url(),
+                src_chain,
+                target,
+                tx_hash
+            );
+            self.pending_update = Some((target, dst_height));
+        }
+
+        let next_send: u64 = self.src.query_service(
+            BRIDGE_SERVICE,
+            "get_send_sequence",
+            json!({ "chain_id": dst_chain }),
+        )?;
+        let next_receive: u64 = self.dst.query_service(
+            BRIDGE_SERVICE,
+            "get_receive_sequence",
+            json!({ "chain_id": src_chain }),
+        )?;
+        if next_receive >= next_send || in_flight(self.pending_packets, next_receive, dst_height) {
+            return Ok(());
+        }
+
+        let mut sent = next_receive;
+        for sequence in next_receive..next_send {
+            let key = commitment_key(&dst_chain, sequence).encode_fixed()?;
+            let proof = self
+                .src
+                .get_state_proof(client.height, BRIDGE_SERVICE, &key)?;
+
+            // Not committed at tracked height yet, wait for client update
+            if proof.verify(&client.state_root)?.is_none() {
+                break;
+            }
+
+            let packet: Packet = self.src.query_service(
+                BRIDGE_SERVICE,
+                "get_packet",
+                json!({ "dest_chain": dst_chain, "sequence": sequence }),
+            )?;
+            let payload = ReceivePacketPayload {
+                packet,
+                proof_height: client.height,
+                proof,
+            };
+
+            let tx_hash = self.dst.send_transaction(
+                &dst_latest,
+                BRIDGE_SERVICE,
+                "receive_packet",
+                serde_json::to_value(payload)?,
+            )?;
+            println!(
+                "{} receive packet {} from {:?}, tx {:?}",
+                self.dst.url(),
+                sequence,
+                src_chain,
+                tx_hash
+            );
+            sent = sequence + 1;
+        }
+
+        if sent > next_receive {
+            self.pending_packets = Some((sent, dst_height));
+        }
+        Ok(())
+    }
+}
+
+// Whether sent transactions go beyond `committed` on destination, and may
+// still be committed
+fn in_flight(pending: Option<(u64, u64)>, committed: u64, dst_height: u64) -> bool {
+    match pending {
+        Some((target, sent_at)) => target > committed && dst_height < sent_at + RESEND_AFTER,
+        None => false,
+    }
+}
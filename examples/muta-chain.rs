@@ -1,7 +1,10 @@
+use account::AccountService;
 use asset::AssetService;
+use bridge::BridgeService;
+use clap::{App, Arg, SubCommand};
 use derive_more::{Display, From};
 use metadata::MetadataService;
-use muta::MutaBuilder;
+use muta::{MutaBuilder, SealMode};
 use protocol::traits::{Service, ServiceMapping, ServiceSDK};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
@@ -14,7 +17,9 @@ impl ServiceMapping for DefaultServiceMapping {
         sdk: SDK,
     ) -> ProtocolResult<Box<dyn Service>> {
         let service = match name {
+            "account" => Box::new(AccountService::new(sdk)) as Box<dyn Service>,
             "asset" => Box::new(AssetService::new(sdk)) as Box<dyn Service>,
+            "bridge" => Box::new(BridgeService::new(sdk)) as Box<dyn Service>,
             "metadata" => Box::new(MetadataService::new(sdk)) as Box<dyn Service>,
             _ => {
                 return Err(MappingError::NotFoundService {
@@ -28,11 +33,29 @@ impl ServiceMapping for DefaultServiceMapping {
     }
 
     fn list_service_name(&self) -> Vec<String> {
-        vec!["asset".to_owned(), "metadata".to_owned()]
+        vec![
+            "account".to_owned(),
+            "asset".to_owned(),
+            "bridge".to_owned(),
+            "metadata".to_owned(),
+        ]
     }
 }
 
 fn main() {
+    let matches = App::new("muta-chain")
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about("Replay a stored block and print its replay log instead of running the node")
+                .arg(
+                    Arg::with_name("height")
+                        .help("Height of the block to replay")
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .get_matches();
+
     let config_path =
         std::env::var("CONFIG").unwrap_or_else(|_| "devtools/chain/config.toml".to_owned());
     let genesis_path =
@@ -49,6 +72,46 @@ fn main() {
     let builer = builder.service_mapping(DefaultServiceMapping {});
 
     let muta = builer.build().expect("build");
+
+    if let Some(m) = matches.subcommand_matches("replay") {
+        let height = m
+            .value_of("height")
+            .expect("height is required")
+            .parse::<u64>()
+            .expect("invalid replay height");
+        let log = muta.replay(height).expect("replay");
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&log).expect("encode replay log")
+        );
+        return;
+    }
+
+    // Verify stored blocks, e.g. after disk issues
+    if let Ok(from) = std::env::var("VERIFY_CHAIN") {
+        let from = from.parse::<u64>().expect("invalid verify chain height");
+        let to = std::env::var("VERIFY_CHAIN_TO")
+            .ok()
+            .map(|to| to.parse::<u64>().expect("invalid verify chain height"));
+        let verification = muta.verify_chain(from, to).expect("verify chain");
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&verification).expect("encode verification")
+        );
+        return;
+    }
+
+    // Seal blocks without consensus, for service development
+    if let Ok(mode) = std::env::var("DEV_CHAIN") {
+        let seal_mode = match mode.as_str() {
+            "instant" => SealMode::Instant,
+            "manual" => SealMode::Manual,
+            _ => panic!("invalid dev chain seal mode, instant or manual"),
+        };
+        muta.run_dev_chain(seal_mode).expect("run dev chain");
+        return;
+    }
+
     muta.run().expect("run");
 }
 
@@ -13,7 +13,9 @@ use protocol::traits::{
     ChainQuerier, Dispatcher, ServiceResponse, ServiceSDK, ServiceState, StoreArray, StoreBool,
     StoreMap, StoreString, StoreUint64,
 };
-use protocol::types::{Address, Block, Hash, Receipt, ServiceContext, SignedTransaction};
+use protocol::types::{
+    Address, Block, Hash, Receipt, ServiceContext, SignedTransaction, VoteExtension, CYCLES_PRICING,
+};
 use protocol::{ProtocolError, ProtocolErrorKind};
 
 use crate::binding::store::{
@@ -134,6 +136,16 @@ impl<S: 'static + ServiceState, C: ChainQuerier, D: Dispatcher> ServiceSDK
             .unwrap_or_else(|e| panic!("service sdk get block by height failed: {}", e))
     }
 
+    // Get vote extensions carried by the block of `height`, they're of
+    // precommits of the previous height
+    // When the parameter `height` is None, get the ones of the latest
+    // (executing) block
+    fn get_vote_extensions(&self, height: Option<u64>) -> Vec<VoteExtension> {
+        self.get_block_by_height(height)
+            .map(|block| block.vote_extensions)
+            .unwrap_or_default()
+    }
+
     // Get a receipt by `tx_hash`
     // if not found on the chain, return None
     fn get_receipt_by_hash(&self, tx_hash: &Hash) -> Option<Receipt> {
@@ -153,6 +165,8 @@ impl<S: 'static + ServiceState, C: ChainQuerier, D: Dispatcher> ServiceSDK
         method: &str,
         payload: &str,
     ) -> ServiceResponse<String> {
+        ctx.charge_priced_cycles(CYCLES_PRICING.service_call);
+
         let ctx = ServiceContext::with_context(
             ctx,
             extra,
@@ -174,6 +188,8 @@ impl<S: 'static + ServiceState, C: ChainQuerier, D: Dispatcher> ServiceSDK
         method: &str,
         payload: &str,
     ) -> ServiceResponse<String> {
+        ctx.charge_priced_cycles(CYCLES_PRICING.service_call);
+
         let ctx = ServiceContext::with_context(
             ctx,
             extra,
@@ -0,0 +1,195 @@
+use bytes::Bytes;
+
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::ServiceState;
+use protocol::types::{Address, Hash, ServiceContext, CYCLES_PRICING};
+use protocol::ProtocolResult;
+
+// Owner, bytes and index slot of a deposited entry are recorded under the key
+const RECORD_PREFIX: &str = "storage_deposit_record";
+// Number of entries deposited by an owner is recorded under the key
+const OWNER_PREFIX: &str = "storage_deposit_owner";
+// Key of the entry in a slot of an owner is recorded under the key
+const SLOT_PREFIX: &str = "storage_deposit_slot";
+
+/// Record the entry stored for the owner, bytes of the key and value are
+/// deposited by it through the context. Bytes deposited before are released
+/// by their owner, empty value means the entry is deleted. Entries stored
+/// before storage deposit is enabled have no owner, nothing is released.
+///
+/// Entries of an owner are indexed by slots, the last one moves to the slot
+/// of a removed entry, so bookkeeping is constant for every write. It's
+/// charged through the context as storage access.
+pub fn deposit<S: ServiceState>(
+    state: &mut S,
+    ctx: &ServiceContext,
+    owner: &Address,
+    key: Bytes,
+    value_len: usize,
+) -> ProtocolResult<()> {
+    let record_key = record_key(&key);
+    let bytes = if value_len == 0 {
+        0
+    } else {
+        (key.len() + value_len) as u64
+    };
+
+    let prev = get_record(state, Some(ctx), &record_key)?;
+    let mut slot = None;
+    if let Some((prev_owner, prev_bytes, prev_slot)) = prev.as_ref() {
+        ctx.change_storage_deposit(prev_owner, -(*prev_bytes as i64));
+        if prev_owner == owner && bytes != 0 {
+            slot = Some(*prev_slot);
+        } else {
+            unindex(state, ctx, prev_owner, *prev_slot)?;
+        }
+    }
+
+    if bytes == 0 {
+        if prev.is_some() {
+            put(state, Some(ctx), record_key, Bytes::new())?;
+        }
+        return Ok(());
+    }
+
+    ctx.change_storage_deposit(owner, bytes as i64);
+    let slot = match slot {
+        Some(slot) => slot,
+        None => index(state, ctx, owner, key)?,
+    };
+    put(state, Some(ctx), record_key, (owner.clone(), bytes, slot))
+}
+
+/// Delete entries deposited by the owner, return bytes released.
+pub fn reclaim<S: ServiceState>(state: &mut S, owner: &Address) -> ProtocolResult<u64> {
+    let mut released = 0;
+    for slot in 0..get_count(state, None, owner)? {
+        let slot_key = slot_key(owner, slot);
+        let key: Bytes = match get_raw(state, None, &slot_key)? {
+            Some(key) => key,
+            None => continue,
+        };
+
+        let record_key = record_key(&key);
+        if let Some((_, bytes, _)) = get_record(state, None, &record_key)? {
+            released += bytes;
+        }
+
+        state.insert(key, Bytes::new())?;
+        state.insert(record_key, Bytes::new())?;
+        state.insert(slot_key, Bytes::new())?;
+    }
+
+    state.insert(owner_key(owner), Bytes::new())?;
+    Ok(released)
+}
+
+// Append the key to entries of the owner, return its slot
+fn index<S: ServiceState>(
+    state: &mut S,
+    ctx: &ServiceContext,
+    owner: &Address,
+    key: Bytes,
+) -> ProtocolResult<u64> {
+    let slot = get_count(state, Some(ctx), owner)?;
+    put(state, Some(ctx), slot_key(owner, slot), key)?;
+    put(state, Some(ctx), owner_key(owner), slot + 1)?;
+    Ok(slot)
+}
+
+// Remove the entry in the slot, the last entry of the owner moves to it
+fn unindex<S: ServiceState>(
+    state: &mut S,
+    ctx: &ServiceContext,
+    owner: &Address,
+    slot: u64,
+) -> ProtocolResult<()> {
+    let last = get_count(state, Some(ctx), owner)?.saturating_sub(1);
+    if slot < last {
+        if let Some(moved) = get_raw(state, Some(ctx), &slot_key(owner, last))? {
+            let moved_key = record_key(&moved);
+            if let Some((moved_owner, bytes, _)) = get_record(state, Some(ctx), &moved_key)? {
+                put(state, Some(ctx), moved_key, (moved_owner, bytes, slot))?;
+            }
+            put(state, Some(ctx), slot_key(owner, slot), moved)?;
+        }
+    }
+    put(state, Some(ctx), slot_key(owner, last), Bytes::new())?;
+
+    if last == 0 {
+        put(state, Some(ctx), owner_key(owner), Bytes::new())
+    } else {
+        put(state, Some(ctx), owner_key(owner), last)
+    }
+}
+
+fn put<S: ServiceState, V: FixedCodec>(
+    state: &mut S,
+    ctx: Option<&ServiceContext>,
+    key: Hash,
+    value: V,
+) -> ProtocolResult<()> {
+    let value = value.encode_fixed()?;
+    if let Some(ctx) = ctx {
+        let bytes = (key.as_bytes().len() + value.len()) as u64;
+        ctx.charge_priced_cycles(CYCLES_PRICING.storage_write_per_byte * bytes);
+    }
+
+    state.insert(key, value)
+}
+
+// Deleted values are empty
+fn get_raw<S: ServiceState>(
+    state: &S,
+    ctx: Option<&ServiceContext>,
+    key: &Hash,
+) -> ProtocolResult<Option<Bytes>> {
+    let raw: Option<Bytes> = state.get(key)?;
+    if let Some(ctx) = ctx {
+        let bytes = (key.as_bytes().len() + raw.as_ref().map(Bytes::len).unwrap_or(0)) as u64;
+        ctx.charge_priced_cycles(CYCLES_PRICING.storage_read_per_byte * bytes);
+    }
+
+    Ok(raw.filter(|raw| !raw.is_empty()))
+}
+
+fn get_record<S: ServiceState>(
+    state: &S,
+    ctx: Option<&ServiceContext>,
+    key: &Hash,
+) -> ProtocolResult<Option<(Address, u64, u64)>> {
+    match get_raw(state, ctx, key)? {
+        Some(raw) => Ok(Some(<_>::decode_fixed(raw)?)),
+        None => Ok(None),
+    }
+}
+
+fn get_count<S: ServiceState>(
+    state: &S,
+    ctx: Option<&ServiceContext>,
+    owner: &Address,
+) -> ProtocolResult<u64> {
+    match get_raw(state, ctx, &owner_key(owner))? {
+        Some(raw) => <_>::decode_fixed(raw),
+        None => Ok(0),
+    }
+}
+
+fn record_key(key: &Bytes) -> Hash {
+    let mut bytes = RECORD_PREFIX.as_bytes().to_vec();
+    bytes.extend_from_slice(key.as_ref());
+    Hash::digest(Bytes::from(bytes))
+}
+
+fn owner_key(owner: &Address) -> Hash {
+    let mut bytes = OWNER_PREFIX.as_bytes().to_vec();
+    bytes.extend_from_slice(owner.as_bytes().as_ref());
+    Hash::digest(Bytes::from(bytes))
+}
+
+fn slot_key(owner: &Address, slot: u64) -> Hash {
+    let mut bytes = SLOT_PREFIX.as_bytes().to_vec();
+    bytes.extend_from_slice(owner.as_bytes().as_ref());
+    bytes.extend_from_slice(&slot.to_le_bytes());
+    Hash::digest(Bytes::from(bytes))
+}
@@ -0,0 +1,183 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use bytes::Bytes;
+
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::ServiceState;
+use protocol::types::{Address, MerkleRoot, ServiceContext, TraceStepKind, CYCLES_PRICING};
+use protocol::ProtocolResult;
+
+use crate::binding::state::{deposit, get_address_key};
+use crate::executor::TraceRecorder;
+
+/// Charge storage access of a call by bytes read and written, through the
+/// context of the call once its cycles pricing is enabled. Without context,
+/// such as genesis and block hooks, access is free. Once storage deposit is
+/// enabled in the context, values and account values are deposited by the
+/// caller writing them.
+pub struct MeteredState<S: ServiceState> {
+    state: Rc<RefCell<S>>,
+    ctx:   Option<ServiceContext>,
+
+    // Access is recorded while a transaction is traced
+    service: String,
+    tracer:  Option<Rc<RefCell<TraceRecorder>>>,
+
+    exempt_deposit: bool,
+}
+
+impl<S: ServiceState> MeteredState<S> {
+    pub fn new(state: Rc<RefCell<S>>, ctx: Option<ServiceContext>) -> Self {
+        MeteredState {
+            state,
+            ctx,
+            service: "".to_owned(),
+            tracer: None,
+            exempt_deposit: false,
+        }
+    }
+
+    /// Nothing is deposited for writes of the state, such as balances of
+    /// the service holding deposits.
+    pub fn exempt_deposit(mut self) -> Self {
+        self.exempt_deposit = true;
+        self
+    }
+
+    pub fn with_tracer(mut self, service: &str, tracer: Rc<RefCell<TraceRecorder>>) -> Self {
+        self.service = service.to_owned();
+        self.tracer = Some(tracer);
+        self
+    }
+
+    fn trace(&self, kind: TraceStepKind, key: &Bytes, size: usize) {
+        if let Some(tracer) = self.tracer.as_ref() {
+            tracer
+                .borrow_mut()
+                .access(kind, &self.service, key.clone(), size);
+        }
+    }
+
+    fn charge_read(&self, bytes: usize) {
+        if let Some(ctx) = self.ctx.as_ref() {
+            ctx.charge_priced_cycles(CYCLES_PRICING.storage_read_per_byte * bytes as u64);
+        }
+    }
+
+    fn charge_write(&self, bytes: usize) {
+        if let Some(ctx) = self.ctx.as_ref() {
+            ctx.charge_priced_cycles(CYCLES_PRICING.storage_write_per_byte * bytes as u64);
+        }
+    }
+
+    fn is_depositing(&self) -> bool {
+        !self.exempt_deposit
+            && self
+                .ctx
+                .as_ref()
+                .map_or(false, ServiceContext::is_storage_deposit_enabled)
+    }
+
+    // Key is the one written to trie
+    fn deposit(&self, key: Bytes, value_len: usize) -> ProtocolResult<()> {
+        let ctx = match self.ctx.as_ref() {
+            Some(ctx) if self.is_depositing() => ctx,
+            _ => return Ok(()),
+        };
+
+        let caller = ctx.get_caller();
+        deposit::deposit(&mut *self.state.borrow_mut(), ctx, &caller, key, value_len)
+    }
+}
+
+impl<S: ServiceState> ServiceState for MeteredState<S> {
+    fn get<Key: FixedCodec, Ret: FixedCodec>(&self, key: &Key) -> ProtocolResult<Option<Ret>> {
+        let encoded_key = key.encode_fixed()?;
+        let value: Option<Bytes> = self.state.borrow().get(&encoded_key)?;
+
+        let value_len = value.as_ref().map(Bytes::len).unwrap_or(0);
+        self.charge_read(encoded_key.len() + value_len);
+        self.trace(TraceStepKind::Read, &encoded_key, value_len);
+
+        match value {
+            Some(value) => Ok(Some(<_>::decode_fixed(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn contains<Key: FixedCodec>(&self, key: &Key) -> ProtocolResult<bool> {
+        let encoded_key = key.encode_fixed()?;
+        self.charge_read(encoded_key.len());
+        self.trace(TraceStepKind::Read, &encoded_key, 0);
+
+        self.state.borrow().contains(&encoded_key)
+    }
+
+    fn insert<Key: FixedCodec, Value: FixedCodec>(
+        &mut self,
+        key: Key,
+        value: Value,
+    ) -> ProtocolResult<()> {
+        let encoded_key = key.encode_fixed()?;
+        let encoded_value = value.encode_fixed()?;
+        self.charge_write(encoded_key.len() + encoded_value.len());
+        self.trace(TraceStepKind::Write, &encoded_key, encoded_value.len());
+        self.deposit(encoded_key.clone(), encoded_value.len())?;
+
+        self.state.borrow_mut().insert(encoded_key, encoded_value)
+    }
+
+    fn get_account_value<Key: FixedCodec, Ret: FixedCodec>(
+        &self,
+        address: &Address,
+        key: &Key,
+    ) -> ProtocolResult<Option<Ret>> {
+        let encoded_key = key.encode_fixed()?;
+        let value: Option<Bytes> = self
+            .state
+            .borrow()
+            .get_account_value(address, &encoded_key)?;
+
+        let value_len = value.as_ref().map(Bytes::len).unwrap_or(0);
+        self.charge_read(encoded_key.len() + value_len);
+        self.trace(TraceStepKind::Read, &encoded_key, value_len);
+
+        match value {
+            Some(value) => Ok(Some(<_>::decode_fixed(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set_account_value<Key: FixedCodec, Val: FixedCodec>(
+        &mut self,
+        address: &Address,
+        key: Key,
+        val: Val,
+    ) -> ProtocolResult<()> {
+        let encoded_key = key.encode_fixed()?;
+        let encoded_val = val.encode_fixed()?;
+        self.charge_write(encoded_key.len() + encoded_val.len());
+        self.trace(TraceStepKind::Write, &encoded_key, encoded_val.len());
+        if self.is_depositing() {
+            let trie_key = get_address_key(address, &encoded_key)?.encode_fixed()?;
+            self.deposit(trie_key, encoded_val.len())?;
+        }
+
+        self.state
+            .borrow_mut()
+            .set_account_value(address, encoded_key, encoded_val)
+    }
+
+    fn revert_cache(&mut self) -> ProtocolResult<()> {
+        self.state.borrow_mut().revert_cache()
+    }
+
+    fn stash(&mut self) -> ProtocolResult<()> {
+        self.state.borrow_mut().stash()
+    }
+
+    fn commit(&mut self) -> ProtocolResult<MerkleRoot> {
+        self.state.borrow_mut().commit()
+    }
+}
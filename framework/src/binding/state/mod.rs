@@ -1,40 +1,68 @@
+pub mod deposit;
+mod metered;
 mod trie;
 mod trie_db;
 
+pub use metered::MeteredState;
 pub use trie::{MPTTrie, MPTTrieError};
-pub use trie_db::{RocksTrieDB, RocksTrieDBError};
+pub use trie_db::{RocksTrieDB, RocksTrieDBError, DEFAULT_TRIE_CACHE_SIZE};
 
 use std::collections::HashMap;
 
 use bytes::Bytes;
-use cita_trie::DB as TrieDB;
-
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::ServiceState;
+use protocol::traits::{ServiceState, StateCommitment};
 use protocol::types::{Address, Hash, MerkleRoot};
 use protocol::ProtocolResult;
 
-pub struct GeneralServiceState<DB: TrieDB> {
-    trie: MPTTrie<DB>,
+pub struct GeneralServiceState<C: StateCommitment> {
+    trie: C,
 
     // TODO(@yejiayu): The value of HashMap should be changed to Box<dyn Any> to avoid multiple
     // serializations.
     cache_map: HashMap<Bytes, Bytes>,
     stash_map: HashMap<Bytes, Bytes>,
+
+    // Stashed writes in order while replaying a block
+    journal: Option<Vec<(Bytes, Bytes)>>,
 }
 
-impl<DB: TrieDB> GeneralServiceState<DB> {
-    pub fn new(trie: MPTTrie<DB>) -> Self {
+impl<C: StateCommitment> GeneralServiceState<C> {
+    pub fn new(trie: C) -> Self {
         Self {
             trie,
 
             cache_map: HashMap::new(),
             stash_map: HashMap::new(),
+
+            journal: None,
         }
     }
+
+    // Start recording stashed writes, previous records are dropped
+    pub fn start_journal(&mut self) {
+        self.journal = Some(Vec::new());
+    }
+
+    // Take recorded writes since last take, in the order they are stashed
+    pub fn take_journal(&mut self) -> Vec<(Bytes, Bytes)> {
+        self.journal
+            .as_mut()
+            .map(|journal| journal.drain(..).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn stop_journal(&mut self) {
+        self.journal = None;
+    }
+
+    // Proof only covers committed data, cache and stash are not in trie yet
+    pub fn get_proof(&self, key: &Bytes) -> ProtocolResult<Vec<Bytes>> {
+        self.trie.get_proof(key)
+    }
 }
 
-impl<DB: TrieDB> ServiceState for GeneralServiceState<DB> {
+impl<C: StateCommitment> ServiceState for GeneralServiceState<C> {
     fn get<Key: FixedCodec, Ret: FixedCodec>(&self, key: &Key) -> ProtocolResult<Option<Ret>> {
         let encoded_key = key.encode_fixed()?;
 
@@ -110,6 +138,9 @@ impl<DB: TrieDB> ServiceState for GeneralServiceState<DB> {
     // Move data from cache to stash
     fn stash(&mut self) -> ProtocolResult<()> {
         for (k, v) in self.cache_map.drain() {
+            if let Some(journal) = self.journal.as_mut() {
+                journal.push((k.clone(), v.clone()));
+            }
             self.stash_map.insert(k, v);
         }
 
@@ -6,7 +6,8 @@ use derive_more::{Display, From};
 use hasher::HasherKeccak;
 use lazy_static::lazy_static;
 
-use protocol::types::{Hash, MerkleRoot};
+use protocol::traits::StateCommitment;
+use protocol::types::{CommitmentScheme, Hash, MerkleRoot};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 lazy_static! {
@@ -18,8 +19,12 @@ pub struct MPTTrie<DB: TrieDB> {
     trie: PatriciaTrie<DB, HasherKeccak>,
 }
 
-impl<DB: TrieDB> MPTTrie<DB> {
-    pub fn new(db: Arc<DB>) -> Self {
+impl<DB: TrieDB> StateCommitment for MPTTrie<DB> {
+    type DB = DB;
+
+    const SCHEME: CommitmentScheme = CommitmentScheme::MerklePatricia;
+
+    fn new(db: Arc<DB>) -> Self {
         let trie = PatriciaTrie::new(db, Arc::clone(&HASHER_INST));
 
         Self {
@@ -28,14 +33,14 @@ impl<DB: TrieDB> MPTTrie<DB> {
         }
     }
 
-    pub fn from(root: MerkleRoot, db: Arc<DB>) -> ProtocolResult<Self> {
+    fn from_root(root: MerkleRoot, db: Arc<DB>) -> ProtocolResult<Self> {
         let trie = PatriciaTrie::from(db, Arc::clone(&HASHER_INST), &root.as_bytes())
             .map_err(MPTTrieError::from)?;
 
         Ok(Self { root, trie })
     }
 
-    pub fn get(&self, key: &Bytes) -> ProtocolResult<Option<Bytes>> {
+    fn get(&self, key: &Bytes) -> ProtocolResult<Option<Bytes>> {
         Ok(self
             .trie
             .get(key)
@@ -43,18 +48,24 @@ impl<DB: TrieDB> MPTTrie<DB> {
             .map(Bytes::from))
     }
 
-    pub fn contains(&self, key: &Bytes) -> ProtocolResult<bool> {
+    fn contains(&self, key: &Bytes) -> ProtocolResult<bool> {
         Ok(self.trie.contains(key).map_err(MPTTrieError::from)?)
     }
 
-    pub fn insert(&mut self, key: Bytes, value: Bytes) -> ProtocolResult<()> {
+    fn insert(&mut self, key: Bytes, value: Bytes) -> ProtocolResult<()> {
         self.trie
             .insert(key.to_vec(), value.to_vec())
             .map_err(MPTTrieError::from)?;
         Ok(())
     }
 
-    pub fn commit(&mut self) -> ProtocolResult<MerkleRoot> {
+    // Nodes on the path to key in committed trie
+    fn get_proof(&self, key: &Bytes) -> ProtocolResult<Vec<Bytes>> {
+        let proof = self.trie.get_proof(key).map_err(MPTTrieError::from)?;
+        Ok(proof.into_iter().map(Bytes::from).collect())
+    }
+
+    fn commit(&mut self) -> ProtocolResult<MerkleRoot> {
         let root_bytes = self.trie.root().map_err(MPTTrieError::from)?;
         let root = MerkleRoot::from_bytes(Bytes::from(root_bytes))?;
         self.root = root;
@@ -3,17 +3,30 @@ use std::sync::Arc;
 
 use bytes::Bytes;
 use derive_more::{Display, From};
+use lru::LruCache;
+use parking_lot::Mutex;
 use rocksdb::{Options, WriteBatch, DB};
 
+use common_metrics::{MemoryAccount, EXECUTOR_TRIE_CACHE_BYTES, EXECUTOR_TRIE_CACHE_TOTAL};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
+pub const DEFAULT_TRIE_CACHE_SIZE: usize = 64 * 1024 * 1024; // 64MiB
+
 pub struct RocksTrieDB {
     light: bool,
     db:    Arc<DB>,
+    cache: Mutex<TrieCache>,
 }
 
 impl RocksTrieDB {
-    pub fn new<P: AsRef<Path>>(path: P, light: bool, max_open_files: i32) -> ProtocolResult<Self> {
+    /// Trie nodes are shared by all blocks, cache_size is the max bytes of
+    /// nodes kept in memory, zero disables the cache.
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        light: bool,
+        cache_size: usize,
+        max_open_files: i32,
+    ) -> ProtocolResult<Self> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
         opts.create_missing_column_families(true);
@@ -24,6 +37,7 @@ impl RocksTrieDB {
         Ok(RocksTrieDB {
             light,
             db: Arc::new(db),
+            cache: Mutex::new(TrieCache::new(cache_size)),
         })
     }
 }
@@ -32,17 +46,32 @@ impl cita_trie::DB for RocksTrieDB {
     type Error = RocksTrieDBError;
 
     fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
-        Ok(self.db.get(key).map_err(to_store_err)?.map(|v| v.to_vec()))
+        if let Some(value) = self.cache.lock().get(key) {
+            EXECUTOR_TRIE_CACHE_TOTAL.with_label_values(&["hit"]).inc();
+            return Ok(Some(value));
+        }
+        EXECUTOR_TRIE_CACHE_TOTAL.with_label_values(&["miss"]).inc();
+
+        let value = self.db.get(key).map_err(to_store_err)?.map(|v| v.to_vec());
+        if let Some(value) = value.as_ref() {
+            self.cache.lock().insert(key.to_vec(), value.clone());
+        }
+        Ok(value)
     }
 
     fn contains(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        if self.cache.lock().contains(key) {
+            return Ok(true);
+        }
         Ok(self.db.get(key).map_err(to_store_err)?.is_some())
     }
 
     fn insert(&self, key: Vec<u8>, value: Vec<u8>) -> Result<(), Self::Error> {
         self.db
-            .put(Bytes::from(key), Bytes::from(value))
+            .put(Bytes::from(key.clone()), Bytes::from(value.clone()))
             .map_err(to_store_err)?;
+
+        self.cache.lock().insert(key, value);
         Ok(())
     }
 
@@ -59,12 +88,18 @@ impl cita_trie::DB for RocksTrieDB {
         }
 
         self.db.write(batch).map_err(to_store_err)?;
+
+        let mut cache = self.cache.lock();
+        for (key, value) in keys.into_iter().zip(values.into_iter()) {
+            cache.insert(key, value);
+        }
         Ok(())
     }
 
     fn remove(&self, key: &[u8]) -> Result<(), Self::Error> {
         if self.light {
             self.db.delete(key).map_err(to_store_err)?;
+            self.cache.lock().remove(key);
         }
         Ok(())
     }
@@ -77,6 +112,11 @@ impl cita_trie::DB for RocksTrieDB {
             }
 
             self.db.write(batch).map_err(to_store_err)?;
+
+            let mut cache = self.cache.lock();
+            for key in keys {
+                cache.remove(key);
+            }
         }
 
         Ok(())
@@ -87,6 +127,91 @@ impl cita_trie::DB for RocksTrieDB {
     }
 }
 
+// Nodes are keyed by their hash, a key never points to other content. Nodes
+// committed by new blocks are inserted, only nodes pruned in light mode are
+// evicted, so cached nodes never go stale.
+// The cache is bounded by its max size, which is also reported as its memory
+// cap, nodes are evicted instead of shed.
+struct TrieCache {
+    nodes:    Option<LruCache<Vec<u8>, Vec<u8>>>,
+    size:     usize,
+    max_size: usize,
+    memory:   MemoryAccount,
+}
+
+impl TrieCache {
+    fn new(max_size: usize) -> Self {
+        let nodes = if max_size > 0 {
+            Some(LruCache::unbounded())
+        } else {
+            None
+        };
+
+        let memory = MemoryAccount::new("trie_cache");
+        memory.set_cap(max_size);
+
+        TrieCache {
+            nodes,
+            size: 0,
+            max_size,
+            memory,
+        }
+    }
+
+    fn get(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        self.nodes.as_mut()?.get(key).cloned()
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        match self.nodes.as_ref() {
+            Some(nodes) => nodes.contains(key),
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        let nodes = match self.nodes.as_mut() {
+            Some(nodes) => nodes,
+            None => return,
+        };
+
+        let node_size = key.len() + value.len();
+        if node_size > self.max_size {
+            return;
+        }
+
+        if let Some(old) = nodes.put(key.clone(), value) {
+            self.size -= key.len() + old.len();
+        }
+        self.size += node_size;
+
+        while self.size > self.max_size {
+            match nodes.pop_lru() {
+                Some((k, v)) => self.size -= k.len() + v.len(),
+                None => break,
+            }
+        }
+        self.report_size();
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        let nodes = match self.nodes.as_mut() {
+            Some(nodes) => nodes,
+            None => return,
+        };
+
+        if let Some(value) = nodes.pop(key) {
+            self.size -= key.len() + value.len();
+            self.report_size();
+        }
+    }
+
+    fn report_size(&self) {
+        EXECUTOR_TRIE_CACHE_BYTES.set(self.size as i64);
+        self.memory.set_used(self.size);
+    }
+}
+
 #[derive(Debug, Display, From)]
 pub enum RocksTrieDBError {
     #[display(fmt = "store error")]
@@ -114,3 +239,40 @@ fn to_store_err(e: rocksdb::Error) -> RocksTrieDBError {
     log::error!("[framework] trie db {:?}", e);
     RocksTrieDBError::Store
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TrieCache;
+
+    #[test]
+    fn should_evict_least_recently_used_nodes_beyond_max_size() {
+        let mut cache = TrieCache::new(8);
+
+        cache.insert(b"k1".to_vec(), b"v1".to_vec());
+        cache.insert(b"k2".to_vec(), b"v2".to_vec());
+        assert_eq!(cache.get(b"k1"), Some(b"v1".to_vec()));
+
+        cache.insert(b"k3".to_vec(), b"v3".to_vec());
+        assert!(cache.contains(b"k1"));
+        assert!(!cache.contains(b"k2"));
+        assert!(cache.contains(b"k3"));
+        assert_eq!(cache.size, 8);
+
+        cache.remove(b"k1");
+        assert!(!cache.contains(b"k1"));
+        assert_eq!(cache.size, 4);
+
+        // Larger than the whole cache
+        cache.insert(b"k4".to_vec(), b"too large".to_vec());
+        assert!(!cache.contains(b"k4"));
+    }
+
+    #[test]
+    fn should_disable_cache_on_zero_max_size() {
+        let mut cache = TrieCache::new(0);
+
+        cache.insert(b"k1".to_vec(), b"v1".to_vec());
+        assert!(!cache.contains(b"k1"));
+        assert_eq!(cache.get(b"k1"), None);
+    }
+}
@@ -9,7 +9,7 @@ use cita_trie::MemoryDB;
 use protocol::traits::{NoopDispatcher, ServiceResponse, ServiceSDK, Storage};
 use protocol::types::{
     Address, Block, BlockHeader, Event, Hash, MerkleRoot, Proof, RawTransaction, Receipt,
-    ReceiptResponse, SignedTransaction, TransactionRequest, Validator,
+    ReceiptResponse, SignedTransaction, TransactionRequest, Validator, VoteExtension,
 };
 use protocol::ProtocolResult;
 
@@ -98,6 +98,9 @@ fn test_service_sdk() {
 
     let block_data = sdk.get_block_by_height(Some(1)).unwrap();
     assert_eq!(mock_block(1), block_data);
+
+    let extensions = sdk.get_vote_extensions(Some(1));
+    assert_eq!(extensions, vec![mock_vote_extension()]);
 }
 
 struct MockStorage;
@@ -192,12 +195,14 @@ pub fn mock_transaction_request() -> TransactionRequest {
 
 pub fn mock_raw_tx() -> RawTransaction {
     RawTransaction {
-        chain_id:     mock_hash(),
-        nonce:        mock_hash(),
-        timeout:      100,
-        cycles_price: 1,
-        cycles_limit: 100,
-        request:      mock_transaction_request(),
+        chain_id:              mock_hash(),
+        nonce:                 mock_hash(),
+        timeout:               100,
+        cycles_price:          1,
+        cycles_limit:          100,
+        request:               mock_transaction_request(),
+        sender:                None,
+        valid_until_timestamp: None,
     }
 }
 
@@ -288,6 +293,7 @@ pub fn mock_block_header() -> BlockHeader {
             mock_validator(),
             mock_validator(),
         ],
+        extra_data:        Bytes::new(),
     }
 }
 
@@ -295,5 +301,16 @@ pub fn mock_block(order_size: usize) -> Block {
     Block {
         header:            mock_block_header(),
         ordered_tx_hashes: (0..order_size).map(|_| mock_hash()).collect(),
+        vote_extensions:   vec![mock_vote_extension()],
+    }
+}
+
+pub fn mock_vote_extension() -> VoteExtension {
+    VoteExtension {
+        height:     41,
+        voter:      mock_address(),
+        block_hash: mock_hash(),
+        payload:    Bytes::from("price 42"),
+        signature:  Bytes::from("signature"),
     }
 }
@@ -3,7 +3,8 @@ use std::sync::Arc;
 use bytes::Bytes;
 use cita_trie::MemoryDB;
 
-use protocol::traits::ServiceState;
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{ServiceState, StateCommitment};
 use protocol::types::{Address, Hash, MerkleRoot};
 
 use crate::binding::state::{GeneralServiceState, MPTTrie};
@@ -56,9 +57,36 @@ fn test_state_account() {
     assert_eq!(val, value);
 }
 
-pub fn new_state(memdb: Arc<MemoryDB>, root: Option<MerkleRoot>) -> GeneralServiceState<MemoryDB> {
+#[test]
+fn test_state_proof_verified_by_scheme() {
+    let memdb = Arc::new(MemoryDB::new(false));
+    let mut state = new_state(Arc::clone(&memdb), None);
+
+    let key = Hash::digest(Bytes::from("key".to_owned()));
+    let value = Hash::digest(Bytes::from("value".to_owned()));
+    state.insert(key.clone(), value.clone()).unwrap();
+    state.stash().unwrap();
+    let root = state.commit().unwrap();
+
+    let scheme = MPTTrie::<MemoryDB>::SCHEME;
+    let encoded_key = key.encode_fixed().unwrap();
+    let proof = state.get_proof(&encoded_key).unwrap();
+    let proved = scheme.verify_proof(&root, &encoded_key, &proof).unwrap();
+    assert_eq!(proved, Some(value.encode_fixed().unwrap()));
+
+    let absent = Hash::digest(Bytes::from("absent".to_owned()))
+        .encode_fixed()
+        .unwrap();
+    let proof = state.get_proof(&absent).unwrap();
+    assert_eq!(scheme.verify_proof(&root, &absent, &proof).unwrap(), None);
+}
+
+pub fn new_state(
+    memdb: Arc<MemoryDB>,
+    root: Option<MerkleRoot>,
+) -> GeneralServiceState<MPTTrie<MemoryDB>> {
     let trie = match root {
-        Some(root) => MPTTrie::from(root, memdb).unwrap(),
+        Some(root) => MPTTrie::from_root(root, memdb).unwrap(),
         None => MPTTrie::new(memdb),
     };
 
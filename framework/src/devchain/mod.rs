@@ -0,0 +1,642 @@
+#[cfg(test)]
+mod tests;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use derive_more::Display;
+use futures::lock::Mutex;
+
+use common_crypto::{Crypto, Secp256k1};
+use common_merkle::Merkle;
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{
+    APIAdapter, Context, Executor, ExecutorParams, ReadinessAdapter, ServiceMapping,
+    ServiceResponse, Storage,
+};
+use protocol::types::{
+    signing_hash, Address, Block, BlockHeader, CommitmentScheme, Feature, Genesis, Hash, Metadata,
+    Proof, Receipt, SignedTransaction, StateProof, TransactionRequest, TxTrace, Validator,
+    TRANSACTION_SIGNATURE_PURPOSE,
+};
+use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
+
+use crate::executor::MPTServiceExecutor;
+
+/// When blocks of a dev chain are sealed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SealMode {
+    /// A block is sealed for each transaction once it's submitted.
+    Instant,
+    /// Transactions wait for `DevChain::seal`, or the `sealBlock` mutation.
+    Manual,
+}
+
+/// A single node chain for service development and integration tests.
+/// Blocks are sealed and executed at once without consensus, they carry no
+/// signatures, and timestamps of them go forward by the block interval of
+/// metadata, so that the same transactions always give the same chain.
+pub struct DevChain<S, DB, Mapping> {
+    storage:         Arc<S>,
+    trie_db:         Arc<DB>,
+    service_mapping: Arc<Mapping>,
+    seal_mode:       SealMode,
+
+    // Transactions of the next block
+    pending: Arc<Mutex<Vec<SignedTransaction>>>,
+    height:  Arc<AtomicU64>,
+}
+
+impl<S, DB, Mapping> Clone for DevChain<S, DB, Mapping> {
+    fn clone(&self) -> Self {
+        DevChain {
+            storage:         Arc::clone(&self.storage),
+            trie_db:         Arc::clone(&self.trie_db),
+            service_mapping: Arc::clone(&self.service_mapping),
+            seal_mode:       self.seal_mode,
+            pending:         Arc::clone(&self.pending),
+            height:          Arc::clone(&self.height),
+        }
+    }
+}
+
+impl<S, DB, Mapping> DevChain<S, DB, Mapping>
+where
+    S: 'static + Storage,
+    DB: 'static + cita_trie::DB,
+    Mapping: 'static + ServiceMapping,
+{
+    /// Resume the chain in storage, or create its genesis block.
+    pub async fn new(
+        genesis: &Genesis,
+        storage: Arc<S>,
+        trie_db: Arc<DB>,
+        service_mapping: Arc<Mapping>,
+        seal_mode: SealMode,
+    ) -> ProtocolResult<Self> {
+        let latest = match storage.get_latest_block().await {
+            Ok(block) => block,
+            Err(e) if !is_missing(&e) => return Err(e),
+            Err(_) => {
+                let block = genesis_block(genesis, &storage, &trie_db, &service_mapping)?;
+                storage.insert_block(block.clone()).await?;
+                storage
+                    .update_latest_proof(block.header.proof.clone())
+                    .await?;
+                block
+            }
+        };
+
+        Ok(DevChain {
+            storage,
+            trie_db,
+            service_mapping,
+            seal_mode,
+            pending: Arc::new(Mutex::new(vec![])),
+            height: Arc::new(AtomicU64::new(latest.header.height)),
+        })
+    }
+
+    pub fn seal_mode(&self) -> SealMode {
+        self.seal_mode
+    }
+
+    /// Check the transaction like mempool does, and queue it for the next
+    /// block. It's sealed at once in instant mode.
+    pub async fn submit(&self, stx: SignedTransaction) -> ProtocolResult<()> {
+        let latest = self.storage.get_latest_block().await?;
+        self.check_tx(&latest.header, &stx)?;
+
+        let mut pending = self.pending.lock().await;
+        if pending.iter().any(|tx| tx.tx_hash == stx.tx_hash) {
+            return Err(DevChainError::Dup {
+                tx_hash: stx.tx_hash,
+            }
+            .into());
+        }
+        match self
+            .storage
+            .get_transaction_by_hash(stx.tx_hash.clone())
+            .await
+        {
+            Ok(_) => {
+                return Err(DevChainError::Committed {
+                    tx_hash: stx.tx_hash,
+                }
+                .into())
+            }
+            Err(e) if !is_missing(&e) => return Err(e),
+            Err(_) => (),
+        }
+
+        pending.push(stx);
+        drop(pending);
+        if self.seal_mode == SealMode::Instant {
+            self.seal().await?;
+        }
+        Ok(())
+    }
+
+    /// Seal queued transactions into the next block, which is empty if
+    /// there are none. Transactions failing the executor are dropped, so
+    /// that they don't fail every later seal.
+    pub async fn seal(&self) -> ProtocolResult<Block> {
+        let mut pending = self.pending.lock().await;
+        let parent = self.storage.get_latest_block().await?;
+        let (block, receipts) = match self.execute(&parent, &pending) {
+            Ok(executed) => executed,
+            Err(e) => {
+                log::warn!("[dev chain]: execute pending transactions: {:?}", e);
+                let txs = pending.drain(..).collect::<Vec<_>>();
+                for stx in txs {
+                    match self.execute(&parent, &[stx.clone()]) {
+                        Ok(_) => pending.push(stx),
+                        Err(e) => log::warn!("[dev chain]: drop {:?}: {:?}", stx.tx_hash, e),
+                    }
+                }
+
+                match self.execute(&parent, &pending) {
+                    Ok(executed) => executed,
+                    Err(e) => {
+                        pending.clear();
+                        return Err(e);
+                    }
+                }
+            }
+        };
+
+        self.storage.insert_transactions(pending.clone()).await?;
+        self.storage.insert_receipts(receipts).await?;
+        self.storage
+            .update_latest_proof(unsigned_proof(&block)?)
+            .await?;
+        self.storage.insert_block(block.clone()).await?;
+
+        pending.clear();
+        self.height.store(block.header.height, Ordering::SeqCst);
+        log::info!("[dev chain]: sealed block {}", block.header.height);
+        Ok(block)
+    }
+
+    fn executor(&self, state_root: Hash) -> ProtocolResult<MPTServiceExecutor<S, DB, Mapping>> {
+        MPTServiceExecutor::with_root(
+            state_root,
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            Arc::clone(&self.service_mapping),
+        )
+    }
+
+    fn check_tx(&self, latest: &BlockHeader, stx: &SignedTransaction) -> ProtocolResult<()> {
+        let tx_hash = Hash::digest(stx.raw.encode_fixed()?);
+        if tx_hash != stx.tx_hash {
+            return Err(DevChainError::TxHash {
+                tx_hash: stx.tx_hash.clone(),
+            }
+            .into());
+        }
+
+        let executor = self.executor(latest.state_root.clone())?;
+        let metadata = read_metadata(&executor, latest)?;
+        if metadata
+            .chain_spec
+            .is_active(Feature::PayloadLimits, latest.height + 1)
+        {
+            if let Some(limit) = executor.payload_limits()?.exceeded_by(&stx.raw.request) {
+                return Err(DevChainError::PayloadLimit { tx_hash, limit }.into());
+            }
+        }
+        if stx.raw.chain_id != metadata.chain_id {
+            return Err(DevChainError::ChainId {
+                tx_hash,
+                chain_id: stx.raw.chain_id.clone(),
+            }
+            .into());
+        }
+
+        // Transactions are sealed into the next block at the earliest
+        if stx.raw.timeout <= latest.height {
+            return Err(DevChainError::Timeout {
+                tx_hash,
+                timeout: stx.raw.timeout,
+            }
+            .into());
+        }
+        if stx.raw.timeout > latest.height + metadata.timeout_gap {
+            return Err(DevChainError::InvalidTimeout { tx_hash }.into());
+        }
+
+        let domain = metadata.signature_domain(latest.height + 1);
+        let signing = signing_hash(domain.as_ref(), TRANSACTION_SIGNATURE_PURPOSE, &tx_hash);
+        Secp256k1::verify_signature(
+            signing.as_bytes().as_ref(),
+            stx.signature.as_ref(),
+            stx.pubkey.as_ref(),
+        )
+        .map_err(|_| DevChainError::Signature {
+            tx_hash: tx_hash.clone(),
+        })?;
+
+        let params = ExecutorParams {
+            state_root:      latest.state_root.clone(),
+            height:          latest.height + 1,
+            timestamp:       latest.timestamp,
+            cycles_limit:    stx.raw.cycles_limit,
+            proposer:        Address::default(),
+            vote_extensions: vec![],
+            extra_data:      Bytes::new(),
+        };
+        executor.verify_authorization(&params, stx)
+    }
+
+    // Blocks are executed in themselves, so that state root and receipts
+    // of a block are in its header, not in the next one.
+    fn execute(
+        &self,
+        parent: &Block,
+        txs: &[SignedTransaction],
+    ) -> ProtocolResult<(Block, Vec<Receipt>)> {
+        let mut executor = self.executor(parent.header.state_root.clone())?;
+        let metadata = read_metadata(&executor, &parent.header)?;
+
+        let height = parent.header.height + 1;
+        let timestamp = parent.header.timestamp + metadata.interval;
+        let proposer = metadata
+            .verifier_list
+            .first()
+            .map(|v| v.address.clone())
+            .unwrap_or_default();
+
+        let params = ExecutorParams {
+            state_root: parent.header.state_root.clone(),
+            height,
+            timestamp,
+            cycles_limit: metadata.cycles_limit,
+            proposer: proposer.clone(),
+            vote_extensions: vec![],
+            extra_data: Bytes::new(),
+        };
+        let resp = executor.exec(&params, txs)?;
+
+        let ordered_tx_hashes = txs
+            .iter()
+            .map(|stx| stx.tx_hash.clone())
+            .collect::<Vec<_>>();
+        let order_root = Merkle::from_hashes(ordered_tx_hashes.clone())
+            .get_root_hash()
+            .unwrap_or_else(Hash::from_empty);
+        let mut receipt_hashes = vec![];
+        for receipt in resp.receipts.iter() {
+            receipt_hashes.push(Hash::digest(receipt.encode_fixed()?));
+        }
+        let receipt_root = Merkle::from_hashes(receipt_hashes)
+            .get_root_hash()
+            .unwrap_or_else(Hash::from_empty);
+
+        let header = BlockHeader {
+            chain_id: metadata.chain_id.clone(),
+            height,
+            exec_height: height,
+            pre_hash: Hash::digest(parent.encode_fixed()?),
+            timestamp,
+            logs_bloom: vec![resp.logs_bloom],
+            order_root: order_root.clone(),
+            confirm_root: vec![order_root],
+            state_root: resp.state_root,
+            receipt_root: vec![receipt_root],
+            cycles_used: vec![resp.all_cycles_used],
+            proposer,
+            proof: unsigned_proof(parent)?,
+            validator_version: 0,
+            validators: validators(&metadata),
+            extra_data: Bytes::new(),
+        };
+        let block = Block {
+            header,
+            ordered_tx_hashes,
+            vote_extensions: vec![],
+        };
+        Ok((block, resp.receipts))
+    }
+
+    fn read(
+        &self,
+        header: &BlockHeader,
+        cycles_limit: u64,
+        cycles_price: u64,
+        caller: &Address,
+        request: &TransactionRequest,
+    ) -> ProtocolResult<ServiceResponse<String>> {
+        let executor = self.executor(header.state_root.clone())?;
+        let params = ExecutorParams {
+            state_root: header.state_root.clone(),
+            height: header.height,
+            timestamp: header.timestamp,
+            cycles_limit,
+            proposer: header.proposer.clone(),
+            vote_extensions: vec![],
+            extra_data: Bytes::new(),
+        };
+        executor.read(&params, caller, cycles_price, request)
+    }
+
+    fn trace(
+        &self,
+        parent: &BlockHeader,
+        block: &BlockHeader,
+        txs: &[SignedTransaction],
+        tx_hash: &Hash,
+    ) -> ProtocolResult<TxTrace> {
+        let mut executor = self.executor(parent.state_root.clone())?;
+        let metadata = read_metadata(&executor, parent)?;
+
+        let params = ExecutorParams {
+            state_root:      parent.state_root.clone(),
+            height:          block.height,
+            timestamp:       block.timestamp,
+            cycles_limit:    metadata.cycles_limit,
+            proposer:        block.proposer.clone(),
+            vote_extensions: vec![],
+            extra_data:      Bytes::new(),
+        };
+        executor.trace(&params, txs, tx_hash)
+    }
+}
+
+#[async_trait]
+impl<S, DB, Mapping> APIAdapter for DevChain<S, DB, Mapping>
+where
+    S: 'static + Storage,
+    DB: 'static + cita_trie::DB,
+    Mapping: 'static + ServiceMapping,
+{
+    async fn insert_signed_txs(
+        &self,
+        _ctx: Context,
+        signed_tx: SignedTransaction,
+    ) -> ProtocolResult<()> {
+        self.submit(signed_tx).await
+    }
+
+    async fn get_block_by_height(
+        &self,
+        _ctx: Context,
+        height: Option<u64>,
+    ) -> ProtocolResult<Block> {
+        match height {
+            Some(id) => self.storage.get_block_by_height(id).await,
+            None => self.storage.get_latest_block().await,
+        }
+    }
+
+    async fn get_commit_proof(&self, _ctx: Context, height: u64) -> ProtocolResult<Proof> {
+        let block = self.storage.get_block_by_height(height).await?;
+        unsigned_proof(&block)
+    }
+
+    async fn get_receipt_by_tx_hash(
+        &self,
+        _ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Receipt> {
+        self.storage.get_receipt(tx_hash).await
+    }
+
+    async fn get_receipts_by_height(
+        &self,
+        _ctx: Context,
+        height: u64,
+    ) -> ProtocolResult<Vec<Receipt>> {
+        let block = self.storage.get_block_by_height(height).await?;
+        self.storage.get_receipts(block.ordered_tx_hashes).await
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        _ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<SignedTransaction> {
+        self.storage.get_transaction_by_hash(tx_hash).await
+    }
+
+    async fn query_service(
+        &self,
+        _ctx: Context,
+        height: u64,
+        cycles_limit: u64,
+        cycles_price: u64,
+        caller: Address,
+        service_name: String,
+        method: String,
+        payload: String,
+    ) -> ProtocolResult<ServiceResponse<String>> {
+        let block = self.storage.get_block_by_height(height).await?;
+        self.read(
+            &block.header,
+            cycles_limit,
+            cycles_price,
+            &caller,
+            &TransactionRequest {
+                service_name,
+                method,
+                payload,
+            },
+        )
+    }
+
+    async fn get_state_proof(
+        &self,
+        _ctx: Context,
+        height: u64,
+        service_name: String,
+        key: Bytes,
+    ) -> ProtocolResult<StateProof> {
+        let block = self.storage.get_block_by_height(height).await?;
+        self.executor(block.header.state_root)?
+            .get_state_proof(&service_name, &key)
+    }
+
+    async fn trace_transaction(&self, _ctx: Context, tx_hash: Hash) -> ProtocolResult<TxTrace> {
+        let receipt = self.storage.get_receipt(tx_hash).await?;
+        let block = self.storage.get_block_by_height(receipt.height).await?;
+        let parent = self
+            .storage
+            .get_block_by_height(receipt.height.saturating_sub(1))
+            .await?;
+        let txs = self
+            .storage
+            .get_transactions(block.ordered_tx_hashes.clone())
+            .await?;
+
+        self.trace(&parent.header, &block.header, &txs, &receipt.tx_hash)
+    }
+
+    async fn seal_block(&self, _ctx: Context) -> ProtocolResult<Block> {
+        self.seal().await
+    }
+}
+
+impl<S, DB, Mapping> ReadinessAdapter for DevChain<S, DB, Mapping>
+where
+    S: 'static + Storage,
+    DB: 'static + cita_trie::DB,
+    Mapping: 'static + ServiceMapping,
+{
+    fn current_height(&self) -> u64 {
+        self.height.load(Ordering::SeqCst)
+    }
+
+    fn best_peer_height(&self) -> u64 {
+        self.current_height()
+    }
+
+    // Blocks are sealed by the node itself
+    fn is_consensus_participating(&self) -> bool {
+        true
+    }
+}
+
+fn genesis_block<S, DB, Mapping>(
+    genesis: &Genesis,
+    storage: &Arc<S>,
+    trie_db: &Arc<DB>,
+    service_mapping: &Arc<Mapping>,
+) -> ProtocolResult<Block>
+where
+    S: 'static + Storage,
+    DB: 'static + cita_trie::DB,
+    Mapping: 'static + ServiceMapping,
+{
+    let metadata: Metadata = serde_json::from_str(genesis.get_payload("metadata"))
+        .map_err(DevChainError::DecodeMetadata)?;
+
+    let state_root = match genesis.state_commitment {
+        CommitmentScheme::MerklePatricia => MPTServiceExecutor::create_genesis(
+            genesis.services.clone(),
+            Arc::clone(trie_db),
+            Arc::clone(storage),
+            Arc::clone(service_mapping),
+        )?,
+    };
+
+    let header = BlockHeader {
+        chain_id: metadata.chain_id.clone(),
+        height: 0,
+        exec_height: 0,
+        pre_hash: Hash::from_empty(),
+        timestamp: genesis.timestamp,
+        logs_bloom: vec![],
+        order_root: Hash::from_empty(),
+        confirm_root: vec![],
+        state_root,
+        receipt_root: vec![],
+        cycles_used: vec![],
+        proposer: Address::default(),
+        proof: Proof {
+            height:     0,
+            round:      0,
+            block_hash: Hash::from_empty(),
+            signature:  Bytes::new(),
+            bitmap:     Bytes::new(),
+        },
+        validator_version: 0,
+        validators: validators(&metadata),
+        extra_data: Bytes::new(),
+    };
+    Ok(Block {
+        header,
+        ordered_tx_hashes: vec![],
+        vote_extensions: vec![],
+    })
+}
+
+fn read_metadata<E: Executor>(executor: &E, header: &BlockHeader) -> ProtocolResult<Metadata> {
+    let caller = Address::default();
+    let params = ExecutorParams {
+        state_root:      header.state_root.clone(),
+        height:          header.height,
+        timestamp:       header.timestamp,
+        cycles_limit:    u64::max_value(),
+        proposer:        caller.clone(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+    let resp = executor.read(&params, &caller, 1, &TransactionRequest {
+        service_name: "metadata".to_owned(),
+        method:       "get_metadata".to_owned(),
+        payload:      "".to_owned(),
+    })?;
+
+    serde_json::from_str(&resp.succeed_data).map_err(|e| DevChainError::DecodeMetadata(e).into())
+}
+
+fn validators(metadata: &Metadata) -> Vec<Validator> {
+    metadata
+        .verifier_list
+        .iter()
+        .map(|v| Validator {
+            address:        v.address.clone(),
+            propose_weight: v.propose_weight,
+            vote_weight:    v.vote_weight,
+        })
+        .collect()
+}
+
+// Values missing in storage are errors of the storage kind
+fn is_missing(e: &ProtocolError) -> bool {
+    match e.kind() {
+        ProtocolErrorKind::Storage => true,
+        _ => false,
+    }
+}
+
+// There is no voting, proofs only link blocks
+fn unsigned_proof(block: &Block) -> ProtocolResult<Proof> {
+    Ok(Proof {
+        height:     block.header.height,
+        round:      0,
+        block_hash: Hash::digest(block.encode_fixed()?),
+        signature:  Bytes::new(),
+        bitmap:     Bytes::new(),
+    })
+}
+
+#[derive(Debug, Display)]
+pub enum DevChainError {
+    #[display(fmt = "Decode metadata {:?}", _0)]
+    DecodeMetadata(serde_json::Error),
+
+    #[display(fmt = "Transaction hash {:?} mismatches its raw transaction", tx_hash)]
+    TxHash { tx_hash: Hash },
+
+    #[display(fmt = "Transaction {:?} is of another chain {:?}", tx_hash, chain_id)]
+    ChainId { tx_hash: Hash, chain_id: Hash },
+
+    #[display(fmt = "Signature of transaction {:?} is invalid", tx_hash)]
+    Signature { tx_hash: Hash },
+
+    #[display(fmt = "Payload of transaction {:?} exceeds limit {}", tx_hash, limit)]
+    PayloadLimit { tx_hash: Hash, limit: u64 },
+
+    #[display(fmt = "Transaction {:?} is pending already", tx_hash)]
+    Dup { tx_hash: Hash },
+
+    #[display(fmt = "Transaction {:?} is committed already", tx_hash)]
+    Committed { tx_hash: Hash },
+
+    #[display(fmt = "Transaction {:?} timed out at {}", tx_hash, timeout)]
+    Timeout { tx_hash: Hash, timeout: u64 },
+
+    #[display(fmt = "Timeout of transaction {:?} exceeds timeout gap", tx_hash)]
+    InvalidTimeout { tx_hash: Hash },
+}
+
+impl std::error::Error for DevChainError {}
+
+impl From<DevChainError> for ProtocolError {
+    fn from(err: DevChainError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Executor, Box::new(err))
+    }
+}
@@ -0,0 +1,298 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use cita_trie::MemoryDB;
+use futures::executor::block_on;
+
+use asset::types::{Asset, GetBalanceResponse};
+use asset::AssetService;
+use binding_macro::{cycles, service};
+use common_crypto::{
+    HashValue, PrivateKey, PublicKey, Secp256k1PrivateKey, Signature, ToPublicKey,
+};
+use metadata::MetadataService;
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{
+    executed_state_root, APIAdapter, Context, ReadinessAdapter, Service, ServiceMapping,
+    ServiceResponse, ServiceSDK, Storage,
+};
+use protocol::types::{
+    Address, Genesis, Hash, RawTransaction, ServiceContext, SignedTransaction, TransactionRequest,
+};
+use protocol::ProtocolResult;
+use testkit::MemoryStorage;
+
+use super::{DevChain, SealMode};
+
+#[test]
+fn test_instant_seal() {
+    let storage = Arc::new(MemoryStorage::default());
+    let chain = dev_chain(Arc::clone(&storage), SealMode::Instant);
+    let genesis_block = block_on(storage.get_latest_block()).unwrap();
+    assert_eq!(chain.current_height(), 0);
+
+    let stx = create_asset_tx(1);
+    block_on(chain.insert_signed_txs(Context::new(), stx.clone())).unwrap();
+    assert_eq!(chain.current_height(), 1);
+
+    let block = block_on(storage.get_latest_block()).unwrap();
+    assert_eq!(block.header.height, 1);
+    assert_eq!(block.header.exec_height, 1);
+    assert_eq!(block.ordered_tx_hashes, vec![stx.tx_hash.clone()]);
+    assert_eq!(
+        block.header.pre_hash,
+        Hash::digest(genesis_block.encode_fixed().unwrap())
+    );
+    // Timestamps go forward by block interval of metadata
+    assert_eq!(
+        block.header.timestamp,
+        genesis_block.header.timestamp + 3000
+    );
+
+    let receipt = block_on(chain.get_receipt_by_tx_hash(Context::new(), stx.tx_hash)).unwrap();
+    assert!(!receipt.response.response.is_error());
+    assert_eq!(receipt.height, 1);
+    assert_eq!(receipt.state_root, block.header.state_root);
+
+    let asset: Asset = serde_json::from_str(&receipt.response.response.succeed_data).unwrap();
+    let resp = block_on(chain.query_service(
+        Context::new(),
+        1,
+        u64::max_value(),
+        1,
+        Address::default(),
+        "asset".to_owned(),
+        "get_balance".to_owned(),
+        format!(
+            r#"{{ "asset_id": "{}", "user": "{}" }}"#,
+            asset.id.as_hex(),
+            asset.issuer.as_hex()
+        ),
+    ))
+    .unwrap();
+    let balance: GetBalanceResponse = serde_json::from_str(&resp.succeed_data).unwrap();
+    assert_eq!(balance.balance, 1000);
+}
+
+#[test]
+fn test_manual_seal() {
+    let storage = Arc::new(MemoryStorage::default());
+    let chain = dev_chain(Arc::clone(&storage), SealMode::Manual);
+
+    let stx = create_asset_tx(1);
+    block_on(chain.submit(stx.clone())).unwrap();
+    assert_eq!(chain.current_height(), 0);
+
+    let block = block_on(chain.seal_block(Context::new())).unwrap();
+    assert_eq!(block.header.height, 1);
+    assert_eq!(block.ordered_tx_hashes, vec![stx.tx_hash]);
+
+    // Blocks without transactions keep the state
+    let empty = block_on(chain.seal()).unwrap();
+    assert_eq!(empty.header.height, 2);
+    assert!(empty.ordered_tx_hashes.is_empty());
+    assert_eq!(empty.header.state_root, block.header.state_root);
+    assert_eq!(
+        block_on(executed_state_root(&*storage, 2)).unwrap(),
+        Some(block.header.state_root)
+    );
+
+    // The chain is resumed from storage
+    let resumed = dev_chain(Arc::clone(&storage), SealMode::Manual);
+    assert_eq!(resumed.current_height(), 2);
+}
+
+#[test]
+fn test_same_txs_give_same_chain() {
+    let seal = || {
+        let storage = Arc::new(MemoryStorage::default());
+        let chain = dev_chain(Arc::clone(&storage), SealMode::Instant);
+
+        block_on(chain.submit(create_asset_tx(1))).unwrap();
+        block_on(chain.submit(create_asset_tx(2))).unwrap();
+        block_on(storage.get_latest_block()).unwrap()
+    };
+
+    assert_eq!(seal(), seal());
+}
+
+#[test]
+fn test_reject_invalid_tx() {
+    let storage = Arc::new(MemoryStorage::default());
+    let chain = dev_chain(Arc::clone(&storage), SealMode::Instant);
+
+    let mut stx = create_asset_tx(1);
+    stx.signature = create_asset_tx(2).signature;
+    assert!(block_on(chain.submit(stx)).is_err());
+
+    let stx = signed_tx(1, Hash::digest(Bytes::from_static(b"other")));
+    assert!(block_on(chain.submit(stx)).is_err());
+
+    assert_eq!(chain.current_height(), 0);
+}
+
+#[test]
+fn test_reject_replayed_tx() {
+    let storage = Arc::new(MemoryStorage::default());
+    let chain = dev_chain(Arc::clone(&storage), SealMode::Manual);
+
+    let stx = create_asset_tx(1);
+    block_on(chain.submit(stx.clone())).unwrap();
+    assert!(block_on(chain.submit(stx.clone())).is_err());
+
+    let block = block_on(chain.seal()).unwrap();
+    assert_eq!(block.ordered_tx_hashes, vec![stx.tx_hash.clone()]);
+    assert!(block_on(chain.submit(stx)).is_err());
+
+    assert!(block_on(chain.seal()).unwrap().ordered_tx_hashes.is_empty());
+}
+
+#[test]
+fn test_reject_timeout_tx() {
+    let storage = Arc::new(MemoryStorage::default());
+    let chain = dev_chain(Arc::clone(&storage), SealMode::Manual);
+    block_on(chain.seal()).unwrap();
+
+    // Timeout gap of genesis metadata is 20
+    for timeout in [0, 1, 22].iter() {
+        let mut raw = create_asset_tx(1).raw;
+        raw.timeout = *timeout;
+        assert!(block_on(chain.submit(sign(1, raw))).is_err());
+    }
+
+    let mut raw = create_asset_tx(1).raw;
+    raw.timeout = 21;
+    block_on(chain.submit(sign(1, raw))).unwrap();
+}
+
+#[test]
+fn test_drop_failed_tx() {
+    let storage = Arc::new(MemoryStorage::default());
+    let chain = dev_chain(Arc::clone(&storage), SealMode::Manual);
+
+    let stx = create_asset_tx(1);
+    let mut raw = create_asset_tx(2).raw;
+    raw.request = TransactionRequest {
+        service_name: "panic".to_owned(),
+        method:       "panic".to_owned(),
+        payload:      "".to_owned(),
+    };
+    let failed = sign(2, raw);
+    block_on(chain.submit(stx.clone())).unwrap();
+    block_on(chain.submit(failed.clone())).unwrap();
+
+    let block = block_on(chain.seal()).unwrap();
+    assert_eq!(block.ordered_tx_hashes, vec![stx.tx_hash]);
+    assert!(block_on(chain.get_transaction_by_hash(Context::new(), failed.tx_hash)).is_err());
+
+    let block = block_on(chain.seal()).unwrap();
+    assert_eq!(block.header.height, 2);
+    assert!(block.ordered_tx_hashes.is_empty());
+}
+
+fn dev_chain(
+    storage: Arc<MemoryStorage>,
+    seal_mode: SealMode,
+) -> DevChain<MemoryStorage, MemoryDB, MockServiceMapping> {
+    let toml_str = include_str!("../../executor/tests/genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    block_on(DevChain::new(
+        &genesis,
+        storage,
+        Arc::new(MemoryDB::new(false)),
+        Arc::new(MockServiceMapping {}),
+        seal_mode,
+    ))
+    .unwrap()
+}
+
+fn create_asset_tx(seed: u8) -> SignedTransaction {
+    let chain_id =
+        Hash::from_hex("0xb6a4d7da21443f5e816e8700eea87610e6d769657d6b8ec73028457bf2ca4036")
+            .unwrap();
+    signed_tx(seed, chain_id)
+}
+
+// Create an asset of supply 1000 by the seeded private key, it's free since
+// the key has no balance to pay fee
+fn signed_tx(seed: u8, chain_id: Hash) -> SignedTransaction {
+    let raw = RawTransaction {
+        chain_id,
+        nonce: Hash::digest(Bytes::from(vec![seed])),
+        timeout: 20,
+        cycles_price: 0,
+        cycles_limit: 1_000_000,
+        request: TransactionRequest {
+            service_name: "asset".to_owned(),
+            method:       "create_asset".to_owned(),
+            payload:      format!(
+                r#"{{ "name": "Token{}", "symbol": "T{}", "supply": 1000 }}"#,
+                seed, seed
+            ),
+        },
+        sender: None,
+        valid_until_timestamp: None,
+    };
+    sign(seed, raw)
+}
+
+fn sign(seed: u8, raw: RawTransaction) -> SignedTransaction {
+    let private_key = Secp256k1PrivateKey::try_from(&[seed; 32][..]).unwrap();
+    let tx_hash = Hash::digest(raw.encode_fixed().unwrap());
+    let hash_value = HashValue::try_from(tx_hash.as_bytes().as_ref()).unwrap();
+
+    SignedTransaction {
+        raw,
+        tx_hash,
+        pubkey: private_key.pub_key().to_bytes(),
+        signature: private_key.sign_message(&hash_value).to_bytes(),
+    }
+}
+
+struct MockServiceMapping;
+
+impl ServiceMapping for MockServiceMapping {
+    fn get_service<SDK: 'static + ServiceSDK>(
+        &self,
+        name: &str,
+        sdk: SDK,
+    ) -> ProtocolResult<Box<dyn Service>> {
+        let service = match name {
+            "asset" => Box::new(AssetService::new(sdk)) as Box<dyn Service>,
+            "metadata" => Box::new(MetadataService::new(sdk)) as Box<dyn Service>,
+            "panic" => Box::new(PanicService::new(sdk)) as Box<dyn Service>,
+            _ => panic!("not found service"),
+        };
+
+        Ok(service)
+    }
+
+    fn list_service_name(&self) -> Vec<String> {
+        vec![
+            "asset".to_owned(),
+            "metadata".to_owned(),
+            "panic".to_owned(),
+        ]
+    }
+}
+
+// Calls of it fail the executor, instead of their receipts
+struct PanicService<SDK> {
+    sdk: SDK,
+}
+
+#[service]
+impl<SDK: ServiceSDK> PanicService<SDK> {
+    fn new(sdk: SDK) -> Self {
+        Self { sdk }
+    }
+
+    #[cycles(100)]
+    #[write]
+    fn panic(&mut self, ctx: ServiceContext) -> ServiceResponse<()> {
+        self.sdk.set_value("panic".to_owned(), "panic".to_owned());
+        panic!("inner chain error")
+    }
+}
@@ -4,8 +4,10 @@ use protocol::traits::{Executor, ExecutorFactory, ServiceMapping, Storage};
 use protocol::types::MerkleRoot;
 use protocol::ProtocolResult;
 
-use crate::executor::ServiceExecutor;
+use crate::executor::MPTServiceExecutor;
 
+/// Factory of executors committing state in merkle patricia tries, the only
+/// scheme of `CommitmentScheme` yet.
 pub struct ServiceExecutorFactory;
 
 impl<DB: 'static + cita_trie::DB, S: 'static + Storage, Mapping: 'static + ServiceMapping>
@@ -17,7 +19,7 @@ impl<DB: 'static + cita_trie::DB, S: 'static + Storage, Mapping: 'static + Servi
         storage: Arc<S>,
         mapping: Arc<Mapping>,
     ) -> ProtocolResult<Box<dyn Executor>> {
-        let executor = ServiceExecutor::with_root(root, db, storage, mapping)?;
+        let executor = MPTServiceExecutor::with_root(root, db, storage, mapping)?;
         Ok(Box::new(executor))
     }
 }
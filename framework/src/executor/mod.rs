@@ -1,8 +1,14 @@
 mod factory;
+mod replay;
 #[cfg(test)]
 mod tests;
+mod trace;
 
 pub use factory::ServiceExecutorFactory;
+pub use replay::{
+    diff_replay_logs, ReplayDivergence, ReplayLog, ReplayStage, ReplayStep, StateWrite,
+};
+pub use trace::TraceRecorder;
 
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -10,22 +16,53 @@ use std::panic::{self, AssertUnwindSafe};
 use std::rc::Rc;
 use std::sync::Arc;
 
-use cita_trie::DB as TrieDB;
 use derive_more::{Display, From};
+use tracing::{debug_span, field};
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
+use protocol::fixed_codec::FixedCodec;
 use protocol::traits::{
-    Dispatcher, Executor, ExecutorParams, ExecutorResp, NoopDispatcher, ServiceMapping,
-    ServiceResponse, ServiceState, Storage,
+    Dispatcher, Executor, ExecutorParams, ExecutorResp, MethodAccess, NoopDispatcher,
+    ServiceMapping, ServiceResponse, ServiceState, StateCommitment, Storage, FORBIDDEN_CODE,
+    HALTED_CODE, INSUFFICIENT_FEE_CODE, OUT_OF_CYCLES_CODE, SERVICE_NOT_FOUND_CODE,
+    UNAUTHORIZED_CODE,
 };
 use protocol::types::{
-    Address, Bloom, BloomInput, Hash, MerkleRoot, Receipt, ReceiptResponse, ServiceContext,
-    ServiceContextParams, ServiceParam, SignedTransaction, TransactionRequest,
+    service_admins_key, Address, Bloom, BloomInput, Feature, Hash, Hex, MerkleRoot, Metadata,
+    PayloadLimits, Receipt, ReceiptResponse, ServiceContext, ServiceContextError,
+    ServiceContextParams, ServiceParam, SignedTransaction, StateProof, TransactionRequest, TxTrace,
+    VerifyAuthorizationPayload, GOVERNANCE_SERVICE, METADATA_KEY, STORAGE_RECLAIM_KEY,
 };
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 use crate::binding::sdk::{DefalutServiceSDK, DefaultChainQuerier};
-use crate::binding::state::{GeneralServiceState, MPTTrie};
+use crate::binding::state::{deposit, GeneralServiceState, MPTTrie, MeteredState};
+
+// Signer of a transaction acting for another sender is authorized by
+// this service method
+const ACCOUNT_SERVICE: &str = "account";
+const VERIFY_AUTHORIZATION_METHOD: &str = "verify_authorization";
+
+// Charges and halt of transactions are configured in metadata of this
+// service
+const METADATA_SERVICE: &str = "metadata";
+// Storage deposit and rent are paid to this service, owners failed to pay
+// rent are written to its state
+const RENT_SERVICE: &str = "asset";
+
+// Rules of transactions configured in metadata, they are read once for a
+// block
+#[derive(Debug, Clone, Copy, Default)]
+struct TxRules {
+    base_cycles:     u64,
+    // Writes of failed calls are reverted, from `Feature::TxCycles`
+    revert_failed:   bool,
+    storage_deposit: bool,
+    // Storage access, events and service calls are charged by the pricing
+    cycles_pricing:  bool,
+    // Only governance transactions are executed
+    halted:          bool,
+}
 
 enum HookType {
     Before,
@@ -38,30 +75,39 @@ enum ExecType {
     Write,
 }
 
-pub struct ServiceExecutor<S: Storage, DB: TrieDB, Mapping: ServiceMapping> {
+/// Executor of services, states of services and the root state mapping
+/// service names to their roots are committed in the scheme of `C`.
+pub struct ServiceExecutor<S: Storage, C: StateCommitment, Mapping: ServiceMapping> {
     service_mapping: Arc<Mapping>,
     querier:         Rc<DefaultChainQuerier<S>>,
-    states:          Rc<HashMap<String, Rc<RefCell<GeneralServiceState<DB>>>>>,
-    root_state:      Rc<RefCell<GeneralServiceState<DB>>>,
+    states:          Rc<HashMap<String, Rc<RefCell<GeneralServiceState<C>>>>>,
+    root_state:      Rc<RefCell<GeneralServiceState<C>>>,
+    tracer:          Rc<RefCell<TraceRecorder>>,
 }
 
-impl<S: Storage, DB: TrieDB, Mapping: ServiceMapping> Clone for ServiceExecutor<S, DB, Mapping> {
+/// Executor of chains committing state in merkle patricia tries.
+pub type MPTServiceExecutor<S, DB, Mapping> = ServiceExecutor<S, MPTTrie<DB>, Mapping>;
+
+impl<S: Storage, C: StateCommitment, Mapping: ServiceMapping> Clone
+    for ServiceExecutor<S, C, Mapping>
+{
     fn clone(&self) -> Self {
         Self {
             service_mapping: Arc::clone(&self.service_mapping),
             querier:         Rc::clone(&self.querier),
             states:          Rc::clone(&self.states),
             root_state:      Rc::clone(&self.root_state),
+            tracer:          Rc::clone(&self.tracer),
         }
     }
 }
 
-impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMapping>
-    ServiceExecutor<S, DB, Mapping>
+impl<S: 'static + Storage, C: 'static + StateCommitment, Mapping: 'static + ServiceMapping>
+    ServiceExecutor<S, C, Mapping>
 {
     pub fn create_genesis(
         services: Vec<ServiceParam>,
-        trie_db: Arc<DB>,
+        trie_db: Arc<C::DB>,
         storage: Arc<S>,
         mapping: Arc<Mapping>,
     ) -> ProtocolResult<MerkleRoot> {
@@ -69,7 +115,7 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
 
         let mut states = HashMap::new();
         for name in mapping.list_service_name().into_iter() {
-            let trie = MPTTrie::new(Arc::clone(&trie_db));
+            let trie = C::new(Arc::clone(&trie_db));
 
             states.insert(name, Rc::new(RefCell::new(GeneralServiceState::new(trie))));
         }
@@ -92,7 +138,7 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
             state.borrow_mut().stash()?;
         }
 
-        let trie = MPTTrie::new(Arc::clone(&trie_db));
+        let trie = C::new(Arc::clone(&trie_db));
         let mut root_state = GeneralServiceState::new(trie);
         for (name, state) in states.iter() {
             let root = state.borrow_mut().commit()?;
@@ -104,18 +150,18 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
 
     pub fn with_root(
         root: MerkleRoot,
-        trie_db: Arc<DB>,
+        trie_db: Arc<C::DB>,
         storage: Arc<S>,
         service_mapping: Arc<Mapping>,
     ) -> ProtocolResult<Self> {
-        let trie = MPTTrie::from(root, Arc::clone(&trie_db))?;
+        let trie = C::from_root(root, Arc::clone(&trie_db))?;
         let root_state = GeneralServiceState::new(trie);
 
         let mut states = HashMap::new();
         for name in service_mapping.list_service_name().into_iter() {
             let trie = match root_state.get(&name)? {
-                Some(service_root) => MPTTrie::from(service_root, Arc::clone(&trie_db))?,
-                None => MPTTrie::new(Arc::clone(&trie_db)),
+                Some(service_root) => C::from_root(service_root, Arc::clone(&trie_db))?,
+                None => C::new(Arc::clone(&trie_db)),
             };
 
             let service_state = GeneralServiceState::new(trie);
@@ -127,6 +173,7 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
             querier: Rc::new(DefaultChainQuerier::new(storage)),
             states: Rc::new(states),
             root_state: Rc::new(RefCell::new(root_state)),
+            tracer: Rc::new(RefCell::new(TraceRecorder::default())),
         })
     }
 
@@ -157,7 +204,7 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
 
     fn hook(&mut self, hook: HookType, exec_params: &ExecutorParams) -> ProtocolResult<()> {
         for name in self.service_mapping.list_service_name().into_iter() {
-            let sdk = self.get_sdk(&name)?;
+            let sdk = self.get_sdk(&name, None)?;
             let mut service = self.service_mapping.get_service(name.as_str(), sdk)?;
 
             let hook_ret = match hook {
@@ -178,20 +225,29 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
         Ok(())
     }
 
+    // Storage access is charged through the context of the call
     fn get_sdk(
         &self,
         service: &str,
-    ) -> ProtocolResult<DefalutServiceSDK<GeneralServiceState<DB>, DefaultChainQuerier<S>, Self>>
-    {
+        context: Option<&ServiceContext>,
+    ) -> ProtocolResult<
+        DefalutServiceSDK<MeteredState<GeneralServiceState<C>>, DefaultChainQuerier<S>, Self>,
+    > {
         let state = self
             .states
             .get(service)
             .ok_or(ExecutorError::NotFoundService {
                 service: service.to_owned(),
             })?;
+        let mut metered = MeteredState::new(Rc::clone(&state), context.cloned())
+            .with_tracer(service, Rc::clone(&self.tracer));
+        // Balances paying for storage are never reclaimed
+        if service == RENT_SERVICE {
+            metered = metered.exempt_deposit();
+        }
 
         Ok(DefalutServiceSDK::new(
-            Rc::clone(&state),
+            Rc::new(RefCell::new(metered)),
             Rc::clone(&self.querier),
             (*self).clone(),
         ))
@@ -226,66 +282,188 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
         Ok(ServiceContext::new(ctx_params))
     }
 
+    // Caller of a transaction pays the base cycles and cycles consumed by
+    // the call, the rest of its cycles limit is refunded, which means never
+    // charged. Writes of a failed call are reverted, a call runs out of
+    // cycles uses up the limit. Fee is collected by `tx_hook_after` after
+    // the revert, so failed transactions pay as well. Before the revert is
+    // active, calls run as they always did: hooks before, the call, hooks
+    // after, whatever the response is. Only calls unwound by charged cycles
+    // are reverted then, with hooks before them, and they pay nothing.
     fn catch_call(
         &mut self,
         context: ServiceContext,
-        exec_type: ExecType,
+        rules: TxRules,
     ) -> ProtocolResult<ServiceResponse<String>> {
-        let result = match exec_type {
-            ExecType::Read => panic::catch_unwind(AssertUnwindSafe(|| {
-                self.call(context.clone(), exec_type.clone())
-            })),
-            ExecType::Write => panic::catch_unwind(AssertUnwindSafe(|| {
-                self.call_with_tx_hooks(context.clone(), exec_type.clone())
-            })),
+        let result = if context.sub_cycles(rules.base_cycles) {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                // TODO: If tx_hook_before_ failed, we should not exec the tx.
+                // Need a mechanism for this.
+                self.call_tx_hooks(HookType::Before, &context);
+                self.call(context.clone(), ExecType::Write)
+            }))
+        } else {
+            Ok(out_of_cycles())
         };
-        match result {
-            Ok(r) => {
-                self.stash()?;
-                Ok(r)
-            }
-            Err(e) => {
-                self.revert_cache()?;
-                log::error!("inner chain error occurred when calling service: {:?}", e);
-                Err(ExecutorError::CallService(format!("{:?}", e)).into())
-            }
+
+        let (mut resp, unwound) = match result {
+            Ok(resp) => (resp, false),
+            Err(e) => match e.downcast_ref() {
+                Some(ServiceContextError::OutOfCycles) => (out_of_cycles(), true),
+                _ => {
+                    self.revert_cache()?;
+                    log::error!("inner chain error occurred when calling service: {:?}", e);
+                    return Err(ExecutorError::CallService(format!("{:?}", e)).into());
+                }
+            },
+        };
+
+        if !rules.revert_failed && unwound {
+            self.revert_cache()?;
+            context.clear_storage_deposit_changes();
+            return Ok(resp);
+        }
+        if resp.is_error() && rules.revert_failed {
+            self.revert_cache()?;
+            context.clear_storage_deposit_changes();
+        }
+        if resp.code == OUT_OF_CYCLES_CODE && rules.revert_failed {
+            let left = context.get_cycles_limit() - context.get_cycles_used();
+            context.sub_cycles(left);
+        }
+
+        let mut result = panic::catch_unwind(AssertUnwindSafe(|| {
+            self.call_tx_hooks(HookType::After, &context)
+        }));
+        if let Err(Some(ServiceContextError::InsufficientFee)) =
+            result.as_ref().map_err(|e| e.downcast_ref())
+        {
+            // Caller can't pay, writes of the transaction are reverted and
+            // hooks charge it again by what it has
+            self.revert_cache()?;
+            context.clear_storage_deposit_changes();
+            resp = insufficient_fee();
+
+            let context = context.clone().with_fee_failed();
+            result = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.call_tx_hooks(HookType::After, &context)
+            }));
         }
+        if let Err(e) = result {
+            self.revert_cache()?;
+            log::error!("inner chain error occurred when calling tx hook: {:?}", e);
+            return Err(ExecutorError::CallService(format!("{:?}", e)).into());
+        }
+
+        self.stash()?;
+        Ok(resp)
     }
 
-    fn call_with_tx_hooks(
-        &self,
-        context: ServiceContext,
-        exec_type: ExecType,
-    ) -> ServiceResponse<String> {
-        let mut tx_hook_services = vec![];
+    fn call_tx_hooks(&self, hook: HookType, context: &ServiceContext) {
         for name in self.service_mapping.list_service_name().into_iter() {
+            // Hooks are not paid by the transaction
             let sdk = self
-                .get_sdk(&name)
+                .get_sdk(&name, None)
                 .unwrap_or_else(|e| panic!("get target service sdk failed: {}", e));
-            let tx_hook_service = self
+            let mut tx_hook_service = self
                 .service_mapping
                 .get_service(name.as_str(), sdk)
                 .unwrap_or_else(|e| panic!("get target service sdk failed: {}", e));
-            tx_hook_services.push(tx_hook_service);
+
+            match hook {
+                HookType::Before => tx_hook_service.tx_hook_before_(context.clone()),
+                HookType::After => tx_hook_service.tx_hook_after_(context.clone()),
+            }
         }
-        // TODO: If tx_hook_before_ failed, we should not exec the tx.
-        // Need a mechanism for this.
-        for tx_hook_service in tx_hook_services.iter_mut() {
-            tx_hook_service.tx_hook_before_(context.clone());
+    }
+
+    fn metadata(&self) -> ProtocolResult<Option<Metadata>> {
+        match self.states.get(METADATA_SERVICE) {
+            Some(state) => state.borrow().get(&METADATA_KEY.to_owned()),
+            None => Ok(None),
+        }
+    }
+
+    // Blocks from the activation of a feature unknown to this node are not
+    // executed, the node stops there until it's upgraded
+    fn check_features(&self, height: u64) -> ProtocolResult<()> {
+        let metadata = match self.metadata()? {
+            Some(metadata) => metadata,
+            None => return Ok(()),
+        };
+
+        match metadata.chain_spec.unsupported_at(height) {
+            Some(activation) => Err(ExecutorError::UnsupportedFeature {
+                name:   activation.name.clone(),
+                height: activation.height,
+            }
+            .into()),
+            None => Ok(()),
         }
-        let original_res = self.call(context.clone(), exec_type);
-        // TODO: If the tx fails, status tx_hook_after_ changes will also be reverted.
-        // It may not be what the developer want.
-        // Need a new mechanism for this.
-        for tx_hook_service in tx_hook_services.iter_mut() {
-            tx_hook_service.tx_hook_after_(context.clone());
+    }
+
+    // Nothing is charged or halted if there's no metadata service. Stored
+    // bytes are recorded once `Feature::StorageDeposit` is active, if either
+    // storage deposit or rent is enabled.
+    fn tx_rules(&self, height: u64) -> ProtocolResult<TxRules> {
+        Ok(self
+            .metadata()?
+            .map(|m| {
+                let tx_cycles = m.chain_spec.is_active(Feature::TxCycles, height);
+                let storage_deposit = m.chain_spec.is_active(Feature::StorageDeposit, height)
+                    && (m.storage_deposit_per_byte > 0 || m.rent_epoch > 0);
+                TxRules {
+                    base_cycles: if tx_cycles { m.tx_base_cycles } else { 0 },
+                    revert_failed: tx_cycles,
+                    storage_deposit,
+                    cycles_pricing: m.chain_spec.is_active(Feature::CyclesPricing, height),
+                    halted: m.is_halted(height),
+                }
+            })
+            .unwrap_or_default())
+    }
+
+    // Delete entries deposited by owners failed to pay storage rent, the
+    // service collecting rent writes them in block hooks.
+    fn reclaim_storage(&mut self) -> ProtocolResult<()> {
+        let owners = match self.states.get(RENT_SERVICE) {
+            Some(state) => {
+                let raw: Option<Bytes> = state.borrow().get(&STORAGE_RECLAIM_KEY.to_owned())?;
+                match raw.filter(|raw| !raw.is_empty()) {
+                    Some(raw) => {
+                        state
+                            .borrow_mut()
+                            .insert(STORAGE_RECLAIM_KEY.to_owned(), Bytes::new())?;
+                        Vec::<Address>::decode_fixed(raw)?
+                    }
+                    None => return Ok(()),
+                }
+            }
+            None => return Ok(()),
+        };
+
+        for state in self.states.values() {
+            for owner in owners.iter() {
+                let released = deposit::reclaim(&mut *state.borrow_mut(), owner)?;
+                if released > 0 {
+                    log::info!("[executor]: reclaim {} bytes of {:?}", released, owner);
+                }
+            }
         }
-        original_res
+
+        self.stash()
     }
 
     fn call(&self, context: ServiceContext, exec_type: ExecType) -> ServiceResponse<String> {
+        if !self.states.contains_key(context.get_service_name()) {
+            return ServiceResponse::<String>::from_error(
+                SERVICE_NOT_FOUND_CODE,
+                format!("not found service:{:?}", context.get_service_name()),
+            );
+        }
+
         let sdk = self
-            .get_sdk(context.get_service_name())
+            .get_sdk(context.get_service_name(), Some(&context))
             .unwrap_or_else(|e| panic!("get target service sdk failed: {}", e));
 
         let mut service = self
@@ -293,71 +471,143 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
             .get_service(context.get_service_name(), sdk)
             .unwrap_or_else(|e| panic!("get target service failed: {}", e));
 
-        match exec_type {
-            ExecType::Read => service.read_(context),
-            ExecType::Write => service.write_(context),
+        let access = service.access_(context.get_service_method());
+        if !self
+            .has_access(&context, &access)
+            .unwrap_or_else(|e| panic!("get service admins failed: {}", e))
+        {
+            return ServiceResponse::<String>::from_error(
+                FORBIDDEN_CODE,
+                format!(
+                    "method {:?} of service {:?} requires {:?}",
+                    context.get_service_method(),
+                    context.get_service_name(),
+                    access
+                ),
+            );
         }
-    }
 
-    fn logs_bloom(&self, receipts: &[Receipt]) -> Bloom {
-        let mut bloom = Bloom::default();
-        for receipt in receipts {
-            for event in receipt.events.iter() {
-                let bytes =
-                    BytesMut::from((event.service.clone() + &event.data).as_bytes()).freeze();
-                let hash = Hash::digest(bytes).as_bytes();
+        let tracing = self.tracer.borrow().is_tracing();
+        if tracing {
+            self.tracer.borrow_mut().call(&context);
+        }
 
-                let input = BloomInput::Raw(hash.as_ref());
-                bloom.accrue(input)
+        let resp = match exec_type {
+            ExecType::Read => service.read_(context.clone()),
+            ExecType::Write => service.write_(context.clone()),
+        };
+
+        if tracing {
+            self.tracer.borrow_mut().ret(&context, &resp);
+        }
+        resp
+    }
+
+    // Methods with admin access are called by admins of the service, which
+    // are written in state of the governance service. Methods limited to a
+    // service are called by the service only, not by transactions.
+    fn has_access(&self, context: &ServiceContext, access: &MethodAccess) -> ProtocolResult<bool> {
+        match access {
+            MethodAccess::Public => Ok(true),
+            MethodAccess::Service(name) => Ok(context.get_caller_service() == Some(name.as_str())),
+            MethodAccess::Admin => {
+                let state = match self.states.get(GOVERNANCE_SERVICE) {
+                    Some(state) => state,
+                    None => return Ok(false),
+                };
+
+                let admins: Option<Vec<Address>> = state
+                    .borrow()
+                    .get(&service_admins_key(context.get_service_name()))?;
+                Ok(admins
+                    .map(|admins| admins.contains(&context.get_caller()))
+                    .unwrap_or(false))
             }
         }
+    }
 
-        bloom
+    fn is_authorized(
+        &self,
+        params: &ExecutorParams,
+        stx: &SignedTransaction,
+        signer: &Address,
+        sender: &Address,
+    ) -> ProtocolResult<bool> {
+        if signer == sender {
+            return Ok(true);
+        }
+
+        let has_account_service = self
+            .service_mapping
+            .list_service_name()
+            .iter()
+            .any(|name| name == ACCOUNT_SERVICE);
+        if !has_account_service {
+            return Ok(false);
+        }
+
+        let payload = VerifyAuthorizationPayload {
+            sender:       sender.clone(),
+            signer:       signer.clone(),
+            tx_hash:      stx.tx_hash.clone(),
+            service_name: stx.raw.request.service_name.clone(),
+            method:       stx.raw.request.method.clone(),
+            payload:      stx.raw.request.payload.clone(),
+        };
+        let request = TransactionRequest {
+            service_name: ACCOUNT_SERVICE.to_owned(),
+            method:       VERIFY_AUTHORIZATION_METHOD.to_owned(),
+            payload:      serde_json::to_string(&payload).map_err(ExecutorError::JsonParse)?,
+        };
+        let context = self.get_context(
+            Some(stx.tx_hash.clone()),
+            Some(stx.raw.nonce.clone()),
+            sender,
+            stx.raw.cycles_price,
+            stx.raw.cycles_limit,
+            params,
+            &request,
+        )?;
+
+        // Panic, such as out of cycles, means not authorized
+        let resp = panic::catch_unwind(AssertUnwindSafe(|| self.call(context, ExecType::Read)));
+        Ok(resp
+            .map(|resp| !resp.is_error() && resp.succeed_data == "true")
+            .unwrap_or(false))
     }
-}
 
-impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMapping> Executor
-    for ServiceExecutor<S, DB, Mapping>
-{
-    fn exec(
+    // Steps of stashed writes are recorded if `steps` is passed
+    fn execute(
         &mut self,
         params: &ExecutorParams,
         txs: &[SignedTransaction],
+        mut steps: Option<&mut Vec<ReplayStep>>,
     ) -> ProtocolResult<ExecutorResp> {
+        self.check_features(params.height)?;
         self.hook(HookType::Before, params)?;
+        if let Some(steps) = steps.as_mut() {
+            steps.push(self.take_step(ReplayStage::HookBefore, None, 0));
+        }
 
-        let mut receipts = txs
-            .iter()
-            .map(|stx| {
-                let caller = Address::from_pubkey_bytes(stx.pubkey.clone())?;
-                let context = self.get_context(
+        let rules = self.tx_rules(params.height)?;
+        let mut receipts = Vec::with_capacity(txs.len());
+        for stx in txs.iter() {
+            let receipt = self.exec_tx(params, stx, rules)?;
+            if let Some(steps) = steps.as_mut() {
+                steps.push(self.take_step(
+                    ReplayStage::Transaction,
                     Some(stx.tx_hash.clone()),
-                    Some(stx.raw.nonce.clone()),
-                    &caller,
-                    stx.raw.cycles_price,
-                    stx.raw.cycles_limit,
-                    params,
-                    &stx.raw.request,
-                )?;
-
-                let exec_resp = self.catch_call(context.clone(), ExecType::Write)?;
-
-                Ok(Receipt {
-                    state_root:  MerkleRoot::from_empty(),
-                    height:      context.get_current_height(),
-                    tx_hash:     stx.tx_hash.clone(),
-                    cycles_used: context.get_cycles_used(),
-                    events:      context.get_events(),
-                    response:    ReceiptResponse {
-                        service_name: context.get_service_name().to_owned(),
-                        method:       context.get_service_method().to_owned(),
-                        response:     exec_resp,
-                    },
-                })
-            })
-            .collect::<Result<Vec<Receipt>, ProtocolError>>()?;
+                    receipt.cycles_used,
+                ));
+            }
+            receipts.push(receipt);
+        }
 
         self.hook(HookType::After, params)?;
+        self.reclaim_storage()?;
+        if let Some(steps) = steps.as_mut() {
+            steps.push(self.take_step(ReplayStage::HookAfter, None, 0));
+        }
 
         let state_root = self.commit()?;
         let mut all_cycles_used = 0;
@@ -376,6 +626,151 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
         })
     }
 
+    fn exec_tx(
+        &mut self,
+        params: &ExecutorParams,
+        stx: &SignedTransaction,
+        rules: TxRules,
+    ) -> ProtocolResult<Receipt> {
+        let span = debug_span!(
+            "executor_tx",
+            tx_hash = field::display(stx.tx_hash.as_hex())
+        );
+        let _enter = span.enter();
+
+        if rules.halted && !stx.raw.request.is_governance() {
+            return Ok(rejected_receipt(
+                params,
+                stx,
+                HALTED_CODE,
+                "chain is halted".to_owned(),
+            ));
+        }
+
+        let signer = Address::from_pubkey_bytes(stx.pubkey.clone())?;
+        let caller = stx.raw.sender.clone().unwrap_or_else(|| signer.clone());
+        if !self.is_authorized(params, stx, &signer, &caller)? {
+            return Ok(rejected_receipt(
+                params,
+                stx,
+                UNAUTHORIZED_CODE,
+                "signer is not authorized by sender".to_owned(),
+            ));
+        }
+
+        let mut context = self.get_context(
+            Some(stx.tx_hash.clone()),
+            Some(stx.raw.nonce.clone()),
+            &caller,
+            stx.raw.cycles_price,
+            stx.raw.cycles_limit,
+            params,
+            &stx.raw.request,
+        )?;
+        if rules.storage_deposit {
+            context = context.with_storage_deposit();
+        }
+        if rules.cycles_pricing {
+            context = context.with_cycles_pricing();
+        }
+
+        if self.tracer.borrow().is_target(&stx.tx_hash) {
+            self.tracer.borrow_mut().start(context.clone());
+        }
+        let exec_resp = self.catch_call(context.clone(), rules)?;
+
+        Ok(Receipt {
+            state_root:  MerkleRoot::from_empty(),
+            height:      context.get_current_height(),
+            tx_hash:     stx.tx_hash.clone(),
+            cycles_used: context.get_cycles_used(),
+            events:      context.get_events(),
+            response:    ReceiptResponse {
+                service_name: context.get_service_name().to_owned(),
+                method:       context.get_service_method().to_owned(),
+                response:     exec_resp,
+            },
+        })
+    }
+
+    // Take writes stashed since last step
+    fn take_step(&self, stage: ReplayStage, tx_hash: Option<Hash>, cycles_used: u64) -> ReplayStep {
+        let mut writes = vec![];
+        for (name, state) in self.states.iter() {
+            for (key, value) in state.borrow_mut().take_journal().into_iter() {
+                writes.push(StateWrite {
+                    service:    name.to_owned(),
+                    key:        Hex::from_bytes(key),
+                    value_hash: Hash::digest(value),
+                });
+            }
+        }
+        writes.sort();
+
+        ReplayStep {
+            stage,
+            tx_hash,
+            cycles_used,
+            writes,
+        }
+    }
+
+    /// Execute a block like `exec`, and record the writes of every block
+    /// hook and transaction. The result is not different from `exec`, the
+    /// state is committed as well.
+    pub fn replay(
+        &mut self,
+        params: &ExecutorParams,
+        txs: &[SignedTransaction],
+    ) -> ProtocolResult<ReplayLog> {
+        for state in self.states.values() {
+            state.borrow_mut().start_journal();
+        }
+
+        let mut steps = vec![];
+        let resp = self.execute(params, txs, Some(&mut steps));
+
+        for state in self.states.values() {
+            state.borrow_mut().stop_journal();
+        }
+
+        Ok(ReplayLog {
+            height: params.height,
+            parent_state_root: params.state_root.clone(),
+            state_root: resp?.state_root,
+            expected_state_root: None,
+            steps,
+        })
+    }
+
+    fn logs_bloom(&self, receipts: &[Receipt]) -> Bloom {
+        let mut bloom = Bloom::default();
+        for receipt in receipts {
+            for event in receipt.events.iter() {
+                let bytes =
+                    BytesMut::from((event.service.clone() + &event.data).as_bytes()).freeze();
+                let hash = Hash::digest(bytes).as_bytes();
+
+                let input = BloomInput::Raw(hash.as_ref());
+                bloom.accrue(input)
+            }
+        }
+
+        bloom
+    }
+}
+
+impl<S: 'static + Storage, C: 'static + StateCommitment, Mapping: 'static + ServiceMapping> Executor
+    for ServiceExecutor<S, C, Mapping>
+{
+    fn exec(
+        &mut self,
+        params: &ExecutorParams,
+        txs: &[SignedTransaction],
+    ) -> ProtocolResult<ExecutorResp> {
+        self.execute(params, txs, None)
+    }
+
     fn read(
         &self,
         params: &ExecutorParams,
@@ -395,10 +790,104 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
         panic::catch_unwind(AssertUnwindSafe(|| self.call(context, ExecType::Read)))
             .map_err(|e| ProtocolError::from(ExecutorError::QueryService(format!("{:?}", e))))
     }
+
+    fn verify_authorization(
+        &self,
+        params: &ExecutorParams,
+        stx: &SignedTransaction,
+    ) -> ProtocolResult<()> {
+        let signer = Address::from_pubkey_bytes(stx.pubkey.clone())?;
+        let sender = match &stx.raw.sender {
+            Some(sender) => sender.clone(),
+            None => return Ok(()),
+        };
+
+        if self.is_authorized(params, stx, &signer, &sender)? {
+            Ok(())
+        } else {
+            Err(ExecutorError::Unauthorized { sender, signer }.into())
+        }
+    }
+
+    fn get_state_proof(&self, service: &str, key: &Bytes) -> ProtocolResult<StateProof> {
+        let state = self
+            .states
+            .get(service)
+            .ok_or(ExecutorError::NotFoundService {
+                service: service.to_owned(),
+            })?;
+
+        let service_key = service.to_owned().encode_fixed()?;
+        let root_state = self.root_state.borrow();
+        let service_root =
+            root_state
+                .get(&service.to_owned())?
+                .ok_or(ExecutorError::NotFoundService {
+                    service: service.to_owned(),
+                })?;
+
+        Ok(StateProof {
+            scheme: C::SCHEME,
+            service_name: service.to_owned(),
+            key: key.clone(),
+            service_root,
+            service_proof: root_state.get_proof(&service_key)?,
+            key_proof: state.borrow().get_proof(key)?,
+        })
+    }
+
+    fn payload_limits(&self) -> ProtocolResult<PayloadLimits> {
+        let mut limits = PayloadLimits::default();
+        for name in self.states.keys() {
+            let sdk = self.get_sdk(name, None)?;
+            let service = self.service_mapping.get_service(name, sdk)?;
+            for (method, limit) in service.payload_limits_() {
+                limits.insert(name, &method, limit);
+            }
+        }
+        Ok(limits)
+    }
+
+    fn trace(
+        &mut self,
+        params: &ExecutorParams,
+        txs: &[SignedTransaction],
+        tx_hash: &Hash,
+    ) -> ProtocolResult<TxTrace> {
+        let index = txs
+            .iter()
+            .position(|stx| &stx.tx_hash == tx_hash)
+            .ok_or_else(|| ExecutorError::NotFoundTx {
+                tx_hash: tx_hash.clone(),
+            })?;
+
+        self.tracer.borrow_mut().set_target(tx_hash.clone());
+        let receipt = self.hook(HookType::Before, params).and_then(|_| {
+            let rules = self.tx_rules(params.height)?;
+            let mut receipt = None;
+            for stx in txs[..=index].iter() {
+                receipt = Some(self.exec_tx(params, stx, rules)?);
+            }
+            Ok(receipt.expect("traced transaction is executed"))
+        });
+        let steps = self.tracer.borrow_mut().finish();
+        let receipt = receipt?;
+
+        Ok(TxTrace {
+            tx_hash: receipt.tx_hash,
+            height: receipt.height,
+            cycles_used: receipt.cycles_used,
+            service_name: receipt.response.service_name,
+            method: receipt.response.method,
+            code: receipt.response.response.code,
+            error_message: receipt.response.response.error_message,
+            steps,
+        })
+    }
 }
 
-impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMapping> Dispatcher
-    for ServiceExecutor<S, DB, Mapping>
+impl<S: 'static + Storage, C: 'static + StateCommitment, Mapping: 'static + ServiceMapping>
+    Dispatcher for ServiceExecutor<S, C, Mapping>
 {
     fn read(&self, context: ServiceContext) -> ServiceResponse<String> {
         self.call(context, ExecType::Read)
@@ -409,6 +898,39 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
     }
 }
 
+// Receipt of a transaction rejected before its service call, nothing is
+// charged
+fn rejected_receipt(
+    params: &ExecutorParams,
+    stx: &SignedTransaction,
+    code: u64,
+    error_message: String,
+) -> Receipt {
+    Receipt {
+        state_root:  MerkleRoot::from_empty(),
+        height:      params.height,
+        tx_hash:     stx.tx_hash.clone(),
+        cycles_used: 0,
+        events:      vec![],
+        response:    ReceiptResponse {
+            service_name: stx.raw.request.service_name.clone(),
+            method:       stx.raw.request.method.clone(),
+            response:     ServiceResponse::<String>::from_error(code, error_message),
+        },
+    }
+}
+
+fn out_of_cycles() -> ServiceResponse<String> {
+    ServiceResponse::<String>::from_error(OUT_OF_CYCLES_CODE, "out of cycles".to_owned())
+}
+
+fn insufficient_fee() -> ServiceResponse<String> {
+    ServiceResponse::<String>::from_error(
+        INSUFFICIENT_FEE_CODE,
+        "insufficient balance for fee".to_owned(),
+    )
+}
+
 #[derive(Debug, Display, From)]
 pub enum ExecutorError {
     #[display(fmt = "service {:?} was not found", service)]
@@ -424,6 +946,19 @@ pub enum ExecutorError {
     QueryService(String),
     #[display(fmt = "Call service failed: {:?}", _0)]
     CallService(String),
+
+    #[display(fmt = "{:?} is not authorized to act for {:?}", signer, sender)]
+    Unauthorized { sender: Address, signer: Address },
+
+    #[display(fmt = "transaction {:?} is not in the block", tx_hash)]
+    NotFoundTx { tx_hash: Hash },
+
+    #[display(
+        fmt = "feature {:?} activated at {} is not supported, upgrade the node",
+        name,
+        height
+    )]
+    UnsupportedFeature { name: String, height: u64 },
 }
 
 impl std::error::Error for ExecutorError {}
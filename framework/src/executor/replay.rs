@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+use protocol::types::{Hash, Hex, MerkleRoot};
+
+/// Execution trace of a block, nodes computing different state roots for
+/// the same block can compare their logs to find the diverging step.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReplayLog {
+    pub height:              u64,
+    pub parent_state_root:   MerkleRoot,
+    pub state_root:          MerkleRoot,
+    // State root of the block recorded by the node, it's unknown to executor
+    #[serde(default)]
+    pub expected_state_root: Option<MerkleRoot>,
+    pub steps:               Vec<ReplayStep>,
+}
+
+/// Writes stashed by a block hook or a transaction. Writes of reverted
+/// calls are not included, since they don't reach the state.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReplayStep {
+    pub stage:       ReplayStage,
+    pub tx_hash:     Option<Hash>,
+    pub cycles_used: u64,
+    // Sorted by service and key
+    pub writes:      Vec<StateWrite>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ReplayStage {
+    HookBefore,
+    Transaction,
+    HookAfter,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StateWrite {
+    pub service:    String,
+    // Fixed encoded key in the service state
+    pub key:        Hex,
+    pub value_hash: Hash,
+}
+
+/// First step at which two replay logs of a block differ.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReplayDivergence {
+    pub height:      u64,
+    // Index of the step, `None` if all steps are the same but the state
+    // roots, which means the parent states differ
+    pub step:        Option<usize>,
+    pub tx_hash:     Option<Hash>,
+    pub local_step:  Option<ReplayStep>,
+    pub remote_step: Option<ReplayStep>,
+    // Writes that are only in one of the steps
+    pub local_only:  Vec<StateWrite>,
+    pub remote_only: Vec<StateWrite>,
+    pub local_root:  MerkleRoot,
+    pub remote_root: MerkleRoot,
+}
+
+/// Compare two replay logs of the same block, return `None` if they are
+/// the same.
+pub fn diff_replay_logs(local: &ReplayLog, remote: &ReplayLog) -> Option<ReplayDivergence> {
+    let step_count = local.steps.len().max(remote.steps.len());
+    let diverged_step = (0..step_count).find(|i| local.steps.get(*i) != remote.steps.get(*i));
+
+    if diverged_step.is_none()
+        && local.state_root == remote.state_root
+        && local.parent_state_root == remote.parent_state_root
+    {
+        return None;
+    }
+
+    let local_step = diverged_step.and_then(|i| local.steps.get(i).cloned());
+    let remote_step = diverged_step.and_then(|i| remote.steps.get(i).cloned());
+    let only = |a: &Option<ReplayStep>, b: &Option<ReplayStep>| -> Vec<StateWrite> {
+        let empty = vec![];
+        let b_writes = b.as_ref().map(|s| &s.writes).unwrap_or(&empty);
+        a.as_ref()
+            .map(|s| {
+                s.writes
+                    .iter()
+                    .filter(|w| !b_writes.contains(w))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    Some(ReplayDivergence {
+        height: local.height,
+        step: diverged_step,
+        tx_hash: local_step
+            .as_ref()
+            .or_else(|| remote_step.as_ref())
+            .and_then(|s| s.tx_hash.clone()),
+        local_only: only(&local_step, &remote_step),
+        remote_only: only(&remote_step, &local_step),
+        local_step,
+        remote_step,
+        local_root: local.state_root.clone(),
+        remote_root: remote.state_root.clone(),
+    })
+}
@@ -10,18 +10,23 @@ use bytes::{Bytes, BytesMut};
 use cita_trie::MemoryDB;
 use test::Bencher;
 
-use asset::types::{Asset, GetBalanceResponse};
+use account::AccountService;
+use asset::types::{Asset, GetBalanceResponse, GetClaimableFeeResponse};
 use asset::AssetService;
 use metadata::MetadataService;
-use protocol::traits::{Executor, ExecutorParams, Service, ServiceMapping, ServiceSDK, Storage};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{
+    ErrorClass, Executor, ExecutorParams, ExecutorResp, Service, ServiceMapping, ServiceSDK,
+    Storage,
+};
 use protocol::types::{
-    Address, Block, Genesis, Hash, Proof, RawTransaction, Receipt, SignedTransaction,
-    TransactionRequest,
+    Address, Block, Genesis, Hash, MerkleRoot, Proof, RawTransaction, Receipt, SignedTransaction,
+    TraceStepKind, TransactionRequest,
 };
 use protocol::ProtocolResult;
 
-use crate::executor::ServiceExecutor;
-use test_service::TestService;
+use crate::executor::{diff_replay_logs, MPTServiceExecutor, ReplayStage, ServiceExecutor};
+use test_service::{TestReadResponse, TestService};
 
 #[test]
 fn test_create_genesis() {
@@ -30,7 +35,7 @@ fn test_create_genesis() {
 
     let db = Arc::new(MemoryDB::new(false));
 
-    let root = ServiceExecutor::create_genesis(
+    let root = MPTServiceExecutor::create_genesis(
         genesis.services,
         Arc::clone(&db),
         Arc::new(MockStorage {}),
@@ -38,7 +43,7 @@ fn test_create_genesis() {
     )
     .unwrap();
 
-    let executor = ServiceExecutor::with_root(
+    let executor = MPTServiceExecutor::with_root(
         root.clone(),
         Arc::clone(&db),
         Arc::new(MockStorage {}),
@@ -46,10 +51,13 @@ fn test_create_genesis() {
     )
     .unwrap();
     let params = ExecutorParams {
-        state_root:   root,
-        height:       1,
-        timestamp:    0,
-        cycles_limit: std::u64::MAX,
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
     };
     let caller = Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap();
     let request = TransactionRequest {
@@ -72,7 +80,7 @@ fn test_exec() {
 
     let db = Arc::new(MemoryDB::new(false));
 
-    let root = ServiceExecutor::create_genesis(
+    let root = MPTServiceExecutor::create_genesis(
         genesis.services,
         Arc::clone(&db),
         Arc::new(MockStorage {}),
@@ -80,7 +88,7 @@ fn test_exec() {
     )
     .unwrap();
 
-    let mut executor = ServiceExecutor::with_root(
+    let mut executor = MPTServiceExecutor::with_root(
         root.clone(),
         Arc::clone(&db),
         Arc::new(MockStorage {}),
@@ -89,10 +97,13 @@ fn test_exec() {
     .unwrap();
 
     let params = ExecutorParams {
-        state_root:   root,
-        height:       1,
-        timestamp:    0,
-        cycles_limit: std::u64::MAX,
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
     };
 
     let stx = mock_signed_tx();
@@ -105,6 +116,153 @@ fn test_exec() {
     assert_eq!(asset.name, "MutaToken2");
     assert_eq!(asset.symbol, "MT2");
     assert_eq!(asset.supply, 320_000_011);
+
+    // Symbol of the genesis asset is indexed before transactions of the block
+    let mut stx = mock_signed_tx();
+    stx.raw.request.payload = r#"{ "name": "MutaToken3", "symbol": "MT", "supply": 1 }"#.to_owned();
+    let txs = vec![stx];
+    let executor_resp = executor.exec(&params, &txs).unwrap();
+    assert_eq!(executor_resp.receipts[0].response.response.code, 109);
+}
+
+#[test]
+fn test_replay() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:      root.clone(),
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+    let txs = vec![mock_signed_tx()];
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+    let executor_resp = executor.exec(&params, &txs).unwrap();
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+    let log = executor.replay(&params, &txs).unwrap();
+
+    assert_eq!(log.parent_state_root, root);
+    assert_eq!(log.state_root, executor_resp.state_root);
+    assert_eq!(log.steps.len(), 3);
+    assert_eq!(log.steps[1].stage, ReplayStage::Transaction);
+    assert_eq!(log.steps[1].tx_hash, Some(txs[0].tx_hash.clone()));
+    assert_eq!(
+        log.steps[1].cycles_used,
+        executor_resp.receipts[0].cycles_used
+    );
+    assert!(!log.steps[1].writes.is_empty());
+    assert!(diff_replay_logs(&log, &log).is_none());
+
+    let mut diverged = log.clone();
+    diverged.steps[1].writes.pop();
+    diverged.state_root = Hash::from_empty();
+    let divergence = diff_replay_logs(&log, &diverged).unwrap();
+    assert_eq!(divergence.step, Some(1));
+    assert_eq!(divergence.tx_hash, Some(txs[0].tx_hash.clone()));
+    assert_eq!(divergence.local_only.len(), 1);
+    assert!(divergence.remote_only.is_empty());
+}
+
+#[test]
+fn test_trace() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:      root.clone(),
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+    let txs = vec![mock_signed_tx()];
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+    let executor_resp = executor.exec(&params, &txs).unwrap();
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+    let trace = executor.trace(&params, &txs, &txs[0].tx_hash).unwrap();
+
+    assert!(!trace.is_error());
+    assert_eq!(trace.cycles_used, executor_resp.receipts[0].cycles_used);
+
+    let first = trace.steps.first().unwrap();
+    assert_eq!(first.kind, TraceStepKind::Call);
+    assert_eq!(first.depth, 0);
+    assert_eq!(first.method, "create_asset");
+    // Storage access of tx hooks follows the return
+    let ret = trace
+        .steps
+        .iter()
+        .find(|step| step.kind == TraceStepKind::Return)
+        .unwrap();
+    assert_eq!(ret.depth, 0);
+    assert_eq!(ret.method, "create_asset");
+    assert_eq!(ret.code, Some(0));
+
+    assert!(trace
+        .steps
+        .iter()
+        .any(|step| step.kind == TraceStepKind::Write && step.service == "asset"));
+    assert!(trace
+        .steps
+        .iter()
+        .any(|step| step.kind == TraceStepKind::Event));
+
+    let not_found = Hash::digest(Bytes::from("not found"));
+    assert!(executor.trace(&params, &txs, &not_found).is_err());
 }
 
 #[test]
@@ -114,7 +272,7 @@ fn test_tx_hook() {
 
     let db = Arc::new(MemoryDB::new(false));
 
-    let root = ServiceExecutor::create_genesis(
+    let root = MPTServiceExecutor::create_genesis(
         genesis.services,
         Arc::clone(&db),
         Arc::new(MockStorage {}),
@@ -122,7 +280,7 @@ fn test_tx_hook() {
     )
     .unwrap();
 
-    let mut executor = ServiceExecutor::with_root(
+    let mut executor = MPTServiceExecutor::with_root(
         root.clone(),
         Arc::clone(&db),
         Arc::new(MockStorage {}),
@@ -131,10 +289,13 @@ fn test_tx_hook() {
     .unwrap();
 
     let params = ExecutorParams {
-        state_root:   root,
-        height:       1,
-        timestamp:    0,
-        cycles_limit: std::u64::MAX,
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
     };
 
     // no tx hook
@@ -185,6 +346,1016 @@ fn test_tx_hook() {
     assert_eq!(&receipt.events[1].data, "test_tx_hook_after invoked");
 }
 
+#[test]
+fn test_distribute_fee() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let proposer = Address::from_hex("0x666cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let treasury = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let params = ExecutorParams {
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        proposer.clone(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+
+    let txs = vec![mock_signed_tx()];
+    let executor_resp = executor.exec(&params, &txs).unwrap();
+    let fee = executor_resp.receipts[0].cycles_used;
+
+    let caller = Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap();
+    let claimable_fee = |user: &Address| {
+        let request = TransactionRequest {
+            service_name: "asset".to_owned(),
+            method:       "get_claimable_fee".to_owned(),
+            payload:      format!(r#"{{"user": "{}"}}"#, user.as_hex()),
+        };
+        let res = executor.read(&params, &caller, 1, &request).unwrap();
+        let resp: GetClaimableFeeResponse = serde_json::from_str(&res.succeed_data).unwrap();
+        resp.value
+    };
+
+    // 8:2 in genesis metadata
+    let proposer_fee = fee * 8 / 10;
+    assert_eq!(claimable_fee(&proposer), proposer_fee);
+    assert_eq!(claimable_fee(&treasury), fee - proposer_fee);
+
+    let request = TransactionRequest {
+        service_name: "asset".to_owned(),
+        method:       "get_balance".to_owned(),
+        payload:
+            r#"{"asset_id": "0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c", "user": "0xf8389d774afdad8755ef8e629e5a154fddc6325a"}"#
+                .to_owned(),
+    };
+    let res = executor.read(&params, &caller, 1, &request).unwrap();
+    let resp: GetBalanceResponse = serde_json::from_str(&res.succeed_data).unwrap();
+    assert_eq!(resp.balance, 320_000_011 - fee);
+}
+
+#[test]
+fn test_tx_sender() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+
+    let account = Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap();
+    let session_pubkey = Bytes::from(
+        hex::decode("031313016e9670deb49779c1b0c646d6a25a545712658f9781995f623bcd0d0b3d").unwrap(),
+    );
+    let session_key = Address::from_pubkey_bytes(session_pubkey.clone()).unwrap();
+
+    let mut add_key_tx = mock_signed_tx();
+    add_key_tx.raw.request = TransactionRequest {
+        service_name: "account".to_owned(),
+        method:       "add_session_key".to_owned(),
+        payload:      format!(
+            r#"{{"key": "{}", "expire_height": 10}}"#,
+            session_key.as_hex()
+        ),
+    };
+
+    let mut session_tx = mock_signed_tx();
+    session_tx.pubkey = session_pubkey.clone();
+    session_tx.raw.sender = Some(account.clone());
+
+    let mut unauthorized_tx = mock_signed_tx();
+    unauthorized_tx.pubkey = session_pubkey;
+    unauthorized_tx.raw.sender =
+        Some(Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap());
+
+    assert!(executor
+        .verify_authorization(&params, &unauthorized_tx)
+        .is_err());
+
+    let txs = vec![add_key_tx, session_tx, unauthorized_tx];
+    let executor_resp = executor.exec(&params, &txs).unwrap();
+
+    assert_eq!(executor_resp.receipts[0].response.response.code, 0);
+
+    let receipt = &executor_resp.receipts[1];
+    assert_eq!(receipt.response.response.code, 0);
+    let asset: Asset = serde_json::from_str(&receipt.response.response.succeed_data).unwrap();
+    assert_eq!(asset.issuer, account);
+
+    let receipt = &executor_resp.receipts[2];
+    assert_eq!(receipt.response.response.code, 4);
+    assert_eq!(receipt.cycles_used, 0);
+}
+
+#[test]
+fn test_state_proof() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    // Asset id indexed by symbol at genesis
+    let key = "symbol_MT".to_owned().encode_fixed().unwrap();
+    let proof = executor.get_state_proof("asset", &key).unwrap();
+    let value = proof.verify(&root).unwrap().unwrap();
+    assert_eq!(
+        Hash::decode_fixed(value).unwrap(),
+        Hash::from_hex("0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c")
+            .unwrap()
+    );
+
+    let key = "symbol_NONE".to_owned().encode_fixed().unwrap();
+    let proof = executor.get_state_proof("asset", &key).unwrap();
+    assert_eq!(proof.verify(&root).unwrap(), None);
+
+    let mut forged = executor.get_state_proof("asset", &key).unwrap();
+    forged.service_root = Hash::digest(Bytes::from("forged"));
+    assert!(forged.verify(&root).is_err());
+}
+
+#[test]
+fn test_payload_limits() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let executor = MPTServiceExecutor::with_root(
+        root,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let limits = executor.payload_limits().unwrap();
+    assert_eq!(limits.get("test", "test_write"), Some(1024));
+    assert_eq!(limits.get("test", "test_write_failed"), None);
+    assert_eq!(limits.get("asset", "transfer"), None);
+}
+
+#[test]
+fn test_revert_tx_out_of_cycles() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let mut genesis: Genesis = toml::from_str(toml_str).unwrap();
+    for service in genesis.services.iter_mut() {
+        service.payload = service.payload.replace(
+            r#""treasury_fee_ratio": 2,"#,
+            r#""treasury_fee_ratio": 2, "chain_spec": [{"name": "cycles_pricing", "height": 1}],"#,
+        );
+    }
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+
+    // Only enough for the base cycles of test_write, not for the storage write
+    let mut stx = mock_signed_tx();
+    stx.raw.cycles_limit = 21_000;
+    stx.raw.request.service_name = "test".to_owned();
+    stx.raw.request.method = "test_write".to_owned();
+    stx.raw.request.payload = r#"{
+        "key": "foo",
+        "value": "bar",
+        "extra": ""
+    }"#
+    .to_owned();
+    let txs = vec![stx];
+    let executor_resp = executor.exec(&params, &txs).unwrap();
+    let receipt = &executor_resp.receipts[0];
+    assert_eq!(receipt.response.response.code, 3);
+    assert_eq!(receipt.cycles_used, 21_000);
+
+    let caller = Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap();
+    let request = TransactionRequest {
+        service_name: "test".to_owned(),
+        method:       "test_read".to_owned(),
+        payload:      r#"{"key": "foo"}"#.to_owned(),
+    };
+    let res = executor.read(&params, &caller, 1, &request).unwrap();
+    let resp: TestReadResponse = serde_json::from_str(&res.succeed_data).unwrap();
+    assert_eq!(resp.value, "");
+}
+
+#[test]
+fn test_legacy_tx_out_of_cycles() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+
+    // Before tx_cycles activates, a call out of cycles runs hooks after it
+    // and is stashed, as it always did
+    let mut stx = mock_signed_tx();
+    stx.raw.cycles_limit = 20_000;
+    stx.raw.request.service_name = "test".to_owned();
+    stx.raw.request.method = "test_write".to_owned();
+    stx.raw.request.payload = r#"{
+        "key": "foo",
+        "value": "bar",
+        "extra": "test_hook_before; test_hook_after"
+    }"#
+    .to_owned();
+    let txs = vec![stx];
+    let executor_resp = executor.exec(&params, &txs).unwrap();
+    let receipt = &executor_resp.receipts[0];
+    assert_eq!(receipt.response.response.code, 3);
+    assert_eq!(receipt.cycles_used, 0);
+    assert_eq!(receipt.events.len(), 2);
+    assert_eq!(&receipt.events[0].data, "test_tx_hook_before invoked");
+    assert_eq!(&receipt.events[1].data, "test_tx_hook_after invoked");
+
+    let caller = Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap();
+    let request = TransactionRequest {
+        service_name: "test".to_owned(),
+        method:       "test_read".to_owned(),
+        payload:      r#"{"key": "foo"}"#.to_owned(),
+    };
+    let params = ExecutorParams {
+        state_root: executor_resp.state_root.clone(),
+        ..params
+    };
+    let res = executor.read(&params, &caller, 1, &request).unwrap();
+    let resp: TestReadResponse = serde_json::from_str(&res.succeed_data).unwrap();
+    assert_eq!(resp.value, "");
+}
+
+#[test]
+fn test_fail_tx_of_insufficient_fee() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+
+    let issuer = Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap();
+    let receiver = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let asset_id = "0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c";
+
+    // Caller has no balance to pay for it
+    let mut unfunded_tx = mock_signed_tx();
+    unfunded_tx.pubkey = Bytes::from(
+        hex::decode("031313016e9670deb49779c1b0c646d6a25a545712658f9781995f623bcd0d0b3d").unwrap(),
+    );
+
+    // Caller transfers away the balance paying for it
+    let mut transfer_tx = mock_signed_tx();
+    transfer_tx.raw.request = TransactionRequest {
+        service_name: "asset".to_owned(),
+        method:       "transfer".to_owned(),
+        payload:      format!(
+            r#"{{"asset_id": "{}", "to": "{}", "value": 320000011}}"#,
+            asset_id,
+            receiver.as_hex()
+        ),
+    };
+
+    // Symbol of the reverted asset is still free
+    let txs = vec![unfunded_tx, transfer_tx, mock_signed_tx()];
+    let executor_resp = executor.exec(&params, &txs).unwrap();
+    let receipts = &executor_resp.receipts;
+    for receipt in receipts[..2].iter() {
+        assert_eq!(
+            receipt.response.response.error_class(),
+            Some(ErrorClass::InsufficientFee)
+        );
+        assert!(receipt.cycles_used > 0);
+    }
+    assert_eq!(receipts[2].response.response.code, 0);
+
+    let balance = |user: &Address| {
+        let request = TransactionRequest {
+            service_name: "asset".to_owned(),
+            method:       "get_balance".to_owned(),
+            payload:      format!(
+                r#"{{"asset_id": "{}", "user": "{}"}}"#,
+                asset_id,
+                user.as_hex()
+            ),
+        };
+        let res = executor.read(&params, &issuer, 1, &request).unwrap();
+        let resp: GetBalanceResponse = serde_json::from_str(&res.succeed_data).unwrap();
+        resp.balance
+    };
+
+    // Transfer is reverted, fee of both transactions of the issuer is paid
+    assert_eq!(balance(&receiver), 0);
+    assert_eq!(
+        balance(&issuer),
+        320_000_011 - receipts[1].cycles_used - receipts[2].cycles_used
+    );
+}
+
+#[test]
+fn test_error_class() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+
+    let payload = r#"{
+        "key": "foo",
+        "value": "bar",
+        "extra": ""
+    }"#;
+    let tx = |service: &str, method: &str, payload: &str| {
+        let mut stx = mock_signed_tx();
+        stx.raw.request.service_name = service.to_owned();
+        stx.raw.request.method = method.to_owned();
+        stx.raw.request.payload = payload.to_owned();
+        stx
+    };
+
+    let mut out_of_cycles = tx("test", "test_write", payload);
+    out_of_cycles.raw.cycles_limit = 20_000;
+    let txs = vec![
+        mock_signed_tx(),
+        tx("unknown", "test_write", payload),
+        tx("test", "unknown", payload),
+        tx("test", "test_write", "foo"),
+        tx("test", "test_write_failed", payload),
+        out_of_cycles,
+    ];
+    let executor_resp = executor.exec(&params, &txs).unwrap();
+    let classes = executor_resp
+        .receipts
+        .iter()
+        .map(|receipt| receipt.response.response.error_class())
+        .collect::<Vec<_>>();
+
+    assert_eq!(classes, vec![
+        None,
+        Some(ErrorClass::ServiceNotFound),
+        Some(ErrorClass::MethodNotFound),
+        Some(ErrorClass::InvalidPayload),
+        Some(ErrorClass::Reverted),
+        Some(ErrorClass::OutOfCycles),
+    ]);
+
+    // Messages are kept for clients before error classes
+    let resp = &executor_resp.receipts[4].response.response;
+    assert_eq!(resp.code, 101);
+    assert_eq!(resp.error_message, "write failed");
+}
+
+#[test]
+fn test_charge_cycles_used() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+    let genesis_of = |fields: &str| {
+        let mut genesis = genesis.clone();
+        for service in genesis.services.iter_mut() {
+            service.payload = service.payload.replace(
+                r#""treasury_fee_ratio": 2,"#,
+                &format!(r#""treasury_fee_ratio": 2, {}"#, fields),
+            );
+        }
+        genesis
+    };
+    let activation = r#""chain_spec": [
+        {"name": "tx_cycles", "height": 1},
+        {"name": "cycles_pricing", "height": 1}
+    ],"#;
+    let no_base_genesis = genesis_of(activation);
+    let base_genesis = genesis_of(&format!(r#""tx_base_cycles": 5000, {}"#, activation));
+    let legacy_genesis = genesis_of(
+        r#""tx_base_cycles": 5000, "chain_spec": [{"name": "cycles_pricing", "height": 1}],"#,
+    );
+
+    let executor_of = |genesis: Genesis| {
+        let db = Arc::new(MemoryDB::new(false));
+        let root = MPTServiceExecutor::create_genesis(
+            genesis.services,
+            Arc::clone(&db),
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+        let executor = MPTServiceExecutor::with_root(
+            root.clone(),
+            db,
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+        let params = ExecutorParams {
+            state_root:      root,
+            height:          1,
+            timestamp:       0,
+            cycles_limit:    std::u64::MAX,
+            proposer:        Address::default(),
+            vote_extensions: vec![],
+            extra_data:      Bytes::new(),
+        };
+        (executor, params)
+    };
+    let read = |executor: &ServiceExecutor<_, _, _>, params: &ExecutorParams, request| {
+        let caller = Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap();
+        executor
+            .read(params, &caller, 1, &request)
+            .unwrap()
+            .succeed_data
+    };
+    let balance = |executor: &ServiceExecutor<_, _, _>, params: &ExecutorParams| {
+        let request = TransactionRequest {
+            service_name: "asset".to_owned(),
+            method:       "get_balance".to_owned(),
+            payload:
+                r#"{"asset_id": "0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c", "user": "0xf8389d774afdad8755ef8e629e5a154fddc6325a"}"#
+                    .to_owned(),
+        };
+        let resp: GetBalanceResponse =
+            serde_json::from_str(&read(executor, params, request)).unwrap();
+        resp.balance
+    };
+    let value_of = |executor: &ServiceExecutor<_, _, _>, params: &ExecutorParams, key: &str| {
+        let request = TransactionRequest {
+            service_name: "test".to_owned(),
+            method:       "test_read".to_owned(),
+            payload:      format!(r#"{{"key": "{}"}}"#, key),
+        };
+        let resp: TestReadResponse =
+            serde_json::from_str(&read(executor, params, request)).unwrap();
+        resp.value
+    };
+    let write_tx = |method: &str, key: &str, cycles_limit: u64| {
+        let mut stx = mock_signed_tx();
+        stx.raw.cycles_limit = cycles_limit;
+        stx.raw.request.service_name = "test".to_owned();
+        stx.raw.request.method = method.to_owned();
+        stx.raw.request.payload = format!(r#"{{"key": "{}", "value": "bar", "extra": ""}}"#, key);
+        stx
+    };
+
+    let txs = vec![
+        write_tx("test_write", "foo", std::u64::MAX),
+        write_tx("test_write_failed", "baz", std::u64::MAX),
+        write_tx("test_write", "qux", 3000),
+    ];
+    let (mut executor, params) = executor_of(no_base_genesis);
+    let no_base_resp = executor.exec(&params, &txs).unwrap();
+    let (mut executor, params) = executor_of(base_genesis);
+    let supply = balance(&executor, &params);
+    let resp = executor.exec(&params, &txs).unwrap();
+    let params = ExecutorParams {
+        state_root: resp.state_root.clone(),
+        ..params
+    };
+
+    // Base cycles are charged besides consumed cycles
+    let receipt = &resp.receipts[0];
+    assert_eq!(receipt.response.response.code, 0);
+    assert_eq!(
+        receipt.cycles_used,
+        no_base_resp.receipts[0].cycles_used + 5000
+    );
+    assert_eq!(value_of(&executor, &params, "foo"), "bar");
+
+    // Failed call is reverted, cycles it consumed are still charged
+    let receipt = &resp.receipts[1];
+    assert_eq!(receipt.response.response.code, 101);
+    assert_eq!(
+        receipt.cycles_used,
+        no_base_resp.receipts[1].cycles_used + 5000
+    );
+    assert!(receipt.cycles_used > 5000 + 21_000);
+    assert_eq!(value_of(&executor, &params, "baz"), "");
+
+    // Out of cycles uses up the limit
+    let receipt = &resp.receipts[2];
+    assert_eq!(receipt.response.response.code, 3);
+    assert_eq!(receipt.cycles_used, 3000);
+    assert_eq!(value_of(&executor, &params, "qux"), "");
+
+    // Caller pays cycles used, the rest is refunded
+    let cycles_used: u64 = resp.receipts.iter().map(|r| r.cycles_used).sum();
+    assert_eq!(resp.all_cycles_used, cycles_used);
+    assert_eq!(balance(&executor, &params), supply - cycles_used);
+
+    // Before the feature activates, base cycles are not charged and writes of
+    // failed calls are kept
+    let (mut executor, params) = executor_of(legacy_genesis);
+    let resp = executor.exec(&params, &txs).unwrap();
+    let params = ExecutorParams {
+        state_root: resp.state_root.clone(),
+        ..params
+    };
+    assert_eq!(
+        resp.receipts[0].cycles_used,
+        no_base_resp.receipts[0].cycles_used
+    );
+    assert_eq!(resp.receipts[1].response.response.code, 101);
+    assert_eq!(value_of(&executor, &params, "baz"), "bar");
+    assert_eq!(resp.receipts[2].response.response.code, 3);
+    assert_eq!(value_of(&executor, &params, "qux"), "");
+
+    // Storage access is free before the pricing activates
+    let (mut executor, params) = executor_of(genesis_of(
+        r#""chain_spec": [{"name": "tx_cycles", "height": 1}],"#,
+    ));
+    let resp = executor.exec(&params, &txs).unwrap();
+    assert_eq!(resp.receipts[0].cycles_used, 21_000);
+}
+
+#[test]
+fn test_storage_deposit_and_rent() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+    let genesis_of = |rent_per_byte: u64, activation: u64| {
+        let mut genesis = genesis.clone();
+        for service in genesis.services.iter_mut() {
+            service.payload = service.payload.replace(
+                r#""treasury_fee_ratio": 2,"#,
+                &format!(
+                    r#""treasury_fee_ratio": 2, "storage_deposit_per_byte": 10, "rent_epoch": 2, "rent_per_byte": {}, "chain_spec": [{{"name": "storage_deposit", "height": {}}}],"#,
+                    rent_per_byte, activation
+                ),
+            );
+        }
+        genesis
+    };
+
+    let executor_of = |genesis: Genesis| {
+        let db = Arc::new(MemoryDB::new(false));
+        let root = MPTServiceExecutor::create_genesis(
+            genesis.services,
+            Arc::clone(&db),
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+        let executor = MPTServiceExecutor::with_root(
+            root.clone(),
+            db,
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+        let params = ExecutorParams {
+            state_root:      root,
+            height:          1,
+            timestamp:       0,
+            cycles_limit:    std::u64::MAX,
+            proposer:        Address::default(),
+            vote_extensions: vec![],
+            extra_data:      Bytes::new(),
+        };
+        (executor, params)
+    };
+    let read = |executor: &ServiceExecutor<_, _, _>, params: &ExecutorParams, request| {
+        let caller = Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap();
+        executor
+            .read(params, &caller, 1, &request)
+            .unwrap()
+            .succeed_data
+    };
+    let balance = |executor: &ServiceExecutor<_, _, _>, params: &ExecutorParams| {
+        let request = TransactionRequest {
+            service_name: "asset".to_owned(),
+            method:       "get_balance".to_owned(),
+            payload:
+                r#"{"asset_id": "0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c", "user": "0xf8389d774afdad8755ef8e629e5a154fddc6325a"}"#
+                    .to_owned(),
+        };
+        let resp: GetBalanceResponse =
+            serde_json::from_str(&read(executor, params, request)).unwrap();
+        resp.balance
+    };
+    let value_of = |executor: &ServiceExecutor<_, _, _>, params: &ExecutorParams| {
+        let request = TransactionRequest {
+            service_name: "test".to_owned(),
+            method:       "test_read".to_owned(),
+            payload:      r#"{"key": "foo"}"#.to_owned(),
+        };
+        let resp: TestReadResponse =
+            serde_json::from_str(&read(executor, params, request)).unwrap();
+        resp.value
+    };
+    let write_tx = |value: &str| {
+        let mut stx = mock_signed_tx();
+        stx.raw.request.service_name = "test".to_owned();
+        stx.raw.request.method = "test_write".to_owned();
+        stx.raw.request.payload = format!(r#"{{"key": "foo", "value": "{}", "extra": ""}}"#, value);
+        stx
+    };
+    let next_block = |params: &ExecutorParams, resp: &ExecutorResp| ExecutorParams {
+        state_root: resp.state_root.clone(),
+        height: params.height + 1,
+        ..params.clone()
+    };
+
+    // Nothing is deposited before the activation
+    let (mut executor, params) = executor_of(genesis_of(1, 2));
+    let supply = balance(&executor, &params);
+    let resp = executor.exec(&params, &[write_tx("bar")]).unwrap();
+    let params = next_block(&params, &resp);
+    assert_eq!(value_of(&executor, &params), "bar");
+    assert_eq!(balance(&executor, &params), supply - resp.all_cycles_used);
+
+    // Bytes of the key and value are deposited by the caller
+    let (mut executor, params) = executor_of(genesis_of(1, 1));
+    let supply = balance(&executor, &params);
+    let resp = executor.exec(&params, &[write_tx("bar")]).unwrap();
+    let params = next_block(&params, &resp);
+    let deposit = 10 * ("foo".len() + "bar".len()) as u64;
+    assert_eq!(value_of(&executor, &params), "bar");
+    assert_eq!(
+        balance(&executor, &params),
+        supply - resp.all_cycles_used - deposit
+    );
+
+    // Rent is charged at the end of the epoch
+    let before = balance(&executor, &params);
+    let resp = executor.exec(&params, &[]).unwrap();
+    let params = next_block(&params, &resp);
+    let rent = ("foo".len() + "bar".len()) as u64;
+    assert_eq!(balance(&executor, &params), before - rent);
+
+    // Deposit is refunded on deletion
+    let before = balance(&executor, &params);
+    let resp = executor.exec(&params, &[write_tx("")]).unwrap();
+    let params = next_block(&params, &resp);
+    assert_eq!(value_of(&executor, &params), "");
+    assert_eq!(
+        balance(&executor, &params),
+        before - resp.all_cycles_used + deposit
+    );
+
+    // Entries of owners can't cover rent are reclaimed
+    let (mut executor, params) = executor_of(genesis_of(supply, 1));
+    let resp = executor.exec(&params, &[write_tx("bar")]).unwrap();
+    let params = next_block(&params, &resp);
+    assert_eq!(value_of(&executor, &params), "bar");
+
+    let resp = executor.exec(&params, &[]).unwrap();
+    let params = next_block(&params, &resp);
+    assert_eq!(value_of(&executor, &params), "");
+    assert_eq!(balance(&executor, &params), 0);
+}
+
+#[test]
+fn test_emergency_halt() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let mut genesis: Genesis = toml::from_str(toml_str).unwrap();
+    for service in genesis.services.iter_mut() {
+        service.payload = service.payload.replace(
+            r#""treasury_fee_ratio": 2,"#,
+            r#""treasury_fee_ratio": 2, "halt_height": 1,"#,
+        );
+    }
+
+    let db = Arc::new(MemoryDB::new(false));
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let exec = |root: MerkleRoot, height: u64, txs: Vec<SignedTransaction>| {
+        let mut executor = MPTServiceExecutor::with_root(
+            root.clone(),
+            Arc::clone(&db),
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+        let params = ExecutorParams {
+            state_root: root,
+            height,
+            timestamp: 0,
+            cycles_limit: std::u64::MAX,
+            proposer: Address::default(),
+            vote_extensions: vec![],
+            extra_data: Bytes::new(),
+        };
+        executor.exec(&params, &txs).unwrap()
+    };
+    let classes = |resp: &ExecutorResp| {
+        resp.receipts
+            .iter()
+            .map(|receipt| receipt.response.response.error_class())
+            .collect::<Vec<_>>()
+    };
+
+    // Signer of mock transactions is the only validator, its vote passes.
+    // Halt is read once for a block, it's lifted from the next one.
+    let mut resume = mock_signed_tx();
+    resume.raw.request.service_name = "metadata".to_owned();
+    resume.raw.request.method = "resume_chain".to_owned();
+    resume.raw.request.payload = "".to_owned();
+    let resp = exec(root, 1, vec![mock_signed_tx(), resume, mock_signed_tx()]);
+    assert_eq!(classes(&resp), vec![
+        Some(ErrorClass::Halted),
+        None,
+        Some(ErrorClass::Halted),
+    ]);
+    assert_eq!(resp.receipts[0].cycles_used, 0);
+
+    let resp = exec(resp.state_root, 2, vec![mock_signed_tx()]);
+    assert_eq!(classes(&resp), vec![None]);
+}
+
+#[test]
+fn test_unsupported_feature() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let mut genesis: Genesis = toml::from_str(toml_str).unwrap();
+    for service in genesis.services.iter_mut() {
+        service.payload = service.payload.replace(
+            r#""treasury_fee_ratio": 2,"#,
+            r#""treasury_fee_ratio": 2, "chain_spec": [
+                {"name": "signature_domain", "height": 1},
+                {"name": "unknown_fork", "height": 3}
+            ],"#,
+        );
+    }
+
+    let db = Arc::new(MemoryDB::new(false));
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let exec = |root: MerkleRoot, height: u64| {
+        let mut executor = MPTServiceExecutor::with_root(
+            root.clone(),
+            Arc::clone(&db),
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+        let params = ExecutorParams {
+            state_root: root,
+            height,
+            timestamp: 0,
+            cycles_limit: std::u64::MAX,
+            proposer: Address::default(),
+            vote_extensions: vec![],
+            extra_data: Bytes::new(),
+        };
+        executor.exec(&params, &[mock_signed_tx()])
+    };
+
+    // Blocks before the unknown feature are executed
+    let resp = exec(root, 2).unwrap();
+    let err = exec(resp.state_root, 3).unwrap_err();
+    assert!(err.to_string().contains("unknown_fork"));
+}
+
+#[test]
+fn test_method_access() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let mut genesis: Genesis = toml::from_str(toml_str).unwrap();
+    for service in genesis.services.iter_mut() {
+        service.payload = service.payload.replace(
+            r#""treasury_fee_ratio": 2,"#,
+            r#""treasury_fee_ratio": 2, "service_admins": [{ "service": "metadata", "admins": ["0xf8389d774afdad8755ef8e629e5a154fddc6325a"] }],"#,
+        );
+    }
+
+    let db = Arc::new(MemoryDB::new(false));
+    let root = MPTServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let mut executor = MPTServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+    let params = ExecutorParams {
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+
+    // Signer of mock transactions is the admin at genesis, it removes itself
+    let mut set_admins = mock_signed_tx();
+    set_admins.raw.request.service_name = "metadata".to_owned();
+    set_admins.raw.request.method = "set_service_admins".to_owned();
+    set_admins.raw.request.payload = r#"{ "service": "metadata", "admins": [] }"#.to_owned();
+    let resp = executor
+        .exec(&params, &[set_admins.clone(), set_admins])
+        .unwrap();
+
+    let classes = resp
+        .receipts
+        .iter()
+        .map(|receipt| receipt.response.response.error_class())
+        .collect::<Vec<_>>();
+    assert_eq!(classes, vec![None, Some(ErrorClass::Forbidden)]);
+
+    // Methods limited to a service are called by the service only
+    let params = ExecutorParams {
+        state_root: resp.state_root,
+        height: 2,
+        ..params
+    };
+    let write_tx = |method: &str, key: &str| {
+        let mut stx = mock_signed_tx();
+        stx.raw.request.service_name = "test".to_owned();
+        stx.raw.request.method = method.to_owned();
+        stx.raw.request.payload = format!(r#"{{"key": "{}", "value": "bar", "extra": ""}}"#, key);
+        stx
+    };
+    let resp = executor
+        .exec(&params, &[
+            write_tx("test_write_by_service", "foo"),
+            write_tx("test_call_write_by_service", "baz"),
+        ])
+        .unwrap();
+
+    let classes = resp
+        .receipts
+        .iter()
+        .map(|receipt| receipt.response.response.error_class())
+        .collect::<Vec<_>>();
+    assert_eq!(classes, vec![Some(ErrorClass::Forbidden), None]);
+}
+
 #[bench]
 fn bench_execute(b: &mut Bencher) {
     let toml_str = include_str!("./genesis_services.toml");
@@ -192,7 +1363,7 @@ fn bench_execute(b: &mut Bencher) {
 
     let db = Arc::new(MemoryDB::new(false));
 
-    let root = ServiceExecutor::create_genesis(
+    let root = MPTServiceExecutor::create_genesis(
         genesis.services,
         Arc::clone(&db),
         Arc::new(MockStorage {}),
@@ -200,7 +1371,7 @@ fn bench_execute(b: &mut Bencher) {
     )
     .unwrap();
 
-    let mut executor = ServiceExecutor::with_root(
+    let mut executor = MPTServiceExecutor::with_root(
         root.clone(),
         Arc::clone(&db),
         Arc::new(MockStorage {}),
@@ -212,10 +1383,13 @@ fn bench_execute(b: &mut Bencher) {
 
     b.iter(|| {
         let params = ExecutorParams {
-            state_root:   root.clone(),
-            height:       1,
-            timestamp:    0,
-            cycles_limit: std::u64::MAX,
+            state_root:      root.clone(),
+            height:          1,
+            timestamp:       0,
+            cycles_limit:    std::u64::MAX,
+            proposer:        Address::default(),
+            vote_extensions: vec![],
+            extra_data:      Bytes::new(),
         };
         let txs = txs.clone();
         executor.exec(&params, &txs).unwrap();
@@ -224,17 +1398,19 @@ fn bench_execute(b: &mut Bencher) {
 
 fn mock_signed_tx() -> SignedTransaction {
     let raw = RawTransaction {
-        chain_id:     Hash::from_empty(),
-        nonce:        Hash::from_empty(),
-        timeout:      0,
-        cycles_price: 1,
-        cycles_limit: std::u64::MAX,
-        request:      TransactionRequest {
+        chain_id:              Hash::from_empty(),
+        nonce:                 Hash::from_empty(),
+        timeout:               0,
+        cycles_price:          1,
+        cycles_limit:          std::u64::MAX,
+        request:               TransactionRequest {
             service_name: "asset".to_owned(),
             method:       "create_asset".to_owned(),
             payload:      r#"{ "name": "MutaToken2", "symbol": "MT2", "supply": 320000011 }"#
                 .to_owned(),
         },
+        sender:                None,
+        valid_until_timestamp: None,
     };
 
     SignedTransaction {
@@ -257,6 +1433,7 @@ impl ServiceMapping for MockServiceMapping {
         sdk: SDK,
     ) -> ProtocolResult<Box<dyn Service>> {
         let service = match name {
+            "account" => Box::new(AccountService::new(sdk)) as Box<dyn Service>,
             "asset" => Box::new(AssetService::new(sdk)) as Box<dyn Service>,
             "metadata" => Box::new(MetadataService::new(sdk)) as Box<dyn Service>,
             "test" => Box::new(TestService::new(sdk)) as Box<dyn Service>,
@@ -267,7 +1444,12 @@ impl ServiceMapping for MockServiceMapping {
     }
 
     fn list_service_name(&self) -> Vec<String> {
-        vec!["asset".to_owned(), "metadata".to_owned(), "test".to_owned()]
+        vec![
+            "account".to_owned(),
+            "asset".to_owned(),
+            "metadata".to_owned(),
+            "test".to_owned(),
+        ]
     }
 }
 
@@ -12,12 +12,13 @@ use protocol::traits::{
     Executor, ExecutorParams, Service, ServiceMapping, ServiceResponse, ServiceSDK,
 };
 use protocol::types::{
-    Genesis, Hash, RawTransaction, ServiceContext, SignedTransaction, TransactionRequest,
+    Address, Genesis, Hash, RawTransaction, ServiceContext, SignedTransaction, TraceStepKind,
+    TransactionRequest, CYCLES_PRICING,
 };
 use protocol::ProtocolResult;
 
 use crate::executor::tests::MockStorage;
-use crate::executor::ServiceExecutor;
+use crate::executor::MPTServiceExecutor;
 
 #[test]
 fn test_service_call_service() {
@@ -25,9 +26,15 @@ fn test_service_call_service() {
     let arcs = Arc::new(MockStorage {});
 
     let toml_str = include_str!("./genesis_services.toml");
-    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+    let mut genesis: Genesis = toml::from_str(toml_str).unwrap();
+    for service in genesis.services.iter_mut() {
+        service.payload = service.payload.replace(
+            r#""treasury_fee_ratio": 2,"#,
+            r#""treasury_fee_ratio": 2, "chain_spec": [{"name": "cycles_pricing", "height": 1}],"#,
+        );
+    }
 
-    let root = ServiceExecutor::create_genesis(
+    let root = MPTServiceExecutor::create_genesis(
         genesis.services,
         Arc::clone(&memdb),
         Arc::new(MockStorage {}),
@@ -35,7 +42,7 @@ fn test_service_call_service() {
     )
     .unwrap();
 
-    let mut executor = ServiceExecutor::with_root(
+    let mut executor = MPTServiceExecutor::with_root(
         root.clone(),
         Arc::clone(&memdb),
         Arc::clone(&arcs),
@@ -44,24 +51,29 @@ fn test_service_call_service() {
     .unwrap();
 
     let params = ExecutorParams {
-        state_root:   root,
-        height:       1,
-        timestamp:    0,
-        cycles_limit: std::u64::MAX,
+        state_root:      root,
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    std::u64::MAX,
+        proposer:        Address::default(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
     };
 
     let raw = RawTransaction {
-        chain_id:     Hash::from_empty(),
-        nonce:        Hash::from_empty(),
-        timeout:      0,
-        cycles_price: 1,
-        cycles_limit: 60_000,
-        request:      TransactionRequest {
+        chain_id:              Hash::from_empty(),
+        nonce:                 Hash::from_empty(),
+        timeout:               0,
+        cycles_price:          1,
+        cycles_limit:          60_000,
+        request:               TransactionRequest {
             service_name: "mock".to_owned(),
             method:       "call_asset".to_owned(),
             payload:      r#"{ "name": "TestCallAsset", "symbol": "TCA", "supply": 320000011 }"#
                 .to_owned(),
         },
+        sender:                None,
+        valid_until_timestamp: None,
     };
     let stx = SignedTransaction {
         raw,
@@ -78,7 +90,39 @@ fn test_service_call_service() {
     let receipt = &executor_resp.receipts[0];
     let event = &receipt.events[1];
 
-    assert_eq!(50_000, receipt.cycles_used);
+    // 50_000 declared by #[cycles] of both methods, the rest is charged by the
+    // framework for the call to asset, events and storage access traced by a
+    // re-execution
+    let mut tracer = MPTServiceExecutor::with_root(
+        params.state_root.clone(),
+        Arc::clone(&memdb),
+        Arc::clone(&arcs),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+    let trace = tracer.trace(&params, &txs, &Hash::from_empty()).unwrap();
+    let storage_cycles: u64 = trace
+        .steps
+        .iter()
+        .map(|step| {
+            let bytes = step.key.as_ref().map(Bytes::len).unwrap_or(0) as u64 + step.size;
+            match step.kind {
+                TraceStepKind::Read => CYCLES_PRICING.storage_read_per_byte * bytes,
+                TraceStepKind::Write => CYCLES_PRICING.storage_write_per_byte * bytes,
+                _ => 0,
+            }
+        })
+        .sum();
+    let event_cycles: u64 = receipt
+        .events
+        .iter()
+        .map(|e| CYCLES_PRICING.event_base + CYCLES_PRICING.event_per_byte * e.data.len() as u64)
+        .sum();
+    assert_eq!(
+        receipt.cycles_used,
+        50_000 + CYCLES_PRICING.service_call + event_cycles + storage_cycles
+    );
+    assert_eq!(trace.cycles_used, receipt.cycles_used);
     assert_eq!(
         ("mock", "call create asset succeed"),
         (event.service.as_str(), event.data.as_str())
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use binding_macro::{cycles, service, tx_hook_after, tx_hook_before};
+use binding_macro::{access, cycles, service, tx_hook_after, tx_hook_before};
 use protocol::traits::{ExecutorParams, ServiceResponse, ServiceSDK};
 use protocol::types::ServiceContext;
 
@@ -47,6 +47,7 @@ impl<SDK: ServiceSDK> TestService<SDK> {
     }
 
     #[cycles(210_00)]
+    #[max_payload_size(1024)]
     #[write]
     fn test_write(
         &mut self,
@@ -57,6 +58,17 @@ impl<SDK: ServiceSDK> TestService<SDK> {
         ServiceResponse::<TestWriteResponse>::from_succeed(TestWriteResponse {})
     }
 
+    #[cycles(210_00)]
+    #[write]
+    fn test_write_failed(
+        &mut self,
+        ctx: ServiceContext,
+        payload: TestWritePayload,
+    ) -> ServiceResponse<TestWriteResponse> {
+        self.sdk.set_value(payload.key, payload.value);
+        ServiceResponse::<TestWriteResponse>::from_error(101, "write failed".to_owned())
+    }
+
     #[cycles(210_00)]
     #[write]
     fn test_service_call_invoke_hook_only_once(
@@ -70,6 +82,35 @@ impl<SDK: ServiceSDK> TestService<SDK> {
         ServiceResponse::<TestWriteResponse>::from_succeed(TestWriteResponse {})
     }
 
+    #[cycles(210_00)]
+    #[access(service = "test")]
+    #[write]
+    fn test_write_by_service(
+        &mut self,
+        ctx: ServiceContext,
+        payload: TestWritePayload,
+    ) -> ServiceResponse<TestWriteResponse> {
+        self.sdk.set_value(payload.key, payload.value);
+        ServiceResponse::<TestWriteResponse>::from_succeed(TestWriteResponse {})
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn test_call_write_by_service(
+        &mut self,
+        ctx: ServiceContext,
+        payload: TestWritePayload,
+    ) -> ServiceResponse<TestWriteResponse> {
+        let payload_str = serde_json::to_string(&payload).unwrap();
+        let resp = self
+            .sdk
+            .write(&ctx, None, "test", "test_write_by_service", &payload_str);
+        if resp.is_error() {
+            return ServiceResponse::<TestWriteResponse>::from_error(resp.code, resp.error_message);
+        }
+        ServiceResponse::<TestWriteResponse>::from_succeed(TestWriteResponse {})
+    }
+
     #[tx_hook_before]
     fn test_tx_hook_before(&mut self, ctx: ServiceContext) {
         if ctx.get_service_name() == "test"
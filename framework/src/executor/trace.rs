@@ -0,0 +1,109 @@
+use bytes::Bytes;
+
+use protocol::traits::ServiceResponse;
+use protocol::types::{Hash, ServiceContext, TraceStep, TraceStepKind};
+
+/// Steps of the traced transaction, shared by the executor and metered
+/// states of its calls. Nothing is recorded for other transactions.
+#[derive(Default)]
+pub struct TraceRecorder {
+    target:      Option<Hash>,
+    // Context of the traced transaction while it's executing
+    ctx:         Option<ServiceContext>,
+    depth:       u64,
+    events_seen: usize,
+    steps:       Vec<TraceStep>,
+}
+
+impl TraceRecorder {
+    pub fn set_target(&mut self, tx_hash: Hash) {
+        self.target = Some(tx_hash);
+    }
+
+    pub fn is_target(&self, tx_hash: &Hash) -> bool {
+        self.target.as_ref() == Some(tx_hash)
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.ctx.is_some()
+    }
+
+    pub fn start(&mut self, ctx: ServiceContext) {
+        self.ctx = Some(ctx);
+        self.depth = 0;
+        self.events_seen = 0;
+        self.steps.clear();
+    }
+
+    pub fn finish(&mut self) -> Vec<TraceStep> {
+        self.record_events();
+        self.target = None;
+        self.ctx = None;
+        std::mem::replace(&mut self.steps, vec![])
+    }
+
+    pub fn call(&mut self, ctx: &ServiceContext) {
+        self.record_events();
+        let step = TraceStep::call(
+            self.depth,
+            ctx.get_service_name(),
+            ctx.get_service_method(),
+            ctx.get_payload(),
+            self.cycles_used(),
+        );
+        self.steps.push(step);
+        self.depth += 1;
+    }
+
+    pub fn ret(&mut self, ctx: &ServiceContext, resp: &ServiceResponse<String>) {
+        self.record_events();
+        self.depth = self.depth.saturating_sub(1);
+        let step = TraceStep::ret(
+            self.depth,
+            ctx.get_service_name(),
+            ctx.get_service_method(),
+            resp,
+            self.cycles_used(),
+        );
+        self.steps.push(step);
+    }
+
+    pub fn access(&mut self, kind: TraceStepKind, service: &str, key: Bytes, size: usize) {
+        if !self.is_tracing() {
+            return;
+        }
+
+        self.record_events();
+        let step = TraceStep::access(
+            self.depth,
+            kind,
+            service,
+            key,
+            size as u64,
+            self.cycles_used(),
+        );
+        self.steps.push(step);
+    }
+
+    // Events emitted since last step, they belong to the current call
+    fn record_events(&mut self) {
+        let events = match self.ctx.as_ref() {
+            Some(ctx) => ctx.get_events(),
+            None => return,
+        };
+
+        let cycles_used = self.cycles_used();
+        for event in events.iter().skip(self.events_seen) {
+            let step = TraceStep::event(self.depth, &event.service, &event.data, cycles_used);
+            self.steps.push(step);
+        }
+        self.events_seen = events.len();
+    }
+
+    fn cycles_used(&self) -> u64 {
+        self.ctx
+            .as_ref()
+            .map(ServiceContext::get_cycles_used)
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,353 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+use asset::types::InitGenesisPayload;
+use common_crypto::{BlsPublicKey, PublicKey, Secp256k1PublicKey};
+use protocol::types::{Address, CommitmentScheme, Genesis, Hash, Hex, Metadata, ServiceParam};
+use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
+
+pub const METADATA_SERVICE_NAME: &str = "metadata";
+pub const ASSET_SERVICE_NAME: &str = "asset";
+
+/// The payload generated for a service which accepts admin keys in its
+/// genesis.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct AdminGenesisPayload {
+    pub admins: Vec<Address>,
+}
+
+/// Build a `Genesis` with typed payloads, every payload is checked before it
+/// is encoded, so that a broken genesis is rejected before the node starts.
+#[derive(Clone, Debug)]
+pub struct GenesisBuilder {
+    timestamp: u64,
+    prevhash:  Hex,
+    scheme:    CommitmentScheme,
+    metadata:  Option<Metadata>,
+    asset:     Option<InitGenesisPayload>,
+    admins:    Vec<(String, Vec<Hex>)>,
+    services:  Vec<ServiceParam>,
+}
+
+impl Default for GenesisBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GenesisBuilder {
+    pub fn new() -> Self {
+        Self {
+            timestamp: 0,
+            prevhash:  Hex::from_string(Hash::from_empty().as_hex())
+                .expect("hash hex always starts with 0x"),
+            scheme:    CommitmentScheme::default(),
+            metadata:  None,
+            asset:     None,
+            admins:    vec![],
+            services:  vec![],
+        }
+    }
+
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn prevhash(mut self, prevhash: Hex) -> Self {
+        self.prevhash = prevhash;
+        self
+    }
+
+    pub fn state_commitment(mut self, scheme: CommitmentScheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    pub fn metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn asset(mut self, asset: InitGenesisPayload) -> Self {
+        self.asset = Some(asset);
+        self
+    }
+
+    /// Set the admin secp256k1 public keys of the giving service, the keys
+    /// are converted to addresses in the generated payload.
+    pub fn admin_keys(mut self, service: &str, keys: Vec<Hex>) -> Self {
+        self.admins.push((service.to_owned(), keys));
+        self
+    }
+
+    /// Set a raw json payload for a service which does not have a typed
+    /// setter.
+    pub fn service_payload(mut self, service: &str, payload: String) -> Self {
+        self.services.push(ServiceParam {
+            name: service.to_owned(),
+            payload,
+        });
+        self
+    }
+
+    pub fn build(self) -> ProtocolResult<Genesis> {
+        let metadata = self.metadata.ok_or(GenesisError::MissingMetadata)?;
+        verify_metadata(&metadata)?;
+
+        let mut services = vec![ServiceParam {
+            name:    METADATA_SERVICE_NAME.to_owned(),
+            payload: to_payload(METADATA_SERVICE_NAME, &metadata)?,
+        }];
+
+        if let Some(asset) = self.asset {
+            verify_asset(&asset)?;
+            services.push(ServiceParam {
+                name:    ASSET_SERVICE_NAME.to_owned(),
+                payload: to_payload(ASSET_SERVICE_NAME, &asset)?,
+            });
+        }
+
+        for (service, keys) in self.admins.into_iter() {
+            let admins = keys
+                .iter()
+                .map(admin_address)
+                .collect::<ProtocolResult<Vec<_>>>()?;
+            if admins.is_empty() {
+                return Err(GenesisError::EmptyAdmins { service }.into());
+            }
+
+            let payload = to_payload(&service, &AdminGenesisPayload { admins })?;
+            services.push(ServiceParam {
+                name: service,
+                payload,
+            });
+        }
+
+        for param in self.services.into_iter() {
+            serde_json::from_str::<serde_json::Value>(&param.payload).map_err(|e| {
+                GenesisError::InvalidPayload {
+                    service: param.name.clone(),
+                    reason:  e.to_string(),
+                }
+            })?;
+            services.push(param);
+        }
+
+        let genesis = Genesis {
+            timestamp: self.timestamp,
+            prevhash: self.prevhash,
+            state_commitment: self.scheme,
+            services,
+        };
+        verify_genesis(&genesis)?;
+        Ok(genesis)
+    }
+}
+
+/// Check a genesis, usually deserialized from `genesis.toml`. The metadata
+/// service payload is required, the asset service payload is checked if it
+/// exists.
+pub fn verify_genesis(genesis: &Genesis) -> ProtocolResult<()> {
+    let mut names = HashSet::new();
+    for param in genesis.services.iter() {
+        if !names.insert(param.name.as_str()) {
+            return Err(GenesisError::DuplicateService(param.name.clone()).into());
+        }
+    }
+
+    let metadata: Metadata =
+        from_payload(genesis, METADATA_SERVICE_NAME)?.ok_or(GenesisError::MissingMetadata)?;
+    verify_metadata(&metadata)?;
+
+    if let Some(asset) = from_payload::<InitGenesisPayload>(genesis, ASSET_SERVICE_NAME)? {
+        verify_asset(&asset)?;
+    }
+
+    Ok(())
+}
+
+pub fn verify_metadata(metadata: &Metadata) -> ProtocolResult<()> {
+    if metadata.common_ref.is_empty() {
+        return Err(GenesisError::InvalidMetadata("common_ref is empty".to_owned()).into());
+    }
+
+    let non_zero_fields = [
+        ("timeout_gap", metadata.timeout_gap),
+        ("cycles_limit", metadata.cycles_limit),
+        ("interval", metadata.interval),
+        ("tx_num_limit", metadata.tx_num_limit),
+        ("max_tx_size", metadata.max_tx_size),
+    ];
+    for (name, value) in non_zero_fields.iter() {
+        if *value == 0 {
+            return Err(GenesisError::InvalidMetadata(format!("{} must be non-zero", name)).into());
+        }
+    }
+
+    verify_ratios(metadata)?;
+
+    if metadata.verifier_list.is_empty() {
+        return Err(GenesisError::EmptyVerifierList.into());
+    }
+
+    let mut addresses = HashSet::new();
+    for validator in metadata.verifier_list.iter() {
+        let address = validator.address.as_hex();
+
+        if !addresses.insert(address.clone()) {
+            return Err(GenesisError::DuplicateValidator(address).into());
+        }
+
+        if validator.propose_weight == 0 || validator.vote_weight == 0 {
+            return Err(GenesisError::ZeroWeight(address).into());
+        }
+
+        BlsPublicKey::try_from(validator.bls_pub_key.as_bytes().as_ref()).map_err(|e| {
+            GenesisError::InvalidBlsPubKey {
+                address: address.clone(),
+                reason:  format!("{:?}", e),
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
+/// The overlord timer ratios are measured in tenths of the interval. Every
+/// step must take some time, and the brake step can not be longer than the
+/// propose step.
+fn verify_ratios(metadata: &Metadata) -> ProtocolResult<()> {
+    let ratios = [
+        ("propose_ratio", metadata.propose_ratio),
+        ("prevote_ratio", metadata.prevote_ratio),
+        ("precommit_ratio", metadata.precommit_ratio),
+        ("brake_ratio", metadata.brake_ratio),
+    ];
+    for (name, ratio) in ratios.iter() {
+        if *ratio == 0 {
+            return Err(GenesisError::InvalidRatio(format!("{} must be non-zero", name)).into());
+        }
+    }
+
+    if metadata.brake_ratio > metadata.propose_ratio {
+        return Err(GenesisError::InvalidRatio(format!(
+            "brake_ratio {} is larger than propose_ratio {}",
+            metadata.brake_ratio, metadata.propose_ratio
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+pub fn verify_asset(asset: &InitGenesisPayload) -> ProtocolResult<()> {
+    if asset.name.is_empty() || asset.symbol.is_empty() {
+        return Err(
+            GenesisError::InvalidAsset("name and symbol can not be empty".to_owned()).into(),
+        );
+    }
+
+    if asset.supply == 0 {
+        return Err(GenesisError::InvalidAsset("supply must be non-zero".to_owned()).into());
+    }
+
+    if asset.max_supply != 0 && asset.supply > asset.max_supply {
+        return Err(GenesisError::InvalidAsset("supply exceeds max supply".to_owned()).into());
+    }
+
+    Ok(())
+}
+
+fn admin_address(key: &Hex) -> ProtocolResult<Address> {
+    let pubkey = Secp256k1PublicKey::try_from(key.as_bytes().as_ref()).map_err(|e| {
+        GenesisError::InvalidAdminKey {
+            key:    key.as_string(),
+            reason: format!("{:?}", e),
+        }
+    })?;
+
+    Address::from_pubkey_bytes(pubkey.to_bytes())
+}
+
+fn to_payload<T: Serialize>(service: &str, payload: &T) -> ProtocolResult<String> {
+    let payload =
+        serde_json::to_string_pretty(payload).map_err(|e| GenesisError::InvalidPayload {
+            service: service.to_owned(),
+            reason:  e.to_string(),
+        })?;
+    Ok(payload)
+}
+
+fn from_payload<T: for<'de> Deserialize<'de>>(
+    genesis: &Genesis,
+    service: &str,
+) -> ProtocolResult<Option<T>> {
+    let param = match genesis.services.iter().find(|param| param.name == service) {
+        Some(param) => param,
+        None => return Ok(None),
+    };
+
+    let payload =
+        serde_json::from_str(&param.payload).map_err(|e| GenesisError::InvalidPayload {
+            service: service.to_owned(),
+            reason:  e.to_string(),
+        })?;
+    Ok(Some(payload))
+}
+
+#[derive(Debug, Display)]
+pub enum GenesisError {
+    #[display(fmt = "genesis metadata is missing")]
+    MissingMetadata,
+
+    #[display(fmt = "service {:?} appears more than once in genesis", _0)]
+    DuplicateService(String),
+
+    #[display(fmt = "service {:?} genesis payload is invalid: {}", service, reason)]
+    InvalidPayload { service: String, reason: String },
+
+    #[display(fmt = "invalid metadata: {}", _0)]
+    InvalidMetadata(String),
+
+    #[display(fmt = "invalid overlord ratio: {}", _0)]
+    InvalidRatio(String),
+
+    #[display(fmt = "metadata verifier_list is empty")]
+    EmptyVerifierList,
+
+    #[display(fmt = "validator {} appears more than once in verifier_list", _0)]
+    DuplicateValidator(String),
+
+    #[display(
+        fmt = "validator {} propose_weight and vote_weight must be non-zero",
+        _0
+    )]
+    ZeroWeight(String),
+
+    #[display(fmt = "validator {} bls_pub_key is invalid: {}", address, reason)]
+    InvalidBlsPubKey { address: String, reason: String },
+
+    #[display(fmt = "invalid asset: {}", _0)]
+    InvalidAsset(String),
+
+    #[display(fmt = "admin key {} is invalid: {}", key, reason)]
+    InvalidAdminKey { key: String, reason: String },
+
+    #[display(fmt = "service {:?} admin keys are empty", service)]
+    EmptyAdmins { service: String },
+}
+
+impl std::error::Error for GenesisError {}
+
+impl From<GenesisError> for ProtocolError {
+    fn from(err: GenesisError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Executor, Box::new(err))
+    }
+}
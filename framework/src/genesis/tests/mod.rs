@@ -0,0 +1,197 @@
+use asset::types::InitGenesisPayload;
+use protocol::types::{
+    Address, ChainSpec, CommitmentScheme, Genesis, Hash, Hex, Metadata, ValidatorExtend,
+};
+
+use crate::genesis::{verify_genesis, AdminGenesisPayload, GenesisBuilder};
+
+#[test]
+fn test_build_genesis() {
+    let admin_key = Hex::from_string(
+        "0x031313016e9670deb49779c1b0c646d6a25a545712658f9781995f623bcd0d0b3d".to_owned(),
+    )
+    .unwrap();
+
+    let genesis = GenesisBuilder::new()
+        .timestamp(1)
+        .metadata(mock_metadata())
+        .asset(mock_asset())
+        .admin_keys("admin", vec![admin_key])
+        .service_payload("util", "{}".to_owned())
+        .build()
+        .unwrap();
+
+    assert_eq!(genesis.timestamp, 1);
+    assert_eq!(genesis.state_commitment, CommitmentScheme::MerklePatricia);
+    assert_eq!(genesis.services.len(), 4);
+
+    let metadata: Metadata = serde_json::from_str(genesis.get_payload("metadata")).unwrap();
+    assert_eq!(metadata, mock_metadata());
+
+    let admin: AdminGenesisPayload = serde_json::from_str(genesis.get_payload("admin")).unwrap();
+    assert_eq!(admin.admins, vec![Address::from_hex(
+        "0xc38f8210896e11a75e1a1f13805d39088d157d7f"
+    )
+    .unwrap()]);
+
+    let toml_str = toml::to_string(&genesis).unwrap();
+    let decoded: Genesis = toml::from_str(&toml_str).unwrap();
+    assert_eq!(decoded, genesis);
+    verify_genesis(&decoded).unwrap();
+}
+
+#[test]
+fn test_missing_metadata() {
+    let err = GenesisBuilder::new()
+        .asset(mock_asset())
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("metadata is missing"));
+}
+
+#[test]
+fn test_invalid_bls_pub_key() {
+    let mut metadata = mock_metadata();
+    metadata.verifier_list[0].bls_pub_key = Hex::from_string("0x0418".to_owned()).unwrap();
+
+    let err = GenesisBuilder::new()
+        .metadata(metadata)
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("bls_pub_key is invalid"));
+}
+
+#[test]
+fn test_zero_weight() {
+    let mut metadata = mock_metadata();
+    metadata.verifier_list[0].vote_weight = 0;
+
+    let err = GenesisBuilder::new()
+        .metadata(metadata)
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("must be non-zero"));
+}
+
+#[test]
+fn test_invalid_ratio() {
+    let mut metadata = mock_metadata();
+    metadata.brake_ratio = metadata.propose_ratio + 1;
+
+    let err = GenesisBuilder::new()
+        .metadata(metadata)
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("brake_ratio"));
+
+    let mut metadata = mock_metadata();
+    metadata.prevote_ratio = 0;
+
+    let err = GenesisBuilder::new()
+        .metadata(metadata)
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("prevote_ratio must be non-zero"));
+}
+
+#[test]
+fn test_duplicate_service() {
+    let err = GenesisBuilder::new()
+        .metadata(mock_metadata())
+        .service_payload("metadata", "{}".to_owned())
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("more than once"));
+}
+
+#[test]
+fn test_invalid_payload() {
+    let err = GenesisBuilder::new()
+        .metadata(mock_metadata())
+        .service_payload("util", "{".to_owned())
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("payload is invalid"));
+}
+
+#[test]
+fn test_invalid_admin_key() {
+    let err = GenesisBuilder::new()
+        .metadata(mock_metadata())
+        .admin_keys("admin", vec![Hex::from_string("0x03".to_owned()).unwrap()])
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("admin key 0x03 is invalid"));
+}
+
+#[test]
+fn test_exceed_max_supply() {
+    let mut asset = mock_asset();
+    asset.max_supply = asset.supply - 1;
+
+    let err = GenesisBuilder::new()
+        .metadata(mock_metadata())
+        .asset(asset)
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("supply exceeds max supply"));
+}
+
+fn mock_metadata() -> Metadata {
+    Metadata {
+        chain_id:        Hash::from_hex(
+            "0xb6a4d7da21443f5e816e8700eea87610e6d769657d6b8ec73028457bf2ca4036",
+        )
+        .unwrap(),
+        common_ref:      Hex::from_string("0x703873635a6b51513451".to_owned()).unwrap(),
+        timeout_gap:     20,
+        cycles_limit:    1_000_000,
+        cycles_price:    1,
+        interval:        3000,
+        verifier_list:   vec![ValidatorExtend {
+            bls_pub_key:    Hex::from_string("0x04188ef9488c19458a963cc57b567adde7db8f8b6bec392d5cb7b67b0abc1ed6cd966edc451f6ac2ef38079460eb965e890d1f576e4039a20467820237cda753f07a8b8febae1ec052190973a1bcf00690ea8fc0168b3fbbccd1c4e402eda5ef22".to_owned()).unwrap(),
+            address:        Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a")
+                .unwrap(),
+            propose_weight: 1,
+            vote_weight:    1,
+        }],
+        propose_ratio:   15,
+        prevote_ratio:   10,
+        precommit_ratio: 10,
+        brake_ratio:     7,
+        tx_num_limit:    20000,
+        max_tx_size:     1024,
+        proposer_fee_ratio: 8,
+        treasury_fee_ratio: 2,
+        treasury:        Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap(),
+        network_tag: "muta-test".to_owned(),
+        domain_separation_height: 0,
+        max_block_size: 0,
+        tx_base_cycles: 0,
+        storage_deposit_per_byte: 0,
+        rent_epoch: 0,
+        rent_per_byte: 0,
+        halt_height: 0,
+        upgrade_name: String::new(),
+        upgrade_window: 0,
+        upgrade_threshold: 0,
+        upgrade_height: 0,
+        chain_spec: ChainSpec::default(),
+    }
+}
+
+fn mock_asset() -> InitGenesisPayload {
+    InitGenesisPayload {
+        id:         Hash::from_hex(
+            "0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c",
+        )
+        .unwrap(),
+        name:       "MutaToken".to_owned(),
+        symbol:     "MT".to_owned(),
+        supply:     320_000_011,
+        issuer:     Address::from_hex("0xf8389d774afdad8755ef8e629e5a154fddc6325a").unwrap(),
+        decimals:   8,
+        max_supply: 0,
+        fee_asset:  true,
+    }
+}
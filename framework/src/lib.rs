@@ -2,4 +2,6 @@
 #![feature(test)]
 
 pub mod binding;
+pub mod devchain;
 pub mod executor;
+pub mod genesis;
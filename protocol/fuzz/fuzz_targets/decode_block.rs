@@ -0,0 +1,16 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use bytes::Bytes;
+use protocol::fixed_codec::FixedCodec;
+use protocol::types::{Block, BlockHeader, Pill, Proof, Validator};
+
+fuzz_target!(|data: &[u8]| {
+    let bytes = Bytes::from(data.to_vec());
+
+    let _ = Proof::decode_fixed(bytes.clone());
+    let _ = Validator::decode_fixed(bytes.clone());
+    let _ = BlockHeader::decode_fixed(bytes.clone());
+    let _ = Block::decode_fixed(bytes.clone());
+    let _ = Pill::decode_fixed(bytes);
+});
@@ -0,0 +1,22 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use bytes::Bytes;
+use protocol::fixed_codec::FixedCodec;
+use protocol::types::{Address, Hash, Hex, Metadata, ValidatorExtend};
+
+fuzz_target!(|data: &[u8]| {
+    let bytes = Bytes::from(data.to_vec());
+
+    let _ = Hash::decode_fixed(bytes.clone());
+    let _ = Address::decode_fixed(bytes.clone());
+    let _ = Hex::decode_fixed(bytes.clone());
+    let _ = Metadata::decode_fixed(bytes.clone());
+    let _ = rlp::decode::<ValidatorExtend>(data);
+    let _ = bool::decode_fixed(bytes.clone());
+    let _ = u8::decode_fixed(bytes.clone());
+    let _ = u32::decode_fixed(bytes.clone());
+    let _ = u64::decode_fixed(bytes.clone());
+    let _ = String::decode_fixed(bytes.clone());
+    let _ = Bytes::decode_fixed(bytes);
+});
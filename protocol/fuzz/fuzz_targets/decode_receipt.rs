@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use bytes::Bytes;
+use protocol::fixed_codec::FixedCodec;
+use protocol::types::{Event, Receipt, ReceiptResponse};
+
+fuzz_target!(|data: &[u8]| {
+    let bytes = Bytes::from(data.to_vec());
+
+    let _ = Receipt::decode_fixed(bytes.clone());
+    let _ = ReceiptResponse::decode_fixed(bytes);
+    let _ = rlp::decode::<Event>(data);
+});
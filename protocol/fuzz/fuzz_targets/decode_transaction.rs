@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use bytes::Bytes;
+use protocol::fixed_codec::FixedCodec;
+use protocol::types::{RawTransaction, SignedTransaction};
+
+fuzz_target!(|data: &[u8]| {
+    let bytes = Bytes::from(data.to_vec());
+
+    let _ = RawTransaction::decode_fixed(bytes.clone());
+    let _ = SignedTransaction::decode_fixed(bytes);
+});
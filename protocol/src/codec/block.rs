@@ -25,6 +25,9 @@ pub struct Block {
 
     #[prost(message, repeated, tag = "2")]
     pub ordered_tx_hashes: Vec<Hash>,
+
+    #[prost(message, repeated, tag = "3")]
+    pub vote_extensions: Vec<VoteExtension>,
 }
 
 #[derive(Clone, Message)]
@@ -73,6 +76,9 @@ pub struct BlockHeader {
 
     #[prost(uint64, tag = "15")]
     pub exec_height: u64,
+
+    #[prost(bytes, tag = "16")]
+    pub extra_data: Vec<u8>,
 }
 
 #[derive(Clone, Message)]
@@ -105,6 +111,24 @@ pub struct Validator {
     pub vote_weight: u32,
 }
 
+#[derive(Clone, Message)]
+pub struct VoteExtension {
+    #[prost(uint64, tag = "1")]
+    pub height: u64,
+
+    #[prost(message, tag = "2")]
+    pub voter: Option<Address>,
+
+    #[prost(bytes, tag = "3")]
+    pub payload: Vec<u8>,
+
+    #[prost(bytes, tag = "4")]
+    pub signature: Vec<u8>,
+
+    #[prost(message, tag = "5")]
+    pub block_hash: Option<Hash>,
+}
+
 #[derive(Clone, Message)]
 pub struct Pill {
     #[prost(message, tag = "1")]
@@ -128,10 +152,16 @@ impl From<block::Block> for Block {
             .into_iter()
             .map(Hash::from)
             .collect::<Vec<_>>();
+        let vote_extensions = block
+            .vote_extensions
+            .into_iter()
+            .map(VoteExtension::from)
+            .collect::<Vec<_>>();
 
         Block {
             header,
             ordered_tx_hashes,
+            vote_extensions,
         }
     }
 }
@@ -147,9 +177,15 @@ impl TryFrom<Block> for block::Block {
             ordered_tx_hashes.push(protocol_primitive::Hash::try_from(hash)?);
         }
 
+        let mut vote_extensions = Vec::new();
+        for extension in block.vote_extensions {
+            vote_extensions.push(block::VoteExtension::try_from(extension)?);
+        }
+
         let block = block::Block {
             header: block::BlockHeader::try_from(header)?,
             ordered_tx_hashes,
+            vote_extensions,
         };
 
         Ok(block)
@@ -204,6 +240,7 @@ impl From<block::BlockHeader> for BlockHeader {
             proof,
             validator_version: block_header.validator_version,
             validators,
+            extra_data: block_header.extra_data.to_vec(),
         }
     }
 }
@@ -255,6 +292,7 @@ impl TryFrom<BlockHeader> for block::BlockHeader {
             proof: block::Proof::try_from(proof)?,
             validator_version: block_header.validator_version,
             validators,
+            extra_data: Bytes::from(block_header.extra_data),
         };
 
         Ok(proof)
@@ -325,6 +363,42 @@ impl TryFrom<Validator> for block::Validator {
     }
 }
 
+// VoteExtension
+
+impl From<block::VoteExtension> for VoteExtension {
+    fn from(extension: block::VoteExtension) -> VoteExtension {
+        let voter = Some(Address::from(extension.voter));
+        let block_hash = Some(Hash::from(extension.block_hash));
+
+        VoteExtension {
+            height: extension.height,
+            voter,
+            payload: extension.payload.to_vec(),
+            signature: extension.signature.to_vec(),
+            block_hash,
+        }
+    }
+}
+
+impl TryFrom<VoteExtension> for block::VoteExtension {
+    type Error = ProtocolError;
+
+    fn try_from(extension: VoteExtension) -> Result<block::VoteExtension, Self::Error> {
+        let voter = field!(extension.voter, "VoteExtension", "voter")?;
+        let block_hash = field!(extension.block_hash, "VoteExtension", "block_hash")?;
+
+        let extension = block::VoteExtension {
+            height:     extension.height,
+            voter:      protocol_primitive::Address::try_from(voter)?,
+            block_hash: protocol_primitive::Hash::try_from(block_hash)?,
+            payload:    Bytes::from(extension.payload),
+            signature:  Bytes::from(extension.signature),
+        };
+
+        Ok(extension)
+    }
+}
+
 // Pill
 
 impl From<block::Pill> for Pill {
@@ -367,7 +441,14 @@ impl TryFrom<Pill> for block::Pill {
 // Codec
 // #################
 
-impl_default_bytes_codec_for!(block, [Block, BlockHeader, Proof, Validator, Pill]);
+impl_default_bytes_codec_for!(block, [
+    Block,
+    BlockHeader,
+    Proof,
+    Validator,
+    Pill,
+    VoteExtension
+]);
 
 #[cfg(test)]
 mod test {
@@ -37,6 +37,7 @@ fn test_codec() {
 
     test!(block, Validator, mock_validator);
     test!(block, Proof, mock_proof);
+    test!(block, VoteExtension, mock_vote_extension);
     test!(block, BlockHeader, mock_block_header);
     test!(block, Block, mock_block, 100);
     test!(block, Pill, mock_pill, 100, 200);
@@ -4,7 +4,10 @@ use bytes::Bytes;
 use prost::Message;
 
 use crate::{
-    codec::{primitive::Hash, CodecError, ProtocolCodecSync},
+    codec::{
+        primitive::{Address, Hash},
+        CodecError, ProtocolCodecSync,
+    },
     field, impl_default_bytes_codec_for,
     types::primitive as protocol_primitive,
     ProtocolError, ProtocolResult,
@@ -41,6 +44,12 @@ pub struct RawTransaction {
 
     #[prost(message, tag = "6")]
     pub request: Option<TransactionRequest>,
+
+    #[prost(message, tag = "7")]
+    pub sender: Option<Address>,
+
+    #[prost(uint64, optional, tag = "8")]
+    pub valid_until_timestamp: Option<u64>,
 }
 
 #[derive(Clone, Message)]
@@ -96,6 +105,7 @@ impl From<transaction::RawTransaction> for RawTransaction {
         let chain_id = Some(Hash::from(raw.chain_id));
         let nonce = Some(Hash::from(raw.nonce));
         let request = Some(TransactionRequest::from(raw.request));
+        let sender = raw.sender.map(Address::from);
 
         RawTransaction {
             chain_id,
@@ -104,6 +114,8 @@ impl From<transaction::RawTransaction> for RawTransaction {
             timeout: raw.timeout,
             cycles_limit: raw.cycles_limit,
             request,
+            sender,
+            valid_until_timestamp: raw.valid_until_timestamp,
         }
     }
 }
@@ -115,14 +127,20 @@ impl TryFrom<RawTransaction> for transaction::RawTransaction {
         let chain_id = field!(raw.chain_id, "RawTransaction", "chain_id")?;
         let nonce = field!(raw.nonce, "RawTransaction", "nonce")?;
         let request = field!(raw.request, "RawTransaction", "request")?;
+        let sender = match raw.sender {
+            Some(sender) => Some(protocol_primitive::Address::try_from(sender)?),
+            None => None,
+        };
 
         let raw_tx = transaction::RawTransaction {
-            chain_id:     protocol_primitive::Hash::try_from(chain_id)?,
-            nonce:        protocol_primitive::Hash::try_from(nonce)?,
-            timeout:      raw.timeout,
+            chain_id: protocol_primitive::Hash::try_from(chain_id)?,
+            nonce: protocol_primitive::Hash::try_from(nonce)?,
+            timeout: raw.timeout,
             cycles_price: raw.cycles_price,
             cycles_limit: raw.cycles_limit,
-            request:      transaction::TransactionRequest::try_from(request)?,
+            request: transaction::TransactionRequest::try_from(request)?,
+            sender,
+            valid_until_timestamp: raw.valid_until_timestamp,
         };
 
         Ok(raw_tx)
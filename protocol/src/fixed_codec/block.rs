@@ -1,13 +1,20 @@
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 
 use crate::fixed_codec::{FixedCodec, FixedCodecError};
-use crate::types::block::{Block, BlockHeader, Pill, Proof, Validator};
+use crate::types::block::{Block, BlockHeader, Pill, Proof, Validator, VoteExtension};
 use crate::types::primitive::Hash;
 use crate::types::Bloom;
 use crate::{impl_default_fixed_codec_for, ProtocolResult};
 
 // Impl FixedCodec trait for types
-impl_default_fixed_codec_for!(block, [Proof, Validator, Block, BlockHeader, Pill]);
+impl_default_fixed_codec_for!(block, [
+    Proof,
+    Validator,
+    Block,
+    BlockHeader,
+    Pill,
+    VoteExtension
+]);
 
 impl rlp::Encodable for Proof {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
@@ -71,7 +78,12 @@ impl rlp::Decodable for Validator {
 
 impl rlp::Encodable for BlockHeader {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
-        s.begin_list(15)
+        // Encoding is versioned by its length, headers without extra data
+        // keep their encoding and hash.
+        //   15 items: legacy
+        //   16 items: with extra data
+        let len = if self.extra_data.is_empty() { 15 } else { 16 };
+        s.begin_list(len)
             .append(&self.chain_id)
             .append_list(&self.confirm_root)
             .append_list(&self.cycles_used)
@@ -87,30 +99,44 @@ impl rlp::Encodable for BlockHeader {
             .append(&self.timestamp)
             .append(&self.validator_version)
             .append_list(&self.validators);
+        if !self.extra_data.is_empty() {
+            s.append(&self.extra_data.to_vec());
+        }
     }
 }
 
 impl rlp::Decodable for BlockHeader {
     fn decode(r: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
-        if !r.is_list() && r.size() != 15 {
+        if !r.is_list() || (r.item_count()? != 15 && r.item_count()? != 16) {
             return Err(rlp::DecoderError::RlpIncorrectListLen);
         }
 
         let chain_id: Hash = rlp::decode(r.at(0)?.as_raw())?;
-        let confirm_root: Vec<Hash> = rlp::decode_list(r.at(1)?.as_raw());
-        let cycles_used: Vec<u64> = rlp::decode_list(r.at(2)?.as_raw());
+        let confirm_root: Vec<Hash> = r.at(1)?.as_list()?;
+        let cycles_used: Vec<u64> = r.at(2)?.as_list()?;
         let height: u64 = r.at(3)?.as_val()?;
         let exec_height: u64 = r.at(4)?.as_val()?;
-        let logs_bloom: Vec<Bloom> = rlp::decode_list(r.at(5)?.as_raw());
+        let logs_bloom: Vec<Bloom> = r.at(5)?.as_list()?;
         let order_root = rlp::decode(r.at(6)?.as_raw())?;
         let pre_hash = rlp::decode(r.at(7)?.as_raw())?;
         let proof: Proof = rlp::decode(r.at(8)?.as_raw())?;
         let proposer = rlp::decode(r.at(9)?.as_raw())?;
-        let receipt_root: Vec<Hash> = rlp::decode_list(r.at(10)?.as_raw());
+        let receipt_root: Vec<Hash> = r.at(10)?.as_list()?;
         let state_root = rlp::decode(r.at(11)?.as_raw())?;
         let timestamp: u64 = r.at(12)?.as_val()?;
         let validator_version: u64 = r.at(13)?.as_val()?;
-        let validators: Vec<Validator> = rlp::decode_list(r.at(14)?.as_raw());
+        let validators: Vec<Validator> = r.at(14)?.as_list()?;
+        // Empty extra data is only encoded as a legacy header, so that a
+        // header has one encoding
+        let extra_data = if r.item_count()? == 16 {
+            let extra_data = BytesMut::from(r.at(15)?.data()?).freeze();
+            if extra_data.is_empty() {
+                return Err(rlp::DecoderError::Custom("empty extra data"));
+            }
+            extra_data
+        } else {
+            Bytes::new()
+        };
 
         Ok(BlockHeader {
             chain_id,
@@ -128,30 +154,81 @@ impl rlp::Decodable for BlockHeader {
             proof,
             validator_version,
             validators,
+            extra_data,
+        })
+    }
+}
+
+impl rlp::Encodable for VoteExtension {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(5)
+            .append(&self.height)
+            .append(&self.voter)
+            .append(&self.block_hash)
+            .append(&self.payload.to_vec())
+            .append(&self.signature.to_vec());
+    }
+}
+
+impl rlp::Decodable for VoteExtension {
+    fn decode(r: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        if !r.is_list() || r.item_count()? != 5 {
+            return Err(rlp::DecoderError::RlpIncorrectListLen);
+        }
+
+        let height = r.at(0)?.as_val()?;
+        let voter = rlp::decode(r.at(1)?.as_raw())?;
+        let block_hash = rlp::decode(r.at(2)?.as_raw())?;
+        let payload = BytesMut::from(r.at(3)?.data()?).freeze();
+        let signature = BytesMut::from(r.at(4)?.data()?).freeze();
+
+        Ok(VoteExtension {
+            height,
+            voter,
+            block_hash,
+            payload,
+            signature,
         })
     }
 }
 
 impl rlp::Encodable for Block {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
-        s.begin_list(2)
-            .append(&self.header)
-            .append_list(&self.ordered_tx_hashes);
+        // Encoding is versioned by its length, blocks without extensions
+        // keep their encoding and hash.
+        //   2 items: legacy
+        //   3 items: with vote extensions
+        if self.vote_extensions.is_empty() {
+            s.begin_list(2)
+                .append(&self.header)
+                .append_list(&self.ordered_tx_hashes);
+        } else {
+            s.begin_list(3)
+                .append(&self.header)
+                .append_list(&self.ordered_tx_hashes)
+                .append_list(&self.vote_extensions);
+        }
     }
 }
 
 impl rlp::Decodable for Block {
     fn decode(r: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
-        if !r.is_list() && r.size() != 2 {
+        if !r.is_list() || (r.item_count()? != 2 && r.item_count()? != 3) {
             return Err(rlp::DecoderError::RlpIncorrectListLen);
         }
 
         let header: BlockHeader = rlp::decode(r.at(0)?.as_raw())?;
-        let ordered_tx_hashes: Vec<Hash> = rlp::decode_list(r.at(1)?.as_raw());
+        let ordered_tx_hashes: Vec<Hash> = r.at(1)?.as_list()?;
+        let vote_extensions: Vec<VoteExtension> = if r.item_count()? == 3 {
+            r.at(2)?.as_list()?
+        } else {
+            vec![]
+        };
 
         Ok(Block {
             header,
             ordered_tx_hashes,
+            vote_extensions,
         })
     }
 }
@@ -171,7 +248,7 @@ impl rlp::Decodable for Pill {
         }
 
         let block: Block = rlp::decode(r.at(0)?.as_raw())?;
-        let propose_hashes: Vec<Hash> = rlp::decode_list(r.at(1)?.as_raw());
+        let propose_hashes: Vec<Hash> = r.at(1)?.as_list()?;
 
         Ok(Pill {
             block,
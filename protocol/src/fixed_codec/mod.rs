@@ -32,6 +32,45 @@ pub enum FixedCodecError {
 
     #[display(fmt = "wrong bytes of u8")]
     DecodeUint8,
+
+    #[display(fmt = "wrong bytes of u16")]
+    DecodeUint16,
+
+    #[display(fmt = "wrong bytes of u32")]
+    DecodeUint32,
+
+    #[display(fmt = "wrong bytes of u64")]
+    DecodeUint64,
+
+    #[display(fmt = "wrong bytes of u128")]
+    DecodeUint128,
+
+    #[display(fmt = "wrong bytes of i8")]
+    DecodeInt8,
+
+    #[display(fmt = "wrong bytes of i16")]
+    DecodeInt16,
+
+    #[display(fmt = "wrong bytes of i32")]
+    DecodeInt32,
+
+    #[display(fmt = "wrong bytes of i64")]
+    DecodeInt64,
+
+    #[display(fmt = "wrong bytes of i128")]
+    DecodeInt128,
+
+    #[display(fmt = "wrong bytes of array, expect {} real {}", expect, real)]
+    DecodeByteArray {
+        expect: usize,
+        real:   usize,
+    },
+
+    #[display(fmt = "wrong bytes of option")]
+    DecodeOption,
+
+    #[display(fmt = "wrong length prefix of item")]
+    DecodeLength,
 }
 
 impl Error for FixedCodecError {}
@@ -4,7 +4,8 @@ use byteorder::{ByteOrder, LittleEndian};
 use bytes::{Bytes, BytesMut};
 
 use crate::fixed_codec::{FixedCodec, FixedCodecError};
-use crate::types::{Address, Hash, Hex, Metadata, ValidatorExtend};
+use crate::types::primitive::encode_hex_digits;
+use crate::types::{Address, ChainSpec, FeatureActivation, Hash, Hex, Metadata, ValidatorExtend};
 use crate::{impl_default_fixed_codec_for, ProtocolResult};
 
 // Impl FixedCodec trait for types
@@ -44,32 +45,174 @@ impl FixedCodec for u8 {
     }
 }
 
-impl FixedCodec for u32 {
-    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
-        let mut buf = [0u8; mem::size_of::<u32>()];
-        LittleEndian::write_u32(&mut buf, *self);
+// Integers are encoded in little endian with their full width, e.g. 1u16 is
+// `[0x01, 0x00]` and -2i32 is `[0xfe, 0xff, 0xff, 0xff]`. Decoding expects
+// exactly that many bytes.
+macro_rules! impl_fixed_codec_for_int {
+    ($($type:ident => $err:ident),+) => (
+        $(
+            impl FixedCodec for $type {
+                fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+                    Ok(BytesMut::from(self.to_le_bytes().as_ref()).freeze())
+                }
+
+                fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+                    let mut buf = [0u8; mem::size_of::<$type>()];
+                    if bytes.len() != buf.len() {
+                        return Err(FixedCodecError::$err.into());
+                    }
+
+                    buf.copy_from_slice(bytes.as_ref());
+                    Ok($type::from_le_bytes(buf))
+                }
+            }
+        )+
+    )
+}
+
+impl_fixed_codec_for_int!(
+    u16 => DecodeUint16,
+    u32 => DecodeUint32,
+    u64 => DecodeUint64,
+    u128 => DecodeUint128,
+    i8 => DecodeInt8,
+    i16 => DecodeInt16,
+    i32 => DecodeInt32,
+    i64 => DecodeInt64,
+    i128 => DecodeInt128
+);
+
+// Byte arrays are encoded as they are, without length. Decoding expects
+// exactly that many bytes.
+macro_rules! impl_fixed_codec_for_byte_array {
+    ($($len:expr),+) => (
+        $(
+            impl FixedCodec for [u8; $len] {
+                fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+                    Ok(BytesMut::from(self.as_ref()).freeze())
+                }
+
+                fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+                    if bytes.len() != $len {
+                        return Err(FixedCodecError::DecodeByteArray {
+                            expect: $len,
+                            real:   bytes.len(),
+                        }
+                        .into());
+                    }
+
+                    let mut buf = [0u8; $len];
+                    buf.copy_from_slice(bytes.as_ref());
+                    Ok(buf)
+                }
+            }
+        )+
+    )
+}
+
+impl_fixed_codec_for_byte_array!(1, 2, 4, 8, 16, 20, 32, 48, 64, 65, 96, 128);
 
-        Ok(BytesMut::from(buf.as_ref()).freeze())
+/// `None` is encoded as `[0x00]`, `Some(v)` as `0x01` followed by the encoded
+/// `v`.
+impl<T: FixedCodec> FixedCodec for Option<T> {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        match self {
+            None => Ok(BytesMut::from([0u8].as_ref()).freeze()),
+            Some(v) => {
+                let inner = v.encode_fixed()?;
+                let mut buf = BytesMut::with_capacity(1 + inner.len());
+                buf.extend_from_slice(&[1u8]);
+                buf.extend_from_slice(&inner);
+                Ok(buf.freeze())
+            }
+        }
     }
 
     fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
-        Ok(LittleEndian::read_u32(bytes.as_ref()))
+        match bytes.get(0).cloned() {
+            Some(0) if bytes.len() == 1 => Ok(None),
+            Some(1) => Ok(Some(T::decode_fixed(bytes.slice(1..))?)),
+            _ => Err(FixedCodecError::DecodeOption.into()),
+        }
     }
 }
 
-impl FixedCodec for u64 {
+/// Items are encoded one by one, each one is prefixed with its length in
+/// u32 little endian. An empty vec is encoded as empty bytes.
+impl<T: FixedCodec> FixedCodec for Vec<T> {
     fn encode_fixed(&self) -> ProtocolResult<Bytes> {
-        let mut buf = [0u8; mem::size_of::<u64>()];
-        LittleEndian::write_u64(&mut buf, *self);
+        let mut buf = BytesMut::new();
+        for item in self.iter() {
+            put_item(&mut buf, item)?;
+        }
 
-        Ok(BytesMut::from(buf.as_ref()).freeze())
+        Ok(buf.freeze())
     }
 
-    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
-        Ok(LittleEndian::read_u64(bytes.as_ref()))
+    fn decode_fixed(mut bytes: Bytes) -> ProtocolResult<Self> {
+        let mut items = vec![];
+        while !bytes.is_empty() {
+            items.push(take_item(&mut bytes)?);
+        }
+
+        Ok(items)
     }
 }
 
+// Tuples are encoded as a vec of their fields, every field is prefixed with
+// its length in u32 little endian. Decoding expects no trailing bytes.
+macro_rules! impl_fixed_codec_for_tuple {
+    ($(($($name:ident),+)),+) => (
+        $(
+            impl<$($name: FixedCodec),+> FixedCodec for ($($name,)+) {
+                #[allow(non_snake_case)]
+                fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+                    let ($($name,)+) = self;
+                    let mut buf = BytesMut::new();
+                    $(put_item(&mut buf, $name)?;)+
+
+                    Ok(buf.freeze())
+                }
+
+                fn decode_fixed(mut bytes: Bytes) -> ProtocolResult<Self> {
+                    let tuple = ($(take_item::<$name>(&mut bytes)?,)+);
+                    if !bytes.is_empty() {
+                        return Err(FixedCodecError::DecodeLength.into());
+                    }
+
+                    Ok(tuple)
+                }
+            }
+        )+
+    )
+}
+
+impl_fixed_codec_for_tuple!((A, B), (A, B, C));
+
+fn put_item<T: FixedCodec>(buf: &mut BytesMut, item: &T) -> ProtocolResult<()> {
+    let bytes = item.encode_fixed()?;
+    let mut len = [0u8; mem::size_of::<u32>()];
+    LittleEndian::write_u32(&mut len, bytes.len() as u32);
+
+    buf.extend_from_slice(&len);
+    buf.extend_from_slice(&bytes);
+    Ok(())
+}
+
+fn take_item<T: FixedCodec>(bytes: &mut Bytes) -> ProtocolResult<T> {
+    let len_size = mem::size_of::<u32>();
+    if bytes.len() < len_size {
+        return Err(FixedCodecError::DecodeLength.into());
+    }
+    let len = LittleEndian::read_u32(&bytes[..len_size]) as usize;
+    if bytes.len() - len_size < len {
+        return Err(FixedCodecError::DecodeLength.into());
+    }
+
+    let _ = bytes.split_to(len_size);
+    T::decode_fixed(bytes.split_to(len))
+}
+
 impl FixedCodec for String {
     fn encode_fixed(&self) -> ProtocolResult<Bytes> {
         Ok(Bytes::from(self.clone()))
@@ -82,15 +225,31 @@ impl FixedCodec for String {
 
 impl rlp::Encodable for Hex {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
-        s.begin_list(1).append(&self.as_string_trim0x());
+        // Encoded as a string of hex digits without 0x, as they're constructed
+        // if they're not lowercase. Short ones are written on the stack.
+        if let Some(digits) = self.kept_digits() {
+            s.begin_list(1).append(&digits);
+            return;
+        }
+
+        let bytes = self.as_bytes();
+        let mut stack_buf = [0u8; 128];
+        let mut heap_buf = Vec::new();
+        let buf = if bytes.len() * 2 <= stack_buf.len() {
+            &mut stack_buf[..]
+        } else {
+            heap_buf.resize(bytes.len() * 2, 0);
+            &mut heap_buf[..]
+        };
+
+        s.begin_list(1).append(&encode_hex_digits(&bytes, buf));
     }
 }
 
 impl rlp::Decodable for Hex {
     fn decode(r: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
-        let s: String = r.at(0)?.as_val()?;
-
-        Hex::from_string("0x".to_owned() + s.as_str())
+        let digits = r.at(0)?.data()?;
+        Hex::from_digits(digits)
             .map_err(|_| rlp::DecoderError::Custom("decode hex from string error"))
     }
 }
@@ -135,9 +294,60 @@ impl rlp::Decodable for Address {
     }
 }
 
+// Encoding is versioned by its length, fields added later are appended up to
+// the last group of them set. Metadata without them keeps the encoding of old
+// nodes, so do genesis state roots of existing chains.
+//   13 items: legacy
+//   16 items: with fee distribution
+//   18 items: with domain separation
+//   19 items: with block size limit
+//   20 items: with base cycles of transactions
+//   23 items: with storage deposit and rent
+//   24 items: with emergency halt
+//   28 items: with upgrade signaling
+//   29 items: with feature activations, once the chain spec schedules any
+const METADATA_LENS: [usize; 9] = [13, 16, 18, 19, 20, 23, 24, 28, 29];
+
+fn metadata_len(m: &Metadata) -> usize {
+    let versions = [
+        (29, !m.chain_spec.activations.is_empty()),
+        (
+            28,
+            !m.upgrade_name.is_empty()
+                || m.upgrade_window != 0
+                || m.upgrade_threshold != 0
+                || m.upgrade_height != 0,
+        ),
+        (24, m.halt_height != 0),
+        (
+            23,
+            m.storage_deposit_per_byte != 0 || m.rent_epoch != 0 || m.rent_per_byte != 0,
+        ),
+        (20, m.tx_base_cycles != 0),
+        (19, m.max_block_size != 0),
+        (
+            18,
+            !m.network_tag.is_empty() || m.domain_separation_height != 0,
+        ),
+        (
+            16,
+            m.proposer_fee_ratio != 0
+                || m.treasury_fee_ratio != 0
+                || m.treasury != Address::default(),
+        ),
+    ];
+
+    versions
+        .iter()
+        .find(|(_, set)| *set)
+        .map(|(len, _)| *len)
+        .unwrap_or(13)
+}
+
 impl rlp::Encodable for Metadata {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
-        s.begin_list(13)
+        let len = metadata_len(self);
+        s.begin_list(len)
             .append(&self.chain_id)
             .append(&self.common_ref)
             .append(&self.timeout_gap)
@@ -151,11 +361,47 @@ impl rlp::Encodable for Metadata {
             .append(&self.brake_ratio)
             .append(&self.tx_num_limit)
             .append(&self.max_tx_size);
+        if len > 13 {
+            s.append(&self.proposer_fee_ratio)
+                .append(&self.treasury_fee_ratio)
+                .append(&self.treasury);
+        }
+        if len > 16 {
+            s.append(&self.network_tag)
+                .append(&self.domain_separation_height);
+        }
+        if len > 18 {
+            s.append(&self.max_block_size);
+        }
+        if len > 19 {
+            s.append(&self.tx_base_cycles);
+        }
+        if len > 20 {
+            s.append(&self.storage_deposit_per_byte)
+                .append(&self.rent_epoch)
+                .append(&self.rent_per_byte);
+        }
+        if len > 23 {
+            s.append(&self.halt_height);
+        }
+        if len > 24 {
+            s.append(&self.upgrade_name)
+                .append(&self.upgrade_window)
+                .append(&self.upgrade_threshold)
+                .append(&self.upgrade_height);
+        }
+        if len > 28 {
+            s.append_list(&self.chain_spec.activations);
+        }
     }
 }
 
 impl rlp::Decodable for Metadata {
     fn decode(r: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        if !r.is_list() || !METADATA_LENS.contains(&r.item_count()?) {
+            return Err(rlp::DecoderError::RlpIncorrectListLen);
+        }
+
         let chain_id: Hash = r.at(0)?.as_val()?;
         let common_ref: Hex = r.at(1)?.as_val()?;
         let timeout_gap: u64 = r.at(2)?.as_val()?;
@@ -170,7 +416,79 @@ impl rlp::Decodable for Metadata {
         let tx_num_limit: u64 = r.at(11)?.as_val()?;
         let max_tx_size: u64 = r.at(12)?.as_val()?;
 
-        Ok(Self {
+        // Metadata encoded before fee distribution
+        let (proposer_fee_ratio, treasury_fee_ratio, treasury) = if r.item_count()? > 13 {
+            (
+                r.at(13)?.as_val()?,
+                r.at(14)?.as_val()?,
+                r.at(15)?.as_val()?,
+            )
+        } else {
+            (0, 0, Address::default())
+        };
+
+        // Metadata encoded before domain separation
+        let (network_tag, domain_separation_height) = if r.item_count()? > 16 {
+            (r.at(16)?.as_val()?, r.at(17)?.as_val()?)
+        } else {
+            (String::new(), 0)
+        };
+
+        // Metadata encoded before block size limit
+        let max_block_size = if r.item_count()? > 18 {
+            r.at(18)?.as_val()?
+        } else {
+            0
+        };
+
+        // Metadata encoded before base cycles of transactions
+        let tx_base_cycles = if r.item_count()? > 19 {
+            r.at(19)?.as_val()?
+        } else {
+            0
+        };
+
+        // Metadata encoded before storage deposit and rent
+        let (storage_deposit_per_byte, rent_epoch, rent_per_byte) = if r.item_count()? > 20 {
+            (
+                r.at(20)?.as_val()?,
+                r.at(21)?.as_val()?,
+                r.at(22)?.as_val()?,
+            )
+        } else {
+            (0, 0, 0)
+        };
+
+        // Metadata encoded before emergency halt
+        let halt_height = if r.item_count()? > 23 {
+            r.at(23)?.as_val()?
+        } else {
+            0
+        };
+
+        // Metadata encoded before upgrade signaling
+        let (upgrade_name, upgrade_window, upgrade_threshold, upgrade_height) =
+            if r.item_count()? > 24 {
+                (
+                    r.at(24)?.as_val()?,
+                    r.at(25)?.as_val()?,
+                    r.at(26)?.as_val()?,
+                    r.at(27)?.as_val()?,
+                )
+            } else {
+                (String::new(), 0, 0, 0)
+            };
+
+        // Metadata encoded before feature activations
+        let chain_spec = if r.item_count()? > 28 {
+            ChainSpec {
+                activations: r.at(28)?.as_list()?,
+            }
+        } else {
+            ChainSpec::default()
+        };
+
+        let metadata = Self {
             chain_id,
             common_ref,
             timeout_gap,
@@ -184,7 +502,30 @@ impl rlp::Decodable for Metadata {
             brake_ratio,
             tx_num_limit,
             max_tx_size,
-        })
+            proposer_fee_ratio,
+            treasury_fee_ratio,
+            treasury,
+            network_tag,
+            domain_separation_height,
+            max_block_size,
+            tx_base_cycles,
+            storage_deposit_per_byte,
+            rent_epoch,
+            rent_per_byte,
+            halt_height,
+            upgrade_name,
+            upgrade_window,
+            upgrade_threshold,
+            upgrade_height,
+            chain_spec,
+        };
+
+        // Trailing fields at their defaults are only encoded shorter, so that
+        // metadata has a single encoding
+        if metadata_len(&metadata) != r.item_count()? {
+            return Err(rlp::DecoderError::RlpIncorrectListLen);
+        }
+        Ok(metadata)
     }
 }
 
@@ -217,3 +558,18 @@ impl rlp::Decodable for ValidatorExtend {
         })
     }
 }
+
+impl rlp::Encodable for FeatureActivation {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(2).append(&self.name).append(&self.height);
+    }
+}
+
+impl rlp::Decodable for FeatureActivation {
+    fn decode(r: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(FeatureActivation {
+            name:   r.at(0)?.as_val()?,
+            height: r.at(1)?.as_val()?,
+        })
+    }
+}
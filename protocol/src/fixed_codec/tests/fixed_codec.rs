@@ -3,7 +3,8 @@ extern crate test;
 use test::Bencher;
 
 use crate::fixed_codec::FixedCodec;
-use crate::types;
+use crate::types::{self, upgrade_signal};
+use crate::ProtocolResult;
 
 use super::*;
 
@@ -43,6 +44,55 @@ fn test_fixed_codec_primitive() {
     );
 }
 
+#[test]
+fn test_fixed_codec_layouts() {
+    let encoded = |bytes: ProtocolResult<Bytes>| bytes.unwrap().to_vec();
+
+    assert_eq!(encoded(1u16.encode_fixed()), vec![1, 0]);
+    assert_eq!(encoded((-2i32).encode_fixed()), vec![
+        0xfe, 0xff, 0xff, 0xff
+    ]);
+    assert_eq!(encoded(1u128.encode_fixed()).len(), 16);
+    assert_eq!(encoded([7u8; 20].encode_fixed()), vec![7u8; 20]);
+
+    assert_eq!(encoded(None::<u16>.encode_fixed()), vec![0]);
+    assert_eq!(encoded(Some(1u16).encode_fixed()), vec![1, 1, 0]);
+
+    assert_eq!(encoded(Vec::<u16>::new().encode_fixed()), Vec::<u8>::new());
+    assert_eq!(encoded(vec![1u16, 2].encode_fixed()), vec![
+        2, 0, 0, 0, 1, 0, 2, 0, 0, 0, 2, 0
+    ]);
+    assert_eq!(encoded((1u8, "a".to_owned()).encode_fixed()), vec![
+        1, 0, 0, 0, 1, 1, 0, 0, 0, b'a'
+    ]);
+
+    let bs = (1u16, [2u8; 32], Some(-3i64)).encode_fixed().unwrap();
+    assert_eq!(
+        <(u16, [u8; 32], Option<i64>)>::decode_fixed(bs).unwrap(),
+        (1u16, [2u8; 32], Some(-3i64))
+    );
+
+    assert!(<[u8; 32]>::decode_fixed(Bytes::from(vec![0u8; 31])).is_err());
+    assert!(<Option<u16>>::decode_fixed(Bytes::from(vec![2u8, 1, 0])).is_err());
+    assert!(<Vec<u16>>::decode_fixed(Bytes::from(vec![2u8, 0, 0, 0, 1])).is_err());
+    assert!(<(u8, u8)>::decode_fixed(Bytes::from(vec![1u8, 0, 0, 0, 1])).is_err());
+}
+
+#[test]
+fn test_fixed_codec_hex_digits() {
+    // Encoded as strings of digits without 0x, digits not in lowercase are
+    // encoded as they're constructed
+    for digits in &["01abff", "01ABff", ""] {
+        let hex = types::Hex::from_string(format!("0x{}", digits)).unwrap();
+        let mut stream = rlp::RlpStream::new_list(1);
+        stream.append(&digits.to_string());
+        assert_eq!(rlp::encode(&hex), stream.out());
+
+        let decoded: types::Hex = rlp::decode(&rlp::encode(&hex)).unwrap();
+        assert_eq!(decoded.as_string_trim0x(), *digits);
+    }
+}
+
 #[test]
 fn test_fixed_codec() {
     test_eq!(primitive, Hash, mock_hash);
@@ -55,6 +105,7 @@ fn test_fixed_codec() {
     test_eq!(block, Block, mock_block, 33);
     test_eq!(block, Pill, mock_pill, 22, 33);
     test_eq!(block, Validator, mock_validator);
+    test_eq!(block, VoteExtension, mock_vote_extension);
 
     test_eq!(receipt, Receipt, mock_receipt);
     test_eq!(receipt, Receipt, mock_receipt);
@@ -62,6 +113,171 @@ fn test_fixed_codec() {
     test_eq!(receipt, Receipt, mock_receipt);
 }
 
+#[test]
+fn test_fixed_codec_raw_tx_versions() {
+    let legacy = mock_raw_tx();
+    let legacy_len = rlp::Rlp::new(&legacy.encode_fixed().unwrap())
+        .item_count()
+        .unwrap();
+    assert_eq!(legacy_len, 8);
+
+    let mut expiring = legacy.clone();
+    expiring.valid_until_timestamp = Some(1_600_000_000_000);
+    let bytes = expiring.encode_fixed().unwrap();
+    assert_eq!(RawTransaction::decode_fixed(bytes).unwrap(), expiring);
+
+    let mut sent_for = expiring.clone();
+    sent_for.sender = Some(mock_address());
+    let bytes = sent_for.encode_fixed().unwrap();
+    assert_eq!(RawTransaction::decode_fixed(bytes).unwrap(), sent_for);
+
+    sent_for.valid_until_timestamp = None;
+    let bytes = sent_for.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(&bytes).item_count().unwrap(), 9);
+    assert_eq!(RawTransaction::decode_fixed(bytes).unwrap(), sent_for);
+
+    // Empty sender would give the legacy transaction another hash
+    let bytes = legacy.encode_fixed().unwrap();
+    let items = rlp::Rlp::new(&bytes);
+    let mut s = rlp::RlpStream::new_list(9);
+    for i in 0..8 {
+        s.append_raw(items.at(i).unwrap().as_raw(), 1);
+    }
+    s.begin_list(0);
+    assert!(RawTransaction::decode_fixed(Bytes::from(s.out())).is_err());
+
+    let mut s = rlp::RlpStream::new_list(11);
+    for i in 0..8 {
+        s.append_raw(items.at(i).unwrap().as_raw(), 1);
+    }
+    s.begin_list(0).append(&1u64).append(&1u64);
+    assert!(RawTransaction::decode_fixed(Bytes::from(s.out())).is_err());
+
+    let mut s = rlp::RlpStream::new_list(7);
+    for i in 0..7 {
+        s.append_raw(items.at(i).unwrap().as_raw(), 1);
+    }
+    assert!(RawTransaction::decode_fixed(Bytes::from(s.out())).is_err());
+}
+
+#[test]
+fn test_fixed_codec_block_versions() {
+    let legacy = mock_block(3);
+    let bytes = legacy.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(&bytes).item_count().unwrap(), 2);
+    assert_eq!(types::block::Block::decode_fixed(bytes).unwrap(), legacy);
+
+    let mut extended = legacy.clone();
+    extended.vote_extensions = vec![mock_vote_extension(), mock_vote_extension()];
+    let bytes = extended.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(&bytes).item_count().unwrap(), 3);
+    assert_eq!(types::block::Block::decode_fixed(bytes).unwrap(), extended);
+}
+
+#[test]
+fn test_fixed_codec_metadata_versions() {
+    let legacy = types::Metadata {
+        chain_id: Hash::from_bytes(Bytes::from(vec![1u8; 32])).unwrap(),
+        common_ref: types::Hex::from_string("0x01ab".to_owned()).unwrap(),
+        timeout_gap: 20,
+        cycles_limit: 999_999_999_999,
+        cycles_price: 1,
+        interval: 3000,
+        propose_ratio: 15,
+        prevote_ratio: 10,
+        precommit_ratio: 10,
+        brake_ratio: 7,
+        tx_num_limit: 20000,
+        max_tx_size: 1024,
+        ..Default::default()
+    };
+
+    // Bytes of genesis metadata of existing chains
+    let bytes = legacy.encode_fixed().unwrap();
+    assert_eq!(
+        hex::encode(&bytes),
+        "f83ee1a00101010101010101010101010101010101010101010101010101010101010101c58430316162\
+         1485e8d4a50fff01820bb8c00f0a0a07824e20820400"
+    );
+    assert_eq!(types::Metadata::decode_fixed(bytes).unwrap(), legacy);
+
+    // Appended up to the last group set
+    let mut halting = legacy.clone();
+    halting.halt_height = 100;
+    let bytes = halting.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(&bytes).item_count().unwrap(), 24);
+    assert_eq!(types::Metadata::decode_fixed(bytes).unwrap(), halting);
+
+    let mut scheduled = legacy.clone();
+    scheduled.chain_spec.schedule("tx_cycles", 10);
+    let bytes = scheduled.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(&bytes).item_count().unwrap(), 29);
+    assert_eq!(types::Metadata::decode_fixed(bytes).unwrap(), scheduled);
+
+    // Trailing defaults are never encoded
+    let mut stream = rlp::RlpStream::new_list(16);
+    for item in rlp::Rlp::new(&legacy.encode_fixed().unwrap()).iter() {
+        stream.append_raw(item.as_raw(), 1);
+    }
+    stream
+        .append(&0u64)
+        .append(&0u64)
+        .append(&Address::default());
+    assert!(types::Metadata::decode_fixed(Bytes::from(stream.out())).is_err());
+}
+
+#[test]
+fn test_fixed_codec_vote_extension_rejects_wrong_len() {
+    let extension = mock_vote_extension();
+    let bytes = extension.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(&bytes).item_count().unwrap(), 5);
+
+    // A list missing the block hash was decoded from the wrong items
+    let mut s = rlp::RlpStream::new_list(4);
+    s.append(&extension.height)
+        .append(&extension.voter)
+        .append(&extension.payload.to_vec())
+        .append(&extension.signature.to_vec());
+    assert!(types::block::VoteExtension::decode_fixed(Bytes::from(s.out())).is_err());
+
+    let mut s = rlp::RlpStream::new_list(4);
+    s.append(&mock_block_header())
+        .append_list(&vec![mock_hash()])
+        .append_list(&vec![extension])
+        .append(&0u64);
+    assert!(types::block::Block::decode_fixed(Bytes::from(s.out())).is_err());
+}
+
+#[test]
+fn test_fixed_codec_block_header_versions() {
+    let legacy = mock_block_header();
+    let bytes = legacy.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(&bytes).item_count().unwrap(), 15);
+    assert_eq!(BlockHeader::decode_fixed(bytes).unwrap(), legacy);
+
+    let mut signaling = legacy.clone();
+    signaling.extra_data = upgrade_signal("fork_1");
+    let bytes = signaling.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(&bytes).item_count().unwrap(), 16);
+    assert_eq!(BlockHeader::decode_fixed(bytes).unwrap(), signaling);
+
+    // Empty extra data would give the legacy header another hash
+    let bytes = legacy.encode_fixed().unwrap();
+    let items = rlp::Rlp::new(&bytes);
+    let mut s = rlp::RlpStream::new_list(16);
+    for i in 0..15 {
+        s.append_raw(items.at(i).unwrap().as_raw(), 1);
+    }
+    s.append_empty_data();
+    assert!(BlockHeader::decode_fixed(Bytes::from(s.out())).is_err());
+
+    let mut s = rlp::RlpStream::new_list(14);
+    for i in 0..14 {
+        s.append_raw(items.at(i).unwrap().as_raw(), 1);
+    }
+    assert!(BlockHeader::decode_fixed(Bytes::from(s.out())).is_err());
+}
+
 #[test]
 fn test_signed_tx_serialize_size() {
     let txs: Vec<Bytes> = (0..50_000)
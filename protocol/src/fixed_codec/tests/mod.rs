@@ -1,11 +1,12 @@
 mod fixed_codec;
+mod round_trip;
 
 use bytes::Bytes;
 use num_traits::FromPrimitive;
 use rand::random;
 
 use crate::traits::ServiceResponse;
-use crate::types::block::{Block, BlockHeader, Pill, Proof, Validator};
+use crate::types::block::{Block, BlockHeader, Pill, Proof, Validator, VoteExtension};
 use crate::types::primitive::{Address, Balance, Hash, MerkleRoot};
 use crate::types::receipt::{Event, Receipt, ReceiptResponse};
 use crate::types::transaction::{RawTransaction, SignedTransaction, TransactionRequest};
@@ -79,12 +80,14 @@ pub fn mock_transaction_request() -> TransactionRequest {
 
 pub fn mock_raw_tx() -> RawTransaction {
     RawTransaction {
-        chain_id:     mock_hash(),
-        nonce:        mock_hash(),
-        timeout:      100,
-        cycles_price: 1,
-        cycles_limit: 100,
-        request:      mock_transaction_request(),
+        chain_id:              mock_hash(),
+        nonce:                 mock_hash(),
+        timeout:               100,
+        cycles_price:          1,
+        cycles_limit:          100,
+        request:               mock_transaction_request(),
+        sender:                None,
+        valid_until_timestamp: None,
     }
 }
 
@@ -141,6 +144,7 @@ pub fn mock_block_header() -> BlockHeader {
             mock_validator(),
             mock_validator(),
         ],
+        extra_data:        Bytes::new(),
     }
 }
 
@@ -148,6 +152,17 @@ pub fn mock_block(order_size: usize) -> Block {
     Block {
         header:            mock_block_header(),
         ordered_tx_hashes: (0..order_size).map(|_| mock_hash()).collect(),
+        vote_extensions:   vec![],
+    }
+}
+
+pub fn mock_vote_extension() -> VoteExtension {
+    VoteExtension {
+        height:     41,
+        voter:      mock_address(),
+        block_hash: mock_hash(),
+        payload:    Bytes::from("price 42"),
+        signature:  get_random_bytes(96),
     }
 }
 
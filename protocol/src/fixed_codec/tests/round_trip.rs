@@ -0,0 +1,365 @@
+use bytes::Bytes;
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+
+use crate::fixed_codec::FixedCodec;
+use crate::types::{
+    Address, Block, BlockHeader, Bloom, ChainSpec, Event, FeatureActivation, Hash, Hex, Metadata,
+    Pill, Proof, RawTransaction, Receipt, ReceiptResponse, SignedTransaction, TransactionRequest,
+    Validator, ValidatorExtend, VoteExtension,
+};
+
+// #####################
+// Strategy
+// #####################
+
+pub fn arb_bytes() -> impl Strategy<Value = Bytes> {
+    vec(any::<u8>(), 0..128).prop_map(Bytes::from)
+}
+
+pub fn arb_hash() -> impl Strategy<Value = Hash> {
+    any::<[u8; 32]>().prop_map(|bytes| Hash::from_bytes(Bytes::from(bytes.to_vec())).unwrap())
+}
+
+pub fn arb_address() -> impl Strategy<Value = Address> {
+    any::<[u8; 20]>().prop_map(|bytes| Address::from_bytes(Bytes::from(bytes.to_vec())).unwrap())
+}
+
+pub fn arb_hex() -> impl Strategy<Value = Hex> {
+    vec(any::<u8>(), 0..64)
+        .prop_map(|bytes| Hex::from_string("0x".to_owned() + &hex::encode(bytes)).unwrap())
+}
+
+pub fn arb_validator_extend() -> impl Strategy<Value = ValidatorExtend> {
+    (arb_hex(), arb_address(), any::<u32>(), any::<u32>()).prop_map(
+        |(bls_pub_key, address, propose_weight, vote_weight)| ValidatorExtend {
+            bls_pub_key,
+            address,
+            propose_weight,
+            vote_weight,
+        },
+    )
+}
+
+pub fn arb_feature_activation() -> impl Strategy<Value = FeatureActivation> {
+    (".*", any::<u64>()).prop_map(|(name, height)| FeatureActivation { name, height })
+}
+
+pub fn arb_metadata() -> impl Strategy<Value = Metadata> {
+    (
+        (arb_hash(), arb_hex(), vec(arb_validator_extend(), 0..4)),
+        any::<[u64; 22]>(),
+        (arb_address(), ".*", ".*"),
+        vec(arb_feature_activation(), 0..4),
+    )
+        .prop_map(
+            |(
+                (chain_id, common_ref, verifier_list),
+                n,
+                (treasury, network_tag, upgrade_name),
+                activations,
+            )| {
+                Metadata {
+                    chain_id,
+                    common_ref,
+                    timeout_gap: n[0],
+                    cycles_limit: n[1],
+                    cycles_price: n[2],
+                    interval: n[3],
+                    verifier_list,
+                    propose_ratio: n[4],
+                    prevote_ratio: n[5],
+                    precommit_ratio: n[6],
+                    brake_ratio: n[7],
+                    tx_num_limit: n[8],
+                    max_tx_size: n[9],
+                    proposer_fee_ratio: n[10],
+                    treasury_fee_ratio: n[11],
+                    treasury,
+                    network_tag,
+                    domain_separation_height: n[12],
+                    max_block_size: n[13],
+                    tx_base_cycles: n[14],
+                    storage_deposit_per_byte: n[15],
+                    rent_epoch: n[16],
+                    rent_per_byte: n[17],
+                    halt_height: n[18],
+                    upgrade_name,
+                    upgrade_window: n[19],
+                    upgrade_threshold: n[20],
+                    upgrade_height: n[21],
+                    chain_spec: ChainSpec { activations },
+                }
+            },
+        )
+}
+
+pub fn arb_raw_tx() -> impl Strategy<Value = RawTransaction> {
+    (
+        (arb_hash(), arb_hash(), any::<[u64; 3]>()),
+        (".*", ".*", ".*"),
+        (option::of(arb_address()), option::of(any::<u64>())),
+    )
+        .prop_map(
+            |((chain_id, nonce, n), (service_name, method, payload), (sender, valid_until))| {
+                RawTransaction {
+                    chain_id,
+                    nonce,
+                    timeout: n[0],
+                    cycles_price: n[1],
+                    cycles_limit: n[2],
+                    request: TransactionRequest {
+                        service_name,
+                        method,
+                        payload,
+                    },
+                    sender,
+                    valid_until_timestamp: valid_until,
+                }
+            },
+        )
+}
+
+pub fn arb_signed_tx() -> impl Strategy<Value = SignedTransaction> {
+    (arb_raw_tx(), arb_hash(), arb_bytes(), arb_bytes()).prop_map(
+        |(raw, tx_hash, pubkey, signature)| SignedTransaction {
+            raw,
+            tx_hash,
+            pubkey,
+            signature,
+        },
+    )
+}
+
+pub fn arb_proof() -> impl Strategy<Value = Proof> {
+    (
+        any::<u64>(),
+        any::<u64>(),
+        arb_hash(),
+        arb_bytes(),
+        arb_bytes(),
+    )
+        .prop_map(|(height, round, block_hash, signature, bitmap)| Proof {
+            height,
+            round,
+            block_hash,
+            signature,
+            bitmap,
+        })
+}
+
+pub fn arb_validator() -> impl Strategy<Value = Validator> {
+    (arb_address(), any::<u32>(), any::<u32>()).prop_map(
+        |(address, propose_weight, vote_weight)| Validator {
+            address,
+            propose_weight,
+            vote_weight,
+        },
+    )
+}
+
+pub fn arb_bloom() -> impl Strategy<Value = Bloom> {
+    vec(any::<u8>(), 256).prop_map(|bytes| Bloom::from_slice(&bytes))
+}
+
+pub fn arb_block_header() -> impl Strategy<Value = BlockHeader> {
+    (
+        (
+            arb_hash(),
+            arb_hash(),
+            arb_hash(),
+            arb_hash(),
+            arb_address(),
+        ),
+        any::<[u64; 4]>(),
+        (
+            vec(arb_bloom(), 0..2),
+            vec(arb_hash(), 0..4),
+            vec(arb_hash(), 0..4),
+            vec(any::<u64>(), 0..4),
+        ),
+        (arb_proof(), vec(arb_validator(), 0..4), arb_bytes()),
+    )
+        .prop_map(
+            |(
+                (chain_id, pre_hash, order_root, state_root, proposer),
+                n,
+                (logs_bloom, confirm_root, receipt_root, cycles_used),
+                (proof, validators, extra_data),
+            )| BlockHeader {
+                chain_id,
+                height: n[0],
+                exec_height: n[1],
+                pre_hash,
+                timestamp: n[2],
+                logs_bloom,
+                order_root,
+                confirm_root,
+                state_root,
+                receipt_root,
+                cycles_used,
+                proposer,
+                proof,
+                validator_version: n[3],
+                validators,
+                extra_data,
+            },
+        )
+}
+
+pub fn arb_vote_extension() -> impl Strategy<Value = VoteExtension> {
+    (
+        any::<u64>(),
+        arb_address(),
+        arb_hash(),
+        arb_bytes(),
+        arb_bytes(),
+    )
+        .prop_map(
+            |(height, voter, block_hash, payload, signature)| VoteExtension {
+                height,
+                voter,
+                block_hash,
+                payload,
+                signature,
+            },
+        )
+}
+
+pub fn arb_block() -> impl Strategy<Value = Block> {
+    (
+        arb_block_header(),
+        vec(arb_hash(), 0..8),
+        vec(arb_vote_extension(), 0..4),
+    )
+        .prop_map(|(header, ordered_tx_hashes, vote_extensions)| Block {
+            header,
+            ordered_tx_hashes,
+            vote_extensions,
+        })
+}
+
+pub fn arb_pill() -> impl Strategy<Value = Pill> {
+    (arb_block(), vec(arb_hash(), 0..8)).prop_map(|(block, propose_hashes)| Pill {
+        block,
+        propose_hashes,
+    })
+}
+
+// #####################
+// Round trip
+// #####################
+
+macro_rules! assert_round_trip {
+    ($type: ty, $val: expr) => {
+        let val = $val;
+
+        let bytes = val.encode_fixed().unwrap();
+        prop_assert_eq!(&<$type as FixedCodec>::decode_fixed(bytes).unwrap(), &val);
+
+        let bytes = rlp::encode(&val);
+        prop_assert_eq!(&rlp::decode::<$type>(&bytes).unwrap(), &val);
+    };
+}
+
+proptest! {
+    #[test]
+    fn prop_hash_round_trip(hash in arb_hash()) {
+        assert_round_trip!(Hash, hash);
+    }
+
+    #[test]
+    fn prop_address_round_trip(address in arb_address()) {
+        assert_round_trip!(Address, address);
+    }
+
+    #[test]
+    fn prop_hex_round_trip(hex in arb_hex()) {
+        assert_round_trip!(Hex, hex);
+    }
+
+    #[test]
+    fn prop_composite_round_trip(
+        val in (any::<i64>(), any::<[u8; 20]>(), vec(option::of(any::<u16>()), 0..8))
+    ) {
+        let bytes = val.encode_fixed().unwrap();
+        let decoded = <(i64, [u8; 20], Vec<Option<u16>>)>::decode_fixed(bytes).unwrap();
+        prop_assert_eq!(decoded, val);
+    }
+
+    #[test]
+    fn prop_validator_extend_round_trip(validator in arb_validator_extend()) {
+        let bytes = rlp::encode(&validator);
+        prop_assert_eq!(rlp::decode::<ValidatorExtend>(&bytes).unwrap(), validator);
+    }
+
+    #[test]
+    fn prop_metadata_round_trip(metadata in arb_metadata()) {
+        assert_round_trip!(Metadata, metadata);
+    }
+
+    #[test]
+    fn prop_signed_tx_round_trip(stx in arb_signed_tx()) {
+        assert_round_trip!(SignedTransaction, stx);
+    }
+
+    #[test]
+    fn prop_block_round_trip(block in arb_block()) {
+        assert_round_trip!(Block, block);
+    }
+
+    #[test]
+    fn prop_pill_round_trip(pill in arb_pill()) {
+        assert_round_trip!(Pill, pill);
+    }
+
+    // Bytes from peers must not panic decoding, errors are fine
+    #[test]
+    fn prop_decode_arbitrary_bytes(bytes in vec(any::<u8>(), 0..512)) {
+        let bytes = Bytes::from(bytes);
+
+        let _ = Hash::decode_fixed(bytes.clone());
+        let _ = Address::decode_fixed(bytes.clone());
+        let _ = Hex::decode_fixed(bytes.clone());
+        let _ = Metadata::decode_fixed(bytes.clone());
+        let _ = rlp::decode::<ValidatorExtend>(&bytes);
+        let _ = RawTransaction::decode_fixed(bytes.clone());
+        let _ = SignedTransaction::decode_fixed(bytes.clone());
+        let _ = Proof::decode_fixed(bytes.clone());
+        let _ = Validator::decode_fixed(bytes.clone());
+        let _ = BlockHeader::decode_fixed(bytes.clone());
+        let _ = Block::decode_fixed(bytes.clone());
+        let _ = VoteExtension::decode_fixed(bytes.clone());
+        let _ = Pill::decode_fixed(bytes.clone());
+        let _ = Receipt::decode_fixed(bytes.clone());
+        let _ = ReceiptResponse::decode_fixed(bytes.clone());
+        let _ = rlp::decode::<Event>(&bytes);
+        let _ = u32::decode_fixed(bytes.clone());
+        let _ = u64::decode_fixed(bytes.clone());
+        let _ = bool::decode_fixed(bytes.clone());
+        let _ = u8::decode_fixed(bytes.clone());
+        let _ = i128::decode_fixed(bytes.clone());
+        let _ = <[u8; 32]>::decode_fixed(bytes.clone());
+        let _ = <Option<u64>>::decode_fixed(bytes.clone());
+        let _ = <Vec<(u16, String)>>::decode_fixed(bytes.clone());
+        let _ = String::decode_fixed(bytes);
+    }
+
+    // Valid rlp of wrong shape reaches the inner decoders
+    #[test]
+    fn prop_decode_block_of_wrong_shape(header in arb_block_header(), cut in 0usize..15) {
+        let mut stream = rlp::RlpStream::new_list(15);
+        let raw = rlp::encode(&header);
+        let rlp = rlp::Rlp::new(&raw);
+        for i in 0..15 {
+            if i == cut {
+                stream.append(&0u64);
+            } else {
+                stream.append_raw(rlp.at(i).unwrap().as_raw(), 1);
+            }
+        }
+
+        let _ = rlp::decode::<BlockHeader>(&stream.out());
+    }
+}
@@ -10,7 +10,20 @@ impl_default_fixed_codec_for!(transaction, [RawTransaction, SignedTransaction]);
 
 impl rlp::Encodable for RawTransaction {
     fn rlp_append(&self, s: &mut rlp::RlpStream) {
-        s.begin_list(8);
+        // Encoding is versioned by its length, transactions without new
+        // fields keep their encoding and hash.
+        //   8 items: legacy
+        //   9 items: with sender
+        //  10 items: with sender, an empty list if none, and expiration
+        let size = if self.valid_until_timestamp.is_some() {
+            10
+        } else if self.sender.is_some() {
+            9
+        } else {
+            8
+        };
+
+        s.begin_list(size);
         s.append(&self.chain_id.as_bytes().to_vec());
         s.append(&self.cycles_limit);
         s.append(&self.cycles_price);
@@ -19,11 +32,30 @@ impl rlp::Encodable for RawTransaction {
         s.append(&self.request.service_name);
         s.append(&self.request.payload);
         s.append(&self.timeout);
+
+        match (&self.sender, self.valid_until_timestamp) {
+            (sender, Some(valid_until)) => {
+                match sender {
+                    Some(sender) => s.append(sender),
+                    None => s.begin_list(0),
+                };
+                s.append(&valid_until);
+            }
+            (Some(sender), None) => {
+                s.append(sender);
+            }
+            (None, None) => {}
+        }
     }
 }
 
 impl rlp::Decodable for RawTransaction {
     fn decode(r: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        if !r.is_list() || r.item_count()? < 8 || r.item_count()? > 10 {
+            return Err(rlp::DecoderError::RlpIncorrectListLen);
+        }
+        let item_count = r.item_count()?;
+
         let chain_id = Hash::from_bytes(BytesMut::from(r.at(0)?.data()?).freeze())
             .map_err(|_| rlp::DecoderError::RlpInvalidLength)?;
 
@@ -39,6 +71,20 @@ impl rlp::Decodable for RawTransaction {
             payload:      r.at(6)?.as_val()?,
         };
         let timeout = r.at(7)?.as_val()?;
+        // No sender is only encoded as an empty list before an expiration, so
+        // that a transaction has one encoding
+        let sender = if item_count > 8 && r.at(8)?.item_count()? > 0 {
+            Some(r.at(8)?.as_val()?)
+        } else if item_count == 9 {
+            return Err(rlp::DecoderError::Custom("empty sender"));
+        } else {
+            None
+        };
+        let valid_until_timestamp = if item_count > 9 {
+            Some(r.at(9)?.as_val()?)
+        } else {
+            None
+        };
 
         Ok(Self {
             chain_id,
@@ -47,6 +93,8 @@ impl rlp::Decodable for RawTransaction {
             nonce,
             request,
             timeout,
+            sender,
+            valid_until_timestamp,
         })
     }
 }
@@ -24,6 +24,8 @@ pub enum ProtocolErrorKind {
     BindingMacro,
     Service,
     Main,
+    Exporter,
+    Keystore,
 
     // codec
     Codec,
@@ -49,6 +51,18 @@ impl From<ProtocolError> for Box<dyn Error + Send> {
     }
 }
 
+impl ProtocolError {
+    pub fn kind(&self) -> &ProtocolErrorKind {
+        &self.kind
+    }
+
+    /// Returns the inner error if it's of type `E`, for example the
+    /// `RpcTimeout` returned by network.
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> {
+        self.error.downcast_ref::<E>()
+    }
+}
+
 impl Error for ProtocolError {}
 
 pub type ProtocolResult<T> = Result<T, ProtocolError>;
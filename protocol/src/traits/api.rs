@@ -1,7 +1,8 @@
 use async_trait::async_trait;
+use bytes::Bytes;
 
-use crate::traits::{Context, ServiceResponse};
-use crate::types::{Address, Block, Hash, Receipt, SignedTransaction};
+use crate::traits::{Context, PeerScore, ServiceResponse};
+use crate::types::{Address, Block, Hash, Proof, Receipt, SignedTransaction, StateProof, TxTrace};
 use crate::ProtocolResult;
 
 #[async_trait]
@@ -15,8 +16,19 @@ pub trait APIAdapter: Send + Sync {
     async fn get_block_by_height(&self, ctx: Context, height: Option<u64>)
         -> ProtocolResult<Block>;
 
+    // Aggregated precommits committing the block, they're in the header of
+    // the next block, or the latest proof for the latest block.
+    async fn get_commit_proof(&self, ctx: Context, height: u64) -> ProtocolResult<Proof>;
+
     async fn get_receipt_by_tx_hash(&self, ctx: Context, tx_hash: Hash) -> ProtocolResult<Receipt>;
 
+    // Receipts of an executed block in the order of its transactions
+    async fn get_receipts_by_height(
+        &self,
+        ctx: Context,
+        height: u64,
+    ) -> ProtocolResult<Vec<Receipt>>;
+
     async fn get_transaction_by_hash(
         &self,
         ctx: Context,
@@ -34,4 +46,48 @@ pub trait APIAdapter: Send + Sync {
         method: String,
         payload: String,
     ) -> ProtocolResult<ServiceResponse<String>>;
+
+    // Proof of a fixed encoded key in service state at state root of the
+    // block, such as packet commitments relayed to other chains.
+    async fn get_state_proof(
+        &self,
+        ctx: Context,
+        height: u64,
+        service_name: String,
+        key: Bytes,
+    ) -> ProtocolResult<StateProof>;
+
+    // Re-execute an executed transaction against the state of its block,
+    // recording its calls, storage access, events and cycles used.
+    async fn trace_transaction(&self, ctx: Context, tx_hash: Hash) -> ProtocolResult<TxTrace>;
+
+    // Seal pending transactions into a block on demand, only dev chains
+    // support it, blocks of others are sealed by consensus.
+    async fn seal_block(&self, ctx: Context) -> ProtocolResult<Block>;
+}
+
+/// The chain status checked by the readiness probe of api.
+pub trait ReadinessAdapter: Send + Sync {
+    fn current_height(&self) -> u64;
+
+    /// The highest height broadcast by the connected peers.
+    fn best_peer_height(&self) -> u64;
+
+    /// Consensus stops voting while the node is syncing blocks from peers.
+    fn is_consensus_participating(&self) -> bool;
+}
+
+/// Node internals exposed by the admin api.
+pub trait AdminAdapter: Send + Sync {
+    fn peer_scores(&self) -> Vec<PeerScore>;
+
+    /// Chain addresses of allowed peers, only they can connect in allowlist
+    /// mode.
+    fn allowlist(&self) -> Vec<String>;
+
+    /// Allow the peer presenting this secp256k1 public key in handshake.
+    fn allow_peer(&self, pubkey: Bytes) -> ProtocolResult<()>;
+
+    /// Disallow the peer, it's disconnected in allowlist mode.
+    fn disallow_peer(&self, pubkey: Bytes) -> ProtocolResult<()>;
 }
@@ -1,10 +1,14 @@
 use std::iter::Iterator;
+use std::sync::Arc;
 
 use bytes::Bytes;
 
 use crate::fixed_codec::FixedCodec;
 use crate::traits::{ExecutorParams, ServiceResponse};
-use crate::types::{Address, Block, Hash, MerkleRoot, Receipt, ServiceContext, SignedTransaction};
+use crate::types::{
+    Address, Block, CommitmentScheme, Hash, MerkleRoot, Receipt, ServiceContext, SignedTransaction,
+    VoteExtension,
+};
 use crate::ProtocolResult;
 
 pub trait ServiceMapping: Send + Sync {
@@ -59,6 +63,32 @@ pub trait ServiceState {
     fn commit(&mut self) -> ProtocolResult<MerkleRoot>;
 }
 
+/// Authenticated key value map that states of services are committed in,
+/// the root of it is the state root of blocks. Executor and proofs work
+/// against this trait, so that tries other than merkle patricia can be
+/// chosen in genesis.
+pub trait StateCommitment: Sized {
+    type DB;
+
+    const SCHEME: CommitmentScheme;
+
+    fn new(db: Arc<Self::DB>) -> Self;
+
+    fn from_root(root: MerkleRoot, db: Arc<Self::DB>) -> ProtocolResult<Self>;
+
+    fn get(&self, key: &Bytes) -> ProtocolResult<Option<Bytes>>;
+
+    fn contains(&self, key: &Bytes) -> ProtocolResult<bool>;
+
+    fn insert(&mut self, key: Bytes, value: Bytes) -> ProtocolResult<()>;
+
+    // Proof of key in committed data, it proves absence if the key doesn't
+    // exist. Proofs are verified by the scheme.
+    fn get_proof(&self, key: &Bytes) -> ProtocolResult<Vec<Bytes>>;
+
+    fn commit(&mut self) -> ProtocolResult<MerkleRoot>;
+}
+
 pub trait ChainQuerier {
     fn get_transaction_by_hash(&self, tx_hash: &Hash) -> ProtocolResult<Option<SignedTransaction>>;
 
@@ -73,6 +103,17 @@ pub trait AdmissionControl {
     fn next<SDK: ServiceSDK>(&self, ctx: ServiceContext, sdk: SDK) -> ProtocolResult<()>;
 }
 
+/// Who may call a method of a service, it's declared by `#[access]` of the
+/// binding macro and checked by the executor before the method is entered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MethodAccess {
+    Public,
+    /// Admins of the service, managed by the governance service
+    Admin,
+    /// Calls made by the named service
+    Service(String),
+}
+
 // Developers can use service to customize blockchain business
 //
 // It contains:
@@ -99,6 +140,16 @@ pub trait Service {
     fn write_(&mut self, ctx: ServiceContext) -> ServiceResponse<String>;
 
     fn read_(&self, ctx: ServiceContext) -> ServiceResponse<String>;
+
+    // Access of the method, public if not declared
+    fn access_(&self, _method: &str) -> MethodAccess {
+        MethodAccess::Public
+    }
+
+    // Max payload sizes of methods in bytes, declared by `#[max_payload_size]`
+    fn payload_limits_(&self) -> Vec<(String, u64)> {
+        vec![]
+    }
 }
 
 // `ServiceSDK` provides multiple rich interfaces for `service` developers
@@ -169,6 +220,12 @@ pub trait ServiceSDK {
     // When the parameter `height` is None, get the latest (executing)` block`
     fn get_block_by_height(&self, height: Option<u64>) -> Option<Block>;
 
+    // Get vote extensions carried by the block of `height`, they're of
+    // precommits of the previous height
+    // When the parameter `height` is None, get the ones of the latest
+    // (executing) block
+    fn get_vote_extensions(&self, height: Option<u64>) -> Vec<VoteExtension>;
+
     // Get a receipt by `tx_hash`
     // if not found on the chain, return None
     fn get_receipt_by_hash(&self, tx_hash: &Hash) -> Option<Receipt>;
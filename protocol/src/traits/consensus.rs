@@ -1,9 +1,10 @@
 use async_trait::async_trait;
 use creep::Context;
 
-use crate::traits::{ExecutorParams, ExecutorResp};
+use crate::traits::{ExecutorParams, ExecutorResp, PeerMisbehavior};
 use crate::types::{
-    Address, Block, Bytes, Hash, MerkleRoot, Metadata, Proof, Receipt, SignedTransaction, Validator,
+    Address, Block, Bytes, Hash, MerkleRoot, Metadata, Proof, Receipt, SignatureDomain,
+    SignedTransaction, Validator, VoteExtension,
 };
 use crate::{traits::mempool::MixedTxHashes, ProtocolResult};
 
@@ -32,6 +33,25 @@ pub trait Consensus: Send + Sync {
 
     /// Network set a received signed choke to consensus.
     async fn set_choke(&self, ctx: Context, choke: Vec<u8>) -> ProtocolResult<()>;
+
+    /// Network set a received vote extension to consensus.
+    async fn set_vote_extension(&self, ctx: Context, extension: Vec<u8>) -> ProtocolResult<()>;
+}
+
+/// Provides payloads validators attach to their precommits, such as price
+/// observations of oracles. It's called once a height, keep it quick since
+/// it's on the path of voting.
+#[async_trait]
+pub trait VoteExtender: Send + Sync {
+    /// Payload of the height, None to attach nothing.
+    async fn extend_vote(&self, ctx: Context, height: u64) -> ProtocolResult<Option<Bytes>>;
+}
+
+/// Notified once a block is saved, by consensus or synchronization, such as
+/// caches of the latest height. It's on the path of committing, keep it
+/// quick.
+pub trait CommitListener: Send + Sync {
+    fn on_commit(&self, height: u64);
 }
 
 #[async_trait]
@@ -63,6 +83,9 @@ pub trait SynchronizationAdapter: CommonConsensusAdapter + Send + Sync {
     /// Pull some blocks from other nodes from `begin` to `end`.
     async fn get_block_from_remote(&self, ctx: Context, height: u64) -> ProtocolResult<Block>;
 
+    /// Pull the proof of the block at the height from other nodes.
+    async fn get_proof_from_remote(&self, ctx: Context, height: u64) -> ProtocolResult<Proof>;
+
     /// Pull signed transactions corresponding to the given hashes from other
     /// nodes.
     async fn get_txs_from_remote(
@@ -114,20 +137,29 @@ pub trait CommonConsensusAdapter: Send + Sync {
         timestamp: u64,
     ) -> ProtocolResult<Metadata>;
 
-    fn set_args(&self, context: Context, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64);
+    fn set_args(
+        &self,
+        context: Context,
+        timeout_gap: u64,
+        cycles_limit: u64,
+        max_tx_size: u64,
+        signature_domain: Option<SignatureDomain>,
+        halt_height: u64,
+    );
 }
 
 #[async_trait]
 pub trait ConsensusAdapter: CommonConsensusAdapter + Send + Sync {
     /// Get some transaction hashes of the given height. The amount of the
-    /// transactions is limited by the given cycle limit and return a
-    /// `MixedTxHashes` struct.
+    /// transactions is limited by the given cycle limit, number limit and
+    /// block size, and return a `MixedTxHashes` struct.
     async fn get_txs_from_mempool(
         &self,
         ctx: Context,
         height: u64,
         cycle_limit: u64,
         tx_num_limit: u64,
+        max_block_size: u64,
     ) -> ProtocolResult<MixedTxHashes>;
 
     /// Check the correctness of the given transactions.
@@ -164,6 +196,8 @@ pub trait ConsensusAdapter: CommonConsensusAdapter + Send + Sync {
         signed_txs: Vec<SignedTransaction>,
         cycles_limit: u64,
         timestamp: u64,
+        vote_extensions: Vec<VoteExtension>,
+        extra_data: Bytes,
     ) -> ProtocolResult<()>;
 
     /// Get the validator list of the given last block.
@@ -193,4 +227,7 @@ pub trait ConsensusAdapter: CommonConsensusAdapter + Send + Sync {
 
     /// Load latest overlord wal info.
     async fn load_overlord_wal(&self, ctx: Context) -> ProtocolResult<Bytes>;
+
+    /// Report misbehavior of the remote peer which sends the message.
+    fn report_bad(&self, ctx: Context, misbehavior: PeerMisbehavior);
 }
@@ -1,8 +1,13 @@
 use std::sync::Arc;
 
+use bytes::Bytes;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
 use crate::traits::{ServiceMapping, Storage};
 use crate::types::{
-    Address, Bloom, MerkleRoot, Receipt, ServiceContext, SignedTransaction, TransactionRequest,
+    Address, Bloom, Hash, MerkleRoot, PayloadLimits, Receipt, ServiceContext, SignedTransaction,
+    StateProof, TransactionRequest, TxTrace, VoteExtension,
 };
 use crate::ProtocolResult;
 
@@ -16,14 +21,94 @@ pub struct ExecutorResp {
 
 #[derive(Debug, Clone)]
 pub struct ExecutorParams {
-    pub state_root:   MerkleRoot,
-    pub height:       u64,
-    pub timestamp:    u64,
-    pub cycles_limit: u64,
+    pub state_root:      MerkleRoot,
+    pub height:          u64,
+    pub timestamp:       u64,
+    pub cycles_limit:    u64,
+    pub proposer:        Address,
+    // Aggregated by the proposer, empty in reads
+    pub vote_extensions: Vec<VoteExtension>,
+    // Extra data of the block header, empty in reads
+    pub extra_data:      Bytes,
 }
 
-#[derive(Debug, Clone, Default)]
+// Codes of responses failed in the framework rather than in services,
+// services define their own codes from 100.
+pub const INVALID_PAYLOAD_CODE: u64 = 1;
+pub const METHOD_NOT_FOUND_CODE: u64 = 2;
+pub const OUT_OF_CYCLES_CODE: u64 = 3;
+pub const UNAUTHORIZED_CODE: u64 = 4;
+pub const SERVICE_NOT_FOUND_CODE: u64 = 5;
+pub const HALTED_CODE: u64 = 6;
+pub const FORBIDDEN_CODE: u64 = 7;
+pub const INSUFFICIENT_FEE_CODE: u64 = 8;
+
+/// Class of a failed response, it's derived from the code so that clients
+/// don't have to parse error messages. Receipts keep only the code and the
+/// message, classes of receipts written before are the same.
+#[derive(Serialize, Deserialize, Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// Payload of the request failed to decode
+    #[display(fmt = "invalid payload")]
+    InvalidPayload,
+
+    /// The service has no such method
+    #[display(fmt = "method not found")]
+    MethodNotFound,
+
+    /// Cycles limit was used up, writes were reverted
+    #[display(fmt = "out of cycles")]
+    OutOfCycles,
+
+    /// Signer is not authorized by the sender
+    #[display(fmt = "unauthorized")]
+    Unauthorized,
+
+    /// No service of the name
+    #[display(fmt = "service not found")]
+    ServiceNotFound,
+
+    /// The chain is halted, only governance transactions are executed
+    #[display(fmt = "halted")]
+    Halted,
+
+    /// Caller has no access to the method
+    #[display(fmt = "forbidden")]
+    Forbidden,
+
+    /// Caller can't pay the fee, writes were reverted and the fee is paid
+    /// by as much as its balance covers
+    #[display(fmt = "insufficient fee")]
+    InsufficientFee,
+
+    /// The service returned an error of its own code, writes were reverted
+    #[display(fmt = "reverted")]
+    Reverted,
+}
+
+impl ErrorClass {
+    /// None for succeed responses
+    pub fn from_code(code: u64) -> Option<Self> {
+        let class = match code {
+            0 => return None,
+            INVALID_PAYLOAD_CODE => ErrorClass::InvalidPayload,
+            METHOD_NOT_FOUND_CODE => ErrorClass::MethodNotFound,
+            OUT_OF_CYCLES_CODE => ErrorClass::OutOfCycles,
+            UNAUTHORIZED_CODE => ErrorClass::Unauthorized,
+            SERVICE_NOT_FOUND_CODE => ErrorClass::ServiceNotFound,
+            HALTED_CODE => ErrorClass::Halted,
+            FORBIDDEN_CODE => ErrorClass::Forbidden,
+            INSUFFICIENT_FEE_CODE => ErrorClass::InsufficientFee,
+            _ => ErrorClass::Reverted,
+        };
+
+        Some(class)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ServiceResponse<T: Default> {
+    #[serde(with = "crate::types::canonical::uint64")]
     pub code:          u64,
     pub succeed_data:  T,
     pub error_message: String,
@@ -49,6 +134,10 @@ impl<T: Default> ServiceResponse<T> {
     pub fn is_error(&self) -> bool {
         self.code != 0
     }
+
+    pub fn error_class(&self) -> Option<ErrorClass> {
+        ErrorClass::from_code(self.code)
+    }
 }
 
 impl<T: Default + PartialEq> PartialEq for ServiceResponse<T> {
@@ -86,6 +175,31 @@ pub trait Executor {
         cycles_price: u64,
         request: &TransactionRequest,
     ) -> ProtocolResult<ServiceResponse<String>>;
+
+    // Check that signer of the transaction is authorized to act for its
+    // sender, always pass if there is no sender.
+    fn verify_authorization(
+        &self,
+        params: &ExecutorParams,
+        stx: &SignedTransaction,
+    ) -> ProtocolResult<()>;
+
+    // Merkle proof of a fixed encoded key in committed state of the service,
+    // it proves absence if the key doesn't exist.
+    fn get_state_proof(&self, service: &str, key: &Bytes) -> ProtocolResult<StateProof>;
+
+    // Max payload sizes declared by schemas of services, they are the same
+    // for all states of a build.
+    fn payload_limits(&self) -> ProtocolResult<PayloadLimits>;
+
+    // Execute the transactions of a block up to the traced one, and record
+    // steps of it. Nothing is committed.
+    fn trace(
+        &mut self,
+        params: &ExecutorParams,
+        txs: &[SignedTransaction],
+        tx_hash: &Hash,
+    ) -> ProtocolResult<TxTrace>;
 }
 
 // `Dispatcher` provides ability to send a call message to other services
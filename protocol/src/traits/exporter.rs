@@ -0,0 +1,27 @@
+use async_trait::async_trait;
+
+use crate::traits::Context;
+use crate::types::{Block, Receipt, SignedTransaction};
+use crate::ProtocolResult;
+
+/// A committed block with its transactions and receipts, both in the order
+/// of `ordered_tx_hashes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportedBlock {
+    pub block:    Block,
+    pub txs:      Vec<SignedTransaction>,
+    pub receipts: Vec<Receipt>,
+}
+
+/// Sink of committed chain data for analytics. Blocks are exported in height
+/// order once they are executed, and a block may be exported again after a
+/// restart if it failed or the node stopped before the progress is saved.
+#[async_trait]
+pub trait Exporter: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn export(&self, ctx: Context, block: &ExportedBlock) -> ProtocolResult<()>;
+
+    /// Persist data buffered by `export`, called before progress is saved.
+    async fn flush(&self, ctx: Context) -> ProtocolResult<()>;
+}
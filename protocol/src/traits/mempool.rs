@@ -1,9 +1,12 @@
 use async_trait::async_trait;
 use creep::Context;
 
-use crate::types::{Hash, SignedTransaction};
+use crate::traits::PeerMisbehavior;
+use crate::types::{Hash, SignatureDomain, SignedTransaction};
 use crate::ProtocolResult;
 
+const PRIVATE_TX_KEY: &str = "private_tx";
+
 #[allow(dead_code)]
 pub struct MixedTxHashes {
     pub order_tx_hashes:   Vec<Hash>,
@@ -16,15 +19,38 @@ impl MixedTxHashes {
     }
 }
 
+/// Private transactions are never broadcast nor announced to peers, they're
+/// only packaged by the local proposer. Peers learn them once they're
+/// proposed, which shields them from front-running.
+pub trait PrivateTxContext {
+    #[must_use]
+    fn with_private_tx(&self) -> Self;
+
+    fn is_private_tx(&self) -> bool;
+}
+
+impl PrivateTxContext for Context {
+    fn with_private_tx(&self) -> Self {
+        self.with_value::<bool>(PRIVATE_TX_KEY, true)
+    }
+
+    fn is_private_tx(&self) -> bool {
+        self.get::<bool>(PRIVATE_TX_KEY) == Some(&true)
+    }
+}
+
 #[async_trait]
 pub trait MemPool: Send + Sync {
     async fn insert(&self, ctx: Context, tx: SignedTransaction) -> ProtocolResult<()>;
 
+    /// Package transactions within limits of a block, `max_block_size` in
+    /// bytes of signed transactions, zero for no limit.
     async fn package(
         &self,
         ctx: Context,
         cycles_limit: u64,
         tx_num_limit: u64,
+        max_block_size: u64,
     ) -> ProtocolResult<MixedTxHashes>;
 
     async fn flush(&self, ctx: Context, tx_hashes: Vec<Hash>) -> ProtocolResult<()>;
@@ -47,7 +73,18 @@ pub trait MemPool: Send + Sync {
         propose_tx_hashes: Vec<Hash>,
     ) -> ProtocolResult<()>;
 
-    fn set_args(&self, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64);
+    /// Pull the announced transactions which are not in pool from the remote
+    /// peer in context.
+    async fn pull_announced_txs(&self, ctx: Context, tx_hashes: Vec<Hash>) -> ProtocolResult<()>;
+
+    fn set_args(
+        &self,
+        timeout_gap: u64,
+        cycles_limit: u64,
+        max_tx_size: u64,
+        signature_domain: Option<SignatureDomain>,
+        halt_height: u64,
+    );
 }
 
 #[async_trait]
@@ -68,5 +105,13 @@ pub trait MemPoolAdapter: Send + Sync {
 
     async fn get_latest_height(&self, ctx: Context) -> ProtocolResult<u64>;
 
-    fn set_args(&self, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64);
+    fn report_bad(&self, ctx: Context, misbehavior: PeerMisbehavior);
+
+    fn set_args(
+        &self,
+        timeout_gap: u64,
+        cycles_limit: u64,
+        max_tx_size: u64,
+        signature_domain: Option<SignatureDomain>,
+    );
 }
@@ -2,25 +2,37 @@ mod api;
 mod binding;
 mod consensus;
 mod executor;
+mod exporter;
 mod mempool;
 mod network;
 mod storage;
+mod trace;
 
-pub use api::APIAdapter;
+pub use api::{APIAdapter, AdminAdapter, ReadinessAdapter};
 pub use binding::{
-    AdmissionControl, ChainQuerier, Service, ServiceMapping, ServiceSDK, ServiceState, StoreArray,
-    StoreBool, StoreMap, StoreString, StoreUint64,
+    AdmissionControl, ChainQuerier, MethodAccess, Service, ServiceMapping, ServiceSDK,
+    ServiceState, StateCommitment, StoreArray, StoreBool, StoreMap, StoreString, StoreUint64,
 };
 pub use consensus::{
-    CommonConsensusAdapter, Consensus, ConsensusAdapter, MessageTarget, NodeInfo, Synchronization,
-    SynchronizationAdapter,
+    CommitListener, CommonConsensusAdapter, Consensus, ConsensusAdapter, MessageTarget, NodeInfo,
+    Synchronization, SynchronizationAdapter, VoteExtender,
 };
 pub use executor::{
-    Dispatcher, Executor, ExecutorFactory, ExecutorParams, ExecutorResp, NoopDispatcher,
-    ServiceResponse,
+    Dispatcher, ErrorClass, Executor, ExecutorFactory, ExecutorParams, ExecutorResp,
+    NoopDispatcher, ServiceResponse, FORBIDDEN_CODE, HALTED_CODE, INSUFFICIENT_FEE_CODE,
+    INVALID_PAYLOAD_CODE, METHOD_NOT_FOUND_CODE, OUT_OF_CYCLES_CODE, SERVICE_NOT_FOUND_CODE,
+    UNAUTHORIZED_CODE,
 };
-pub use mempool::{MemPool, MemPoolAdapter, MixedTxHashes};
-pub use network::{Gossip, MessageCodec, MessageHandler, Priority, Rpc};
-pub use storage::{Storage, StorageAdapter, StorageBatchModify, StorageCategory, StorageSchema};
+pub use exporter::{ExportedBlock, Exporter};
+pub use mempool::{MemPool, MemPoolAdapter, MixedTxHashes, PrivateTxContext};
+pub use network::{
+    Gossip, MessageCodec, MessageHandler, PeerMisbehavior, PeerReporter, PeerScore, Priority, Rpc,
+    RpcContext, RpcTimeout,
+};
+pub use storage::{
+    executed_state_root, Storage, StorageAdapter, StorageBatchModify, StorageCategory,
+    StorageSchema,
+};
+pub use trace::{TraceContext, TraceId};
 
 pub use creep::{Cloneable, Context};
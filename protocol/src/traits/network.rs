@@ -1,13 +1,20 @@
-use std::{error::Error, fmt::Debug};
+use std::{error::Error, fmt::Debug, time::Duration};
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
 
-use crate::{traits::Context, types::Address, ProtocolError, ProtocolErrorKind, ProtocolResult};
+use crate::{
+    traits::Context,
+    types::{Address, Hash},
+    ProtocolError, ProtocolErrorKind, ProtocolResult,
+};
 
-#[derive(Debug)]
+const RPC_TIMEOUT_KEY: &str = "rpc_timeout";
+const RPC_RETRIES_KEY: &str = "rpc_retries";
+
+#[derive(Debug, Clone, Copy)]
 pub enum Priority {
     High,
     Normal,
@@ -84,6 +91,94 @@ pub trait Rpc: Send + Sync {
         M: MessageCodec;
 }
 
+/// No response within timeout, from the called peer and all retried
+/// alternate peers.
+#[derive(Debug, Display)]
+#[display(fmt = "rpc {} timeout after {} attempt(s)", end, attempts)]
+pub struct RpcTimeout {
+    pub end:      String,
+    pub attempts: usize,
+}
+
+impl Error for RpcTimeout {}
+
+impl From<RpcTimeout> for ProtocolError {
+    fn from(err: RpcTimeout) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Network, Box::new(err))
+    }
+}
+
+/// Options of one rpc call, carried by `Context`.
+pub trait RpcContext {
+    /// Timeout of every attempt, network default is used if there isn't one.
+    fn rpc_timeout(&self) -> Option<Duration>;
+
+    #[must_use]
+    fn with_rpc_timeout(&self, timeout: Duration) -> Self;
+
+    /// Times to retry against alternate connected peers after the called
+    /// peer timeout, zero by default. Only set it if any peer can answer
+    /// the call.
+    fn rpc_retries(&self) -> usize;
+
+    #[must_use]
+    fn with_rpc_retries(&self, retries: usize) -> Self;
+}
+
+impl RpcContext for Context {
+    fn rpc_timeout(&self) -> Option<Duration> {
+        self.get::<Duration>(RPC_TIMEOUT_KEY).copied()
+    }
+
+    fn with_rpc_timeout(&self, timeout: Duration) -> Self {
+        self.with_value::<Duration>(RPC_TIMEOUT_KEY, timeout)
+    }
+
+    fn rpc_retries(&self) -> usize {
+        self.get::<usize>(RPC_RETRIES_KEY).copied().unwrap_or(0)
+    }
+
+    fn with_rpc_retries(&self, retries: usize) -> Self {
+        self.with_value::<usize>(RPC_RETRIES_KEY, retries)
+    }
+}
+
+/// Misbehaviors found outside the network layer, such as by consensus and
+/// mempool, reported against the remote peer of a message.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum PeerMisbehavior {
+    #[display(fmt = "undecodable message")]
+    UndecodableMessage,
+
+    #[display(fmt = "invalid block")]
+    InvalidBlock,
+
+    #[display(fmt = "invalid vote extension")]
+    InvalidVoteExtension,
+
+    /// Transaction already in the pool, honest peers gossip some of them,
+    /// only the same one sent again by a peer soon is punished.
+    #[display(fmt = "duplicate transaction {:?}", _0)]
+    DuplicateTx(Hash),
+}
+
+pub trait PeerReporter: Send + Sync {
+    /// Report misbehavior of the remote peer carried by context, nothing
+    /// happens if the context comes from local.
+    fn report(&self, ctx: Context, misbehavior: PeerMisbehavior);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerScore {
+    pub peer_id:      String,
+    pub chain_addr:   Option<String>,
+    // Role advertised by the peer, e.g. validator, full or seed
+    pub role:         Option<String>,
+    pub score:        i32,
+    // Unix timestamp in seconds, zero if never banned
+    pub banned_until: u64,
+}
+
 #[async_trait]
 pub trait MessageHandler: Sync + Send + 'static {
     type Message: MessageCodec;
@@ -4,7 +4,7 @@ use derive_more::Display;
 use crate::codec::ProtocolCodec;
 use crate::types::block::{Block, Proof};
 use crate::types::receipt::Receipt;
-use crate::types::{Hash, SignedTransaction};
+use crate::types::{Hash, MerkleRoot, SignedTransaction};
 use crate::{Bytes, ProtocolResult};
 
 #[derive(Debug, Copy, Clone, Display)]
@@ -51,6 +51,42 @@ pub trait Storage: Send + Sync {
     async fn update_overlord_wal(&self, info: Bytes) -> ProtocolResult<()>;
 
     async fn load_overlord_wal(&self) -> ProtocolResult<Bytes>;
+
+    /// Persist buffered writes, called before the node exits.
+    async fn flush(&self) -> ProtocolResult<()> {
+        Ok(())
+    }
+}
+
+/// State root after the block of the height is executed. Receipts of the
+/// block carry it, otherwise a later block executed up to the height has it
+/// in header. Blocks of dev chains are executed in themselves.
+pub async fn executed_state_root<S: Storage>(
+    storage: &S,
+    height: u64,
+) -> ProtocolResult<Option<MerkleRoot>> {
+    let block = storage.get_block_by_height(height).await?;
+    if height == 0 || block.header.exec_height == height {
+        return Ok(Some(block.header.state_root));
+    }
+
+    if let Some(tx_hash) = block.ordered_tx_hashes.first() {
+        let receipt = storage.get_receipt(tx_hash.clone()).await?;
+        return Ok(Some(receipt.state_root));
+    }
+
+    let latest_height = storage.get_latest_block().await?.header.height;
+    for later in height + 1..=latest_height {
+        let header = storage.get_block_by_height(later).await?.header;
+        if header.exec_height == height {
+            return Ok(Some(header.state_root));
+        }
+        if header.exec_height > height {
+            break;
+        }
+    }
+
+    Ok(None)
 }
 
 pub enum StorageBatchModify<S: StorageSchema> {
@@ -96,4 +132,8 @@ pub trait StorageAdapter: Send + Sync {
         keys: Vec<<S as StorageSchema>::Key>,
         vals: Vec<StorageBatchModify<S>>,
     ) -> ProtocolResult<()>;
+
+    async fn flush(&self) -> ProtocolResult<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,60 @@
+use derive_more::Display;
+use rand::random;
+
+use crate::traits::Context;
+
+const TRACE_ID_KEY: &str = "trace_id";
+
+/// Identify a trace across async tasks and nodes, it's carried by `Context`
+/// and the network message header.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+#[display(fmt = "{:016x}", _0)]
+pub struct TraceId(u64);
+
+impl TraceId {
+    pub fn new() -> Self {
+        TraceId(random())
+    }
+
+    pub fn from_u64(id: u64) -> Self {
+        TraceId(id)
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for TraceId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub trait TraceContext {
+    fn trace_id(&self) -> Option<TraceId>;
+
+    #[must_use]
+    fn with_trace_id(&self, id: TraceId) -> Self;
+
+    /// Start a new trace if there isn't one.
+    #[must_use]
+    fn with_new_trace(&self) -> Self;
+}
+
+impl TraceContext for Context {
+    fn trace_id(&self) -> Option<TraceId> {
+        self.get::<TraceId>(TRACE_ID_KEY).copied()
+    }
+
+    fn with_trace_id(&self, id: TraceId) -> Self {
+        self.with_value::<TraceId>(TRACE_ID_KEY, id)
+    }
+
+    fn with_new_trace(&self) -> Self {
+        match self.trace_id() {
+            Some(_) => self.clone(),
+            None => self.with_trace_id(TraceId::new()),
+        }
+    }
+}
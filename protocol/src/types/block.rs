@@ -3,37 +3,69 @@ use serde::{Deserialize, Serialize};
 
 use crate::types::{Address, Bloom, Hash, MerkleRoot};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Bytes of extra data a proposer may put in the header of its block.
+pub const MAX_EXTRA_DATA_SIZE: usize = 32;
+
+/// Validators ready for a hard fork signal it in extra data of the blocks
+/// they propose, by the prefix followed by the name of the fork.
+pub const UPGRADE_SIGNAL_PREFIX: &str = "ready:";
+
+/// Extra data signaling readiness for the fork of the name.
+pub fn upgrade_signal(name: &str) -> Bytes {
+    Bytes::from(format!("{}{}", UPGRADE_SIGNAL_PREFIX, name))
+}
+
+/// Whether the extra data signals readiness for the fork of the name.
+pub fn is_upgrade_signal(extra_data: &[u8], name: &str) -> bool {
+    !name.is_empty() && extra_data == upgrade_signal(name).as_ref()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Block {
     pub header:            BlockHeader,
     pub ordered_tx_hashes: Vec<Hash>,
+    // Extensions of precommits of the previous height
+    #[serde(default)]
+    pub vote_extensions:   Vec<VoteExtension>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct BlockHeader {
     pub chain_id:          Hash,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub height:            u64,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub exec_height:       u64,
     pub pre_hash:          Hash,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub timestamp:         u64,
     pub logs_bloom:        Vec<Bloom>,
     pub order_root:        MerkleRoot,
     pub confirm_root:      Vec<MerkleRoot>,
     pub state_root:        MerkleRoot,
     pub receipt_root:      Vec<MerkleRoot>,
+    #[serde(with = "crate::types::canonical::vec_uint64")]
     pub cycles_used:       Vec<u64>,
     pub proposer:          Address,
     pub proof:             Proof,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub validator_version: u64,
     pub validators:        Vec<Validator>,
+    // Bytes of the proposer, at most `MAX_EXTRA_DATA_SIZE`
+    #[serde(default, with = "crate::types::canonical::hex_bytes")]
+    pub extra_data:        Bytes,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Proof {
+    #[serde(with = "crate::types::canonical::uint64")]
     pub height:     u64,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub round:      u64,
     pub block_hash: Hash,
+    #[serde(with = "crate::types::canonical::hex_bytes")]
     pub signature:  Bytes,
+    #[serde(with = "crate::types::canonical::hex_bytes")]
     pub bitmap:     Bytes,
 }
 
@@ -44,7 +76,37 @@ pub struct Validator {
     pub vote_weight:    u32,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Payload a validator attaches to its precommit of the block of the height,
+/// such as price observations of oracles. Proposer of the next height
+/// aggregates the ones of voters in its proof into its block, services read
+/// them by `get_vote_extensions` of the SDK.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VoteExtension {
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub height:     u64,
+    pub voter:      Address,
+    // Block the voter precommitted
+    pub block_hash: Hash,
+    #[serde(with = "crate::types::canonical::hex_bytes")]
+    pub payload:    Bytes,
+    #[serde(with = "crate::types::canonical::hex_bytes")]
+    pub signature:  Bytes,
+}
+
+impl VoteExtension {
+    /// Hash signed by the voter, it covers everything but the signature.
+    pub fn signing_hash(&self) -> Hash {
+        let mut s = rlp::RlpStream::new_list(4);
+        s.append(&self.height)
+            .append(&self.voter)
+            .append(&self.block_hash)
+            .append(&self.payload.to_vec());
+
+        Hash::digest(Bytes::from(s.out()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Pill {
     pub block:          Block,
     pub propose_hashes: Vec<Hash>,
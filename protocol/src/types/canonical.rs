@@ -0,0 +1,217 @@
+//! Canonical json of protocol types, it's used by graphql, genesis files and
+//! interop tooling. Bytes are hex strings started with 0x, and u64 are
+//! decimal strings since javascript loses precision of numbers above 2^53.
+//!
+//! Numbers and arrays of bytes are still accepted decoding, so files written
+//! before keep working. Formats which are not human readable, such as the
+//! bincode of network messages, keep the native encoding.
+//!
+//! Use them on fields with `#[serde(with = "...")]`, e.g.
+//! `#[serde(with = "crate::types::canonical::uint64")]`.
+
+use std::fmt;
+
+use bytes::Bytes;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::types::Hex;
+
+struct Uint64(u64);
+
+impl Serialize for Uint64 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        uint64::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Uint64 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        uint64::deserialize(deserializer).map(Uint64)
+    }
+}
+
+struct HexBytes(Bytes);
+
+impl Serialize for HexBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hex_bytes::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for HexBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        hex_bytes::deserialize(deserializer).map(HexBytes)
+    }
+}
+
+struct Uint64Visitor;
+
+impl<'de> de::Visitor<'de> for Uint64Visitor {
+    type Value = u64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("Expect a decimal string or a number")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        if v < 0 {
+            return Err(de::Error::invalid_value(de::Unexpected::Signed(v), &self));
+        }
+        Ok(v as u64)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse::<u64>()
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+struct HexBytesVisitor;
+
+impl<'de> de::Visitor<'de> for HexBytesVisitor {
+    type Value = Bytes;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("Expect a hex string or an array of bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        let digits = if v.starts_with("0x") { &v[2..] } else { v };
+        let bytes = hex::decode(digits).map_err(|e| de::Error::custom(e.to_string()))?;
+
+        Ok(Bytes::from(bytes))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Bytes::from(v.to_vec()))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(b) = seq.next_element::<u8>()? {
+            bytes.push(b);
+        }
+
+        Ok(Bytes::from(bytes))
+    }
+}
+
+pub mod uint64 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Uint64Visitor;
+
+    // Signature is required by serde
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S: Serializer>(n: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(n)
+        } else {
+            serializer.serialize_u64(*n)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(Uint64Visitor)
+        } else {
+            u64::deserialize(deserializer)
+        }
+    }
+}
+
+pub mod option_uint64 {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Uint64;
+
+    // Signature is required by serde
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S: Serializer>(n: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        n.map(Uint64).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        Ok(Option::<Uint64>::deserialize(deserializer)?.map(|n| n.0))
+    }
+}
+
+pub mod vec_uint64 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Uint64;
+
+    pub fn serialize<S: Serializer>(ns: &[u64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(ns.iter().map(|n| Uint64(*n)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+        let ns = Vec::<Uint64>::deserialize(deserializer)?;
+        Ok(ns.into_iter().map(|n| n.0).collect())
+    }
+}
+
+pub mod hex_bytes {
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{Hex, HexBytesVisitor};
+
+    pub fn serialize<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&Hex::from_bytes(bytes.clone()))
+        } else {
+            bytes.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(HexBytesVisitor)
+        } else {
+            Bytes::deserialize(deserializer)
+        }
+    }
+}
+
+pub mod option_hex_bytes {
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::HexBytes;
+
+    pub fn serialize<S: Serializer>(
+        bytes: &Option<Bytes>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        bytes.clone().map(HexBytes).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Bytes>, D::Error> {
+        Ok(Option::<HexBytes>::deserialize(deserializer)?.map(|b| b.0))
+    }
+}
+
+pub mod vec_hex_bytes {
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::HexBytes;
+
+    pub fn serialize<S: Serializer>(bytes: &[Bytes], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(bytes.iter().cloned().map(HexBytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Bytes>, D::Error> {
+        let bytes = Vec::<HexBytes>::deserialize(deserializer)?;
+        Ok(bytes.into_iter().map(|b| b.0).collect())
+    }
+}
@@ -0,0 +1,184 @@
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+/// Blocks at least between an update of the chain spec and activations it
+/// schedules, validators upgrade their nodes in them.
+pub const MIN_ACTIVATION_LEAD: u64 = 10_000;
+
+/// Behaviors of the protocol changed by hard forks. Each activates from the
+/// height scheduled for its name in the chain spec, code paths of consensus
+/// check the feature at the height of the block they process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display)]
+pub enum Feature {
+    /// Signatures cover the chain id and network tag, the same as from
+    /// `domain_separation_height`.
+    #[display(fmt = "signature_domain")]
+    SignatureDomain,
+
+    /// Transactions pay `tx_base_cycles` of metadata, writes of failed calls
+    /// are reverted while cycles they consumed are still charged.
+    #[display(fmt = "tx_cycles")]
+    TxCycles,
+
+    /// Callers lock deposit for bytes they store in service state, rent is
+    /// charged for them every `rent_epoch`.
+    #[display(fmt = "storage_deposit")]
+    StorageDeposit,
+
+    /// Validators attach extensions to their precommits, blocks carry the
+    /// ones of the previous height.
+    #[display(fmt = "vote_extension")]
+    VoteExtension,
+
+    /// Timestamps of blocks increase strictly from their parents, proposals
+    /// are within the max drift of the local clock as well.
+    #[display(fmt = "block_timestamp")]
+    BlockTimestamp,
+
+    /// Headers carry extra data of their proposers, such as upgrade signals.
+    #[display(fmt = "extra_data")]
+    ExtraData,
+
+    /// Blocks with payloads larger than `#[max_payload_size]` of their
+    /// methods are invalid.
+    #[display(fmt = "payload_limits")]
+    PayloadLimits,
+
+    /// Calls pay cycles of `CYCLES_PRICING` for storage access, events and
+    /// service calls.
+    #[display(fmt = "cycles_pricing")]
+    CyclesPricing,
+}
+
+impl Feature {
+    /// Features known by this node.
+    pub const ALL: [Feature; 8] = [
+        Feature::SignatureDomain,
+        Feature::TxCycles,
+        Feature::StorageDeposit,
+        Feature::VoteExtension,
+        Feature::BlockTimestamp,
+        Feature::ExtraData,
+        Feature::PayloadLimits,
+        Feature::CyclesPricing,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|f| f.to_string() == name).cloned()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FeatureActivation {
+    pub name:   String,
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub height: u64,
+}
+
+/// Heights features of the chain activate from, agreed in genesis metadata
+/// and scheduled by admins of the metadata service. Names unknown to a node
+/// are kept, the node stops at their heights instead of forking off the
+/// chain, until it's upgraded.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(transparent)]
+pub struct ChainSpec {
+    pub activations: Vec<FeatureActivation>,
+}
+
+impl ChainSpec {
+    pub fn activation_height(&self, feature: Feature) -> Option<u64> {
+        let name = feature.to_string();
+        self.activations
+            .iter()
+            .filter(|a| a.name == name)
+            .map(|a| a.height)
+            .min()
+    }
+
+    pub fn is_active(&self, feature: Feature, height: u64) -> bool {
+        self.activation_height(feature)
+            .map(|activation| height >= activation)
+            .unwrap_or(false)
+    }
+
+    /// Activate the feature of the name from the height, or earlier if it's
+    /// scheduled later.
+    pub fn schedule(&mut self, name: &str, height: u64) {
+        match self.activations.iter_mut().find(|a| a.name == name) {
+            Some(activation) => activation.height = activation.height.min(height),
+            None => self.activations.push(FeatureActivation {
+                name: name.to_owned(),
+                height,
+            }),
+        }
+    }
+
+    /// Features known by this node active at the height.
+    pub fn features_at(&self, height: u64) -> Vec<Feature> {
+        Feature::ALL
+            .iter()
+            .cloned()
+            .filter(|f| self.is_active(*f, height))
+            .collect()
+    }
+
+    /// The earliest activation at or before the height unknown to this node,
+    /// blocks of the height can't be processed by it.
+    pub fn unsupported_at(&self, height: u64) -> Option<&FeatureActivation> {
+        self.activations
+            .iter()
+            .filter(|a| a.height <= height && Feature::from_name(&a.name).is_none())
+            .min_by_key(|a| a.height)
+    }
+
+    /// Check the spec replacing the current one by governance at the height.
+    /// Activations at or before the height are kept as they are, new ones
+    /// activate `MIN_ACTIVATION_LEAD` blocks after it at the earliest, and a
+    /// name is scheduled once. Names added by the update are known to this
+    /// node, validators would halt at an unknown one for good.
+    pub fn verify_update(&self, current: &ChainSpec, height: u64) -> Result<(), String> {
+        let activated = |spec: &ChainSpec| {
+            let mut activated = spec
+                .activations
+                .iter()
+                .filter(|a| a.height <= height)
+                .cloned()
+                .collect::<Vec<_>>();
+            activated.sort_by(|a, b| (a.height, &a.name).cmp(&(b.height, &b.name)));
+            activated
+        };
+        if activated(self) != activated(current) {
+            return Err(format!("activations at or before {} are changed", height));
+        }
+
+        for (i, activation) in self.activations.iter().enumerate() {
+            if self.activations[..i]
+                .iter()
+                .any(|a| a.name == activation.name)
+            {
+                return Err(format!("feature {} is scheduled twice", activation.name));
+            }
+        }
+
+        for activation in self.activations.iter().filter(|a| a.height > height) {
+            if current.activations.contains(activation) {
+                continue;
+            }
+            if !current
+                .activations
+                .iter()
+                .any(|a| a.name == activation.name)
+                && Feature::from_name(&activation.name).is_none()
+            {
+                return Err(format!("feature {} is unknown", activation.name));
+            }
+            if activation.height < height.saturating_add(MIN_ACTIVATION_LEAD) {
+                return Err(format!(
+                    "feature {} activates within {} blocks",
+                    activation.name, MIN_ACTIVATION_LEAD
+                ));
+            }
+        }
+        Ok(())
+    }
+}
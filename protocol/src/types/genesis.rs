@@ -1,12 +1,17 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::types::primitive::Hex;
+use crate::types::CommitmentScheme;
 
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Genesis {
-    pub timestamp: u64,
-    pub prevhash:  Hex,
-    pub services:  Vec<ServiceParam>,
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub timestamp:        u64,
+    pub prevhash:         Hex,
+    // Chains created before schemes commit in merkle patricia tries
+    #[serde(default)]
+    pub state_commitment: CommitmentScheme,
+    pub services:         Vec<ServiceParam>,
 }
 
 impl Genesis {
@@ -20,7 +25,7 @@ impl Genesis {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ServiceParam {
     pub name:    String,
     pub payload: String,
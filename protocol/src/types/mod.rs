@@ -1,8 +1,15 @@
 pub(crate) mod block;
+pub mod canonical;
+pub(crate) mod chain_spec;
 pub(crate) mod genesis;
 pub(crate) mod primitive;
+pub(crate) mod query;
 pub(crate) mod receipt;
 pub(crate) mod service_context;
+pub(crate) mod state_proof;
+#[cfg(test)]
+mod tests;
+pub(crate) mod trace;
 pub(crate) mod transaction;
 
 use std::error::Error;
@@ -11,17 +18,31 @@ use derive_more::{Display, From};
 
 use crate::{ProtocolError, ProtocolErrorKind};
 
-pub use block::{Block, BlockHeader, Pill, Proof, Validator};
+pub use block::{
+    is_upgrade_signal, upgrade_signal, Block, BlockHeader, Pill, Proof, Validator, VoteExtension,
+    MAX_EXTRA_DATA_SIZE, UPGRADE_SIGNAL_PREFIX,
+};
 pub use bytes::{Bytes, BytesMut};
+pub use chain_spec::{ChainSpec, Feature, FeatureActivation, MIN_ACTIVATION_LEAD};
 pub use ethbloom::{Bloom, BloomRef, Input as BloomInput};
 pub use genesis::{Genesis, ServiceParam};
 pub use primitive::{
-    Address, Balance, Hash, Hex, JsonString, MerkleRoot, Metadata, ValidatorExtend, GENESIS_HEIGHT,
-    METADATA_KEY,
+    is_halted, service_admins_key, signing_hash, Address, Balance, Hash, Hex, JsonString,
+    MerkleRoot, Metadata, SignatureDomain, ValidatorExtend, CONSENSUS_SIGNATURE_PURPOSE,
+    GENESIS_HEIGHT, METADATA_KEY, STORAGE_RECLAIM_KEY, TRANSACTION_SIGNATURE_PURPOSE,
 };
+pub use query::{query_result_hash, QueryAttestation, QUERY_SIGNATURE_PURPOSE};
 pub use receipt::{Event, Receipt, ReceiptResponse};
-pub use service_context::{ServiceContext, ServiceContextError, ServiceContextParams};
-pub use transaction::{RawTransaction, SignedTransaction, TransactionRequest};
+pub use service_context::{
+    service_address, CyclesPricing, ServiceContext, ServiceContextError, ServiceContextParams,
+    CYCLES_PRICING,
+};
+pub use state_proof::{CommitmentScheme, StateProof, StateProofError};
+pub use trace::{TraceStep, TraceStepKind, TxTrace};
+pub use transaction::{
+    PayloadLimits, RawTransaction, SignedTransaction, TransactionRequest,
+    VerifyAuthorizationPayload, GOVERNANCE_SERVICE,
+};
 
 #[derive(Debug, Display, From)]
 pub enum TypesError {
@@ -7,10 +7,20 @@ use num_bigint::BigUint;
 use serde::de;
 use serde::{Deserialize, Serialize};
 
-use crate::types::TypesError;
+use crate::types::{ChainSpec, Feature, TypesError};
 use crate::ProtocolResult;
 
 pub const METADATA_KEY: &str = "metadata";
+// Owners whose balance can't cover storage rent are written under the key
+// by the service collecting rent, executor deletes entries deposited by them
+// in every service at the end of the block.
+pub const STORAGE_RECLAIM_KEY: &str = "storage_reclaim";
+
+/// Admins of the service are written under the key in state of the
+/// governance service, they may call methods with `#[access(admin)]`.
+pub fn service_admins_key(service_name: &str) -> String {
+    format!("service_admins:{}", service_name)
+}
 
 lazy_static! {
     static ref HASHER_INST: HasherKeccak = HasherKeccak::new();
@@ -22,25 +32,148 @@ pub const GENESIS_HEIGHT: u64 = 0;
 /// Hash length
 const HASH_LEN: usize = 32;
 
-// Should started with 0x
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
-pub struct Hex(String);
+/// Hex encoded bytes, the digits are validated on construction and the bytes
+/// are stored. Hex values are equal if their bytes are.
+///
+/// Digits of a string not in lowercase are kept, it's displayed, serialized
+/// and encoded as it's constructed, so that bytes encoded into state and
+/// receipts before bytes were stored are unchanged. `{:x}` formats the
+/// lowercase digits for callers showing normalized hex.
+#[derive(Clone, Default)]
+pub struct Hex {
+    bytes:  Bytes,
+    digits: Option<Bytes>,
+}
 
 impl Hex {
+    /// Hex of a fixed literal, e.g. `Hex::from_static(b"\x01\x02")`.
+    pub const fn from_static(bytes: &'static [u8]) -> Self {
+        Self {
+            bytes:  Bytes::from_static(bytes),
+            digits: None,
+        }
+    }
+
+    pub fn from_bytes(bytes: Bytes) -> Self {
+        Self {
+            bytes,
+            digits: None,
+        }
+    }
+
+    /// The string should be started with 0x.
     pub fn from_string(s: String) -> ProtocolResult<Self> {
         if s.starts_with("0x") {
-            Ok(Self(s))
+            Self::from_digits(&s[2..])
         } else {
             Err(TypesError::HexPrefix.into())
         }
     }
 
+    // Digits without 0x
+    pub(crate) fn from_digits<D: AsRef<[u8]>>(digits: D) -> ProtocolResult<Self> {
+        let digits = digits.as_ref();
+        let bytes = hex::decode(digits).map_err(TypesError::from)?;
+        let lowercase = !digits.iter().any(u8::is_ascii_uppercase);
+
+        Ok(Self {
+            bytes:  Bytes::from(bytes),
+            digits: if lowercase {
+                None
+            } else {
+                Some(Bytes::copy_from_slice(digits))
+            },
+        })
+    }
+
+    pub fn as_bytes(&self) -> Bytes {
+        self.bytes.clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
     pub fn as_string(&self) -> String {
-        self.0.to_owned()
+        self.to_string()
     }
 
+    /// Digits as constructed, without 0x.
     pub fn as_string_trim0x(&self) -> String {
-        (&self.0[2..]).to_owned()
+        match &self.digits {
+            Some(digits) => String::from_utf8_lossy(digits).into_owned(),
+            None => format!("{:x}", self),
+        }
+    }
+
+    // Digits not in lowercase the hex is constructed from
+    pub(crate) fn kept_digits(&self) -> Option<&[u8]> {
+        self.digits.as_ref().map(|digits| digits.as_ref())
+    }
+}
+
+impl PartialEq for Hex {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for Hex {}
+
+impl PartialOrd for Hex {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Hex {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.bytes.cmp(&other.bytes)
+    }
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Write lowercase hex digits of the bytes to the buffer, the buffer should
+/// be at least twice as long as the bytes.
+pub(crate) fn encode_hex_digits<'a>(bytes: &[u8], buf: &'a mut [u8]) -> &'a [u8] {
+    for (i, b) in bytes.iter().enumerate() {
+        buf[i * 2] = HEX_DIGITS[(b >> 4) as usize];
+        buf[i * 2 + 1] = HEX_DIGITS[(b & 0x0f) as usize];
+    }
+    &buf[..bytes.len() * 2]
+}
+
+impl fmt::LowerHex for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+
+        let mut buf = [0u8; 128];
+        for chunk in self.bytes.chunks(buf.len() / 2) {
+            let digits = encode_hex_digits(chunk, &mut buf);
+            f.write_str(std::str::from_utf8(digits).map_err(|_| fmt::Error)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kept_digits() {
+            Some(digits) => {
+                f.write_str("0x")?;
+                f.write_str(std::str::from_utf8(digits).map_err(|_| fmt::Error)?)
+            }
+            None => write!(f, "{:#x}", self),
+        }
+    }
+}
+
+impl fmt::Debug for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
     }
 }
 
@@ -49,7 +182,7 @@ impl Serialize for Hex {
     where
         S: serde::ser::Serializer,
     {
-        serializer.serialize_str(&self.0)
+        serializer.collect_str(self)
     }
 }
 
@@ -66,14 +199,16 @@ impl<'de> de::Visitor<'de> for HexVisitor {
     where
         E: de::Error,
     {
-        Hex::from_string(v).map_err(|e| de::Error::custom(e.to_string()))
+        self.visit_str(&v)
     }
 
+    // Both 0x started and bare digits are accepted
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        Hex::from_string(v.to_owned()).map_err(|e| de::Error::custom(e.to_string()))
+        let digits = if v.starts_with("0x") { &v[2..] } else { v };
+        Hex::from_digits(digits).map_err(|e| de::Error::custom(e.to_string()))
     }
 }
 
@@ -82,7 +217,7 @@ impl<'de> Deserialize<'de> for Hex {
     where
         D: de::Deserializer<'de>,
     {
-        deserializer.deserialize_string(HexVisitor)
+        deserializer.deserialize_str(HexVisitor)
     }
 }
 
@@ -292,17 +427,150 @@ impl fmt::Debug for Address {
 pub struct Metadata {
     pub chain_id:        Hash,
     pub common_ref:      Hex,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub timeout_gap:     u64,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub cycles_limit:    u64,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub cycles_price:    u64,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub interval:        u64,
     pub verifier_list:   Vec<ValidatorExtend>,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub propose_ratio:   u64,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub prevote_ratio:   u64,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub precommit_ratio: u64,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub brake_ratio:     u64,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub tx_num_limit:    u64,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub max_tx_size:     u64,
+
+    // Paid cycles of a block are split between its proposer and the treasury
+    // by these ratios, the proposer takes all if both are zero.
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub proposer_fee_ratio: u64,
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub treasury_fee_ratio: u64,
+    #[serde(default)]
+    pub treasury:           Address,
+
+    // Signatures of transactions and consensus messages cover the chain id
+    // and network tag from this height on, zero keeps legacy signing hashes
+    // so existing chains can phase it in.
+    #[serde(default)]
+    pub network_tag:              String,
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub domain_separation_height: u64,
+
+    // Total bytes of transactions in a block, zero for no limit
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub max_block_size: u64,
+
+    // Cycles charged to every transaction besides its service call, once
+    // `Feature::TxCycles` activates
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub tx_base_cycles: u64,
+
+    // Fee asset locked by the owner for every byte stored by its
+    // transactions and refunded on deletion, zero disables storage deposit.
+    // Deposit and rent apply from `Feature::StorageDeposit`
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub storage_deposit_per_byte: u64,
+
+    // Blocks between charges of storage rent, zero disables rent
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub rent_epoch: u64,
+
+    // Fee asset charged for every byte stored each rent epoch
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub rent_per_byte: u64,
+
+    // Transactions other than governance ones are neither packaged nor
+    // executed from the height on, zero if the chain isn't halted
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub halt_height: u64,
+
+    // The fork activates from the height once proposers of threshold blocks
+    // within the window signal it in extra data, empty name disables it.
+    // The height is set by the metadata service and scheduled for the name
+    // in the chain spec, zero before activation.
+    #[serde(default)]
+    pub upgrade_name:      String,
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub upgrade_window:    u64,
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub upgrade_threshold: u64,
+    #[serde(default, with = "crate::types::canonical::uint64")]
+    pub upgrade_height:    u64,
+
+    // Heights features activate from, see `ChainSpec`
+    #[serde(default)]
+    pub chain_spec: ChainSpec,
+}
+
+impl Metadata {
+    /// Signature domain of blocks at the height, `None` before domain
+    /// separation is enabled by either its height or the feature.
+    pub fn signature_domain(&self, height: u64) -> Option<SignatureDomain> {
+        let separated =
+            self.domain_separation_height != 0 && height >= self.domain_separation_height;
+        if !separated && !self.chain_spec.is_active(Feature::SignatureDomain, height) {
+            return None;
+        }
+
+        Some(SignatureDomain {
+            network_tag: self.network_tag.clone(),
+            chain_id:    self.chain_id.clone(),
+        })
+    }
+
+    /// Whether the chain is halted at the height by emergency governance.
+    pub fn is_halted(&self, height: u64) -> bool {
+        is_halted(self.halt_height, height)
+    }
+}
+
+/// Whether the height is halted, by halt height of metadata.
+pub fn is_halted(halt_height: u64, height: u64) -> bool {
+    halt_height != 0 && height >= halt_height
+}
+
+pub const TRANSACTION_SIGNATURE_PURPOSE: &str = "muta-transaction";
+pub const CONSENSUS_SIGNATURE_PURPOSE: &str = "muta-consensus";
+
+/// Messages signed in a domain don't verify on other networks or forked
+/// chains, even with the same key.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SignatureDomain {
+    pub network_tag: String,
+    pub chain_id:    Hash,
+}
+
+impl SignatureDomain {
+    /// Hash to sign instead of the message hash. Purpose separates kinds of
+    /// messages, for example a transaction hash can't be replayed as a vote.
+    pub fn signing_hash(&self, purpose: &str, hash: &Hash) -> Hash {
+        let mut stream = rlp::RlpStream::new_list(4);
+        stream
+            .append(&purpose.to_owned())
+            .append(&self.network_tag)
+            .append(&self.chain_id)
+            .append(hash);
+
+        Hash::digest(Bytes::from(stream.out()))
+    }
+}
+
+/// Hash to sign in the domain, the message hash itself without domain.
+pub fn signing_hash(domain: Option<&SignatureDomain>, purpose: &str, hash: &Hash) -> Hash {
+    match domain {
+        Some(domain) => domain.signing_hash(purpose, hash),
+        None => hash.clone(),
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
@@ -315,17 +583,14 @@ pub struct ValidatorExtend {
 
 impl fmt::Debug for ValidatorExtend {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let bls_pub_key = self.bls_pub_key.as_string_trim0x();
-        let pk = if bls_pub_key.len() > 8 {
-            unsafe { bls_pub_key.get_unchecked(0..8) }
-        } else {
-            bls_pub_key.as_str()
-        };
+        // First 4 bytes are enough to tell keys apart
+        let bls_pub_key = self.bls_pub_key.as_bytes();
+        let pk = Hex::from_bytes(bls_pub_key.slice(..bls_pub_key.len().min(4)));
 
         write!(
             f,
             "bls public key {:?}, address {:?}, propose weight {}, vote weight {}",
-            pk,
+            format!("{:x}", pk),
             self.address.as_hex(),
             self.propose_weight,
             self.vote_weight
@@ -353,7 +618,7 @@ fn ensure_len(real: usize, expect: usize) -> ProtocolResult<()> {
 mod tests {
     use bytes::Bytes;
 
-    use super::{Address, Hash};
+    use super::{signing_hash, Address, Hash, Hex, Metadata, TRANSACTION_SIGNATURE_PURPOSE};
 
     #[test]
     fn test_hash() {
@@ -363,6 +628,48 @@ mod tests {
         Hash::from_bytes(bytes).unwrap();
     }
 
+    #[test]
+    fn test_hex() {
+        const HEX: Hex = Hex::from_static(b"\x01\xab\xff");
+
+        let hex = Hex::from_string("0x01abff".to_owned()).unwrap();
+        assert_eq!(hex, HEX);
+        assert_eq!(hex.to_string(), "0x01abff");
+        assert_eq!(format!("{:x}", hex), "01abff");
+        assert_eq!(hex.as_string_trim0x(), "01abff");
+        assert_eq!(hex.as_bytes(), Bytes::from_static(b"\x01\xab\xff"));
+
+        // Digits not in lowercase are kept, only `{:x}` normalizes them
+        let upper = Hex::from_string("0x01ABff".to_owned()).unwrap();
+        assert_eq!(upper, HEX);
+        assert_eq!(upper.to_string(), "0x01ABff");
+        assert_eq!(upper.as_string_trim0x(), "01ABff");
+        assert_eq!(format!("{:x}", upper), "01abff");
+        assert_eq!(format!("{:#x}", upper), "0x01abff");
+
+        assert!(Hex::from_string("01abff".to_owned()).is_err());
+        assert!(Hex::from_string("0x01abf".to_owned()).is_err());
+        assert!(Hex::from_string("0xzz".to_owned()).is_err());
+
+        // Longer than the stack buffer of formatting
+        let long = Hex::from_bytes(Bytes::from(vec![0xa5; 100]));
+        assert_eq!(long.to_string(), "0x".to_owned() + &"a5".repeat(100));
+    }
+
+    #[test]
+    fn test_hex_serde() {
+        let hex = Hex::from_static(b"\x01\xab");
+
+        assert_eq!(serde_json::to_string(&hex).unwrap(), "\"0x01ab\"");
+        assert_eq!(serde_json::from_str::<Hex>("\"0x01ab\"").unwrap(), hex);
+        assert_eq!(serde_json::from_str::<Hex>("\"01ab\"").unwrap(), hex);
+        assert!(serde_json::from_str::<Hex>("\"0x01a\"").is_err());
+
+        let upper = serde_json::from_str::<Hex>("\"0x01AB\"").unwrap();
+        assert_eq!(upper, hex);
+        assert_eq!(serde_json::to_string(&upper).unwrap(), "\"0x01AB\"");
+    }
+
     #[test]
     fn test_from_pubkey_bytes() {
         let pubkey = "031313016e9670deb49779c1b0c646d6a25a545712658f9781995f623bcd0d0b3d";
@@ -382,4 +689,49 @@ mod tests {
         let address = Address::from_bytes(bytes).unwrap();
         assert_eq!(add_str, &address.as_hex().to_uppercase().as_str()[2..]);
     }
+
+    #[test]
+    fn test_signature_domain() {
+        let tx_hash = Hash::digest(Bytes::from("tx"));
+        let mut metadata = Metadata {
+            chain_id: Hash::digest(Bytes::from("chain")),
+            network_tag: "mainnet".to_owned(),
+            ..Default::default()
+        };
+
+        // Disabled by default
+        assert_eq!(metadata.signature_domain(100), None);
+
+        metadata.domain_separation_height = 10;
+        assert_eq!(metadata.signature_domain(9), None);
+        let domain = metadata.signature_domain(10).unwrap();
+        let signing = signing_hash(Some(&domain), TRANSACTION_SIGNATURE_PURPOSE, &tx_hash);
+        assert_ne!(signing, tx_hash);
+
+        // Forked chain with another id signs another hash
+        let mut forked = metadata.clone();
+        forked.chain_id = Hash::digest(Bytes::from("fork"));
+        let forked_domain = forked.signature_domain(10).unwrap();
+        assert_ne!(
+            signing_hash(
+                Some(&forked_domain),
+                TRANSACTION_SIGNATURE_PURPOSE,
+                &tx_hash
+            ),
+            signing
+        );
+
+        // So does another network
+        let mut testnet = metadata.clone();
+        testnet.network_tag = "testnet".to_owned();
+        let testnet_domain = testnet.signature_domain(10).unwrap();
+        assert_ne!(
+            signing_hash(
+                Some(&testnet_domain),
+                TRANSACTION_SIGNATURE_PURPOSE,
+                &tx_hash
+            ),
+            signing
+        );
+    }
 }
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use crate::traits::ServiceResponse;
+use crate::types::{
+    signing_hash, Address, Bytes, Hash, MerkleRoot, SignatureDomain, TransactionRequest,
+};
+
+pub const QUERY_SIGNATURE_PURPOSE: &str = "muta-query";
+
+/// What an api node signs for the response of a service query, read at the
+/// state root of the block of the height. The signature makes the node
+/// accountable for the response, and clients check the state root is of a
+/// committed block by the proof of the block.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct QueryAttestation {
+    pub height:      u64,
+    pub state_root:  MerkleRoot,
+    pub result_hash: Hash,
+}
+
+impl QueryAttestation {
+    pub fn new(
+        height: u64,
+        state_root: MerkleRoot,
+        caller: &Address,
+        request: &TransactionRequest,
+        resp: &ServiceResponse<String>,
+    ) -> Self {
+        QueryAttestation {
+            height,
+            state_root,
+            result_hash: query_result_hash(caller, request, resp),
+        }
+    }
+
+    /// Hash signed by the secp256k1 key of the node.
+    pub fn signing_hash(&self, domain: Option<&SignatureDomain>) -> Hash {
+        let mut stream = rlp::RlpStream::new_list(3);
+        stream
+            .append(&self.height)
+            .append(&self.state_root)
+            .append(&self.result_hash);
+
+        let hash = Hash::digest(Bytes::from(stream.out()));
+        signing_hash(domain, QUERY_SIGNATURE_PURPOSE, &hash)
+    }
+}
+
+/// Hash of a query and its response, so that a response can't be presented
+/// as the answer of another query.
+pub fn query_result_hash(
+    caller: &Address,
+    request: &TransactionRequest,
+    resp: &ServiceResponse<String>,
+) -> Hash {
+    let mut stream = rlp::RlpStream::new_list(7);
+    stream
+        .append(caller)
+        .append(&request.service_name)
+        .append(&request.method)
+        .append(&request.payload)
+        .append(&resp.code)
+        .append(&resp.succeed_data)
+        .append(&resp.error_message);
+
+    Hash::digest(Bytes::from(stream.out()))
+}
@@ -1,23 +1,27 @@
+use serde::{Deserialize, Serialize};
+
 use crate::traits::ServiceResponse;
 use crate::types::{Hash, MerkleRoot};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Event {
     pub service: String,
     pub data:    String,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Receipt {
     pub state_root:  MerkleRoot,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub height:      u64,
     pub tx_hash:     Hash,
+    #[serde(with = "crate::types::canonical::uint64")]
     pub cycles_used: u64,
     pub events:      Vec<Event>,
     pub response:    ReceiptResponse,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct ReceiptResponse {
     pub service_name: String,
     pub method:       String,
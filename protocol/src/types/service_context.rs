@@ -1,4 +1,6 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::panic;
 use std::rc::Rc;
 
 use bytes::Bytes;
@@ -7,6 +9,26 @@ use derive_more::{Display, From};
 use crate::types::{Address, Event, Hash};
 use crate::{ProtocolError, ProtocolErrorKind};
 
+/// Cycles charged by the framework on every call, on top of the base cost
+/// declared by `#[cycles(n)]` on service methods. They're charged from
+/// `Feature::CyclesPricing`, calls of earlier blocks only pay the base cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CyclesPricing {
+    pub storage_read_per_byte:  u64,
+    pub storage_write_per_byte: u64,
+    pub event_base:             u64,
+    pub event_per_byte:         u64,
+    pub service_call:           u64,
+}
+
+pub const CYCLES_PRICING: CyclesPricing = CyclesPricing {
+    storage_read_per_byte:  1,
+    storage_write_per_byte: 10,
+    event_base:             100,
+    event_per_byte:         1,
+    service_call:           1000,
+};
+
 #[derive(Debug, Clone)]
 pub struct ServiceContextParams {
     pub tx_hash:         Option<Hash>,
@@ -39,6 +61,16 @@ pub struct ServiceContext {
     extra:           Option<Bytes>,
     timestamp:       u64,
     events:          Rc<RefCell<Vec<Event>>>,
+    // Service making the call, None for transactions
+    caller_service:  Option<String>,
+
+    // Bytes stored by owners during the transaction, negative if released,
+    // None unless storage deposit is enabled
+    storage_deposits: Option<Rc<RefCell<BTreeMap<Address, i64>>>>,
+    // Cycles of `CYCLES_PRICING` are charged
+    cycles_pricing:   bool,
+    // Writes of the transaction were reverted since its caller couldn't pay
+    fee_failed:       bool,
 }
 
 impl ServiceContext {
@@ -57,9 +89,35 @@ impl ServiceContext {
             extra:           params.extra,
             timestamp:       params.timestamp,
             events:          params.events,
+            caller_service:  None,
+
+            storage_deposits: None,
+            cycles_pricing:   false,
+            fee_failed:       false,
         }
     }
 
+    /// Charge storage access, events and service calls through the context
+    /// by `CYCLES_PRICING`, calls made with it are charged as well.
+    pub fn with_cycles_pricing(mut self) -> Self {
+        self.cycles_pricing = true;
+        self
+    }
+
+    /// Record bytes stored by owners through the context, calls made with
+    /// it share the records.
+    pub fn with_storage_deposit(mut self) -> Self {
+        self.storage_deposits = Some(Rc::new(RefCell::new(BTreeMap::new())));
+        self
+    }
+
+    /// Context of tx hooks run again once the transaction failed to pay its
+    /// fee, see `fail_insufficient_fee`.
+    pub fn with_fee_failed(mut self) -> Self {
+        self.fee_failed = true;
+        self
+    }
+
     pub fn with_context(
         context: &ServiceContext,
         extra: Option<Bytes>,
@@ -81,9 +139,21 @@ impl ServiceContext {
             extra,
             timestamp: context.get_timestamp(),
             events: Rc::clone(&context.events),
+            caller_service: Some(context.service_name.clone()),
+            storage_deposits: context.storage_deposits.clone(),
+            cycles_pricing: context.cycles_pricing,
+            fee_failed: context.fee_failed,
         }
     }
 
+    /// Context of calls made by the service on its own behalf, caller is
+    /// the address of the service, such as an escrow releasing assets.
+    pub fn with_service_caller(context: &ServiceContext) -> Self {
+        let mut ctx = context.clone();
+        ctx.caller = service_address(&context.service_name);
+        ctx
+    }
+
     pub fn get_tx_hash(&self) -> Option<Hash> {
         self.tx_hash.clone()
     }
@@ -105,6 +175,25 @@ impl ServiceContext {
         }
     }
 
+    /// Charge cycles consumed by the framework, such as storage access and
+    /// events. Once cycles limit is reached, the call unwinds with
+    /// `ServiceContextError::OutOfCycles`, executor reverts the whole
+    /// transaction then.
+    pub fn charge_cycles(&self, cycles: u64) {
+        if !self.sub_cycles(cycles) {
+            *self.cycles_used.borrow_mut() = self.cycles_limit;
+            panic::resume_unwind(Box::new(ServiceContextError::OutOfCycles));
+        }
+    }
+
+    /// Charge cycles of `CYCLES_PRICING`, nothing unless the pricing is
+    /// enabled for the context.
+    pub fn charge_priced_cycles(&self, cycles: u64) {
+        if self.cycles_pricing {
+            self.charge_cycles(cycles);
+        }
+    }
+
     pub fn get_cycles_price(&self) -> u64 {
         self.cycles_price
     }
@@ -121,6 +210,10 @@ impl ServiceContext {
         self.caller.clone()
     }
 
+    pub fn get_caller_service(&self) -> Option<&str> {
+        self.caller_service.as_ref().map(String::as_str)
+    }
+
     pub fn get_current_height(&self) -> u64 {
         self.height
     }
@@ -141,11 +234,68 @@ impl ServiceContext {
         self.extra.clone()
     }
 
+    /// Milliseconds timestamp of the block being executed, or of the latest
+    /// block in reads. It's after the timestamp of the parent block, and was
+    /// within the max timestamp drift of the local clock of the validators
+    /// voting for it, so the proposer can't skew it further than that bound.
     pub fn get_timestamp(&self) -> u64 {
         self.timestamp
     }
 
+    pub fn is_fee_failed(&self) -> bool {
+        self.fee_failed
+    }
+
+    /// Fail the transaction since its caller can't pay the fee, called by
+    /// fee hooks after the transaction. The call unwinds with
+    /// `ServiceContextError::InsufficientFee`, executor reverts writes of
+    /// the transaction and runs tx hooks again with `is_fee_failed`, then
+    /// fee is paid by as much as the balance covers.
+    pub fn fail_insufficient_fee(&self) -> ! {
+        panic::resume_unwind(Box::new(ServiceContextError::InsufficientFee))
+    }
+
+    pub fn is_storage_deposit_enabled(&self) -> bool {
+        self.storage_deposits.is_some()
+    }
+
+    /// Bytes are positive if stored by the owner, negative if released.
+    /// Nothing is recorded if storage deposit is disabled.
+    pub fn change_storage_deposit(&self, owner: &Address, bytes: i64) {
+        if let Some(deposits) = self.storage_deposits.as_ref() {
+            *deposits.borrow_mut().entry(owner.clone()).or_insert(0) += bytes;
+        }
+    }
+
+    /// Changes of stored bytes by owners, in order of addresses. Zero
+    /// changes are skipped.
+    pub fn get_storage_deposit_changes(&self) -> Vec<(Address, i64)> {
+        self.storage_deposits
+            .as_ref()
+            .map(|deposits| {
+                deposits
+                    .borrow()
+                    .iter()
+                    .filter(|(_, bytes)| **bytes != 0)
+                    .map(|(owner, bytes)| (owner.clone(), *bytes))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Writes of a failed call are reverted, so are bytes stored by them.
+    pub fn clear_storage_deposit_changes(&self) {
+        if let Some(deposits) = self.storage_deposits.as_ref() {
+            deposits.borrow_mut().clear();
+        }
+    }
+
     pub fn emit_event(&self, message: String) {
+        let pricing = CYCLES_PRICING;
+        self.charge_priced_cycles(
+            pricing.event_base + pricing.event_per_byte * message.len() as u64,
+        );
+
         self.events.borrow_mut().push(Event {
             service: self.service_name.clone(),
             data:    message,
@@ -153,10 +303,19 @@ impl ServiceContext {
     }
 }
 
+/// Address owned by a service, no private key derives it.
+pub fn service_address(service_name: &str) -> Address {
+    let hash = Hash::digest(Bytes::from("service:".to_owned() + service_name));
+    Address::from_hash(hash).expect("hash is longer than address")
+}
+
 #[derive(Debug, Display, From)]
 pub enum ServiceContextError {
     #[display(fmt = "out of cycles")]
     OutOfCycles,
+
+    #[display(fmt = "insufficient balance for fee")]
+    InsufficientFee,
 }
 
 impl std::error::Error for ServiceContextError {}
@@ -170,11 +329,60 @@ impl From<ServiceContextError> for ProtocolError {
 #[cfg(test)]
 mod tests {
     use std::cell::RefCell;
+    use std::panic::{self, AssertUnwindSafe};
     use std::rc::Rc;
 
-    use super::{ServiceContext, ServiceContextParams};
+    use super::{ServiceContext, ServiceContextError, ServiceContextParams, CYCLES_PRICING};
     use crate::types::{Address, Hash};
 
+    fn mock_context(cycles_limit: u64) -> ServiceContext {
+        let params = ServiceContextParams {
+            tx_hash: None,
+            nonce: None,
+            cycles_limit,
+            cycles_price: 1,
+            cycles_used: Rc::new(RefCell::new(0)),
+            caller: Address::from_hash(Hash::from_empty()).unwrap(),
+            height: 1,
+            timestamp: 0,
+            service_name: "service_name".to_owned(),
+            service_method: "service_method".to_owned(),
+            service_payload: "service_payload".to_owned(),
+            extra: None,
+            events: Rc::new(RefCell::new(vec![])),
+        };
+
+        ServiceContext::new(params)
+    }
+
+    #[test]
+    fn test_charge_event_cycles() {
+        let ctx = mock_context(1000);
+        ctx.emit_event("event".to_owned());
+        assert_eq!(ctx.get_cycles_used(), 0);
+
+        let ctx = mock_context(1000).with_cycles_pricing();
+
+        ctx.emit_event("event".to_owned());
+        assert_eq!(
+            ctx.get_cycles_used(),
+            CYCLES_PRICING.event_base + CYCLES_PRICING.event_per_byte * 5
+        );
+        assert_eq!(ctx.get_events().len(), 1);
+    }
+
+    #[test]
+    fn test_charge_cycles_out_of_cycles() {
+        let ctx = mock_context(100);
+
+        let unwind = panic::catch_unwind(AssertUnwindSafe(|| ctx.charge_cycles(101))).unwrap_err();
+        match unwind.downcast_ref::<ServiceContextError>() {
+            Some(ServiceContextError::OutOfCycles) => (),
+            _ => panic!("should unwind with out of cycles"),
+        }
+        assert_eq!(ctx.get_cycles_used(), 100);
+    }
+
     #[test]
     fn test_request_context() {
         let params = ServiceContextParams {
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use cita_trie::{MemoryDB, PatriciaTrie, Trie};
+use derive_more::Display;
+use hasher::HasherKeccak;
+use serde::{Deserialize, Serialize};
+
+use crate::fixed_codec::FixedCodec;
+use crate::types::MerkleRoot;
+use crate::{ProtocolError, ProtocolErrorKind, ProtocolResult};
+
+/// Scheme the state of a chain is committed in, it's chosen in genesis and
+/// never changes. Proofs of keys are verified by the scheme of the chain.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Display, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitmentScheme {
+    #[display(fmt = "merkle patricia")]
+    MerklePatricia,
+}
+
+impl Default for CommitmentScheme {
+    fn default() -> Self {
+        CommitmentScheme::MerklePatricia
+    }
+}
+
+impl CommitmentScheme {
+    /// Value of the key committed in root, none if the proof proves absence.
+    pub fn verify_proof(
+        &self,
+        root: &MerkleRoot,
+        key: &Bytes,
+        proof: &[Bytes],
+    ) -> ProtocolResult<Option<Bytes>> {
+        match self {
+            CommitmentScheme::MerklePatricia => verify_trie_proof(root, key, proof),
+        }
+    }
+}
+
+/// Merkle proof of a key in the state of a service. State root of a block
+/// maps service names to service roots, so the proof has two levels, the
+/// service root in block state and the key in service state.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StateProof {
+    // Proofs made before schemes are merkle patricia ones
+    #[serde(default)]
+    pub scheme:        CommitmentScheme,
+    pub service_name:  String,
+    #[serde(with = "crate::types::canonical::hex_bytes")]
+    pub key:           Bytes,
+    pub service_root:  MerkleRoot,
+    #[serde(with = "crate::types::canonical::vec_hex_bytes")]
+    pub service_proof: Vec<Bytes>,
+    #[serde(with = "crate::types::canonical::vec_hex_bytes")]
+    pub key_proof:     Vec<Bytes>,
+}
+
+impl StateProof {
+    /// Verify both levels against the state root, return the value of key,
+    /// none if the key doesn't exist.
+    pub fn verify(&self, state_root: &MerkleRoot) -> ProtocolResult<Option<Bytes>> {
+        let service_key = self.service_name.encode_fixed()?;
+        let root_value = self
+            .scheme
+            .verify_proof(state_root, &service_key, &self.service_proof)?
+            .ok_or(StateProofError::ServiceNotFound)?;
+
+        if MerkleRoot::decode_fixed(root_value)? != self.service_root {
+            return Err(StateProofError::ServiceRootMismatch.into());
+        }
+
+        self.scheme
+            .verify_proof(&self.service_root, &self.key, &self.key_proof)
+    }
+}
+
+fn verify_trie_proof(
+    root: &MerkleRoot,
+    key: &Bytes,
+    proof: &[Bytes],
+) -> ProtocolResult<Option<Bytes>> {
+    // Nodes are taken from proof, the db is never read
+    let trie = PatriciaTrie::new(Arc::new(MemoryDB::new(true)), Arc::new(HasherKeccak::new()));
+    let proof = proof.iter().map(|node| node.to_vec()).collect();
+
+    let value = trie
+        .verify_proof(&root.as_bytes(), key, proof)
+        .map_err(|e| StateProofError::InvalidProof(format!("{:?}", e)))?;
+    Ok(value.map(Bytes::from))
+}
+
+#[derive(Debug, Display)]
+pub enum StateProofError {
+    #[display(fmt = "invalid proof {:?}", _0)]
+    InvalidProof(String),
+
+    #[display(fmt = "service not found in state")]
+    ServiceNotFound,
+
+    #[display(fmt = "service root mismatch")]
+    ServiceRootMismatch,
+}
+
+impl std::error::Error for StateProofError {}
+
+impl From<StateProofError> for ProtocolError {
+    fn from(err: StateProofError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Types, Box::new(err))
+    }
+}
@@ -0,0 +1,293 @@
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::traits::ServiceResponse;
+use crate::types::{
+    Address, Block, BlockHeader, ChainSpec, Event, Feature, Hash, Hex, Metadata, PayloadLimits,
+    Proof, QueryAttestation, RawTransaction, Receipt, ReceiptResponse, SignatureDomain,
+    SignedTransaction, TransactionRequest, Validator, ValidatorExtend,
+};
+
+// Json of the value must be the same as the golden file, the layout is relied
+// on by graphql clients and genesis files. Update the golden file only if the
+// change is intended.
+fn assert_golden<T>(val: &T, golden: &str)
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    assert_eq!(
+        serde_json::to_string_pretty(val).unwrap(),
+        golden.trim_end()
+    );
+    assert_eq!(&serde_json::from_str::<T>(golden).unwrap(), val);
+}
+
+fn hash(n: u8) -> Hash {
+    Hash::from_bytes(Bytes::from(vec![n; 32])).unwrap()
+}
+
+fn address(n: u8) -> Address {
+    Address::from_bytes(Bytes::from(vec![n; 20])).unwrap()
+}
+
+fn mock_proof() -> Proof {
+    Proof {
+        height:     9,
+        round:      1,
+        block_hash: hash(8),
+        signature:  Bytes::from_static(b"\xab\xcd"),
+        bitmap:     Bytes::from_static(b"\x01"),
+    }
+}
+
+#[test]
+fn test_golden_block() {
+    let block = Block {
+        header:            BlockHeader {
+            chain_id:          hash(1),
+            // Larger than integers of javascript
+            height:            9_007_199_254_740_993,
+            exec_height:       9,
+            pre_hash:          hash(2),
+            timestamp:         1_600_000_000_000,
+            logs_bloom:        vec![],
+            order_root:        hash(3),
+            confirm_root:      vec![hash(4)],
+            state_root:        hash(5),
+            receipt_root:      vec![hash(6)],
+            cycles_used:       vec![100],
+            proposer:          address(7),
+            proof:             mock_proof(),
+            validator_version: 0,
+            validators:        vec![Validator {
+                address:        address(7),
+                propose_weight: 1,
+                vote_weight:    1,
+            }],
+            extra_data:        Bytes::new(),
+        },
+        ordered_tx_hashes: vec![hash(9)],
+        vote_extensions:   vec![],
+    };
+
+    assert_golden(&block, include_str!("golden/block.json"));
+}
+
+#[test]
+fn test_golden_signed_transaction() {
+    let stx = SignedTransaction {
+        raw:       RawTransaction {
+            chain_id:              hash(1),
+            nonce:                 hash(2),
+            timeout:               100,
+            cycles_price:          1,
+            cycles_limit:          u64::max_value(),
+            request:               TransactionRequest {
+                service_name: "asset".to_owned(),
+                method:       "transfer".to_owned(),
+                payload:      "{}".to_owned(),
+            },
+            sender:                None,
+            valid_until_timestamp: Some(1_600_000_000_000),
+        },
+        tx_hash:   hash(3),
+        pubkey:    Bytes::from_static(b"\x02\x03"),
+        signature: Bytes::from_static(b"\x04"),
+    };
+
+    assert_golden(&stx, include_str!("golden/signed_transaction.json"));
+}
+
+#[test]
+fn test_golden_receipt() {
+    let receipt = Receipt {
+        state_root:  hash(1),
+        height:      10,
+        tx_hash:     hash(2),
+        cycles_used: 21000,
+        events:      vec![Event {
+            service: "asset".to_owned(),
+            data:    "{}".to_owned(),
+        }],
+        response:    ReceiptResponse {
+            service_name: "asset".to_owned(),
+            method:       "transfer".to_owned(),
+            response:     ServiceResponse::from_succeed("".to_owned()),
+        },
+    };
+
+    assert_golden(&receipt, include_str!("golden/receipt.json"));
+}
+
+#[test]
+fn test_golden_metadata() {
+    let metadata = Metadata {
+        chain_id:                 hash(1),
+        common_ref:               Hex::from_static(b"\x01\xab"),
+        timeout_gap:              20,
+        cycles_limit:             999_999_999_999,
+        cycles_price:             1,
+        interval:                 3000,
+        verifier_list:            vec![ValidatorExtend {
+            bls_pub_key:    Hex::from_static(b"\x04\x18"),
+            address:        address(7),
+            propose_weight: 1,
+            vote_weight:    1,
+        }],
+        propose_ratio:            15,
+        prevote_ratio:            10,
+        precommit_ratio:          10,
+        brake_ratio:              7,
+        tx_num_limit:             20000,
+        max_tx_size:              1024,
+        proposer_fee_ratio:       0,
+        treasury_fee_ratio:       0,
+        treasury:                 address(0),
+        network_tag:              "mainnet".to_owned(),
+        domain_separation_height: 0,
+        max_block_size:           0,
+        tx_base_cycles:           0,
+        storage_deposit_per_byte: 0,
+        rent_epoch:               0,
+        rent_per_byte:            0,
+        halt_height:              0,
+        upgrade_name:             String::new(),
+        upgrade_window:           0,
+        upgrade_threshold:        0,
+        upgrade_height:           0,
+        chain_spec:               ChainSpec::default(),
+    };
+
+    assert_golden(&metadata, include_str!("golden/metadata.json"));
+}
+
+#[test]
+fn test_legacy_json() {
+    // Numbers and arrays of bytes written before canonical json
+    let json = r#"{
+        "height": 9,
+        "round": 1,
+        "block_hash": "0x0808080808080808080808080808080808080808080808080808080808080808",
+        "signature": [171, 205],
+        "bitmap": "01"
+    }"#;
+
+    assert_eq!(serde_json::from_str::<Proof>(json).unwrap(), mock_proof());
+    assert!(serde_json::from_str::<Proof>(&json.replace("9,", "-9,")).is_err());
+}
+
+#[test]
+fn test_bincode_keeps_native_encoding() {
+    #[derive(Serialize)]
+    struct NativeProof {
+        height:     u64,
+        round:      u64,
+        block_hash: Hash,
+        signature:  Bytes,
+        bitmap:     Bytes,
+    }
+
+    let proof = mock_proof();
+    let native = NativeProof {
+        height:     proof.height,
+        round:      proof.round,
+        block_hash: proof.block_hash.clone(),
+        signature:  proof.signature.clone(),
+        bitmap:     proof.bitmap.clone(),
+    };
+
+    let bytes = bincode::serialize(&proof).unwrap();
+    assert_eq!(bytes, bincode::serialize(&native).unwrap());
+    assert_eq!(bincode::deserialize::<Proof>(&bytes).unwrap(), proof);
+}
+
+#[test]
+fn test_query_attestation_binds_query() {
+    let request = TransactionRequest {
+        service_name: "asset".to_owned(),
+        method:       "get_balance".to_owned(),
+        payload:      "{}".to_owned(),
+    };
+    let resp = ServiceResponse::from_succeed("100".to_owned());
+    let attestation = QueryAttestation::new(9, hash(5), &address(7), &request, &resp);
+
+    // Same response of another caller or another query
+    let other_caller = QueryAttestation::new(9, hash(5), &address(8), &request, &resp);
+    assert_ne!(attestation.result_hash, other_caller.result_hash);
+    let mut other_request = request.clone();
+    other_request.method = "get_asset".to_owned();
+    let other_query = QueryAttestation::new(9, hash(5), &address(7), &other_request, &resp);
+    assert_ne!(attestation.result_hash, other_query.result_hash);
+
+    let domain = SignatureDomain {
+        network_tag: "mainnet".to_owned(),
+        chain_id:    hash(1),
+    };
+    assert_ne!(
+        attestation.signing_hash(None),
+        attestation.signing_hash(Some(&domain))
+    );
+    assert_ne!(
+        attestation.signing_hash(None),
+        other_caller.signing_hash(None)
+    );
+}
+
+#[test]
+fn test_payload_limits() {
+    let mut limits = PayloadLimits::default();
+    limits.insert("anchor", "anchor", 1_048_576);
+    limits.insert("asset", "transfer", 8);
+
+    let mut request = TransactionRequest {
+        service_name: "asset".to_owned(),
+        method:       "transfer".to_owned(),
+        payload:      "12345678".to_owned(),
+    };
+    assert_eq!(limits.exceeded_by(&request), None);
+    request.payload.push('9');
+    assert_eq!(limits.exceeded_by(&request), Some(8));
+
+    // Limits are by methods, not services
+    request.method = "approve".to_owned();
+    assert_eq!(limits.exceeded_by(&request), None);
+    assert_eq!(limits.get("anchor", "anchor"), Some(1_048_576));
+}
+
+#[test]
+fn test_chain_spec() {
+    let chain_spec: ChainSpec = serde_json::from_str(
+        r#"[{"name": "signature_domain", "height": "10"}, {"name": "fork", "height": "20"}]"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        chain_spec.activation_height(Feature::SignatureDomain),
+        Some(10)
+    );
+    assert!(!chain_spec.is_active(Feature::SignatureDomain, 9));
+    assert_eq!(chain_spec.features_at(10), vec![Feature::SignatureDomain]);
+    assert_eq!(chain_spec.unsupported_at(19), None);
+    assert_eq!(chain_spec.unsupported_at(20).unwrap().name, "fork");
+
+    // Domain separation is enabled by the feature as well
+    let metadata = Metadata {
+        chain_spec,
+        ..Default::default()
+    };
+    assert_eq!(metadata.signature_domain(9), None);
+    assert!(metadata.signature_domain(10).is_some());
+}
+
+#[test]
+fn test_chain_spec_schedule() {
+    let mut chain_spec = ChainSpec::default();
+    chain_spec.schedule("extra_data", 20);
+    assert_eq!(chain_spec.activation_height(Feature::ExtraData), Some(20));
+
+    // Scheduled once, by the earliest height
+    chain_spec.schedule("extra_data", 30);
+    chain_spec.schedule("extra_data", 10);
+    assert_eq!(chain_spec.activations.len(), 1);
+    assert_eq!(chain_spec.activation_height(Feature::ExtraData), Some(10));
+}
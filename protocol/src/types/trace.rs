@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+use crate::traits::ServiceResponse;
+use crate::types::{Bytes, Hash};
+
+/// Steps of a transaction re-executed against the state of its block, in
+/// the order they happened.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TxTrace {
+    pub tx_hash:       Hash,
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub height:        u64,
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub cycles_used:   u64,
+    pub service_name:  String,
+    pub method:        String,
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub code:          u64,
+    pub error_message: String,
+    pub steps:         Vec<TraceStep>,
+}
+
+impl TxTrace {
+    pub fn is_error(&self) -> bool {
+        self.code != 0
+    }
+}
+
+/// One step of a trace. Cycles used are counted by the transaction up to
+/// the end of the step, calls panicked, such as out of cycles, have no
+/// return step.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TraceStep {
+    // Calls made by the transaction are at depth 0
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub depth:       u64,
+    pub kind:        TraceStepKind,
+    pub service:     String,
+    // Method of calls and returns
+    pub method:      String,
+    // Fixed encoded key of storage access
+    #[serde(default, with = "crate::types::canonical::option_hex_bytes")]
+    pub key:         Option<Bytes>,
+    // Payload of calls, response of returns and data of events
+    pub data:        String,
+    // Response code of returns
+    #[serde(default, with = "crate::types::canonical::option_uint64")]
+    pub code:        Option<u64>,
+    // Bytes of the value read or written
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub size:        u64,
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub cycles_used: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceStepKind {
+    Call,
+    Return,
+    Read,
+    Write,
+    Event,
+}
+
+impl TraceStep {
+    pub fn call(depth: u64, service: &str, method: &str, payload: &str, cycles_used: u64) -> Self {
+        TraceStep {
+            depth,
+            kind: TraceStepKind::Call,
+            service: service.to_owned(),
+            method: method.to_owned(),
+            key: None,
+            data: payload.to_owned(),
+            code: None,
+            size: 0,
+            cycles_used,
+        }
+    }
+
+    pub fn ret(
+        depth: u64,
+        service: &str,
+        method: &str,
+        resp: &ServiceResponse<String>,
+        cycles_used: u64,
+    ) -> Self {
+        let data = if resp.is_error() {
+            resp.error_message.clone()
+        } else {
+            resp.succeed_data.clone()
+        };
+
+        TraceStep {
+            depth,
+            kind: TraceStepKind::Return,
+            service: service.to_owned(),
+            method: method.to_owned(),
+            key: None,
+            data,
+            code: Some(resp.code),
+            size: 0,
+            cycles_used,
+        }
+    }
+
+    pub fn access(
+        depth: u64,
+        kind: TraceStepKind,
+        service: &str,
+        key: Bytes,
+        size: u64,
+        cycles_used: u64,
+    ) -> Self {
+        TraceStep {
+            depth,
+            kind,
+            service: service.to_owned(),
+            method: "".to_owned(),
+            key: Some(key),
+            data: "".to_owned(),
+            code: None,
+            size,
+            cycles_used,
+        }
+    }
+
+    pub fn event(depth: u64, service: &str, data: &str, cycles_used: u64) -> Self {
+        TraceStep {
+            depth,
+            kind: TraceStepKind::Event,
+            service: service.to_owned(),
+            method: "".to_owned(),
+            key: None,
+            data: data.to_owned(),
+            code: None,
+            size: 0,
+            cycles_used,
+        }
+    }
+}
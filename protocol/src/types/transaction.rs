@@ -1,28 +1,97 @@
+use std::collections::HashMap;
+
 use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::types::primitive::{Address, Hash, JsonString};
 
-use crate::types::primitive::{Hash, JsonString};
+// Transactions calling the service are governance ones, they are still
+// packaged and executed while the chain is halted.
+pub const GOVERNANCE_SERVICE: &str = "metadata";
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct RawTransaction {
-    pub chain_id:     Hash,
-    pub nonce:        Hash,
-    pub timeout:      u64,
-    pub cycles_price: u64,
-    pub cycles_limit: u64,
-    pub request:      TransactionRequest,
+    pub chain_id:              Hash,
+    pub nonce:                 Hash,
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub timeout:               u64,
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub cycles_price:          u64,
+    #[serde(with = "crate::types::canonical::uint64")]
+    pub cycles_limit:          u64,
+    pub request:               TransactionRequest,
+    // Account the transaction acts for, the signer itself if none. Signer
+    // must be authorized by the account service to act for it.
+    #[serde(default)]
+    pub sender:                Option<Address>,
+    // Unix timestamp in milliseconds, the transaction is not executed in
+    // blocks after it no matter how far `timeout` is.
+    #[serde(default, with = "crate::types::canonical::option_uint64")]
+    pub valid_until_timestamp: Option<u64>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct TransactionRequest {
     pub service_name: String,
     pub method:       String,
     pub payload:      JsonString,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+impl TransactionRequest {
+    pub fn is_governance(&self) -> bool {
+        self.service_name == GOVERNANCE_SERVICE
+    }
+}
+
+/// Max sizes of payloads in bytes by methods of services, declared by
+/// `#[max_payload_size]` in schemas of services. Payloads of other methods
+/// are limited by `max_tx_size` of metadata only.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PayloadLimits {
+    limits: HashMap<String, HashMap<String, u64>>,
+}
+
+impl PayloadLimits {
+    pub fn insert(&mut self, service: &str, method: &str, limit: u64) {
+        self.limits
+            .entry(service.to_owned())
+            .or_default()
+            .insert(method.to_owned(), limit);
+    }
+
+    pub fn get(&self, service: &str, method: &str) -> Option<u64> {
+        self.limits
+            .get(service)
+            .and_then(|methods| methods.get(method))
+            .copied()
+    }
+
+    /// The limit of the method called by the request, if its payload is
+    /// larger than that.
+    pub fn exceeded_by(&self, request: &TransactionRequest) -> Option<u64> {
+        self.get(&request.service_name, &request.method)
+            .filter(|limit| request.payload.len() as u64 > *limit)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct SignedTransaction {
     pub raw:       RawTransaction,
     pub tx_hash:   Hash,
+    #[serde(with = "crate::types::canonical::hex_bytes")]
     pub pubkey:    Bytes,
+    #[serde(with = "crate::types::canonical::hex_bytes")]
     pub signature: Bytes,
 }
+
+/// Payload of `verify_authorization` in account service, executor calls it
+/// when signer of a transaction is not its sender.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VerifyAuthorizationPayload {
+    pub sender:       Address,
+    pub signer:       Address,
+    pub tx_hash:      Hash,
+    pub service_name: String,
+    pub method:       String,
+    pub payload:      JsonString,
+}
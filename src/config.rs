@@ -4,10 +4,25 @@ use std::path::PathBuf;
 
 use serde_derive::Deserialize;
 
-use core_mempool::{DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE};
-use protocol::types::Hex;
+use common_keystore::{read_passphrase, Keystore};
+use core_consensus::DEFAULT_MAX_TIMESTAMP_DRIFT;
+use core_exporter::DEFAULT_EXPORT_INTERVAL;
+use core_mempool::{
+    DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE, DEFAULT_REBROADCAST_AFTER,
+};
+use core_network::{CompressionKind, NodeRole};
+use framework::binding::state::DEFAULT_TRIE_CACHE_SIZE;
+use protocol::types::{Address, Hex};
+use protocol::ProtocolResult;
 
-#[derive(Debug, Deserialize)]
+use crate::MainError;
+
+const DEFAULT_PASSPHRASE_ENV: &str = "MUTA_KEYSTORE_PASSPHRASE";
+const DEFAULT_REMOTE_SIGNER_TIMEOUT: u64 = 1000;
+const DEFAULT_COLD_STORAGE_INTERVAL: u64 = 10_000;
+const DEFAULT_COLD_STORAGE_MAX_HEIGHTS: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ConfigGraphQL {
     pub listening_address: SocketAddr,
     pub graphql_uri:       String,
@@ -18,43 +33,131 @@ pub struct ConfigGraphQL {
     pub maxconn:           usize,
     #[serde(default)]
     pub max_payload_size:  usize,
+    pub ready_sync_gap:    Option<u64>,
+    pub admin_uri:         Option<String>,
+    // Admin requests carry it in the `x-admin-token` header, without it only
+    // requests from localhost are served
+    pub admin_token:       Option<String>,
+    pub archive_limits:    Option<ConfigArchiveLimits>,
+    // Archive limits of api keys, keys are sent in the `x-api-key` header
+    #[serde(default)]
+    pub api_keys:          HashMap<String, ConfigArchiveLimits>,
+    // Max number of cached responses of read queries by service
+    #[serde(default)]
+    pub query_cache:       HashMap<String, usize>,
+    // Responses of `signedQueryService` are signed by the node key
+    #[serde(default)]
+    pub sign_queries:      bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ConfigArchiveLimits {
+    pub max_page_size:    u64,
+    pub max_height_range: u64,
+    #[serde(default)]
+    pub trace:            bool,
+    #[serde(default)]
+    pub signed_query:     bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ConfigNetwork {
-    pub bootstraps:           Option<Vec<ConfigNetworkBootstrap>>,
-    pub whitelist:            Option<Vec<String>>,
-    pub whitelist_peers_only: Option<bool>,
-    pub max_connected_peers:  Option<usize>,
-    pub listening_address:    SocketAddr,
-    pub rpc_timeout:          Option<u64>,
-    pub selfcheck_interval:   Option<u64>,
-    pub send_buffer_size:     Option<usize>,
-    pub write_timeout:        Option<u64>,
-    pub recv_buffer_size:     Option<usize>,
-    pub max_frame_length:     Option<usize>,
-    pub max_wait_streams:     Option<usize>,
-}
-
-#[derive(Debug, Deserialize)]
+    pub bootstraps:               Option<Vec<ConfigNetworkBootstrap>>,
+    pub private_peers:            Option<Vec<ConfigNetworkPrivatePeer>>,
+    pub dns_seeds:                Option<Vec<String>>,
+    pub persist_peers:            Option<bool>,
+    pub whitelist:                Option<Vec<String>>,
+    pub whitelist_peers_only:     Option<bool>,
+    pub allowlist:                Option<Vec<Hex>>,
+    pub max_connected_peers:      Option<usize>,
+    pub listening_address:        SocketAddr,
+    pub rpc_timeout:              Option<u64>,
+    pub selfcheck_interval:       Option<u64>,
+    pub send_buffer_size:         Option<usize>,
+    pub write_timeout:            Option<u64>,
+    pub recv_buffer_size:         Option<usize>,
+    pub max_frame_length:         Option<usize>,
+    pub max_wait_streams:         Option<usize>,
+    pub compression:              Option<CompressionKind>,
+    pub max_pending_data_size:    Option<usize>,
+    pub max_blocked_duration:     Option<u64>,
+    // Bytes per second sent to all peers and to each peer
+    pub consensus_bandwidth:      Option<u64>,
+    pub peer_consensus_bandwidth: Option<u64>,
+    pub gossip_bandwidth:         Option<u64>,
+    pub peer_gossip_bandwidth:    Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ConfigNetworkBootstrap {
     pub pubkey:  Hex,
     pub address: String,
 }
 
-#[derive(Debug, Deserialize)]
+// Sentries of a validator and the validator behind them configure each other
+// as private peers.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ConfigNetworkPrivatePeer {
+    pub pubkey:  Hex,
+    // Peer without address is never dialed, it dials us
+    pub address: Option<String>,
+    // Relay consensus messages of the peer
+    #[serde(default)]
+    pub relay:   bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ConfigConsensus {
     pub sync_txs_chunk_size: usize,
+    // Proposals with timestamps off the local clock by more milliseconds are
+    // not voted, zero only checks they're after the parent block.
+    #[serde(default = "default_max_timestamp_drift")]
+    pub max_timestamp_drift: u64,
+    // Put into headers of proposed blocks, `ready:<fork>` signals upgrades
+    #[serde(default)]
+    pub extra_data:          String,
+    // Votes are signed by the bls key of the signer instead of `privkey`
+    pub remote_signer:       Option<ConfigRemoteSigner>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ConfigRemoteSigner {
+    pub address:           SocketAddr,
+    // Milliseconds to wait for a signature
+    #[serde(default = "default_remote_signer_timeout")]
+    pub timeout:           u64,
+    // Key shared with the signer, connections of both sides are
+    // authenticated by it
+    pub shared_key:        Hex,
+    // Address of the validator signed for, `privkey` is only the network
+    // key of the node if it's set
+    #[serde(default)]
+    pub validator_address: Option<Address>,
 }
 
 impl Default for ConfigConsensus {
     fn default() -> Self {
         Self {
             sync_txs_chunk_size: 5000,
+            max_timestamp_drift: DEFAULT_MAX_TIMESTAMP_DRIFT,
+            extra_data:          String::new(),
+            remote_signer:       None,
         }
     }
 }
 
+fn default_max_timestamp_drift() -> u64 {
+    DEFAULT_MAX_TIMESTAMP_DRIFT
+}
+
+fn default_remote_signer_timeout() -> u64 {
+    DEFAULT_REMOTE_SIGNER_TIMEOUT
+}
+
+fn default_passphrase_env() -> String {
+    DEFAULT_PASSPHRASE_ENV.to_owned()
+}
+
 fn default_broadcast_txs_size() -> usize {
     DEFAULT_BROADCAST_TXS_SIZE
 }
@@ -63,7 +166,15 @@ fn default_broadcast_txs_interval() -> u64 {
     DEFAULT_BROADCAST_TXS_INTERVAL
 }
 
-#[derive(Debug, Deserialize)]
+fn default_rebroadcast_after() -> u64 {
+    DEFAULT_REBROADCAST_AFTER
+}
+
+fn default_trie_cache_size() -> usize {
+    DEFAULT_TRIE_CACHE_SIZE
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ConfigMempool {
     pub pool_size: u64,
 
@@ -71,14 +182,96 @@ pub struct ConfigMempool {
     pub broadcast_txs_size:     usize,
     #[serde(default = "default_broadcast_txs_interval")]
     pub broadcast_txs_interval: u64,
+    // Accept transactions sent with `broadcast: false`, they're only
+    // packaged when this node proposes, so enable it on validators only
+    #[serde(default)]
+    pub allow_private_txs:      bool,
+    // Pending transactions are announced again after these blocks, then
+    // at doubled intervals, zero disables it
+    #[serde(default = "default_rebroadcast_after")]
+    pub rebroadcast_after:      u64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ConfigExecutor {
     pub light: bool,
+
+    // Bytes of trie nodes cached in memory
+    #[serde(default = "default_trie_cache_size")]
+    pub trie_cache_size: usize,
+}
+
+fn default_export_interval() -> u64 {
+    DEFAULT_EXPORT_INTERVAL
 }
 
-#[derive(Debug, Deserialize)]
+fn default_cold_storage_interval() -> u64 {
+    DEFAULT_COLD_STORAGE_INTERVAL
+}
+
+fn default_cold_storage_max_heights() -> u64 {
+    DEFAULT_COLD_STORAGE_MAX_HEIGHTS
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ConfigExporter {
+    // Directory of exported files and the progress cursor
+    pub path:         PathBuf,
+    #[serde(default)]
+    pub start_height: u64,
+    // Milliseconds between polls for newly executed blocks
+    #[serde(default = "default_export_interval")]
+    pub interval:     u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ConfigStream {
+    pub listening_address: SocketAddr,
+    // Directory of cursors acked by consumers
+    pub cursor_path:       PathBuf,
+    // Serve writes to the state, by replaying each height against its parent
+    #[serde(default)]
+    pub state_diff:        bool,
+    // Milliseconds between polls for newly executed blocks
+    #[serde(default = "default_export_interval")]
+    pub interval:          u64,
+}
+
+// Blocks with their transactions and receipts are moved to a compressed cold
+// store once they are more than `keep_heights` below the latest executed
+// height, reads fall through to it. Its path is recorded in the block db, the
+// cold store is still read after the section is removed, nothing is moved
+// back.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ConfigColdStorage {
+    pub keep_heights: u64,
+    // Directory of the cold store, data_path/rocksdb/cold_block_data if unset
+    pub path:         Option<PathBuf>,
+    // Milliseconds between moves, and max heights moved by each
+    #[serde(default = "default_cold_storage_interval")]
+    pub interval:     u64,
+    #[serde(default = "default_cold_storage_max_heights")]
+    pub max_heights:  u64,
+}
+
+// Hard caps of estimated bytes held by subsystems, zero means no cap. Data over
+// a cap is shed: new transactions are rejected by mempool, received messages
+// are dropped by network and blocks ahead of the chain are pulled again
+// instead of being buffered. Trie cache is capped by executor.trie_cache_size.
+// Received consensus and sync messages are capped by network_consensus only.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct ConfigMemory {
+    #[serde(default)]
+    pub mempool:           usize,
+    #[serde(default)]
+    pub network:           usize,
+    #[serde(default)]
+    pub network_consensus: usize,
+    #[serde(default)]
+    pub consensus_orphans: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ConfigRocksDB {
     pub max_open_files: i32,
 }
@@ -89,7 +282,7 @@ impl Default for ConfigRocksDB {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ConfigLogger {
     pub filter:                     String,
     pub log_to_console:             bool,
@@ -115,26 +308,69 @@ impl Default for ConfigLogger {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ConfigKeystore {
+    pub path:           PathBuf,
+    // Env var of the passphrase, it's prompted in the terminal if unset
+    #[serde(default = "default_passphrase_env")]
+    pub passphrase_env: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct Config {
-    // crypto
+    // crypto, left out if it's decrypted from the keystore. It's only the
+    // network key if `consensus.remote_signer.validator_address` is set
+    #[serde(default)]
     pub privkey:   Hex,
+    pub keystore:  Option<ConfigKeystore>,
     // db config
     pub data_path: PathBuf,
+    // Subsystems started by the role: validators run everything, full nodes
+    // don't run consensus and seed nodes only relay peers
+    #[serde(default)]
+    pub role:      NodeRole,
 
-    pub graphql:   ConfigGraphQL,
-    pub network:   ConfigNetwork,
-    pub mempool:   ConfigMempool,
-    pub executor:  ConfigExecutor,
+    pub graphql:      ConfigGraphQL,
+    pub network:      ConfigNetwork,
+    pub mempool:      ConfigMempool,
+    pub executor:     ConfigExecutor,
+    #[serde(default)]
+    pub consensus:    ConfigConsensus,
     #[serde(default)]
-    pub consensus: ConfigConsensus,
+    pub logger:       ConfigLogger,
     #[serde(default)]
-    pub logger:    ConfigLogger,
+    pub rocksdb:      ConfigRocksDB,
     #[serde(default)]
-    pub rocksdb:   ConfigRocksDB,
+    pub memory:       ConfigMemory,
+    pub exporter:     Option<ConfigExporter>,
+    pub stream:       Option<ConfigStream>,
+    pub cold_storage: Option<ConfigColdStorage>,
 }
 
 impl Config {
+    /// Decrypt `privkey` from the keystore, if the config has one instead of
+    /// the plaintext key.
+    pub fn unlock_keystore(&mut self) -> ProtocolResult<()> {
+        let has_privkey = !self.privkey.as_bytes().is_empty();
+        let keystore = match (&self.keystore, has_privkey) {
+            (Some(keystore), false) => keystore,
+            (None, true) => return Ok(()),
+            (Some(_), true) => {
+                return Err(MainError::Other("both privkey and keystore are set".to_owned()).into())
+            }
+            (None, false) => {
+                return Err(
+                    MainError::Other("either privkey or keystore is required".to_owned()).into(),
+                )
+            }
+        };
+
+        let passphrase = read_passphrase(&keystore.passphrase_env)?;
+        let privkey = Keystore::load(&keystore.path)?.decrypt(&passphrase)?;
+        self.privkey = Hex::from_bytes(privkey);
+        Ok(())
+    }
+
     pub fn data_path_for_state(&self) -> PathBuf {
         let mut path_state = self.data_path.clone();
         path_state.push("rocksdb");
@@ -149,6 +385,17 @@ impl Config {
         path_state
     }
 
+    pub fn data_path_for_cold_block(&self) -> PathBuf {
+        if let Some(path) = self.cold_storage.as_ref().and_then(|c| c.path.clone()) {
+            return path;
+        }
+
+        let mut path_state = self.data_path.clone();
+        path_state.push("rocksdb");
+        path_state.push("cold_block_data");
+        path_state
+    }
+
     pub fn data_path_for_txs_wal(&self) -> PathBuf {
         let mut path_state = self.data_path.clone();
         path_state.push("txs_wal");
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::sync::Arc;
+use std::time::Duration;
 
 use bytes::Bytes;
 use futures::{future, lock::Mutex};
@@ -11,35 +12,46 @@ use common_crypto::{
     BlsCommonReference, BlsPrivateKey, BlsPublicKey, PublicKey, Secp256k1, Secp256k1PrivateKey,
     ToPublicKey,
 };
+use common_keystore::{RemoteSigner, Signer};
 use core_api::adapter::DefaultAPIAdapter;
-use core_api::config::GraphQLConfig;
-use core_consensus::fixed_types::{FixedBlock, FixedSignedTxs};
+use core_api::archive::ArchiveLimits;
+use core_api::config::{GraphQLConfig, GraphQLLimits};
+use core_consensus::fixed_types::{FixedBlock, FixedProof, FixedSignedTxs};
 use core_consensus::message::{
-    ChokeMessageHandler, ProposalMessageHandler, PullBlockRpcHandler, PullTxsRpcHandler,
-    QCMessageHandler, RemoteHeightMessageHandler, VoteMessageHandler, BROADCAST_HEIGHT,
-    END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE, END_GOSSIP_SIGNED_PROPOSAL,
-    END_GOSSIP_SIGNED_VOTE, RPC_RESP_SYNC_PULL_BLOCK, RPC_RESP_SYNC_PULL_TXS, RPC_SYNC_PULL_BLOCK,
-    RPC_SYNC_PULL_TXS,
+    ChokeMessageHandler, ProposalMessageHandler, PullBlockRpcHandler, PullProofRpcHandler,
+    PullTxsRpcHandler, QCMessageHandler, RemoteHeightMessageHandler, VoteExtensionMessageHandler,
+    VoteMessageHandler, BROADCAST_HEIGHT, END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE,
+    END_GOSSIP_SIGNED_PROPOSAL, END_GOSSIP_SIGNED_VOTE, END_GOSSIP_VOTE_EXTENSION,
+    RPC_RESP_SYNC_PULL_BLOCK, RPC_RESP_SYNC_PULL_PROOF, RPC_RESP_SYNC_PULL_TXS,
+    RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_PROOF, RPC_SYNC_PULL_TXS,
 };
 use core_consensus::status::{CurrentConsensusStatus, StatusAgent};
 use core_consensus::util::OverlordCrypto;
 use core_consensus::{
     DurationConfig, Node, OverlordConsensus, OverlordConsensusAdapter, OverlordSynchronization,
-    RichBlock, SignedTxsWAL,
+    RichBlock, SignedTxsWAL, MAX_VOTE_EXTENSION_SIZE,
 };
+use core_exporter::{CsvExporter, ExportDaemon, StreamServer};
 use core_mempool::{
-    DefaultMemPoolAdapter, HashMemPool, MsgPushTxs, NewTxsHandler, PullTxsHandler,
-    END_GOSSIP_NEW_TXS, RPC_PULL_TXS, RPC_RESP_PULL_TXS,
+    DefaultMemPoolAdapter, HashMemPool, MsgPushTxs, NewTxHashesHandler, NewTxsHandler,
+    PullTxsHandler, END_GOSSIP_NEW_TXS, END_GOSSIP_NEW_TX_HASHES, RPC_PULL_TXS, RPC_RESP_PULL_TXS,
 };
-use core_network::{NetworkConfig, NetworkService};
-use core_storage::{adapter::rocks::RocksAdapter, ImplStorage};
+use core_network::{NetworkConfig, NetworkService, NodeRole};
 use framework::binding::state::RocksTrieDB;
-use framework::executor::{ServiceExecutor, ServiceExecutorFactory};
-use protocol::traits::{APIAdapter, Context, MemPool, NodeInfo, ServiceMapping, Storage};
-use protocol::types::{Address, Block, BlockHeader, Genesis, Hash, Metadata, Proof, Validator};
+use framework::executor::{MPTServiceExecutor, ServiceExecutorFactory};
+use protocol::traits::{
+    APIAdapter, Context, Executor, MemPool, NodeInfo, ServiceMapping, Storage, VoteExtender,
+};
+use protocol::types::{
+    Address, Block, BlockHeader, CommitmentScheme, Genesis, Hash, Metadata, Proof, Validator,
+    MAX_EXTRA_DATA_SIZE,
+};
 use protocol::{fixed_codec::FixedCodec, ProtocolResult};
 
-use crate::config::Config;
+use crate::config::{Config, ConfigArchiveLimits};
+use crate::reload::ConfigReloader;
+use crate::replay::ReplayStateDiffs;
+use crate::storage::{open_block_storage, run_cold_storage};
 use crate::MainError;
 
 pub async fn create_genesis<Mapping: 'static + ServiceMapping>(
@@ -64,12 +76,7 @@ pub async fn create_genesis<Mapping: 'static + ServiceMapping>(
     log::info!("Genesis data: {:?}", genesis);
 
     // Init Block db
-    let path_block = config.data_path_for_block();
-    let rocks_adapter = Arc::new(RocksAdapter::new(
-        path_block,
-        config.rocksdb.max_open_files,
-    )?);
-    let storage = Arc::new(ImplStorage::new(Arc::clone(&rocks_adapter)));
+    let storage = Arc::new(open_block_storage(config).await?);
 
     match storage.get_latest_block().await {
         Ok(genesis_block) => {
@@ -88,16 +95,19 @@ pub async fn create_genesis<Mapping: 'static + ServiceMapping>(
     let trie_db = Arc::new(RocksTrieDB::new(
         path_state,
         config.executor.light,
+        config.executor.trie_cache_size,
         config.rocksdb.max_open_files,
     )?);
 
-    // Init genesis
-    let genesis_state_root = ServiceExecutor::create_genesis(
-        genesis.services.clone(),
-        Arc::clone(&trie_db),
-        Arc::clone(&storage),
-        servive_mapping,
-    )?;
+    // Init genesis, state is committed in the scheme of the chain from now on
+    let genesis_state_root = match genesis.state_commitment {
+        CommitmentScheme::MerklePatricia => MPTServiceExecutor::create_genesis(
+            genesis.services.clone(),
+            Arc::clone(&trie_db),
+            Arc::clone(&storage),
+            servive_mapping,
+        )?,
+    };
 
     // Build genesis block.
     let genesis_block_header = BlockHeader {
@@ -122,11 +132,13 @@ pub async fn create_genesis<Mapping: 'static + ServiceMapping>(
         },
         validator_version: 0,
         validators,
+        extra_data: Bytes::new(),
     };
     let latest_proof = genesis_block_header.proof.clone();
     let genesis_block = Block {
         header:            genesis_block_header,
         ordered_tx_hashes: vec![],
+        vote_extensions:   vec![],
     };
     storage.insert_block(genesis_block.clone()).await?;
     storage.update_latest_proof(latest_proof).await?;
@@ -136,18 +148,20 @@ pub async fn create_genesis<Mapping: 'static + ServiceMapping>(
 }
 
 pub async fn start<Mapping: 'static + ServiceMapping>(
+    config_path: String,
     config: Config,
     service_mapping: Arc<Mapping>,
+    vote_extender: Option<Arc<dyn VoteExtender>>,
 ) -> ProtocolResult<()> {
     // Init Block db
-    let path_block = config.data_path_for_block();
-    log::info!("Data path for block: {:?}", path_block);
+    log::info!("Data path for block: {:?}", config.data_path_for_block());
 
-    let rocks_adapter = Arc::new(RocksAdapter::new(
-        path_block.clone(),
-        config.rocksdb.max_open_files,
-    )?);
-    let storage = Arc::new(ImplStorage::new(Arc::clone(&rocks_adapter)));
+    let storage = Arc::new(open_block_storage(&config).await?);
+
+    // Repair records of a commit interrupted by the last exit
+    for repair in storage.recover().await? {
+        log::warn!("muta: repaired {}", repair);
+    }
 
     // Init network
     let network_config = NetworkConfig::new()
@@ -159,7 +173,24 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         .max_frame_length(config.network.max_frame_length.clone())
         .send_buffer_size(config.network.send_buffer_size.clone())
         .write_timeout(config.network.write_timeout)
-        .recv_buffer_size(config.network.recv_buffer_size.clone());
+        .recv_buffer_size(config.network.recv_buffer_size.clone())
+        .compression(config.network.compression)
+        .max_pending_data_size(config.network.max_pending_data_size)
+        .max_blocked_duration(config.network.max_blocked_duration)
+        .consensus_bandwidth(
+            config.network.consensus_bandwidth,
+            config.network.peer_consensus_bandwidth,
+        )
+        .gossip_bandwidth(
+            config.network.gossip_bandwidth,
+            config.network.peer_gossip_bandwidth,
+        )
+        .max_inbound_memory(Some(config.memory.network))
+        .max_inbound_consensus_memory(Some(config.memory.network_consensus))
+        .dns_seeds(config.network.dns_seeds.clone().unwrap_or_default())
+        .enable_save_restore(config.network.persist_peers.unwrap_or(true))
+        .peer_dat_file(&config.data_path)
+        .node_role(config.role);
 
     let network_privkey = config.privkey.as_string_trim0x();
 
@@ -173,44 +204,93 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         }
     }
 
+    let mut private_pairs = vec![];
+    let mut relay_pubkeys = vec![];
+    if let Some(private_peers) = &config.network.private_peers {
+        for peer in private_peers.iter() {
+            private_pairs.push((peer.pubkey.as_string_trim0x(), peer.address.clone()));
+            if peer.relay {
+                relay_pubkeys.push(peer.pubkey.as_string_trim0x());
+            }
+        }
+    }
+
     let whitelist = config.network.whitelist.clone().unwrap_or_default();
+    let allowlist = config.network.allowlist.clone().unwrap_or_default();
+    let allowlist = allowlist.iter().map(|pk| pk.as_string_trim0x()).collect();
 
     let network_config = network_config
         .bootstraps(bootstrap_pairs)?
+        .private_peers(private_pairs)?
+        .relay_peers(relay_pubkeys)?
         .whitelist(whitelist)?
+        .allowlist(allowlist)?
         .secio_keypair(network_privkey)?;
     let mut network_service = NetworkService::new(network_config);
     network_service
         .listen(config.network.listening_address)
         .await?;
 
-    // Init mempool
-    let current_block = storage.get_latest_block().await?;
-    let mempool_adapter = DefaultMemPoolAdapter::<Secp256k1, _, _>::new(
-        network_service.handle(),
-        Arc::clone(&storage),
-        config.mempool.broadcast_txs_size,
-        config.mempool.broadcast_txs_interval,
-    );
-    let mempool = Arc::new(HashMemPool::new(
-        config.mempool.pool_size as usize,
-        mempool_adapter,
-    ));
+    // Seed nodes only relay peer addresses
+    if config.role == NodeRole::Seed {
+        log::info!("muta: run as a seed node");
+        tokio::spawn(network_service);
+        wait_for_exit(None).await;
+        return Ok(());
+    }
 
     // Init trie db
     let path_state = config.data_path_for_state();
     let trie_db = Arc::new(RocksTrieDB::new(
         path_state,
         config.executor.light,
+        config.executor.trie_cache_size,
         config.rocksdb.max_open_files,
     )?);
 
+    // Init mempool
+    let current_block = storage.get_latest_block().await?;
+    let mempool_adapter =
+        DefaultMemPoolAdapter::<Secp256k1, _, _, _, _, ServiceExecutorFactory>::new(
+            network_service.handle(),
+            Arc::clone(&storage),
+            Arc::clone(&trie_db),
+            Arc::clone(&service_mapping),
+            config.mempool.broadcast_txs_size,
+            config.mempool.broadcast_txs_interval,
+        );
+    let mempool = Arc::new(HashMemPool::new(
+        config.mempool.pool_size as usize,
+        mempool_adapter,
+    ));
+    mempool.set_memory_cap(config.memory.mempool);
+    mempool.set_allow_private_txs(config.mempool.allow_private_txs);
+    mempool.set_rebroadcast_after(config.mempool.rebroadcast_after);
+
+    // Payload limits are declared by schemas of services, they don't change
+    // with state
+    let payload_limits = MPTServiceExecutor::with_root(
+        current_block.header.state_root.clone(),
+        Arc::clone(&trie_db),
+        Arc::clone(&storage),
+        Arc::clone(&service_mapping),
+    )?
+    .payload_limits()?;
+    mempool.set_payload_limits(payload_limits.clone());
+
     // self private key
-    let hex_privkey = hex::decode(config.privkey.as_string_trim0x()).map_err(MainError::FromHex)?;
-    let my_privkey =
-        Secp256k1PrivateKey::try_from(hex_privkey.as_ref()).map_err(MainError::Crypto)?;
+    let my_privkey = Secp256k1PrivateKey::try_from(config.privkey.as_bytes().as_ref())
+        .map_err(MainError::Crypto)?;
     let my_pubkey = my_privkey.pub_key();
-    let my_address = Address::from_pubkey_bytes(my_pubkey.to_bytes())?;
+    let my_address = match config
+        .consensus
+        .remote_signer
+        .as_ref()
+        .and_then(|remote| remote.validator_address.clone())
+    {
+        Some(address) => address,
+        None => Address::from_pubkey_bytes(my_pubkey.to_bytes())?,
+    };
 
     // Get metadata
     let api_adapter = DefaultAPIAdapter::<ServiceExecutorFactory, _, _, _, _>::new(
@@ -218,7 +298,8 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         Arc::clone(&storage),
         Arc::clone(&trie_db),
         Arc::clone(&service_mapping),
-    );
+    )
+    .query_cache(config.graphql.query_cache.clone());
 
     // Create full transactions wal
     let wal_path = config.data_path_for_txs_wal().to_str().unwrap().to_string();
@@ -245,13 +326,25 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         metadata.timeout_gap,
         metadata.cycles_limit,
         metadata.max_tx_size,
+        metadata.signature_domain(current_block.header.height + 1),
+        metadata.halt_height,
     );
 
+    // Limit message sizes, so that a peer cannot flood us with messages which
+    // are never valid under current metadata
+    for (end, limit) in message_size_limits(&metadata, config.mempool.broadcast_txs_size) {
+        network_service.set_message_size_limit(end, limit)?;
+    }
+
     // register broadcast new transaction
     network_service.register_endpoint_handler(
         END_GOSSIP_NEW_TXS,
         Box::new(NewTxsHandler::new(Arc::clone(&mempool))),
     )?;
+    network_service.register_endpoint_handler(
+        END_GOSSIP_NEW_TX_HASHES,
+        Box::new(NewTxHashesHandler::new(Arc::clone(&mempool))),
+    )?;
 
     // register pull txs from other node
     network_service.register_endpoint_handler(
@@ -289,6 +382,7 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         current_height:             current_block.header.height,
         exec_height:                current_block.header.exec_height,
         current_hash:               block_hash,
+        current_timestamp:          current_header.timestamp,
         latest_commited_state_root: current_header.state_root.clone(),
         list_logs_bloom:            vec![],
         list_confirm_root:          vec![],
@@ -304,6 +398,8 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         brake_ratio:                metadata.brake_ratio,
         max_tx_size:                metadata.max_tx_size,
         tx_num_limit:               metadata.tx_num_limit,
+        max_block_size:             metadata.max_block_size,
+        chain_spec:                 metadata.chain_spec.clone(),
     };
 
     let consensus_interval = current_consensus_status.consensus_interval;
@@ -312,24 +408,38 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
     let mut bls_pub_keys = HashMap::new();
     for validator_extend in metadata.verifier_list.iter() {
         let address = validator_extend.address.as_bytes();
-        let hex_pubkey = hex::decode(validator_extend.bls_pub_key.as_string_trim0x())
-            .map_err(MainError::FromHex)?;
-        let pub_key = BlsPublicKey::try_from(hex_pubkey.as_ref()).map_err(MainError::Crypto)?;
+        let pub_key = BlsPublicKey::try_from(validator_extend.bls_pub_key.as_bytes().as_ref())
+            .map_err(MainError::Crypto)?;
         bls_pub_keys.insert(address, pub_key);
     }
 
-    let mut priv_key = Vec::new();
-    priv_key.extend_from_slice(&[0u8; 16]);
-    let mut tmp = hex::decode(config.privkey.as_string_trim0x()).unwrap();
-    priv_key.append(&mut tmp);
-    let bls_priv_key = BlsPrivateKey::try_from(priv_key.as_ref()).map_err(MainError::Crypto)?;
-
-    let hex_common_ref =
-        hex::decode(metadata.common_ref.as_string_trim0x()).map_err(MainError::FromHex)?;
-    let common_ref: BlsCommonReference = std::str::from_utf8(hex_common_ref.as_ref())
+    let common_ref_str = std::str::from_utf8(metadata.common_ref.as_bytes().as_ref())
         .map_err(MainError::Utf8)?
-        .into();
-    let crypto = Arc::new(OverlordCrypto::new(bls_priv_key, bls_pub_keys, common_ref));
+        .to_owned();
+    let common_ref: BlsCommonReference = common_ref_str.as_str().into();
+
+    let bls_signer: Arc<dyn Signer> = match &config.consensus.remote_signer {
+        Some(remote) => {
+            let signer = RemoteSigner::new(
+                remote.address,
+                Duration::from_millis(remote.timeout),
+                remote.shared_key.as_bytes(),
+            );
+            if let Some(pub_key) = bls_pub_keys.get(&my_address.as_bytes()) {
+                check_bls_signer(&signer, pub_key, &common_ref_str)?;
+            }
+            log::info!("muta: votes are signed by remote signer {}", remote.address);
+            Arc::new(signer)
+        }
+        None => {
+            let mut priv_key = Vec::new();
+            priv_key.extend_from_slice(&[0u8; 16]);
+            priv_key.extend_from_slice(&config.privkey.as_bytes());
+            Arc::new(BlsPrivateKey::try_from(priv_key.as_ref()).map_err(MainError::Crypto)?)
+        }
+    };
+    let crypto = Arc::new(OverlordCrypto::new(bls_signer, bls_pub_keys, common_ref));
+    crypto.update_signature_domain(metadata.signature_domain(current_block.header.height + 1));
 
     core_consensus::trace::init_tracer(my_address.as_hex())?;
 
@@ -344,28 +454,54 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
             status_agent.clone(),
         )?;
 
-    let exec_demon = consensus_adapter.take_exec_demon();
+    if let Some(listener) = api_adapter.commit_listener() {
+        consensus_adapter.add_commit_listener(listener);
+    }
+
+    let mut exec_demon = consensus_adapter.take_exec_demon();
     let consensus_adapter = Arc::new(consensus_adapter);
 
+    // Only validators run consensus, full nodes follow the chain by sync
     let lock = Arc::new(Mutex::new(()));
-    let overlord_consensus = Arc::new(OverlordConsensus::new(
-        status_agent.clone(),
-        node_info,
-        Arc::clone(&crypto),
-        Arc::clone(&txs_wal),
-        Arc::clone(&consensus_adapter),
-        Arc::clone(&lock),
-    ));
+    let overlord_consensus = if config.role.is_validator() {
+        let extra_data = Bytes::from(config.consensus.extra_data.clone());
+        if extra_data.len() > MAX_EXTRA_DATA_SIZE {
+            return Err(MainError::Other(format!(
+                "consensus.extra_data of {} bytes exceeds limit {}",
+                extra_data.len(),
+                MAX_EXTRA_DATA_SIZE
+            ))
+            .into());
+        }
 
-    consensus_adapter.set_overlord_handler(overlord_consensus.get_overlord_handler());
+        let overlord_consensus = Arc::new(OverlordConsensus::new(
+            status_agent.clone(),
+            node_info,
+            Arc::clone(&crypto),
+            Arc::clone(&txs_wal),
+            Arc::clone(&consensus_adapter),
+            Arc::clone(&lock),
+            config.consensus.max_timestamp_drift,
+            extra_data,
+            payload_limits,
+            vote_extender,
+        ));
+        consensus_adapter.set_overlord_handler(overlord_consensus.get_overlord_handler());
+        Some(overlord_consensus)
+    } else {
+        log::info!("muta: run as a {} node without consensus", config.role);
+        None
+    };
 
     let synchronization = Arc::new(OverlordSynchronization::new(
         config.consensus.sync_txs_chunk_size,
         consensus_adapter,
         status_agent.clone(),
         crypto,
-        lock,
+        Arc::clone(&lock),
     ));
+    synchronization.set_orphan_memory_cap(config.memory.consensus_orphans);
+    let sync_progress = synchronization.progress();
 
     // Re-execute block from exec_height + 1 to current_height, so that init the
     // lost current status.
@@ -382,22 +518,30 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
     }
 
     // register consensus
-    network_service.register_endpoint_handler(
-        END_GOSSIP_SIGNED_PROPOSAL,
-        Box::new(ProposalMessageHandler::new(Arc::clone(&overlord_consensus))),
-    )?;
-    network_service.register_endpoint_handler(
-        END_GOSSIP_AGGREGATED_VOTE,
-        Box::new(QCMessageHandler::new(Arc::clone(&overlord_consensus))),
-    )?;
-    network_service.register_endpoint_handler(
-        END_GOSSIP_SIGNED_VOTE,
-        Box::new(VoteMessageHandler::new(Arc::clone(&overlord_consensus))),
-    )?;
-    network_service.register_endpoint_handler(
-        END_GOSSIP_SIGNED_CHOKE,
-        Box::new(ChokeMessageHandler::new(Arc::clone(&overlord_consensus))),
-    )?;
+    if let Some(overlord_consensus) = &overlord_consensus {
+        network_service.register_endpoint_handler(
+            END_GOSSIP_SIGNED_PROPOSAL,
+            Box::new(ProposalMessageHandler::new(Arc::clone(overlord_consensus))),
+        )?;
+        network_service.register_endpoint_handler(
+            END_GOSSIP_AGGREGATED_VOTE,
+            Box::new(QCMessageHandler::new(Arc::clone(overlord_consensus))),
+        )?;
+        network_service.register_endpoint_handler(
+            END_GOSSIP_SIGNED_VOTE,
+            Box::new(VoteMessageHandler::new(Arc::clone(overlord_consensus))),
+        )?;
+        network_service.register_endpoint_handler(
+            END_GOSSIP_SIGNED_CHOKE,
+            Box::new(ChokeMessageHandler::new(Arc::clone(overlord_consensus))),
+        )?;
+        network_service.register_endpoint_handler(
+            END_GOSSIP_VOTE_EXTENSION,
+            Box::new(VoteExtensionMessageHandler::new(Arc::clone(
+                overlord_consensus,
+            ))),
+        )?;
+    }
     network_service.register_endpoint_handler(
         BROADCAST_HEIGHT,
         Box::new(RemoteHeightMessageHandler::new(Arc::clone(
@@ -411,6 +555,13 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
             Arc::clone(&storage),
         )),
     )?;
+    network_service.register_endpoint_handler(
+        RPC_SYNC_PULL_PROOF,
+        Box::new(PullProofRpcHandler::new(
+            Arc::new(network_service.handle()),
+            Arc::clone(&storage),
+        )),
+    )?;
     network_service.register_endpoint_handler(
         RPC_SYNC_PULL_TXS,
         Box::new(PullTxsRpcHandler::new(
@@ -419,9 +570,11 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         )),
     )?;
     network_service.register_rpc_response::<FixedBlock>(RPC_RESP_SYNC_PULL_BLOCK)?;
+    network_service.register_rpc_response::<FixedProof>(RPC_RESP_SYNC_PULL_PROOF)?;
     network_service.register_rpc_response::<FixedSignedTxs>(RPC_RESP_SYNC_PULL_TXS)?;
 
     // Run network
+    let network_handle = network_service.handle();
     tokio::spawn(network_service);
 
     // Run sync
@@ -431,36 +584,133 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         }
     });
 
-    // Run consensus
-    let authority_list = validators
-        .iter()
-        .map(|v| Node {
-            address:        v.address.as_bytes(),
-            propose_weight: v.propose_weight,
-            vote_weight:    v.vote_weight,
-        })
-        .collect::<Vec<_>>();
-
-    let timer_config = DurationConfig {
-        propose_ratio:   metadata.propose_ratio,
-        prevote_ratio:   metadata.prevote_ratio,
-        precommit_ratio: metadata.precommit_ratio,
-        brake_ratio:     metadata.brake_ratio,
-    };
+    // Run exporter
+    if let Some(exporter_config) = &config.exporter {
+        let mut export_daemon = ExportDaemon::new(
+            Arc::clone(&storage),
+            exporter_config.path.join("cursor"),
+            exporter_config.start_height,
+        )?
+        .interval(exporter_config.interval);
+        export_daemon.register(Box::new(CsvExporter::new(exporter_config.path.clone())?));
+        exec_demon.on_executed(export_daemon.executed_hook());
+        tokio::spawn(export_daemon.run());
+    }
 
-    tokio::spawn(async move {
-        if let Err(e) = overlord_consensus
-            .run(consensus_interval, authority_list, Some(timer_config))
-            .await
-        {
-            log::error!("muta-consensus: {:?} error", e);
+    // Run stream of executed heights
+    if let Some(stream_config) = &config.stream {
+        let mut stream_server =
+            StreamServer::new(Arc::clone(&storage), stream_config.cursor_path.clone())?
+                .interval(stream_config.interval);
+        if stream_config.state_diff {
+            stream_server = stream_server.state_diffs(Arc::new(ReplayStateDiffs::new(
+                Arc::clone(&storage),
+                Arc::clone(&trie_db),
+                Arc::clone(&service_mapping),
+            )));
         }
-    });
+
+        let addr = stream_config.listening_address;
+        tokio::spawn(async move {
+            if let Err(e) = stream_server.serve(addr).await {
+                log::error!("stream: {:?}", e);
+            }
+        });
+    }
+
+    // Run moves of old blocks to the cold store
+    if let Some(cold_storage_config) = &config.cold_storage {
+        tokio::spawn(run_cold_storage(
+            Arc::clone(&storage),
+            cold_storage_config.clone(),
+        ));
+    }
+
+    // Run consensus
+    if let Some(overlord_consensus) = overlord_consensus {
+        let authority_list = validators
+            .iter()
+            .map(|v| Node {
+                address:        v.address.as_bytes(),
+                propose_weight: v.propose_weight,
+                vote_weight:    v.vote_weight,
+            })
+            .collect::<Vec<_>>();
+
+        let timer_config = DurationConfig {
+            propose_ratio:   metadata.propose_ratio,
+            prevote_ratio:   metadata.prevote_ratio,
+            precommit_ratio: metadata.precommit_ratio,
+            brake_ratio:     metadata.brake_ratio,
+        };
+
+        tokio::spawn(async move {
+            if let Err(e) = overlord_consensus
+                .run(consensus_interval, authority_list, Some(timer_config))
+                .await
+            {
+                log::error!("muta-consensus: {:?} error", e);
+            }
+        });
+    }
 
     let (abortable_demon, abort_handle) = future::abortable(exec_demon.run());
     tokio::task::spawn_local(abortable_demon);
 
     // Init graphql
+    let graphql_config = graphql_config(&config);
+    let graphql_limits = Arc::new(GraphQLLimits::new(graphql_config.max_payload_size));
+
+    let api_limits = Arc::clone(&graphql_limits);
+    let admin_adapter = network_handle.clone();
+    tokio::task::spawn_local(async move {
+        let local = tokio::task::LocalSet::new();
+        let actix_rt = actix_rt::System::run_in_tokio("muta-graphql", &local);
+        tokio::task::spawn_local(actix_rt);
+
+        core_api::start_graphql(
+            graphql_config,
+            api_limits,
+            api_adapter,
+            Arc::new(sync_progress),
+            Arc::new(admin_adapter),
+        )
+        .await;
+    });
+
+    let reloader = ConfigReloader::new(
+        config_path,
+        config,
+        Arc::clone(&mempool),
+        network_handle,
+        graphql_limits,
+    );
+    wait_for_exit(Some(&|| match reloader.reload() {
+        Ok(()) => log::info!("muta: config reloaded"),
+        Err(e) => log::error!("muta: reload config failed: {}", e),
+    }))
+    .await;
+
+    // Stop accepting transactions and wait for the commit in progress, the
+    // lock is held till exit so that no other commit starts. A commit which
+    // doesn't finish in time is aborted and repaired on the next startup.
+    log::info!("muta: shutting down");
+    mempool.close();
+    let commit_lock =
+        tokio::time::timeout(Duration::from_millis(SHUTDOWN_COMMIT_TIMEOUT), lock.lock()).await;
+    if commit_lock.is_err() {
+        log::warn!("muta: abort the commit in progress");
+    }
+
+    // Abort consensus
+    abort_handle.abort();
+    storage.flush().await?;
+
+    Ok(())
+}
+
+/// Api config of the node, zero fields of config are defaults.
+pub(crate) fn graphql_config(config: &Config) -> GraphQLConfig {
     let mut graphql_config = GraphQLConfig::default();
     graphql_config.listening_address = config.graphql.listening_address;
     graphql_config.graphql_uri = config.graphql.graphql_uri.clone();
@@ -474,29 +724,107 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
     if config.graphql.max_payload_size != 0 {
         graphql_config.max_payload_size = config.graphql.max_payload_size;
     }
+    if let Some(gap) = config.graphql.ready_sync_gap {
+        graphql_config.ready_sync_gap = gap;
+    }
+    graphql_config.admin_uri = config.graphql.admin_uri.clone();
+    graphql_config.admin_token = config.graphql.admin_token.clone();
+    let archive_limits = |limits: &ConfigArchiveLimits| ArchiveLimits {
+        max_page_size:    limits.max_page_size,
+        max_height_range: limits.max_height_range,
+        trace:            limits.trace,
+        signed_query:     limits.signed_query,
+    };
+    if let Some(limits) = &config.graphql.archive_limits {
+        graphql_config.archive_limits = archive_limits(limits);
+    }
+    graphql_config.api_keys = config
+        .graphql
+        .api_keys
+        .iter()
+        .map(|(key, limits)| (key.clone(), archive_limits(limits)))
+        .collect();
+    if config.graphql.sign_queries {
+        graphql_config.query_signing_key = Some(config.privkey.as_bytes());
+    }
+    graphql_config
+}
 
-    tokio::task::spawn_local(async move {
-        let local = tokio::task::LocalSet::new();
-        let actix_rt = actix_rt::System::run_in_tokio("muta-graphql", &local);
-        tokio::task::spawn_local(actix_rt);
-
-        core_api::start_graphql(graphql_config, api_adapter).await;
-    });
-
+// Wait for interrupt or terminate signal, hangup signals are passed to the
+// handler if there's one, or exit too
+pub(crate) async fn wait_for_exit(on_hangup: Option<&dyn Fn()>) {
     #[cfg(windows)]
-    let _ = tokio::signal::ctrl_c().await;
+    {
+        let _ = on_hangup;
+        let _ = tokio::signal::ctrl_c().await;
+    }
     #[cfg(unix)]
     {
         let mut sigtun_int = os_impl::signal(os_impl::SignalKind::interrupt()).unwrap();
         let mut sigtun_term = os_impl::signal(os_impl::SignalKind::terminate()).unwrap();
-        tokio::select! {
-            _ = sigtun_int.recv() => {}
-            _ = sigtun_term.recv() => {}
+        let mut sigtun_hup = os_impl::signal(os_impl::SignalKind::hangup()).unwrap();
+        loop {
+            tokio::select! {
+                _ = sigtun_int.recv() => break,
+                _ = sigtun_term.recv() => break,
+                _ = sigtun_hup.recv() => match on_hangup {
+                    Some(on_hangup) => on_hangup(),
+                    None => break,
+                },
+            }
         }
     }
+}
 
-    // Abort consensus
-    abort_handle.abort();
+// Milliseconds to wait for the commit in progress on shutdown
+const SHUTDOWN_COMMIT_TIMEOUT: u64 = 10_000;
+
+// Size of a serialized hash with its length prefix, rounded up
+const HASH_WIRE_SIZE: usize = 64;
+// Room for block header, proof and message encoding
+const MESSAGE_SIZE_MARGIN: usize = 64 * 1024;
+
+fn message_size_limits(
+    metadata: &Metadata,
+    broadcast_txs_size: usize,
+) -> Vec<(&'static str, usize)> {
+    let max_tx_size = metadata.max_tx_size as usize;
+    let tx_num_limit = metadata.tx_num_limit as usize;
+
+    let txs_limit = |num: usize| num * max_tx_size + MESSAGE_SIZE_MARGIN;
+    let hashes_limit = |num: usize| num * HASH_WIRE_SIZE + MESSAGE_SIZE_MARGIN;
+    // Blocks carry vote extensions of validators besides hashes
+    let extensions_size = metadata.verifier_list.len() * (MAX_VOTE_EXTENSION_SIZE + 256);
+    let block_limit = hashes_limit(tx_num_limit) + extensions_size;
+
+    vec![
+        (END_GOSSIP_NEW_TXS, txs_limit(broadcast_txs_size)),
+        (END_GOSSIP_NEW_TX_HASHES, hashes_limit(broadcast_txs_size)),
+        (RPC_PULL_TXS, hashes_limit(tx_num_limit)),
+        (RPC_RESP_PULL_TXS, txs_limit(tx_num_limit)),
+        (END_GOSSIP_SIGNED_PROPOSAL, block_limit),
+        (
+            END_GOSSIP_VOTE_EXTENSION,
+            MAX_VOTE_EXTENSION_SIZE + MESSAGE_SIZE_MARGIN,
+        ),
+        (RPC_SYNC_PULL_TXS, hashes_limit(tx_num_limit)),
+        (RPC_RESP_SYNC_PULL_TXS, txs_limit(tx_num_limit)),
+        (RPC_RESP_SYNC_PULL_BLOCK, block_limit),
+        (RPC_RESP_SYNC_PULL_PROOF, MESSAGE_SIZE_MARGIN),
+    ]
+}
 
+// A signer of another key would fail every vote, so it's checked by the
+// signature of a probe hash before consensus starts
+fn check_bls_signer(
+    signer: &dyn Signer,
+    pub_key: &BlsPublicKey,
+    common_ref: &str,
+) -> ProtocolResult<()> {
+    if signer.bls_pub_key(common_ref)? != pub_key.to_bytes() {
+        return Err(
+            MainError::Other("remote signer has no bls key of this validator".to_owned()).into(),
+        );
+    }
     Ok(())
 }
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use core_api::config::GraphQLLimits;
+use framework::binding::state::RocksTrieDB;
+use framework::devchain::{DevChain, SealMode};
+use protocol::traits::{AdminAdapter, PeerScore, ServiceMapping, Storage};
+use protocol::types::Genesis;
+use protocol::{Bytes, ProtocolResult};
+
+use crate::config::Config;
+use crate::default_start::{graphql_config, wait_for_exit};
+use crate::storage::open_block_storage;
+use crate::MainError;
+
+/// Run a single node chain sealing blocks without consensus, for service
+/// development and integration tests. There is no network, only the api is
+/// served, blocks are sealed on demand by the `sealBlock` mutation in
+/// manual mode.
+pub async fn start_dev_chain<Mapping: 'static + ServiceMapping>(
+    config: &Config,
+    genesis: &Genesis,
+    service_mapping: Arc<Mapping>,
+    seal_mode: SealMode,
+) -> ProtocolResult<()> {
+    let storage = Arc::new(open_block_storage(config).await?);
+    let trie_db = Arc::new(RocksTrieDB::new(
+        config.data_path_for_state(),
+        config.executor.light,
+        config.executor.trie_cache_size,
+        config.rocksdb.max_open_files,
+    )?);
+
+    let dev_chain = DevChain::new(
+        genesis,
+        Arc::clone(&storage),
+        trie_db,
+        service_mapping,
+        seal_mode,
+    )
+    .await?;
+    log::info!("muta: dev chain started in {:?} seal mode", seal_mode);
+
+    let graphql_config = graphql_config(config);
+    let graphql_limits = Arc::new(GraphQLLimits::new(graphql_config.max_payload_size));
+    let readiness = Arc::new(dev_chain.clone());
+    tokio::task::spawn_local(async move {
+        let local = tokio::task::LocalSet::new();
+        let actix_rt = actix_rt::System::run_in_tokio("muta-graphql", &local);
+        tokio::task::spawn_local(actix_rt);
+
+        core_api::start_graphql(
+            graphql_config,
+            graphql_limits,
+            dev_chain,
+            readiness,
+            Arc::new(NoPeers),
+        )
+        .await;
+    });
+
+    wait_for_exit(None).await;
+    log::info!("muta: shutting down");
+    storage.flush().await
+}
+
+// Dev chains connect no peers
+struct NoPeers;
+
+impl AdminAdapter for NoPeers {
+    fn peer_scores(&self) -> Vec<PeerScore> {
+        vec![]
+    }
+
+    fn allowlist(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn allow_peer(&self, _pubkey: Bytes) -> ProtocolResult<()> {
+        Err(MainError::Other("dev chain connects no peers".to_owned()).into())
+    }
+
+    fn disallow_peer(&self, _pubkey: Bytes) -> ProtocolResult<()> {
+        Err(MainError::Other("dev chain connects no peers".to_owned()).into())
+    }
+}
@@ -2,24 +2,35 @@
 
 mod config;
 mod default_start;
+mod dev_chain;
+mod reload;
+mod replay;
+mod storage;
+mod verify_chain;
 
 use std::fs;
 use std::sync::Arc;
 
 use derive_more::{Display, From};
 
-use protocol::traits::ServiceMapping;
+use framework::executor::ReplayLog;
+use framework::genesis::verify_genesis;
+use protocol::traits::{ServiceMapping, VoteExtender};
 use protocol::types::{Block, Genesis};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 use crate::config::Config;
 use crate::default_start::{create_genesis, start};
 
+pub use framework::devchain::SealMode;
+pub use verify_chain::{ChainVerification, Corruption};
+
 #[derive(Default)]
 pub struct MutaBuilder<Mapping: ServiceMapping> {
     config_path:     Option<String>,
     genesis_path:    Option<String>,
     servive_mapping: Option<Arc<Mapping>>,
+    vote_extender:   Option<Arc<dyn VoteExtender>>,
 }
 
 impl<Mapping: 'static + ServiceMapping> MutaBuilder<Mapping> {
@@ -28,6 +39,7 @@ impl<Mapping: 'static + ServiceMapping> MutaBuilder<Mapping> {
             servive_mapping: None,
             config_path:     None,
             genesis_path:    None,
+            vote_extender:   None,
         }
     }
 
@@ -46,49 +58,63 @@ impl<Mapping: 'static + ServiceMapping> MutaBuilder<Mapping> {
         self
     }
 
+    /// Payloads attached to precommits of this validator, such as prices of
+    /// an oracle. They're aggregated into the next block and passed to
+    /// services by `ExecutorParams::vote_extensions`.
+    pub fn vote_extender<E: VoteExtender + 'static>(mut self, extender: E) -> MutaBuilder<Mapping> {
+        self.vote_extender = Some(Arc::new(extender));
+        self
+    }
+
     pub fn build(self) -> ProtocolResult<Muta<Mapping>> {
+        let config_path = self.config_path.expect("config path is not set");
         let config: Config =
-            common_config_parser::parse(&self.config_path.expect("config path is not set"))
-                .map_err(MainError::ConfigParse)?;
+            common_config_parser::parse(&config_path).map_err(MainError::ConfigParse)?;
 
         let genesis_toml = fs::read_to_string(&self.genesis_path.expect("genesis path is not set"))
             .map_err(MainError::Io)?;
         let genesis: Genesis = toml::from_str(&genesis_toml).map_err(MainError::GenesisTomlDe)?;
+        verify_genesis(&genesis)?;
 
-        Ok(Muta::new(
+        let mut muta = Muta::new(
+            config_path,
             config,
             genesis,
             self.servive_mapping
                 .expect("service mapping cannot be None"),
-        ))
+        );
+        muta.vote_extender = self.vote_extender;
+        Ok(muta)
     }
 }
 
 pub struct Muta<Mapping: ServiceMapping> {
+    config_path:     String,
     config:          Config,
     genesis:         Genesis,
     service_mapping: Arc<Mapping>,
+    vote_extender:   Option<Arc<dyn VoteExtender>>,
 }
 
 impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
-    pub fn new(config: Config, genesis: Genesis, service_mapping: Arc<Mapping>) -> Self {
+    pub fn new(
+        config_path: String,
+        config: Config,
+        genesis: Genesis,
+        service_mapping: Arc<Mapping>,
+    ) -> Self {
         Self {
+            config_path,
             config,
             genesis,
             service_mapping,
+            vote_extender: None,
         }
     }
 
-    pub fn run(self) -> ProtocolResult<()> {
-        common_logger::init(
-            self.config.logger.filter.clone(),
-            self.config.logger.log_to_console,
-            self.config.logger.console_show_file_and_line,
-            self.config.logger.log_to_file,
-            self.config.logger.metrics,
-            self.config.logger.log_path.clone(),
-            self.config.logger.modules_level.clone(),
-        );
+    pub fn run(mut self) -> ProtocolResult<()> {
+        self.init_logger();
+        self.config.unlock_keystore()?;
 
         // run muta
         let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
@@ -96,12 +122,70 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
         local.block_on(&mut rt, async move {
             self.create_genesis().await?;
 
-            start(self.config, Arc::clone(&self.service_mapping)).await
+            start(
+                self.config_path,
+                self.config,
+                Arc::clone(&self.service_mapping),
+                self.vote_extender,
+            )
+            .await
         })?;
 
         Ok(())
     }
 
+    /// Re-execute a committed block and log the writes of every transaction,
+    /// see `framework::executor::diff_replay_logs` to compare logs of nodes.
+    pub fn replay(self, height: u64) -> ProtocolResult<ReplayLog> {
+        let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&mut rt, async move {
+            replay::replay(&self.config, height, Arc::clone(&self.service_mapping)).await
+        })
+    }
+
+    /// Verify stored blocks from the height to the optional end, the latest
+    /// height by default, and report the first corrupt height.
+    pub fn verify_chain(self, from: u64, to: Option<u64>) -> ProtocolResult<ChainVerification> {
+        let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&mut rt, async move {
+            verify_chain::verify_chain(&self.config, from, to, Arc::clone(&self.service_mapping))
+                .await
+        })
+    }
+
+    /// Run a dev chain of this node alone, blocks are sealed without
+    /// consensus, see `framework::devchain::DevChain`.
+    pub fn run_dev_chain(mut self, seal_mode: SealMode) -> ProtocolResult<()> {
+        self.init_logger();
+        self.config.unlock_keystore()?;
+
+        let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&mut rt, async move {
+            dev_chain::start_dev_chain(
+                &self.config,
+                &self.genesis,
+                Arc::clone(&self.service_mapping),
+                seal_mode,
+            )
+            .await
+        })
+    }
+
+    fn init_logger(&self) {
+        common_logger::init(
+            self.config.logger.filter.clone(),
+            self.config.logger.log_to_console,
+            self.config.logger.console_show_file_and_line,
+            self.config.logger.log_to_file,
+            self.config.logger.metrics,
+            self.config.logger.log_path.clone(),
+            self.config.logger.modules_level.clone(),
+        );
+    }
+
     async fn create_genesis(&self) -> ProtocolResult<Block> {
         create_genesis(
             &self.config,
@@ -132,6 +216,9 @@ pub enum MainError {
     #[display(fmt = "{:?}", _0)]
     Utf8(std::str::Utf8Error),
 
+    #[display(fmt = "config fields {:?} can not be changed without restart", _0)]
+    ImmutableConfig(Vec<String>),
+
     #[display(fmt = "other error {:?}", _0)]
     Other(String),
 }
@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use core_api::config::{GraphQLConfig, GraphQLLimits};
+use core_mempool::HashMemPool;
+use core_network::{NetworkServiceHandle, DEFAULT_MAX_CONNECTIONS};
+use protocol::traits::MemPoolAdapter;
+use protocol::ProtocolResult;
+
+use crate::config::{Config, ConfigGraphQL, ConfigNetwork};
+use crate::MainError;
+
+/// Re-read the config file and apply the fields which are safe to change
+/// while the node is running: the logger section,
+/// `graphql.max_payload_size`, `network.max_connected_peers`,
+/// `mempool.pool_size`, `mempool.allow_private_txs` and
+/// `mempool.rebroadcast_after`. If any other field is changed, the whole
+/// reload is rejected and nothing is applied. A value rejected while
+/// applying, like a pool size above the startup capacity, also leaves the
+/// running config untouched.
+pub struct ConfigReloader<Adapter: MemPoolAdapter> {
+    config_path: String,
+    config:      Mutex<Config>,
+
+    mempool:          Arc<HashMemPool<Adapter>>,
+    network:          NetworkServiceHandle,
+    graphql_limits:   Arc<GraphQLLimits>,
+    // The payload size limit of the http server, it is fixed at startup.
+    max_payload_size: usize,
+}
+
+impl<Adapter: MemPoolAdapter + 'static> ConfigReloader<Adapter> {
+    pub fn new(
+        config_path: String,
+        config: Config,
+        mempool: Arc<HashMemPool<Adapter>>,
+        network: NetworkServiceHandle,
+        graphql_limits: Arc<GraphQLLimits>,
+    ) -> Self {
+        let max_payload_size = graphql_limits.max_payload_size();
+
+        Self {
+            config_path,
+            config: Mutex::new(config),
+            mempool,
+            network,
+            graphql_limits,
+            max_payload_size,
+        }
+    }
+
+    pub fn reload(&self) -> ProtocolResult<()> {
+        let mut new_config: Config =
+            common_config_parser::parse(&self.config_path).map_err(MainError::ConfigParse)?;
+        let mut config = self.config.lock();
+        // The key was decrypted at startup, changes of the keystore are checked
+        if new_config.keystore.is_some() {
+            new_config.privkey = config.privkey.clone();
+        }
+
+        let changed = immutable_changes(&config, &new_config);
+        if !changed.is_empty() {
+            return Err(MainError::ImmutableConfig(changed).into());
+        }
+
+        let max_payload_size = match new_config.graphql.max_payload_size {
+            0 => GraphQLConfig::default().max_payload_size,
+            size => size,
+        };
+        if max_payload_size > self.max_payload_size {
+            return Err(MainError::Other(format!(
+                "graphql.max_payload_size {} is larger than the startup value {}",
+                max_payload_size, self.max_payload_size
+            ))
+            .into());
+        }
+
+        // The fallible fields are applied first, each failure restores the
+        // ones applied before it, so a rejected reload changes nothing.
+        self.mempool
+            .set_pool_size(new_config.mempool.pool_size as usize)?;
+        if let Err(e) = self.network.set_max_connections(
+            new_config
+                .network
+                .max_connected_peers
+                .unwrap_or(DEFAULT_MAX_CONNECTIONS),
+        ) {
+            self.restore(&config);
+            return Err(e);
+        }
+
+        let logger = &new_config.logger;
+        if let Err(e) = common_logger::reload(
+            logger.filter.clone(),
+            logger.log_to_console,
+            logger.console_show_file_and_line,
+            logger.log_to_file,
+            logger.metrics,
+            logger.log_path.clone(),
+            logger.modules_level.clone(),
+        ) {
+            self.restore(&config);
+            return Err(MainError::Other(e).into());
+        }
+
+        self.mempool
+            .set_allow_private_txs(new_config.mempool.allow_private_txs);
+        self.mempool
+            .set_rebroadcast_after(new_config.mempool.rebroadcast_after);
+        self.graphql_limits.set_max_payload_size(max_payload_size);
+
+        *config = new_config;
+        Ok(())
+    }
+
+    // Put back the fallible fields of the running config. They were accepted
+    // before, so errors are ignored: the network only fails on shutdown.
+    fn restore(&self, config: &Config) {
+        let _ = self
+            .mempool
+            .set_pool_size(config.mempool.pool_size as usize);
+        let _ = self.network.set_max_connections(
+            config
+                .network
+                .max_connected_peers
+                .unwrap_or(DEFAULT_MAX_CONNECTIONS),
+        );
+    }
+}
+
+fn immutable_changes(old: &Config, new: &Config) -> Vec<String> {
+    let mut changed = vec![];
+    let mut check = |name: &str, is_changed: bool| {
+        if is_changed {
+            changed.push(name.to_owned());
+        }
+    };
+
+    check("privkey", old.privkey != new.privkey);
+    check("keystore", old.keystore != new.keystore);
+    check("data_path", old.data_path != new.data_path);
+    check("role", old.role != new.role);
+
+    let graphql = ConfigGraphQL {
+        max_payload_size: old.graphql.max_payload_size,
+        ..new.graphql.clone()
+    };
+    check("graphql", graphql != old.graphql);
+
+    let network = ConfigNetwork {
+        max_connected_peers: old.network.max_connected_peers,
+        ..new.network.clone()
+    };
+    check("network", network != old.network);
+
+    check(
+        "mempool.broadcast_txs_size",
+        old.mempool.broadcast_txs_size != new.mempool.broadcast_txs_size,
+    );
+    check(
+        "mempool.broadcast_txs_interval",
+        old.mempool.broadcast_txs_interval != new.mempool.broadcast_txs_interval,
+    );
+    check("executor", old.executor != new.executor);
+    check("consensus", old.consensus != new.consensus);
+    check("rocksdb", old.rocksdb != new.rocksdb);
+    check("memory", old.memory != new.memory);
+    check("exporter", old.exporter != new.exporter);
+    check("stream", old.stream != new.stream);
+    check("cold_storage", old.cold_storage != new.cold_storage);
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::immutable_changes;
+    use crate::config::Config;
+
+    fn config() -> Config {
+        toml::from_str(include_str!("../devtools/chain/config.toml")).unwrap()
+    }
+
+    #[test]
+    fn test_dynamic_fields_are_not_immutable_changes() {
+        let old = config();
+        let mut new = config();
+        assert!(immutable_changes(&old, &new).is_empty());
+
+        new.logger.filter = "debug".to_owned();
+        new.graphql.max_payload_size = 1024;
+        new.network.max_connected_peers = Some(10);
+        new.mempool.pool_size = 1;
+        new.mempool.allow_private_txs = !old.mempool.allow_private_txs;
+        new.mempool.rebroadcast_after = 0;
+        assert!(immutable_changes(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_immutable_changes() {
+        let old = config();
+        let mut new = config();
+        new.data_path = "./other".into();
+        new.graphql.graphql_uri = "/other".to_owned();
+        new.network.rpc_timeout = Some(1);
+        new.mempool.broadcast_txs_size = 1;
+        new.consensus.sync_txs_chunk_size = 1;
+
+        assert_eq!(immutable_changes(&old, &new), vec![
+            "data_path",
+            "graphql",
+            "network",
+            "mempool.broadcast_txs_size",
+            "consensus"
+        ]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use core_exporter::{StateChange, StateDiffSource};
+use framework::binding::state::RocksTrieDB;
+use framework::executor::{MPTServiceExecutor, ReplayLog};
+use protocol::traits::{executed_state_root, Executor, ExecutorParams, ServiceMapping, Storage};
+use protocol::types::{Address, Metadata, TransactionRequest};
+use protocol::{Bytes, ProtocolResult};
+
+use crate::config::Config;
+use crate::storage::open_block_storage;
+use crate::MainError;
+
+/// Re-execute the block of the height against the state of its parent. The
+/// node should be stopped, since databases are opened exclusively. State
+/// written by replay is the same as the original execution, nodes of trie
+/// are addressed by their hashes.
+pub async fn replay<Mapping: 'static + ServiceMapping>(
+    config: &Config,
+    height: u64,
+    service_mapping: Arc<Mapping>,
+) -> ProtocolResult<ReplayLog> {
+    if height == 0 {
+        return Err(MainError::Other("genesis block can not be replayed".to_owned()).into());
+    }
+
+    let storage = Arc::new(open_block_storage(config).await?);
+    let trie_db = Arc::new(RocksTrieDB::new(
+        config.data_path_for_state(),
+        config.executor.light,
+        config.executor.trie_cache_size,
+        config.rocksdb.max_open_files,
+    )?);
+
+    let mut log = replay_block(&storage, &trie_db, service_mapping, height).await?;
+    log.expected_state_root = executed_state_root(&storage, height).await?;
+
+    Ok(log)
+}
+
+/// Writes to the state by committed heights for the stream, replayed against
+/// the state of the running node. Replay doesn't change the state, each write
+/// is the same as the original execution.
+pub struct ReplayStateDiffs<S, Mapping> {
+    storage:         Arc<S>,
+    trie_db:         Arc<RocksTrieDB>,
+    service_mapping: Arc<Mapping>,
+}
+
+impl<S, Mapping> ReplayStateDiffs<S, Mapping> {
+    pub fn new(storage: Arc<S>, trie_db: Arc<RocksTrieDB>, service_mapping: Arc<Mapping>) -> Self {
+        ReplayStateDiffs {
+            storage,
+            trie_db,
+            service_mapping,
+        }
+    }
+}
+
+#[async_trait]
+impl<S, Mapping> StateDiffSource for ReplayStateDiffs<S, Mapping>
+where
+    S: 'static + Storage,
+    Mapping: 'static + ServiceMapping,
+{
+    async fn state_diff(&self, height: u64) -> ProtocolResult<Vec<StateChange>> {
+        // Genesis is initialized by the genesis services, not replayable
+        if height == 0 {
+            return Ok(vec![]);
+        }
+
+        let log = replay_block(
+            &self.storage,
+            &self.trie_db,
+            Arc::clone(&self.service_mapping),
+            height,
+        )
+        .await?;
+
+        let mut changes = vec![];
+        for step in log.steps {
+            for write in step.writes {
+                changes.push(StateChange {
+                    tx_hash:    step.tx_hash.clone(),
+                    service:    write.service,
+                    key:        write.key.as_bytes(),
+                    value_hash: write.value_hash,
+                });
+            }
+        }
+        Ok(changes)
+    }
+}
+
+// Blocks and state are fetched before the executor is created, it's held
+// across no await
+async fn replay_block<S: 'static + Storage, Mapping: 'static + ServiceMapping>(
+    storage: &Arc<S>,
+    trie_db: &Arc<RocksTrieDB>,
+    service_mapping: Arc<Mapping>,
+    height: u64,
+) -> ProtocolResult<ReplayLog> {
+    let block = storage.get_block_by_height(height).await?;
+    let parent_state_root = executed_state_root(&**storage, height - 1)
+        .await?
+        .ok_or_else(|| {
+            MainError::Other(format!("state root of height {} is not found", height - 1))
+        })?;
+    let txs = storage
+        .get_transactions(block.ordered_tx_hashes.clone())
+        .await?;
+
+    let mut executor = MPTServiceExecutor::with_root(
+        parent_state_root.clone(),
+        Arc::clone(trie_db),
+        Arc::clone(storage),
+        service_mapping,
+    )?;
+
+    // Same cycles limit as consensus, it's from metadata of the parent state
+    let caller = Address::default();
+    let metadata_params = ExecutorParams {
+        state_root:      parent_state_root.clone(),
+        height:          height - 1,
+        timestamp:       block.header.timestamp,
+        cycles_limit:    u64::max_value(),
+        proposer:        caller.clone(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    };
+    let resp = executor.read(&metadata_params, &caller, 1, &TransactionRequest {
+        service_name: "metadata".to_owned(),
+        method:       "get_metadata".to_owned(),
+        payload:      "".to_owned(),
+    })?;
+    let metadata: Metadata = serde_json::from_str(&resp.succeed_data)
+        .map_err(|e| MainError::Other(format!("decode metadata: {}", e)))?;
+
+    let params = ExecutorParams {
+        state_root: parent_state_root,
+        height,
+        timestamp: block.header.timestamp,
+        cycles_limit: metadata.cycles_limit,
+        proposer: block.header.proposer.clone(),
+        vote_extensions: block.vote_extensions.clone(),
+        extra_data: block.header.extra_data.clone(),
+    };
+    executor.replay(&params, &txs)
+}
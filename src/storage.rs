@@ -0,0 +1,74 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use core_storage::adapter::{rocks::RocksAdapter, tiered::TieredAdapter};
+use core_storage::{get_cold_path, set_cold_path, ImplStorage};
+use protocol::ProtocolResult;
+
+use crate::config::{Config, ConfigColdStorage};
+use crate::MainError;
+
+pub type BlockStorage = ImplStorage<TieredAdapter<RocksAdapter, RocksAdapter>>;
+
+/// Open the block db, with the cold store if it's configured or has been
+/// created by an earlier run. The path of the cold store is recorded in the
+/// hot db when it's created, and read from there by later runs.
+pub async fn open_block_storage(config: &Config) -> ProtocolResult<BlockStorage> {
+    let hot = RocksAdapter::new(config.data_path_for_block(), config.rocksdb.max_open_files)?;
+
+    let path_cold = match get_cold_path(&hot).await? {
+        Some(recorded) => {
+            // Blocks moved to the recorded store would be lost by another one
+            if config.cold_storage.is_some() && config.data_path_for_cold_block() != recorded {
+                return Err(MainError::Other(format!(
+                    "cold storage path {:?} differs from the cold store {:?} of the block db",
+                    config.data_path_for_cold_block(),
+                    recorded
+                ))
+                .into());
+            }
+            Some(recorded)
+        }
+        None => {
+            // Block dbs of earlier versions don't record the default path
+            let path_cold = config.data_path_for_cold_block();
+            if config.cold_storage.is_some() || path_cold.exists() {
+                set_cold_path(&hot, &path_cold).await?;
+                Some(path_cold)
+            } else {
+                None
+            }
+        }
+    };
+
+    let cold = match path_cold {
+        Some(path_cold) => {
+            log::info!("Data path for cold block: {:?}", path_cold);
+            let cold = RocksAdapter::compressed(path_cold, config.rocksdb.max_open_files)?;
+            Some(Arc::new(cold))
+        }
+        None => None,
+    };
+
+    Ok(ImplStorage::new(Arc::new(TieredAdapter::new(
+        Arc::new(hot),
+        cold,
+    ))))
+}
+
+/// Move old blocks to the cold store every interval, errors are logged and
+/// retried by the next move.
+pub async fn run_cold_storage(storage: Arc<BlockStorage>, config: ConfigColdStorage) {
+    let interval = Duration::from_millis(config.interval);
+
+    loop {
+        match storage
+            .move_to_cold(config.keep_heights, config.max_heights)
+            .await
+        {
+            Ok(height) => log::debug!("cold storage: blocks below {} are moved", height),
+            Err(e) => log::error!("cold storage: {:?}", e),
+        }
+        tokio::time::delay_for(interval).await;
+    }
+}
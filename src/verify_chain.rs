@@ -0,0 +1,526 @@
+use std::sync::Arc;
+
+use derive_more::Display;
+use serde_derive::Serialize;
+
+use common_merkle::Merkle;
+use core_consensus::util::verify_proof;
+use framework::binding::state::RocksTrieDB;
+use framework::executor::MPTServiceExecutor;
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{executed_state_root, Executor, ExecutorParams, ServiceMapping, Storage};
+use protocol::types::{Address, Block, Hash, MerkleRoot, Metadata, Proof, TransactionRequest};
+use protocol::{Bytes, ProtocolResult};
+
+use crate::config::Config;
+use crate::storage::{open_block_storage, BlockStorage};
+use crate::MainError;
+
+/// The first height failed to verify.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Display)]
+#[display(fmt = "block {} is corrupt, {}", height, reason)]
+pub struct Corruption {
+    pub height: u64,
+    pub reason: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChainVerification {
+    pub from:       u64,
+    pub to:         u64,
+    /// Heights verified before the corruption, or all of them.
+    pub verified:   u64,
+    pub corruption: Option<Corruption>,
+}
+
+/// Walk the stored blocks from the height, the block before it is trusted as
+/// a checkpoint. Links of block hashes, transaction and receipt roots and
+/// proofs against the validators recorded in metadata are checked, the walk
+/// stops at the first corrupt height. The node should be stopped, since
+/// databases are opened exclusively.
+pub async fn verify_chain<Mapping: 'static + ServiceMapping>(
+    config: &Config,
+    from: u64,
+    to: Option<u64>,
+    service_mapping: Arc<Mapping>,
+) -> ProtocolResult<ChainVerification> {
+    let storage = Arc::new(open_block_storage(config).await?);
+    let trie_db = Arc::new(RocksTrieDB::new(
+        config.data_path_for_state(),
+        config.executor.light,
+        config.executor.trie_cache_size,
+        config.rocksdb.max_open_files,
+    )?);
+
+    let reader = {
+        let storage = Arc::clone(&storage);
+        move |state_root: MerkleRoot, height: u64| {
+            read_metadata(&trie_db, &storage, &service_mapping, state_root, height)
+        }
+    };
+    let mut verifier = Verifier::new(storage, config.executor.light, reader).await?;
+    verifier.verify(from, to).await
+}
+
+struct Verifier<S, R> {
+    storage:      Arc<S>,
+    // Light nodes prune history states
+    light:        bool,
+    reader:       R,
+    latest_block: Block,
+    // Metadata is only read again once validators change
+    metadata:     Option<Metadata>,
+}
+
+impl<S, R> Verifier<S, R>
+where
+    S: Storage,
+    R: Fn(MerkleRoot, u64) -> ProtocolResult<Metadata>,
+{
+    async fn new(storage: Arc<S>, light: bool, reader: R) -> ProtocolResult<Self> {
+        let latest_block = storage.get_latest_block().await?;
+        Ok(Verifier {
+            storage,
+            light,
+            reader,
+            latest_block,
+            metadata: None,
+        })
+    }
+
+    async fn verify(&mut self, from: u64, to: Option<u64>) -> ProtocolResult<ChainVerification> {
+        let latest_height = self.latest_block.header.height;
+        let to = to.unwrap_or(latest_height).min(latest_height);
+        if from > to {
+            return Err(MainError::Other(format!(
+                "height {} is above the latest height {}",
+                from, to
+            ))
+            .into());
+        }
+
+        let mut parent = if from == 0 {
+            None
+        } else {
+            Some(self.storage.get_block_by_height(from - 1).await?)
+        };
+
+        let mut verification = ChainVerification {
+            from,
+            to,
+            verified: 0,
+            corruption: None,
+        };
+        for height in from..=to {
+            match self.verify_block(height, parent.as_ref()).await {
+                Ok(block) => {
+                    verification.verified += 1;
+                    parent = Some(block);
+                }
+                Err(e) => {
+                    verification.corruption = Some(Corruption {
+                        height,
+                        reason: e.to_string(),
+                    });
+                    break;
+                }
+            }
+        }
+
+        Ok(verification)
+    }
+
+    async fn verify_block(&mut self, height: u64, parent: Option<&Block>) -> ProtocolResult<Block> {
+        let block = self.storage.get_block_by_height(height).await?;
+        let header = &block.header;
+        if header.height != height {
+            return Err(corrupt(format!("height of the block is {}", header.height)));
+        }
+
+        if let Some(parent) = parent {
+            if header.pre_hash != Hash::digest(parent.encode_fixed()?) {
+                return Err(corrupt("pre_hash doesn't link to the parent".to_owned()));
+            }
+        }
+
+        if header.order_root != merkle_root(block.ordered_tx_hashes.clone()) {
+            return Err(corrupt("order_root mismatches transactions".to_owned()));
+        }
+
+        let stxs = self
+            .storage
+            .get_transactions(block.ordered_tx_hashes.clone())
+            .await?;
+        if stxs.len() != block.ordered_tx_hashes.len() {
+            return Err(corrupt(format!(
+                "{} of {} transactions are stored",
+                stxs.len(),
+                block.ordered_tx_hashes.len()
+            )));
+        }
+        for (stx, tx_hash) in stxs.iter().zip(block.ordered_tx_hashes.iter()) {
+            if &stx.tx_hash != tx_hash || Hash::digest(stx.raw.encode_fixed()?) != stx.tx_hash {
+                return Err(corrupt(format!("transaction {:?} is corrupt", tx_hash)));
+            }
+        }
+
+        self.verify_executed_roots(&block).await?;
+
+        // Genesis is not voted
+        if height > 0 {
+            let proof = self.proof_of(height).await?;
+            let metadata = self.metadata_for(&block).await?;
+            verify_proof(&block, &proof, &metadata)?;
+        }
+
+        Ok(block)
+    }
+
+    // Roots in the header are of the executed heights not confirmed yet, the
+    // last one is of the exec_height.
+    async fn verify_executed_roots(&self, block: &Block) -> ProtocolResult<()> {
+        let header = &block.header;
+        let len = header.confirm_root.len() as u64;
+        if header.receipt_root.len() as u64 != len || len > header.exec_height + 1 {
+            return Err(corrupt(format!(
+                "{} confirm roots and {} receipt roots at exec height {}",
+                len,
+                header.receipt_root.len(),
+                header.exec_height
+            )));
+        }
+
+        let first = header.exec_height + 1 - len;
+        for (i, (confirm_root, receipt_root)) in header
+            .confirm_root
+            .iter()
+            .zip(header.receipt_root.iter())
+            .enumerate()
+        {
+            let exec_height = first + i as u64;
+            let executed = self.storage.get_block_by_height(exec_height).await?;
+            if confirm_root != &executed.header.order_root {
+                return Err(corrupt(format!(
+                    "confirm root of height {} mismatches",
+                    exec_height
+                )));
+            }
+
+            let receipts = self
+                .storage
+                .get_receipts(executed.ordered_tx_hashes.clone())
+                .await?;
+            if receipts.len() != executed.ordered_tx_hashes.len() {
+                return Err(corrupt(format!(
+                    "{} of {} receipts of height {} are stored",
+                    receipts.len(),
+                    executed.ordered_tx_hashes.len(),
+                    exec_height
+                )));
+            }
+            let mut receipt_hashes = Vec::with_capacity(receipts.len());
+            for receipt in receipts.into_iter() {
+                receipt_hashes.push(Hash::digest(receipt.encode_fixed()?));
+            }
+            if receipt_root != &merkle_root(receipt_hashes) {
+                return Err(corrupt(format!(
+                    "receipt root of height {} mismatches",
+                    exec_height
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Proof of a block is carried by the next one, or stored alone for the
+    // latest block.
+    async fn proof_of(&self, height: u64) -> ProtocolResult<Proof> {
+        if height == self.latest_block.header.height {
+            self.storage.get_latest_proof().await
+        } else {
+            let next = self.storage.get_block_by_height(height + 1).await?;
+            Ok(next.header.proof)
+        }
+    }
+
+    async fn metadata_for(&mut self, block: &Block) -> ProtocolResult<Metadata> {
+        let known = self.metadata.as_ref().map_or(false, |metadata| {
+            block.header.validators.iter().all(|validator| {
+                metadata
+                    .verifier_list
+                    .iter()
+                    .any(|v| v.address == validator.address)
+            })
+        });
+        if known {
+            return Ok(self.metadata.clone().expect("checked"));
+        }
+
+        let height = block.header.height - 1;
+        let state_root = match executed_state_root(&self.storage, height).await {
+            Ok(Some(state_root)) => state_root,
+            _ => self.latest_block.header.state_root.clone(),
+        };
+        let metadata = match (self.reader)(state_root, height) {
+            Ok(metadata) => metadata,
+            // Validators are read from the latest state instead
+            Err(_) if self.light => {
+                let state_root = self.latest_block.header.state_root.clone();
+                (self.reader)(state_root, self.latest_block.header.height)?
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.metadata = Some(metadata.clone());
+        Ok(metadata)
+    }
+}
+
+fn read_metadata<Mapping: 'static + ServiceMapping>(
+    trie_db: &Arc<RocksTrieDB>,
+    storage: &Arc<BlockStorage>,
+    service_mapping: &Arc<Mapping>,
+    state_root: MerkleRoot,
+    height: u64,
+) -> ProtocolResult<Metadata> {
+    let executor = MPTServiceExecutor::with_root(
+        state_root.clone(),
+        Arc::clone(trie_db),
+        Arc::clone(storage),
+        Arc::clone(service_mapping),
+    )?;
+
+    let caller = Address::default();
+    let params = ExecutorParams {
+        state_root,
+        height,
+        timestamp: 0,
+        cycles_limit: u64::max_value(),
+        proposer: caller.clone(),
+        vote_extensions: vec![],
+        extra_data: Bytes::new(),
+    };
+    let resp = executor.read(&params, &caller, 1, &TransactionRequest {
+        service_name: "metadata".to_owned(),
+        method:       "get_metadata".to_owned(),
+        payload:      "".to_owned(),
+    })?;
+    serde_json::from_str(&resp.succeed_data)
+        .map_err(|e| MainError::Other(format!("decode metadata: {}", e)).into())
+}
+
+fn corrupt(reason: String) -> protocol::ProtocolError {
+    MainError::Other(reason).into()
+}
+
+fn merkle_root(hashes: Vec<Hash>) -> MerkleRoot {
+    Merkle::from_hashes(hashes)
+        .get_root_hash()
+        .unwrap_or_else(Hash::from_empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    use futures::executor::block_on;
+
+    use common_crypto::{BlsPrivateKey, HashValue, PrivateKey, Signature};
+    use core_consensus::util::precommit_hash;
+    use core_storage::{adapter::memory::MemoryAdapter, ImplStorage};
+    use protocol::fixed_codec::FixedCodec;
+    use protocol::traits::Storage;
+    use protocol::types::{
+        Address, Block, BlockHeader, Bytes, Hash, Hex, Metadata, Proof, RawTransaction,
+        SignedTransaction, TransactionRequest, Validator, ValidatorExtend,
+    };
+
+    use super::{merkle_root, ChainVerification, Verifier};
+
+    const PRIVATE_KEY: &str =
+        "000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970";
+    const PUBLIC_KEY: &str = "041054fe9a65be0891094ed37fb3655e3ffb12353bc0a1b4f8673b52ad65d1ca481780cf7e988eb8dcdc05d8352f03605b0d11afb2525b3f1b55ec694509248bcfead39cbb292725d710e2a509c77ed051d1d49e15e429cf6d12b9be7c02179612";
+
+    type MemoryStorage = ImplStorage<MemoryAdapter>;
+
+    fn validator() -> Validator {
+        Validator {
+            address:        Address::from_bytes(Bytes::from(vec![1u8; 20])).unwrap(),
+            propose_weight: 1,
+            vote_weight:    1,
+        }
+    }
+
+    fn metadata() -> Metadata {
+        Metadata {
+            common_ref: Hex::from_bytes(Bytes::from("muta")),
+            verifier_list: vec![ValidatorExtend {
+                bls_pub_key:    Hex::from_bytes(Bytes::from(hex::decode(PUBLIC_KEY).unwrap())),
+                address:        validator().address,
+                propose_weight: 1,
+                vote_weight:    1,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn mock_tx(height: u64) -> SignedTransaction {
+        let raw = RawTransaction {
+            chain_id:              Hash::from_empty(),
+            nonce:                 Hash::digest(Bytes::from(height.to_string())),
+            timeout:               height + 10,
+            cycles_limit:          10,
+            cycles_price:          1,
+            request:               TransactionRequest {
+                service_name: "test".to_owned(),
+                method:       "test".to_owned(),
+                payload:      "test".to_owned(),
+            },
+            sender:                None,
+            valid_until_timestamp: None,
+        };
+        SignedTransaction {
+            tx_hash: Hash::digest(raw.encode_fixed().unwrap()),
+            raw,
+            pubkey: Bytes::new(),
+            signature: Bytes::new(),
+        }
+    }
+
+    // The only validator precommits the block
+    fn sign(block: &Block) -> Proof {
+        let mut proof = Proof {
+            height:     block.header.height,
+            round:      0,
+            block_hash: Hash::digest(block.encode_fixed().unwrap()),
+            signature:  Bytes::new(),
+            bitmap:     Bytes::from(vec![0b1000_0000u8]),
+        };
+        let hash = precommit_hash(&proof, None);
+        let hash = HashValue::try_from(hash.as_bytes().as_ref()).unwrap();
+        let priv_key = BlsPrivateKey::try_from(hex::decode(PRIVATE_KEY).unwrap().as_ref()).unwrap();
+        proof.signature = priv_key.sign_message(&hash).to_bytes();
+        proof
+    }
+
+    fn mock_block(height: u64, parent: Option<&Block>, stxs: &[SignedTransaction]) -> Block {
+        let hashes = stxs
+            .iter()
+            .map(|stx| stx.tx_hash.clone())
+            .collect::<Vec<_>>();
+        let (pre_hash, proof) = match parent {
+            Some(parent) => (Hash::digest(parent.encode_fixed().unwrap()), sign(parent)),
+            None => (Hash::from_empty(), Proof {
+                height:     0,
+                round:      0,
+                block_hash: Hash::from_empty(),
+                signature:  Bytes::new(),
+                bitmap:     Bytes::new(),
+            }),
+        };
+
+        Block {
+            header:            BlockHeader {
+                chain_id: Hash::from_empty(),
+                height,
+                exec_height: height.saturating_sub(1),
+                pre_hash,
+                timestamp: height * 1000,
+                logs_bloom: vec![],
+                order_root: merkle_root(hashes.clone()),
+                confirm_root: vec![],
+                state_root: Hash::from_empty(),
+                receipt_root: vec![],
+                cycles_used: vec![],
+                proposer: validator().address,
+                proof,
+                validator_version: 0,
+                validators: vec![validator()],
+                extra_data: Bytes::new(),
+            },
+            ordered_tx_hashes: hashes,
+            vote_extensions:   vec![],
+        }
+    }
+
+    // Blocks from genesis to the height, each with a transaction
+    fn mock_chain(storage: &MemoryStorage, height: u64) -> Vec<Block> {
+        let mut blocks: Vec<Block> = vec![];
+        for h in 0..=height {
+            let stxs = if h == 0 { vec![] } else { vec![mock_tx(h)] };
+            let block = mock_block(h, blocks.last(), &stxs);
+            block_on(storage.insert_transactions(stxs)).unwrap();
+            block_on(storage.insert_block(block.clone())).unwrap();
+            blocks.push(block);
+        }
+        let latest = blocks.last().unwrap();
+        block_on(storage.update_latest_proof(sign(latest))).unwrap();
+        blocks
+    }
+
+    fn verify(storage: MemoryStorage, from: u64) -> ChainVerification {
+        let verifier = Verifier::new(Arc::new(storage), false, |_, _| Ok(metadata()));
+        let mut verifier = block_on(verifier).unwrap();
+        block_on(verifier.verify(from, None)).unwrap()
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let storage = MemoryStorage::new(Arc::new(MemoryAdapter::new()));
+        mock_chain(&storage, 4);
+
+        let verification = verify(storage, 1);
+        assert_eq!(verification.to, 4);
+        assert_eq!(verification.verified, 4);
+        assert_eq!(verification.corruption, None);
+    }
+
+    #[test]
+    fn test_verify_chain_tampered_tx() {
+        let storage = MemoryStorage::new(Arc::new(MemoryAdapter::new()));
+        let blocks = mock_chain(&storage, 4);
+
+        let mut stx = mock_tx(3);
+        stx.raw.cycles_limit += 1;
+        stx.tx_hash = blocks[3].ordered_tx_hashes[0].clone();
+        block_on(storage.insert_transactions(vec![stx])).unwrap();
+
+        let verification = verify(storage, 1);
+        assert_eq!(verification.verified, 2);
+        assert_eq!(verification.corruption.unwrap().height, 3);
+    }
+
+    #[test]
+    fn test_verify_chain_wrong_proof() {
+        let storage = MemoryStorage::new(Arc::new(MemoryAdapter::new()));
+        let blocks = mock_chain(&storage, 4);
+
+        // Proof of the latest block is signed for another round
+        let mut proof = sign(&blocks[4]);
+        proof.round = 1;
+        block_on(storage.update_latest_proof(proof)).unwrap();
+
+        let verification = verify(storage, 1);
+        assert_eq!(verification.verified, 3);
+        assert_eq!(verification.corruption.unwrap().height, 4);
+    }
+
+    #[test]
+    fn test_verify_chain_broken_link() {
+        let storage = MemoryStorage::new(Arc::new(MemoryAdapter::new()));
+        let blocks = mock_chain(&storage, 4);
+
+        // Block 2 is replaced after it's voted, the proof carried by block 3
+        // is not for it
+        let mut block = blocks[2].clone();
+        block.header.timestamp += 1;
+        block_on(storage.insert_block(block)).unwrap();
+        block_on(storage.insert_block(blocks[4].clone())).unwrap();
+
+        let verification = verify(storage, 1);
+        assert_eq!(verification.verified, 1);
+        assert_eq!(verification.corruption.unwrap().height, 2);
+    }
+}
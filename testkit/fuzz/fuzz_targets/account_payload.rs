@@ -0,0 +1,32 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use account::AccountService;
+use protocol::traits::Service;
+use testkit::{ContextBuilder, TestKit};
+
+const METHODS: [&str; 5] = [
+    "get_authorization",
+    "verify_authorization",
+    "set_authorizer",
+    "add_session_key",
+    "remove_session_key",
+];
+
+// The first byte selects the method, the rest is its json payload
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    let method = METHODS[data[0] as usize % METHODS.len()];
+    let payload = String::from_utf8_lossy(&data[1..]);
+
+    let kit = TestKit::new();
+    let mut service = AccountService::new(kit.sdk());
+    let ctx = ContextBuilder::new()
+        .service("account", method, &payload)
+        .build();
+
+    let _ = service.read_(ctx.clone());
+    let _ = service.write_(ctx);
+});
@@ -0,0 +1,38 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use asset::AssetService;
+use protocol::traits::Service;
+use testkit::{ContextBuilder, TestKit};
+
+const METHODS: [&str; 11] = [
+    "get_claimable_fee",
+    "claim_fee",
+    "get_asset",
+    "get_balance",
+    "get_allowance",
+    "create_asset",
+    "transfer",
+    "approve",
+    "transfer_from",
+    "mint",
+    "burn",
+];
+
+// The first byte selects the method, the rest is its json payload
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    let method = METHODS[data[0] as usize % METHODS.len()];
+    let payload = String::from_utf8_lossy(&data[1..]);
+
+    let kit = TestKit::new();
+    let mut service = AssetService::new(kit.sdk());
+    let ctx = ContextBuilder::new()
+        .service("asset", method, &payload)
+        .build();
+
+    let _ = service.read_(ctx.clone());
+    let _ = service.write_(ctx);
+});
@@ -0,0 +1,36 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use bridge::BridgeService;
+use protocol::traits::Service;
+use testkit::{ContextBuilder, TestKit};
+
+const METHODS: [&str; 9] = [
+    "get_client",
+    "get_state_root",
+    "get_send_sequence",
+    "get_receive_sequence",
+    "get_packet",
+    "create_client",
+    "update_client",
+    "transfer",
+    "receive_packet",
+];
+
+// The first byte selects the method, the rest is its json payload
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    let method = METHODS[data[0] as usize % METHODS.len()];
+    let payload = String::from_utf8_lossy(&data[1..]);
+
+    let kit = TestKit::new();
+    let mut service = BridgeService::new(kit.sdk());
+    let ctx = ContextBuilder::new()
+        .service("bridge", method, &payload)
+        .build();
+
+    let _ = service.read_(ctx.clone());
+    let _ = service.write_(ctx);
+});
@@ -0,0 +1,33 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use metadata::MetadataService;
+use protocol::traits::Service;
+use testkit::{ContextBuilder, TestKit};
+
+const METHODS: [&str; 6] = [
+    "get_metadata",
+    "get_service_admins",
+    "set_service_admins",
+    "update_metadata",
+    "halt_chain",
+    "resume_chain",
+];
+
+// The first byte selects the method, the rest is its json payload
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    let method = METHODS[data[0] as usize % METHODS.len()];
+    let payload = String::from_utf8_lossy(&data[1..]);
+
+    let kit = TestKit::new();
+    let mut service = MetadataService::new(kit.sdk());
+    let ctx = ContextBuilder::new()
+        .service("metadata", method, &payload)
+        .build();
+
+    let _ = service.read_(ctx.clone());
+    let _ = service.write_(ctx);
+});
@@ -0,0 +1,50 @@
+//! Assertions on events emitted through a context. Events are shared by
+//! contexts of nested calls, so they are checked on the context passed to
+//! the service.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use protocol::types::ServiceContext;
+
+/// Data of events emitted by the service, in order.
+pub fn events_of(ctx: &ServiceContext, service: &str) -> Vec<String> {
+    ctx.get_events()
+        .into_iter()
+        .filter(|event| event.service == service)
+        .map(|event| event.data)
+        .collect()
+}
+
+/// Assert that the service emitted the event. Data of events are compared
+/// as json, so field order doesn't matter.
+pub fn assert_emitted<T: Serialize>(ctx: &ServiceContext, service: &str, event: &T) {
+    let expect = serde_json::to_value(event).expect("encode expected event");
+    let events = events_of(ctx, service);
+
+    let emitted = events
+        .iter()
+        .any(|data| serde_json::from_str::<Value>(data).ok().as_ref() == Some(&expect));
+    assert!(
+        emitted,
+        "event {} is not emitted by {}, emitted: {:?}",
+        expect, service, events
+    );
+}
+
+/// Assert that the service emitted an event of the raw data.
+pub fn assert_emitted_data(ctx: &ServiceContext, service: &str, data: &str) {
+    let events = events_of(ctx, service);
+    assert!(
+        events.iter().any(|emitted| emitted == data),
+        "event {:?} is not emitted by {}, emitted: {:?}",
+        data,
+        service,
+        events
+    );
+}
+
+pub fn assert_no_events(ctx: &ServiceContext) {
+    let events = ctx.get_events();
+    assert!(events.is_empty(), "unexpected events: {:?}", events);
+}
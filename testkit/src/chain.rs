@@ -0,0 +1,52 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+use protocol::traits::ChainQuerier;
+use protocol::types::{Block, Hash, Receipt, SignedTransaction};
+use protocol::ProtocolResult;
+
+/// Chain data inserted by the test, the highest block is the latest.
+#[derive(Default)]
+pub struct MockChainQuerier {
+    blocks:       RefCell<BTreeMap<u64, Block>>,
+    transactions: RefCell<HashMap<Hash, SignedTransaction>>,
+    receipts:     RefCell<HashMap<Hash, Receipt>>,
+}
+
+impl MockChainQuerier {
+    pub fn insert_block(&self, block: Block) {
+        self.blocks.borrow_mut().insert(block.header.height, block);
+    }
+
+    pub fn insert_transaction(&self, stx: SignedTransaction) {
+        self.transactions
+            .borrow_mut()
+            .insert(stx.tx_hash.clone(), stx);
+    }
+
+    pub fn insert_receipt(&self, receipt: Receipt) {
+        self.receipts
+            .borrow_mut()
+            .insert(receipt.tx_hash.clone(), receipt);
+    }
+}
+
+impl ChainQuerier for MockChainQuerier {
+    fn get_transaction_by_hash(&self, tx_hash: &Hash) -> ProtocolResult<Option<SignedTransaction>> {
+        Ok(self.transactions.borrow().get(tx_hash).cloned())
+    }
+
+    fn get_block_by_height(&self, height: Option<u64>) -> ProtocolResult<Option<Block>> {
+        let blocks = self.blocks.borrow();
+        let block = match height {
+            Some(height) => blocks.get(&height),
+            None => blocks.values().next_back(),
+        };
+
+        Ok(block.cloned())
+    }
+
+    fn get_receipt_by_hash(&self, tx_hash: &Hash) -> ProtocolResult<Option<Receipt>> {
+        Ok(self.receipts.borrow().get(tx_hash).cloned())
+    }
+}
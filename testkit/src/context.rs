@@ -0,0 +1,99 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use bytes::Bytes;
+
+use protocol::types::{Address, Hash, ServiceContext, ServiceContextParams};
+
+/// Context of a call to the service under test. Cycles limit is unlimited
+/// by default, set it to test out of cycles.
+pub struct ContextBuilder {
+    params: ServiceContextParams,
+}
+
+impl Default for ContextBuilder {
+    fn default() -> Self {
+        ContextBuilder {
+            params: ServiceContextParams {
+                tx_hash:         None,
+                nonce:           None,
+                cycles_limit:    u64::max_value(),
+                cycles_price:    1,
+                cycles_used:     Rc::new(RefCell::new(0)),
+                caller:          Address::default(),
+                height:          1,
+                timestamp:       0,
+                service_name:    "".to_owned(),
+                service_method:  "".to_owned(),
+                service_payload: "".to_owned(),
+                extra:           None,
+                events:          Rc::new(RefCell::new(vec![])),
+            },
+        }
+    }
+}
+
+impl ContextBuilder {
+    pub fn new() -> Self {
+        ContextBuilder::default()
+    }
+
+    pub fn caller(mut self, caller: Address) -> Self {
+        self.params.caller = caller;
+        self
+    }
+
+    pub fn cycles_limit(mut self, cycles_limit: u64) -> Self {
+        self.params.cycles_limit = cycles_limit;
+        self
+    }
+
+    pub fn cycles_price(mut self, cycles_price: u64) -> Self {
+        self.params.cycles_price = cycles_price;
+        self
+    }
+
+    // Cycles used before the call, such as by a calling service
+    pub fn cycles_used(self, cycles_used: u64) -> Self {
+        *self.params.cycles_used.borrow_mut() = cycles_used;
+        self
+    }
+
+    pub fn height(mut self, height: u64) -> Self {
+        self.params.height = height;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.params.timestamp = timestamp;
+        self
+    }
+
+    pub fn tx_hash(mut self, tx_hash: Hash) -> Self {
+        self.params.tx_hash = Some(tx_hash);
+        self
+    }
+
+    pub fn nonce(mut self, nonce: Hash) -> Self {
+        self.params.nonce = Some(nonce);
+        self
+    }
+
+    pub fn extra(mut self, extra: Bytes) -> Self {
+        self.params.extra = Some(extra);
+        self
+    }
+
+    // Called service and method, events are emitted in the name of the
+    // service. Payload is only read by `read_` and `write_`.
+    pub fn service(mut self, service: &str, method: &str, payload: &str) -> Self {
+        self.params.service_name = service.to_owned();
+        self.params.service_method = method.to_owned();
+        self.params.service_payload = payload.to_owned();
+        self
+    }
+
+    pub fn build(self) -> ServiceContext {
+        ServiceContext::new(self.params)
+    }
+}
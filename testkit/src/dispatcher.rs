@@ -0,0 +1,108 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use protocol::traits::{Dispatcher, ServiceResponse};
+use protocol::types::{Address, ServiceContext};
+
+type Stub = Rc<dyn Fn(&ServiceContext) -> ServiceResponse<String>>;
+
+/// Call of the service under test to another service.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DispatchedCall {
+    pub service: String,
+    pub method:  String,
+    pub payload: String,
+    pub caller:  Address,
+    pub write:   bool,
+}
+
+#[derive(Default)]
+struct Stubs {
+    reads:  HashMap<(String, String), Stub>,
+    writes: HashMap<(String, String), Stub>,
+    calls:  Vec<DispatchedCall>,
+}
+
+/// Answers calls to other services with stubbed responses, and records
+/// them. A call without stub panics, like a missing service.
+#[derive(Clone, Default)]
+pub struct MockDispatcher {
+    stubs: Rc<RefCell<Stubs>>,
+}
+
+impl MockDispatcher {
+    pub fn stub_read(&self, service: &str, method: &str, succeed_data: &str) {
+        let resp = ServiceResponse::<String>::from_succeed(succeed_data.to_owned());
+        self.stub_read_with(service, method, move |_| resp.clone());
+    }
+
+    pub fn stub_write(&self, service: &str, method: &str, succeed_data: &str) {
+        let resp = ServiceResponse::<String>::from_succeed(succeed_data.to_owned());
+        self.stub_write_with(service, method, move |_| resp.clone());
+    }
+
+    pub fn stub_read_with<F>(&self, service: &str, method: &str, stub: F)
+    where
+        F: 'static + Fn(&ServiceContext) -> ServiceResponse<String>,
+    {
+        self.stubs
+            .borrow_mut()
+            .reads
+            .insert((service.to_owned(), method.to_owned()), Rc::new(stub));
+    }
+
+    pub fn stub_write_with<F>(&self, service: &str, method: &str, stub: F)
+    where
+        F: 'static + Fn(&ServiceContext) -> ServiceResponse<String>,
+    {
+        self.stubs
+            .borrow_mut()
+            .writes
+            .insert((service.to_owned(), method.to_owned()), Rc::new(stub));
+    }
+
+    pub fn calls(&self) -> Vec<DispatchedCall> {
+        self.stubs.borrow().calls.clone()
+    }
+
+    fn dispatch(&self, context: ServiceContext, write: bool) -> ServiceResponse<String> {
+        let service = context.get_service_name().to_owned();
+        let method = context.get_service_method().to_owned();
+
+        // Stub may call the dispatcher again, don't hold the borrow
+        let stub = {
+            let mut stubs = self.stubs.borrow_mut();
+            stubs.calls.push(DispatchedCall {
+                service: service.clone(),
+                method: method.clone(),
+                payload: context.get_payload().to_owned(),
+                caller: context.get_caller(),
+                write,
+            });
+
+            let stubs = if write { &stubs.writes } else { &stubs.reads };
+            stubs.get(&(service.clone(), method.clone())).cloned()
+        };
+
+        match stub {
+            Some(stub) => stub(&context),
+            None => panic!(
+                "no stub for {} {} of service {}",
+                if write { "write" } else { "read" },
+                method,
+                service
+            ),
+        }
+    }
+}
+
+impl Dispatcher for MockDispatcher {
+    fn read(&self, context: ServiceContext) -> ServiceResponse<String> {
+        self.dispatch(context, false)
+    }
+
+    fn write(&self, context: ServiceContext) -> ServiceResponse<String> {
+        self.dispatch(context, true)
+    }
+}
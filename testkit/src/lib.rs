@@ -0,0 +1,95 @@
+//! Unit testing of services without executor and storage. Services are
+//! created with `TestKit::sdk`, their state lives in memory, chain data and
+//! calls to other services are stubbed by the test.
+//!
+//! ```ignore
+//! let kit = TestKit::new();
+//! kit.dispatcher().stub_read("metadata", "get_metadata", metadata_json);
+//!
+//! let mut service = AssetService::new(kit.sdk());
+//! let ctx = ContextBuilder::new().caller(caller).build();
+//! service.create_asset(ctx.clone(), payload);
+//!
+//! assert_emitted(&ctx, "asset", &expected_event);
+//! assert!(kit.diff().is_written(&symbol_key)?);
+//! ```
+//!
+//! Tests running blocks through the executor keep the chain in
+//! `MemoryStorage`.
+
+pub mod assert;
+mod chain;
+mod context;
+mod dispatcher;
+mod state;
+mod storage;
+#[cfg(test)]
+mod tests;
+
+pub use assert::{assert_emitted, assert_emitted_data, assert_no_events, events_of};
+pub use chain::MockChainQuerier;
+pub use context::ContextBuilder;
+pub use dispatcher::{DispatchedCall, MockDispatcher};
+pub use state::{MemoryState, StateChange, StateDiff};
+pub use storage::{MemoryStorage, MemoryStorageError};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use framework::binding::sdk::DefalutServiceSDK;
+use protocol::traits::ServiceState;
+use protocol::types::MerkleRoot;
+use protocol::ProtocolResult;
+
+pub type MockServiceSDK = DefalutServiceSDK<MemoryState, MockChainQuerier, MockDispatcher>;
+
+/// State, chain and dispatcher shared by the SDKs it creates, so a service
+/// recreated from a new SDK sees the same state.
+#[derive(Default)]
+pub struct TestKit {
+    state:      Rc<RefCell<MemoryState>>,
+    chain:      Rc<MockChainQuerier>,
+    dispatcher: MockDispatcher,
+}
+
+impl TestKit {
+    pub fn new() -> Self {
+        TestKit::default()
+    }
+
+    pub fn sdk(&self) -> MockServiceSDK {
+        DefalutServiceSDK::new(
+            Rc::clone(&self.state),
+            Rc::clone(&self.chain),
+            self.dispatcher.clone(),
+        )
+    }
+
+    pub fn chain(&self) -> &MockChainQuerier {
+        &self.chain
+    }
+
+    pub fn dispatcher(&self) -> &MockDispatcher {
+        &self.dispatcher
+    }
+
+    pub fn state(&self) -> Rc<RefCell<MemoryState>> {
+        Rc::clone(&self.state)
+    }
+
+    /// Changes of state since last checkpoint.
+    pub fn diff(&self) -> StateDiff {
+        self.state.borrow().diff()
+    }
+
+    pub fn checkpoint(&self) {
+        self.state.borrow_mut().checkpoint()
+    }
+
+    /// Stash and commit state like the executor after a call.
+    pub fn commit(&self) -> ProtocolResult<MerkleRoot> {
+        let mut state = self.state.borrow_mut();
+        state.stash()?;
+        state.commit()
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::ServiceState;
+use protocol::types::{Address, Hash, MerkleRoot};
+use protocol::ProtocolResult;
+
+/// Service state in memory, cache and stash work like `GeneralServiceState`,
+/// keys of account values are hashed the same way. Committed root is a
+/// digest of all committed pairs rather than a trie root.
+#[derive(Default)]
+pub struct MemoryState {
+    cache:     BTreeMap<Bytes, Bytes>,
+    stash:     BTreeMap<Bytes, Bytes>,
+    committed: BTreeMap<Bytes, Bytes>,
+
+    checkpoint: BTreeMap<Bytes, Bytes>,
+}
+
+impl MemoryState {
+    pub fn new() -> Self {
+        MemoryState::default()
+    }
+
+    // Pairs visible to the service, from cache, stash and committed data
+    pub fn pairs(&self) -> BTreeMap<Bytes, Bytes> {
+        let mut pairs = self.committed.clone();
+        pairs.extend(self.stash.clone());
+        pairs.extend(self.cache.clone());
+        pairs
+    }
+
+    pub fn checkpoint(&mut self) {
+        self.checkpoint = self.pairs();
+    }
+
+    pub fn diff(&self) -> StateDiff {
+        let changes = self
+            .pairs()
+            .into_iter()
+            .filter_map(|(key, after)| {
+                let before = self.checkpoint.get(&key).cloned();
+                if before.as_ref() == Some(&after) {
+                    None
+                } else {
+                    Some((key, StateChange { before, after }))
+                }
+            })
+            .collect();
+
+        StateDiff { changes }
+    }
+
+    fn get_bytes(&self, key: &Bytes) -> Option<Bytes> {
+        self.cache
+            .get(key)
+            .or_else(|| self.stash.get(key))
+            .or_else(|| self.committed.get(key))
+            .cloned()
+    }
+}
+
+impl ServiceState for MemoryState {
+    fn get<Key: FixedCodec, Ret: FixedCodec>(&self, key: &Key) -> ProtocolResult<Option<Ret>> {
+        match self.get_bytes(&key.encode_fixed()?) {
+            Some(value) => Ok(Some(<_>::decode_fixed(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn contains<Key: FixedCodec>(&self, key: &Key) -> ProtocolResult<bool> {
+        Ok(self.get_bytes(&key.encode_fixed()?).is_some())
+    }
+
+    fn insert<Key: FixedCodec, Value: FixedCodec>(
+        &mut self,
+        key: Key,
+        value: Value,
+    ) -> ProtocolResult<()> {
+        self.cache
+            .insert(key.encode_fixed()?, value.encode_fixed()?);
+        Ok(())
+    }
+
+    fn get_account_value<Key: FixedCodec, Ret: FixedCodec>(
+        &self,
+        address: &Address,
+        key: &Key,
+    ) -> ProtocolResult<Option<Ret>> {
+        self.get(&account_key(address, key)?)
+    }
+
+    fn set_account_value<Key: FixedCodec, Val: FixedCodec>(
+        &mut self,
+        address: &Address,
+        key: Key,
+        val: Val,
+    ) -> ProtocolResult<()> {
+        let hash_key = account_key(address, &key)?;
+        self.insert(hash_key, val)
+    }
+
+    fn revert_cache(&mut self) -> ProtocolResult<()> {
+        self.cache.clear();
+        Ok(())
+    }
+
+    fn stash(&mut self) -> ProtocolResult<()> {
+        let cache = std::mem::replace(&mut self.cache, BTreeMap::new());
+        self.stash.extend(cache);
+        Ok(())
+    }
+
+    fn commit(&mut self) -> ProtocolResult<MerkleRoot> {
+        let stash = std::mem::replace(&mut self.stash, BTreeMap::new());
+        self.committed.extend(stash);
+
+        let mut bytes = vec![];
+        for (key, value) in self.committed.iter() {
+            bytes.extend_from_slice(Hash::digest(key.clone()).as_bytes().as_ref());
+            bytes.extend_from_slice(Hash::digest(value.clone()).as_bytes().as_ref());
+        }
+        Ok(Hash::digest(Bytes::from(bytes)))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateChange {
+    pub before: Option<Bytes>,
+    pub after:  Bytes,
+}
+
+/// Pairs changed since a checkpoint, keyed by fixed encoded key.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub changes: BTreeMap<Bytes, StateChange>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_written<Key: FixedCodec>(&self, key: &Key) -> ProtocolResult<bool> {
+        Ok(self.changes.contains_key(&key.encode_fixed()?))
+    }
+
+    pub fn is_account_written<Key: FixedCodec>(
+        &self,
+        address: &Address,
+        key: &Key,
+    ) -> ProtocolResult<bool> {
+        self.is_written(&account_key(address, key)?)
+    }
+
+    /// Value written to the key, `None` if it is not changed.
+    pub fn written<Key: FixedCodec, Value: FixedCodec>(
+        &self,
+        key: &Key,
+    ) -> ProtocolResult<Option<Value>> {
+        match self.changes.get(&key.encode_fixed()?) {
+            Some(change) => Ok(Some(<_>::decode_fixed(change.after.clone())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn account_written<Key: FixedCodec, Value: FixedCodec>(
+        &self,
+        address: &Address,
+        key: &Key,
+    ) -> ProtocolResult<Option<Value>> {
+        self.written(&account_key(address, key)?)
+    }
+}
+
+fn account_key<Key: FixedCodec>(address: &Address, key: &Key) -> ProtocolResult<Hash> {
+    let mut hash_bytes = address.as_bytes().to_vec();
+    hash_bytes.extend_from_slice(key.encode_fixed()?.as_ref());
+
+    Ok(Hash::digest(Bytes::from(hash_bytes)))
+}
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, HashMap};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use derive_more::Display;
+use parking_lot::Mutex;
+
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::Storage;
+use protocol::types::{Block, Hash, Proof, Receipt, SignedTransaction};
+use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
+
+/// Chain storage in memory for tests of executors and dev chains, the
+/// highest block is the latest.
+#[derive(Default)]
+pub struct MemoryStorage {
+    blocks:   Mutex<BTreeMap<u64, Block>>,
+    txs:      Mutex<HashMap<Hash, SignedTransaction>>,
+    receipts: Mutex<HashMap<Hash, Receipt>>,
+    proof:    Mutex<Option<Proof>>,
+    wal:      Mutex<Option<Bytes>>,
+}
+
+#[derive(Debug, Display)]
+pub enum MemoryStorageError {
+    // Same as the error of missing values in `core_storage`
+    #[display(fmt = "GetNone")]
+    GetNone,
+}
+
+impl std::error::Error for MemoryStorageError {}
+
+impl From<MemoryStorageError> for ProtocolError {
+    fn from(err: MemoryStorageError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Storage, Box::new(err))
+    }
+}
+
+fn get_none<T>(value: Option<T>) -> ProtocolResult<T> {
+    value.ok_or_else(|| MemoryStorageError::GetNone.into())
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn insert_transactions(&self, signed_txs: Vec<SignedTransaction>) -> ProtocolResult<()> {
+        let mut txs = self.txs.lock();
+        for stx in signed_txs {
+            txs.insert(stx.tx_hash.clone(), stx);
+        }
+        Ok(())
+    }
+
+    async fn insert_block(&self, block: Block) -> ProtocolResult<()> {
+        self.blocks.lock().insert(block.header.height, block);
+        Ok(())
+    }
+
+    async fn insert_receipts(&self, receipts: Vec<Receipt>) -> ProtocolResult<()> {
+        let mut stored = self.receipts.lock();
+        for receipt in receipts {
+            stored.insert(receipt.tx_hash.clone(), receipt);
+        }
+        Ok(())
+    }
+
+    async fn update_latest_proof(&self, proof: Proof) -> ProtocolResult<()> {
+        *self.proof.lock() = Some(proof);
+        Ok(())
+    }
+
+    async fn get_transaction_by_hash(&self, tx_hash: Hash) -> ProtocolResult<SignedTransaction> {
+        get_none(self.txs.lock().get(&tx_hash).cloned())
+    }
+
+    async fn get_transactions(&self, hashes: Vec<Hash>) -> ProtocolResult<Vec<SignedTransaction>> {
+        let txs = self.txs.lock();
+        hashes
+            .iter()
+            .map(|hash| get_none(txs.get(hash).cloned()))
+            .collect()
+    }
+
+    async fn get_latest_block(&self) -> ProtocolResult<Block> {
+        get_none(self.blocks.lock().values().last().cloned())
+    }
+
+    async fn get_block_by_height(&self, height: u64) -> ProtocolResult<Block> {
+        get_none(self.blocks.lock().get(&height).cloned())
+    }
+
+    async fn get_block_by_hash(&self, block_hash: Hash) -> ProtocolResult<Block> {
+        let blocks = self.blocks.lock();
+        for block in blocks.values() {
+            if Hash::digest(block.encode_fixed()?) == block_hash {
+                return Ok(block.clone());
+            }
+        }
+        get_none(None)
+    }
+
+    async fn get_receipt(&self, hash: Hash) -> ProtocolResult<Receipt> {
+        get_none(self.receipts.lock().get(&hash).cloned())
+    }
+
+    async fn get_receipts(&self, hashes: Vec<Hash>) -> ProtocolResult<Vec<Receipt>> {
+        let receipts = self.receipts.lock();
+        hashes
+            .iter()
+            .map(|hash| get_none(receipts.get(hash).cloned()))
+            .collect()
+    }
+
+    async fn get_latest_proof(&self) -> ProtocolResult<Proof> {
+        get_none(self.proof.lock().clone())
+    }
+
+    async fn update_overlord_wal(&self, info: Bytes) -> ProtocolResult<()> {
+        *self.wal.lock() = Some(info);
+        Ok(())
+    }
+
+    async fn load_overlord_wal(&self) -> ProtocolResult<Bytes> {
+        get_none(self.wal.lock().clone())
+    }
+}
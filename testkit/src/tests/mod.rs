@@ -0,0 +1,137 @@
+use asset::types::{Asset, GetClaimableFeeResponse, InitGenesisPayload};
+use asset::AssetService;
+use protocol::traits::{Dispatcher, ExecutorParams, Service, ServiceState};
+use protocol::types::{Address, Hash, Metadata};
+use protocol::Bytes;
+
+use crate::{assert_emitted, assert_no_events, ContextBuilder, MemoryState, TestKit};
+
+#[test]
+fn test_memory_state() {
+    let mut state = MemoryState::new();
+    state.insert("a".to_owned(), 1u64).unwrap();
+    state.revert_cache().unwrap();
+    assert!(!state.contains(&"a".to_owned()).unwrap());
+
+    state.insert("a".to_owned(), 1u64).unwrap();
+    state.stash().unwrap();
+    state.revert_cache().unwrap();
+    assert_eq!(state.get(&"a".to_owned()).unwrap(), Some(1u64));
+
+    let root = state.commit().unwrap();
+    state.checkpoint();
+    assert!(state.diff().is_empty());
+
+    state.insert("a".to_owned(), 2u64).unwrap();
+    state.insert("b".to_owned(), 3u64).unwrap();
+    let diff = state.diff();
+    assert_eq!(diff.len(), 2);
+    assert_eq!(diff.written(&"a".to_owned()).unwrap(), Some(2u64));
+    assert_ne!(state.commit().unwrap(), root);
+}
+
+#[test]
+fn test_create_asset() {
+    let kit = TestKit::new();
+    let mut service = AssetService::new(kit.sdk());
+
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let ctx = ContextBuilder::new()
+        .caller(caller.clone())
+        .service(
+            "asset",
+            "create_asset",
+            r#"{ "name": "test", "symbol": "TT", "supply": 1024 }"#,
+        )
+        .build();
+
+    let resp = service.write_(ctx.clone());
+    assert!(!resp.is_error());
+    let asset: Asset = serde_json::from_str(&resp.succeed_data).unwrap();
+    assert_emitted(&ctx, "asset", &asset);
+
+    let diff = kit.diff();
+    let symbol_key = "symbol_TT".to_owned();
+    assert_eq!(diff.written(&symbol_key).unwrap(), Some(asset.id.clone()));
+    assert!(diff.is_account_written(&caller, &asset.id).unwrap());
+
+    kit.commit().unwrap();
+    kit.checkpoint();
+    let ctx = ContextBuilder::new()
+        .service(
+            "asset",
+            "get_asset",
+            &format!(r#"{{ "id": "{}" }}"#, asset.id.as_hex()),
+        )
+        .build();
+    assert!(!service.read_(ctx.clone()).is_error());
+    assert_no_events(&ctx);
+    assert!(kit.diff().is_empty());
+}
+
+#[test]
+fn test_stub_cross_service_call() {
+    let kit = TestKit::new();
+    let mut service = AssetService::new(kit.sdk());
+
+    let issuer = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let proposer = Address::from_hex("0x666cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    service.genesis_(
+        serde_json::to_string(&InitGenesisPayload {
+            id:         Hash::digest(Bytes::from("fee")),
+            name:       "fee".to_owned(),
+            symbol:     "FEE".to_owned(),
+            supply:     1024,
+            issuer:     issuer.clone(),
+            decimals:   8,
+            max_supply: 0,
+            fee_asset:  true,
+        })
+        .unwrap(),
+    );
+
+    let metadata = serde_json::to_string(&Metadata::default()).unwrap();
+    kit.dispatcher()
+        .stub_read("metadata", "get_metadata", &metadata);
+
+    let ctx = ContextBuilder::new()
+        .caller(issuer)
+        .cycles_used(100)
+        .build();
+    service.tx_hook_after_(ctx);
+    service.hook_after_(&ExecutorParams {
+        state_root:      Hash::from_empty(),
+        height:          1,
+        timestamp:       0,
+        cycles_limit:    u64::max_value(),
+        proposer:        proposer.clone(),
+        vote_extensions: vec![],
+        extra_data:      Bytes::new(),
+    });
+
+    let calls = kit.dispatcher().calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].service, "metadata");
+    assert!(!calls[0].write);
+
+    let ctx = ContextBuilder::new()
+        .service(
+            "asset",
+            "get_claimable_fee",
+            &format!(r#"{{ "user": "{}" }}"#, proposer.as_hex()),
+        )
+        .build();
+    let resp = service.read_(ctx);
+    let fee: GetClaimableFeeResponse = serde_json::from_str(&resp.succeed_data).unwrap();
+    assert_eq!(fee.value, 100);
+}
+
+#[test]
+#[should_panic(expected = "no stub")]
+fn test_call_without_stub() {
+    let kit = TestKit::new();
+    let ctx = ContextBuilder::new()
+        .service("metadata", "get_metadata", "")
+        .build();
+    kit.dispatcher().read(ctx);
+}